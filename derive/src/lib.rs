@@ -0,0 +1,361 @@
+//! # Kube Resource Extra Derive
+//! Derive macros for the resource types of `kube-resource-extras`, enabled with its `derive`
+//! feature.
+//!
+//! `Builder` generates a fluent `with_<field>` method per field:
+//!
+//! ```ignore
+//! #[derive(Builder)]
+//! pub struct HttpRetry {
+//!     pub attempts: i32,
+//!     pub retry_on: Option<String>,
+//!     #[builder(skip)]
+//!     pub backoff: Option<Duration>,
+//! }
+//!
+//! let retry = HttpRetry::default().with_attempts(3).with_retry_on("5xx");
+//! ```
+//!
+//! `Validate` implements `kube_resource_extras::istio::validate::Validate` from field attributes.
+//! Errors are reported with the serialized field path, e.g. `route[1].weight`:
+//!
+//! - `#[validate(required)]`: an `Option` must be set, a `Vec` must not be empty.
+//! - `#[validate(non_empty)]`: a list must not be empty when set.
+//! - `#[validate(range(min = 0, max = 100))]`: numbers must be within the bounds.
+//! - `#[validate(host)]`: strings must be valid hosts.
+//! - `#[validate(nested)]`: the value must be valid itself.
+//! - `#[validate(with = "path::to::check")]`: calls `fn(&T) -> Result<(), String>`.
+//!
+//! Each attribute applies to the value of an `Option` and to every item of a `Vec`.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Expr, Field, Fields, GenericArgument, Ident, LitStr,
+    Meta, PathArguments, Token, Type,
+};
+
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    builder(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    validate(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<&Field>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter().collect()),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs are supported",
+        )),
+    }
+}
+
+fn builder(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut methods = Vec::new();
+    for field in named_fields(input)? {
+        let mut skip = false;
+        for attribute in field.attrs.iter().filter(|a| a.path().is_ident("builder")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip`"))
+                }
+            })?;
+        }
+        if skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = unraw(ident);
+        let method = format_ident!("with_{}", name);
+        let doc = format!("Sets `{}`.", name);
+        let method = match wrapped(&field.ty, "Option") {
+            Some(inner) => quote! {
+                #[doc = #doc]
+                pub fn #method(mut self, #ident: impl Into<#inner>) -> Self {
+                    self.#ident = Some(#ident.into());
+                    self
+                }
+            },
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    #[doc = #doc]
+                    pub fn #method(mut self, #ident: impl Into<#ty>) -> Self {
+                        self.#ident = #ident.into();
+                        self
+                    }
+                }
+            }
+        };
+        methods.push(method);
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    })
+}
+
+enum Check {
+    Required,
+    NonEmpty,
+    Range(Box<(Option<Expr>, Option<Expr>)>),
+    Host,
+    Nested,
+    With(syn::Path),
+}
+
+fn checks(field: &Field) -> syn::Result<Vec<Check>> {
+    let mut checks = Vec::new();
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident("validate")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("required") {
+                checks.push(Check::Required);
+            } else if meta.path.is_ident("non_empty") {
+                checks.push(Check::NonEmpty);
+            } else if meta.path.is_ident("host") {
+                checks.push(Check::Host);
+            } else if meta.path.is_ident("nested") {
+                checks.push(Check::Nested);
+            } else if meta.path.is_ident("with") {
+                let path: LitStr = meta.value()?.parse()?;
+                checks.push(Check::With(path.parse()?));
+            } else if meta.path.is_ident("range") {
+                let (mut min, mut max) = (None, None);
+                meta.parse_nested_meta(|bound| {
+                    let value: Expr = bound.value()?.parse()?;
+                    if bound.path.is_ident("min") {
+                        min = Some(value);
+                    } else if bound.path.is_ident("max") {
+                        max = Some(value);
+                    } else {
+                        return Err(bound.error("expected `min` or `max`"));
+                    }
+                    Ok(())
+                })?;
+                checks.push(Check::Range(Box::new((min, max))));
+            } else {
+                return Err(meta.error(
+                    "expected one of `required`, `non_empty`, `range`, `host`, `nested`, `with`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(checks)
+}
+
+/// The name of the field in the serialized form: its `serde(rename)` or the field name.
+fn serialized_name(field: &Field) -> syn::Result<String> {
+    let mut name = unraw(field.ident.as_ref().expect("named field"));
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        let nested = attribute.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        for meta in nested {
+            if let Meta::NameValue(value) = meta {
+                if value.path.is_ident("rename") {
+                    if let Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(rename),
+                        ..
+                    }) = &value.value
+                    {
+                        name = rename.value();
+                    }
+                }
+            }
+        }
+    }
+    Ok(name)
+}
+
+fn validate(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = quote!(::kube_resource_extras::istio);
+    let mut statements = Vec::new();
+    for field in named_fields(input)? {
+        let checks = checks(field)?;
+        if checks.is_empty() {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = serialized_name(field)?;
+        let shape = Shape::of(&field.ty);
+        // `(path, value)` pairs of the value of an Option or the items of a Vec.
+        let values = match shape {
+            Shape::Plain => quote!(::std::iter::once((#name.to_string(), &self.#ident))),
+            Shape::Option => quote!(self.#ident.iter().map(|value| (#name.to_string(), value))),
+            Shape::Vec => quote! {
+                self.#ident.iter().enumerate().map(|(i, value)| (format!("{}[{}]", #name, i), value))
+            },
+            Shape::OptionVec => quote! {
+                self.#ident.iter().flatten().enumerate().map(|(i, value)| (format!("{}[{}]", #name, i), value))
+            },
+        };
+        for check in checks {
+            let statement = match check {
+                Check::Required => {
+                    let missing = match shape {
+                        Shape::Plain => quote!(false),
+                        Shape::Option | Shape::OptionVec => quote!(self.#ident.is_none()),
+                        Shape::Vec => quote!(self.#ident.is_empty()),
+                    };
+                    quote! {
+                        if #missing {
+                            errors.0.push(#krate::validate::ValidationError::new(#name, format!("{} is required", #name)));
+                        }
+                    }
+                }
+                Check::NonEmpty => {
+                    let empty = match shape {
+                        Shape::OptionVec => {
+                            quote!(self.#ident.as_ref().is_some_and(|values| values.is_empty()))
+                        }
+                        Shape::Vec => quote!(self.#ident.is_empty()),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                &field.ty,
+                                "`non_empty` needs a Vec",
+                            ))
+                        }
+                    };
+                    quote! {
+                        if #empty {
+                            errors.0.push(#krate::validate::ValidationError::new(#name, "must not be empty"));
+                        }
+                    }
+                }
+                Check::Range(bounds) => {
+                    let (min, max) = *bounds;
+                    let below = min.as_ref().map(|min| quote!(*value < #min));
+                    let above = max.as_ref().map(|max| quote!(*value > #max));
+                    let outside = match (below, above) {
+                        (Some(below), Some(above)) => quote!(#below || #above),
+                        (Some(below), None) => below,
+                        (None, Some(above)) => above,
+                        (None, None) => continue,
+                    };
+                    let bounds = match (&min, &max) {
+                        (Some(min), Some(max)) => {
+                            quote!(format!("{} is outside of {}-{}", value, #min, #max))
+                        }
+                        (Some(min), None) => quote!(format!("{} is below {}", value, #min)),
+                        (None, _) => quote!(format!("{} is above {}", value, #max)),
+                    };
+                    quote! {
+                        for (path, value) in #values {
+                            if #outside {
+                                errors.0.push(#krate::validate::ValidationError::new(path, #bounds));
+                            }
+                        }
+                    }
+                }
+                Check::Host => quote! {
+                    for (path, value) in #values {
+                        if let Err(error) = #krate::host::Host::new(value) {
+                            errors.0.push(#krate::validate::ValidationError::new(path, error.to_string()));
+                        }
+                    }
+                },
+                Check::Nested => quote! {
+                    for (path, value) in #values {
+                        if let Err(nested) = #krate::validate::Validate::validate(value) {
+                            errors.0.extend(nested.0.into_iter().map(|error| {
+                                #krate::validate::ValidationError::new(format!("{}.{}", path, error.path), error.message)
+                            }));
+                        }
+                    }
+                },
+                Check::With(check) => quote! {
+                    for (path, value) in #values {
+                        if let Err(message) = #check(value) {
+                            errors.0.push(#krate::validate::ValidationError::new(path, message));
+                        }
+                    }
+                },
+            };
+            statements.push(statement);
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::validate::Validate for #name #ty_generics #where_clause {
+            fn validate(&self) -> Result<(), #krate::validate::ValidationErrors> {
+                let mut errors = #krate::validate::ValidationErrors::default();
+                #(#statements)*
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(errors)
+                }
+            }
+        }
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+    Plain,
+    Option,
+    Vec,
+    OptionVec,
+}
+
+impl Shape {
+    fn of(ty: &Type) -> Self {
+        match wrapped(ty, "Option") {
+            Some(inner) if wrapped(inner, "Vec").is_some() => Shape::OptionVec,
+            Some(_) => Shape::Option,
+            None if wrapped(ty, "Vec").is_some() => Shape::Vec,
+            None => Shape::Plain,
+        }
+    }
+}
+
+/// The argument of `wrapper<T>`, if `ty` is one.
+fn wrapped<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first()? {
+                GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn unraw(ident: &Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#")
+        .map(ToString::to_string)
+        .unwrap_or(name)
+}
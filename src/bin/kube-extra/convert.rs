@@ -0,0 +1,132 @@
+// `kube-extra convert`: rewrites manifests to another API version. Documents that convert without
+// loss only get their `apiVersion` line replaced, so comments and formatting are kept. Documents
+// with fields the typed model does not know are re-emitted from the converted resource, which drops
+// those fields and the comments of the document; every dropped field is reported as a warning.
+use crate::manifest::{self, Document};
+use kube_resource_extras::istio::conversion::{self, ApiVersion};
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// YAML files or directories to convert. Directories are searched recursively.
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Target API version: v1alpha3, v1beta1 or v1.
+    #[arg(long)]
+    pub to: ApiVersion,
+
+    /// Rewrite the files instead of printing the converted manifests.
+    #[arg(long, short = 'i')]
+    pub in_place: bool,
+}
+
+pub fn run(args: Args) -> ExitCode {
+    let files = match manifest::files(&args.paths) {
+        Ok(files) => files,
+        Err((path, error)) => {
+            eprintln!("{}: error: {}", path.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+    let mut output = Vec::new();
+    for file in files {
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{}: error: {}", file.display(), error);
+                return ExitCode::from(2);
+            }
+        };
+        let documents: Vec<String> = manifest::split(&file, &text)
+            .iter()
+            .map(|document| match convert(document, args.to) {
+                Ok(text) => text,
+                Err(message) => {
+                    failed = true;
+                    eprintln!(
+                        "{}:{}: error: {}",
+                        file.display(),
+                        document.locate(""),
+                        message
+                    );
+                    document.text.clone()
+                }
+            })
+            .collect();
+        let converted = documents.join("---\n");
+        if !args.in_place {
+            output.push(converted);
+        } else if converted != text {
+            if let Err(error) = fs::write(&file, converted) {
+                eprintln!("{}: error: {}", file.display(), error);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if !args.in_place {
+        print!("{}", output.join("---\n"));
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The text of the converted document. Documents of kinds that cannot be converted are returned
+/// unchanged.
+fn convert(document: &Document, target: ApiVersion) -> Result<String, String> {
+    let value = match manifest::value(document).map_err(|error| error.to_string())? {
+        Some(value) => value,
+        None => return Ok(document.text.clone()),
+    };
+    let api_version = value.get("apiVersion").and_then(|value| value.as_str());
+    let kind = value.get("kind").and_then(|value| value.as_str());
+    let (group, version) = match api_version.zip(kind) {
+        Some((api_version, kind)) => match api_version.split_once('/') {
+            Some((group, version)) if !conversion::versions(group, kind).is_empty() => {
+                (group, version)
+            }
+            _ => return Ok(document.text.clone()),
+        },
+        None => return Ok(document.text.clone()),
+    };
+    if version == target.as_str() {
+        return Ok(document.text.clone());
+    }
+
+    let converted = conversion::convert(&value, target).map_err(|error| error.to_string())?;
+    if converted.dropped.is_empty() {
+        return Ok(replace_api_version(
+            &document.text,
+            &format!("{}/{}", group, target),
+        ));
+    }
+    for path in &converted.dropped {
+        eprintln!(
+            "{}:{}: warning: {} is not supported and was dropped",
+            document.file.display(),
+            document.locate(path),
+            path
+        );
+    }
+    serde_yaml::to_string(&converted.value).map_err(|error| error.to_string())
+}
+
+/// Replaces the value of the top level `apiVersion` key, keeping a trailing comment.
+fn replace_api_version(text: &str, api_version: &str) -> String {
+    text.lines()
+        .map(|line| match line.strip_prefix("apiVersion:") {
+            Some(value) => match value.find(" #") {
+                Some(comment) => format!("apiVersion: {}{}\n", api_version, &value[comment..]),
+                None => format!("apiVersion: {}\n", api_version),
+            },
+            None => format!("{}\n", line),
+        })
+        .collect()
+}
@@ -0,0 +1,186 @@
+// `kube-extra diff`: semantic diff of two manifests, or of a manifest against the objects in the
+// cluster. Resources are matched by kind, namespace and name and compared in their typed form, so
+// formatting, key order and fields set by the API server do not show up as changes.
+use crate::manifest::{self, Resource};
+use k8s_openapi::serde_json::{self, Value};
+use kube::api::{Api, DynamicObject};
+use kube::{Client, Config};
+use kube_resource_extras::istio::dependency::ObjectRef;
+use kube_resource_extras::istio::diff::diff_values;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Annotation kubectl stores the applied manifest in.
+const LAST_APPLIED: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The old manifest, file or directory.
+    pub old: PathBuf,
+
+    /// The new manifest, file or directory. Required unless `--live` is given.
+    #[arg(required_unless_present = "live", conflicts_with = "live")]
+    pub new: Option<PathBuf>,
+
+    /// Compare the manifest against the objects in the cluster of the current kubeconfig context.
+    #[arg(long)]
+    pub live: bool,
+}
+
+pub fn run(args: Args) -> ExitCode {
+    let old = match read(&args.old) {
+        Ok(resources) => resources,
+        Err(()) => return ExitCode::from(2),
+    };
+    let (old, new) = match &args.new {
+        Some(path) => match read(path) {
+            Ok(new) => (index(&old, None), index(&new, None)),
+            Err(()) => return ExitCode::from(2),
+        },
+        None => match live(&old) {
+            Ok((namespace, new)) => (index(&old, Some(&namespace)), index(&new, Some(&namespace))),
+            Err(()) => return ExitCode::from(2),
+        },
+    };
+
+    let mut objects: Vec<&ObjectRef> = old.keys().chain(new.keys()).collect();
+    objects.sort();
+    objects.dedup();
+    let mut changed = false;
+    for object in objects {
+        match (old.get(object), new.get(object)) {
+            (Some(_), None) => println!("- {}", object),
+            (None, Some(_)) => println!("+ {}", object),
+            (Some(old), Some(new)) => {
+                let changes = diff_values(old, new);
+                if changes.is_empty() {
+                    continue;
+                }
+                println!("~ {}", object);
+                for change in changes {
+                    println!("    {}", change);
+                }
+            }
+            (None, None) => continue,
+        }
+        changed = true;
+    }
+    // Like diff(1): 1 when the manifests differ.
+    if changed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<Resource>, ()> {
+    let documents = manifest::load(&[path.to_path_buf()]).map_err(|(path, error)| {
+        eprintln!("{}: error: {}", path.display(), error);
+    })?;
+    let mut resources = Vec::new();
+    let mut failed = false;
+    for document in &documents {
+        match manifest::parse(document) {
+            Ok(Some(resource)) => resources.push(resource),
+            Ok(None) => {}
+            Err(error) => {
+                failed = true;
+                let line = match (&error.line, &error.path) {
+                    (Some(line), _) => document.line + line,
+                    (None, path) => document.locate(path.as_deref().unwrap_or_default()),
+                };
+                eprintln!("{}:{}: error: {}", document.file.display(), line, error);
+            }
+        }
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(resources)
+    }
+}
+
+/// Fetches the cluster objects of `resources`. Returns the default namespace of the kubeconfig
+/// context, resources without a namespace are looked up in it.
+fn live(resources: &[Resource]) -> Result<(String, Vec<Resource>), ()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|error| eprintln!("error: {}", error))?;
+    runtime.block_on(async {
+        let config = Config::infer()
+            .await
+            .map_err(|error| eprintln!("error: {}", error))?;
+        let namespace = config.default_namespace.clone();
+        let client = Client::try_from(config).map_err(|error| eprintln!("error: {}", error))?;
+
+        let mut objects = Vec::new();
+        for resource in resources {
+            let object = resource.object();
+            let api: Api<DynamicObject> = Api::namespaced_with(
+                client.clone(),
+                object.resource.namespace.as_deref().unwrap_or(&namespace),
+                &resource.api_resource(),
+            );
+            let mut value = match api.get(&object.resource.name).await {
+                Ok(live) => serde_json::to_value(live).unwrap_or(Value::Null),
+                Err(kube::Error::Api(response)) if response.code == 404 => continue,
+                Err(error) => {
+                    eprintln!("{}: error: {}", object, error);
+                    return Err(());
+                }
+            };
+            // Istio reports its analysis in the status, which the typed resources do not model.
+            if let Some(fields) = value.as_object_mut() {
+                fields.remove("status");
+            }
+            match manifest::resource(value) {
+                Ok(Some(live)) => objects.push(live),
+                Ok(None) => {}
+                Err(error) => {
+                    eprintln!("{}: error: {}", object, error);
+                    return Err(());
+                }
+            }
+        }
+        Ok((namespace, objects))
+    })
+}
+
+/// The normalized resources by object, with `namespace` filled in where it is missing.
+fn index(resources: &[Resource], namespace: Option<&str>) -> BTreeMap<ObjectRef, Value> {
+    resources
+        .iter()
+        .map(|resource| {
+            let mut object = resource.object();
+            let mut value = normalize(resource);
+            if let (None, Some(namespace)) = (&object.resource.namespace, namespace) {
+                object.resource.namespace = Some(namespace.to_string());
+                value["metadata"]["namespace"] = Value::from(namespace);
+            }
+            (object, value)
+        })
+        .collect()
+}
+
+/// The typed form of a resource with the metadata reduced to what a manifest sets: name,
+/// namespace, labels and annotations.
+fn normalize(resource: &Resource) -> Value {
+    let mut value = resource.to_value();
+    if let Some(Value::Object(metadata)) = value.get_mut("metadata") {
+        metadata.retain(|key, _| {
+            matches!(
+                key.as_str(),
+                "name" | "namespace" | "labels" | "annotations"
+            )
+        });
+        if let Some(Value::Object(annotations)) = metadata.get_mut("annotations") {
+            annotations.remove(LAST_APPLIED);
+            if annotations.is_empty() {
+                metadata.remove("annotations");
+            }
+        }
+    }
+    value
+}
@@ -0,0 +1,39 @@
+// kube-extra: offline tooling for Istio manifests, meant to run as a pre-commit hook or CI gate.
+mod convert;
+mod diff;
+mod manifest;
+mod validate;
+
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "kube-extra",
+    version,
+    about = "Offline checks for Istio manifests"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validate manifests and report problems as `file:line` diagnostics.
+    Validate(validate::Args),
+
+    /// Rewrite manifests to another API version.
+    Convert(convert::Args),
+
+    /// Show the semantic differences between two manifests, or a manifest and the cluster.
+    Diff(diff::Args),
+}
+
+fn main() -> ExitCode {
+    match Cli::parse().command {
+        Command::Validate(args) => validate::run(args),
+        Command::Convert(args) => convert::run(args),
+        Command::Diff(args) => diff::run(args),
+    }
+}
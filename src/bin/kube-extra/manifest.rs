@@ -0,0 +1,435 @@
+// Loading of YAML manifests: files are split into documents, every document keeps the line it
+// starts at so problems found in a resource can be reported as `file:line`.
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::{self, Value};
+use kube::core::ApiResource;
+use kube_resource_extras::istio::dependency::ObjectRef;
+use kube_resource_extras::istio::serialization;
+use kube_resource_extras::istio::topology::ResourceRef;
+use kube_resource_extras::istio::{
+    AuthorizationPolicy, DestinationRule, EnvoyFilter, Gateway, PeerAuthentication, ProxyConfig,
+    RequestAuthentication, ServiceEntry, Sidecar, VirtualService, WorkloadEntry, WorkloadGroup,
+};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// # Document
+/// One document of a YAML file.
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub file: PathBuf,
+
+    /// Line of the file the document starts at, starting at 1.
+    pub line: usize,
+
+    pub text: String,
+}
+
+impl Document {
+    /// Whether the document has nothing but blank lines and comments.
+    pub fn is_empty(&self) -> bool {
+        !self.text.lines().any(is_content)
+    }
+
+    /// The line of the field at `path`, e.g. `spec.http[0].route`. Falls back to the closest
+    /// enclosing field that can be found, or to the start of the document.
+    pub fn locate(&self, path: &str) -> usize {
+        let lines: Vec<&str> = self.text.lines().collect();
+        let mut found = first_content_line(&lines, 0, lines.len()).unwrap_or(0);
+        let (mut start, mut end) = (0, lines.len());
+        for segment in segments(path) {
+            let next = match segment {
+                Segment::Key(key) => find_key(&lines, start, end, key),
+                Segment::Index(index) => find_item(&lines, start, end, index),
+            };
+            match next {
+                Some((line, region)) => {
+                    found = line;
+                    start = region.0;
+                    end = region.1;
+                }
+                None => break,
+            }
+        }
+        self.line + found
+    }
+}
+
+/// # Resource
+/// A resource of a kind known to this crate.
+#[derive(Clone, Debug)]
+pub enum Resource {
+    VirtualService(VirtualService),
+    DestinationRule(Box<DestinationRule>),
+    Gateway(Gateway),
+    ServiceEntry(ServiceEntry),
+    Sidecar(Sidecar),
+    WorkloadEntry(WorkloadEntry),
+    WorkloadGroup(Box<WorkloadGroup>),
+    EnvoyFilter(EnvoyFilter),
+    RequestAuthentication(RequestAuthentication),
+    AuthorizationPolicy(AuthorizationPolicy),
+    PeerAuthentication(PeerAuthentication),
+    ProxyConfig(ProxyConfig),
+    Secret(Secret),
+}
+
+impl Resource {
+    /// Kind, namespace and name of the resource.
+    pub fn object(&self) -> ObjectRef {
+        match self {
+            Resource::VirtualService(item) => object::<VirtualService>(&item.metadata),
+            Resource::DestinationRule(item) => object::<DestinationRule>(&item.metadata),
+            Resource::Gateway(item) => object::<Gateway>(&item.metadata),
+            Resource::ServiceEntry(item) => object::<ServiceEntry>(&item.metadata),
+            Resource::Sidecar(item) => object::<Sidecar>(&item.metadata),
+            Resource::WorkloadEntry(item) => object::<WorkloadEntry>(&item.metadata),
+            Resource::WorkloadGroup(item) => object::<WorkloadGroup>(&item.metadata),
+            Resource::EnvoyFilter(item) => object::<EnvoyFilter>(&item.metadata),
+            Resource::RequestAuthentication(item) => {
+                object::<RequestAuthentication>(&item.metadata)
+            }
+            Resource::AuthorizationPolicy(item) => object::<AuthorizationPolicy>(&item.metadata),
+            Resource::PeerAuthentication(item) => object::<PeerAuthentication>(&item.metadata),
+            Resource::ProxyConfig(item) => object::<ProxyConfig>(&item.metadata),
+            Resource::Secret(item) => object::<Secret>(&item.metadata),
+        }
+    }
+
+    /// The serialized resource, without `apiVersion` and `kind`.
+    pub fn to_value(&self) -> Value {
+        let value = match self {
+            Resource::VirtualService(item) => serde_json::to_value(item),
+            Resource::DestinationRule(item) => serde_json::to_value(item),
+            Resource::Gateway(item) => serde_json::to_value(item),
+            Resource::ServiceEntry(item) => serde_json::to_value(item),
+            Resource::Sidecar(item) => serde_json::to_value(item),
+            Resource::WorkloadEntry(item) => serde_json::to_value(item),
+            Resource::WorkloadGroup(item) => serde_json::to_value(item),
+            Resource::EnvoyFilter(item) => serde_json::to_value(item),
+            Resource::RequestAuthentication(item) => serde_json::to_value(item),
+            Resource::AuthorizationPolicy(item) => serde_json::to_value(item),
+            Resource::PeerAuthentication(item) => serde_json::to_value(item),
+            Resource::ProxyConfig(item) => serde_json::to_value(item),
+            Resource::Secret(item) => serde_json::to_value(item),
+        };
+        value.unwrap_or(Value::Null)
+    }
+
+    /// Paths of the fields of `value`, the document the resource was read from, that this crate
+    /// does not model.
+    pub fn unknown_fields(&self, value: &Value) -> Vec<String> {
+        match self {
+            Resource::VirtualService(item) => serialization::unknown_fields(value, item),
+            Resource::DestinationRule(item) => serialization::unknown_fields(value, item.as_ref()),
+            Resource::Gateway(item) => serialization::unknown_fields(value, item),
+            Resource::ServiceEntry(item) => serialization::unknown_fields(value, item),
+            Resource::Sidecar(item) => serialization::unknown_fields(value, item),
+            Resource::WorkloadEntry(item) => serialization::unknown_fields(value, item),
+            Resource::WorkloadGroup(item) => serialization::unknown_fields(value, item.as_ref()),
+            Resource::EnvoyFilter(item) => serialization::unknown_fields(value, item),
+            Resource::RequestAuthentication(item) => serialization::unknown_fields(value, item),
+            Resource::AuthorizationPolicy(item) => serialization::unknown_fields(value, item),
+            Resource::PeerAuthentication(item) => serialization::unknown_fields(value, item),
+            Resource::ProxyConfig(item) => serialization::unknown_fields(value, item),
+            Resource::Secret(_) => Vec::new(),
+        }
+    }
+
+    /// The API the resource is served by, in the version this crate models.
+    pub fn api_resource(&self) -> ApiResource {
+        match self {
+            Resource::VirtualService(_) => ApiResource::erase::<VirtualService>(&()),
+            Resource::DestinationRule(_) => ApiResource::erase::<DestinationRule>(&()),
+            Resource::Gateway(_) => ApiResource::erase::<Gateway>(&()),
+            Resource::ServiceEntry(_) => ApiResource::erase::<ServiceEntry>(&()),
+            Resource::Sidecar(_) => ApiResource::erase::<Sidecar>(&()),
+            Resource::WorkloadEntry(_) => ApiResource::erase::<WorkloadEntry>(&()),
+            Resource::WorkloadGroup(_) => ApiResource::erase::<WorkloadGroup>(&()),
+            Resource::EnvoyFilter(_) => ApiResource::erase::<EnvoyFilter>(&()),
+            Resource::RequestAuthentication(_) => ApiResource::erase::<RequestAuthentication>(&()),
+            Resource::AuthorizationPolicy(_) => ApiResource::erase::<AuthorizationPolicy>(&()),
+            Resource::PeerAuthentication(_) => ApiResource::erase::<PeerAuthentication>(&()),
+            Resource::ProxyConfig(_) => ApiResource::erase::<ProxyConfig>(&()),
+            Resource::Secret(_) => ApiResource::erase::<Secret>(&()),
+        }
+    }
+}
+
+fn object<K: k8s_openapi::Resource>(metadata: &ObjectMeta) -> ObjectRef {
+    ObjectRef::new::<K>(ResourceRef::from_metadata(metadata))
+}
+
+/// # LoadError
+#[derive(Clone, Debug)]
+pub struct LoadError {
+    /// Path of the offending field within the document, if known.
+    pub path: Option<String>,
+
+    /// Line of the error within the document, starting at 0, if known.
+    pub line: Option<usize>,
+
+    pub message: String,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The content of a document as a JSON value, `None` for empty documents.
+pub fn value(document: &Document) -> Result<Option<Value>, LoadError> {
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(&document.text).map_err(|error| LoadError {
+            path: None,
+            line: error
+                .location()
+                .map(|location| location.line().saturating_sub(1)),
+            message: error.to_string(),
+        })?;
+    if yaml.is_null() {
+        return Ok(None);
+    }
+    // The resource types are deserialized from JSON values, which map externally tagged enums
+    // (e.g. StringMatch) the same way the API server does.
+    serde_json::to_value(&yaml)
+        .map(Some)
+        .map_err(|error| LoadError {
+            path: None,
+            line: None,
+            message: error.to_string(),
+        })
+}
+
+/// Deserializes a document. Documents of unknown kinds and empty documents give `None`.
+pub fn parse(document: &Document) -> Result<Option<Resource>, LoadError> {
+    match value(document)? {
+        Some(value) => resource(value),
+        None => Ok(None),
+    }
+}
+
+/// Deserializes a resource given as a JSON value. Values of unknown kinds give `None`.
+pub fn resource(value: Value) -> Result<Option<Resource>, LoadError> {
+    let api_version = value.get("apiVersion").and_then(Value::as_str);
+    let kind = value.get("kind").and_then(Value::as_str);
+    let resource = match (api_version, kind) {
+        (Some(api_version), Some(kind)) if api_version.starts_with("networking.istio.io/") => {
+            match kind {
+                "VirtualService" => Resource::VirtualService(deserialize(value)?),
+                "DestinationRule" => Resource::DestinationRule(Box::new(deserialize(value)?)),
+                "Gateway" => Resource::Gateway(deserialize(value)?),
+                "ServiceEntry" => Resource::ServiceEntry(deserialize(value)?),
+                "Sidecar" => Resource::Sidecar(deserialize(value)?),
+                "WorkloadEntry" => Resource::WorkloadEntry(deserialize(value)?),
+                "WorkloadGroup" => Resource::WorkloadGroup(Box::new(deserialize(value)?)),
+                "EnvoyFilter" => Resource::EnvoyFilter(deserialize(value)?),
+                "ProxyConfig" => Resource::ProxyConfig(deserialize(value)?),
+                _ => return Ok(None),
+            }
+        }
+        (Some(api_version), Some(kind)) if api_version.starts_with("security.istio.io/") => {
+            match kind {
+                "RequestAuthentication" => Resource::RequestAuthentication(deserialize(value)?),
+                "AuthorizationPolicy" => Resource::AuthorizationPolicy(deserialize(value)?),
+                "PeerAuthentication" => Resource::PeerAuthentication(deserialize(value)?),
+                _ => return Ok(None),
+            }
+        }
+        (Some("v1"), Some("Secret")) => Resource::Secret(deserialize(value)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(resource))
+}
+
+fn deserialize<T: k8s_openapi::Resource + serde::de::DeserializeOwned>(
+    value: Value,
+) -> Result<T, LoadError> {
+    serialization::deserialize(value).map_err(|error| LoadError {
+        path: error.path,
+        line: None,
+        message: error.message,
+    })
+}
+
+/// The YAML files in `paths`. Directories are searched recursively for `.yaml` and `.yml` files.
+pub fn files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, (PathBuf, std::io::Error)> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect(path, &mut files).map_err(|error| (path.clone(), error))?;
+    }
+    Ok(files)
+}
+
+/// Reads the documents of all YAML files in `paths`, see [`files`].
+pub fn load(paths: &[PathBuf]) -> Result<Vec<Document>, (PathBuf, std::io::Error)> {
+    let mut documents = Vec::new();
+    for file in files(paths)? {
+        let text = fs::read_to_string(&file).map_err(|error| (file.clone(), error))?;
+        documents.extend(
+            split(&file, &text)
+                .into_iter()
+                .filter(|document| !document.is_empty()),
+        );
+    }
+    Ok(documents)
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let yaml = entry
+                .extension()
+                .is_some_and(|extension| extension == "yaml" || extension == "yml");
+            if entry.is_dir() || yaml {
+                collect(&entry, files)?;
+            }
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Splits a file at its `---` document separators. Documents without content, e.g. a leading
+/// comment block, are kept so the file can be reassembled.
+pub fn split(file: &Path, text: &str) -> Vec<Document> {
+    let mut documents = Vec::new();
+    let mut current = Document {
+        file: file.to_path_buf(),
+        line: 1,
+        text: String::new(),
+    };
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_end() == "---" || line.starts_with("--- ") {
+            let next = Document {
+                file: file.to_path_buf(),
+                line: i + 2,
+                text: String::new(),
+            };
+            documents.push(std::mem::replace(&mut current, next));
+        } else {
+            current.text.push_str(line);
+            current.text.push('\n');
+        }
+    }
+    documents.push(current);
+    documents
+}
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Splits `spec.http[0].route` into `spec`, `http`, `0` and `route`.
+fn segments(path: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|part| !part.is_empty()) {
+        let mut parts = part.split('[');
+        if let Some(key) = parts.next().filter(|key| !key.is_empty()) {
+            segments.push(Segment::Key(key));
+        }
+        for index in parts {
+            if let Ok(index) = index.trim_end_matches(']').parse() {
+                segments.push(Segment::Index(index));
+            }
+        }
+    }
+    segments
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+fn first_content_line(lines: &[&str], start: usize, end: usize) -> Option<usize> {
+    (start..end.min(lines.len())).find(|i| is_content(lines[*i]))
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The column and name of the mapping key on a line, skipping sequence item markers.
+fn key_of(line: &str) -> Option<(usize, &str)> {
+    let mut column = indent(line);
+    let mut rest = line.trim_start();
+    while let Some(item) = rest.strip_prefix("- ") {
+        let trimmed = item.trim_start();
+        column += rest.len() - trimmed.len();
+        rest = trimmed;
+    }
+    let (key, _) = rest.split_once(':')?;
+    Some((column, key.trim().trim_matches(|c| c == '"' || c == '\'')))
+}
+
+/// Finds `key` in the mapping starting at the first content line of the region. Returns the line
+/// of the key and the region of its value.
+fn find_key(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    key: &str,
+) -> Option<(usize, (usize, usize))> {
+    let first = first_content_line(lines, start, end)?;
+    let (column, _) = key_of(lines[first])?;
+    for i in first..end {
+        if !is_content(lines[i]) {
+            continue;
+        }
+        // The region of the first line may start inside a sequence item, e.g. `- name: x`.
+        if i > first && indent(lines[i]) < column {
+            break;
+        }
+        if let Some((key_column, name)) = key_of(lines[i]) {
+            if key_column == column && name == key {
+                let value_end = (i + 1..end)
+                    .find(|j| {
+                        is_content(lines[*j])
+                            && (indent(lines[*j]) < column
+                                || (indent(lines[*j]) == column
+                                    && !lines[*j].trim_start().starts_with('-')))
+                    })
+                    .unwrap_or(end);
+                return Some((i, (i + 1, value_end)));
+            }
+        }
+    }
+    None
+}
+
+/// Finds the item `index` of the sequence starting at the first content line of the region.
+/// Returns the line of the item and the region of its content, including the item line.
+fn find_item(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    index: usize,
+) -> Option<(usize, (usize, usize))> {
+    let first = first_content_line(lines, start, end)?;
+    let column = indent(lines[first]);
+    let items: Vec<usize> = (first..end)
+        .filter(|i| {
+            is_content(lines[*i])
+                && indent(lines[*i]) == column
+                && lines[*i].trim_start().starts_with('-')
+        })
+        .collect();
+    let item = *items.get(index)?;
+    let item_end = (item + 1..end)
+        .find(|j| is_content(lines[*j]) && indent(lines[*j]) <= column)
+        .unwrap_or(end);
+    Some((item, (item, item_end)))
+}
@@ -0,0 +1,258 @@
+// `kube-extra validate`: deserializes manifests, runs the offline validation, resolves the
+// references between the resources found and checks the DestinationRules against STRICT mutual TLS
+// and, given a target Istio release, the fields it does not support. Fields this crate does not
+// model, typos most of the time, are warned about. Problems are reported as
+// `file:line: level: message`.
+use crate::manifest::{self, Document, Resource};
+use kube_resource_extras::istio::dependency::{DependencyGraph, ObjectRef};
+use kube_resource_extras::istio::mtls::strict_conflicts;
+use kube_resource_extras::istio::patch_order::DEFAULT_ROOT_NAMESPACE;
+use kube_resource_extras::istio::topology::ResourceRef;
+use kube_resource_extras::istio::validate::Validate;
+use kube_resource_extras::istio::version::{self, IstioVersion};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// YAML files or directories to validate. Directories are searched recursively.
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Warn about fields the given Istio release does not support, e.g. `1.17`.
+    #[arg(long)]
+    pub istio_version: Option<IstioVersion>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warning,
+}
+
+struct Diagnostic<'a> {
+    document: &'a Document,
+    line: usize,
+    level: Level,
+    message: String,
+}
+
+impl Diagnostic<'_> {
+    fn print(&self) {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        eprintln!(
+            "{}:{}: {}: {}",
+            self.document.file.display(),
+            self.line,
+            level,
+            self.message
+        );
+    }
+}
+
+pub fn run(args: Args) -> ExitCode {
+    let documents = match manifest::load(&args.paths) {
+        Ok(documents) => documents,
+        Err((path, error)) => {
+            eprintln!("{}: error: {}", path.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut resources = Vec::new();
+    for document in &documents {
+        match manifest::parse(document) {
+            Ok(Some(resource)) => resources.push((document, resource)),
+            Ok(None) => {}
+            Err(error) => {
+                let line = match (&error.line, &error.path) {
+                    (Some(line), _) => document.line + line,
+                    (None, Some(path)) => document.locate(path),
+                    (None, None) => document.locate(""),
+                };
+                diagnostics.push(Diagnostic {
+                    document,
+                    line,
+                    level: Level::Error,
+                    message: error.to_string(),
+                });
+            }
+        }
+    }
+
+    for (document, resource) in &resources {
+        let result = match resource {
+            Resource::VirtualService(item) => item.validate(),
+            Resource::DestinationRule(item) => item.validate(),
+            Resource::Gateway(item) => item.validate(),
+            Resource::ServiceEntry(item) => item.validate(),
+            Resource::EnvoyFilter(item) => item.validate(),
+            Resource::RequestAuthentication(item) => item.validate(),
+            _ => Ok(()),
+        };
+        if let Err(errors) = result {
+            diagnostics.extend(errors.iter().map(|error| Diagnostic {
+                document,
+                line: document.locate(&error.path),
+                level: Level::Error,
+                message: error.to_string(),
+            }));
+        }
+    }
+    diagnostics.extend(unknown(&resources));
+    if let Some(version) = args.istio_version {
+        diagnostics.extend(skew(&documents, version));
+    }
+    diagnostics.extend(dangling(&resources));
+    diagnostics.extend(mtls(&resources));
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level == Level::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    diagnostics.sort_by(|a, b| (&a.document.file, a.line).cmp(&(&b.document.file, b.line)));
+    for diagnostic in &diagnostics {
+        diagnostic.print();
+    }
+    if !diagnostics.is_empty() {
+        eprintln!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 || (args.strict && warnings > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Warnings for fields `version` does not support. The documents are checked as written, so fields
+/// this crate does not model are covered as well.
+fn skew(documents: &[Document], version: IstioVersion) -> Vec<Diagnostic<'_>> {
+    documents
+        .iter()
+        .filter_map(|document| Some((document, manifest::value(document).ok()??)))
+        .flat_map(|(document, value)| {
+            version::unsupported(&value, version)
+                .into_iter()
+                .map(move |error| Diagnostic {
+                    document,
+                    line: document.locate(&error.path),
+                    level: Level::Warning,
+                    message: error.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Warnings for fields of the documents that the resources read from them do not model.
+fn unknown<'a>(resources: &[(&'a Document, Resource)]) -> Vec<Diagnostic<'a>> {
+    resources
+        .iter()
+        .filter_map(|(document, resource)| {
+            Some((*document, resource, manifest::value(document).ok()??))
+        })
+        .flat_map(|(document, resource, value)| {
+            resource
+                .unknown_fields(&value)
+                .into_iter()
+                .map(move |path| Diagnostic {
+                    document,
+                    line: document.locate(&path),
+                    level: Level::Warning,
+                    message: format!("{}: unknown field", path),
+                })
+        })
+        .collect()
+}
+
+/// Warnings for references to objects missing from the manifests. Kinds without any document are
+/// not passed to the graph, so references to them are left unchecked.
+fn dangling<'a>(resources: &[(&'a Document, Resource)]) -> Vec<Diagnostic<'a>> {
+    let mut builder = DependencyGraph::builder();
+    let mut objects: HashMap<ObjectRef, &Document> = HashMap::new();
+    let mut destination_rules = Vec::new();
+    let mut gateways = Vec::new();
+    let mut secrets = Vec::new();
+    for (document, resource) in resources {
+        match resource {
+            Resource::VirtualService(item) => builder = builder.virtual_services([item]),
+            Resource::DestinationRule(item) => destination_rules.push(item.as_ref()),
+            Resource::Gateway(item) => gateways.push(item),
+            Resource::Secret(item) => secrets.push(item),
+            _ => {}
+        }
+        objects.insert(resource.object(), *document);
+    }
+    if !destination_rules.is_empty() {
+        builder = builder.destination_rules(destination_rules);
+    }
+    if !gateways.is_empty() {
+        builder = builder.gateways(gateways);
+    }
+    if !secrets.is_empty() {
+        builder = builder.secrets(secrets);
+    }
+
+    let graph = builder.build();
+    graph
+        .dangling()
+        .filter_map(|reference| {
+            let document = *objects.get(&reference.from)?;
+            Some(Diagnostic {
+                document,
+                line: document.locate(&reference.field),
+                level: Level::Warning,
+                message: format!(
+                    "{}: references missing {}",
+                    reference.field, reference.target
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Warnings for DestinationRules whose TLS settings break under a STRICT PeerAuthentication.
+fn mtls<'a>(resources: &[(&'a Document, Resource)]) -> Vec<Diagnostic<'a>> {
+    let peer_authentications = resources.iter().filter_map(|(_, resource)| match resource {
+        Resource::PeerAuthentication(item) => Some(item),
+        _ => None,
+    });
+    let destination_rules = resources.iter().filter_map(|(_, resource)| match resource {
+        Resource::DestinationRule(item) => Some(item.as_ref()),
+        _ => None,
+    });
+    strict_conflicts(
+        peer_authentications,
+        destination_rules,
+        DEFAULT_ROOT_NAMESPACE,
+    )
+    .into_iter()
+    .filter_map(|conflict| {
+        let document = resources
+            .iter()
+            .find_map(|(document, resource)| match resource {
+                Resource::DestinationRule(item)
+                    if ResourceRef::from_metadata(&item.metadata) == conflict.destination_rule =>
+                {
+                    Some(*document)
+                }
+                _ => None,
+            })?;
+        Some(Diagnostic {
+            document,
+            line: document.locate(&format!("spec.{}.tls.mode", conflict.path)),
+            level: Level::Warning,
+            message: conflict.to_string(),
+        })
+    })
+    .collect()
+}
@@ -0,0 +1,83 @@
+// A validating admission webhook for the Istio kinds. `review` takes the AdmissionReview the API
+// server posts, runs the offline validation of this crate on the object and answers with an
+// AdmissionReview allowing or denying it; fields the crate does not model are returned as
+// warnings. The function is independent of any HTTP framework; with axum the handler is
+//
+//     async fn validate(Json(review): Json<AdmissionReview<DynamicObject>>) -> Json<AdmissionReview<DynamicObject>> {
+//         Json(admission::review(review))
+//     }
+//
+// and `tower::service_fn` wraps it the same way. Needs the `admission` feature.
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::gateway::Gateway;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::serialization::{self, PreservedFields};
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::validate::Validate;
+use crate::istio::virtual_service::VirtualService;
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::Resource;
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use kube::core::DynamicObject;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Answers an AdmissionReview. A review without a request is answered as invalid.
+pub fn review(review: AdmissionReview<DynamicObject>) -> AdmissionReview<DynamicObject> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(error) => return AdmissionResponse::invalid(error.to_string()).into_review(),
+    };
+    respond(&request).into_review()
+}
+
+/// The response to an admission request. Requests without an object, e.g. deletions, and objects
+/// of kinds without validation rules are allowed.
+pub fn respond(request: &AdmissionRequest<DynamicObject>) -> AdmissionResponse {
+    let mut response = AdmissionResponse::from(request);
+    let object = match &request.object {
+        Some(object) => object,
+        None => return response,
+    };
+    let value = match serde_json::to_value(object) {
+        Ok(value) => value,
+        Err(error) => return response.deny(error),
+    };
+    let outcome = match (request.kind.group.as_str(), request.kind.kind.as_str()) {
+        (group, kind) if matches::<VirtualService>(group, kind) => check::<VirtualService>(value),
+        (group, kind) if matches::<DestinationRule>(group, kind) => check::<DestinationRule>(value),
+        (group, kind) if matches::<Gateway>(group, kind) => check::<Gateway>(value),
+        (group, kind) if matches::<ServiceEntry>(group, kind) => check::<ServiceEntry>(value),
+        (group, kind) if matches::<EnvoyFilter>(group, kind) => check::<EnvoyFilter>(value),
+        (group, kind) if matches::<RequestAuthentication>(group, kind) => {
+            check::<RequestAuthentication>(value)
+        }
+        _ => return response,
+    };
+    match outcome {
+        Ok(warnings) => {
+            response.warnings = Some(warnings).filter(|warnings| !warnings.is_empty());
+            response
+        }
+        Err(reason) => response.deny(reason),
+    }
+}
+
+fn matches<T: Resource>(group: &str, kind: &str) -> bool {
+    group == T::GROUP && kind == T::KIND
+}
+
+/// Validates `value` as a `T`, returning the unknown fields as warnings or the reason to deny it.
+fn check<T>(value: Value) -> Result<Vec<String>, String>
+where
+    T: Resource + DeserializeOwned + Serialize + PreservedFields + Validate,
+{
+    let resource: T =
+        serialization::deserialize(value.clone()).map_err(|error| error.to_string())?;
+    resource.validate().map_err(|errors| errors.to_string())?;
+    Ok(serialization::unknown_fields(&value, &resource)
+        .into_iter()
+        .map(|path| format!("{}: unknown field", path))
+        .collect())
+}
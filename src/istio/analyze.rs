@@ -0,0 +1,649 @@
+// Analysis of a set of resources in the manner of `istioctl analyze`: every check produces
+// diagnostics with the code and severity of the equivalent Istio analyzer, the resource and the
+// path of the offending field, so a CI pipeline can fail on errors and show the rest. Checks that
+// need resources of a kind that was not provided, e.g. references to Gateways, are skipped.
+use crate::istio::client_tls_settings::TLSmode;
+use crate::istio::dependency::{DependencyGraph, ObjectRef, ReferenceStatus};
+use crate::istio::destination_rule::{DestinationRule, TrafficPolicy};
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::gateway::Gateway;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::topology::{ResourceRef, MESH_GATEWAY};
+use crate::istio::validate::Validate;
+use crate::istio::virtual_service::VirtualService;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::{Metadata, Resource};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// # Severity
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// # Code
+/// The check that produced a diagnostic, named after the Istio analyzer message.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Code {
+    /// A Gateway, delegate VirtualService, subset or credential Secret that does not exist.
+    ReferencedResourceNotFound,
+
+    /// A resource the Istio validation webhook would reject.
+    SchemaValidationError,
+
+    /// VirtualServices bound to the mesh gateway defining the same host.
+    ConflictingMeshGatewayVirtualServiceHosts,
+
+    /// A DestinationRule originating TLS without verifying the server certificate.
+    NoServerCertificateVerificationDestinationLevel,
+
+    /// An HTTP route that follows a route matching all requests.
+    VirtualServiceUnreachableRule,
+
+    /// A match that repeats a match of an earlier HTTP route.
+    VirtualServiceIneffectiveMatch,
+}
+
+impl Code {
+    /// The code of the message in `istioctl analyze`, e.g. `IST0101`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Code::ReferencedResourceNotFound => "IST0101",
+            Code::SchemaValidationError => "IST0106",
+            Code::ConflictingMeshGatewayVirtualServiceHosts => "IST0109",
+            Code::NoServerCertificateVerificationDestinationLevel => "IST0128",
+            Code::VirtualServiceUnreachableRule => "IST0130",
+            Code::VirtualServiceIneffectiveMatch => "IST0131",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Code::ReferencedResourceNotFound
+            | Code::SchemaValidationError
+            | Code::ConflictingMeshGatewayVirtualServiceHosts => Severity::Error,
+            Code::NoServerCertificateVerificationDestinationLevel
+            | Code::VirtualServiceUnreachableRule => Severity::Warning,
+            Code::VirtualServiceIneffectiveMatch => Severity::Info,
+        }
+    }
+}
+
+/// # Diagnostic
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Diagnostic {
+    pub code: Code,
+    pub severity: Severity,
+    pub resource: ObjectRef,
+
+    /// Path of the offending field, e.g. `spec.http[1]`.
+    pub path: Option<String>,
+
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(code: Code, resource: ObjectRef, path: Option<String>, message: String) -> Self {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            resource,
+            path,
+            message,
+        }
+    }
+}
+
+/// Formatted as by `istioctl analyze`, e.g.
+/// `Error [IST0101] (VirtualService default/reviews) spec.gateways[0]: gateway ... not found`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] ({})",
+            self.severity,
+            self.code.id(),
+            self.resource
+        )?;
+        if let Some(path) = &self.path {
+            write!(f, " {}", path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The highest severity of `diagnostics`, `None` if there are none.
+pub fn max_severity(diagnostics: &[Diagnostic]) -> Option<Severity> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.severity)
+        .max()
+}
+
+/// # Analyzer
+/// The resources to analyze. References to a kind are only checked when resources of the kind
+/// were given, even if none.
+#[derive(Clone, Debug, Default)]
+pub struct Analyzer<'a> {
+    virtual_services: Vec<&'a VirtualService>,
+    destination_rules: Option<Vec<&'a DestinationRule>>,
+    gateways: Option<Vec<&'a Gateway>>,
+    secrets: Option<Vec<&'a Secret>>,
+    service_entries: Vec<&'a ServiceEntry>,
+    envoy_filters: Vec<&'a EnvoyFilter>,
+    request_authentications: Vec<&'a RequestAuthentication>,
+}
+
+impl<'a> Analyzer<'a> {
+    pub fn virtual_services(mut self, items: impl IntoIterator<Item = &'a VirtualService>) -> Self {
+        self.virtual_services.extend(items);
+        self
+    }
+
+    pub fn destination_rules(
+        mut self,
+        items: impl IntoIterator<Item = &'a DestinationRule>,
+    ) -> Self {
+        self.destination_rules
+            .get_or_insert_with(Vec::new)
+            .extend(items);
+        self
+    }
+
+    pub fn gateways(mut self, items: impl IntoIterator<Item = &'a Gateway>) -> Self {
+        self.gateways.get_or_insert_with(Vec::new).extend(items);
+        self
+    }
+
+    pub fn secrets(mut self, items: impl IntoIterator<Item = &'a Secret>) -> Self {
+        self.secrets.get_or_insert_with(Vec::new).extend(items);
+        self
+    }
+
+    pub fn service_entries(mut self, items: impl IntoIterator<Item = &'a ServiceEntry>) -> Self {
+        self.service_entries.extend(items);
+        self
+    }
+
+    pub fn envoy_filters(mut self, items: impl IntoIterator<Item = &'a EnvoyFilter>) -> Self {
+        self.envoy_filters.extend(items);
+        self
+    }
+
+    pub fn request_authentications(
+        mut self,
+        items: impl IntoIterator<Item = &'a RequestAuthentication>,
+    ) -> Self {
+        self.request_authentications.extend(items);
+        self
+    }
+
+    /// Runs all checks, returning the diagnostics grouped by check.
+    pub fn analyze(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.validation(&mut diagnostics);
+        self.references(&mut diagnostics);
+        self.mesh_hosts(&mut diagnostics);
+        for dr in self.destination_rules.iter().flatten() {
+            certificate_verification(dr, &mut diagnostics);
+        }
+        for vs in &self.virtual_services {
+            route_reachability(vs, &mut diagnostics);
+        }
+        diagnostics
+    }
+
+    fn validation(&self, diagnostics: &mut Vec<Diagnostic>) {
+        fn check<T: Validate + Resource + Metadata<Ty = ObjectMeta>>(
+            resource: &T,
+            diagnostics: &mut Vec<Diagnostic>,
+        ) {
+            if let Err(errors) = resource.validate() {
+                diagnostics.extend(errors.iter().map(|error| {
+                    Diagnostic::new(
+                        Code::SchemaValidationError,
+                        object_ref(resource),
+                        Some(error.path.clone()),
+                        error.message.clone(),
+                    )
+                }));
+            }
+        }
+        self.virtual_services
+            .iter()
+            .for_each(|vs| check(*vs, diagnostics));
+        self.destination_rules
+            .iter()
+            .flatten()
+            .for_each(|dr| check(*dr, diagnostics));
+        self.gateways
+            .iter()
+            .flatten()
+            .for_each(|gateway| check(*gateway, diagnostics));
+        self.service_entries
+            .iter()
+            .for_each(|se| check(*se, diagnostics));
+        self.envoy_filters
+            .iter()
+            .for_each(|filter| check(*filter, diagnostics));
+        self.request_authentications
+            .iter()
+            .for_each(|ra| check(*ra, diagnostics));
+    }
+
+    fn references(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut builder =
+            DependencyGraph::builder().virtual_services(self.virtual_services.iter().copied());
+        if let Some(destination_rules) = &self.destination_rules {
+            builder = builder.destination_rules(destination_rules.iter().copied());
+        }
+        if let Some(gateways) = &self.gateways {
+            builder = builder.gateways(gateways.iter().copied());
+        }
+        if let Some(secrets) = &self.secrets {
+            builder = builder.secrets(secrets.iter().copied());
+        }
+        let graph = builder.build();
+        diagnostics.extend(
+            graph
+                .references()
+                .iter()
+                .filter(|reference| reference.status == ReferenceStatus::Dangling)
+                .map(|reference| {
+                    Diagnostic::new(
+                        Code::ReferencedResourceNotFound,
+                        reference.from.clone(),
+                        Some(reference.field.clone()),
+                        format!("{} not found", reference.target),
+                    )
+                }),
+        );
+    }
+
+    /// VirtualServices bound to the mesh gateway, explicitly or by not naming any gateway, that
+    /// define the same host. Short hosts are qualified with the namespace of their VirtualService.
+    fn mesh_hosts(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut hosts: BTreeMap<String, Vec<&VirtualService>> = BTreeMap::new();
+        for vs in &self.virtual_services {
+            let spec = match &vs.spec {
+                Some(spec) => spec,
+                None => continue,
+            };
+            let bound_to_mesh = match &spec.gateways {
+                None => true,
+                Some(gateways) => gateways.iter().any(|gateway| gateway == MESH_GATEWAY),
+            };
+            if !bound_to_mesh {
+                continue;
+            }
+            for host in spec.hosts.iter().flatten() {
+                let host = match (
+                    host.contains('.') || host.contains('*'),
+                    &vs.metadata.namespace,
+                ) {
+                    (false, Some(namespace)) => format!("{}.{}", host, namespace),
+                    _ => host.clone(),
+                };
+                hosts.entry(host).or_default().push(vs);
+            }
+        }
+        for (host, services) in hosts.into_iter().filter(|(_, services)| services.len() > 1) {
+            let names: Vec<String> = services
+                .iter()
+                .map(|vs| ResourceRef::from_metadata(&vs.metadata).to_string())
+                .collect();
+            for vs in services {
+                diagnostics.push(Diagnostic::new(
+                    Code::ConflictingMeshGatewayVirtualServiceHosts,
+                    object_ref(vs),
+                    Some("spec.hosts".to_string()),
+                    format!(
+                        "the VirtualServices {} bound to the mesh gateway define the same host {}",
+                        names.join(", "),
+                        host
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// TLS origination without `caCertificates` or `credentialName`, in the traffic policy of the
+/// DestinationRule or of one of its subsets.
+fn certificate_verification(dr: &DestinationRule, diagnostics: &mut Vec<Diagnostic>) {
+    let spec = match &dr.spec {
+        Some(spec) => spec,
+        None => return,
+    };
+    let mut policies: Vec<(String, &TrafficPolicy)> = Vec::new();
+    if let Some(policy) = &spec.traffic_policy {
+        policies.push(("spec.trafficPolicy".to_string(), policy));
+    }
+    for (i, subset) in spec.subsets.iter().flatten().enumerate() {
+        if let Some(policy) = &subset.traffic_policy {
+            policies.push((format!("spec.subsets[{}].trafficPolicy", i), policy));
+        }
+    }
+    for (path, policy) in policies {
+        let tls = match &policy.tls {
+            Some(tls) => tls,
+            None => continue,
+        };
+        let originates = matches!(tls.mode, TLSmode::SIMPLE | TLSmode::MUTUAL);
+        if originates && tls.ca_certificates.is_none() && tls.credential_name.is_none() {
+            diagnostics.push(Diagnostic::new(
+                Code::NoServerCertificateVerificationDestinationLevel,
+                object_ref(dr),
+                Some(format!("{}.tls", path)),
+                format!(
+                    "TLS to {} is originated without verifying the server certificate, caCertificates is not set",
+                    spec.host
+                ),
+            ));
+        }
+    }
+}
+
+/// HTTP routes after a route without match conditions, and matches repeating an earlier one.
+fn route_reachability(vs: &VirtualService, diagnostics: &mut Vec<Diagnostic>) {
+    let routes = match vs.spec.as_ref().and_then(|spec| spec.http.as_ref()) {
+        Some(routes) => routes,
+        None => return,
+    };
+    let mut catch_all: Option<usize> = None;
+    let mut seen = Vec::new();
+    for (i, route) in routes.iter().enumerate() {
+        if let Some(j) = catch_all {
+            diagnostics.push(Diagnostic::new(
+                Code::VirtualServiceUnreachableRule,
+                object_ref(vs),
+                Some(format!("spec.http[{}]", i)),
+                format!(
+                    "the route is unreachable, spec.http[{}] matches all requests",
+                    j
+                ),
+            ));
+            continue;
+        }
+        let matches = route.r#match.as_deref().unwrap_or_default();
+        if matches.is_empty() {
+            catch_all = Some(i);
+            continue;
+        }
+        for (k, request) in matches.iter().enumerate() {
+            let mut unnamed = request.clone();
+            unnamed.name = None;
+            match seen.iter().find(|(_, earlier)| earlier == &unnamed) {
+                Some((earlier_path, _)) => diagnostics.push(Diagnostic::new(
+                    Code::VirtualServiceIneffectiveMatch,
+                    object_ref(vs),
+                    Some(format!("spec.http[{}].match[{}]", i, k)),
+                    format!(
+                        "the match is ineffective, {} matches the same requests",
+                        earlier_path
+                    ),
+                )),
+                None => seen.push((format!("spec.http[{}].match[{}]", i, k), unnamed)),
+            }
+        }
+    }
+}
+
+fn object_ref<T>(resource: &T) -> ObjectRef
+where
+    T: Resource + Metadata<Ty = ObjectMeta>,
+{
+    ObjectRef::new::<T>(ResourceRef::from_metadata(resource.metadata()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn resource<T: serde::de::DeserializeOwned>(name: &str, spec: Value) -> T {
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": "default" },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn findings(diagnostics: &[Diagnostic]) -> Vec<(Code, String, Option<&str>)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    diagnostic.resource.to_string(),
+                    diagnostic.path.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn codes_have_the_id_and_severity_of_istioctl() {
+        assert_eq!(Code::ReferencedResourceNotFound.id(), "IST0101");
+        assert_eq!(Code::ReferencedResourceNotFound.severity(), Severity::Error);
+        assert_eq!(Code::VirtualServiceUnreachableRule.id(), "IST0130");
+        assert_eq!(
+            Code::VirtualServiceUnreachableRule.severity(),
+            Severity::Warning
+        );
+        assert_eq!(
+            Code::VirtualServiceIneffectiveMatch.severity(),
+            Severity::Info
+        );
+    }
+
+    #[test]
+    fn formats_diagnostics_like_istioctl() {
+        let vs: VirtualService = resource("reviews", json!({ "hosts": ["reviews"] }));
+        let diagnostic = Diagnostic::new(
+            Code::ReferencedResourceNotFound,
+            object_ref(&vs),
+            Some("spec.gateways[0]".to_string()),
+            "Gateway default/ingress not found".to_string(),
+        );
+
+        assert_eq!(
+            diagnostic.to_string(),
+            "Error [IST0101] (VirtualService default/reviews) spec.gateways[0]: Gateway default/ingress not found"
+        );
+    }
+
+    #[test]
+    fn max_severity_is_none_without_diagnostics() {
+        assert_eq!(max_severity(&[]), None);
+
+        let vs: VirtualService = resource("reviews", json!({ "hosts": ["reviews"] }));
+        let diagnostics = vec![
+            Diagnostic::new(
+                Code::VirtualServiceIneffectiveMatch,
+                object_ref(&vs),
+                None,
+                String::new(),
+            ),
+            Diagnostic::new(
+                Code::VirtualServiceUnreachableRule,
+                object_ref(&vs),
+                None,
+                String::new(),
+            ),
+        ];
+        assert_eq!(max_severity(&diagnostics), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn reports_unreachable_routes_and_ineffective_matches() {
+        let vs: VirtualService = resource(
+            "reviews",
+            json!({
+                "hosts": ["reviews"],
+                "http": [
+                    { "name": "a", "match": [{ "uri": { "prefix": "/api" } }], "route": [{ "destination": { "host": "a" } }] },
+                    { "match": [{ "name": "again", "uri": { "prefix": "/api" } }, { "uri": { "exact": "/" } }], "route": [{ "destination": { "host": "b" } }] },
+                    { "route": [{ "destination": { "host": "c" } }] },
+                    { "route": [{ "destination": { "host": "d" } }] },
+                ],
+            }),
+        );
+
+        let diagnostics = Analyzer::default().virtual_services([&vs]).analyze();
+
+        assert_eq!(
+            findings(&diagnostics),
+            vec![
+                (
+                    Code::VirtualServiceIneffectiveMatch,
+                    "VirtualService default/reviews".to_string(),
+                    Some("spec.http[1].match[0]"),
+                ),
+                (
+                    Code::VirtualServiceUnreachableRule,
+                    "VirtualService default/reviews".to_string(),
+                    Some("spec.http[3]"),
+                ),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "the match is ineffective, spec.http[0].match[0] matches the same requests"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "the route is unreachable, spec.http[2] matches all requests"
+        );
+    }
+
+    #[test]
+    fn reports_virtual_services_defining_the_same_mesh_host() {
+        let reviews: VirtualService = resource("reviews", json!({ "hosts": ["reviews"] }));
+        let qualified: VirtualService = resource(
+            "reviews-qualified",
+            json!({ "hosts": ["reviews.default"], "gateways": ["mesh"] }),
+        );
+        let ingress: VirtualService = resource(
+            "reviews-ingress",
+            json!({ "hosts": ["reviews"], "gateways": ["ingress"] }),
+        );
+
+        let diagnostics = Analyzer::default()
+            .virtual_services([&reviews, &qualified, &ingress])
+            .analyze();
+
+        assert_eq!(
+            findings(&diagnostics),
+            vec![
+                (
+                    Code::ConflictingMeshGatewayVirtualServiceHosts,
+                    "VirtualService default/reviews".to_string(),
+                    Some("spec.hosts"),
+                ),
+                (
+                    Code::ConflictingMeshGatewayVirtualServiceHosts,
+                    "VirtualService default/reviews-qualified".to_string(),
+                    Some("spec.hosts"),
+                ),
+            ]
+        );
+        assert!(diagnostics[0]
+            .message
+            .ends_with("same host reviews.default"));
+    }
+
+    #[test]
+    fn checks_references_only_to_kinds_provided() {
+        let vs: VirtualService = resource(
+            "reviews",
+            json!({
+                "hosts": ["reviews"],
+                "gateways": ["ingress"],
+                "http": [{ "route": [{ "destination": { "host": "reviews", "subset": "v2" } }] }],
+            }),
+        );
+        let dr: DestinationRule = resource(
+            "reviews",
+            json!({ "host": "reviews", "subsets": [{ "name": "v1", "labels": { "version": "v1" } }] }),
+        );
+
+        let unchecked = Analyzer::default().virtual_services([&vs]).analyze();
+        assert!(unchecked.is_empty());
+
+        let diagnostics = Analyzer::default()
+            .virtual_services([&vs])
+            .destination_rules([&dr])
+            .gateways([])
+            .analyze();
+        let mut paths: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == Code::ReferencedResourceNotFound)
+            .map(|diagnostic| diagnostic.path.as_deref().unwrap())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "spec.gateways[0]",
+                "spec.http[0].route[0].destination.subset"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_tls_origination_without_certificate_verification() {
+        let dr: DestinationRule = resource(
+            "external",
+            json!({
+                "host": "api.example.com",
+                "trafficPolicy": { "tls": { "mode": "SIMPLE" } },
+                "subsets": [
+                    { "name": "verified", "trafficPolicy": { "tls": { "mode": "SIMPLE", "credentialName": "api" } } },
+                    { "name": "mesh", "trafficPolicy": { "tls": { "mode": "ISTIO_MUTUAL" } } },
+                ],
+            }),
+        );
+
+        let diagnostics = Analyzer::default().destination_rules([&dr]).analyze();
+
+        assert_eq!(
+            findings(&diagnostics),
+            vec![(
+                Code::NoServerCertificateVerificationDestinationLevel,
+                "DestinationRule default/external".to_string(),
+                Some("spec.trafficPolicy.tls"),
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_validation_errors() {
+        let vs: VirtualService = resource(
+            "reviews",
+            json!({ "hosts": ["reviews.*"], "http": [{ "route": [{ "destination": { "host": "reviews" } }] }] }),
+        );
+
+        let diagnostics = Analyzer::default().virtual_services([&vs]).analyze();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Code::SchemaValidationError);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].path.as_deref(), Some("spec.hosts[0]"));
+    }
+}
@@ -0,0 +1,211 @@
+// The well-known labels and annotations Istio reads from the metadata of namespaces and pods:
+// sidecar injection, the control plane revision and the traffic interception settings of the
+// sidecar. `IstioMetadata` is implemented for `ObjectMeta`, so it works on a pod template as well
+// as on the object itself.
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::fmt;
+
+/// Label (or, deprecated, annotation) of a pod enabling or disabling sidecar injection.
+pub const SIDECAR_INJECT: &str = "sidecar.istio.io/inject";
+
+/// Label of a namespace enabling or disabling sidecar injection for its pods.
+pub const NAMESPACE_INJECTION: &str = "istio-injection";
+
+/// Label of a namespace or pod selecting the control plane revision injecting and managing it.
+pub const REVISION: &str = "istio.io/rev";
+
+/// # PortAnnotation
+/// The annotations setting which ports the sidecar intercepts traffic on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PortAnnotation {
+    IncludeInbound,
+    ExcludeInbound,
+    IncludeOutbound,
+    ExcludeOutbound,
+}
+
+impl PortAnnotation {
+    pub fn key(&self) -> &'static str {
+        match self {
+            PortAnnotation::IncludeInbound => "traffic.sidecar.istio.io/includeInboundPorts",
+            PortAnnotation::ExcludeInbound => "traffic.sidecar.istio.io/excludeInboundPorts",
+            PortAnnotation::IncludeOutbound => "traffic.sidecar.istio.io/includeOutboundPorts",
+            PortAnnotation::ExcludeOutbound => "traffic.sidecar.istio.io/excludeOutboundPorts",
+        }
+    }
+}
+
+/// # IpRangeAnnotation
+/// The annotations setting which outbound IP ranges the sidecar intercepts traffic to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IpRangeAnnotation {
+    IncludeOutbound,
+    ExcludeOutbound,
+}
+
+impl IpRangeAnnotation {
+    pub fn key(&self) -> &'static str {
+        match self {
+            IpRangeAnnotation::IncludeOutbound => {
+                "traffic.sidecar.istio.io/includeOutboundIPRanges"
+            }
+            IpRangeAnnotation::ExcludeOutbound => {
+                "traffic.sidecar.istio.io/excludeOutboundIPRanges"
+            }
+        }
+    }
+}
+
+/// # Ports
+/// The value of a port annotation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ports {
+    /// `*`, every port.
+    All,
+    List(Vec<u16>),
+}
+
+impl fmt::Display for Ports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ports::All => write!(f, "*"),
+            Ports::List(ports) => {
+                let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+                write!(f, "{}", ports.join(","))
+            }
+        }
+    }
+}
+
+/// # IstioMetadata
+pub trait IstioMetadata {
+    /// Whether sidecar injection is enabled for the pod, from the `sidecar.istio.io/inject` label
+    /// or the deprecated annotation of the same name. `None` when neither is set, in which case
+    /// the namespace decides.
+    fn sidecar_injection(&self) -> Option<bool>;
+
+    /// Sets the `sidecar.istio.io/inject` label.
+    fn set_sidecar_injection(&mut self, enabled: bool);
+
+    /// Whether sidecar injection is enabled for the pods of the namespace, from the
+    /// `istio-injection` label.
+    fn namespace_injection(&self) -> Option<bool>;
+
+    fn set_namespace_injection(&mut self, enabled: bool);
+
+    /// The control plane revision from the `istio.io/rev` label.
+    fn revision(&self) -> Option<&str>;
+
+    fn set_revision(&mut self, revision: &str);
+
+    fn ports(&self, annotation: PortAnnotation) -> Result<Option<Ports>, AnnotationError>;
+
+    fn set_ports(&mut self, annotation: PortAnnotation, ports: &Ports);
+
+    /// The CIDRs of an IP range annotation, `*` standing for every address.
+    fn ip_ranges(&self, annotation: IpRangeAnnotation) -> Option<Vec<String>>;
+
+    fn set_ip_ranges(&mut self, annotation: IpRangeAnnotation, ranges: &[String]);
+}
+
+impl IstioMetadata for ObjectMeta {
+    fn sidecar_injection(&self) -> Option<bool> {
+        get(&self.labels, SIDECAR_INJECT)
+            .or_else(|| get(&self.annotations, SIDECAR_INJECT))
+            .map(|value| value == "true")
+    }
+
+    fn set_sidecar_injection(&mut self, enabled: bool) {
+        set(&mut self.labels, SIDECAR_INJECT, enabled.to_string());
+    }
+
+    fn namespace_injection(&self) -> Option<bool> {
+        get(&self.labels, NAMESPACE_INJECTION).map(|value| value == "enabled")
+    }
+
+    fn set_namespace_injection(&mut self, enabled: bool) {
+        let value = if enabled { "enabled" } else { "disabled" };
+        set(&mut self.labels, NAMESPACE_INJECTION, value.to_string());
+    }
+
+    fn revision(&self) -> Option<&str> {
+        get(&self.labels, REVISION)
+    }
+
+    fn set_revision(&mut self, revision: &str) {
+        set(&mut self.labels, REVISION, revision.to_string());
+    }
+
+    fn ports(&self, annotation: PortAnnotation) -> Result<Option<Ports>, AnnotationError> {
+        let value = match get(&self.annotations, annotation.key()) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        if value.trim() == "*" {
+            return Ok(Some(Ports::All));
+        }
+        split(value)
+            .map(|port| {
+                port.parse().map_err(|_| AnnotationError::InvalidPort {
+                    key: annotation.key(),
+                    value: port.to_string(),
+                })
+            })
+            .collect::<Result<Vec<u16>, _>>()
+            .map(|ports| Some(Ports::List(ports)))
+    }
+
+    fn set_ports(&mut self, annotation: PortAnnotation, ports: &Ports) {
+        set(&mut self.annotations, annotation.key(), ports.to_string());
+    }
+
+    fn ip_ranges(&self, annotation: IpRangeAnnotation) -> Option<Vec<String>> {
+        get(&self.annotations, annotation.key())
+            .map(|value| split(value).map(str::to_string).collect())
+    }
+
+    fn set_ip_ranges(&mut self, annotation: IpRangeAnnotation, ranges: &[String]) {
+        set(&mut self.annotations, annotation.key(), ranges.join(","));
+    }
+}
+
+/// # AnnotationError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnotationError {
+    InvalidPort { key: &'static str, value: String },
+}
+
+impl fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnotationError::InvalidPort { key, value } => {
+                write!(f, "invalid port {} in {}", value, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnnotationError {}
+
+type Entries = Option<std::collections::BTreeMap<String, String>>;
+
+fn get<'a>(entries: &'a Entries, key: &str) -> Option<&'a str> {
+    entries
+        .as_ref()
+        .and_then(|entries| entries.get(key))
+        .map(String::as_str)
+}
+
+fn set(entries: &mut Entries, key: &str, value: String) {
+    entries
+        .get_or_insert_with(Default::default)
+        .insert(key.to_string(), value);
+}
+
+/// The non-empty entries of a comma separated list.
+fn split(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
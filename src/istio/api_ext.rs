@@ -0,0 +1,146 @@
+// Operations a controller runs against the Istio kinds again and again, as an extension of kube's
+// `Api`: create-or-replace, patching the spec alone and listing the resources of a host. The
+// methods work for every kind, listing by host for the kinds implementing `Hosts`.
+use crate::istio::binding::hosts_intersect;
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::gateway::Gateway;
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::sidecar::Sidecar;
+use crate::istio::virtual_service::VirtualService;
+use k8s_openapi::serde_json::json;
+use kube::api::{Api, ListParams, Patch, PatchParams, PostParams};
+use kube::core::object::HasSpec;
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::future::Future;
+
+/// # Hosts
+/// The hostnames a resource applies to.
+pub trait Hosts {
+    fn hosts(&self) -> Vec<&str>;
+
+    /// Whether one of the hosts, possibly a `*` wildcard, matches `host`.
+    fn applies_to(&self, host: &str) -> bool {
+        self.hosts()
+            .into_iter()
+            .any(|candidate| hosts_intersect(candidate, host))
+    }
+}
+
+impl Hosts for VirtualService {
+    fn hosts(&self) -> Vec<&str> {
+        self.spec
+            .iter()
+            .flat_map(|spec| spec.hosts.iter().flatten())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl Hosts for DestinationRule {
+    fn hosts(&self) -> Vec<&str> {
+        self.spec.iter().map(|spec| spec.host.as_str()).collect()
+    }
+}
+
+impl Hosts for Gateway {
+    /// The hosts of all servers, without their namespace.
+    fn hosts(&self) -> Vec<&str> {
+        self.spec
+            .iter()
+            .flat_map(|spec| &spec.servers)
+            .flat_map(|server| &server.hosts)
+            .map(|host| host.split_once('/').map_or(host.as_str(), |(_, host)| host))
+            .collect()
+    }
+}
+
+impl Hosts for ServiceEntry {
+    fn hosts(&self) -> Vec<&str> {
+        self.spec
+            .iter()
+            .flat_map(|spec| &spec.hosts)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl Hosts for Sidecar {
+    /// The hosts of the egress listeners, without their namespace.
+    fn hosts(&self) -> Vec<&str> {
+        self.spec
+            .iter()
+            .flat_map(|spec| spec.egress.iter().flatten())
+            .flat_map(|listener| &listener.hosts)
+            .map(|host| host.split_once('/').map_or(host.as_str(), |(_, host)| host))
+            .collect()
+    }
+}
+
+/// # IstioApiExt
+/// Convenience operations on an `Api` of an Istio kind.
+pub trait IstioApiExt<K> {
+    /// Creates `resource`, or replaces the one of the same name with it. The resource version of
+    /// the existing resource is taken over, so a concurrent update still fails with a conflict. A
+    /// resource without a name is created, for `generateName`.
+    fn ensure(&self, resource: &K) -> impl Future<Output = Result<K, kube::Error>> + Send;
+
+    /// Merge patches the spec of the resource `name` with `spec`. Fields unset in `spec` are left
+    /// as they are; use `ensure` to drop them.
+    fn patch_spec<S>(
+        &self,
+        name: &str,
+        spec: &S,
+    ) -> impl Future<Output = Result<K, kube::Error>> + Send
+    where
+        K: HasSpec<Spec = Option<S>>,
+        S: Serialize + Debug + Sync;
+
+    /// The resources applying to `host`, wildcards on either side included. For an `Api` of
+    /// VirtualServices these are the VirtualServices routing the host.
+    fn list_for_host(&self, host: &str) -> impl Future<Output = Result<Vec<K>, kube::Error>> + Send
+    where
+        K: Hosts;
+}
+
+impl<K> IstioApiExt<K> for Api<K>
+where
+    K: Resource + Clone + DeserializeOwned + Serialize + Debug + Send + Sync,
+{
+    async fn ensure(&self, resource: &K) -> Result<K, kube::Error> {
+        let name = match resource.meta().name.as_deref() {
+            Some(name) => name,
+            None => return self.create(&PostParams::default(), resource).await,
+        };
+        match self.get_opt(name).await? {
+            None => self.create(&PostParams::default(), resource).await,
+            Some(existing) => {
+                let mut resource = resource.clone();
+                resource.meta_mut().resource_version = existing.meta().resource_version.clone();
+                self.replace(name, &PostParams::default(), &resource).await
+            }
+        }
+    }
+
+    async fn patch_spec<S>(&self, name: &str, spec: &S) -> Result<K, kube::Error>
+    where
+        K: HasSpec<Spec = Option<S>>,
+        S: Serialize + Debug + Sync,
+    {
+        let patch = Patch::Merge(json!({ "spec": spec }));
+        self.patch(name, &PatchParams::default(), &patch).await
+    }
+
+    async fn list_for_host(&self, host: &str) -> Result<Vec<K>, kube::Error>
+    where
+        K: Hosts,
+    {
+        let resources = self.list(&ListParams::default()).await?;
+        Ok(resources
+            .into_iter()
+            .filter(|resource| resource.applies_to(host))
+            .collect())
+    }
+}
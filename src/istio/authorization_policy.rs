@@ -0,0 +1,362 @@
+use crate::istio::authorization_policy::rule::{From, To};
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuthorizationPolicy {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<AuthorizationPolicySpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for AuthorizationPolicy {
+    const API_VERSION: &'static str = "security.istio.io/v1beta1";
+    const GROUP: &'static str = "security.istio.io";
+    const KIND: &'static str = "AuthorizationPolicy";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "authorizationpolicies";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for AuthorizationPolicy {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+/// # AuthorizationPolicy
+/// Istio Authorization Policy enables access control on workloads in the mesh.
+///
+/// Authorization policy supports CUSTOM, DENY and ALLOW actions for access control. When CUSTOM,
+/// DENY and ALLOW actions are used for a workload at the same time, the CUSTOM action is evaluated
+/// first, then the DENY action, and finally the ALLOW action. The evaluation is determined by the
+/// following rules:
+///
+/// 1. If there are any CUSTOM policies that match the request, evaluate and deny the request if
+///    the evaluation result is deny.
+/// 2. If there are any DENY policies that match the request, deny the request.
+/// 3. If there are no ALLOW policies for the workload, allow the request.
+/// 4. If any of the ALLOW policies match the request, allow the request.
+/// 5. Deny the request.
+///
+/// The following is an example of AuthorizationPolicy that sets action to ALLOW to create an
+/// allow policy. It allows requests from:
+///
+/// - service account `cluster.local/ns/default/sa/sleep` or
+/// - namespace `test`
+///
+/// to access the workload with:
+///
+/// - `GET` method at paths of prefix `/info` or,
+/// - `POST` method at path `/data`
+///
+/// when the request has a valid JWT token issued by `https://accounts.google.com`.
+///
+/// ```yaml
+/// apiVersion: security.istio.io/v1beta1
+/// kind: AuthorizationPolicy
+/// metadata:
+///   name: httpbin
+///   namespace: foo
+/// spec:
+///   action: ALLOW
+///   rules:
+///   - from:
+///     - source:
+///         principals: ["cluster.local/ns/default/sa/sleep"]
+///     - source:
+///         namespaces: ["test"]
+///     to:
+///     - operation:
+///         methods: ["GET"]
+///         paths: ["/info*"]
+///     - operation:
+///         methods: ["POST"]
+///         paths: ["/data"]
+///     when:
+///     - key: request.auth.claims[iss]
+///       values: ["https://accounts.google.com"]
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuthorizationPolicySpec {
+    // Optional. Workload selector decides where to apply the authorization policy. If not set, the authorization policy will be applied to all workloads in the same namespace as the authorization policy.
+    // No
+    pub selector: Option<WorkloadSelector>,
+
+    // Optional. A list of rules to match the request. A match occurs when at least one rule matches the request.
+    //
+    // If not set, the match will never occur. This is equivalent to setting a default of deny for the target workloads if the action is ALLOW.
+    // No
+    pub rules: Option<Vec<Rule>>,
+
+    // Optional. The action to take if the request is matched with the rules. Default is ALLOW if not specified.
+    // No
+    pub action: Option<Action>,
+
+    // Specifies detailed configuration of the CUSTOM action. Must be used only with CUSTOM action.
+    // No
+    pub provider: Option<ExtensionProvider>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # Action
+/// Action specifies the operation to take.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Action {
+    // Allow a request only if it matches the rules. This is the default type.
+    ALLOW,
+
+    // Deny a request if it matches any of the rules.
+    DENY,
+
+    // Audit a request if it matches any of the rules.
+    AUDIT,
+
+    // The CUSTOM action allows an extension to handle the user request if the matching rules evaluate to true. The extension is evaluated independently and before the native ALLOW and DENY actions. When used together, A request is allowed if and only if all the actions return allow, in other words, the extension cannot bypass the authorization decision made by ALLOW and DENY action. Extension behavior is defined by the named providers declared in MeshConfig. The authorization policy refers to the extension by specifying the name of the provider. One example use case of the extension is to integrate with a custom external authorization system to delegate the authorization decision to it.
+    CUSTOM,
+}
+
+/// # ExtensionProvider
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "AuthorizationPolicyExtensionProvider"))]
+pub struct ExtensionProvider {
+    // Specifies the name of the extension provider. The list of available providers is defined in the MeshConfig. Note, currently at most 1 extension provider is allowed per workload. Different workloads can use different extension provider.
+    // No
+    pub name: String,
+}
+
+/// # Rule
+/// Rule matches requests from a list of sources that perform a list of operations subject to a
+/// list of conditions. A match occurs when at least one source, one operation and all conditions
+/// matches the request. An empty rule is always matched.
+///
+/// Any string field in the rule supports Exact, Prefix, Suffix and Presence match:
+///
+/// - Exact match: `abc` will match on value `abc`.
+/// - Prefix match: `abc*` will match on value `abc` and `abcd`.
+/// - Suffix match: `*abc` will match on value `abc` and `xabc`.
+/// - Presence match: `*` will match when value is not empty.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Rule {
+    // Optional. from specifies the source of a request.
+    //
+    // If not set, any source is allowed.
+    // No
+    pub from: Option<Vec<From>>,
+
+    // Optional. to specifies the operation of a request.
+    //
+    // If not set, any operation is allowed.
+    // No
+    pub to: Option<Vec<To>>,
+
+    // Optional. when specifies a list of additional conditions of a request.
+    //
+    // If not set, any condition is allowed.
+    // No
+    pub when: Option<Vec<Condition>>,
+}
+
+pub mod rule {
+    use crate::istio::authorization_policy::{Operation, Source};
+
+    /// # From
+    /// From includes a list or sources.
+    #[skip_serializing_none]
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct From {
+        // Source specifies the source of a request.
+        // No
+        pub source: Source,
+    }
+
+    /// # To
+    /// To includes a list or operations.
+    #[skip_serializing_none]
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct To {
+        // Operation specifies the operation of a request.
+        // No
+        pub operation: Operation,
+    }
+}
+
+/// # Source
+/// Source specifies the source identities of a request. Fields in the source are ANDed together.
+///
+/// For example, the following source matches if the principal is `admin` or `dev` and the namespace
+/// is `prod` or `test` and the ip is not `1.2.3.4`.
+///
+/// ```yaml
+/// principals: ["admin", "dev"]
+/// namespaces: ["prod", "test"]
+/// notIpBlocks: ["1.2.3.4"]
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Source {
+    // Optional. A list of source peer identities (i.e. service account), which matches to the “source.principal” attribute. This field requires mTLS enabled.
+    //
+    // If not set, any principal is allowed.
+    // No
+    pub principals: Option<Vec<String>>,
+
+    // Optional. A list of negative match of source peer identities.
+    // No
+    #[serde(rename = "notPrincipals")]
+    pub not_principals: Option<Vec<String>>,
+
+    // Optional. A list of request identities (i.e. “iss/sub” claims), which matches to the “request.auth.principal” attribute.
+    //
+    // If not set, any request principal is allowed.
+    // No
+    #[serde(rename = "requestPrincipals")]
+    pub request_principals: Option<Vec<String>>,
+
+    // Optional. A list of negative match of request identities.
+    // No
+    #[serde(rename = "notRequestPrincipals")]
+    pub not_request_principals: Option<Vec<String>>,
+
+    // Optional. A list of namespaces, which matches to the “source.namespace” attribute. This field requires mTLS enabled.
+    //
+    // If not set, any namespace is allowed.
+    // No
+    pub namespaces: Option<Vec<String>>,
+
+    // Optional. A list of negative match of namespaces.
+    // No
+    #[serde(rename = "notNamespaces")]
+    pub not_namespaces: Option<Vec<String>>,
+
+    // Optional. A list of IP blocks, which matches to the “source.ip” attribute. Populated from the source address of the IP packet. Single IP (e.g. “1.2.3.4”) and CIDR (e.g. “1.2.3.0/24”) are supported.
+    //
+    // If not set, any IP is allowed.
+    // No
+    #[serde(rename = "ipBlocks")]
+    pub ip_blocks: Option<Vec<String>>,
+
+    // Optional. A list of negative match of IP blocks.
+    // No
+    #[serde(rename = "notIpBlocks")]
+    pub not_ip_blocks: Option<Vec<String>>,
+
+    // Optional. A list of IP blocks, which matches to the “remote.ip” attribute. Populated from X-Forwarded-For header or proxy protocol. To make use of this field, you must configure the numTrustedProxies field of the gatewayTopology under the meshConfig when you install Istio or using an annotation on the ingress gateway.
+    //
+    // If not set, any IP is allowed.
+    // No
+    #[serde(rename = "remoteIpBlocks")]
+    pub remote_ip_blocks: Option<Vec<String>>,
+
+    // Optional. A list of negative match of remote IP blocks.
+    // No
+    #[serde(rename = "notRemoteIpBlocks")]
+    pub not_remote_ip_blocks: Option<Vec<String>>,
+}
+
+/// # Operation
+/// Operation specifies the operations of a request. Fields in the operation are ANDed together.
+///
+/// For example, the following operation matches if the host has suffix `.example.com` and the
+/// method is `GET` or `HEAD` and the path doesn’t have prefix `/admin`.
+///
+/// ```yaml
+/// hosts: ["*.example.com"]
+/// methods: ["GET", "HEAD"]
+/// notPaths: ["/admin*"]
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Operation {
+    // Optional. A list of hosts, which matches to the “request.host” attribute.
+    //
+    // If not set, any host is allowed. Must be used only with HTTP.
+    // No
+    pub hosts: Option<Vec<String>>,
+
+    // Optional. A list of negative match of hosts.
+    // No
+    #[serde(rename = "notHosts")]
+    pub not_hosts: Option<Vec<String>>,
+
+    // Optional. A list of ports, which matches to the “destination.port” attribute.
+    //
+    // If not set, any port is allowed.
+    // No
+    pub ports: Option<Vec<String>>,
+
+    // Optional. A list of negative match of ports.
+    // No
+    #[serde(rename = "notPorts")]
+    pub not_ports: Option<Vec<String>>,
+
+    // Optional. A list of methods, which matches to the “request.method” attribute. For gRPC service, this will always be “POST”.
+    //
+    // If not set, any method is allowed. Must be used only with HTTP.
+    // No
+    pub methods: Option<Vec<String>>,
+
+    // Optional. A list of negative match of methods.
+    // No
+    #[serde(rename = "notMethods")]
+    pub not_methods: Option<Vec<String>>,
+
+    // Optional. A list of paths, which matches to the “request.url_path” attribute. For gRPC service, this will be the fully-qualified name in the form of “/package.service/method”.
+    //
+    // If not set, any path is allowed. Must be used only with HTTP.
+    // No
+    pub paths: Option<Vec<String>>,
+
+    // Optional. A list of negative match of paths.
+    // No
+    #[serde(rename = "notPaths")]
+    pub not_paths: Option<Vec<String>>,
+}
+
+/// # Condition
+/// Condition specifies additional required attributes.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Condition {
+    // The name of an Istio attribute. See the full list of supported attributes.
+    // Yes
+    pub key: String,
+
+    // Optional. A list of allowed values for the attribute. Note: at least one of values or notValues must be set.
+    // No
+    pub values: Option<Vec<String>>,
+
+    // Optional. A list of negative match of values for the attribute. Note: at least one of values or notValues must be set.
+    // No
+    #[serde(rename = "notValues")]
+    pub not_values: Option<Vec<String>>,
+}
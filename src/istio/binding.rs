@@ -0,0 +1,412 @@
+// Resolution of the VirtualServices and route rules attached to the servers of a Gateway. A
+// VirtualService is attached to a server when it is bound to the gateway, is exported to the
+// gateway's namespace and has a host selected by one of the server's `<namespace>/<dnsName>` hosts.
+// The route rules attached are those of the list served by the server's protocol whose match
+// conditions apply to the gateway and the server port.
+use crate::istio::export_to::is_exported;
+use crate::istio::gateway::{Gateway, Server, TLSmode};
+use crate::istio::host::Host;
+use crate::istio::topology::{ResourceRef, RouteProtocol};
+use crate::istio::virtual_service::{VirtualService, VirtualServiceSpec};
+
+/// # RouteRef
+/// A route rule of a VirtualService, identified by its list and index.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RouteRef {
+    pub protocol: RouteProtocol,
+    pub index: usize,
+    pub name: Option<String>,
+}
+
+/// # Binding
+/// A VirtualService attached to a server of a Gateway.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Binding {
+    /// Index of the server in the Gateway spec.
+    pub server: usize,
+
+    #[serde(rename = "serverName")]
+    pub server_name: Option<String>,
+
+    pub port: i32,
+
+    pub protocol: String,
+
+    #[serde(rename = "virtualService")]
+    pub virtual_service: ResourceRef,
+
+    /// The hosts of the VirtualService selected by the server.
+    pub hosts: Vec<String>,
+
+    /// The route rules applied on the server. Empty when the VirtualService is attached but none
+    /// of its rules applies to the server's protocol or port.
+    pub routes: Vec<RouteRef>,
+}
+
+/// # GatewayBindings
+/// The VirtualServices attached to the servers of a Gateway.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GatewayBindings {
+    pub gateway: ResourceRef,
+
+    pub bindings: Vec<Binding>,
+
+    /// VirtualServices bound to the gateway that are not attached to any server, either because
+    /// none of their hosts is selected or because they are not exported to the gateway.
+    pub unattached: Vec<ResourceRef>,
+}
+
+impl GatewayBindings {
+    pub fn resolve<'a>(
+        gateway: &Gateway,
+        virtual_services: impl IntoIterator<Item = &'a VirtualService>,
+    ) -> Self {
+        let gateway_ref = ResourceRef::from_metadata(&gateway.metadata);
+        let servers: &[Server] = gateway
+            .spec
+            .as_ref()
+            .map(|spec| spec.servers.as_slice())
+            .unwrap_or_default();
+        let mut bindings = Vec::new();
+        let mut unattached = Vec::new();
+
+        for vs in virtual_services {
+            let spec = match &vs.spec {
+                Some(spec) => spec,
+                None => continue,
+            };
+            let vs_ref = ResourceRef::from_metadata(&vs.metadata);
+            let namespace = vs_ref.namespace.as_deref();
+            if !references(spec.gateways.iter().flatten(), &gateway_ref, namespace) {
+                continue;
+            }
+            let exported = is_exported(
+                spec.export_to.as_deref(),
+                namespace,
+                gateway_ref.namespace.as_deref(),
+            );
+            let mut attached = false;
+            for (index, server) in servers.iter().enumerate() {
+                let hosts: Vec<String> = spec
+                    .hosts
+                    .iter()
+                    .flatten()
+                    .filter(|host| {
+                        server.hosts.iter().any(|selector| {
+                            selects(selector, gateway_ref.namespace.as_deref(), namespace, host)
+                        })
+                    })
+                    .cloned()
+                    .collect();
+                if !exported || hosts.is_empty() {
+                    continue;
+                }
+                attached = true;
+                bindings.push(Binding {
+                    server: index,
+                    server_name: server.name.clone(),
+                    port: server.port.number,
+                    protocol: server.port.protocol.clone(),
+                    virtual_service: vs_ref.clone(),
+                    hosts,
+                    routes: routes(spec, server, &gateway_ref, namespace),
+                });
+            }
+            if !attached {
+                unattached.push(vs_ref);
+            }
+        }
+
+        GatewayBindings {
+            gateway: gateway_ref,
+            bindings,
+            unattached,
+        }
+    }
+
+    /// The bindings of the servers listening on `port`.
+    pub fn on_port(&self, port: i32) -> impl Iterator<Item = &Binding> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.port == port)
+    }
+
+    /// The hosts exposed on `port`, sorted and without duplicates.
+    pub fn exposed_hosts(&self, port: i32) -> Vec<&str> {
+        let mut hosts: Vec<&str> = self
+            .on_port(port)
+            .filter(|binding| !binding.routes.is_empty())
+            .flat_map(|binding| binding.hosts.iter().map(String::as_str))
+            .collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        hosts
+    }
+}
+
+/// Whether two hostnames, each possibly with a leading `*` wildcard, have a host in common.
+pub fn hosts_intersect(a: &str, b: &str) -> bool {
+    match (a.strip_prefix('*'), b.strip_prefix('*')) {
+        (Some(a), Some(b)) => a.ends_with(b) || b.ends_with(a),
+        (Some(suffix), None) => b.ends_with(suffix),
+        (None, Some(suffix)) => a.ends_with(suffix),
+        (None, None) => a == b,
+    }
+}
+
+/// Whether a server host `[<namespace>/]<dnsName>` selects `host` of a VirtualService in
+/// `vs_namespace`.
+fn selects(
+    selector: &str,
+    gateway_namespace: Option<&str>,
+    vs_namespace: Option<&str>,
+    host: &str,
+) -> bool {
+    let selector = match Host::new(selector) {
+        Ok(selector) => selector,
+        Err(_) => return false,
+    };
+    let namespace_matches = match selector.namespace().unwrap_or("*") {
+        "*" => true,
+        "." => gateway_namespace == vs_namespace,
+        namespace => vs_namespace == Some(namespace),
+    };
+    namespace_matches && hosts_intersect(selector.name(), host)
+}
+
+/// Whether one of the gateway names, resolved relative to `namespace`, refers to `gateway`.
+fn references<'a>(
+    names: impl IntoIterator<Item = &'a String>,
+    gateway: &ResourceRef,
+    namespace: Option<&str>,
+) -> bool {
+    names
+        .into_iter()
+        .any(|name| ResourceRef::parse(name, namespace) == *gateway)
+}
+
+/// The route list a server of the given protocol and TLS mode is served from. HTTPS servers
+/// passing TLS through and TLS servers not terminating TLS use the tls routes; TLS servers
+/// terminating TLS and all non HTTP protocols use the tcp routes.
+fn served_protocol(server: &Server) -> RouteProtocol {
+    let passthrough = matches!(
+        server.tls.as_ref().and_then(|tls| tls.mode.as_ref()),
+        Some(TLSmode::PASSTHROUGH) | Some(TLSmode::AUTO_PASSTHROUGH)
+    );
+    match server.port.protocol.to_ascii_uppercase().as_str() {
+        "HTTP" | "HTTP2" | "GRPC" => RouteProtocol::Http,
+        "HTTPS" if passthrough => RouteProtocol::Tls,
+        "HTTPS" => RouteProtocol::Http,
+        "TLS"
+            if passthrough
+                || server
+                    .tls
+                    .as_ref()
+                    .and_then(|tls| tls.mode.as_ref())
+                    .is_none() =>
+        {
+            RouteProtocol::Tls
+        }
+        _ => RouteProtocol::Tcp,
+    }
+}
+
+/// The route rules of the VirtualService applied on `server`. A rule applies when it has no match
+/// conditions or one of its conditions applies to the gateway and the server port.
+fn routes(
+    spec: &VirtualServiceSpec,
+    server: &Server,
+    gateway: &ResourceRef,
+    namespace: Option<&str>,
+) -> Vec<RouteRef> {
+    let port = server.port.number;
+    let applies = |gateways: Option<&Vec<String>>, match_port: Option<i64>| {
+        gateways.is_none_or(|gateways| references(gateways, gateway, namespace))
+            && match_port.is_none_or(|match_port| match_port == port as i64)
+    };
+    let protocol = served_protocol(server);
+    let mut routes = Vec::new();
+    match protocol {
+        RouteProtocol::Http => {
+            for (index, rule) in spec.http.iter().flatten().enumerate() {
+                let matches = rule.r#match.as_deref().unwrap_or_default();
+                if matches.is_empty()
+                    || matches
+                        .iter()
+                        .any(|m| applies(m.gateways.as_ref(), m.port.map(i64::from)))
+                {
+                    routes.push(RouteRef {
+                        protocol,
+                        index,
+                        name: rule.name.clone(),
+                    });
+                }
+            }
+        }
+        RouteProtocol::Tls => {
+            for (index, rule) in spec.tls.iter().flatten().enumerate() {
+                if rule.r#match.is_empty()
+                    || rule
+                        .r#match
+                        .iter()
+                        .any(|m| applies(m.gateways.as_ref(), m.port.map(i64::from)))
+                {
+                    routes.push(RouteRef {
+                        protocol,
+                        index,
+                        name: None,
+                    });
+                }
+            }
+        }
+        RouteProtocol::Tcp => {
+            for (index, rule) in spec.tcp.iter().flatten().enumerate() {
+                let matches = rule.r#match.as_deref().unwrap_or_default();
+                if matches.is_empty()
+                    || matches
+                        .iter()
+                        .any(|m| applies(m.gateways.as_ref(), m.port.map(i64::from)))
+                {
+                    routes.push(RouteRef {
+                        protocol,
+                        index,
+                        name: None,
+                    });
+                }
+            }
+        }
+    }
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn gateway() -> Gateway {
+        serde_json::from_value(json!({
+            "metadata": { "name": "ingress", "namespace": "istio-system" },
+            "spec": {
+                "selector": { "istio": "ingressgateway" },
+                "servers": [
+                    { "port": { "number": 80, "name": "http", "protocol": "HTTP" }, "hosts": ["shop/*.example.com"] },
+                    {
+                        "port": { "number": 443, "name": "tls", "protocol": "TLS" },
+                        "hosts": ["*/db.example.com"],
+                        "tls": { "mode": "PASSTHROUGH" },
+                    },
+                ],
+            },
+        }))
+        .unwrap()
+    }
+
+    fn virtual_service(name: &str, namespace: &str, spec: Value) -> VirtualService {
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": namespace },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn attaches_virtual_services_to_the_servers_selecting_their_hosts() {
+        let web = virtual_service(
+            "web",
+            "shop",
+            json!({
+                "hosts": ["www.example.com", "internal.local"],
+                "gateways": ["istio-system/ingress"],
+                "http": [
+                    { "name": "admin", "match": [{ "port": 8080 }], "route": [{ "destination": { "host": "admin" } }] },
+                    { "name": "web", "route": [{ "destination": { "host": "web" } }] },
+                ],
+            }),
+        );
+        let db = virtual_service(
+            "db",
+            "data",
+            json!({
+                "hosts": ["db.example.com"],
+                "gateways": ["istio-system/ingress"],
+                "tls": [{ "match": [{ "sniHosts": ["db.example.com"] }], "route": [{ "destination": { "host": "db" } }] }],
+            }),
+        );
+        let bindings = GatewayBindings::resolve(&gateway(), [&web, &db]);
+
+        assert_eq!(
+            bindings.bindings,
+            vec![
+                Binding {
+                    server: 0,
+                    server_name: None,
+                    port: 80,
+                    protocol: "HTTP".to_string(),
+                    virtual_service: ResourceRef::new(Some("shop"), "web"),
+                    hosts: vec!["www.example.com".to_string()],
+                    routes: vec![RouteRef {
+                        protocol: RouteProtocol::Http,
+                        index: 1,
+                        name: Some("web".to_string()),
+                    }],
+                },
+                Binding {
+                    server: 1,
+                    server_name: None,
+                    port: 443,
+                    protocol: "TLS".to_string(),
+                    virtual_service: ResourceRef::new(Some("data"), "db"),
+                    hosts: vec!["db.example.com".to_string()],
+                    routes: vec![RouteRef {
+                        protocol: RouteProtocol::Tls,
+                        index: 0,
+                        name: None,
+                    }],
+                },
+            ]
+        );
+        assert_eq!(bindings.exposed_hosts(80), ["www.example.com"]);
+        assert!(bindings.unattached.is_empty());
+    }
+
+    #[test]
+    fn reports_bound_virtual_services_that_are_not_attached() {
+        // The server only selects hosts of the shop namespace, and the second VirtualService is
+        // not exported to the gateway namespace.
+        let other = virtual_service(
+            "other",
+            "other",
+            json!({ "hosts": ["www.example.com"], "gateways": ["istio-system/ingress"] }),
+        );
+        let private = virtual_service(
+            "private",
+            "shop",
+            json!({ "hosts": ["www.example.com"], "gateways": ["istio-system/ingress"], "exportTo": ["."] }),
+        );
+        let unbound = virtual_service("unbound", "shop", json!({ "hosts": ["www.example.com"] }));
+        let bindings = GatewayBindings::resolve(&gateway(), [&other, &private, &unbound]);
+        assert!(bindings.bindings.is_empty());
+        assert_eq!(
+            bindings.unattached,
+            vec![
+                ResourceRef::new(Some("other"), "other"),
+                ResourceRef::new(Some("shop"), "private"),
+            ]
+        );
+    }
+
+    #[test]
+    fn wildcard_hosts_intersect() {
+        assert!(hosts_intersect("*.example.com", "www.example.com"));
+        assert!(hosts_intersect("*.example.com", "*.shop.example.com"));
+        assert!(hosts_intersect("*", "anything"));
+        assert!(!hosts_intersect("*.example.com", "example.org"));
+        assert!(!hosts_intersect("a.example.com", "b.example.com"));
+    }
+}
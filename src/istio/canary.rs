@@ -0,0 +1,709 @@
+// Progressive delivery of a service modelled as a state machine. A rollout walks through a list of
+// steps (traffic weights, pauses and analysis hooks) and every transition yields the mutations
+// that have to be applied to the VirtualService and DestinationRule of the service, so a controller
+// only has to persist the rollout state, feed events into it and patch the resources it is given.
+use crate::istio::destination_rule::{DestinationRule, DestinationRuleSpec, Subset};
+use crate::istio::duration::IstioDuration;
+use crate::istio::virtual_service::{
+    Destination, HttpRouteDestination, RouteDestination, VirtualService,
+};
+use crate::istio::weight::Weight;
+use crate::istio::Map;
+use std::fmt;
+
+/// # Step
+/// A single step of a canary rollout.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Step {
+    /// Send the given percentage (0-100) of the traffic to the canary subset.
+    #[serde(rename = "setWeight")]
+    SetWeight(u32),
+
+    /// Hold the current weights. With a duration the controller is expected to resume the rollout
+    /// once it has elapsed, without one the rollout waits for an explicit `Event::Resume`.
+    #[serde(rename = "pause")]
+    Pause(Option<IstioDuration>),
+
+    /// Run the named analysis hook and wait for its outcome.
+    #[serde(rename = "analysis")]
+    Analysis(String),
+}
+
+/// # CanaryStrategy
+/// The service being rolled out and the steps of the rollout.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CanaryStrategy {
+    /// Host of the service, as used by the route destinations and the DestinationRule.
+    pub host: String,
+
+    /// Subset currently receiving the traffic.
+    #[serde(rename = "stableSubset")]
+    pub stable_subset: String,
+
+    /// Labels selecting the stable workload.
+    #[serde(rename = "stableLabels")]
+    pub stable_labels: Map<String, String>,
+
+    /// Subset receiving the shifted traffic.
+    #[serde(rename = "canarySubset")]
+    pub canary_subset: String,
+
+    /// Labels selecting the canary workload.
+    #[serde(rename = "canaryLabels")]
+    pub canary_labels: Map<String, String>,
+
+    /// Steps executed in order once the rollout is started. The canary is promoted after the last
+    /// step.
+    pub steps: Vec<Step>,
+}
+
+/// # Phase
+/// Where a rollout currently is.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Phase {
+    /// Not started yet.
+    Pending,
+
+    /// Waiting at a pause step.
+    Paused {
+        step: usize,
+        duration: Option<IstioDuration>,
+    },
+
+    /// Waiting for the outcome of an analysis step.
+    Analyzing { step: usize, hook: String },
+
+    /// All traffic is served by the canary, which became the new stable workload.
+    Promoted,
+
+    /// The rollout was aborted and all traffic was returned to the stable workload.
+    Aborted,
+}
+
+impl Phase {
+    /// Whether the rollout has finished, successfully or not.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Phase::Promoted | Phase::Aborted)
+    }
+}
+
+/// # Event
+/// Input driving a rollout from one phase to the next.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Event {
+    /// Start a pending rollout.
+    Start,
+
+    /// Continue after a pause step.
+    Resume,
+
+    /// The running analysis hook succeeded.
+    AnalysisPassed,
+
+    /// The running analysis hook failed, aborting the rollout.
+    AnalysisFailed,
+
+    /// Skip the remaining steps and promote the canary.
+    Promote,
+
+    /// Return all traffic to the stable workload.
+    Abort,
+}
+
+/// # Mutation
+/// A change to the VirtualService or DestinationRule of the rolled out service.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Mutation {
+    /// DestinationRule: declare the subset with the given labels, replacing the labels of an
+    /// existing subset with the same name.
+    UpsertSubset {
+        host: String,
+        name: String,
+        labels: Map<String, String>,
+    },
+
+    /// VirtualService: split the traffic to `host` between the two subsets. Routes that do not
+    /// target the host are left untouched.
+    SetWeights {
+        host: String,
+        #[serde(rename = "stableSubset")]
+        stable_subset: String,
+        #[serde(rename = "stableWeight")]
+        stable_weight: Weight,
+        #[serde(rename = "canarySubset")]
+        canary_subset: String,
+        #[serde(rename = "canaryWeight")]
+        canary_weight: Weight,
+    },
+}
+
+impl Mutation {
+    /// Applies the mutation to a VirtualService, returning whether it was changed.
+    pub fn apply_to_virtual_service(&self, vs: &mut VirtualService) -> bool {
+        let (host, stable_subset, stable_weight, canary_subset, canary_weight) = match self {
+            Mutation::SetWeights {
+                host,
+                stable_subset,
+                stable_weight,
+                canary_subset,
+                canary_weight,
+            } => (
+                host,
+                stable_subset,
+                *stable_weight,
+                canary_subset,
+                *canary_weight,
+            ),
+            Mutation::UpsertSubset { .. } => return false,
+        };
+        let spec = match vs.spec.as_mut() {
+            Some(spec) => spec,
+            None => return false,
+        };
+
+        let mut changed = false;
+        for routes in spec
+            .http
+            .iter_mut()
+            .flatten()
+            .filter_map(|http| http.route.as_mut())
+        {
+            let template = match routes.iter().find(|route| &route.destination.host == host) {
+                Some(route) => route.clone(),
+                None => continue,
+            };
+            let split = vec![
+                HttpRouteDestination {
+                    destination: with_subset(&template.destination, stable_subset),
+                    weight: Some(stable_weight),
+                    headers: template.headers.clone(),
+                    extra: template.extra.clone(),
+                },
+                HttpRouteDestination {
+                    destination: with_subset(&template.destination, canary_subset),
+                    weight: Some(canary_weight),
+                    headers: template.headers,
+                    extra: template.extra,
+                },
+            ];
+            changed |= replace_host_destinations(routes, host, split, |route| &route.destination);
+        }
+        let l4_routes = spec
+            .tls
+            .iter_mut()
+            .flatten()
+            .filter_map(|tls| tls.route.as_mut())
+            .chain(
+                spec.tcp
+                    .iter_mut()
+                    .flatten()
+                    .filter_map(|tcp| tcp.route.as_mut()),
+            );
+        for routes in l4_routes {
+            let template = match routes.iter().find(|route| &route.destination.host == host) {
+                Some(route) => route.destination.clone(),
+                None => continue,
+            };
+            let split = vec![
+                RouteDestination {
+                    destination: with_subset(&template, stable_subset),
+                    weight: Some(stable_weight),
+                },
+                RouteDestination {
+                    destination: with_subset(&template, canary_subset),
+                    weight: Some(canary_weight),
+                },
+            ];
+            changed |= replace_host_destinations(routes, host, split, |route| &route.destination);
+        }
+        changed
+    }
+
+    /// Applies the mutation to a DestinationRule, returning whether it was changed.
+    pub fn apply_to_destination_rule(&self, rule: &mut DestinationRule) -> bool {
+        let (host, name, labels) = match self {
+            Mutation::UpsertSubset { host, name, labels } => (host, name, labels),
+            Mutation::SetWeights { .. } => return false,
+        };
+        let spec = rule.spec.get_or_insert_with(|| DestinationRuleSpec {
+            host: host.clone(),
+            traffic_policy: None,
+            subsets: None,
+            export_to: None,
+            workload_selector: None,
+            extra: Map::new(),
+        });
+        if &spec.host != host {
+            return false;
+        }
+        let subsets = spec.subsets.get_or_insert_with(Vec::new);
+        match subsets.iter_mut().find(|subset| &subset.name == name) {
+            Some(subset) if subset.labels.as_ref() == Some(labels) => false,
+            Some(subset) => {
+                subset.labels = Some(labels.clone());
+                true
+            }
+            None => {
+                subsets.push(Subset {
+                    name: name.clone(),
+                    labels: Some(labels.clone()),
+                    traffic_policy: None,
+                    extra: Map::new(),
+                });
+                true
+            }
+        }
+    }
+}
+
+/// # Transition
+/// The result of feeding an event into a rollout.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Transition {
+    pub from: Phase,
+    pub to: Phase,
+    pub mutations: Vec<Mutation>,
+}
+
+/// # CanaryError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CanaryError {
+    /// A `SetWeight` step outside of 0-100.
+    InvalidWeight { step: usize, weight: u32 },
+
+    /// A stored rollout sending more than 100% of the traffic to the canary.
+    InvalidCanaryWeight(u32),
+
+    /// The event is not accepted in the current phase.
+    InvalidTransition { phase: Phase, event: Event },
+}
+
+impl fmt::Display for CanaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanaryError::InvalidWeight { step, weight } => {
+                write!(f, "step {} sets weight {}, expected 0-100", step, weight)
+            }
+            CanaryError::InvalidCanaryWeight(weight) => {
+                write!(f, "canary weight {}, expected 0-100", weight)
+            }
+            CanaryError::InvalidTransition { phase, event } => {
+                write!(f, "event {:?} is not valid in phase {:?}", event, phase)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanaryError {}
+
+/// # Rollout
+/// A canary rollout and its current phase. A stored rollout is checked like `Rollout::new` when it
+/// is read back.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "RawRollout")]
+pub struct Rollout {
+    strategy: CanaryStrategy,
+    phase: Phase,
+    #[serde(rename = "canaryWeight")]
+    canary_weight: u32,
+}
+
+#[derive(Deserialize)]
+struct RawRollout {
+    strategy: CanaryStrategy,
+    phase: Phase,
+    #[serde(rename = "canaryWeight")]
+    canary_weight: u32,
+}
+
+impl TryFrom<RawRollout> for Rollout {
+    type Error = CanaryError;
+
+    fn try_from(raw: RawRollout) -> Result<Self, Self::Error> {
+        let rollout = Rollout::new(raw.strategy)?;
+        if Weight::new(raw.canary_weight).is_err() {
+            return Err(CanaryError::InvalidCanaryWeight(raw.canary_weight));
+        }
+        Ok(Rollout {
+            phase: raw.phase,
+            canary_weight: raw.canary_weight,
+            ..rollout
+        })
+    }
+}
+
+impl Rollout {
+    pub fn new(strategy: CanaryStrategy) -> Result<Self, CanaryError> {
+        for (step, item) in strategy.steps.iter().enumerate() {
+            if let Step::SetWeight(weight) = item {
+                if Weight::new(*weight).is_err() {
+                    return Err(CanaryError::InvalidWeight {
+                        step,
+                        weight: *weight,
+                    });
+                }
+            }
+        }
+        Ok(Rollout {
+            strategy,
+            phase: Phase::Pending,
+            canary_weight: 0,
+        })
+    }
+
+    pub fn strategy(&self) -> &CanaryStrategy {
+        &self.strategy
+    }
+
+    pub fn phase(&self) -> &Phase {
+        &self.phase
+    }
+
+    /// Percentage of the traffic currently sent to the canary subset.
+    pub fn canary_weight(&self) -> u32 {
+        self.canary_weight
+    }
+
+    /// Feeds an event into the rollout. On success the rollout moves to the new phase and the
+    /// returned transition lists the mutations to apply, in order.
+    pub fn handle(&mut self, event: Event) -> Result<Transition, CanaryError> {
+        let from = self.phase.clone();
+        let mut mutations = Vec::new();
+        match (&self.phase, &event) {
+            (Phase::Pending, Event::Start) => {
+                mutations.push(
+                    self.upsert_subset(&self.strategy.stable_subset, &self.strategy.stable_labels),
+                );
+                mutations.push(
+                    self.upsert_subset(&self.strategy.canary_subset, &self.strategy.canary_labels),
+                );
+                mutations.push(self.set_weight(0));
+                self.run_from(0, &mut mutations);
+            }
+            (Phase::Paused { step, .. }, Event::Resume)
+            | (Phase::Analyzing { step, .. }, Event::AnalysisPassed) => {
+                self.run_from(step + 1, &mut mutations);
+            }
+            (Phase::Analyzing { .. }, Event::AnalysisFailed) => self.abort(&mut mutations),
+            (phase, Event::Promote) if !phase.is_terminal() && phase != &Phase::Pending => {
+                self.promote(&mut mutations)
+            }
+            (phase, Event::Abort) if !phase.is_terminal() => self.abort(&mut mutations),
+            (phase, _) => {
+                return Err(CanaryError::InvalidTransition {
+                    phase: phase.clone(),
+                    event,
+                })
+            }
+        }
+        Ok(Transition {
+            from,
+            to: self.phase.clone(),
+            mutations,
+        })
+    }
+
+    /// Executes the steps starting at `first` until a step blocks the rollout, promoting the
+    /// canary when the steps are exhausted.
+    fn run_from(&mut self, first: usize, mutations: &mut Vec<Mutation>) {
+        for (index, step) in self.strategy.steps.iter().enumerate().skip(first) {
+            match step {
+                Step::SetWeight(weight) => {
+                    if *weight != self.canary_weight {
+                        self.canary_weight = *weight;
+                        mutations.push(self.set_weight(*weight));
+                    }
+                }
+                Step::Pause(duration) => {
+                    self.phase = Phase::Paused {
+                        step: index,
+                        duration: *duration,
+                    };
+                    return;
+                }
+                Step::Analysis(hook) => {
+                    self.phase = Phase::Analyzing {
+                        step: index,
+                        hook: hook.clone(),
+                    };
+                    return;
+                }
+            }
+        }
+        self.promote(mutations);
+    }
+
+    fn promote(&mut self, mutations: &mut Vec<Mutation>) {
+        // The stable subset takes over the canary labels, after which all traffic can be routed
+        // back to it without switching workloads.
+        mutations.push(self.set_weight(100));
+        mutations
+            .push(self.upsert_subset(&self.strategy.stable_subset, &self.strategy.canary_labels));
+        mutations.push(self.set_weight(0));
+        self.canary_weight = 0;
+        self.phase = Phase::Promoted;
+    }
+
+    fn abort(&mut self, mutations: &mut Vec<Mutation>) {
+        mutations.push(self.set_weight(0));
+        self.canary_weight = 0;
+        self.phase = Phase::Aborted;
+    }
+
+    fn set_weight(&self, canary_weight: u32) -> Mutation {
+        let canary_weight = Weight::new(canary_weight).expect("validated by Rollout::new");
+        Mutation::SetWeights {
+            host: self.strategy.host.clone(),
+            stable_subset: self.strategy.stable_subset.clone(),
+            stable_weight: canary_weight.complement(),
+            canary_subset: self.strategy.canary_subset.clone(),
+            canary_weight,
+        }
+    }
+
+    fn upsert_subset(&self, name: &str, labels: &Map<String, String>) -> Mutation {
+        Mutation::UpsertSubset {
+            host: self.strategy.host.clone(),
+            name: name.to_string(),
+            labels: labels.clone(),
+        }
+    }
+}
+
+fn with_subset(destination: &Destination, subset: &str) -> Destination {
+    Destination {
+        subset: Some(subset.to_string()),
+        ..destination.clone()
+    }
+}
+
+/// Replaces the destinations of `routes` targeting `host` with `split`, at the position of the
+/// first one.
+fn replace_host_destinations<T: Clone + PartialEq>(
+    routes: &mut Vec<T>,
+    host: &str,
+    split: Vec<T>,
+    destination: impl Fn(&T) -> &Destination,
+) -> bool {
+    let position = match routes
+        .iter()
+        .position(|route| destination(route).host == host)
+    {
+        Some(position) => position,
+        None => return false,
+    };
+    let mut updated = Vec::with_capacity(routes.len() + 1);
+    let mut split = Some(split);
+    for (index, route) in routes.iter().enumerate() {
+        if index == position {
+            updated.extend(split.take().into_iter().flatten());
+        }
+        if destination(route).host != host {
+            updated.push(route.clone());
+        }
+    }
+    if &updated == routes {
+        return false;
+    }
+    *routes = updated;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    fn strategy(steps: Vec<Step>) -> CanaryStrategy {
+        CanaryStrategy {
+            host: "reviews".to_string(),
+            stable_subset: "stable".to_string(),
+            stable_labels: Map::from([("version".to_string(), "v1".to_string())]),
+            canary_subset: "canary".to_string(),
+            canary_labels: Map::from([("version".to_string(), "v2".to_string())]),
+            steps,
+        }
+    }
+
+    fn weights(mutation: &Mutation) -> (u32, u32) {
+        match mutation {
+            Mutation::SetWeights {
+                stable_weight,
+                canary_weight,
+                ..
+            } => (stable_weight.get(), canary_weight.get()),
+            other => panic!("expected SetWeights, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn walks_the_steps_and_promotes_the_canary() {
+        let mut rollout = Rollout::new(strategy(vec![
+            Step::SetWeight(10),
+            Step::Pause(None),
+            Step::SetWeight(50),
+            Step::Analysis("success-rate".to_string()),
+        ]))
+        .unwrap();
+
+        let start = rollout.handle(Event::Start).unwrap();
+        assert_eq!(start.from, Phase::Pending);
+        assert_eq!(
+            start.to,
+            Phase::Paused {
+                step: 1,
+                duration: None
+            }
+        );
+        assert!(
+            matches!(&start.mutations[0], Mutation::UpsertSubset { name, .. } if name == "stable")
+        );
+        assert!(
+            matches!(&start.mutations[1], Mutation::UpsertSubset { name, .. } if name == "canary")
+        );
+        assert_eq!(weights(&start.mutations[2]), (100, 0));
+        assert_eq!(weights(&start.mutations[3]), (90, 10));
+        assert_eq!(rollout.canary_weight(), 10);
+
+        let resume = rollout.handle(Event::Resume).unwrap();
+        assert_eq!(
+            resume.to,
+            Phase::Analyzing {
+                step: 3,
+                hook: "success-rate".to_string()
+            }
+        );
+        assert_eq!(resume.mutations.len(), 1);
+        assert_eq!(weights(&resume.mutations[0]), (50, 50));
+
+        let passed = rollout.handle(Event::AnalysisPassed).unwrap();
+        assert_eq!(passed.to, Phase::Promoted);
+        assert_eq!(weights(&passed.mutations[0]), (0, 100));
+        assert_eq!(
+            passed.mutations[1],
+            Mutation::UpsertSubset {
+                host: "reviews".to_string(),
+                name: "stable".to_string(),
+                labels: Map::from([("version".to_string(), "v2".to_string())]),
+            }
+        );
+        assert_eq!(weights(&passed.mutations[2]), (100, 0));
+        assert_eq!(rollout.canary_weight(), 0);
+    }
+
+    #[test]
+    fn a_failed_analysis_aborts_the_rollout() {
+        let mut rollout = Rollout::new(strategy(vec![
+            Step::SetWeight(20),
+            Step::Analysis("latency".to_string()),
+        ]))
+        .unwrap();
+        rollout.handle(Event::Start).unwrap();
+        let failed = rollout.handle(Event::AnalysisFailed).unwrap();
+        assert_eq!(failed.to, Phase::Aborted);
+        assert_eq!(failed.mutations.len(), 1);
+        assert_eq!(weights(&failed.mutations[0]), (100, 0));
+        assert_eq!(
+            rollout.handle(Event::Resume),
+            Err(CanaryError::InvalidTransition {
+                phase: Phase::Aborted,
+                event: Event::Resume
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_weights_above_100_and_promoting_a_pending_rollout() {
+        assert_eq!(
+            Rollout::new(strategy(vec![Step::SetWeight(10), Step::SetWeight(120)])),
+            Err(CanaryError::InvalidWeight {
+                step: 1,
+                weight: 120
+            })
+        );
+        let mut rollout = Rollout::new(strategy(vec![Step::Pause(None)])).unwrap();
+        assert!(rollout.handle(Event::Promote).is_err());
+        assert_eq!(rollout.phase(), &Phase::Pending);
+    }
+
+    #[test]
+    fn mutations_split_the_routes_of_the_host() {
+        let mut vs: VirtualService = serde_json::from_value(json!({
+            "metadata": { "name": "reviews" },
+            "spec": {
+                "hosts": ["reviews"],
+                "http": [{
+                    "route": [
+                        { "destination": { "host": "reviews", "port": { "number": 9080 } } },
+                        { "destination": { "host": "ratings" } },
+                    ],
+                }],
+            },
+        }))
+        .unwrap();
+        let mutation = Mutation::SetWeights {
+            host: "reviews".to_string(),
+            stable_subset: "stable".to_string(),
+            stable_weight: Weight::new(75).unwrap(),
+            canary_subset: "canary".to_string(),
+            canary_weight: Weight::new(25).unwrap(),
+        };
+        assert!(mutation.apply_to_virtual_service(&mut vs));
+        assert_eq!(
+            serde_json::to_value(&vs.spec.as_ref().unwrap().http).unwrap(),
+            json!([{
+                "route": [
+                    { "destination": { "host": "reviews", "subset": "stable", "port": { "number": 9080 } }, "weight": 75 },
+                    { "destination": { "host": "reviews", "subset": "canary", "port": { "number": 9080 } }, "weight": 25 },
+                    { "destination": { "host": "ratings" } },
+                ],
+            }])
+        );
+        // Applying the same split again changes nothing.
+        assert!(!mutation.apply_to_virtual_service(&mut vs));
+
+        let mut rule = DestinationRule::default();
+        let upsert = Mutation::UpsertSubset {
+            host: "reviews".to_string(),
+            name: "canary".to_string(),
+            labels: Map::from([("version".to_string(), "v2".to_string())]),
+        };
+        assert!(upsert.apply_to_destination_rule(&mut rule));
+        assert!(!upsert.apply_to_destination_rule(&mut rule));
+        let spec = rule.spec.unwrap();
+        assert_eq!(spec.host, "reviews");
+        assert_eq!(spec.subsets.unwrap()[0].name, "canary");
+    }
+
+    #[test]
+    fn a_stored_rollout_is_checked_when_read_back() {
+        let mut rollout = Rollout::new(strategy(vec![
+            Step::SetWeight(20),
+            Step::Pause(Some(IstioDuration::from_secs(300))),
+        ]))
+        .unwrap();
+        rollout.handle(Event::Start).unwrap();
+        let mut stored = serde_json::to_value(&rollout).unwrap();
+        assert_eq!(stored["strategy"]["steps"][1], json!({ "pause": "5m" }));
+        assert_eq!(
+            serde_json::from_value::<Rollout>(stored.clone()).unwrap(),
+            rollout
+        );
+
+        stored["strategy"]["steps"][0] = json!({ "setWeight": 120 });
+        let error = serde_json::from_value::<Rollout>(stored.clone()).unwrap_err();
+        assert_eq!(error.to_string(), "step 0 sets weight 120, expected 0-100");
+
+        stored["strategy"]["steps"][0] = json!({ "setWeight": 20 });
+        stored["canaryWeight"] = json!(101);
+        let error = serde_json::from_value::<Rollout>(stored).unwrap_err();
+        assert_eq!(error.to_string(), "canary weight 101, expected 0-100");
+    }
+}
@@ -1,9 +1,13 @@
 /// # TLSMode
 /// TLS connection mode
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "ClientTLSmode"))]
+#[allow(non_camel_case_types)]
 pub enum TLSmode {
     // Do not setup a TLS connection to the upstream endpoint.
+    #[default]
     DISABLE,
 
     // Originate a TLS connection to the upstream endpoint.
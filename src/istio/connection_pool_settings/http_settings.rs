@@ -1,7 +1,9 @@
 /// # H2UpgradePolicy
 /// Policy for upgrading http1.1 connections to http2.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum H2UpgradePolicy {
     // Use the global default.
     DEFAULT,
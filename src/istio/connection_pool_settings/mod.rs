@@ -1,4 +1,4 @@
-use std::time::Duration;
+use crate::istio::duration::IstioDuration;
 
 pub mod http_settings;
 pub mod tcp_settings;
@@ -6,7 +6,8 @@ pub mod tcp_settings;
 /// # TCPSettings
 /// Settings common to both HTTP and TCP upstream connections.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TCPSettings {
     // Maximum number of HTTP1 /TCP connections to a destination host. Default 2^32-1.
     // Required: No
@@ -16,18 +17,29 @@ pub struct TCPSettings {
     // TCP connection timeout. format: 1h/1m/1s/1ms. MUST BE >=1ms. Default is 10s.
     // Required: No
     #[serde(rename = "connectTimeout")]
-    pub connect_timeout: Option<Duration>,
+    pub connect_timeout: Option<IstioDuration>,
 
     // If set then set SO_KEEPALIVE on the socket to enable TCP Keepalives.
     // Required: No
     #[serde(rename = "tcpKeepalive")]
     pub tcp_keepalive: Option<tcp_settings::TcpKeepalive>,
+
+    // The maximum duration of a connection. The duration is defined as the period since a connection was established. If not set, there is no max duration. When maxConnectionDuration is reached the connection will be closed. Duration must be at least 1ms.
+    // Required: No
+    #[serde(rename = "maxConnectionDuration")]
+    pub max_connection_duration: Option<IstioDuration>,
+
+    // The idle timeout for TCP connections. The idle timeout is defined as the period in which there are no bytes sent or received on either the upstream or downstream connection. If not set, the default idle timeout is 1 hour. If set to 0s, the timeout will be disabled.
+    // Required: No
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<IstioDuration>,
 }
 
 /// #HTTPSettings
 /// Settings applicable to HTTP1.1/HTTP2/GRPC connections.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HTTPSettings {
     // Maximum number of pending HTTP requests to a destination. Default 2^32-1.
     // No
@@ -52,7 +64,7 @@ pub struct HTTPSettings {
     // The idle timeout for upstream connection pool connections. The idle timeout is defined as the period in which there are no active requests. If not set, the default is 1 hour. When the idle timeout is reached, the connection will be closed. If the connection is an HTTP/2 connection a drain sequence will occur prior to closing the connection. Note that request based timeouts mean that HTTP/2 PINGs will not keep the connection alive. Applies to both HTTP1.1 and HTTP2 connections.
     // No
     #[serde(rename = "idleTimeout")]
-    pub idle_timeout: Option<Duration>,
+    pub idle_timeout: Option<IstioDuration>,
 
     // Specify if http1.1 connection should be upgraded to http2 for the associated destination.
     // No
@@ -63,4 +75,9 @@ pub struct HTTPSettings {
     // No
     #[serde(rename = "useClientProtocol")]
     pub use_client_protocol: Option<bool>,
+
+    // The maximum number of concurrent streams allowed for a peer on one HTTP/2 connection. Defaults to 2^31-1.
+    // No
+    #[serde(rename = "maxConcurrentStreams")]
+    pub max_concurrent_streams: Option<i32>,
 }
@@ -1,9 +1,10 @@
-use std::time::Duration;
+use crate::istio::duration::IstioDuration;
 
 /// # TcpKeepalive
 /// TCP keepalive.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TcpKeepalive {
 
     // Maximum number of keepalive probes to send without response before deciding the connection is dead. Default is to use the OS level configuration (unless overridden, Linux defaults to 9.)
@@ -12,9 +13,9 @@ pub struct TcpKeepalive {
 
     // The time duration a connection needs to be idle before keep-alive probes start being sent. Default is to use the OS level configuration (unless overridden, Linux defaults to 7200s (ie 2 hours.)
     // No
-    pub time: Option<Duration>,
+    pub time: Option<IstioDuration>,
 
     // The time duration between keep-alive probes. Default is to use the OS level configuration (unless overridden, Linux defaults to 75s.)
     // No
-    pub interval: Option<Duration>,
+    pub interval: Option<IstioDuration>,
 }
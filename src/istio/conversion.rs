@@ -0,0 +1,550 @@
+// Conversion of resources between the API versions Istio serves them in. The versions of a kind
+// share one schema, so converting rewrites the `apiVersion` and round-trips the object through the
+// typed model of this crate. Fields the model does not know are dropped by the round trip and
+// reported, so a conversion never loses configuration silently.
+use crate::istio::authorization_policy::AuthorizationPolicy;
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::diff::{diff_values, ChangeKind};
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::gateway::Gateway;
+use crate::istio::peer_authentication::PeerAuthentication;
+use crate::istio::proxy_config::ProxyConfig;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::serialization::{self, DeserializeError};
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::sidecar::Sidecar;
+use crate::istio::virtual_service::VirtualService;
+use crate::istio::workload_entry::WorkloadEntry;
+use crate::istio::workload_group::WorkloadGroup;
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::Resource;
+use serde::de::DeserializeOwned;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// # ApiVersion
+/// Version of an Istio API group.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ApiVersion {
+    #[serde(rename = "v1alpha3")]
+    V1alpha3,
+    #[serde(rename = "v1beta1")]
+    V1beta1,
+    #[serde(rename = "v1")]
+    V1,
+}
+
+impl ApiVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1alpha3 => "v1alpha3",
+            ApiVersion::V1beta1 => "v1beta1",
+            ApiVersion::V1 => "v1",
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1alpha3" => Ok(ApiVersion::V1alpha3),
+            "v1beta1" => Ok(ApiVersion::V1beta1),
+            "v1" => Ok(ApiVersion::V1),
+            _ => Err(ConversionError::UnknownVersion(s.to_string())),
+        }
+    }
+}
+
+/// The versions `kind` of `group` is served in, oldest first. Empty for kinds this crate does not
+/// model.
+pub fn versions(group: &str, kind: &str) -> &'static [ApiVersion] {
+    const ALL: &[ApiVersion] = &[ApiVersion::V1alpha3, ApiVersion::V1beta1, ApiVersion::V1];
+    match (group, kind) {
+        (
+            "networking.istio.io",
+            "VirtualService" | "DestinationRule" | "Gateway" | "ServiceEntry" | "Sidecar"
+            | "WorkloadEntry" | "WorkloadGroup",
+        ) => ALL,
+        ("networking.istio.io", "EnvoyFilter") => &ALL[..1],
+        ("networking.istio.io", "ProxyConfig") => &ALL[1..2],
+        (
+            "security.istio.io",
+            "RequestAuthentication" | "AuthorizationPolicy" | "PeerAuthentication",
+        ) => &ALL[1..],
+        _ => &[],
+    }
+}
+
+/// # Conversion
+/// A resource converted to another API version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversion {
+    /// The converted resource, including `apiVersion` and `kind`.
+    pub value: Value,
+
+    /// Paths of the fields of the source that are not part of the converted resource, e.g.
+    /// `spec.http[0].mirrorPercent`.
+    pub dropped: Vec<String>,
+}
+
+/// # ConversionError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The resource has no `apiVersion` or `kind`.
+    MissingType,
+    UnknownVersion(String),
+    UnknownKind {
+        api_version: String,
+        kind: String,
+    },
+
+    /// The kind is not served in the target version.
+    UnsupportedVersion {
+        kind: String,
+        version: ApiVersion,
+    },
+
+    /// The resource does not match the schema of its kind.
+    Invalid(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::MissingType => write!(f, "resource has no apiVersion or kind"),
+            ConversionError::UnknownVersion(version) => {
+                write!(f, "unknown API version {}", version)
+            }
+            ConversionError::UnknownKind { api_version, kind } => {
+                write!(f, "{} of {} cannot be converted", kind, api_version)
+            }
+            ConversionError::UnsupportedVersion { kind, version } => {
+                write!(f, "{} is not served in {}", kind, version)
+            }
+            ConversionError::Invalid(message) => write!(f, "invalid resource: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Converts a resource, given as a JSON value with `apiVersion` and `kind`, to `target`.
+pub fn convert(value: &Value, target: ApiVersion) -> Result<Conversion, ConversionError> {
+    let api_version = value.get("apiVersion").and_then(Value::as_str);
+    let kind = value.get("kind").and_then(Value::as_str);
+    let (api_version, kind) = api_version.zip(kind).ok_or(ConversionError::MissingType)?;
+    let unknown = || ConversionError::UnknownKind {
+        api_version: api_version.to_string(),
+        kind: kind.to_string(),
+    };
+    let (group, version) = api_version.split_once('/').ok_or_else(unknown)?;
+    let supported = versions(group, kind);
+    if supported.is_empty() {
+        return Err(unknown());
+    }
+    version.parse::<ApiVersion>()?;
+    if !supported.contains(&target) {
+        return Err(ConversionError::UnsupportedVersion {
+            kind: kind.to_string(),
+            version: target,
+        });
+    }
+
+    let mut source = value.clone();
+    if let Some(object) = source.as_object_mut() {
+        object.remove("apiVersion");
+        object.remove("kind");
+    }
+    let mut converted = match kind {
+        "VirtualService" => round_trip::<VirtualService>(&source),
+        "DestinationRule" => round_trip::<DestinationRule>(&source),
+        "Gateway" => round_trip::<Gateway>(&source),
+        "ServiceEntry" => round_trip::<ServiceEntry>(&source),
+        "Sidecar" => round_trip::<Sidecar>(&source),
+        "WorkloadEntry" => round_trip::<WorkloadEntry>(&source),
+        "WorkloadGroup" => round_trip::<WorkloadGroup>(&source),
+        "EnvoyFilter" => round_trip::<EnvoyFilter>(&source),
+        "ProxyConfig" => round_trip::<ProxyConfig>(&source),
+        "RequestAuthentication" => round_trip::<RequestAuthentication>(&source),
+        "AuthorizationPolicy" => round_trip::<AuthorizationPolicy>(&source),
+        "PeerAuthentication" => round_trip::<PeerAuthentication>(&source),
+        _ => return Err(unknown()),
+    }?;
+    let dropped = diff_values(&source, &converted)
+        .into_iter()
+        .filter(|change| change.kind == ChangeKind::Removed)
+        .map(|change| change.path)
+        .collect();
+
+    let mut resource = serde_json::Map::new();
+    resource.insert(
+        "apiVersion".to_string(),
+        Value::String(format!("{}/{}", group, target)),
+    );
+    resource.insert("kind".to_string(), Value::String(kind.to_string()));
+    if let Some(object) = converted.as_object_mut() {
+        resource.append(object);
+    }
+    Ok(Conversion {
+        value: Value::Object(resource),
+        dropped,
+    })
+}
+
+/// # AnyVersion
+/// A resource read from a manifest of any version its kind is served in, e.g. a `v1alpha3`
+/// VirtualService exported from an older cluster. The resource is read with the model of this crate
+/// and keeps the version the manifest declared, which it is serialized with again.
+#[derive(Clone, Debug)]
+pub struct AnyVersion<T> {
+    pub api_version: ApiVersion,
+    pub resource: T,
+}
+
+impl<T: Resource + DeserializeOwned> AnyVersion<T> {
+    /// Reads the resource from a JSON value with `apiVersion`, accepting every version of `T`'s group
+    /// its kind is served in. The `kind` is checked when present.
+    pub fn from_json_value(value: Value) -> Result<Self, DeserializeError> {
+        let error = |message: String| DeserializeError {
+            kind: Some(T::KIND.to_string()),
+            name: value
+                .pointer("/metadata/name")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            path: None,
+            message,
+        };
+        if let Some(kind) = value.get("kind").and_then(Value::as_str) {
+            if kind != T::KIND {
+                return Err(error(format!("expected kind {}, found {}", T::KIND, kind)));
+            }
+        }
+        let api_version = value
+            .get("apiVersion")
+            .and_then(Value::as_str)
+            .ok_or_else(|| error("missing apiVersion".to_string()))?;
+        let api_version = match api_version.split_once('/') {
+            Some((group, version)) if group == T::GROUP => version
+                .parse::<ApiVersion>()
+                .ok()
+                .filter(|version| versions(T::GROUP, T::KIND).contains(version)),
+            _ => None,
+        }
+        .ok_or_else(|| error(format!("{} is not served in {}", T::KIND, api_version)))?;
+        Ok(AnyVersion {
+            api_version,
+            resource: serialization::deserialize(value)?,
+        })
+    }
+}
+
+impl<T> AnyVersion<T> {
+    pub fn into_inner(self) -> T {
+        self.resource
+    }
+}
+
+impl<'de, T: Resource + DeserializeOwned> Deserialize<'de> for AnyVersion<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        AnyVersion::from_json_value(value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T: Resource + Serialize> Serialize for AnyVersion<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let resource = serde_json::to_value(&self.resource).map_err(serde::ser::Error::custom)?;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("apiVersion", &format!("{}/{}", T::GROUP, self.api_version))?;
+        map.serialize_entry("kind", T::KIND)?;
+        if let Value::Object(fields) = resource {
+            for (key, value) in fields {
+                map.serialize_entry(&key, &value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+fn round_trip<T: DeserializeOwned + Serialize>(value: &Value) -> Result<Value, ConversionError> {
+    let typed: T = serde_json::from_value(value.clone())
+        .map_err(|error| ConversionError::Invalid(error.to_string()))?;
+    serde_json::to_value(&typed).map_err(|error| ConversionError::Invalid(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::json;
+
+    fn virtual_service(api_version: &str) -> Value {
+        json!({
+            "apiVersion": api_version,
+            "kind": "VirtualService",
+            "metadata": { "name": "reviews", "namespace": "shop", "labels": { "app": "reviews" } },
+            "spec": {
+                "hosts": ["reviews"],
+                "http": [{
+                    "name": "default",
+                    "match": [{ "uri": { "prefix": "/api" }, "futureMatchField": "kept" }],
+                    "route": [
+                        { "destination": { "host": "reviews", "subset": "v1" }, "weight": 90 },
+                        { "destination": { "host": "reviews", "subset": "v2" }, "weight": 10 },
+                    ],
+                    "timeout": "5s",
+                    "futureRouteField": { "enabled": true },
+                }],
+                "futureSpecField": ["a", "b"],
+            },
+        })
+    }
+
+    fn without_api_version(mut value: Value) -> Value {
+        value.as_object_mut().unwrap().remove("apiVersion");
+        value
+    }
+
+    fn without_kind(mut value: Value) -> Value {
+        value.as_object_mut().unwrap().remove("kind");
+        value
+    }
+
+    #[test]
+    fn converts_between_versions_without_loss() {
+        let source = virtual_service("networking.istio.io/v1alpha3");
+        let mut current = source.clone();
+        for (target, api_version) in [
+            (ApiVersion::V1beta1, "networking.istio.io/v1beta1"),
+            (ApiVersion::V1, "networking.istio.io/v1"),
+            (ApiVersion::V1alpha3, "networking.istio.io/v1alpha3"),
+        ] {
+            let conversion = convert(&current, target).unwrap();
+            assert_eq!(conversion.value["apiVersion"], json!(api_version));
+            assert_eq!(conversion.value["kind"], json!("VirtualService"));
+            // Fields the model does not know are kept in `extra`.
+            assert_eq!(conversion.dropped, Vec::<String>::new(), "{}", target);
+            assert_eq!(
+                without_api_version(conversion.value.clone()),
+                without_api_version(source.clone()),
+                "{}",
+                target
+            );
+            current = conversion.value;
+        }
+        assert_eq!(current, source);
+    }
+
+    #[test]
+    fn keeps_the_extra_fields_of_destination_rules() {
+        let source = json!({
+            "apiVersion": "networking.istio.io/v1beta1",
+            "kind": "DestinationRule",
+            "metadata": { "name": "reviews" },
+            "spec": {
+                "host": "reviews",
+                "trafficPolicy": {
+                    "loadBalancer": { "simple": "LEAST_REQUEST" },
+                    "futurePolicy": { "mode": "FAST" },
+                },
+                "subsets": [{ "name": "v1", "labels": { "version": "v1" } }],
+                "futureSpecField": 1,
+            },
+        });
+        let conversion = convert(&source, ApiVersion::V1).unwrap();
+        assert!(conversion.dropped.is_empty());
+        assert_eq!(conversion.value["spec"], source["spec"]);
+        assert_eq!(
+            conversion.value["apiVersion"],
+            json!("networking.istio.io/v1")
+        );
+    }
+
+    #[test]
+    fn reports_the_fields_the_model_drops() {
+        let mut source = virtual_service("networking.istio.io/v1beta1");
+        source["spec"]["http"][0]["route"][0]["destination"]["weightHint"] = json!(3);
+        let conversion = convert(&source, ApiVersion::V1).unwrap();
+        assert_eq!(
+            conversion.dropped,
+            vec!["spec.http[0].route[0].destination.weightHint".to_string()]
+        );
+        assert_eq!(
+            conversion.value["spec"]["http"][0]["route"][0]["destination"],
+            json!({ "host": "reviews", "subset": "v1" })
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_convert() {
+        assert_eq!(
+            convert(&json!({ "kind": "VirtualService" }), ApiVersion::V1),
+            Err(ConversionError::MissingType)
+        );
+        assert_eq!(
+            convert(
+                &json!({ "apiVersion": "apps/v1", "kind": "Deployment" }),
+                ApiVersion::V1
+            ),
+            Err(ConversionError::UnknownKind {
+                api_version: "apps/v1".to_string(),
+                kind: "Deployment".to_string(),
+            })
+        );
+        assert_eq!(
+            convert(&virtual_service("networking.istio.io/v2"), ApiVersion::V1),
+            Err(ConversionError::UnknownVersion("v2".to_string()))
+        );
+        let envoy_filter = json!({
+            "apiVersion": "networking.istio.io/v1alpha3",
+            "kind": "EnvoyFilter",
+            "metadata": { "name": "lua" },
+        });
+        assert_eq!(
+            convert(&envoy_filter, ApiVersion::V1beta1)
+                .unwrap_err()
+                .to_string(),
+            "EnvoyFilter is not served in v1beta1"
+        );
+        let mut invalid = virtual_service("networking.istio.io/v1beta1");
+        invalid["spec"]["hosts"] = json!("reviews");
+        assert!(matches!(
+            convert(&invalid, ApiVersion::V1),
+            Err(ConversionError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn lists_the_versions_of_each_kind() {
+        assert_eq!(
+            versions("networking.istio.io", "Sidecar"),
+            &[ApiVersion::V1alpha3, ApiVersion::V1beta1, ApiVersion::V1]
+        );
+        assert_eq!(
+            versions("networking.istio.io", "EnvoyFilter"),
+            &[ApiVersion::V1alpha3]
+        );
+        assert_eq!(
+            versions("networking.istio.io", "ProxyConfig"),
+            &[ApiVersion::V1beta1]
+        );
+        assert_eq!(
+            versions("security.istio.io", "AuthorizationPolicy"),
+            &[ApiVersion::V1beta1, ApiVersion::V1]
+        );
+        assert!(versions("security.istio.io", "VirtualService").is_empty());
+        assert_eq!("v1beta1".parse::<ApiVersion>(), Ok(ApiVersion::V1beta1));
+        assert_eq!(
+            serde_json::to_value(ApiVersion::V1alpha3).unwrap(),
+            json!("v1alpha3")
+        );
+    }
+
+    fn read<T: Resource + DeserializeOwned>(value: Value) -> Result<AnyVersion<T>, String> {
+        AnyVersion::from_json_value(value).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn reads_every_served_version_and_writes_it_back() {
+        for version in [ApiVersion::V1alpha3, ApiVersion::V1beta1, ApiVersion::V1] {
+            let api_version = format!("networking.istio.io/{}", version);
+            let manifest = virtual_service(&api_version);
+            let read: AnyVersion<VirtualService> = read(manifest.clone()).unwrap();
+            assert_eq!(read.api_version, version);
+            assert_eq!(
+                read.resource.spec.as_ref().unwrap().hosts,
+                Some(vec!["reviews".to_string()])
+            );
+            assert_eq!(serde_json::to_value(&read).unwrap(), manifest);
+
+            let deserialized: AnyVersion<VirtualService> =
+                serde_json::from_value(manifest).unwrap();
+            assert_eq!(deserialized.api_version, version);
+            assert_eq!(deserialized.into_inner(), read.resource);
+        }
+
+        let policy: AnyVersion<AuthorizationPolicy> = read(json!({
+            "apiVersion": "security.istio.io/v1",
+            "kind": "AuthorizationPolicy",
+            "metadata": { "name": "deny-all", "namespace": "shop" },
+            "spec": {},
+        }))
+        .unwrap();
+        assert_eq!(policy.api_version, ApiVersion::V1);
+    }
+
+    #[test]
+    fn rejects_versions_the_kind_is_not_served_in() {
+        assert_eq!(
+            read::<VirtualService>(virtual_service("networking.istio.io/v2")).unwrap_err(),
+            "VirtualService reviews: VirtualService is not served in networking.istio.io/v2"
+        );
+        assert_eq!(
+            read::<VirtualService>(virtual_service("security.istio.io/v1beta1")).unwrap_err(),
+            "VirtualService reviews: VirtualService is not served in security.istio.io/v1beta1"
+        );
+        assert_eq!(
+            read::<EnvoyFilter>(json!({
+                "apiVersion": "networking.istio.io/v1",
+                "kind": "EnvoyFilter",
+                "metadata": { "name": "lua" },
+            }))
+            .unwrap_err(),
+            "EnvoyFilter lua: EnvoyFilter is not served in networking.istio.io/v1"
+        );
+        assert_eq!(
+            read::<VirtualService>(without_api_version(virtual_service(""))).unwrap_err(),
+            "VirtualService reviews: missing apiVersion"
+        );
+        let mut gateway = virtual_service("networking.istio.io/v1beta1");
+        gateway["kind"] = json!("Gateway");
+        assert_eq!(
+            read::<VirtualService>(gateway).unwrap_err(),
+            "VirtualService reviews: expected kind VirtualService, found Gateway"
+        );
+        assert!(
+            serde_json::from_value::<AnyVersion<VirtualService>>(virtual_service(
+                "networking.istio.io/v1beta2"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn versioned_resources_convert_to_and_from_the_modelled_ones() {
+        use crate::istio::{v1, v1alpha3};
+
+        let manifest = virtual_service("networking.istio.io/v1beta1");
+        let modelled: VirtualService = serde_json::from_value(manifest.clone()).unwrap();
+
+        let legacy = v1alpha3::VirtualService::from(modelled.clone());
+        assert_eq!(
+            <v1alpha3::VirtualService as Resource>::API_VERSION,
+            "networking.istio.io/v1alpha3"
+        );
+        assert_eq!(legacy.spec, modelled.spec);
+        assert_eq!(VirtualService::from(legacy), modelled);
+
+        let stable = v1::VirtualService::from(modelled.clone());
+        assert_eq!(
+            <v1::VirtualService as Resource>::API_VERSION,
+            "networking.istio.io/v1"
+        );
+        assert_eq!(<v1::VirtualService as Resource>::KIND, "VirtualService");
+        assert_eq!(
+            serde_json::to_value(&stable).unwrap(),
+            without_api_version(without_kind(manifest))
+        );
+        assert_eq!(VirtualService::from(stable), modelled);
+    }
+}
@@ -0,0 +1,169 @@
+// CustomResourceDefinitions of the resources, built from the schemas of the types, so a test
+// cluster or a bootstrap tool can install the Istio CRDs before it creates resources. A CRD serves
+// the version the crate models and stores it; it is no replacement for the CRDs of an Istio
+// release, which serve every version. Needs the `schema` feature.
+use crate::istio::authorization_policy::AuthorizationPolicy;
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::gateway::Gateway;
+use crate::istio::istio_operator::IstioOperator;
+use crate::istio::peer_authentication::PeerAuthentication;
+use crate::istio::proxy_config::ProxyConfig;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::schema::schema_of;
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::sidecar::Sidecar;
+use crate::istio::virtual_service::VirtualService;
+use crate::istio::wasm_plugin::WasmPlugin;
+use crate::istio::workload_entry::WorkloadEntry;
+use crate::istio::workload_group::WorkloadGroup;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, CustomResourceDefinitionNames, CustomResourceDefinitionSpec,
+    CustomResourceDefinitionVersion, CustomResourceSubresourceStatus, CustomResourceSubresources,
+    CustomResourceValidation, JSONSchemaProps,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::{self, json, Value};
+use k8s_openapi::Resource;
+use schemars::JsonSchema;
+
+/// Category all Istio CRDs are listed in, as in `kubectl get istio-io`.
+pub const CATEGORY: &str = "istio-io";
+
+/// The CRD of `T` with the given short names. The schema of `metadata` is left to the API server
+/// and `status` accepts any content, as in the CRDs of Istio.
+pub fn crd<T: Resource + JsonSchema>(short_names: &[&str]) -> CustomResourceDefinition {
+    let plural = T::URL_PATH_SEGMENT;
+    let short_names = short_names
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    CustomResourceDefinition {
+        metadata: ObjectMeta {
+            name: Some(format!("{}.{}", plural, T::GROUP)),
+            ..ObjectMeta::default()
+        },
+        spec: CustomResourceDefinitionSpec {
+            group: T::GROUP.to_string(),
+            names: CustomResourceDefinitionNames {
+                categories: Some(vec![CATEGORY.to_string(), T::GROUP.replace('.', "-")]),
+                kind: T::KIND.to_string(),
+                list_kind: Some(format!("{}List", T::KIND)),
+                plural: plural.to_string(),
+                short_names: Some(short_names).filter(|names| !names.is_empty()),
+                singular: Some(T::KIND.to_lowercase()),
+            },
+            scope: "Namespaced".to_string(),
+            versions: vec![CustomResourceDefinitionVersion {
+                name: T::VERSION.to_string(),
+                schema: Some(CustomResourceValidation {
+                    open_api_v3_schema: Some(open_api_v3_schema::<T>()),
+                }),
+                served: true,
+                storage: true,
+                subresources: Some(CustomResourceSubresources {
+                    status: Some(CustomResourceSubresourceStatus(Value::Object(
+                        Default::default(),
+                    ))),
+                    ..CustomResourceSubresources::default()
+                }),
+                ..CustomResourceDefinitionVersion::default()
+            }],
+            ..CustomResourceDefinitionSpec::default()
+        },
+        status: None,
+    }
+}
+
+/// The schema of `T` as a CRD takes it: without the JSON Schema keywords of the root, with an
+/// opaque `metadata` and a `status` preserving unknown fields.
+fn open_api_v3_schema<T: JsonSchema>() -> JSONSchemaProps {
+    let mut schema = schema_of::<T>();
+    if let Some(root) = schema.as_object_mut() {
+        root.remove("$schema");
+        root.remove("title");
+        root.remove("definitions");
+        root.remove("required");
+        if let Some(properties) = root.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert("metadata".to_string(), json!({ "type": "object" }));
+            properties.insert(
+                "status".to_string(),
+                json!({ "type": "object", "x-kubernetes-preserve-unknown-fields": true }),
+            );
+        }
+    }
+    preserve_unknown_fields(&mut schema);
+    serde_json::from_value(schema).unwrap_or_default()
+}
+
+/// Structural schemas forbid `additionalProperties` next to `properties`; the specs keeping unknown
+/// fields (`#[serde(flatten)] extra`) say so with `x-kubernetes-preserve-unknown-fields` instead.
+fn preserve_unknown_fields(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties")
+                && object.get("additionalProperties") == Some(&Value::Bool(true))
+            {
+                object.remove("additionalProperties");
+                object.insert(
+                    "x-kubernetes-preserve-unknown-fields".to_string(),
+                    Value::Bool(true),
+                );
+            }
+            object.values_mut().for_each(preserve_unknown_fields);
+        }
+        Value::Array(items) => items.iter_mut().for_each(preserve_unknown_fields),
+        _ => {}
+    }
+}
+
+/// Adds `crd()` to `$resource`, returning its CRD with the short names of the Istio CRD.
+macro_rules! crd {
+    ($($resource:ty: [$($short_name:literal),*];)*) => {
+        $(
+            impl $resource {
+                /// The CustomResourceDefinition of the kind, serving the modelled version.
+                pub fn crd() -> CustomResourceDefinition {
+                    crd::<$resource>(&[$($short_name),*])
+                }
+            }
+        )*
+    };
+}
+
+crd! {
+    AuthorizationPolicy: [];
+    DestinationRule: ["dr"];
+    EnvoyFilter: [];
+    Gateway: ["gw"];
+    IstioOperator: ["iop", "io"];
+    PeerAuthentication: ["pa"];
+    ProxyConfig: [];
+    RequestAuthentication: ["ra"];
+    ServiceEntry: ["se"];
+    Sidecar: [];
+    VirtualService: ["vs"];
+    WasmPlugin: [];
+    WorkloadEntry: ["we"];
+    WorkloadGroup: ["wg"];
+}
+
+/// The CRDs of all kinds the crate models.
+pub fn all() -> Vec<CustomResourceDefinition> {
+    vec![
+        AuthorizationPolicy::crd(),
+        DestinationRule::crd(),
+        EnvoyFilter::crd(),
+        Gateway::crd(),
+        IstioOperator::crd(),
+        PeerAuthentication::crd(),
+        ProxyConfig::crd(),
+        RequestAuthentication::crd(),
+        ServiceEntry::crd(),
+        Sidecar::crd(),
+        VirtualService::crd(),
+        WasmPlugin::crd(),
+        WorkloadEntry::crd(),
+        WorkloadGroup::crd(),
+    ]
+}
@@ -0,0 +1,913 @@
+// Defaulting of Istio resources, in two flavours.
+//
+// `ApplyDefaults` materializes the defaults Istio documents for unset fields (the ring size of
+// consistent hashing, the retry policy of HTTP routes, the TCP connect timeout, ...) in a typed
+// spec, so comparisons of desired and actual configuration and simulations see fully resolved
+// values.
+//
+// `Defaults` are house defaults, applied by a mutating admission webhook so every team gets them
+// whatever tool produced the manifest. They work on the JSON of the object rather than the typed
+// resources, so fields this crate does not model are left untouched:
+//
+// - routes splitting traffic with some destination weights missing get weights adding up to 100,
+// - duration strings in Go notation (`1m30s`, `250ms`) are rewritten in the protobuf notation
+//   (`90s`, `0.250s`) that Istio accepts,
+// - the standard labels are added where missing.
+use crate::istio::connection_pool_settings::TCPSettings;
+use crate::istio::destination_rule::{
+    ConnectionPoolSettings, DestinationRule, DestinationRuleSpec, LbPolicy, LoadBalancerSettings,
+    OutlierDetection, TrafficPolicy,
+};
+use crate::istio::duration::IstioDuration;
+use crate::istio::gateway::{Gateway, GatewaySpec};
+use crate::istio::google::protobuf::UInt32Value;
+use crate::istio::load_balancer_settings::HashAlgorithm;
+use crate::istio::virtual_service::{
+    HttpRedirect, HttpRetry, HttpRoute, RedirectPortSelection, VirtualServiceSpec,
+};
+use crate::istio::weight::{Weight, Weights};
+use crate::istio::Map;
+use crate::istio::VirtualService;
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::Resource;
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, Operation};
+use kube::core::DynamicObject;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+pub use crate::istio::duration::parse_duration;
+
+/// Minimum number of virtual nodes of a consistent hash ring.
+pub const DEFAULT_MINIMUM_RING_SIZE: u64 = 1024;
+
+/// Number of entries of a Maglev lookup table.
+pub const DEFAULT_MAGLEV_TABLE_SIZE: u64 = 65537;
+
+/// TCP connect timeout of the connections to a destination.
+pub const DEFAULT_CONNECT_TIMEOUT: IstioDuration = IstioDuration::from_secs(10);
+
+/// Retry attempts of HTTP routes without a retry policy.
+pub const DEFAULT_RETRY_ATTEMPTS: i32 = 2;
+
+/// Conditions retried by HTTP routes without `retryOn`.
+pub const DEFAULT_RETRY_ON: &str =
+    "connect-failure,refused-stream,unavailable,cancelled,retriable-status-codes";
+
+/// Base interval of the exponential backoff between retries.
+pub const DEFAULT_RETRY_BACKOFF: IstioDuration = IstioDuration::from_millis(25);
+
+/// Status code of HTTP redirects.
+pub const DEFAULT_REDIRECT_CODE: i32 = 301;
+
+/// Outlier detection settings used for the fields left unset.
+pub const DEFAULT_CONSECUTIVE_5XX_ERRORS: u32 = 5;
+pub const DEFAULT_EJECTION_INTERVAL: IstioDuration = IstioDuration::from_secs(10);
+pub const DEFAULT_BASE_EJECTION_TIME: IstioDuration = IstioDuration::from_secs(30);
+pub const DEFAULT_MAX_EJECTION_PERCENT: i32 = 10;
+
+/// # ApplyDefaults
+/// Sets the fields Istio gives a documented default when unset. Applying the defaults twice changes
+/// nothing.
+pub trait ApplyDefaults {
+    fn apply_defaults(&mut self);
+}
+
+impl ApplyDefaults for VirtualServiceSpec {
+    fn apply_defaults(&mut self) {
+        for route in self.http.iter_mut().flatten() {
+            route.apply_defaults();
+        }
+    }
+}
+
+/// Forwarding routes get the default retry policy, redirects the default status code and, when
+/// changing the scheme, the default port of the new scheme (80 or 443).
+impl ApplyDefaults for HttpRoute {
+    fn apply_defaults(&mut self) {
+        if self.route.is_some() {
+            let retries = self.retries.get_or_insert(HttpRetry {
+                attempts: DEFAULT_RETRY_ATTEMPTS,
+                per_try_timeout: None,
+                retry_on: None,
+                retry_remote_localities: None,
+                retry_ignore_previous_hosts: None,
+                backoff: None,
+            });
+            if retries.attempts > 0 {
+                retries
+                    .retry_on
+                    .get_or_insert_with(|| DEFAULT_RETRY_ON.to_string());
+                retries.retry_remote_localities.get_or_insert(false);
+                retries.retry_ignore_previous_hosts.get_or_insert(true);
+                retries.backoff.get_or_insert(DEFAULT_RETRY_BACKOFF);
+            }
+        }
+        if let Some(redirect) = &mut self.redirect {
+            redirect.apply_defaults();
+        }
+    }
+}
+
+impl ApplyDefaults for HttpRedirect {
+    fn apply_defaults(&mut self) {
+        self.redirect_code.get_or_insert(DEFAULT_REDIRECT_CODE);
+        if self.scheme.is_some() && self.port.is_none() {
+            self.derive_port
+                .get_or_insert(RedirectPortSelection::FromProtocolDefault);
+        }
+    }
+}
+
+/// The top-level traffic policy is created if needed, as its defaults apply to every connection to
+/// the host; port-level and subset policies only get defaults for the settings they override.
+impl ApplyDefaults for DestinationRuleSpec {
+    fn apply_defaults(&mut self) {
+        let traffic_policy = self.traffic_policy.get_or_insert(TrafficPolicy {
+            load_balancer: None,
+            connection_pool: None,
+            outlier_detection: None,
+            tls: None,
+            port_level_settings: None,
+            tunnel: None,
+            proxy_protocol: None,
+            extra: Map::new(),
+        });
+        traffic_policy
+            .connection_pool
+            .get_or_insert(ConnectionPoolSettings {
+                tcp: None,
+                http: None,
+            })
+            .tcp
+            .get_or_insert(TCPSettings {
+                max_connections: None,
+                connect_timeout: None,
+                tcp_keepalive: None,
+                max_connection_duration: None,
+                idle_timeout: None,
+            });
+        traffic_policy.apply_defaults();
+        for subset in self.subsets.iter_mut().flatten() {
+            if let Some(traffic_policy) = &mut subset.traffic_policy {
+                traffic_policy.apply_defaults();
+            }
+        }
+    }
+}
+
+impl ApplyDefaults for TrafficPolicy {
+    fn apply_defaults(&mut self) {
+        if let Some(load_balancer) = &mut self.load_balancer {
+            load_balancer.apply_defaults();
+        }
+        if let Some(tcp) = self
+            .connection_pool
+            .as_mut()
+            .and_then(|pool| pool.tcp.as_mut())
+        {
+            tcp.connect_timeout.get_or_insert(DEFAULT_CONNECT_TIMEOUT);
+        }
+        if let Some(outlier_detection) = &mut self.outlier_detection {
+            outlier_detection.apply_defaults();
+        }
+        for port in self.port_level_settings.iter_mut().flatten() {
+            if let Some(load_balancer) = &mut port.load_balancer {
+                load_balancer.apply_defaults();
+            }
+            if let Some(tcp) = port
+                .connection_pool
+                .as_mut()
+                .and_then(|pool| pool.tcp.as_mut())
+            {
+                tcp.connect_timeout.get_or_insert(DEFAULT_CONNECT_TIMEOUT);
+            }
+            if let Some(outlier_detection) = &mut port.outlier_detection {
+                outlier_detection.apply_defaults();
+            }
+        }
+    }
+}
+
+impl ApplyDefaults for LoadBalancerSettings {
+    fn apply_defaults(&mut self) {
+        if let Some(LbPolicy::ConsistentHash(consistent_hash)) = &mut self.lb_policy {
+            match &mut consistent_hash.hash_algorithm {
+                Some(HashAlgorithm::RingHash(ring_hash)) => {
+                    ring_hash
+                        .minimum_ring_size
+                        .get_or_insert(DEFAULT_MINIMUM_RING_SIZE);
+                }
+                Some(HashAlgorithm::Maglev(maglev)) => {
+                    maglev.table_size.get_or_insert(DEFAULT_MAGLEV_TABLE_SIZE);
+                }
+                None => {
+                    consistent_hash
+                        .minimum_ring_size
+                        .get_or_insert(DEFAULT_MINIMUM_RING_SIZE);
+                }
+            }
+        }
+    }
+}
+
+/// The deprecated `consecutiveErrors` is left alone; `consecutive5xxErrors` only gets its default
+/// when neither is set.
+impl ApplyDefaults for OutlierDetection {
+    fn apply_defaults(&mut self) {
+        if self.consecutive_5xx_errors().is_none() {
+            self.consecutive5xx_errors = Some(UInt32Value {
+                value: Some(DEFAULT_CONSECUTIVE_5XX_ERRORS),
+            });
+        }
+        self.interval.get_or_insert(DEFAULT_EJECTION_INTERVAL);
+        self.base_ejection_time
+            .get_or_insert(DEFAULT_BASE_EJECTION_TIME);
+        self.max_ejection_percent
+            .get_or_insert(DEFAULT_MAX_EJECTION_PERCENT);
+    }
+}
+
+/// TLS servers get an explicit `httpsRedirect: false`.
+impl ApplyDefaults for GatewaySpec {
+    fn apply_defaults(&mut self) {
+        for tls in self
+            .servers
+            .iter_mut()
+            .filter_map(|server| server.tls.as_mut())
+        {
+            tls.https_redirect.get_or_insert(false);
+        }
+    }
+}
+
+macro_rules! apply_defaults {
+    ($($resource:ty),*) => {
+        $(
+            impl ApplyDefaults for $resource {
+                fn apply_defaults(&mut self) {
+                    if let Some(spec) = &mut self.spec {
+                        spec.apply_defaults();
+                    }
+                }
+            }
+        )*
+    };
+}
+
+apply_defaults!(VirtualService, DestinationRule, Gateway);
+
+/// Fields holding a `google.protobuf.Duration` in the Istio resources.
+pub const DURATION_FIELDS: &[&str] = &[
+    "backoff",
+    "baseEjectionTime",
+    "connectTimeout",
+    "fixedDelay",
+    "idleTimeout",
+    "interval",
+    "maxAge",
+    "maxConnectionDuration",
+    "perTryTimeout",
+    "time",
+    "timeout",
+    "ttl",
+    "warmupDurationSecs",
+];
+
+/// # Defaults
+#[derive(Clone, Debug)]
+pub struct Defaults {
+    /// Labels added to every object that does not have them yet.
+    pub labels: Map<String, String>,
+    pub fill_weights: bool,
+    pub normalize_durations: bool,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults {
+            labels: Map::new(),
+            fill_weights: true,
+            normalize_durations: true,
+        }
+    }
+}
+
+impl Defaults {
+    pub fn new() -> Self {
+        Defaults::default()
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn without_weights(mut self) -> Self {
+        self.fill_weights = false;
+        self
+    }
+
+    pub fn without_durations(mut self) -> Self {
+        self.normalize_durations = false;
+        self
+    }
+
+    /// Applies the defaults to a manifest.
+    pub fn apply(&self, object: &mut Value) {
+        if !self.labels.is_empty() {
+            let labels = object
+                .as_object_mut()
+                .map(|object| {
+                    object
+                        .entry("metadata")
+                        .or_insert_with(|| Value::Object(Default::default()))
+                })
+                .and_then(Value::as_object_mut)
+                .map(|metadata| {
+                    metadata
+                        .entry("labels")
+                        .or_insert_with(|| Value::Object(Default::default()))
+                })
+                .and_then(Value::as_object_mut);
+            if let Some(labels) = labels {
+                for (key, value) in &self.labels {
+                    labels
+                        .entry(key.clone())
+                        .or_insert_with(|| Value::String(value.clone()));
+                }
+            }
+        }
+        let is_virtual_service = object.get("kind").and_then(Value::as_str)
+            == Some(VirtualService::KIND)
+            && object
+                .get("apiVersion")
+                .and_then(Value::as_str)
+                .is_some_and(|version| version.starts_with(VirtualService::GROUP));
+        let spec = match object.get_mut("spec") {
+            Some(spec) => spec,
+            None => return,
+        };
+        if self.fill_weights && is_virtual_service {
+            for protocol in ["http", "tcp", "tls"] {
+                let routes = spec.get_mut(protocol).and_then(Value::as_array_mut);
+                for route in routes.into_iter().flatten() {
+                    if let Some(destinations) = route.get_mut("route").and_then(Value::as_array_mut)
+                    {
+                        fill_weights(destinations);
+                    }
+                }
+            }
+        }
+        if self.normalize_durations {
+            normalize_durations(spec);
+        }
+    }
+
+    /// The JSON patch applying the defaults to `object`.
+    pub fn patch(&self, object: &Value) -> json_patch::Patch {
+        let mut defaulted = object.clone();
+        self.apply(&mut defaulted);
+        json_patch::diff(object, &defaulted)
+    }
+
+    /// Answers a mutating admission request with the patch applying the defaults. Deletions are
+    /// allowed as they are.
+    pub fn mutate(&self, request: &AdmissionRequest<DynamicObject>) -> AdmissionResponse {
+        let response = AdmissionResponse::from(request);
+        let object = match (&request.operation, &request.object) {
+            (Operation::Create | Operation::Update, Some(object)) => object,
+            _ => return response,
+        };
+        let object = match serde_json::to_value(object) {
+            Ok(object) => object,
+            Err(error) => return response.deny(error),
+        };
+        let patch = self.patch(&object);
+        if patch.0.is_empty() {
+            return response;
+        }
+        match response.clone().with_patch(patch) {
+            Ok(response) => response,
+            Err(error) => response.deny(error),
+        }
+    }
+}
+
+/// Gives the destinations of a route splitting traffic without a weight the rest of the 100 in
+/// equal shares. Destinations that all have weights are left for validation to check, as are
+/// weights already adding up to more than 100, which are scaled down.
+fn fill_weights(destinations: &mut [Value]) {
+    let weights: Vec<Option<Weight>> = destinations
+        .iter()
+        .map(|destination| {
+            destination
+                .get("weight")
+                .and_then(Value::as_i64)
+                .and_then(|weight| Weight::try_from(weight).ok())
+        })
+        .collect();
+    let missing = weights.iter().filter(|weight| weight.is_none()).count() as u32;
+    if destinations.len() < 2 || missing == 0 {
+        return;
+    }
+    let total: u32 = weights.iter().flatten().map(|weight| weight.get()).sum();
+    let weights: Vec<u32> = match 100u32.checked_sub(total) {
+        Some(rest) => {
+            let mut share = (0..missing).map(|i| rest / missing + u32::from(i < rest % missing));
+            weights
+                .iter()
+                .map(|weight| weight.map_or_else(|| share.next().unwrap_or(0), Weight::get))
+                .collect()
+        }
+        None => Weights::normalize(weights)
+            .iter()
+            .map(Weight::get)
+            .collect(),
+    };
+    for (destination, weight) in destinations.iter_mut().zip(weights) {
+        if let Some(destination) = destination.as_object_mut() {
+            destination.insert("weight".to_string(), Value::from(weight));
+        }
+    }
+}
+
+fn normalize_durations(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                match field {
+                    Value::String(duration) if DURATION_FIELDS.contains(&key.as_str()) => {
+                        if !is_proto_duration(duration) {
+                            if let Some(parsed) = parse_duration(duration) {
+                                *duration = proto_duration(parsed);
+                            }
+                        }
+                    }
+                    Value::Object(_) if DURATION_FIELDS.contains(&key.as_str()) => {
+                        // The protobuf object form, `{ seconds, nanos }`.
+                        if let Ok(parsed) = serde_json::from_value::<IstioDuration>(field.clone()) {
+                            *field = Value::String(proto_duration(parsed.0));
+                        }
+                    }
+                    _ => normalize_durations(field),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(normalize_durations),
+        _ => {}
+    }
+}
+
+fn is_proto_duration(duration: &str) -> bool {
+    duration
+        .strip_suffix('s')
+        .is_some_and(|seconds| seconds.parse::<f64>().is_ok())
+}
+
+/// Formats a duration in the protobuf JSON notation: seconds with 0, 3, 6 or 9 fractional digits.
+fn proto_duration(duration: Duration) -> String {
+    let nanos = duration.subsec_nanos();
+    match nanos {
+        0 => format!("{}s", duration.as_secs()),
+        _ if nanos.is_multiple_of(1_000_000) => {
+            format!("{}.{:03}s", duration.as_secs(), nanos / 1_000_000)
+        }
+        _ if nanos.is_multiple_of(1_000) => format!("{}.{:06}s", duration.as_secs(), nanos / 1_000),
+        _ => format!("{}.{:09}s", duration.as_secs(), nanos),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::json;
+    use kube::core::admission::AdmissionReview;
+    use std::convert::TryInto;
+
+    fn defaulted<T>(value: Value) -> Value
+    where
+        T: ApplyDefaults + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let mut resource: T = serde_json::from_value(value).unwrap();
+        resource.apply_defaults();
+        let defaulted = serde_json::to_value(&resource).unwrap();
+        resource.apply_defaults();
+        assert_eq!(serde_json::to_value(&resource).unwrap(), defaulted);
+        defaulted
+    }
+
+    #[test]
+    fn forwarding_routes_get_the_default_retry_policy() {
+        let spec = defaulted::<VirtualServiceSpec>(json!({
+            "hosts": ["reviews"],
+            "http": [
+                { "route": [{ "destination": { "host": "reviews" } }] },
+                {
+                    "route": [{ "destination": { "host": "reviews" } }],
+                    "retries": { "attempts": 5, "retryOn": "5xx" },
+                },
+                {
+                    "route": [{ "destination": { "host": "reviews" } }],
+                    "retries": { "attempts": 0 },
+                },
+            ],
+        }));
+        assert_eq!(
+            spec["http"][0]["retries"],
+            json!({
+                "attempts": 2,
+                "retryOn": DEFAULT_RETRY_ON,
+                "retryRemoteLocalities": false,
+                "retryIgnorePreviousHosts": true,
+                "backoff": "25ms",
+            })
+        );
+        assert_eq!(
+            spec["http"][1]["retries"],
+            json!({
+                "attempts": 5,
+                "retryOn": "5xx",
+                "retryRemoteLocalities": false,
+                "retryIgnorePreviousHosts": true,
+                "backoff": "25ms",
+            })
+        );
+        // Disabled retries stay disabled.
+        assert_eq!(spec["http"][2]["retries"], json!({ "attempts": 0 }));
+    }
+
+    #[test]
+    fn redirects_get_the_default_code_and_port() {
+        let spec = defaulted::<VirtualServiceSpec>(json!({
+            "hosts": ["reviews"],
+            "http": [
+                { "redirect": { "scheme": "https" } },
+                { "redirect": { "scheme": "https", "port": 8443, "redirectCode": 308 } },
+                { "redirect": { "uri": "/v2" } },
+            ],
+        }));
+        assert_eq!(
+            spec["http"][0],
+            json!({
+                "redirect": {
+                    "scheme": "https",
+                    "derivePort": "FROM_PROTOCOL_DEFAULT",
+                    "redirectCode": 301,
+                },
+            })
+        );
+        assert_eq!(
+            spec["http"][1],
+            json!({ "redirect": { "scheme": "https", "port": 8443, "redirectCode": 308 } })
+        );
+        assert_eq!(
+            spec["http"][2],
+            json!({ "redirect": { "uri": "/v2", "redirectCode": 301 } })
+        );
+    }
+
+    #[test]
+    fn destination_rules_get_the_default_connect_timeout_and_load_balancing() {
+        let spec = defaulted::<DestinationRuleSpec>(json!({ "host": "reviews" }));
+        assert_eq!(
+            spec["trafficPolicy"],
+            json!({ "connectionPool": { "tcp": { "connectTimeout": "10s" } } })
+        );
+
+        let spec = defaulted::<DestinationRuleSpec>(json!({
+            "host": "reviews",
+            "trafficPolicy": {
+                "connectionPool": { "tcp": { "connectTimeout": "3s", "maxConnections": 100 } },
+                "loadBalancer": { "consistentHash": { "httpHeaderName": "x-user" } },
+                "portLevelSettings": [{
+                    "port": { "number": 9080 },
+                    "loadBalancer": {
+                        "consistentHash": { "useSourceIp": true, "maglev": {} },
+                    },
+                }],
+            },
+            "subsets": [
+                {
+                    "name": "v1",
+                    "labels": { "version": "v1" },
+                    "trafficPolicy": {
+                        "loadBalancer": {
+                            "consistentHash": { "httpHeaderName": "x-user", "ringHash": { "minimumRingSize": 64 } },
+                        },
+                    },
+                },
+                { "name": "v2", "labels": { "version": "v2" } },
+            ],
+        }));
+        let traffic_policy = &spec["trafficPolicy"];
+        assert_eq!(
+            traffic_policy["connectionPool"],
+            json!({ "tcp": { "connectTimeout": "3s", "maxConnections": 100 } })
+        );
+        assert_eq!(
+            traffic_policy["loadBalancer"],
+            json!({ "consistentHash": { "httpHeaderName": "x-user", "minimumRingSize": 1024 } })
+        );
+        assert_eq!(
+            traffic_policy["portLevelSettings"][0]["loadBalancer"],
+            json!({ "consistentHash": { "useSourceIp": true, "maglev": { "tableSize": 65537 } } })
+        );
+        assert_eq!(
+            spec["subsets"][0]["trafficPolicy"]["loadBalancer"]["consistentHash"]["ringHash"],
+            json!({ "minimumRingSize": 64 })
+        );
+        // Subsets without a policy of their own inherit the top-level one.
+        assert_eq!(spec["subsets"][1].get("trafficPolicy"), None);
+    }
+
+    #[test]
+    fn outlier_detection_gets_the_default_thresholds() {
+        let spec = defaulted::<DestinationRuleSpec>(json!({
+            "host": "reviews",
+            "trafficPolicy": { "outlierDetection": { "interval": "1m" } },
+            "subsets": [{
+                "name": "v1",
+                "trafficPolicy": { "outlierDetection": { "consecutiveErrors": 7, "maxEjectionPercent": 50 } },
+            }],
+        }));
+        assert_eq!(
+            spec["trafficPolicy"]["outlierDetection"],
+            json!({
+                "consecutive5xxErrors": 5,
+                "interval": "1m",
+                "baseEjectionTime": "30s",
+                "maxEjectionPercent": 10,
+            })
+        );
+        // The deprecated field stands in for `consecutive5xxErrors`.
+        assert_eq!(
+            spec["subsets"][0]["trafficPolicy"]["outlierDetection"],
+            json!({
+                "consecutiveErrors": 7,
+                "interval": "10s",
+                "baseEjectionTime": "30s",
+                "maxEjectionPercent": 50,
+            })
+        );
+    }
+
+    #[test]
+    fn tls_servers_do_not_redirect_by_default() {
+        let spec = defaulted::<GatewaySpec>(json!({
+            "selector": { "istio": "ingressgateway" },
+            "servers": [
+                { "port": { "number": 80, "name": "http", "protocol": "HTTP" }, "hosts": ["*"] },
+                {
+                    "port": { "number": 80, "name": "http-redirect", "protocol": "HTTP" },
+                    "hosts": ["www.example.com"],
+                    "tls": { "httpsRedirect": true },
+                },
+                {
+                    "port": { "number": 443, "name": "https", "protocol": "HTTPS" },
+                    "hosts": ["*"],
+                    "tls": { "mode": "SIMPLE", "credentialName": "cert" },
+                },
+            ],
+        }));
+        assert_eq!(spec["servers"][0].get("tls"), None);
+        assert_eq!(spec["servers"][1]["tls"], json!({ "httpsRedirect": true }));
+        assert_eq!(
+            spec["servers"][2]["tls"],
+            json!({ "mode": "SIMPLE", "credentialName": "cert", "httpsRedirect": false })
+        );
+    }
+
+    #[test]
+    fn resources_default_their_spec() {
+        let gateway = defaulted::<Gateway>(json!({
+            "metadata": { "name": "ingress", "namespace": "istio-system" },
+            "spec": {
+                "selector": { "istio": "ingressgateway" },
+                "servers": [{
+                    "port": { "number": 443, "name": "https", "protocol": "HTTPS" },
+                    "hosts": ["*"],
+                    "tls": { "mode": "SIMPLE" },
+                }],
+            },
+        }));
+        assert_eq!(
+            gateway["spec"]["servers"][0]["tls"]["httpsRedirect"],
+            json!(false)
+        );
+
+        let mut rule: DestinationRule = serde_json::from_value(json!({
+            "metadata": { "name": "reviews", "namespace": "shop" },
+        }))
+        .unwrap();
+        rule.apply_defaults();
+        assert!(rule.spec.is_none());
+    }
+
+    fn virtual_service(routes: Value) -> Value {
+        json!({
+            "apiVersion": "networking.istio.io/v1beta1",
+            "kind": "VirtualService",
+            "metadata": { "name": "reviews", "namespace": "shop" },
+            "spec": { "hosts": ["reviews"], "http": [{ "route": routes }] },
+        })
+    }
+
+    fn weights(object: &Value) -> Vec<Option<u64>> {
+        object["spec"]["http"][0]["route"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|destination| destination.get("weight").and_then(Value::as_u64))
+            .collect()
+    }
+
+    fn destinations(weights: &[Option<u32>]) -> Value {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| match weight {
+                Some(weight) => {
+                    json!({ "destination": { "host": format!("v{}", i) }, "weight": weight })
+                }
+                None => json!({ "destination": { "host": format!("v{}", i) } }),
+            })
+            .collect()
+    }
+
+    fn defaulted_weights(weights_before: &[Option<u32>]) -> Vec<Option<u64>> {
+        let mut object = virtual_service(destinations(weights_before));
+        Defaults::new().apply(&mut object);
+        weights(&object)
+    }
+
+    #[test]
+    fn adds_missing_labels_and_keeps_existing_ones() {
+        let defaults = Defaults::new()
+            .with_label("team", "platform")
+            .with_label("managed-by", "webhook");
+        let mut object = virtual_service(json!([]));
+        object["metadata"]["labels"] = json!({ "team": "checkout" });
+        defaults.apply(&mut object);
+        assert_eq!(
+            object["metadata"]["labels"],
+            json!({ "team": "checkout", "managed-by": "webhook" })
+        );
+
+        let mut object = json!({ "kind": "Gateway" });
+        defaults.apply(&mut object);
+        assert_eq!(
+            object["metadata"]["labels"],
+            json!({ "team": "platform", "managed-by": "webhook" })
+        );
+    }
+
+    #[test]
+    fn fills_missing_weights_with_the_rest_of_100() {
+        assert_eq!(
+            defaulted_weights(&[Some(70), None, None]),
+            vec![Some(70), Some(15), Some(15)]
+        );
+        assert_eq!(
+            defaulted_weights(&[None, None, None]),
+            vec![Some(34), Some(33), Some(33)]
+        );
+        assert_eq!(
+            defaulted_weights(&[Some(100), None]),
+            vec![Some(100), Some(0)]
+        );
+        // Weights over 100 are scaled down, the missing ones get nothing.
+        assert_eq!(
+            defaulted_weights(&[Some(80), Some(40), None]),
+            vec![Some(67), Some(33), Some(0)]
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_weights_and_single_destinations() {
+        // Weights that do not add up to 100 are left for validation to report.
+        assert_eq!(
+            defaulted_weights(&[Some(60), Some(30)]),
+            vec![Some(60), Some(30)]
+        );
+        assert_eq!(defaulted_weights(&[None]), vec![None]);
+
+        let mut object = virtual_service(destinations(&[None, None]));
+        Defaults::new().without_weights().apply(&mut object);
+        assert_eq!(weights(&object), vec![None, None]);
+
+        // Only VirtualServices have weighted routes.
+        let mut object = virtual_service(destinations(&[None, None]));
+        object["kind"] = json!("DestinationRule");
+        Defaults::new().apply(&mut object);
+        assert_eq!(weights(&object), vec![None, None]);
+    }
+
+    #[test]
+    fn rewrites_durations_in_protobuf_notation() {
+        let mut object = json!({
+            "kind": "DestinationRule",
+            "spec": {
+                "host": "reviews",
+                "trafficPolicy": {
+                    "connectionPool": { "tcp": { "connectTimeout": "250ms", "idleTimeout": "1h" } },
+                    "outlierDetection": { "interval": "1m30s", "baseEjectionTime": "30s" },
+                },
+                "subsets": [{
+                    "name": "v1",
+                    "trafficPolicy": {
+                        "outlierDetection": {
+                            "interval": { "seconds": 2, "nanos": 500000000 },
+                            "baseEjectionTime": "1.5s",
+                        },
+                    },
+                }],
+            },
+        });
+        Defaults::new().apply(&mut object);
+        let traffic_policy = &object["spec"]["trafficPolicy"];
+        assert_eq!(
+            traffic_policy["connectionPool"]["tcp"],
+            json!({ "connectTimeout": "0.250s", "idleTimeout": "3600s" })
+        );
+        assert_eq!(
+            traffic_policy["outlierDetection"],
+            json!({ "interval": "90s", "baseEjectionTime": "30s" })
+        );
+        assert_eq!(
+            object["spec"]["subsets"][0]["trafficPolicy"]["outlierDetection"],
+            json!({ "interval": "2.500s", "baseEjectionTime": "1.5s" })
+        );
+        // Only duration fields are rewritten.
+        assert_eq!(object["spec"]["subsets"][0]["name"], json!("v1"));
+
+        let mut object = json!({ "spec": { "timeout": "1m", "name": "1m" } });
+        Defaults::new().without_durations().apply(&mut object);
+        assert_eq!(object["spec"], json!({ "timeout": "1m", "name": "1m" }));
+        Defaults::new().apply(&mut object);
+        assert_eq!(object["spec"], json!({ "timeout": "60s", "name": "1m" }));
+    }
+
+    #[test]
+    fn patches_only_what_changes() {
+        let defaults = Defaults::new().with_label("team", "platform");
+        let object = virtual_service(destinations(&[Some(90), None]));
+        let patch = serde_json::to_value(defaults.patch(&object)).unwrap();
+        assert_eq!(
+            patch,
+            json!([
+                { "op": "add", "path": "/metadata/labels", "value": { "team": "platform" } },
+                { "op": "add", "path": "/spec/http/0/route/1/weight", "value": 10 },
+            ])
+        );
+
+        let mut defaulted = object;
+        defaults.apply(&mut defaulted);
+        assert!(defaults.patch(&defaulted).0.is_empty());
+    }
+
+    fn admission_request(operation: &str, object: Value) -> AdmissionRequest<DynamicObject> {
+        let review: AdmissionReview<DynamicObject> = serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": { "group": "networking.istio.io", "version": "v1beta1", "kind": "VirtualService" },
+                "resource": { "group": "networking.istio.io", "version": "v1beta1", "resource": "virtualservices" },
+                "name": "reviews",
+                "namespace": "shop",
+                "operation": operation,
+                "userInfo": { "username": "admin" },
+                "object": object,
+                "oldObject": null,
+                "dryRun": false,
+                "options": null,
+            },
+        }))
+        .unwrap();
+        review.try_into().unwrap()
+    }
+
+    #[test]
+    fn mutates_created_and_updated_objects() {
+        let defaults = Defaults::new();
+        let object = virtual_service(destinations(&[Some(90), None]));
+
+        let response = defaults.mutate(&admission_request("CREATE", object.clone()));
+        assert!(response.allowed);
+        let patch: Value = serde_json::from_slice(&response.patch.unwrap()).unwrap();
+        assert_eq!(
+            patch,
+            json!([{ "op": "add", "path": "/spec/http/0/route/1/weight", "value": 10 }])
+        );
+
+        let mut defaulted = object.clone();
+        defaults.apply(&mut defaulted);
+        let response = defaults.mutate(&admission_request("UPDATE", defaulted));
+        assert!(response.allowed);
+        assert_eq!(response.patch, None);
+
+        let response = defaults.mutate(&admission_request("DELETE", Value::Null));
+        assert!(response.allowed);
+        assert_eq!(response.patch, None);
+    }
+}
@@ -0,0 +1,496 @@
+// Cross-resource references of the routing configuration: VirtualService → Gateway, VirtualService
+// → delegate VirtualService, route destination → DestinationRule subset and Gateway → credential
+// Secret. Every reference is resolved against the resources given to the builder, so references to
+// missing objects can be reported before they break traffic. References to a kind of resource that
+// was not provided at all are left unchecked.
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::gateway::Gateway;
+use crate::istio::subset_registry::SubsetRegistry;
+use crate::istio::topology::{ResourceRef, MESH_GATEWAY};
+use crate::istio::virtual_service::{Destination, VirtualService};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::Resource;
+use std::collections::HashSet;
+use std::fmt;
+
+/// # ObjectRef
+/// Reference to a resource by kind, namespace and name.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ObjectRef {
+    pub kind: String,
+    #[serde(flatten)]
+    pub resource: ResourceRef,
+}
+
+impl ObjectRef {
+    pub fn new<K: Resource>(resource: ResourceRef) -> Self {
+        ObjectRef {
+            kind: K::KIND.to_string(),
+            resource,
+        }
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.resource)
+    }
+}
+
+/// # ReferenceTarget
+/// What a reference points to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum ReferenceTarget {
+    /// A resource referenced by name.
+    Object { object: ObjectRef },
+
+    /// A subset of a host, declared by a DestinationRule.
+    Subset { host: String, subset: String },
+}
+
+impl fmt::Display for ReferenceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceTarget::Object { object } => write!(f, "{}", object),
+            ReferenceTarget::Subset { host, subset } => write!(f, "subset {} of {}", subset, host),
+        }
+    }
+}
+
+/// # ReferenceStatus
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReferenceStatus {
+    Resolved,
+
+    /// The referenced object does not exist.
+    Dangling,
+
+    /// No resources of the referenced kind were provided, so the reference could not be checked.
+    Unchecked,
+}
+
+/// # Reference
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Reference {
+    pub from: ObjectRef,
+
+    /// Path of the referencing field, e.g. `spec.http[0].route[1].destination.subset`.
+    pub field: String,
+
+    pub target: ReferenceTarget,
+
+    pub status: ReferenceStatus,
+
+    /// The object the reference resolved to. For subsets this is the declaring DestinationRule.
+    pub resolved: Option<ObjectRef>,
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} references {} ({:?})",
+            self.from, self.field, self.target, self.status
+        )
+    }
+}
+
+/// # DependencyGraph
+/// The references between a set of resources.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DependencyGraph {
+    references: Vec<Reference>,
+}
+
+impl DependencyGraph {
+    pub fn builder<'a>() -> DependencyGraphBuilder<'a> {
+        DependencyGraphBuilder::default()
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// References to objects that do not exist.
+    pub fn dangling(&self) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(|reference| reference.status == ReferenceStatus::Dangling)
+    }
+
+    /// The references made by `object`.
+    pub fn dependencies<'g>(
+        &'g self,
+        object: &'g ObjectRef,
+    ) -> impl Iterator<Item = &'g Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| &reference.from == object)
+    }
+
+    /// The references resolved to `object`, i.e. everything affected by changing or deleting it.
+    pub fn dependents<'g>(&'g self, object: &'g ObjectRef) -> impl Iterator<Item = &'g Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.resolved.as_ref() == Some(object))
+    }
+
+    /// Records a reference to an object. `found` is `None` when the target kind was not provided.
+    fn object(&mut self, from: &ObjectRef, field: String, target: ObjectRef, found: Option<bool>) {
+        self.references.push(Reference {
+            from: from.clone(),
+            field,
+            resolved: Some(target.clone()).filter(|_| found == Some(true)),
+            target: ReferenceTarget::Object { object: target },
+            status: status(found),
+        });
+    }
+}
+
+/// # DependencyGraphBuilder
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraphBuilder<'a> {
+    virtual_services: Vec<&'a VirtualService>,
+    destination_rules: Option<Vec<&'a DestinationRule>>,
+    gateways: Option<Vec<&'a Gateway>>,
+    secrets: Option<Vec<&'a Secret>>,
+}
+
+impl<'a> DependencyGraphBuilder<'a> {
+    pub fn virtual_services(mut self, items: impl IntoIterator<Item = &'a VirtualService>) -> Self {
+        self.virtual_services.extend(items);
+        self
+    }
+
+    pub fn destination_rules(
+        mut self,
+        items: impl IntoIterator<Item = &'a DestinationRule>,
+    ) -> Self {
+        self.destination_rules
+            .get_or_insert_with(Vec::new)
+            .extend(items);
+        self
+    }
+
+    pub fn gateways(mut self, items: impl IntoIterator<Item = &'a Gateway>) -> Self {
+        self.gateways.get_or_insert_with(Vec::new).extend(items);
+        self
+    }
+
+    /// The Secrets gateway credentials are resolved against. A `credentialName` is expected in the
+    /// namespace of its Gateway.
+    pub fn secrets(mut self, items: impl IntoIterator<Item = &'a Secret>) -> Self {
+        self.secrets.get_or_insert_with(Vec::new).extend(items);
+        self
+    }
+
+    pub fn build(self) -> DependencyGraph {
+        let registry = self
+            .destination_rules
+            .as_ref()
+            .map(|rules| SubsetRegistry::new(rules.iter().copied()));
+        let gateways: Option<HashSet<ResourceRef>> = self.gateways.as_ref().map(|gateways| {
+            gateways
+                .iter()
+                .map(|gateway| ResourceRef::from_metadata(&gateway.metadata))
+                .collect()
+        });
+        let secrets: Option<HashSet<ResourceRef>> = self.secrets.as_ref().map(|secrets| {
+            secrets
+                .iter()
+                .map(|secret| ResourceRef::from_metadata(&secret.metadata))
+                .collect()
+        });
+        let virtual_services: HashSet<ResourceRef> = self
+            .virtual_services
+            .iter()
+            .map(|vs| ResourceRef::from_metadata(&vs.metadata))
+            .collect();
+
+        let mut graph = DependencyGraph::default();
+
+        for vs in &self.virtual_services {
+            let vs_ref = ResourceRef::from_metadata(&vs.metadata);
+            let namespace = vs_ref.namespace.clone();
+            let from = ObjectRef::new::<VirtualService>(vs_ref);
+            let spec = match &vs.spec {
+                Some(spec) => spec,
+                None => continue,
+            };
+
+            let mut gateway_fields: Vec<(String, &String)> = spec
+                .gateways
+                .iter()
+                .flatten()
+                .enumerate()
+                .map(|(i, gateway)| (format!("spec.gateways[{}]", i), gateway))
+                .collect();
+            for (i, http) in spec.http.iter().flatten().enumerate() {
+                for (j, m) in http.r#match.iter().flatten().enumerate() {
+                    for (k, gateway) in m.gateways.iter().flatten().enumerate() {
+                        gateway_fields.push((
+                            format!("spec.http[{}].match[{}].gateways[{}]", i, j, k),
+                            gateway,
+                        ));
+                    }
+                }
+            }
+            for (i, tls) in spec.tls.iter().flatten().enumerate() {
+                for (j, m) in tls.r#match.iter().enumerate() {
+                    for (k, gateway) in m.gateways.iter().flatten().enumerate() {
+                        gateway_fields.push((
+                            format!("spec.tls[{}].match[{}].gateways[{}]", i, j, k),
+                            gateway,
+                        ));
+                    }
+                }
+            }
+            for (i, tcp) in spec.tcp.iter().flatten().enumerate() {
+                for (j, m) in tcp.r#match.iter().flatten().enumerate() {
+                    for (k, gateway) in m.gateways.iter().flatten().enumerate() {
+                        gateway_fields.push((
+                            format!("spec.tcp[{}].match[{}].gateways[{}]", i, j, k),
+                            gateway,
+                        ));
+                    }
+                }
+            }
+            for (field, gateway) in gateway_fields {
+                if gateway == MESH_GATEWAY {
+                    continue;
+                }
+                let target = ResourceRef::parse(gateway, namespace.as_deref());
+                let found = gateways.as_ref().map(|known| known.contains(&target));
+                graph.object(&from, field, ObjectRef::new::<Gateway>(target), found);
+            }
+
+            for (i, http) in spec.http.iter().flatten().enumerate() {
+                if let Some(delegate) = &http.delegate {
+                    let target = ResourceRef {
+                        namespace: delegate.namespace.clone().or_else(|| namespace.clone()),
+                        name: delegate.name.clone().unwrap_or_default(),
+                    };
+                    let found = Some(virtual_services.contains(&target));
+                    graph.object(
+                        &from,
+                        format!("spec.http[{}].delegate", i),
+                        ObjectRef::new::<VirtualService>(target),
+                        found,
+                    );
+                }
+            }
+
+            let mut destinations: Vec<(String, &Destination)> = Vec::new();
+            for (i, http) in spec.http.iter().flatten().enumerate() {
+                for (j, route) in http.route.iter().flatten().enumerate() {
+                    destinations.push((
+                        format!("spec.http[{}].route[{}].destination", i, j),
+                        &route.destination,
+                    ));
+                }
+                if let Some(mirror) = &http.mirror {
+                    destinations.push((format!("spec.http[{}].mirror", i), mirror));
+                }
+                for (j, mirror) in http.mirrors.iter().flatten().enumerate() {
+                    destinations.push((
+                        format!("spec.http[{}].mirrors[{}].destination", i, j),
+                        &mirror.destination,
+                    ));
+                }
+            }
+            let l4_routes = spec
+                .tls
+                .iter()
+                .flatten()
+                .enumerate()
+                .map(|(i, tls)| (format!("spec.tls[{}]", i), &tls.route))
+                .chain(
+                    spec.tcp
+                        .iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(i, tcp)| (format!("spec.tcp[{}]", i), &tcp.route)),
+                );
+            for (rule, routes) in l4_routes {
+                for (j, route) in routes.iter().flatten().enumerate() {
+                    destinations.push((
+                        format!("{}.route[{}].destination", rule, j),
+                        &route.destination,
+                    ));
+                }
+            }
+            for (field, destination) in destinations {
+                let subset = match &destination.subset {
+                    Some(subset) => subset,
+                    None => continue,
+                };
+                let declared = registry
+                    .as_ref()
+                    .map(|registry| registry.subset(&destination.host, subset));
+                graph.references.push(Reference {
+                    from: from.clone(),
+                    field: format!("{}.subset", field),
+                    target: ReferenceTarget::Subset {
+                        host: destination.host.clone(),
+                        subset: subset.clone(),
+                    },
+                    status: status(declared.map(|registered| registered.is_some())),
+                    resolved: declared.flatten().map(|registered| {
+                        ObjectRef::new::<DestinationRule>(registered.destination_rule.clone())
+                    }),
+                });
+            }
+        }
+
+        for gateway in self.gateways.iter().flatten() {
+            let gateway_ref = ResourceRef::from_metadata(&gateway.metadata);
+            let namespace = gateway_ref.namespace.clone();
+            let from = ObjectRef::new::<Gateway>(gateway_ref);
+            let servers = gateway.spec.iter().flat_map(|spec| spec.servers.iter());
+            for (i, server) in servers.enumerate() {
+                let credential = server
+                    .tls
+                    .as_ref()
+                    .and_then(|tls| tls.credential_name.as_ref());
+                if let Some(credential) = credential {
+                    let target = ResourceRef::new(namespace.as_deref(), credential);
+                    let found = secrets.as_ref().map(|known| known.contains(&target));
+                    graph.object(
+                        &from,
+                        format!("spec.servers[{}].tls.credentialName", i),
+                        ObjectRef::new::<Secret>(target),
+                        found,
+                    );
+                }
+            }
+        }
+
+        graph
+    }
+}
+
+fn status(found: Option<bool>) -> ReferenceStatus {
+    match found {
+        Some(true) => ReferenceStatus::Resolved,
+        Some(false) => ReferenceStatus::Dangling,
+        None => ReferenceStatus::Unchecked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn resource<T: serde::de::DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn reviews() -> VirtualService {
+        resource(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": {
+                "hosts": ["reviews"],
+                "gateways": ["mesh", "ingress", "other/edge"],
+                "http": [
+                    { "route": [
+                        { "destination": { "host": "reviews", "subset": "v1" } },
+                        { "destination": { "host": "reviews", "subset": "v3" } },
+                    ] },
+                    { "delegate": { "name": "missing" } },
+                ],
+            },
+        }))
+    }
+
+    fn status_of<'g>(graph: &'g DependencyGraph, field: &str) -> Option<&'g Reference> {
+        graph
+            .references()
+            .iter()
+            .find(|reference| reference.field == field)
+    }
+
+    #[test]
+    fn references_to_kinds_not_provided_are_unchecked() {
+        let vs = reviews();
+        let graph = DependencyGraph::builder().virtual_services([&vs]).build();
+        // The mesh gateway is not a reference.
+        assert!(status_of(&graph, "spec.gateways[0]").is_none());
+        assert_eq!(
+            status_of(&graph, "spec.gateways[1]").unwrap().status,
+            ReferenceStatus::Unchecked
+        );
+        assert_eq!(
+            status_of(&graph, "spec.http[0].route[0].destination.subset")
+                .unwrap()
+                .status,
+            ReferenceStatus::Unchecked
+        );
+        // VirtualServices are always provided, so delegates are checked.
+        assert_eq!(
+            status_of(&graph, "spec.http[1].delegate").unwrap().status,
+            ReferenceStatus::Dangling
+        );
+    }
+
+    #[test]
+    fn resolves_gateways_subsets_and_credentials() {
+        let vs = reviews();
+        let dr: DestinationRule = resource(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": { "host": "reviews", "subsets": [{ "name": "v1", "labels": { "version": "v1" } }] },
+        }));
+        let gateway: Gateway = resource(json!({
+            "metadata": { "name": "ingress", "namespace": "default" },
+            "spec": {
+                "selector": { "istio": "ingressgateway" },
+                "servers": [{
+                    "port": { "number": 443, "name": "https", "protocol": "HTTPS" },
+                    "hosts": ["*"],
+                    "tls": { "mode": "SIMPLE", "credentialName": "reviews-cert" },
+                }],
+            },
+        }));
+        let graph = DependencyGraph::builder()
+            .virtual_services([&vs])
+            .destination_rules([&dr])
+            .gateways([&gateway])
+            .secrets([])
+            .build();
+
+        let resolved = |field| status_of(&graph, field).unwrap().status;
+        assert_eq!(resolved("spec.gateways[1]"), ReferenceStatus::Resolved);
+        assert_eq!(resolved("spec.gateways[2]"), ReferenceStatus::Dangling);
+        assert_eq!(
+            resolved("spec.http[0].route[0].destination.subset"),
+            ReferenceStatus::Resolved
+        );
+        assert_eq!(
+            resolved("spec.http[0].route[1].destination.subset"),
+            ReferenceStatus::Dangling
+        );
+        assert_eq!(
+            resolved("spec.servers[0].tls.credentialName"),
+            ReferenceStatus::Dangling
+        );
+
+        let dr_ref =
+            ObjectRef::new::<DestinationRule>(ResourceRef::new(Some("default"), "reviews"));
+        let dependents: Vec<&str> = graph
+            .dependents(&dr_ref)
+            .map(|reference| reference.field.as_str())
+            .collect();
+        assert_eq!(dependents, ["spec.http[0].route[0].destination.subset"]);
+        assert_eq!(graph.dangling().count(), 4);
+        assert_eq!(
+            graph.dangling().next().unwrap().to_string(),
+            "VirtualService default/reviews spec.gateways[2] references Gateway other/edge (Dangling)"
+        );
+    }
+}
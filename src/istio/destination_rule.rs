@@ -1,6 +1,3 @@
-use crate::istio::load_balancer_settings::{ConsistentHashLB, SimpleLB};
-use crate::istio::traffic_policy::PortTrafficPolicy;
-use k8s_openapi::{Metadata, Resource};
 /// # Destination Rule
 /// DestinationRule defines policies that apply to traffic intended for a service after routing has occurred. These rules specify configuration for load balancing, connection pool size from the sidecar, and outlier detection settings to detect and evict unhealthy hosts from the load balancing pool. For example, a simple load balancing policy for the ratings service would look as follows:
 /// ```yaml
@@ -54,11 +51,19 @@ use k8s_openapi::{Metadata, Resource};
 ///       loadBalancer:
 ///         simple: ROUND_ROBIN
 /// ```
-use std::collections::HashMap;
-use std::time::Duration;
+use crate::istio::duration::IstioDuration;
+use crate::istio::export_to::ExportTo;
+use crate::istio::load_balancer_settings::{ConsistentHashLB, SimpleLB};
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::status::IstioStatus;
+use crate::istio::traffic_policy::{PortTrafficPolicy, ProxyProtocol, TunnelSettings};
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DestinationRule {
     /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
     pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
@@ -67,7 +72,7 @@ pub struct DestinationRule {
     pub spec: Option<DestinationRuleSpec>,
 
     /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
-    pub status: Option<()>,
+    pub status: Option<IstioStatus>,
 }
 
 impl Resource for DestinationRule {
@@ -94,7 +99,8 @@ impl Metadata for DestinationRule {
 /// # DestinationRuleSpec
 /// DestinationRule defines policies that apply to traffic intended for a service after routing has occurred.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DestinationRuleSpec {
     // The name of a service from the service registry.Service names are looked up from the platform’s service registry (e.g.,
     // Kubernetes services,
@@ -116,7 +122,7 @@ pub struct DestinationRuleSpec {
     // outlier detection).
     // Required: No
     #[serde(rename = "trafficPolicy")]
-    pub traffic_policy: TrafficPolicy,
+    pub traffic_policy: Option<TrafficPolicy>,
 
     // One or more named sets that represent individual versions of a service.Traffic policies can be overridden at subset level.
     // Required: No
@@ -130,14 +136,25 @@ pub struct DestinationRuleSpec {
     // the value “ * ” is reserved and defines an export to all namespaces.
     // Required: No
     #[serde(rename = "exportTo")]
-    pub export_to: Option<Vec<String>>,
+    pub export_to: Option<Vec<ExportTo>>,
+
+    // Criteria used to select the specific set of pods/VMs on which this DestinationRule configuration should be applied. If specified, the DestinationRule configuration will be applied only to the workload instances matching the workload selector label in the same namespace. Workload selectors do not apply across namespace boundaries. If omitted, the DestinationRule falls back to its default behavior. For example, if specific sidecars need to have egress TLS settings for services outside of the mesh, instead of every sidecar in the mesh needing to have the configuration (which is the default behaviour), a workload selector can be specified.
+    // Required: No
+    #[serde(rename = "workloadSelector")]
+    pub workload_selector: Option<WorkloadSelector>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// # TrafficPolicy
 ///
 /// Traffic policies to apply for a specific destination, across all destination ports. See DestinationRule for examples.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TrafficPolicy {
     // Settings controlling the load balancer algorithms.
     // Required: No
@@ -163,6 +180,20 @@ pub struct TrafficPolicy {
     // Required: No
     #[serde(rename = "portLevelSettings")]
     pub port_level_settings: Option<Vec<PortTrafficPolicy>>,
+
+    // Configuration of tunneling TCP over other transport or application layers for the host configured in the DestinationRule. Tunnel settings can be applied to TCP or TLS routes and can't be applied to HTTP routes.
+    // Required: No
+    pub tunnel: Option<TunnelSettings>,
+
+    // The upstream PROXY protocol settings.
+    // Required: No
+    #[serde(rename = "proxyProtocol")]
+    pub proxy_protocol: Option<ProxyProtocol>,
+
+    // Fields of the policy unknown to this crate, kept as is like the `extra` of the spec.
+    // Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// # Subset
@@ -189,7 +220,8 @@ pub struct TrafficPolicy {
 ///
 /// One or more labels are typically required to identify the subset destination, however, when the corresponding DestinationRule represents a host that supports multiple SNI hosts (e.g., an egress gateway), a subset without labels may be meaningful. In this case a traffic policy with ClientTLSSettings can be used to identify a specific SNI host corresponding to the named subset.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Subset {
     // Name of the subset.The service name and the subset name can be used for traffic splitting in a route rule.
     // Yes
@@ -197,12 +229,17 @@ pub struct Subset {
 
     // Labels apply a filter over the endpoints of a service in the service registry.See route rules for examples of usage.
     // No
-    pub labels: HashMap<String, String>,
+    pub labels: Option<Map<String, String>>,
 
     // Traffic policies that apply to this subset.Subsets inherit the traffic policies specified at the DestinationRule level.Settings specified at the subset level will override the corresponding settings specified at the DestinationRule level.
     // No
     #[serde(rename = "trafficPolicy")]
-    pub traffic_policy: TrafficPolicy,
+    pub traffic_policy: Option<TrafficPolicy>,
+
+    // Fields of the subset unknown to this crate, kept as is like the `extra` of the spec.
+    // Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// # LoadBalancerSettings
@@ -235,25 +272,92 @@ pub struct Subset {
 ///           name: user
 ///           ttl: 0s
 /// ```
+///
+/// `localityLbSetting` and `warmupDurationSecs` sit next to the policy in the same object, and the
+/// policy may be left out to only tune locality load balancing. Setting both `simple` and
+/// `consistentHash` is rejected, as is an invalid value for either of them.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum LoadBalancerSettings {
-    Simple {
-        simple: SimpleLB,
-        // Locality load balancer settings,
-        // this will override mesh wide settings in entirety,
-        // meaning no merging would be performed between this object and the object one in MeshConfig
-        // Required: No
-        localityLbSetting: LocalityLoadBalancerSetting,
-    },
-    ConsistentHash {
-        consistentHash: ConsistentHashLB,
-        // Locality load balancer settings,
-        // this will override mesh wide settings in entirety,
-        // meaning no merging would be performed between this object and the object one in MeshConfig
-        // Required: No
-        localityLbSetting: LocalityLoadBalancerSetting,
-    },
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "RawLoadBalancerSettings")]
+pub struct LoadBalancerSettings {
+    // The load balancing policy, either simple or consistent hash.
+    // Required: No
+    #[serde(flatten)]
+    pub lb_policy: Option<LbPolicy>,
+
+    // Locality load balancer settings,
+    // this will override mesh wide settings in entirety,
+    // meaning no merging would be performed between this object and the object one in MeshConfig
+    // Required: No
+    #[serde(rename = "localityLbSetting")]
+    pub locality_lb_setting: Option<LocalityLoadBalancerSetting>,
+
+    // Represents the warmup duration of Service. If set, the newly created endpoint of service remains in warmup mode starting from its creation time for the duration of this window and Istio progressively increases amount of traffic for that endpoint instead of sending proportional amount of traffic. This should be enabled for services that require warm up time to serve full production load with reasonable latency. Only supported with ROUND_ROBIN and LEAST_REQUEST.
+    // Required: No
+    #[serde(rename = "warmupDurationSecs")]
+    pub warmup_duration_secs: Option<IstioDuration>,
+}
+
+impl LoadBalancerSettings {
+    /// A simple load balancing policy without locality or warmup settings.
+    pub fn simple(simple: SimpleLB) -> Self {
+        LoadBalancerSettings {
+            lb_policy: Some(LbPolicy::Simple(simple)),
+            ..Default::default()
+        }
+    }
+
+    /// A consistent hash policy without locality or warmup settings.
+    pub fn consistent_hash(consistent_hash: ConsistentHashLB) -> Self {
+        LoadBalancerSettings {
+            lb_policy: Some(LbPolicy::ConsistentHash(consistent_hash)),
+            ..Default::default()
+        }
+    }
+}
+
+/// `LoadBalancerSettings` as written, with both policies as plain keys so that an invalid policy
+/// is an error rather than a missing one.
+#[derive(Deserialize)]
+struct RawLoadBalancerSettings {
+    simple: Option<SimpleLB>,
+    #[serde(rename = "consistentHash")]
+    consistent_hash: Option<ConsistentHashLB>,
+    #[serde(rename = "localityLbSetting")]
+    locality_lb_setting: Option<LocalityLoadBalancerSetting>,
+    #[serde(rename = "warmupDurationSecs")]
+    warmup_duration_secs: Option<IstioDuration>,
+}
+
+impl TryFrom<RawLoadBalancerSettings> for LoadBalancerSettings {
+    type Error = &'static str;
+
+    fn try_from(raw: RawLoadBalancerSettings) -> Result<Self, Self::Error> {
+        let lb_policy = match (raw.simple, raw.consistent_hash) {
+            (Some(_), Some(_)) => return Err("only one of simple or consistentHash can be set"),
+            (Some(simple), None) => Some(LbPolicy::Simple(simple)),
+            (None, Some(consistent_hash)) => Some(LbPolicy::ConsistentHash(consistent_hash)),
+            (None, None) => None,
+        };
+        Ok(LoadBalancerSettings {
+            lb_policy,
+            locality_lb_setting: raw.locality_lb_setting,
+            warmup_duration_secs: raw.warmup_duration_secs,
+        })
+    }
+}
+
+/// # LoadBalancerSettings.lb_policy
+/// The load balancing algorithm, serialized as the `simple` or `consistentHash` key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LbPolicy {
+    #[serde(rename = "simple")]
+    Simple(SimpleLB),
+
+    #[serde(rename = "consistentHash")]
+    ConsistentHash(ConsistentHashLB),
 }
 
 /// # ConnectionPoolSettings
@@ -277,7 +381,8 @@ pub enum LoadBalancerSettings {
 ///           interval: 75s
 /// ```
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ConnectionPoolSettings {
     // Settings common to both HTTP and TCP upstream connections.
     // Required: No
@@ -312,7 +417,8 @@ pub struct ConnectionPoolSettings {
 ///       baseEjectionTime: 15m
 /// ```
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct OutlierDetection {
     // Determines whether to distinguish local origin failures from external errors.If set to true consecutivelocalorigin_failure is taken into account for outlier detection calculations.This should be used when you want to derive the outlier detection status based on the errors seen locally such as failure to connect,
     // timeout while connecting etc.rather than the status code retuned by upstream service.This is especially useful when the upstream service explicitly returns a 5xx for some requests and you want to ignore those responses from upstream service while determining the outlier detection status of a host.Defaults to false.
@@ -347,14 +453,19 @@ pub struct OutlierDetection {
     #[serde(rename = "consecutive5xxErrors")]
     pub consecutive5xx_errors: Option<super::google::protobuf::UInt32Value>,
 
+    // Deprecated, use consecutiveGatewayErrors or consecutive5xxErrors instead. Number of 5xx errors before a host is ejected from the connection pool. Kept so that older manifests can still be read, see `OutlierDetection::consecutive_5xx_errors`.
+    // No
+    #[serde(rename = "consecutiveErrors")]
+    pub consecutive_errors: Option<i32>,
+
     // Time interval between ejection sweep analysis.format: 1h / 1m / 1s / 1ms.MUST BE > = 1ms.Default is 10s.
     // No
-    pub interval: Option<Duration>,
+    pub interval: Option<IstioDuration>,
 
     // Minimum ejection duration.A host will remain ejected for a period equal to the product of minimum ejection duration and the number of times the host has been ejected.This technique allows the system to automatically increase the ejection period for unhealthy upstream servers.format: 1h / 1m / 1s / 1ms.MUST BE > = 1ms.Default is 30s.
     // No
     #[serde(rename = "baseEjectionTime")]
-    pub base_ejection_time: Option<Duration>,
+    pub base_ejection_time: Option<IstioDuration>,
 
     // Maximum % of hosts in the load balancing pool for the upstream service that can be ejected.Defaults to 10 %.
     // No
@@ -368,6 +479,20 @@ pub struct OutlierDetection {
     pub min_health_percent: Option<i32>,
 }
 
+impl OutlierDetection {
+    /// The number of 5xx errors before ejection, falling back to the deprecated `consecutiveErrors`
+    /// when `consecutive5xxErrors` is not set.
+    pub fn consecutive_5xx_errors(&self) -> Option<u32> {
+        self.consecutive5xx_errors
+            .as_ref()
+            .and_then(|errors| errors.value)
+            .or_else(|| {
+                self.consecutive_errors
+                    .and_then(|errors| u32::try_from(errors).ok())
+            })
+    }
+}
+
 /// # ClientTLSSettings
 /// SSL/TLS related settings for upstream connections. See Envoy’s TLS context for more details. These settings are common to both HTTP and TCP upstreams.
 ///
@@ -413,7 +538,8 @@ pub struct OutlierDetection {
 ///       mode: ISTIO_MUTUAL
 /// ```
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ClientTLSSettings {
     // Indicates whether connections to this port should be secured using TLS.The value of this field determines how TLS is enforced.
     // Yes
@@ -495,7 +621,8 @@ pub struct ClientTLSSettings {
 /// ```
 /// Locality load balancing settings.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LocalityLoadBalancerSetting {
     // Optional: only one of distribute, failover or failoverPriority can be set. Explicitly specify loadbalancing weight across different zones and geographical locations. Refer to Locality weighted load balancing If empty, the locality weight is set according to the endpoints number within it.
     // Required: No
@@ -545,3 +672,207 @@ pub struct LocalityLoadBalancerSetting {
     // Required: No
     pub enabled: Option<bool>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn load_balancer(value: Value) -> Result<LoadBalancerSettings, serde_json::Error> {
+        serde_json::from_value(value)
+    }
+
+    #[test]
+    fn reads_simple_and_consistent_hash_policies() {
+        let simple = load_balancer(json!({ "simple": "LEAST_REQUEST" })).unwrap();
+        assert_eq!(
+            simple,
+            LoadBalancerSettings::simple(SimpleLB::LEAST_REQUEST)
+        );
+        let unspecified = load_balancer(json!({ "simple": "UNSPECIFIED" })).unwrap();
+        assert_eq!(
+            unspecified.lb_policy,
+            Some(LbPolicy::Simple(SimpleLB::UNSPECIFIED))
+        );
+
+        let value = json!({ "consistentHash": { "httpHeaderName": "x-user" } });
+        let consistent_hash = load_balancer(value.clone()).unwrap();
+        assert!(matches!(
+            consistent_hash.lb_policy,
+            Some(LbPolicy::ConsistentHash(_))
+        ));
+        assert_eq!(serde_json::to_value(&consistent_hash).unwrap(), value);
+
+        let locality_only = load_balancer(json!({ "warmupDurationSecs": "60s" })).unwrap();
+        assert_eq!(locality_only.lb_policy, None);
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        for value in [
+            json!({ "simple": "LEAST_REQUESTS" }),
+            json!({ "simple": "round_robin" }),
+            json!({ "consistentHash": { "httpCookie": { "name": "user", "ttl": 30 } } }),
+            json!({ "consistentHash": { "httpCookie": { "ttl": "0s" } } }),
+        ] {
+            assert!(load_balancer(value.clone()).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_policies() {
+        let error = load_balancer(json!({
+            "simple": "ROUND_ROBIN",
+            "consistentHash": { "useSourceIp": true },
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "only one of simple or consistentHash can be set"
+        );
+    }
+
+    #[test]
+    fn outlier_detection_counts_are_plain_numbers() {
+        let value = json!({ "consecutive5xxErrors": 7, "consecutiveGatewayErrors": 0 });
+        let outlier: OutlierDetection = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(outlier.consecutive_5xx_errors(), Some(7));
+        assert_eq!(
+            outlier.consecutive_gateway_errors,
+            Some(super::super::google::protobuf::UInt32Value { value: Some(0) })
+        );
+        assert_eq!(serde_json::to_value(&outlier).unwrap(), value);
+        assert!(serde_json::from_value::<OutlierDetection>(
+            json!({ "consecutive5xxErrors": { "value": 7 } })
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn workload_selector_round_trips() {
+        let selected = json!({
+            "host": "ratings.prod.svc.cluster.local",
+            "workloadSelector": { "matchLabels": { "app": "ratings" } },
+        });
+        let spec: DestinationRuleSpec = serde_json::from_value(selected.clone()).unwrap();
+        let labels = spec
+            .workload_selector
+            .as_ref()
+            .unwrap()
+            .match_labels
+            .as_ref();
+        assert_eq!(
+            labels
+                .and_then(|labels| labels.get("app"))
+                .map(String::as_str),
+            Some("ratings")
+        );
+        assert!(spec.extra.is_empty());
+        assert_eq!(serde_json::to_value(&spec).unwrap(), selected);
+
+        let omitted = json!({ "host": "ratings.prod.svc.cluster.local" });
+        let spec: DestinationRuleSpec = serde_json::from_value(omitted.clone()).unwrap();
+        assert_eq!(spec.workload_selector, None);
+        assert_eq!(serde_json::to_value(&spec).unwrap(), omitted);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn reads_the_istio_documentation_examples() {
+        let subsets = DestinationRule::from_yaml_str(
+            r#"
+apiVersion: networking.istio.io/v1beta1
+kind: DestinationRule
+metadata:
+  name: bookinfo-ratings
+spec:
+  host: ratings.prod.svc.cluster.local
+  trafficPolicy:
+    loadBalancer:
+      simple: LEAST_REQUEST
+  subsets:
+  - name: testversion
+    labels:
+      version: v3
+    trafficPolicy:
+      loadBalancer:
+        simple: ROUND_ROBIN
+"#,
+        )
+        .unwrap();
+        let policy = subsets
+            .spec
+            .as_ref()
+            .unwrap()
+            .traffic_policy
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            policy.load_balancer,
+            Some(LoadBalancerSettings::simple(SimpleLB::LEAST_REQUEST))
+        );
+
+        let cookie = DestinationRule::from_yaml_str(
+            r#"
+apiVersion: networking.istio.io/v1beta1
+kind: DestinationRule
+metadata:
+  name: bookinfo-ratings
+spec:
+  host: ratings.prod.svc.cluster.local
+  trafficPolicy:
+    loadBalancer:
+      consistentHash:
+        httpCookie:
+          name: user
+          ttl: 0s
+"#,
+        )
+        .unwrap();
+        let load_balancer = cookie
+            .spec
+            .unwrap()
+            .traffic_policy
+            .unwrap()
+            .load_balancer
+            .unwrap();
+        assert!(matches!(
+            load_balancer.lb_policy,
+            Some(LbPolicy::ConsistentHash(ConsistentHashLB {
+                hash_key: crate::istio::load_balancer_settings::HashKey::HttpCookie(_),
+                ..
+            }))
+        ));
+
+        let circuit_breaker = DestinationRule::from_yaml_str(
+            r#"
+apiVersion: networking.istio.io/v1beta1
+kind: DestinationRule
+metadata:
+  name: reviews-cb-policy
+spec:
+  host: reviews.prod.svc.cluster.local
+  trafficPolicy:
+    connectionPool:
+      tcp:
+        maxConnections: 100
+      http:
+        http2MaxRequests: 1000
+        maxRequestsPerConnection: 10
+    outlierDetection:
+      consecutive5xxErrors: 7
+      interval: 5m
+      baseEjectionTime: 15m
+"#,
+        )
+        .unwrap();
+        let outlier = circuit_breaker
+            .spec
+            .unwrap()
+            .traffic_policy
+            .unwrap()
+            .outlier_detection
+            .unwrap();
+        assert_eq!(outlier.consecutive_5xx_errors(), Some(7));
+    }
+}
@@ -0,0 +1,160 @@
+// Semantic diff of serializable resources. Values are compared in their serialized (JSON) form, so
+// the reported paths use the field names of the Istio API, e.g. `spec.http[0].route[1].weight`.
+use k8s_openapi::serde_json::{Map, Value};
+use serde::Serialize;
+use std::fmt;
+
+/// # ChangeKind
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// # Change
+/// A single changed field, identified by its path in the serialized resource.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| value.as_ref().map(Value::to_string).unwrap_or_default();
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {}: {}", self.path, show(&self.new)),
+            ChangeKind::Removed => write!(f, "- {}: {}", self.path, show(&self.old)),
+            ChangeKind::Modified => write!(
+                f,
+                "~ {}: {} -> {}",
+                self.path,
+                show(&self.old),
+                show(&self.new)
+            ),
+        }
+    }
+}
+
+/// Compares two serializable values and returns the changed fields. Values that fail to serialize
+/// are compared as `null`.
+pub fn diff<T: Serialize + ?Sized>(old: &T, new: &T) -> Vec<Change> {
+    let old = k8s_openapi::serde_json::to_value(old).unwrap_or(Value::Null);
+    let new = k8s_openapi::serde_json::to_value(new).unwrap_or(Value::Null);
+    diff_values(&old, &new)
+}
+
+/// Compares two JSON values. Objects are compared key by key and arrays element by element, so a
+/// changed list item is reported at its index instead of as a change of the whole list.
+pub fn diff_values(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    walk("", old, new, &mut changes);
+    changes
+}
+
+/// Builds a JSON merge patch (RFC 7386) turning `old` into `new`. Arrays are replaced as a whole, as
+/// mandated by the merge patch format. Returns `None` when both values are equal.
+pub fn merge_patch(old: &Value, new: &Value) -> Option<Value> {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for (key, old_value) in old {
+                match new.get(key) {
+                    Some(new_value) => {
+                        if let Some(value) = merge_patch(old_value, new_value) {
+                            patch.insert(key.clone(), value);
+                        }
+                    }
+                    None => {
+                        patch.insert(key.clone(), Value::Null);
+                    }
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    patch.insert(key.clone(), new_value.clone());
+                }
+            }
+            if patch.is_empty() {
+                None
+            } else {
+                Some(Value::Object(patch))
+            }
+        }
+        (old, new) if old == new => None,
+        (_, new) => Some(new.clone()),
+    }
+}
+
+fn walk(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = field_path(path, key);
+                match new.get(key) {
+                    Some(new_value) => walk(&child, old_value, new_value, changes),
+                    None => changes.push(removed(child, old_value)),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push(added(field_path(path, key), new_value));
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let child = format!("{}[{}]", path, index);
+                match (old.get(index), new.get(index)) {
+                    (Some(old_value), Some(new_value)) => {
+                        walk(&child, old_value, new_value, changes)
+                    }
+                    (Some(old_value), None) => changes.push(removed(child, old_value)),
+                    (None, Some(new_value)) => changes.push(added(child, new_value)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (old, new) if old == new => {}
+        (Value::Null, new) => changes.push(added(path.to_string(), new)),
+        (old, Value::Null) => changes.push(removed(path.to_string(), old)),
+        (old, new) => changes.push(Change {
+            path: path.to_string(),
+            kind: ChangeKind::Modified,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+    }
+}
+
+fn field_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+fn added(path: String, value: &Value) -> Change {
+    Change {
+        path,
+        kind: ChangeKind::Added,
+        old: None,
+        new: Some(value.clone()),
+    }
+}
+
+fn removed(path: String, value: &Value) -> Change {
+    Change {
+        path,
+        kind: ChangeKind::Removed,
+        old: Some(value.clone()),
+        new: None,
+    }
+}
@@ -0,0 +1,282 @@
+// The `google.protobuf.Duration` fields of the resources. Istio reads them from Go-style strings such
+// as `30s`, `5m` or `250ms`; `std::time::Duration` would serialize as `{ secs, nanos }`, which the
+// API server rejects, so the fields use `IstioDuration` instead. The protobuf object form
+// `{ seconds, nanos }` written by some tooling is accepted as well, but never emitted.
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// # IstioDuration
+/// A duration serialized in Go notation with the largest unit that represents it exactly, e.g. `1h`,
+/// `90s` or `250ms`. Deserializes from such a string or from `{ "seconds": 30, "nanos": 0 }`.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+#[serde(try_from = "LegacyDuration", into = "String")]
+pub struct IstioDuration(pub Duration);
+
+impl IstioDuration {
+    pub const fn from_secs(secs: u64) -> Self {
+        IstioDuration(Duration::from_secs(secs))
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        IstioDuration(Duration::from_millis(millis))
+    }
+
+    pub const fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Deref for IstioDuration {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl From<Duration> for IstioDuration {
+    fn from(duration: Duration) -> Self {
+        IstioDuration(duration)
+    }
+}
+
+impl From<IstioDuration> for Duration {
+    fn from(duration: IstioDuration) -> Self {
+        duration.0
+    }
+}
+
+impl fmt::Display for IstioDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: &[(&str, u128)] = &[
+            ("h", 3_600_000_000_000),
+            ("m", 60_000_000_000),
+            ("s", 1_000_000_000),
+            ("ms", 1_000_000),
+            ("us", 1_000),
+        ];
+        let nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return write!(f, "0s");
+        }
+        match UNITS.iter().find(|(_, unit)| nanos.is_multiple_of(*unit)) {
+            Some((name, unit)) => write!(f, "{}{}", nanos / unit, name),
+            None => write!(f, "{}ns", nanos),
+        }
+    }
+}
+
+impl FromStr for IstioDuration {
+    type Err = DurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s)
+            .map(IstioDuration)
+            .ok_or_else(|| DurationError(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for IstioDuration {
+    type Error = DurationError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// The wire formats of a duration: a Go-style string or the protobuf object, whose `seconds` may be
+/// quoted as protobuf JSON does for 64-bit integers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyDuration {
+    Text(String),
+    Proto {
+        seconds: Option<ProtoSeconds>,
+        nanos: Option<u32>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProtoSeconds {
+    Number(u64),
+    Text(String),
+}
+
+impl TryFrom<LegacyDuration> for IstioDuration {
+    type Error = DurationError;
+
+    fn try_from(value: LegacyDuration) -> Result<Self, Self::Error> {
+        match value {
+            LegacyDuration::Text(text) => text.parse(),
+            LegacyDuration::Proto { seconds, nanos } => {
+                let seconds = match seconds {
+                    None => 0,
+                    Some(ProtoSeconds::Number(seconds)) => seconds,
+                    Some(ProtoSeconds::Text(text)) => text
+                        .parse()
+                        .map_err(|_| DurationError(format!("{} seconds", text)))?,
+                };
+                let nanos = nanos.unwrap_or_default();
+                if nanos >= 1_000_000_000 {
+                    return Err(DurationError(format!("{} nanos", nanos)));
+                }
+                Ok(IstioDuration(Duration::new(seconds, nanos)))
+            }
+        }
+    }
+}
+
+impl From<IstioDuration> for String {
+    fn from(duration: IstioDuration) -> Self {
+        duration.to_string()
+    }
+}
+
+/// Serialized as a plain string.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for IstioDuration {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "IstioDuration".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// # DurationError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DurationError(pub String);
+
+impl fmt::Display for DurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid duration {}", self.0)
+    }
+}
+
+impl std::error::Error for DurationError {}
+
+/// Parses a duration in Go notation, a sequence of decimal numbers with a unit each (`ns`, `us`,
+/// `µs`, `ms`, `s`, `m`, `h`), e.g. `1h30m` or `1.5s`, or a unitless `0` as Go accepts it. `None`
+/// when the duration does not fit in `u64` nanoseconds, about 584 years.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    if rest == "0" {
+        return Some(Duration::ZERO);
+    }
+    let mut nanos = 0u64;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = &rest[number_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_end] {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None,
+        };
+        rest = &rest[unit_end..];
+        let value = (number * unit).round();
+        // `u64::MAX as f64` rounds up to 2^64, so this excludes every value out of range; the number
+        // has digits and dots only, so it is never NaN.
+        if value >= u64::MAX as f64 {
+            return None;
+        }
+        nanos = nanos.checked_add(value as u64)?;
+    }
+    Some(Duration::from_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    #[test]
+    fn writes_the_largest_exact_unit_and_reads_it_back() {
+        let cases = [
+            (Duration::ZERO, "0s"),
+            (Duration::from_secs(7200), "2h"),
+            (Duration::from_secs(90), "90s"),
+            (Duration::from_millis(250), "250ms"),
+            (Duration::from_micros(1500), "1500us"),
+            (Duration::from_nanos(7), "7ns"),
+        ];
+        for (duration, text) in cases {
+            let duration = IstioDuration(duration);
+            assert_eq!(duration.to_string(), text);
+            assert_eq!(text.parse::<IstioDuration>(), Ok(duration));
+            assert_eq!(serde_json::to_value(duration).unwrap(), json!(text));
+        }
+    }
+
+    #[test]
+    fn parses_go_notation() {
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("10µs"), Some(Duration::from_micros(10)));
+        assert_eq!(parse_duration(" 5m "), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("0s"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for text in ["", "5", "s", "1d", "1.2.3s", "-1s", "1s 2s"] {
+            assert_eq!(parse_duration(text), None, "{}", text);
+        }
+        assert_eq!(
+            "1d".parse::<IstioDuration>().unwrap_err().to_string(),
+            "invalid duration 1d"
+        );
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert_eq!(parse_duration("1000000000h1s"), None);
+        assert_eq!(
+            parse_duration("5124095h"),
+            Some(Duration::from_secs(5124095 * 3600))
+        );
+        assert_eq!(parse_duration("5124095h5124095h"), None);
+        assert_eq!(parse_duration("18446744073709551616ns"), None);
+    }
+
+    #[test]
+    fn reads_the_protobuf_object_form() {
+        let duration: IstioDuration =
+            serde_json::from_value(json!({ "seconds": 30, "nanos": 500_000_000 })).unwrap();
+        assert_eq!(duration, IstioDuration(Duration::from_millis(30_500)));
+
+        let duration: IstioDuration = serde_json::from_value(json!({ "seconds": "60" })).unwrap();
+        assert_eq!(duration, IstioDuration::from_secs(60));
+
+        let duration: IstioDuration = serde_json::from_value(json!({ "nanos": 5 })).unwrap();
+        assert_eq!(duration.to_string(), "5ns");
+
+        assert!(
+            serde_json::from_value::<IstioDuration>(json!({ "nanos": 1_000_000_000 })).is_err()
+        );
+        assert!(serde_json::from_value::<IstioDuration>(json!({ "seconds": "soon" })).is_err());
+    }
+}
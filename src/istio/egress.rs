@@ -0,0 +1,599 @@
+// Egress reachability per namespace. Under the REGISTRY_ONLY outbound traffic policy a sidecar only
+// forwards traffic to hosts it knows, so the external hosts a namespace can reach are the hosts of
+// the MESH_EXTERNAL ServiceEntries exported to the namespace and imported by the egress listeners
+// of its Sidecar. The analysis only covers namespace-wide Sidecars: a Sidecar with a workload
+// selector narrows the hosts further for the pods it selects.
+use crate::istio::export_to::is_exported;
+use crate::istio::host::Host;
+use crate::istio::mesh_config::MeshConfig;
+use crate::istio::patch_order::DEFAULT_ROOT_NAMESPACE;
+use crate::istio::service_entry::{Location, ServiceEntry};
+use crate::istio::sidecar::{OutboundTrafficMode, Sidecar};
+use crate::istio::topology::{creation_order, ResourceRef};
+use crate::istio::virtual_service::VirtualService;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// # ExternalHost
+/// An external host a namespace can reach.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExternalHost {
+    pub host: String,
+
+    /// The ServiceEntry declaring the host.
+    #[serde(rename = "serviceEntry")]
+    pub service_entry: ResourceRef,
+
+    /// The ports of the ServiceEntry; empty when it declares none.
+    pub ports: Vec<u32>,
+
+    /// VirtualServices visible in the namespace that route the host, e.g. through an egress
+    /// gateway.
+    #[serde(rename = "routedBy")]
+    pub routed_by: Vec<ResourceRef>,
+}
+
+/// # BlockedHost
+/// An external host a namespace cannot reach under REGISTRY_ONLY.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "reason")]
+pub enum BlockedHost {
+    /// The ServiceEntry of the host is not exported to the namespace.
+    NotExported {
+        host: String,
+        #[serde(rename = "serviceEntry")]
+        service_entry: ResourceRef,
+    },
+
+    /// The Sidecar of the namespace does not import the host.
+    NotImported {
+        host: String,
+        #[serde(rename = "serviceEntry")]
+        service_entry: ResourceRef,
+        sidecar: ResourceRef,
+    },
+
+    /// A VirtualService routes the host, but no ServiceEntry visible in the namespace declares it.
+    Unregistered {
+        host: String,
+        #[serde(rename = "virtualService")]
+        virtual_service: ResourceRef,
+    },
+}
+
+impl fmt::Display for BlockedHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockedHost::NotExported {
+                host,
+                service_entry,
+            } => write!(
+                f,
+                "{}: ServiceEntry {} is not exported",
+                host, service_entry
+            ),
+            BlockedHost::NotImported { host, sidecar, .. } => {
+                write!(f, "{}: not imported by Sidecar {}", host, sidecar)
+            }
+            BlockedHost::Unregistered {
+                host,
+                virtual_service,
+            } => write!(
+                f,
+                "{}: routed by VirtualService {} but has no ServiceEntry",
+                host, virtual_service
+            ),
+        }
+    }
+}
+
+/// # NamespaceEgress
+/// The egress of the workloads of a namespace.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NamespaceEgress {
+    pub namespace: String,
+
+    /// The effective outbound traffic policy. Under ALLOW_ANY, hosts without a ServiceEntry are
+    /// reachable too and nothing is reported as blocked.
+    pub mode: OutboundTrafficMode,
+
+    /// The Sidecar scoping the namespace: its own namespace-wide one, or the one of the root
+    /// namespace. `None` when all hosts are imported.
+    pub sidecar: Option<ResourceRef>,
+
+    pub reachable: Vec<ExternalHost>,
+    pub blocked: Vec<BlockedHost>,
+}
+
+/// # EgressAnalyzer
+#[derive(Clone, Debug, Default)]
+pub struct EgressAnalyzer<'a> {
+    sidecars: Vec<&'a Sidecar>,
+    service_entries: Vec<&'a ServiceEntry>,
+    virtual_services: Vec<&'a VirtualService>,
+    namespaces: BTreeSet<String>,
+    mesh_config: Option<&'a MeshConfig>,
+}
+
+impl<'a> EgressAnalyzer<'a> {
+    pub fn new() -> Self {
+        EgressAnalyzer::default()
+    }
+
+    pub fn sidecars(mut self, items: impl IntoIterator<Item = &'a Sidecar>) -> Self {
+        self.sidecars.extend(items);
+        self
+    }
+
+    pub fn service_entries(mut self, items: impl IntoIterator<Item = &'a ServiceEntry>) -> Self {
+        self.service_entries.extend(items);
+        self
+    }
+
+    pub fn virtual_services(mut self, items: impl IntoIterator<Item = &'a VirtualService>) -> Self {
+        self.virtual_services.extend(items);
+        self
+    }
+
+    /// Namespaces to report on, in addition to the namespaces of the given resources.
+    pub fn namespaces<N: Into<String>>(mut self, items: impl IntoIterator<Item = N>) -> Self {
+        self.namespaces.extend(items.into_iter().map(Into::into));
+        self
+    }
+
+    /// The mesh-wide outbound traffic policy and root namespace. Without it the mesh defaults
+    /// apply: ALLOW_ANY and `istio-system`.
+    pub fn mesh_config(mut self, mesh_config: &'a MeshConfig) -> Self {
+        self.mesh_config = Some(mesh_config);
+        self
+    }
+
+    pub fn analyze(&self) -> Vec<NamespaceEgress> {
+        let root_namespace = self
+            .mesh_config
+            .map_or(DEFAULT_ROOT_NAMESPACE, MeshConfig::root_namespace);
+        let mut namespaces = self.namespaces.clone();
+        namespaces.extend(
+            self.sidecars
+                .iter()
+                .filter_map(|sidecar| sidecar.metadata.namespace.clone())
+                .chain(
+                    self.service_entries
+                        .iter()
+                        .filter_map(|se| se.metadata.namespace.clone()),
+                )
+                .chain(
+                    self.virtual_services
+                        .iter()
+                        .filter_map(|vs| vs.metadata.namespace.clone()),
+                ),
+        );
+        namespaces
+            .iter()
+            .map(|namespace| self.namespace(namespace, root_namespace))
+            .collect()
+    }
+
+    fn namespace(&self, namespace: &str, root_namespace: &str) -> NamespaceEgress {
+        let sidecar = self
+            .namespace_sidecar(namespace)
+            .or_else(|| self.namespace_sidecar(root_namespace));
+        let mesh_registry_only = self.mesh_config.is_some_and(MeshConfig::is_registry_only);
+        let mode = sidecar
+            .and_then(|sidecar| {
+                sidecar
+                    .spec
+                    .as_ref()?
+                    .outbound_traffic_policy
+                    .as_ref()?
+                    .mode
+            })
+            .unwrap_or(if mesh_registry_only {
+                OutboundTrafficMode::REGISTRY_ONLY
+            } else {
+                OutboundTrafficMode::ALLOW_ANY
+            });
+        let sidecar_ref = sidecar.map(|sidecar| ResourceRef::from_metadata(&sidecar.metadata));
+        // The egress hosts of the Sidecar and the Sidecar itself; `None` imports every host.
+        let imports: Option<(Vec<&String>, &ResourceRef)> = sidecar
+            .zip(sidecar_ref.as_ref())
+            .and_then(|(sidecar, sidecar_ref)| {
+                let egress = sidecar.spec.as_ref()?.egress.as_ref()?;
+                let hosts = egress.iter().flat_map(|listener| &listener.hosts).collect();
+                Some((hosts, sidecar_ref))
+            });
+        let visible_virtual_services: Vec<&VirtualService> = self
+            .virtual_services
+            .iter()
+            .copied()
+            .filter(|vs| {
+                let export_to = vs.spec.as_ref().and_then(|spec| spec.export_to.as_deref());
+                is_exported(export_to, vs.metadata.namespace.as_deref(), Some(namespace))
+            })
+            .collect();
+
+        let mut reachable = Vec::new();
+        let mut blocked = Vec::new();
+        let mut registered: Vec<Host> = Vec::new();
+        for se in &self.service_entries {
+            let spec = match &se.spec {
+                Some(spec) => spec,
+                None => continue,
+            };
+            if spec.location.unwrap_or(Location::MESH_EXTERNAL) != Location::MESH_EXTERNAL {
+                continue;
+            }
+            let se_ref = ResourceRef::from_metadata(&se.metadata);
+            let exported = is_exported(
+                spec.export_to.as_deref(),
+                se.metadata.namespace.as_deref(),
+                Some(namespace),
+            );
+            for host in &spec.hosts {
+                let parsed = match Host::new(host) {
+                    Ok(parsed) => parsed,
+                    Err(_) => continue,
+                };
+                let importing_sidecar = imports.as_ref().and_then(|(hosts, sidecar)| {
+                    let owner = se.metadata.namespace.as_deref();
+                    let imported = hosts
+                        .iter()
+                        .any(|import| imports_host(import, namespace, owner, &parsed));
+                    Some(*sidecar).filter(|_| !imported)
+                });
+                if !exported {
+                    blocked.push(BlockedHost::NotExported {
+                        host: host.clone(),
+                        service_entry: se_ref.clone(),
+                    });
+                } else if let Some(sidecar) = importing_sidecar {
+                    blocked.push(BlockedHost::NotImported {
+                        host: host.clone(),
+                        service_entry: se_ref.clone(),
+                        sidecar: sidecar.clone(),
+                    });
+                } else {
+                    registered.push(parsed.clone());
+                    reachable.push(ExternalHost {
+                        host: host.clone(),
+                        service_entry: se_ref.clone(),
+                        ports: spec
+                            .ports
+                            .iter()
+                            .flatten()
+                            .map(|port| port.number)
+                            .collect(),
+                        routed_by: visible_virtual_services
+                            .iter()
+                            .filter(|vs| routes(vs, &parsed))
+                            .map(|vs| ResourceRef::from_metadata(&vs.metadata))
+                            .collect(),
+                    });
+                }
+            }
+        }
+        for vs in &visible_virtual_services {
+            for host in hosts(vs).into_iter().filter(is_external) {
+                if !registered.iter().any(|known| known.matches(&host)) {
+                    blocked.push(BlockedHost::Unregistered {
+                        host: host.to_string(),
+                        virtual_service: ResourceRef::from_metadata(&vs.metadata),
+                    });
+                }
+            }
+        }
+        if mode == OutboundTrafficMode::ALLOW_ANY {
+            blocked.clear();
+        }
+        NamespaceEgress {
+            namespace: namespace.to_string(),
+            mode,
+            sidecar: sidecar_ref,
+            reachable,
+            blocked,
+        }
+    }
+
+    /// The namespace-wide Sidecar of `namespace`. Istio picks the oldest when there are several.
+    fn namespace_sidecar(&self, namespace: &str) -> Option<&'a Sidecar> {
+        self.sidecars
+            .iter()
+            .copied()
+            .filter(|sidecar| sidecar.metadata.namespace.as_deref() == Some(namespace))
+            .filter(|sidecar| {
+                sidecar
+                    .spec
+                    .as_ref()
+                    .is_none_or(|spec| spec.workload_selector.is_none())
+            })
+            .min_by_key(|sidecar| creation_order(&sidecar.metadata))
+    }
+}
+
+/// Whether the egress host `import` (`namespace/dnsName`) of a Sidecar in `namespace` imports
+/// `host`, declared in `owner`. `~` imports nothing, `.` is the namespace of the Sidecar.
+fn imports_host(import: &str, namespace: &str, owner: Option<&str>, host: &Host) -> bool {
+    let (import_namespace, name) = import.split_once('/').unwrap_or(("*", import));
+    let namespace_matches = match import_namespace {
+        "*" => true,
+        "~" => false,
+        "." => owner == Some(namespace),
+        other => owner == Some(other),
+    };
+    namespace_matches && Host::new(name).is_ok_and(|name| name.matches(host) || host.matches(&name))
+}
+
+fn routes(vs: &VirtualService, host: &Host) -> bool {
+    hosts(vs).iter().any(|routed| routed.intersects(host))
+}
+
+/// The valid hosts of `vs`.
+fn hosts(vs: &VirtualService) -> Vec<Host> {
+    vs.spec
+        .iter()
+        .flat_map(|spec| spec.hosts.iter().flatten())
+        .filter_map(|host| Host::new(host).ok())
+        .collect()
+}
+
+/// Whether `host` names a service outside the cluster: a DNS name that is not a Kubernetes
+/// service name (`name.namespace.svc...`) and not a short name.
+fn is_external(host: &Host) -> bool {
+    let name = host.name();
+    name.contains('.')
+        && !name.ends_with(".svc")
+        && !name.contains(".svc.")
+        && !name.ends_with(".local")
+        && name.trim_start_matches("*.").contains('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn resource<T: serde::de::DeserializeOwned>(name: &str, namespace: &str, spec: Value) -> T {
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": namespace },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn registry_only() -> MeshConfig {
+        serde_json::from_value(json!({ "outboundTrafficPolicy": { "mode": "REGISTRY_ONLY" } }))
+            .unwrap()
+    }
+
+    fn service_entries() -> Vec<ServiceEntry> {
+        vec![
+            resource(
+                "stripe",
+                "shop",
+                json!({
+                    "hosts": ["api.stripe.com"],
+                    "location": "MESH_EXTERNAL",
+                    "ports": [{ "number": 443, "name": "tls", "protocol": "TLS" }],
+                    "exportTo": ["."],
+                }),
+            ),
+            resource(
+                "github",
+                "istio-system",
+                json!({ "hosts": ["github.com"], "resolution": "DNS" }),
+            ),
+            resource(
+                "ratings",
+                "shop",
+                json!({ "hosts": ["ratings.internal"], "location": "MESH_INTERNAL" }),
+            ),
+        ]
+    }
+
+    fn root_sidecar() -> Sidecar {
+        resource(
+            "default",
+            "istio-system",
+            json!({ "egress": [{ "hosts": ["./*", "istio-system/*"] }] }),
+        )
+    }
+
+    fn egress<'a>(report: &'a [NamespaceEgress], namespace: &str) -> &'a NamespaceEgress {
+        report
+            .iter()
+            .find(|egress| egress.namespace == namespace)
+            .unwrap()
+    }
+
+    #[test]
+    fn reports_the_external_hosts_each_namespace_reaches() {
+        let mesh_config = registry_only();
+        let service_entries = service_entries();
+        let sidecars = vec![root_sidecar()];
+        let virtual_services: Vec<VirtualService> = vec![resource(
+            "stripe-egress",
+            "shop",
+            json!({
+                "hosts": ["api.stripe.com"],
+                "exportTo": ["."],
+                "tls": [{
+                    "match": [{ "port": 443, "sniHosts": ["api.stripe.com"] }],
+                    "route": [{ "destination": { "host": "istio-egressgateway.istio-system.svc.cluster.local" } }],
+                }],
+            }),
+        )];
+        let report = EgressAnalyzer::new()
+            .sidecars(&sidecars)
+            .service_entries(&service_entries)
+            .virtual_services(&virtual_services)
+            .namespaces(["web"])
+            .mesh_config(&mesh_config)
+            .analyze();
+        let namespaces: Vec<&str> = report
+            .iter()
+            .map(|egress| egress.namespace.as_str())
+            .collect();
+        assert_eq!(namespaces, vec!["istio-system", "shop", "web"]);
+
+        assert_eq!(
+            serde_json::to_value(egress(&report, "shop")).unwrap(),
+            json!({
+                "namespace": "shop",
+                "mode": "REGISTRY_ONLY",
+                "sidecar": { "namespace": "istio-system", "name": "default" },
+                "reachable": [
+                    {
+                        "host": "api.stripe.com",
+                        "serviceEntry": { "namespace": "shop", "name": "stripe" },
+                        "ports": [443],
+                        "routedBy": [{ "namespace": "shop", "name": "stripe-egress" }],
+                    },
+                    {
+                        "host": "github.com",
+                        "serviceEntry": { "namespace": "istio-system", "name": "github" },
+                        "ports": [],
+                        "routedBy": [],
+                    },
+                ],
+                "blocked": [],
+            })
+        );
+
+        let web = egress(&report, "web");
+        assert_eq!(web.reachable.len(), 1);
+        assert_eq!(web.reachable[0].host, "github.com");
+        assert_eq!(
+            web.blocked,
+            vec![BlockedHost::NotExported {
+                host: "api.stripe.com".to_string(),
+                service_entry: ResourceRef::new(Some("shop"), "stripe"),
+            }]
+        );
+        assert_eq!(
+            serde_json::to_value(&web.blocked[0]).unwrap(),
+            json!({
+                "reason": "NotExported",
+                "host": "api.stripe.com",
+                "serviceEntry": { "namespace": "shop", "name": "stripe" },
+            })
+        );
+    }
+
+    #[test]
+    fn the_namespace_sidecar_overrides_the_root_one() {
+        let mesh_config = registry_only();
+        let service_entries = service_entries();
+        let sidecars: Vec<Sidecar> = vec![
+            root_sidecar(),
+            resource(
+                "default",
+                "shop",
+                json!({ "egress": [{ "hosts": ["./*"] }] }),
+            ),
+            // Sidecars selecting workloads do not scope the namespace.
+            resource(
+                "workload",
+                "shop",
+                json!({
+                    "workloadSelector": { "labels": { "app": "web" } },
+                    "egress": [{ "hosts": ["*/*"] }],
+                }),
+            ),
+        ];
+        let report = EgressAnalyzer::new()
+            .sidecars(&sidecars)
+            .service_entries(&service_entries)
+            .mesh_config(&mesh_config)
+            .analyze();
+        let shop = egress(&report, "shop");
+        assert_eq!(
+            shop.sidecar,
+            Some(ResourceRef::new(Some("shop"), "default"))
+        );
+        let reachable: Vec<&str> = shop
+            .reachable
+            .iter()
+            .map(|host| host.host.as_str())
+            .collect();
+        assert_eq!(reachable, vec!["api.stripe.com"]);
+        assert_eq!(
+            shop.blocked[0].to_string(),
+            "github.com: not imported by Sidecar shop/default"
+        );
+    }
+
+    #[test]
+    fn reports_routed_hosts_without_a_service_entry() {
+        let mesh_config = registry_only();
+        let service_entries = service_entries();
+        let virtual_services: Vec<VirtualService> = vec![resource(
+            "partners",
+            "web",
+            json!({
+                "hosts": ["partner.example.org", "reviews.shop.svc.cluster.local", "reviews"],
+                "http": [{ "route": [{ "destination": { "host": "reviews.shop.svc.cluster.local" } }] }],
+            }),
+        )];
+        let report = EgressAnalyzer::new()
+            .service_entries(&service_entries)
+            .virtual_services(&virtual_services)
+            .mesh_config(&mesh_config)
+            .analyze();
+        // Exported to every namespace, internal hosts are left out.
+        for egress in &report {
+            let unregistered: Vec<&BlockedHost> = egress
+                .blocked
+                .iter()
+                .filter(|blocked| matches!(blocked, BlockedHost::Unregistered { .. }))
+                .collect();
+            assert_eq!(
+                unregistered,
+                vec![&BlockedHost::Unregistered {
+                    host: "partner.example.org".to_string(),
+                    virtual_service: ResourceRef::new(Some("web"), "partners"),
+                }],
+                "{}",
+                egress.namespace
+            );
+            // Without a Sidecar every exported host is imported.
+            assert_eq!(egress.sidecar, None);
+        }
+    }
+
+    #[test]
+    fn allow_any_blocks_nothing() {
+        let service_entries = service_entries();
+        let report = EgressAnalyzer::new()
+            .service_entries(&service_entries)
+            .namespaces(["web"])
+            .analyze();
+        let web = egress(&report, "web");
+        assert_eq!(web.mode, OutboundTrafficMode::ALLOW_ANY);
+        assert!(web.blocked.is_empty());
+        assert_eq!(web.reachable.len(), 1);
+
+        // A Sidecar can relax the policy of the mesh for its namespace.
+        let mesh_config = registry_only();
+        let sidecars: Vec<Sidecar> = vec![resource(
+            "default",
+            "web",
+            json!({ "outboundTrafficPolicy": { "mode": "ALLOW_ANY" } }),
+        )];
+        let report = EgressAnalyzer::new()
+            .sidecars(&sidecars)
+            .service_entries(&service_entries)
+            .mesh_config(&mesh_config)
+            .analyze();
+        assert_eq!(egress(&report, "web").mode, OutboundTrafficMode::ALLOW_ANY);
+        assert!(egress(&report, "web").blocked.is_empty());
+        assert_eq!(
+            egress(&report, "shop").mode,
+            OutboundTrafficMode::REGISTRY_ONLY
+        );
+        assert_eq!(egress(&report, "shop").blocked.len(), 0);
+    }
+}
@@ -0,0 +1,1145 @@
+// Experimental translation of a VirtualService, and optionally the DestinationRule of its hosts,
+// into the Envoy configuration istiod would push for it: a `RouteConfiguration` with one virtual
+// host and a cluster per destination. The output is the JSON of the Envoy v3 API, so a test can
+// start a local Envoy with `Snapshot::bootstrap` and check how requests are routed without a mesh.
+// Endpoints are resolved by DNS from the destination host, as nothing knows the pods of a
+// service outside a cluster. What has no equivalent in a single Envoy, e.g. delegation, gateway
+// and source matches or TLS origination, is reported in `Snapshot::unsupported`.
+use crate::istio::client_tls_settings::TLSmode;
+use crate::istio::destination_rule::{
+    ConnectionPoolSettings, DestinationRule, LbPolicy, LoadBalancerSettings, OutlierDetection,
+    TrafficPolicy,
+};
+use crate::istio::load_balancer_settings::{HashAlgorithm, HashKey, SimpleLB};
+use crate::istio::retry_on;
+use crate::istio::virtual_service::{
+    CorsPolicy, Destination, HeaderOperations, HttpBody, HttpFaultInjection, HttpMatchRequest,
+    HttpRetry, HttpRoute, Percent, StringMatch, VirtualService,
+};
+use crate::istio::weight::Weights;
+use k8s_openapi::serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Port of a destination that does not select one.
+pub const DEFAULT_PORT: u32 = 80;
+
+/// `retryOn` of Istio when a route does not set it.
+pub const DEFAULT_RETRY_ON: &str =
+    "connect-failure,refused-stream,unavailable,cancelled,retriable-status-codes";
+
+const FAULT_FILTER: &str = "envoy.filters.http.fault";
+const FAULT_TYPE: &str = "type.googleapis.com/envoy.extensions.filters.http.fault.v3.HTTPFault";
+const CORS_FILTER: &str = "envoy.filters.http.cors";
+const CORS_TYPE: &str = "type.googleapis.com/envoy.extensions.filters.http.cors.v3.CorsPolicy";
+const ROUTER_FILTER: &str = "envoy.filters.http.router";
+const ROUTER_TYPE: &str = "type.googleapis.com/envoy.extensions.filters.http.router.v3.Router";
+
+/// # Snapshot
+/// The Envoy configuration of a VirtualService.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    /// The `RouteConfiguration`, named after the VirtualService.
+    pub route_configuration: Value,
+
+    /// A `Cluster` per destination, named `outbound|<port>|<subset>|<host>` as in Istio.
+    pub clusters: Vec<Value>,
+
+    /// Paths of the fields that were not translated, e.g. `spec.http[0].delegate`.
+    pub unsupported: Vec<String>,
+}
+
+impl Snapshot {
+    /// A static bootstrap serving the routes on `port` of all addresses, with the fault and CORS
+    /// filters the routes may configure. Run it with `envoy -c`.
+    pub fn bootstrap(&self, port: u16) -> Value {
+        json!({
+            "static_resources": {
+                "listeners": [{
+                    "name": format!("listener_{}", port),
+                    "address": { "socket_address": { "address": "0.0.0.0", "port_value": port } },
+                    "filter_chains": [{
+                        "filters": [{
+                            "name": "envoy.filters.network.http_connection_manager",
+                            "typed_config": {
+                                "@type": "type.googleapis.com/envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager",
+                                "stat_prefix": format!("listener_{}", port),
+                                "route_config": self.route_configuration,
+                                "http_filters": [
+                                    { "name": FAULT_FILTER, "typed_config": { "@type": FAULT_TYPE } },
+                                    { "name": CORS_FILTER, "typed_config": { "@type": "type.googleapis.com/envoy.extensions.filters.http.cors.v3.Cors" } },
+                                    { "name": ROUTER_FILTER, "typed_config": { "@type": ROUTER_TYPE } },
+                                ],
+                            },
+                        }],
+                    }],
+                }],
+                "clusters": self.clusters,
+            },
+        })
+    }
+}
+
+/// Translates the HTTP routes of `vs`. The traffic policy of `dr` applies to the clusters of its
+/// host; TLS and TCP routes are not translated.
+pub fn translate(vs: &VirtualService, dr: Option<&DestinationRule>) -> Snapshot {
+    let mut translator = Translator {
+        dr,
+        clusters: BTreeMap::new(),
+        unsupported: Vec::new(),
+    };
+    let spec = vs.spec.clone().unwrap_or_default();
+    let mut routes = Vec::new();
+    for (i, route) in spec.http.iter().flatten().enumerate() {
+        routes.extend(translator.routes(route, &format!("spec.http[{}]", i)));
+    }
+    if spec.gateways.is_some() {
+        translator.unsupported("spec.gateways");
+    }
+    if spec.tls.is_some() {
+        translator.unsupported("spec.tls");
+    }
+    if spec.tcp.is_some() {
+        translator.unsupported("spec.tcp");
+    }
+
+    let name = vs.metadata.name.clone().unwrap_or_default();
+    let route_configuration = json!({
+        "name": name,
+        "virtual_hosts": [{
+            "name": name,
+            "domains": spec.hosts.clone().unwrap_or_default(),
+            "routes": routes,
+        }],
+    });
+    Snapshot {
+        route_configuration,
+        clusters: translator.clusters.into_values().collect(),
+        unsupported: translator.unsupported,
+    }
+}
+
+/// The name istiod gives the cluster of `destination`.
+pub fn cluster_name(destination: &Destination) -> String {
+    format!(
+        "outbound|{}|{}|{}",
+        port(destination),
+        destination.subset.as_deref().unwrap_or_default(),
+        destination.host
+    )
+}
+
+fn port(destination: &Destination) -> u32 {
+    destination
+        .port
+        .as_ref()
+        .and_then(|port| port.number)
+        .unwrap_or(DEFAULT_PORT)
+}
+
+struct Translator<'a> {
+    dr: Option<&'a DestinationRule>,
+    clusters: BTreeMap<String, Value>,
+    unsupported: Vec<String>,
+}
+
+impl Translator<'_> {
+    /// Reports `path` once, however many routes or clusters run into it.
+    fn unsupported(&mut self, path: &str) {
+        if !self.unsupported.iter().any(|reported| reported == path) {
+            self.unsupported.push(path.to_string());
+        }
+    }
+
+    /// The Envoy routes of `route`, one per match.
+    fn routes(&mut self, route: &HttpRoute, path: &str) -> Vec<Value> {
+        let action = self.action(route, path);
+        let matches = match &route.r#match {
+            Some(matches) if !matches.is_empty() => matches.clone(),
+            _ => vec![HttpMatchRequest::default()],
+        };
+        matches
+            .iter()
+            .enumerate()
+            .map(|(j, request)| {
+                let mut envoy = action.clone();
+                envoy.insert(
+                    "match".to_string(),
+                    self.route_match(request, &format!("{}.match[{}]", path, j)),
+                );
+                if let Some(name) = &route.name {
+                    envoy.insert("name".to_string(), json!(name));
+                }
+                Value::Object(envoy)
+            })
+            .collect()
+    }
+
+    fn route_match(&mut self, request: &HttpMatchRequest, path: &str) -> Value {
+        let mut envoy = Map::new();
+        match &request.uri {
+            Some(StringMatch::Exact(uri)) => envoy.insert("path".to_string(), json!(uri)),
+            Some(StringMatch::Prefix(uri)) => envoy.insert("prefix".to_string(), json!(uri)),
+            Some(StringMatch::Regex(uri)) => {
+                envoy.insert("safe_regex".to_string(), json!({ "regex": uri }))
+            }
+            None => envoy.insert("prefix".to_string(), json!("/")),
+        };
+        if request.ignore_uri_case == Some(true) {
+            envoy.insert("case_sensitive".to_string(), json!(false));
+        }
+
+        let mut headers = Vec::new();
+        let pseudo_headers = [
+            (":method", &request.method),
+            (":authority", &request.authority),
+            (":scheme", &request.scheme),
+        ];
+        for (name, value) in pseudo_headers {
+            if let Some(value) = value {
+                headers.push(json!({ "name": name, "string_match": string_match(value) }));
+            }
+        }
+        for (name, value) in request.headers.iter().flatten() {
+            headers.push(json!({ "name": name, "string_match": string_match(value) }));
+        }
+        for (name, value) in request.without_headers.iter().flatten() {
+            headers.push(json!({
+                "name": name,
+                "string_match": string_match(value),
+                "invert_match": true,
+            }));
+        }
+        if !headers.is_empty() {
+            envoy.insert("headers".to_string(), Value::Array(headers));
+        }
+        if let Some(query_params) = &request.query_params {
+            let query_params: Vec<Value> = query_params
+                .iter()
+                .map(|(name, value)| json!({ "name": name, "string_match": string_match(value) }))
+                .collect();
+            envoy.insert("query_parameters".to_string(), Value::Array(query_params));
+        }
+
+        if request.port.is_some() {
+            self.unsupported(&format!("{}.port", path));
+        }
+        if request.source_labels.is_some() {
+            self.unsupported(&format!("{}.sourceLabels", path));
+        }
+        if request.gateways.is_some() {
+            self.unsupported(&format!("{}.gateways", path));
+        }
+        if request.source_namespace.is_some() {
+            self.unsupported(&format!("{}.sourceNamespace", path));
+        }
+        Value::Object(envoy)
+    }
+
+    /// The fields of the Envoy route besides its match.
+    fn action(&mut self, route: &HttpRoute, path: &str) -> Map<String, Value> {
+        let mut envoy = Map::new();
+        if let Some(redirect) = &route.redirect {
+            let mut action = Map::new();
+            if let Some(uri) = &redirect.uri {
+                action.insert("path_redirect".to_string(), json!(uri));
+            }
+            if let Some(authority) = &redirect.authority {
+                action.insert("host_redirect".to_string(), json!(authority));
+            }
+            if let Some(port) = redirect.port {
+                action.insert("port_redirect".to_string(), json!(port));
+            }
+            if let Some(scheme) = &redirect.scheme {
+                action.insert("scheme_redirect".to_string(), json!(scheme));
+            }
+            let code = match redirect.redirect_code.unwrap_or(301) {
+                301 => "MOVED_PERMANENTLY",
+                302 => "FOUND",
+                303 => "SEE_OTHER",
+                307 => "TEMPORARY_REDIRECT",
+                308 => "PERMANENT_REDIRECT",
+                _ => {
+                    self.unsupported(&format!("{}.redirect.redirectCode", path));
+                    "MOVED_PERMANENTLY"
+                }
+            };
+            action.insert("response_code".to_string(), json!(code));
+            if redirect.derive_port.is_some() {
+                self.unsupported(&format!("{}.redirect.derivePort", path));
+            }
+            envoy.insert("redirect".to_string(), Value::Object(action));
+        } else if let Some(response) = &route.direct_response {
+            let mut action = json!({ "status": response.status });
+            match &response.body {
+                Some(HttpBody::String(body)) => action["body"] = json!({ "inline_string": body }),
+                Some(HttpBody::Bytes(body)) => action["body"] = json!({ "inline_bytes": body }),
+                None => {}
+            }
+            envoy.insert("direct_response".to_string(), action);
+        } else if let Some(destinations) = &route.route {
+            let action = self.route_action(route, path);
+            envoy.insert("route".to_string(), action);
+            if let [destination] = destinations.as_slice() {
+                add_headers(
+                    &mut envoy,
+                    destination
+                        .headers
+                        .as_ref()
+                        .and_then(|h| h.request.as_ref()),
+                    "request",
+                );
+                add_headers(
+                    &mut envoy,
+                    destination
+                        .headers
+                        .as_ref()
+                        .and_then(|h| h.response.as_ref()),
+                    "response",
+                );
+            }
+        } else if route.delegate.is_some() {
+            self.unsupported(&format!("{}.delegate", path));
+        }
+
+        if let Some(headers) = &route.headers {
+            add_headers(&mut envoy, headers.request.as_ref(), "request");
+            add_headers(&mut envoy, headers.response.as_ref(), "response");
+        }
+        let mut per_filter = Map::new();
+        if let Some(fault) = &route.fault {
+            per_filter.insert(FAULT_FILTER.to_string(), self.fault(fault, path));
+        }
+        if let Some(cors) = &route.cors_policy {
+            per_filter.insert(CORS_FILTER.to_string(), cors_policy(cors));
+        }
+        if !per_filter.is_empty() {
+            envoy.insert(
+                "typed_per_filter_config".to_string(),
+                Value::Object(per_filter),
+            );
+        }
+        envoy
+    }
+
+    fn route_action(&mut self, route: &HttpRoute, path: &str) -> Value {
+        let destinations = route.route.as_deref().unwrap_or_default();
+        let mut action = Map::new();
+        match destinations {
+            [destination] => {
+                action.insert(
+                    "cluster".to_string(),
+                    json!(self.cluster(&destination.destination, path)),
+                );
+            }
+            _ => {
+                let weights = Weights::normalize(destinations.iter().map(|d| d.weight));
+                let clusters: Vec<Value> = destinations
+                    .iter()
+                    .zip(weights.iter())
+                    .map(|(destination, weight)| {
+                        let mut cluster = Map::new();
+                        cluster.insert(
+                            "name".to_string(),
+                            json!(self.cluster(&destination.destination, path)),
+                        );
+                        cluster.insert("weight".to_string(), json!(weight.get()));
+                        let headers = destination.headers.as_ref();
+                        add_headers(
+                            &mut cluster,
+                            headers.and_then(|h| h.request.as_ref()),
+                            "request",
+                        );
+                        add_headers(
+                            &mut cluster,
+                            headers.and_then(|h| h.response.as_ref()),
+                            "response",
+                        );
+                        Value::Object(cluster)
+                    })
+                    .collect();
+                action.insert(
+                    "weighted_clusters".to_string(),
+                    json!({ "clusters": clusters }),
+                );
+            }
+        }
+        if let Some(hash_policy) = destinations
+            .first()
+            .and_then(|destination| self.hash_policy(&destination.destination))
+        {
+            action.insert("hash_policy".to_string(), json!([hash_policy]));
+        }
+
+        let timeout = route.timeout.map_or(Duration::ZERO, |timeout| timeout.0);
+        action.insert("timeout".to_string(), json!(proto_duration(timeout)));
+        if let Some(retry_policy) = retry_policy(route.retries.as_ref()) {
+            action.insert("retry_policy".to_string(), retry_policy);
+        }
+
+        if let Some(rewrite) = &route.rewrite {
+            if let Some(uri) = &rewrite.uri {
+                action.insert("prefix_rewrite".to_string(), json!(uri));
+            }
+            if let Some(authority) = &rewrite.authority {
+                action.insert("host_rewrite_literal".to_string(), json!(authority));
+            }
+            if let Some(regex) = &rewrite.uri_regex_rewrite {
+                action.insert(
+                    "regex_rewrite".to_string(),
+                    json!({ "pattern": { "regex": regex.r#match }, "substitution": regex.rewrite }),
+                );
+            }
+        }
+
+        let mut mirrors = Vec::new();
+        if let Some(mirror) = &route.mirror {
+            let percent = route.mirror_percentage.clone().or_else(|| {
+                route
+                    .mirror_percent
+                    .map(|percent| Percent::from(percent as f64))
+            });
+            mirrors.push((mirror, percent));
+        }
+        for mirror in route.mirrors.iter().flatten() {
+            mirrors.push((&mirror.destination, mirror.percentage.clone()));
+        }
+        if !mirrors.is_empty() {
+            let mirrors: Vec<Value> = mirrors
+                .into_iter()
+                .map(|(destination, percent)| {
+                    json!({
+                        "cluster": self.cluster(destination, path),
+                        "runtime_fraction": {
+                            "default_value": fractional_percent(percent.as_ref()),
+                        },
+                    })
+                })
+                .collect();
+            action.insert("request_mirror_policies".to_string(), Value::Array(mirrors));
+        }
+        Value::Object(action)
+    }
+
+    /// The name of the cluster of `destination`, adding the cluster on first use.
+    fn cluster(&mut self, destination: &Destination, path: &str) -> String {
+        let name = cluster_name(destination);
+        if self.clusters.contains_key(&name) {
+            return name;
+        }
+        if destination
+            .port
+            .as_ref()
+            .is_some_and(|port| port.number.is_none())
+        {
+            self.unsupported(&format!("{}.route.destination.port.name", path));
+        }
+        let port = port(destination);
+        let mut cluster = json!({
+            "name": name,
+            "type": "STRICT_DNS",
+            "connect_timeout": "10s",
+            "load_assignment": {
+                "cluster_name": name,
+                "endpoints": [{
+                    "lb_endpoints": [{
+                        "endpoint": {
+                            "address": {
+                                "socket_address": { "address": destination.host, "port_value": port },
+                            },
+                        },
+                    }],
+                }],
+            },
+        });
+        if let Some(policy) = self.traffic_policy(destination) {
+            self.apply_traffic_policy(&mut cluster, &policy, port);
+        }
+        self.clusters.insert(name.clone(), cluster);
+        name
+    }
+
+    /// The traffic policy of `destination`: the one of its subset, else the one of the
+    /// DestinationRule, if the DestinationRule is for its host.
+    fn traffic_policy(&mut self, destination: &Destination) -> Option<TrafficPolicy> {
+        let spec = self.dr?.spec.as_ref()?;
+        if spec.host != destination.host {
+            return None;
+        }
+        let subset_policy = match &destination.subset {
+            Some(name) => {
+                match spec
+                    .subsets
+                    .iter()
+                    .flatten()
+                    .find(|subset| &subset.name == name)
+                {
+                    Some(subset) => subset.traffic_policy.clone(),
+                    None => {
+                        self.unsupported(&format!("subset {} of {}", name, destination.host));
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        subset_policy.or_else(|| spec.traffic_policy.clone())
+    }
+
+    fn apply_traffic_policy(&mut self, cluster: &mut Value, policy: &TrafficPolicy, port: u32) {
+        let port_policy = policy
+            .port_level_settings
+            .iter()
+            .flatten()
+            .find(|settings| {
+                settings.port.as_ref().and_then(|selector| selector.number) == Some(port)
+            });
+        let load_balancer = port_policy
+            .and_then(|settings| settings.load_balancer.as_ref())
+            .or(policy.load_balancer.as_ref());
+        let connection_pool = port_policy
+            .and_then(|settings| settings.connection_pool.as_ref())
+            .or(policy.connection_pool.as_ref());
+        let outlier_detection = port_policy
+            .and_then(|settings| settings.outlier_detection.as_ref())
+            .or(policy.outlier_detection.as_ref());
+        let tls = port_policy
+            .and_then(|settings| settings.tls.as_ref())
+            .or(policy.tls.as_ref());
+
+        if let Some(load_balancer) = load_balancer {
+            self.load_balancer(cluster, load_balancer);
+        }
+        if let Some(connection_pool) = connection_pool {
+            connection_pool_settings(cluster, connection_pool);
+        }
+        if let Some(outlier_detection) = outlier_detection {
+            cluster["outlier_detection"] = outlier_detection_settings(outlier_detection);
+        }
+        if tls.is_some_and(|tls| tls.mode != TLSmode::DISABLE) {
+            self.unsupported(&format!("trafficPolicy.tls of {}", cluster["name"]));
+        }
+    }
+
+    fn load_balancer(&mut self, cluster: &mut Value, settings: &LoadBalancerSettings) {
+        match &settings.lb_policy {
+            Some(LbPolicy::Simple(simple)) => {
+                let policy = match simple {
+                    SimpleLB::UNSPECIFIED | SimpleLB::ROUND_ROBIN => "ROUND_ROBIN",
+                    SimpleLB::LEAST_REQUEST | SimpleLB::LEAST_CONN => "LEAST_REQUEST",
+                    SimpleLB::RANDOM => "RANDOM",
+                    SimpleLB::PASSTHROUGH => {
+                        self.unsupported(&format!("loadBalancer.simple of {}", cluster["name"]));
+                        return;
+                    }
+                };
+                cluster["lb_policy"] = json!(policy);
+            }
+            Some(LbPolicy::ConsistentHash(consistent_hash)) => {
+                match &consistent_hash.hash_algorithm {
+                    Some(HashAlgorithm::Maglev(maglev)) => {
+                        cluster["lb_policy"] = json!("MAGLEV");
+                        if let Some(table_size) = maglev.table_size {
+                            cluster["maglev_lb_config"] = json!({ "table_size": table_size });
+                        }
+                    }
+                    Some(HashAlgorithm::RingHash(ring_hash)) => {
+                        cluster["lb_policy"] = json!("RING_HASH");
+                        if let Some(size) = ring_hash.minimum_ring_size {
+                            cluster["ring_hash_lb_config"] = json!({ "minimum_ring_size": size });
+                        }
+                    }
+                    None => {
+                        cluster["lb_policy"] = json!("RING_HASH");
+                        if let Some(size) = consistent_hash.minimum_ring_size {
+                            cluster["ring_hash_lb_config"] = json!({ "minimum_ring_size": size });
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+        if settings.locality_lb_setting.is_some() {
+            self.unsupported(&format!(
+                "loadBalancer.localityLbSetting of {}",
+                cluster["name"]
+            ));
+        }
+    }
+
+    /// The hash policy of a route to `destination`, when its load balancer hashes consistently.
+    fn hash_policy(&mut self, destination: &Destination) -> Option<Value> {
+        let policy = self.traffic_policy(destination)?;
+        let consistent_hash = match policy.load_balancer?.lb_policy? {
+            LbPolicy::ConsistentHash(consistent_hash) => consistent_hash,
+            LbPolicy::Simple(_) => return None,
+        };
+        Some(match consistent_hash.hash_key {
+            HashKey::HttpHeaderName(name) => json!({ "header": { "header_name": name } }),
+            HashKey::HttpCookie(cookie) => {
+                let mut envoy = json!({ "name": cookie.name, "ttl": proto_duration(cookie.ttl.0) });
+                if let Some(path) = cookie.path {
+                    envoy["path"] = json!(path);
+                }
+                json!({ "cookie": envoy })
+            }
+            HashKey::UseSourceIp(_) => json!({ "connection_properties": { "source_ip": true } }),
+            HashKey::HttpQueryParameterName(name) => json!({ "query_parameter": { "name": name } }),
+        })
+    }
+
+    fn fault(&mut self, fault: &HttpFaultInjection, path: &str) -> Value {
+        let mut envoy = json!({ "@type": FAULT_TYPE });
+        if let Some(delay) = &fault.delay {
+            let percent = delay
+                .percentage
+                .clone()
+                .or_else(|| delay.percent.map(|percent| Percent::from(percent as f64)));
+            envoy["delay"] = json!({
+                "fixed_delay": proto_duration(delay.fixed_delay.0),
+                "percentage": fractional_percent(percent.as_ref()),
+            });
+        }
+        if let Some(abort) = &fault.abort {
+            let mut envoy_abort = json!({
+                "percentage": fractional_percent(abort.percentage.as_ref()),
+            });
+            match (&abort.http_status, &abort.grpc_status) {
+                (Some(status), _) => envoy_abort["http_status"] = json!(status),
+                (None, Some(status)) => match GRPC_STATUS_CODES.iter().position(|s| s == status) {
+                    Some(code) => envoy_abort["grpc_status"] = json!(code),
+                    None => self.unsupported(&format!("{}.fault.abort.grpcStatus", path)),
+                },
+                (None, None) => self.unsupported(&format!("{}.fault.abort.http2Error", path)),
+            }
+            envoy["abort"] = envoy_abort;
+        }
+        envoy
+    }
+}
+
+/// The gRPC status names, in the order of their codes.
+const GRPC_STATUS_CODES: &[&str] = &[
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+fn string_match(value: &StringMatch) -> Value {
+    match value {
+        StringMatch::Exact(value) => json!({ "exact": value }),
+        StringMatch::Prefix(value) => json!({ "prefix": value }),
+        StringMatch::Regex(value) => json!({ "safe_regex": { "regex": value } }),
+    }
+}
+
+/// Adds the header operations to the `<direction>_headers_to_add` and `_to_remove` lists of a
+/// route or weighted cluster.
+fn add_headers(
+    envoy: &mut Map<String, Value>,
+    operations: Option<&HeaderOperations>,
+    direction: &str,
+) {
+    let operations = match operations {
+        Some(operations) => operations,
+        None => return,
+    };
+    let mut to_add = Vec::new();
+    for (key, value) in operations.set.iter().flatten() {
+        to_add.push(json!({
+            "header": { "key": key, "value": value },
+            "append_action": "OVERWRITE_IF_EXISTS_OR_ADD",
+        }));
+    }
+    for (key, value) in operations.add.iter().flatten() {
+        to_add.push(json!({
+            "header": { "key": key, "value": value },
+            "append_action": "APPEND_IF_EXISTS_OR_ADD",
+        }));
+    }
+    if !to_add.is_empty() {
+        let list = envoy
+            .entry(format!("{}_headers_to_add", direction))
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(list) = list {
+            list.extend(to_add);
+        }
+    }
+    if let Some(remove) = &operations.remove {
+        let list = envoy
+            .entry(format!("{}_headers_to_remove", direction))
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(list) = list {
+            list.extend(remove.iter().map(|header| json!(header)));
+        }
+    }
+}
+
+/// The retry policy of a route. Istio retries twice when `retries` is unset, and not at all when
+/// it has 0 attempts. Status codes in `retryOn` become retriable status codes.
+fn retry_policy(retries: Option<&HttpRetry>) -> Option<Value> {
+    let retries = match retries {
+        Some(retries) if retries.attempts <= 0 => return None,
+        Some(retries) => retries.clone(),
+        None => HttpRetry {
+            attempts: 2,
+            ..HttpRetry::default()
+        },
+    };
+    let mut retry_on = retries
+        .retry_on
+        .clone()
+        .unwrap_or_else(|| DEFAULT_RETRY_ON.to_string());
+    let mut status_codes = Vec::new();
+    if let Ok(policies) = retry_on::parse(&retry_on) {
+        let mut names = Vec::new();
+        for policy in policies {
+            match policy.as_str() {
+                Some(name) => names.push(name),
+                None => status_codes.push(policy.to_string()),
+            }
+        }
+        if !status_codes.is_empty() && !names.contains(&"retriable-status-codes") {
+            names.push("retriable-status-codes");
+        }
+        retry_on = names.join(",");
+    }
+    let mut envoy = json!({ "retry_on": retry_on, "num_retries": retries.attempts });
+    if !status_codes.is_empty() {
+        let codes: Vec<u32> = status_codes
+            .iter()
+            .filter_map(|code| code.parse().ok())
+            .collect();
+        envoy["retriable_status_codes"] = json!(codes);
+    }
+    if let Some(per_try_timeout) = retries.per_try_timeout {
+        envoy["per_try_timeout"] = json!(proto_duration(per_try_timeout.0));
+    }
+    if let Some(backoff) = retries.backoff {
+        envoy["retry_back_off"] = json!({ "base_interval": proto_duration(backoff.0) });
+    }
+    if retries.retry_ignore_previous_hosts != Some(false) {
+        envoy["retry_host_predicate"] = json!([{
+            "name": "envoy.retry_host_predicates.previous_hosts",
+            "typed_config": {
+                "@type": "type.googleapis.com/envoy.extensions.retry.host.previous_hosts.v3.PreviousHostsPredicate",
+            },
+        }]);
+        envoy["host_selection_retry_max_attempts"] = json!(5);
+    }
+    Some(envoy)
+}
+
+fn cors_policy(cors: &CorsPolicy) -> Value {
+    let mut envoy = json!({ "@type": CORS_TYPE });
+    if let Some(origins) = &cors.allow_origins {
+        envoy["allow_origin_string_match"] =
+            Value::Array(origins.iter().map(string_match).collect());
+    }
+    let lists = [
+        ("allow_methods", &cors.allow_methods),
+        ("allow_headers", &cors.allow_headers),
+        ("expose_headers", &cors.expose_headers),
+    ];
+    for (field, values) in lists {
+        if let Some(values) = values {
+            envoy[field] = json!(values.join(","));
+        }
+    }
+    if let Some(max_age) = cors.max_age {
+        envoy["max_age"] = json!(max_age.as_secs().to_string());
+    }
+    if let Some(allow_credentials) = cors.allow_credentials {
+        envoy["allow_credentials"] = json!(allow_credentials);
+    }
+    envoy
+}
+
+fn connection_pool_settings(cluster: &mut Value, settings: &ConnectionPoolSettings) {
+    let mut thresholds = Map::new();
+    if let Some(tcp) = &settings.tcp {
+        if let Some(max_connections) = tcp.max_connections {
+            thresholds.insert("max_connections".to_string(), json!(max_connections));
+        }
+        if let Some(connect_timeout) = tcp.connect_timeout {
+            cluster["connect_timeout"] = json!(proto_duration(connect_timeout.0));
+        }
+    }
+    if let Some(http) = &settings.http {
+        let limits = [
+            ("max_pending_requests", http.http1_max_pending_requests),
+            ("max_requests", http.http2_max_requests),
+            ("max_retries", http.max_retries),
+        ];
+        for (field, limit) in limits {
+            if let Some(limit) = limit {
+                thresholds.insert(field.to_string(), json!(limit));
+            }
+        }
+        if let Some(max_requests) = http.max_requests_per_connection {
+            cluster["max_requests_per_connection"] = json!(max_requests);
+        }
+    }
+    if !thresholds.is_empty() {
+        cluster["circuit_breakers"] = json!({ "thresholds": [thresholds] });
+    }
+}
+
+fn outlier_detection_settings(settings: &OutlierDetection) -> Value {
+    let mut envoy = json!({});
+    let consecutive_5xx = settings
+        .consecutive5xx_errors
+        .as_ref()
+        .and_then(|errors| errors.value)
+        .or_else(|| {
+            settings
+                .consecutive_errors
+                .map(|errors| errors.max(0) as u32)
+        });
+    if let Some(errors) = consecutive_5xx {
+        envoy["consecutive_5xx"] = json!(errors);
+        envoy["enforcing_consecutive_5xx"] = json!(if errors > 0 { 100 } else { 0 });
+    }
+    if let Some(errors) = settings
+        .consecutive_gateway_errors
+        .as_ref()
+        .and_then(|errors| errors.value)
+    {
+        envoy["consecutive_gateway_failure"] = json!(errors);
+        envoy["enforcing_consecutive_gateway_failure"] = json!(if errors > 0 { 100 } else { 0 });
+    }
+    if let Some(split) = settings.split_external_local_origin_errors {
+        envoy["split_external_local_origin_errors"] = json!(split);
+    }
+    if let Some(failures) = settings
+        .consecutive_local_origin_failures
+        .as_ref()
+        .and_then(|failures| failures.value)
+    {
+        envoy["consecutive_local_origin_failure"] = json!(failures);
+    }
+    if let Some(interval) = settings.interval {
+        envoy["interval"] = json!(proto_duration(interval.0));
+    }
+    if let Some(base_ejection_time) = settings.base_ejection_time {
+        envoy["base_ejection_time"] = json!(proto_duration(base_ejection_time.0));
+    }
+    if let Some(percent) = settings.max_ejection_percent {
+        envoy["max_ejection_percent"] = json!(percent);
+    }
+    if let Some(percent) = settings.min_health_percent {
+        envoy["common_config"] = json!({ "healthy_panic_threshold": { "value": percent } });
+    }
+    envoy
+}
+
+/// A percentage as an Envoy `FractionalPercent` in millionths; 100% when unset.
+fn fractional_percent(percent: Option<&Percent>) -> Value {
+    let value = percent.map_or(100.0, |percent| percent.value);
+    json!({
+        "numerator": (value.clamp(0.0, 100.0) * 10_000.0).round() as u64,
+        "denominator": "MILLION",
+    })
+}
+
+/// A duration in the JSON form of `google.protobuf.Duration`, e.g. `1.500s`.
+fn proto_duration(duration: Duration) -> String {
+    let nanos = duration.subsec_nanos();
+    match nanos {
+        0 => format!("{}s", duration.as_secs()),
+        _ if nanos.is_multiple_of(1_000_000) => {
+            format!("{}.{:03}s", duration.as_secs(), nanos / 1_000_000)
+        }
+        _ if nanos.is_multiple_of(1_000) => format!("{}.{:06}s", duration.as_secs(), nanos / 1_000),
+        _ => format!("{}.{:09}s", duration.as_secs(), nanos),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn virtual_service(spec: Value) -> VirtualService {
+        serde_json::from_value(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn destination_rule(spec: Value) -> DestinationRule {
+        serde_json::from_value(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn routes(snapshot: &Snapshot) -> &Vec<Value> {
+        snapshot.route_configuration["virtual_hosts"][0]["routes"]
+            .as_array()
+            .unwrap()
+    }
+
+    fn cluster<'a>(snapshot: &'a Snapshot, name: &str) -> &'a Value {
+        snapshot
+            .clusters
+            .iter()
+            .find(|cluster| cluster["name"] == name)
+            .unwrap_or_else(|| panic!("no cluster {}", name))
+    }
+
+    #[test]
+    fn names_clusters_like_istiod() {
+        let destination: Destination = serde_json::from_value(
+            json!({ "host": "reviews", "subset": "v1", "port": { "number": 9080 } }),
+        )
+        .unwrap();
+        assert_eq!(cluster_name(&destination), "outbound|9080|v1|reviews");
+
+        let destination: Destination =
+            serde_json::from_value(json!({ "host": "reviews" })).unwrap();
+        assert_eq!(cluster_name(&destination), "outbound|80||reviews");
+    }
+
+    #[test]
+    fn translates_matches_and_weighted_routes() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "http": [{
+                "name": "split",
+                "match": [
+                    { "uri": { "prefix": "/api" }, "method": { "exact": "GET" }, "ignoreUriCase": true },
+                    { "headers": { "end-user": { "exact": "jason" } }, "withoutHeaders": { "x-debug": { "regex": ".*" } } },
+                ],
+                "route": [
+                    { "destination": { "host": "reviews", "subset": "v1" }, "weight": 75 },
+                    { "destination": { "host": "reviews", "subset": "v2" }, "weight": 25 },
+                ],
+                "timeout": "1.5s",
+            }],
+        }));
+
+        let snapshot = translate(&vs, None);
+
+        assert_eq!(snapshot.route_configuration["name"], "reviews");
+        assert_eq!(
+            snapshot.route_configuration["virtual_hosts"][0]["domains"],
+            json!(["reviews"])
+        );
+        let routes = routes(&snapshot);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0]["name"], "split");
+        assert_eq!(
+            routes[0]["match"],
+            json!({
+                "prefix": "/api",
+                "case_sensitive": false,
+                "headers": [{ "name": ":method", "string_match": { "exact": "GET" } }],
+            })
+        );
+        assert_eq!(
+            routes[1]["match"],
+            json!({
+                "prefix": "/",
+                "headers": [
+                    { "name": "end-user", "string_match": { "exact": "jason" } },
+                    { "name": "x-debug", "string_match": { "safe_regex": { "regex": ".*" } }, "invert_match": true },
+                ],
+            })
+        );
+        assert_eq!(
+            routes[0]["route"]["weighted_clusters"],
+            json!({ "clusters": [
+                { "name": "outbound|80|v1|reviews", "weight": 75 },
+                { "name": "outbound|80|v2|reviews", "weight": 25 },
+            ]})
+        );
+        assert_eq!(routes[0]["route"]["timeout"], "1.500s");
+        assert_eq!(snapshot.clusters.len(), 2);
+        assert!(snapshot.unsupported.is_empty());
+    }
+
+    #[test]
+    fn retries_twice_by_default_and_not_with_zero_attempts() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "http": [
+                { "route": [{ "destination": { "host": "reviews" } }] },
+                { "route": [{ "destination": { "host": "reviews" } }], "retries": { "attempts": 0 } },
+                { "route": [{ "destination": { "host": "reviews" } }], "retries": { "attempts": 3, "retryOn": "5xx,503", "perTryTimeout": "2s" } },
+            ],
+        }));
+
+        let snapshot = translate(&vs, None);
+        let routes = routes(&snapshot);
+
+        assert_eq!(routes[0]["route"]["retry_policy"]["num_retries"], 2);
+        assert_eq!(
+            routes[0]["route"]["retry_policy"]["retry_on"],
+            DEFAULT_RETRY_ON
+        );
+        assert!(routes[1]["route"].get("retry_policy").is_none());
+        let retry_policy = &routes[2]["route"]["retry_policy"];
+        assert_eq!(retry_policy["num_retries"], 3);
+        assert_eq!(retry_policy["retry_on"], "5xx,retriable-status-codes");
+        assert_eq!(retry_policy["retriable_status_codes"], json!([503]));
+        assert_eq!(retry_policy["per_try_timeout"], "2s");
+    }
+
+    #[test]
+    fn translates_redirects_direct_responses_and_faults() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "http": [
+                { "redirect": { "uri": "/v2", "authority": "new", "redirectCode": 308 } },
+                { "directResponse": { "status": 503, "body": { "string": "down" } } },
+                {
+                    "route": [{ "destination": { "host": "reviews" } }],
+                    "fault": {
+                        "delay": { "fixedDelay": "5s", "percentage": { "value": 10 } },
+                        "abort": { "grpcStatus": "UNAVAILABLE" },
+                    },
+                },
+            ],
+        }));
+
+        let snapshot = translate(&vs, None);
+        let routes = routes(&snapshot);
+
+        assert_eq!(
+            routes[0]["redirect"],
+            json!({ "path_redirect": "/v2", "host_redirect": "new", "response_code": "PERMANENT_REDIRECT" })
+        );
+        assert_eq!(
+            routes[1]["direct_response"],
+            json!({ "status": 503, "body": { "inline_string": "down" } })
+        );
+        let fault = &routes[2]["typed_per_filter_config"][FAULT_FILTER];
+        assert_eq!(
+            fault["delay"],
+            json!({ "fixed_delay": "5s", "percentage": { "numerator": 100_000, "denominator": "MILLION" } })
+        );
+        assert_eq!(fault["abort"]["grpc_status"], 14);
+    }
+
+    #[test]
+    fn applies_the_traffic_policy_of_the_subset() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "http": [{
+                "route": [
+                    { "destination": { "host": "reviews", "subset": "v1" }, "weight": 50 },
+                    { "destination": { "host": "reviews", "subset": "v2" }, "weight": 50 },
+                ],
+            }],
+        }));
+        let dr = destination_rule(json!({
+            "host": "reviews",
+            "trafficPolicy": {
+                "loadBalancer": { "simple": "LEAST_CONN" },
+                "outlierDetection": { "consecutive5xxErrors": 5, "interval": "10s" },
+            },
+            "subsets": [
+                { "name": "v1", "labels": { "version": "v1" } },
+                {
+                    "name": "v2",
+                    "labels": { "version": "v2" },
+                    "trafficPolicy": { "loadBalancer": { "consistentHash": { "httpHeaderName": "x-user", "maglev": { "tableSize": 65537 } } } },
+                },
+            ],
+        }));
+
+        let snapshot = translate(&vs, Some(&dr));
+
+        let v1 = cluster(&snapshot, "outbound|80|v1|reviews");
+        assert_eq!(v1["lb_policy"], "LEAST_REQUEST");
+        assert_eq!(
+            v1["outlier_detection"],
+            json!({ "consecutive_5xx": 5, "enforcing_consecutive_5xx": 100, "interval": "10s" })
+        );
+        let v2 = cluster(&snapshot, "outbound|80|v2|reviews");
+        assert_eq!(v2["lb_policy"], "MAGLEV");
+        assert_eq!(v2["maglev_lb_config"], json!({ "table_size": 65537 }));
+        assert!(v2.get("outlier_detection").is_none());
+        assert!(snapshot.unsupported.is_empty());
+    }
+
+    #[test]
+    fn reports_what_has_no_envoy_equivalent() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "gateways": ["ingress"],
+            "http": [
+                { "match": [{ "sourceLabels": { "app": "web" }, "port": 8080 }], "route": [{ "destination": { "host": "reviews", "subset": "v3" } }] },
+                { "delegate": { "name": "reviews-delegate" } },
+            ],
+            "tcp": [{ "route": [{ "destination": { "host": "mongo" } }] }],
+        }));
+        let dr = destination_rule(json!({
+            "host": "reviews",
+            "subsets": [{ "name": "v1", "labels": { "version": "v1" } }],
+        }));
+
+        let snapshot = translate(&vs, Some(&dr));
+
+        assert_eq!(
+            snapshot.unsupported,
+            vec![
+                "subset v3 of reviews",
+                "spec.http[0].match[0].port",
+                "spec.http[0].match[0].sourceLabels",
+                "spec.http[1].delegate",
+                "spec.gateways",
+                "spec.tcp",
+            ]
+        );
+    }
+
+    #[test]
+    fn bootstraps_a_listener_with_the_routes_and_clusters() {
+        let vs = virtual_service(json!({
+            "hosts": ["reviews"],
+            "http": [{ "route": [{ "destination": { "host": "reviews" } }] }],
+        }));
+        let snapshot = translate(&vs, None);
+
+        let bootstrap = snapshot.bootstrap(10000);
+
+        let listener = &bootstrap["static_resources"]["listeners"][0];
+        assert_eq!(listener["name"], "listener_10000");
+        assert_eq!(listener["address"]["socket_address"]["port_value"], 10000);
+        let manager = &listener["filter_chains"][0]["filters"][0]["typed_config"];
+        assert_eq!(manager["route_config"], snapshot.route_configuration);
+        assert_eq!(
+            bootstrap["static_resources"]["clusters"],
+            json!(snapshot.clusters)
+        );
+    }
+
+    #[test]
+    fn formats_durations_like_protobuf_json() {
+        assert_eq!(proto_duration(Duration::from_secs(5)), "5s");
+        assert_eq!(proto_duration(Duration::from_millis(1500)), "1.500s");
+        assert_eq!(
+            proto_duration(Duration::from_micros(1_000_001)),
+            "1.000001s"
+        );
+        assert_eq!(proto_duration(Duration::from_nanos(7)), "0.000000007s");
+    }
+}
@@ -1,12 +1,12 @@
 // EnvoyFilter provides a mechanism to customize the Envoy configuration generated by Istio Pilot. Use EnvoyFilter to modify values for certain fields, add specific filters, or even add entirely new listeners, clusters, etc. This feature must be used with care, as incorrect configurations could potentially destabilize the entire mesh. Unlike other Istio networking objects, EnvoyFilters are additively applied. Any number of EnvoyFilters can exist for a given workload in a specific namespace. The order of application of these EnvoyFilters is as follows: all EnvoyFilters in the config root namespace, followed by all matching EnvoyFilters in the workload’s namespace.
-use crate::istio::envoy_filter::listener_match::FilterChainMatch;
+use crate::istio::envoy_filter::listener_match::{FilterChainMatch, FilterMatch, SubFilterMatch};
 use crate::istio::envoy_filter::patch::{FilterClass, Operation};
 use crate::istio::envoy_filter::route_configuration_match::VirtualHostMatch;
 use crate::istio::google::Struct;
-use crate::istio::load_balancer_settings::{ConsistentHashLB, SimpleLB};
-use crate::istio::traffic_policy::PortTrafficPolicy;
+use crate::istio::status::IstioStatus;
 use crate::istio::WorkloadSelector;
 use k8s_openapi::{Metadata, Resource};
+use std::fmt;
 ///
 /// NOTE 1: Some aspects of this API are deeply tied to the internal implementation in Istio networking subsystem as well as Envoy’s XDS API. While the EnvoyFilter API by itself will maintain backward compatibility, any envoy configuration provided through this mechanism should be carefully monitored across Istio proxy version upgrades, to ensure that deprecated fields are removed and replaced appropriately.
 ///
@@ -325,11 +325,12 @@ use k8s_openapi::{Metadata, Resource};
 ///                   local:
 ///                     filename: "/etc/envoy_filter_http_wasm_example.wasm"
 /// ```
-use std::collections::HashMap;
-use std::time::Duration;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
 
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EnvoyFilter {
     /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
     pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
@@ -338,7 +339,7 @@ pub struct EnvoyFilter {
     pub spec: Option<EnvoyFilterSpec>,
 
     /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
-    pub status: Option<()>,
+    pub status: Option<IstioStatus>,
 }
 
 impl Resource for EnvoyFilter {
@@ -364,7 +365,8 @@ impl Metadata for EnvoyFilter {
 
 /// EnvoyFilter provides a mechanism to customize the Envoy configuration generated by Istio Pilot.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EnvoyFilterSpec {
     // Criteria used to select the specific set of pods/VMs on which this patch configuration should be applied. If omitted, the set of patches in this configuration will be applied to all workload instances in the same namespace. If omitted, the EnvoyFilter patches will be applied to all workloads in the same namespace. If the EnvoyFilter is present in the config root namespace, it will be applied to all applicable workloads in any namespace.
     // No
@@ -385,12 +387,18 @@ pub struct EnvoyFilterSpec {
     // Patch sets are sorted in the following ascending key order: priority, creation time, fully qualified resource name.
     // No
     pub priority: Option<i32>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// # ProxyMatch
 /// One or more properties of the proxy to match on.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ProxyMatch {
     // A regular expression in golang regex format (RE2) that can be used to select proxies using a specific version of istio proxy. The Istio version for a given proxy is obtained from the node metadata field ISTIO_VERSION supplied by the proxy when connecting to Pilot. This value is embedded as an environment variable (ISTIO_META_ISTIO_VERSION) in the Istio proxy docker image. Custom proxy implementations should provide this metadata variable to take advantage of the Istio version check option.
     // No
@@ -399,13 +407,14 @@ pub struct ProxyMatch {
 
     // Match on the node metadata supplied by a proxy when connecting to Istio Pilot. Note that while Envoy’s node metadata is of type Struct, only string key-value pairs are processed by Pilot. All keys specified in the metadata must match with exact values. The match will fail if any of the specified keys are absent or the values fail to match.
     // No
-    pub metadata: Option<HashMap<String, String>>,
+    pub metadata: Option<Map<String, String>>,
 }
 
 /// # ClusterMatch
 /// Conditions specified in ClusterMatch must be met for the patch to be applied to a cluster.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ClusterMatch {
     // The service port for which this cluster was generated. If omitted, applies to clusters for any port. Note: for inbound cluster, it is the service target port.
     // No
@@ -428,7 +437,8 @@ pub struct ClusterMatch {
 /// # RouteConfigurationMatch
 /// Conditions specified in RouteConfigurationMatch must be met for the patch to be applied to a route configuration object or a specific virtual host within the route configuration.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RouteConfigurationMatch {
     // The service port number or gateway server port number for which this route configuration was generated. If omitted, applies to route configurations for all ports.
     // No
@@ -456,7 +466,8 @@ pub struct RouteConfigurationMatch {
 /// # ListenerMatch
 /// Conditions specified in a listener match must be met for the patch to be applied to a specific listener across all filter chains, or a specific filter chain inside the listener.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ListenerMatch {
     // The service port/gateway port to which traffic is being sent/received. If not specified, matches all listeners. Even though inbound listeners are generated for the instance/pod ports, only service ports should be used to match listeners.
     // No
@@ -477,7 +488,10 @@ pub mod patch {
     /// # Operation
     /// Operation denotes how the patch should be applied to the selected configuration.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "PatchOperation"))]
+#[allow(non_camel_case_types)]
     pub enum Operation {
         INVALID,
 
@@ -505,7 +519,8 @@ pub mod patch {
     /// # FilterClass
     /// FilterClass determines the filter insertion point in the filter chain relative to the filters implicitly inserted by the control plane. It is used in conjuction with the ADD operation. This is the preferred insertion mechanism for adding filters over the INSERT_* operations since those operations rely on potentially unstable filter names. Filter ordering is important if your filter depends on or affects the functioning of a another filter in the filter chain. Within a filter class, filters are inserted in the order of processing.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum FilterClass {
         // Control plane decides where to insert the filter. Do not specify FilterClass if the filter is independent of others.
         UNSPECIFIED,
@@ -524,7 +539,8 @@ pub mod patch {
 /// # Patch
 /// Patch specifies how the selected object should be modified.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Patch {
     // Determines how the patch should be applied.
     // No
@@ -540,56 +556,226 @@ pub struct Patch {
     pub filter_class: Option<FilterClass>,
 }
 
+impl Patch {
+    pub fn new(operation: Operation, value: Option<Struct>) -> Self {
+        Patch {
+            operation: Some(operation),
+            value,
+            filter_class: None,
+        }
+    }
+
+    pub fn merge(value: Struct) -> Self {
+        Patch::new(Operation::MERGE, Some(value))
+    }
+
+    pub fn add(value: Struct) -> Self {
+        Patch::new(Operation::ADD, Some(value))
+    }
+
+    pub fn remove() -> Self {
+        Patch::new(Operation::REMOVE, None)
+    }
+
+    pub fn replace(value: Struct) -> Self {
+        Patch::new(Operation::REPLACE, Some(value))
+    }
+
+    pub fn insert_before(value: Struct) -> Self {
+        Patch::new(Operation::INSERT_BEFORE, Some(value))
+    }
+
+    pub fn insert_after(value: Struct) -> Self {
+        Patch::new(Operation::INSERT_AFTER, Some(value))
+    }
+
+    pub fn insert_first(value: Struct) -> Self {
+        Patch::new(Operation::INSERT_FIRST, Some(value))
+    }
+
+    /// Places an added filter relative to the filters of the control plane, see `FilterClass`.
+    pub fn with_filter_class(mut self, filter_class: FilterClass) -> Self {
+        self.filter_class = Some(filter_class);
+        self
+    }
+}
+
 /// # EnvoyConfigObjectMatch
-///
+/// One or more match conditions to be met before a patch is applied to the generated configuration for a given proxy.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum EnvoyConfigObjectMatch {
-    Proxy {
-        // The specific config generation context to match on. Istio Pilot generates envoy configuration in the context of a gateway, inbound traffic to sidecar and outbound traffic from sidecar.
-        // No
-        context: PatchContext,
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EnvoyConfigObjectMatch {
+    // The specific config generation context to match on. Istio Pilot generates envoy configuration in the context of a gateway, inbound traffic to sidecar and outbound traffic from sidecar.
+    // No
+    pub context: Option<PatchContext>,
 
-        // Match on properties associated with a proxy.
-        // No
-        proxy: ProxyMatch,
-    },
+    // Match on properties associated with a proxy.
+    // No
+    pub proxy: Option<ProxyMatch>,
 
-    Listener {
-        // The specific config generation context to match on. Istio Pilot generates envoy configuration in the context of a gateway, inbound traffic to sidecar and outbound traffic from sidecar.
-        // No
-        context: PatchContext,
+    // Match on envoy listener, route configuration or cluster attributes.
+    // No
+    #[serde(flatten)]
+    pub object_types: Option<ObjectTypes>,
+}
 
-        // Match on envoy listener attributes.
-        // No
-        listener: ListenerMatch,
-    },
+impl EnvoyConfigObjectMatch {
+    pub fn listener(listener: ListenerMatch) -> Self {
+        EnvoyConfigObjectMatch {
+            object_types: Some(ObjectTypes::Listener(listener)),
+            ..EnvoyConfigObjectMatch::default()
+        }
+    }
 
-    RouteConfiguration {
-        // The specific config generation context to match on. Istio Pilot generates envoy configuration in the context of a gateway, inbound traffic to sidecar and outbound traffic from sidecar.
-        // No
-        context: PatchContext,
+    pub fn route_configuration(route_configuration: RouteConfigurationMatch) -> Self {
+        EnvoyConfigObjectMatch {
+            object_types: Some(ObjectTypes::RouteConfiguration(route_configuration)),
+            ..EnvoyConfigObjectMatch::default()
+        }
+    }
 
-        // Match on envoy HTTP route configuration attributes.
-        // No
-        routeConfiguration: RouteConfigurationMatch,
-    },
+    pub fn cluster(cluster: ClusterMatch) -> Self {
+        EnvoyConfigObjectMatch {
+            object_types: Some(ObjectTypes::Cluster(cluster)),
+            ..EnvoyConfigObjectMatch::default()
+        }
+    }
 
-    Cluster {
-        // The specific config generation context to match on. Istio Pilot generates envoy configuration in the context of a gateway, inbound traffic to sidecar and outbound traffic from sidecar.
-        // No
-        context: PatchContext,
+    pub fn with_context(mut self, context: PatchContext) -> Self {
+        self.context = Some(context);
+        self
+    }
 
-        // Match on envoy cluster attributes.
-        // No
-        cluster: ClusterMatch,
-    },
+    /// Only matches proxies whose Istio version matches the RE2 `proxy_version`, e.g.
+    /// `^1\.2[0-9].*`.
+    pub fn with_proxy_version(mut self, proxy_version: &str) -> Self {
+        self.proxy
+            .get_or_insert_with(ProxyMatch::default)
+            .proxy_version = Some(proxy_version.to_string());
+        self
+    }
+
+    /// Only matches proxies with the node metadata.
+    pub fn with_proxy_metadata(mut self, key: &str, value: &str) -> Self {
+        self.proxy
+            .get_or_insert_with(ProxyMatch::default)
+            .metadata
+            .get_or_insert_with(Map::default)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// The field set in `object_types`, e.g. `listener`.
+    pub fn object_type(&self) -> Option<&'static str> {
+        self.object_types
+            .as_ref()
+            .map(|object_types| match object_types {
+                ObjectTypes::Listener(_) => "listener",
+                ObjectTypes::RouteConfiguration(_) => "routeConfiguration",
+                ObjectTypes::Cluster(_) => "cluster",
+            })
+    }
+}
+
+impl ListenerMatch {
+    /// The listeners of a service or gateway port.
+    pub fn port(port_number: u32) -> Self {
+        ListenerMatch {
+            port_number: Some(port_number),
+            ..ListenerMatch::default()
+        }
+    }
+
+    /// Restricts the match to the filter chains with the network filter `name`, e.g.
+    /// `envoy.filters.network.http_connection_manager`.
+    pub fn with_filter(mut self, name: &str) -> Self {
+        self.filter_chain
+            .get_or_insert_with(FilterChainMatch::default)
+            .filter = Some(FilterMatch {
+            name: name.to_string(),
+            sub_filter: None,
+        });
+        self
+    }
+
+    /// Restricts the match to the HTTP filter `name` of the network filter `filter`.
+    pub fn with_sub_filter(mut self, filter: &str, name: &str) -> Self {
+        self.filter_chain
+            .get_or_insert_with(FilterChainMatch::default)
+            .filter = Some(FilterMatch {
+            name: filter.to_string(),
+            sub_filter: Some(SubFilterMatch {
+                name: name.to_string(),
+            }),
+        });
+        self
+    }
+}
+
+impl RouteConfigurationMatch {
+    /// The route configurations of a service or gateway port.
+    pub fn port(port_number: u32) -> Self {
+        RouteConfigurationMatch {
+            port_number: Some(port_number),
+            ..RouteConfigurationMatch::default()
+        }
+    }
+
+    /// Restricts the match to the virtual host `name`, `host:port` for the ones generated by
+    /// Istio.
+    pub fn with_vhost(mut self, name: &str) -> Self {
+        self.vhost = Some(VirtualHostMatch {
+            name: Some(name.to_string()),
+            route: None,
+        });
+        self
+    }
+}
+
+impl ClusterMatch {
+    /// The clusters of `service`, a fully qualified host.
+    pub fn service(service: &str) -> Self {
+        ClusterMatch {
+            service: Some(service.to_string()),
+            ..ClusterMatch::default()
+        }
+    }
+
+    pub fn with_port(mut self, port_number: u32) -> Self {
+        self.port_number = Some(port_number);
+        self
+    }
+
+    pub fn with_subset(mut self, subset: &str) -> Self {
+        self.subset = Some(subset.to_string());
+        self
+    }
+}
+
+/// # ObjectTypes
+/// The kind of Envoy configuration object a patch matches on.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ObjectTypes {
+    // Match on envoy listener attributes.
+    #[serde(rename = "listener")]
+    Listener(ListenerMatch),
+
+    // Match on envoy HTTP route configuration attributes.
+    #[serde(rename = "routeConfiguration")]
+    RouteConfiguration(RouteConfigurationMatch),
+
+    // Match on envoy cluster attributes.
+    #[serde(rename = "cluster")]
+    Cluster(ClusterMatch),
 }
 
 /// # EnvoyConfigObjectPatch
 /// Changes to be made to various envoy config objects.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EnvoyConfigObjectPatch {
     // Specifies where in the Envoy configuration, the patch should be applied. The match is expected to select the appropriate object based on applyTo. For example, an applyTo with HTTP_FILTER is expected to have a match condition on the listeners, with a network filter selection on envoy.filters.network.http_connection_manager and a sub filter selection on the HTTP filter relative to which the insertion should be performed. Similarly, an applyTo on CLUSTER should have a match (if provided) on the cluster and not on a listener.
     // No
@@ -605,6 +791,111 @@ pub struct EnvoyConfigObjectPatch {
     pub patch: Patch,
 }
 
+impl EnvoyConfigObjectPatch {
+    /// A patch of the objects selected by `match`, checked with `EnvoyConfigObjectPatch::check`.
+    pub fn new(
+        apply_to: ApplyTo,
+        r#match: EnvoyConfigObjectMatch,
+        patch: Patch,
+    ) -> Result<Self, PatchError> {
+        let patch = EnvoyConfigObjectPatch {
+            apply_to,
+            r#match,
+            patch,
+        };
+        patch.check()?;
+        Ok(patch)
+    }
+
+    /// Checks the combination of `applyTo`, match and patch as the Istio validation webhook does:
+    /// the match must select the kind of object patched, the operation must be supported by it,
+    /// every operation but `REMOVE` needs a value and `filterClass` only places filters added with
+    /// `ADD`.
+    pub fn check(&self) -> Result<(), PatchError> {
+        let operation = self.patch.operation.unwrap_or(Operation::INVALID);
+        if self.apply_to == ApplyTo::INVALID {
+            return Err(PatchError::InvalidApplyTo);
+        }
+        if let (Some(expected), Some(found)) =
+            (self.apply_to.object_type(), self.r#match.object_type())
+        {
+            if expected != found {
+                return Err(PatchError::MismatchedMatch {
+                    apply_to: self.apply_to,
+                    r#match: found,
+                });
+            }
+        }
+        if operation == Operation::INVALID {
+            return Err(PatchError::InvalidOperation);
+        }
+        if !self.apply_to.supports(operation) {
+            return Err(PatchError::UnsupportedOperation {
+                apply_to: self.apply_to,
+                operation,
+            });
+        }
+        if operation != Operation::REMOVE && self.patch.value.is_none() {
+            return Err(PatchError::MissingValue { operation });
+        }
+        match self.patch.filter_class {
+            None | Some(FilterClass::UNSPECIFIED) => Ok(()),
+            Some(_) if operation != Operation::ADD => {
+                Err(PatchError::FilterClassWithoutAdd { operation })
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// # PatchError
+/// A combination of `applyTo`, operation and value that istiod rejects or ignores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchError {
+    InvalidApplyTo,
+    InvalidOperation,
+    /// The match selects another kind of object, e.g. a cluster for an `HTTP_FILTER`.
+    MismatchedMatch {
+        apply_to: ApplyTo,
+        r#match: &'static str,
+    },
+    UnsupportedOperation {
+        apply_to: ApplyTo,
+        operation: Operation,
+    },
+    /// Only `REMOVE` can do without a value.
+    MissingValue {
+        operation: Operation,
+    },
+    FilterClassWithoutAdd {
+        operation: Operation,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::InvalidApplyTo => write!(f, "applyTo is required"),
+            PatchError::InvalidOperation => write!(f, "operation is required"),
+            PatchError::MismatchedMatch { apply_to, r#match } => {
+                write!(f, "{:?} cannot be matched with {}", apply_to, r#match)
+            }
+            PatchError::UnsupportedOperation {
+                apply_to,
+                operation,
+            } => write!(f, "{:?} does not support {:?}", apply_to, operation),
+            PatchError::MissingValue { operation } => {
+                write!(f, "{:?} requires a value", operation)
+            }
+            PatchError::FilterClassWithoutAdd { operation } => {
+                write!(f, "filterClass only applies to ADD, not {:?}", operation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
 pub mod route_configuration_match {
     use crate::istio::envoy_filter::route_configuration_match::route_match::Action;
 
@@ -612,7 +903,10 @@ pub mod route_configuration_match {
         /// # Action
         /// Action refers to the route action taken by Envoy when a http route matches.
         #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "RouteAction"))]
+#[allow(non_camel_case_types)]
         pub enum Action {
             // All three route actions
             ANY,
@@ -631,29 +925,31 @@ pub mod route_configuration_match {
     /// # RouteMatch
     /// Match a specific route inside a virtual host in a route configuration.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct RouteMatch {
         // The Route objects generated by default are named as default.Route objects generated using a virtual service will carry the name used in the virtual service’s HTTP routes.
         // No
-        pub name: String,
+        pub name: Option<String>,
 
         // Match a route with specific action type.
         // No
-        pub action: Action,
+        pub action: Option<Action>,
     }
 
     /// # VirtualHostMatch
     /// Match a specific virtual host inside a route configuration.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct VirtualHostMatch {
         // The VirtualHosts objects generated by Istio are named as host:port, where the host typically corresponds to the VirtualService’s host field or the hostname of a service in the registry.
         // No
-        pub name: String,
+        pub name: Option<String>,
 
         // Match a specific route within the virtual host.
         // No
-        pub route: RouteMatch,
+        pub route: Option<RouteMatch>,
     }
 }
 
@@ -662,15 +958,16 @@ pub mod listener_match {
     /// # FilterChainMatch
     /// For listeners with multiple filter chains (e.g., inbound listeners on sidecars with permissive mTLS, gateway listeners with multiple SNI matches), the filter chain match can be used to select a specific filter chain to patch.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct FilterChainMatch {
         // The name assigned to the filter chain.
         // No
-        pub name: String,
+        pub name: Option<String>,
 
         // The SNI value used by a filter chain’s match condition. This condition will evaluate to false if the filter chain has no sni match.
         // No
-        pub sni: String,
+        pub sni: Option<String>,
 
         // Applies only to SIDECAR_INBOUND context. If non-empty, a transport protocol to consider when determining a filter chain match. This value will be compared against the transport protocol of a new connection, when it’s detected by the tls_inspector listener filter.
         //
@@ -681,29 +978,30 @@ pub mod listener_match {
         //
         // No
         #[serde(rename = "transportProtocol")]
-        pub transport_protocol: String,
+        pub transport_protocol: Option<String>,
 
         // Applies only to sidecars. If non-empty, a comma separated set of application protocols to consider when determining a filter chain match. This value will be compared against the application protocols of a new connection, when it’s detected by one of the listener filters such as the http_inspector.
         //
         // Accepted values include: h2, http/1.1, http/1.0
         // No
         #[serde(rename = "applicationProtocols")]
-        pub application_protocols: String,
+        pub application_protocols: Option<String>,
 
         // The name of a specific filter to apply the patch to. Set this to envoy.filters.network.http_connection_manager to add a filter or apply a patch to the HTTP connection manager.
         // No
-        pub filter: FilterMatch,
+        pub filter: Option<FilterMatch>,
 
         // The destinationport value used by a filter chain’s match condition. This condition will evaluate to false if the filter chain has no destinationport match.
         // No
         #[serde(rename = "destinationPort")]
-        pub destination_port: u32,
+        pub destination_port: Option<u32>,
     }
 
     /// # FilterMatch
     /// Conditions to match a specific filter within a filter chain.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct FilterMatch {
         // The filter name to match on. For standard Envoy filters, canonical filter names should be used.
         // No
@@ -712,13 +1010,14 @@ pub mod listener_match {
         // The next level filter within this filter to match upon. Typically used for HTTP Connection Manager filters and Thrift filters.
         // No
         #[serde(rename = "subFilter")]
-        pub sub_filter: SubFilterMatch,
+        pub sub_filter: Option<SubFilterMatch>,
     }
 
     /// # SubFilterMatch
     /// Conditions to match a specific filter within another filter. This field is typically useful to match a HTTP filter inside the `envoy.filters.network.http_connection_manager` network filter. This could also be applicable for thrift filters.
     #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct SubFilterMatch {
         // The filter name to match on.
         // No
@@ -728,8 +1027,11 @@ pub mod listener_match {
 /// #ApplyTo
 /// ApplyTo specifies where in the Envoy configuration, the given patch should be applied.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum ApplyTo {
+    #[default]
     INVALID,
 
     // Applies the patch to the listener.
@@ -763,10 +1065,58 @@ pub enum ApplyTo {
     BOOTSTRAP,
 }
 
+impl ApplyTo {
+    /// The operations istiod applies to this kind of object; others are rejected or ignored.
+    pub fn operations(self) -> &'static [Operation] {
+        use Operation::*;
+        match self {
+            ApplyTo::INVALID => &[],
+            ApplyTo::LISTENER
+            | ApplyTo::FILTER_CHAIN
+            | ApplyTo::VIRTUAL_HOST
+            | ApplyTo::CLUSTER => &[MERGE, ADD, REMOVE],
+            ApplyTo::NETWORK_FILTER | ApplyTo::HTTP_FILTER => &[
+                MERGE,
+                ADD,
+                REMOVE,
+                INSERT_BEFORE,
+                INSERT_AFTER,
+                INSERT_FIRST,
+                REPLACE,
+            ],
+            ApplyTo::HTTP_ROUTE => &[MERGE, REMOVE, INSERT_BEFORE, INSERT_AFTER, INSERT_FIRST],
+            ApplyTo::EXTENSION_CONFIG => &[MERGE, ADD],
+            ApplyTo::ROUTE_CONFIGURATION | ApplyTo::BOOTSTRAP => &[MERGE],
+        }
+    }
+
+    pub fn supports(self, operation: Operation) -> bool {
+        self.operations().contains(&operation)
+    }
+
+    /// The field of `EnvoyConfigObjectMatch` selecting the objects patched, `None` for objects
+    /// that are not matched on.
+    pub fn object_type(self) -> Option<&'static str> {
+        match self {
+            ApplyTo::LISTENER
+            | ApplyTo::FILTER_CHAIN
+            | ApplyTo::NETWORK_FILTER
+            | ApplyTo::HTTP_FILTER => Some("listener"),
+            ApplyTo::ROUTE_CONFIGURATION | ApplyTo::VIRTUAL_HOST | ApplyTo::HTTP_ROUTE => {
+                Some("routeConfiguration")
+            }
+            ApplyTo::CLUSTER => Some("cluster"),
+            ApplyTo::INVALID | ApplyTo::EXTENSION_CONFIG | ApplyTo::BOOTSTRAP => None,
+        }
+    }
+}
+
 /// # PatchContext
 /// PatchContext selects a class of configurations based on the traffic flow direction and workload type.
 #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum PatchContext {
     // All listeners/routes/clusters in both sidecars and gateways.
     ANY,
@@ -0,0 +1,103 @@
+// The `exportTo` visibility of VirtualServices, DestinationRules and ServiceEntries. The reserved
+// values `.` and `*` get their own variants, so a typo shows up as an unexpected namespace instead of
+// silently changing who can see a resource.
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// # ExportTo
+/// A namespace a resource is exported to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(from = "String", into = "String")]
+pub enum ExportTo {
+    /// `.`: the namespace the resource is declared in.
+    CurrentNamespace,
+
+    /// `*`: all namespaces.
+    All,
+
+    Namespace(String),
+}
+
+impl ExportTo {
+    /// Whether a resource declared in `owner` is visible in `namespace` through this entry.
+    pub fn includes(&self, owner: Option<&str>, namespace: Option<&str>) -> bool {
+        match self {
+            ExportTo::All => true,
+            ExportTo::CurrentNamespace => owner == namespace,
+            ExportTo::Namespace(target) => namespace == Some(target.as_str()),
+        }
+    }
+}
+
+/// Whether a resource declared in `owner` with the given `exportTo` is visible in `namespace`. An
+/// unset or empty list exports to all namespaces.
+pub fn is_exported(
+    export_to: Option<&[ExportTo]>,
+    owner: Option<&str>,
+    namespace: Option<&str>,
+) -> bool {
+    match export_to {
+        None => true,
+        Some([]) => true,
+        Some(export_to) => export_to
+            .iter()
+            .any(|target| target.includes(owner, namespace)),
+    }
+}
+
+impl From<String> for ExportTo {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "." => ExportTo::CurrentNamespace,
+            "*" => ExportTo::All,
+            _ => ExportTo::Namespace(value),
+        }
+    }
+}
+
+impl From<&str> for ExportTo {
+    fn from(value: &str) -> Self {
+        ExportTo::from(value.to_string())
+    }
+}
+
+impl FromStr for ExportTo {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ExportTo::from(s))
+    }
+}
+
+impl From<ExportTo> for String {
+    fn from(value: ExportTo) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ExportTo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportTo::CurrentNamespace => write!(f, "."),
+            ExportTo::All => write!(f, "*"),
+            ExportTo::Namespace(namespace) => write!(f, "{}", namespace),
+        }
+    }
+}
+
+/// Serialized as a plain string.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for ExportTo {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "ExportTo".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
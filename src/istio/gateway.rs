@@ -1,8 +1,12 @@
+use crate::istio::host::{self, Host, HostError};
+use crate::istio::status::IstioStatus;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
 use k8s_openapi::{Metadata, Resource};
-use std::collections::HashMap;
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Gateway {
     /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
     pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
@@ -11,7 +15,7 @@ pub struct Gateway {
     pub spec: Option<GatewaySpec>,
 
     /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
-    pub status: Option<()>,
+    pub status: Option<IstioStatus>,
 }
 
 impl Resource for Gateway {
@@ -39,7 +43,8 @@ impl Metadata for Gateway {
 ///
 /// Gateway describes a load balancer operating at the edge of the mesh receiving incoming or outgoing HTTP/TCP connections.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GatewaySpec {
     /// A list of server specifications.
     /// Required: Yes
@@ -54,14 +59,21 @@ pub struct GatewaySpec {
     /// same namespace as the gateway workload instance. If selector is nil, the Gateway will be
     /// applied to all workloads.
     /// Required: Yes
-    pub selector: HashMap<String, String>,
+    pub selector: Map<String, String>,
+
+    /// Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as
+    /// is so that the resource survives a round trip.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// # Server
 ///
 /// Server describes the properties of the proxy on a given load balancer port. For example,
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Server {
     /// The Port on which the proxy should listen for incoming connections.
     /// Required: Yes
@@ -113,10 +125,18 @@ pub struct Server {
     pub name: Option<String>,
 }
 
+impl Server {
+    /// The hosts, parsed with their namespace qualifiers.
+    pub fn parse_hosts(&self) -> Result<Vec<Host>, HostError> {
+        host::parse_all(&self.hosts)
+    }
+}
+
 /// # Port
 /// Port describes the properties of a specific port of a service.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Port {
     /// A valid non-negative integer port number.
     /// Required: Yes
@@ -138,7 +158,8 @@ pub struct Port {
 
 /// # ServerTLSSettings
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ServerTLSSettings {
     /// If set to true, the load balancer will send a 301 redirect for all http connections, asking the clients to use HTTPS.
     /// Required: No
@@ -207,7 +228,9 @@ pub struct ServerTLSSettings {
 /// # ServerTLSSettings.TLSmode
 /// TLS modes enforced by the proxy
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum TLSmode {
     /// The SNI string presented by the client will be used as the match criterion in a VirtualService TLS route to determine the destination service from the service registry.
     PASSTHROUGH,
@@ -228,7 +251,9 @@ pub enum TLSmode {
 /// # ServerTLSSettings.TLSProtocol
 /// TLS protocol versions.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum TLSProtocol {
     /// Automatically choose the optimal TLS version.
     TLS_AUTO,
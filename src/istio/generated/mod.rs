@@ -0,0 +1,3 @@
+// Types generated from the istio/api protos of an Istio release, next to the hand-written types of
+// the parent module. Nothing has been generated yet: `cargo xtask codegen --release <release>`
+// replaces this file with the generated modules.
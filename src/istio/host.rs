@@ -0,0 +1,245 @@
+// Hostnames as used by Istio resources: a DNS name with an optional wildcard in the left-most
+// label (`*.example.com`), optionally qualified by a namespace (`ns/*.example.com`) where a
+// Gateway server or Sidecar selects hosts across namespaces.
+use crate::istio::binding::hosts_intersect;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// # Host
+/// A hostname of the form `[<namespace>/]<dnsName>`. The namespace may be `.` for the namespace of
+/// the declaring resource or `*` for any namespace.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct Host {
+    namespace: Option<String>,
+    name: String,
+}
+
+impl Host {
+    pub fn new(host: &str) -> Result<Self, HostError> {
+        let (namespace, name) = match host.split_once('/') {
+            Some((namespace, name)) => (Some(namespace), name),
+            None => (None, host),
+        };
+        if namespace.is_some_and(str::is_empty) {
+            return Err(HostError::EmptyNamespace(host.to_string()));
+        }
+        if name.is_empty() {
+            return Err(HostError::Empty);
+        }
+        if name != "*"
+            && name
+                .rfind('*')
+                .is_some_and(|i| i != 0 || !name[1..].starts_with('.'))
+        {
+            return Err(HostError::InvalidWildcard(host.to_string()));
+        }
+        Ok(Host {
+            namespace: namespace.map(str::to_string),
+            name: name.to_string(),
+        })
+    }
+
+    /// The namespace qualifier, e.g. `ns` of `ns/*.example.com`.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The DNS name, without the namespace qualifier.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.name.starts_with('*')
+    }
+
+    /// Whether every name `other` stands for is matched by this host: `*.example.com` matches
+    /// `api.example.com` and `*.api.example.com`, but not `example.com`. Namespaces are ignored.
+    pub fn matches(&self, other: &Host) -> bool {
+        match self.name.strip_prefix('*') {
+            Some(suffix) => other.name.ends_with(suffix),
+            None => self.name == other.name,
+        }
+    }
+
+    /// Whether this host and `other` have a name in common. Namespaces are ignored.
+    pub fn intersects(&self, other: &Host) -> bool {
+        hosts_intersect(&self.name, &other.name)
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}/{}", namespace, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl FromStr for Host {
+    type Err = HostError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Host::new(s)
+    }
+}
+
+impl TryFrom<String> for Host {
+    type Error = HostError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Host::new(&value)
+    }
+}
+
+impl From<Host> for String {
+    fn from(host: Host) -> Self {
+        host.to_string()
+    }
+}
+
+/// # HostError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HostError {
+    Empty,
+    EmptyNamespace(String),
+
+    /// A wildcard that is not the whole left-most label.
+    InvalidWildcard(String),
+}
+
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostError::Empty => write!(f, "host is required"),
+            HostError::EmptyNamespace(host) => write!(f, "empty namespace in {}", host),
+            HostError::InvalidWildcard(host) => {
+                write!(f, "wildcard must be the left-most label of {}", host)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HostError {}
+
+/// Parses a list of hosts, failing on the first invalid one.
+pub(crate) fn parse_all<'a>(
+    hosts: impl IntoIterator<Item = &'a String>,
+) -> Result<Vec<Host>, HostError> {
+    hosts.into_iter().map(|host| Host::new(host)).collect()
+}
+
+/// Serialized as a plain string.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Host {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "Host".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    fn host(host: &str) -> Host {
+        Host::new(host).unwrap()
+    }
+
+    #[test]
+    fn parses_the_namespace_qualifier() {
+        let qualified = host("shop/reviews.shop.svc.cluster.local");
+        assert_eq!(qualified.namespace(), Some("shop"));
+        assert_eq!(qualified.name(), "reviews.shop.svc.cluster.local");
+        assert!(!qualified.is_wildcard());
+        assert_eq!(qualified.to_string(), "shop/reviews.shop.svc.cluster.local");
+
+        let any_namespace = host("*/api.example.com");
+        assert_eq!(any_namespace.namespace(), Some("*"));
+        assert_eq!(any_namespace.name(), "api.example.com");
+
+        assert_eq!(host("./*.example.com").namespace(), Some("."));
+        assert_eq!(host("api.example.com").namespace(), None);
+    }
+
+    #[test]
+    fn wildcards_match_the_names_ending_with_their_suffix() {
+        let wildcard = host("*.example.com");
+        assert!(wildcard.is_wildcard());
+        assert!(wildcard.matches(&host("api.example.com")));
+        assert!(wildcard.matches(&host("*.api.example.com")));
+        assert!(wildcard.matches(&host("*.example.com")));
+        assert!(!wildcard.matches(&host("example.com")));
+        assert!(!wildcard.matches(&host("*.com")));
+        // A wildcard is matched by nothing but a broader wildcard.
+        assert!(!host("api.example.com").matches(&wildcard));
+        // Namespaces are ignored.
+        assert!(host("shop/*.example.com").matches(&host("web/api.example.com")));
+
+        let everything = host("*");
+        assert!(everything.is_wildcard());
+        assert!(everything.matches(&host("api.example.com")));
+        assert!(everything.matches(&host("*.example.com")));
+        assert!(everything.matches(&host("*/*")));
+    }
+
+    #[test]
+    fn intersects_when_a_name_is_shared() {
+        assert!(host("*.example.com").intersects(&host("api.example.com")));
+        assert!(host("api.example.com").intersects(&host("*.example.com")));
+        assert!(host("*.example.com").intersects(&host("*.api.example.com")));
+        assert!(host("*").intersects(&host("api.example.com")));
+        assert!(!host("*.example.com").intersects(&host("*.example.org")));
+        assert!(!host("api.example.com").intersects(&host("web.example.com")));
+    }
+
+    #[test]
+    fn rejects_malformed_hosts() {
+        assert_eq!(Host::new(""), Err(HostError::Empty));
+        assert_eq!(Host::new("shop/"), Err(HostError::Empty));
+        assert_eq!(
+            Host::new("/api.example.com"),
+            Err(HostError::EmptyNamespace("/api.example.com".to_string()))
+        );
+        for invalid in [
+            "api.*.com",
+            "*api.example.com",
+            "api.example.*",
+            "**",
+            "*.*.com",
+        ] {
+            assert_eq!(
+                Host::new(invalid),
+                Err(HostError::InvalidWildcard(invalid.to_string())),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            Host::new("shop/api*").unwrap_err().to_string(),
+            "wildcard must be the left-most label of shop/api*"
+        );
+    }
+
+    #[test]
+    fn serializes_as_a_string() {
+        let hosts: Vec<Host> = serde_json::from_value(json!(["*/*.example.com", "web"])).unwrap();
+        assert_eq!(hosts, vec![host("*/*.example.com"), host("web")]);
+        assert_eq!(
+            serde_json::to_value(&hosts).unwrap(),
+            json!(["*/*.example.com", "web"])
+        );
+        assert!(serde_json::from_value::<Host>(json!("api.*.com")).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// Typed payloads for EnvoyFilter patches inserting common HTTP filters. Each payload serializes to
+// the `typed_config` of its filter, so a patch value can be built without spelling out the Envoy
+// configuration as an untyped `Struct`.
+use crate::istio::envoy_filter::patch::Operation;
+use crate::istio::envoy_filter::Patch;
+use crate::istio::google::Struct;
+use crate::istio::rate_limit::{structure, TokenBucket, LOCAL_RATE_LIMIT_FILTER};
+use k8s_openapi::serde_json::{self, json, Value};
+use serde::{Serialize, Serializer};
+
+/// # HttpFilter
+/// An Envoy HTTP filter that can be inserted by an EnvoyFilter patch.
+pub trait HttpFilter: Serialize {
+    /// The name of the filter in the filter chain, e.g. `envoy.filters.http.lua`.
+    const NAME: &'static str;
+
+    /// The type URL of the filter configuration.
+    const TYPE_URL: &'static str;
+
+    /// The fields of the `typed_config` of the filter, without `@type`.
+    fn typed_config(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    /// The patch value declaring the filter: its name and `typed_config`.
+    fn to_struct(&self) -> Struct {
+        let mut typed_config = json!({ "@type": Self::TYPE_URL });
+        if let Value::Object(fields) = self.typed_config() {
+            typed_config
+                .as_object_mut()
+                .expect("built as an object")
+                .extend(fields);
+        }
+        structure(json!({
+            "name": Self::NAME,
+            "typed_config": typed_config,
+        }))
+    }
+
+    /// A patch inserting the filter with `operation`, usually `INSERT_BEFORE` the router.
+    fn patch(&self, operation: Operation) -> Patch {
+        Patch {
+            operation: Some(operation),
+            value: Some(self.to_struct()),
+            filter_class: None,
+        }
+    }
+}
+
+/// # LuaFilter
+/// Runs a Lua script on every request and response.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LuaFilter {
+    /// The script, defining `envoy_on_request` and/or `envoy_on_response`.
+    pub inline_code: String,
+}
+
+impl LuaFilter {
+    pub fn new(inline_code: &str) -> Self {
+        LuaFilter {
+            inline_code: inline_code.to_string(),
+        }
+    }
+}
+
+impl HttpFilter for LuaFilter {
+    const NAME: &'static str = "envoy.filters.http.lua";
+    const TYPE_URL: &'static str = "type.googleapis.com/envoy.extensions.filters.http.lua.v3.Lua";
+}
+
+/// # FractionalPercent
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FractionalPercent {
+    pub numerator: u32,
+    pub denominator: Denominator,
+}
+
+impl FractionalPercent {
+    pub fn percent(numerator: u32) -> Self {
+        FractionalPercent {
+            numerator,
+            denominator: Denominator::HUNDRED,
+        }
+    }
+}
+
+/// # Denominator
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum Denominator {
+    HUNDRED,
+    TEN_THOUSAND,
+    MILLION,
+}
+
+/// # RuntimeFractionalPercent
+/// A percentage that can be overridden at runtime under `runtime_key`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeFractionalPercent {
+    pub default_value: FractionalPercent,
+    pub runtime_key: String,
+}
+
+/// # LocalRateLimitFilter
+/// The local rate limit filter with a single token bucket for all requests. See
+/// `rate_limit::LocalRateLimit` for per-descriptor limits.
+#[skip_serializing_none]
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LocalRateLimitFilter {
+    pub stat_prefix: String,
+
+    #[serde(serialize_with = "token_bucket")]
+    pub token_bucket: Option<TokenBucket>,
+
+    /// The share of requests the filter checks. The filter does nothing while unset.
+    pub filter_enabled: Option<RuntimeFractionalPercent>,
+
+    /// The share of checked requests that are rejected when over the limit, the others are only
+    /// counted.
+    pub filter_enforced: Option<RuntimeFractionalPercent>,
+}
+
+impl LocalRateLimitFilter {
+    /// Enforces `bucket` on all requests.
+    pub fn new(stat_prefix: &str, bucket: TokenBucket) -> Self {
+        let percent = |runtime_key: &str| RuntimeFractionalPercent {
+            default_value: FractionalPercent::percent(100),
+            runtime_key: runtime_key.to_string(),
+        };
+        LocalRateLimitFilter {
+            stat_prefix: stat_prefix.to_string(),
+            token_bucket: Some(bucket),
+            filter_enabled: Some(percent("local_rate_limit_enabled")),
+            filter_enforced: Some(percent("local_rate_limit_enforced")),
+        }
+    }
+}
+
+impl HttpFilter for LocalRateLimitFilter {
+    const NAME: &'static str = LOCAL_RATE_LIMIT_FILTER;
+    const TYPE_URL: &'static str =
+        "type.googleapis.com/envoy.extensions.filters.http.local_ratelimit.v3.LocalRateLimit";
+}
+
+/// # HeaderMutationFilter
+/// Adds, overwrites or removes request and response headers.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeaderMutationFilter {
+    pub mutations: Mutations,
+}
+
+impl HeaderMutationFilter {
+    pub fn on_request(mut self, mutation: HeaderMutation) -> Self {
+        self.mutations.request_mutations.push(mutation);
+        self
+    }
+
+    pub fn on_response(mut self, mutation: HeaderMutation) -> Self {
+        self.mutations.response_mutations.push(mutation);
+        self
+    }
+}
+
+impl HttpFilter for HeaderMutationFilter {
+    const NAME: &'static str = "envoy.filters.http.header_mutation";
+    const TYPE_URL: &'static str =
+        "type.googleapis.com/envoy.extensions.filters.http.header_mutation.v3.HeaderMutation";
+}
+
+/// # Mutations
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mutations {
+    pub request_mutations: Vec<HeaderMutation>,
+    pub response_mutations: Vec<HeaderMutation>,
+}
+
+/// # HeaderMutation
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderMutation {
+    Append(HeaderValueOption),
+
+    /// Removes the header with the given name.
+    Remove(String),
+}
+
+impl HeaderMutation {
+    /// Sets `key` to `value`, replacing earlier values.
+    pub fn set(key: &str, value: &str) -> Self {
+        HeaderMutation::Append(HeaderValueOption {
+            header: HeaderValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+            append_action: AppendAction::OVERWRITE_IF_EXISTS_OR_ADD,
+        })
+    }
+
+    /// Adds `value` to the values of `key`.
+    pub fn append(key: &str, value: &str) -> Self {
+        HeaderMutation::Append(HeaderValueOption {
+            header: HeaderValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+            append_action: AppendAction::APPEND_IF_EXISTS_OR_ADD,
+        })
+    }
+
+    pub fn remove(key: &str) -> Self {
+        HeaderMutation::Remove(key.to_string())
+    }
+}
+
+/// # HeaderValueOption
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HeaderValueOption {
+    pub header: HeaderValue,
+    pub append_action: AppendAction,
+}
+
+/// # HeaderValue
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HeaderValue {
+    pub key: String,
+    pub value: String,
+}
+
+/// # AppendAction
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum AppendAction {
+    APPEND_IF_EXISTS_OR_ADD,
+    ADD_IF_ABSENT,
+    OVERWRITE_IF_EXISTS_OR_ADD,
+    OVERWRITE_IF_EXISTS,
+}
+
+/// # GzipFilter
+/// Compresses responses with gzip. Unset fields keep the Envoy defaults.
+#[skip_serializing_none]
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GzipFilter {
+    /// 1 to 9, how much memory the compressor may use.
+    pub memory_level: Option<u32>,
+
+    pub compression_level: Option<CompressionLevel>,
+
+    /// 9 to 15, the base two logarithm of the compressor window size.
+    pub window_bits: Option<u32>,
+
+    /// Responses shorter than this are sent uncompressed.
+    pub min_content_length: Option<u32>,
+
+    /// The content types compressed, Envoy compresses common text types by default.
+    pub content_types: Option<Vec<String>>,
+}
+
+impl HttpFilter for GzipFilter {
+    const NAME: &'static str = "envoy.filters.http.compressor";
+    const TYPE_URL: &'static str =
+        "type.googleapis.com/envoy.extensions.filters.http.compressor.v3.Compressor";
+
+    /// The gzip settings go into the compressor library, the content settings into the response
+    /// direction of the compressor.
+    fn typed_config(&self) -> Value {
+        let mut gzip = json!({
+            "@type": "type.googleapis.com/envoy.extensions.compression.gzip.compressor.v3.Gzip",
+        });
+        let mut common_config = json!({});
+        if let Some(memory_level) = self.memory_level {
+            gzip["memory_level"] = json!(memory_level);
+        }
+        if let Some(compression_level) = self.compression_level {
+            gzip["compression_level"] = json!(compression_level);
+        }
+        if let Some(window_bits) = self.window_bits {
+            gzip["window_bits"] = json!(window_bits);
+        }
+        if let Some(min_content_length) = self.min_content_length {
+            common_config["min_content_length"] = json!(min_content_length);
+        }
+        if let Some(content_types) = &self.content_types {
+            common_config["content_type"] = json!(content_types);
+        }
+        json!({
+            "compressor_library": {
+                "name": "text_optimized",
+                "typed_config": gzip,
+            },
+            "response_direction_config": {
+                "common_config": common_config,
+            },
+        })
+    }
+}
+
+/// # CompressionLevel
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum CompressionLevel {
+    DEFAULT_COMPRESSION,
+    BEST_SPEED,
+    COMPRESSION_LEVEL_1,
+    COMPRESSION_LEVEL_2,
+    COMPRESSION_LEVEL_3,
+    COMPRESSION_LEVEL_4,
+    COMPRESSION_LEVEL_5,
+    COMPRESSION_LEVEL_6,
+    COMPRESSION_LEVEL_7,
+    COMPRESSION_LEVEL_8,
+    COMPRESSION_LEVEL_9,
+    BEST_COMPRESSION,
+}
+
+fn token_bucket<S: Serializer>(
+    bucket: &Option<TokenBucket>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    bucket.map(TokenBucket::to_value).serialize(serializer)
+}
@@ -0,0 +1,589 @@
+// Migration of Kubernetes Ingresses to Istio. An Ingress becomes a Gateway accepting its hosts on
+// the ingress gateway pods and a VirtualService per host routing its paths, following the
+// conversion istiod applies to the Ingresses it serves itself: Exact paths come before Prefix
+// paths, longer paths before shorter ones, and a Prefix path matches whole path elements only.
+// What has no Istio equivalent, e.g. resource backends or the annotations of an ingress
+// controller, is reported in `IngressResources::unsupported`, so nothing is dropped silently.
+use crate::istio::gateway::{Gateway, GatewaySpec, Port, Server, ServerTLSSettings, TLSmode};
+use crate::istio::validate::{Validate, ValidationErrors};
+use crate::istio::virtual_service::{
+    Destination, HttpMatchRequest, HttpRoute, HttpRouteDestination, PortSelector, StringMatch,
+    VirtualService, VirtualServiceSpec,
+};
+use crate::istio::Map;
+use k8s_openapi::api::networking::v1::{Ingress, IngressBackend};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::Resource;
+use std::fmt;
+
+/// The annotation selecting the ingress controller before `spec.ingressClassName`; it needs no
+/// equivalent once the Ingress is converted.
+pub const INGRESS_CLASS_ANNOTATION: &str = "kubernetes.io/ingress.class";
+
+/// # IngressResources
+/// The Istio resources equivalent to an Ingress.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IngressResources {
+    pub gateway: Gateway,
+
+    /// A VirtualService per host of the Ingress, `*` for the rules without host.
+    pub virtual_services: Vec<VirtualService>,
+
+    /// Paths of the fields that were not converted, e.g. `spec.rules[0].http.paths[1].backend.resource`.
+    pub unsupported: Vec<String>,
+}
+
+/// # IngressError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngressError {
+    /// The Ingress has no name to derive the names of the resources from.
+    MissingName,
+
+    /// The Ingress routes nothing: it has neither a path with a service backend nor a default
+    /// backend.
+    NoRoutes,
+
+    /// A converted resource failed validation.
+    Invalid {
+        kind: &'static str,
+        name: String,
+        errors: ValidationErrors,
+    },
+}
+
+impl fmt::Display for IngressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngressError::MissingName => write!(f, "ingress has no name"),
+            IngressError::NoRoutes => write!(f, "ingress routes no path to a service"),
+            IngressError::Invalid { kind, name, errors } => {
+                write!(f, "converted {} {} is invalid: {}", kind, name, errors)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IngressError {}
+
+/// # IngressConverter
+/// How Ingresses are converted: the ingress gateway pods serving them, the namespace of the
+/// Gateways and the domain of the cluster the backends are addressed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IngressConverter {
+    /// The labels of the ingress gateway pods, `istio: ingressgateway` by default.
+    pub selector: Map<String, String>,
+
+    /// The namespace of the Gateways; the namespace of the Ingress when unset. The secrets named
+    /// by `credentialName` are read from the namespace of the ingress gateway pods, so the TLS
+    /// secrets of the Ingress have to be copied there.
+    pub gateway_namespace: Option<String>,
+
+    pub cluster_domain: String,
+}
+
+impl Default for IngressConverter {
+    fn default() -> Self {
+        IngressConverter {
+            selector: Map::from([("istio".to_string(), "ingressgateway".to_string())]),
+            gateway_namespace: None,
+            cluster_domain: "cluster.local".to_string(),
+        }
+    }
+}
+
+impl IngressConverter {
+    pub fn with_selector(mut self, selector: Map<String, String>) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    pub fn with_gateway_namespace(mut self, namespace: &str) -> Self {
+        self.gateway_namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn with_cluster_domain(mut self, domain: &str) -> Self {
+        self.cluster_domain = domain.to_string();
+        self
+    }
+
+    /// Converts `ingress`. The Gateway is named `<ingress>-gateway`, the VirtualServices
+    /// `<ingress>-<host>` with the dots of the host replaced by dashes.
+    pub fn convert(&self, ingress: &Ingress) -> Result<IngressResources, IngressError> {
+        let name = ingress
+            .metadata
+            .name
+            .as_deref()
+            .ok_or(IngressError::MissingName)?;
+        let namespace = ingress.metadata.namespace.as_deref();
+        let spec = ingress.spec.clone().unwrap_or_default();
+        let mut unsupported: Vec<String> = ingress
+            .metadata
+            .annotations
+            .iter()
+            .flatten()
+            .map(|(key, _)| key)
+            .filter(|key| key.contains("ingress") && key.as_str() != INGRESS_CLASS_ANNOTATION)
+            .map(|key| format!("metadata.annotations.{}", key))
+            .collect();
+
+        let mut hosts: Vec<(String, Vec<PathRoute>)> = Vec::new();
+        for (i, rule) in spec.rules.iter().flatten().enumerate() {
+            let host = rule.host.clone().unwrap_or_else(|| "*".to_string());
+            let mut routes = Vec::new();
+            for (j, path) in rule.http.iter().flat_map(|http| &http.paths).enumerate() {
+                let field = format!("spec.rules[{}].http.paths[{}]", i, j);
+                let destination = match self.destination(
+                    &path.backend,
+                    namespace,
+                    &format!("{}.backend", field),
+                    &mut unsupported,
+                ) {
+                    Some(destination) => destination,
+                    None => continue,
+                };
+                let value = path.path.as_deref().unwrap_or("/");
+                routes.push(PathRoute {
+                    exact: path.path_type == "Exact",
+                    length: value.len(),
+                    route: route(Some(matches(&path.path_type, value)), destination),
+                });
+            }
+            match hosts.iter_mut().find(|(candidate, _)| *candidate == host) {
+                Some((_, existing)) => existing.extend(routes),
+                None => hosts.push((host, routes)),
+            }
+        }
+        let default_route = spec.default_backend.as_ref().and_then(|backend| {
+            self.destination(backend, namespace, "spec.defaultBackend", &mut unsupported)
+                .map(|destination| route(None, destination))
+        });
+        if default_route.is_some() && hosts.iter().all(|(host, _)| host != "*") {
+            hosts.push(("*".to_string(), Vec::new()));
+        }
+
+        let gateway_name = format!("{}-gateway", name);
+        let gateway_ref = match &self.gateway_namespace {
+            Some(gateway_namespace) => format!("{}/{}", gateway_namespace, gateway_name),
+            None => gateway_name.clone(),
+        };
+        let mut virtual_services = Vec::new();
+        for (host, mut routes) in hosts {
+            // Exact paths first, then the longest paths; the sort is stable, so paths of the same
+            // length keep the order of the Ingress.
+            routes.sort_by(|a, b| b.exact.cmp(&a.exact).then(b.length.cmp(&a.length)));
+            let mut http: Vec<HttpRoute> = routes.into_iter().map(|path| path.route).collect();
+            http.extend(default_route.clone());
+            if http.is_empty() {
+                continue;
+            }
+            virtual_services.push(VirtualService {
+                metadata: metadata(
+                    ingress,
+                    format!(
+                        "{}-{}",
+                        name,
+                        host.replace('*', "wildcard").replace('.', "-")
+                    ),
+                    namespace,
+                ),
+                spec: Some(VirtualServiceSpec {
+                    hosts: Some(vec![host]),
+                    gateways: Some(vec![gateway_ref.clone()]),
+                    http: Some(http),
+                    ..VirtualServiceSpec::default()
+                }),
+                status: None,
+            });
+        }
+        if virtual_services.is_empty() {
+            return Err(IngressError::NoRoutes);
+        }
+
+        let routed: Vec<String> = virtual_services
+            .iter()
+            .flat_map(|vs| vs.spec.iter().flat_map(|spec| spec.hosts.iter().flatten()))
+            .cloned()
+            .collect();
+        let mut servers = vec![server(80, "HTTP", "http", routed.clone(), None)];
+        for (i, tls) in spec.tls.iter().flatten().enumerate() {
+            let secret = match &tls.secret_name {
+                Some(secret) => secret,
+                None => {
+                    unsupported.push(format!("spec.tls[{}]", i));
+                    continue;
+                }
+            };
+            servers.push(server(
+                443,
+                "HTTPS",
+                &format!("https-{}", i),
+                tls.hosts.clone().unwrap_or_else(|| routed.clone()),
+                Some(ServerTLSSettings {
+                    mode: Some(TLSmode::SIMPLE),
+                    credential_name: Some(secret.clone()),
+                    ..ServerTLSSettings::default()
+                }),
+            ));
+        }
+        let gateway = Gateway {
+            metadata: metadata(
+                ingress,
+                gateway_name,
+                self.gateway_namespace.as_deref().or(namespace),
+            ),
+            spec: Some(GatewaySpec {
+                servers,
+                selector: self.selector.clone(),
+                extra: Map::new(),
+            }),
+            status: None,
+        };
+
+        check(&gateway, &gateway.metadata)?;
+        for vs in &virtual_services {
+            check(vs, &vs.metadata)?;
+        }
+        Ok(IngressResources {
+            gateway,
+            virtual_services,
+            unsupported,
+        })
+    }
+
+    /// The destination of a service backend, addressed by its FQDN. Resource backends and ports
+    /// selected by name have no equivalent and are reported at `field`.
+    fn destination(
+        &self,
+        backend: &IngressBackend,
+        namespace: Option<&str>,
+        field: &str,
+        unsupported: &mut Vec<String>,
+    ) -> Option<Destination> {
+        if backend.resource.is_some() {
+            unsupported.push(format!("{}.resource", field));
+        }
+        let service = backend.service.as_ref()?;
+        let port = service.port.as_ref().and_then(|port| match port.number {
+            Some(number) => Some(PortSelector {
+                number: Some(number as u32),
+                name: None,
+            }),
+            None => {
+                if port.name.is_some() {
+                    unsupported.push(format!("{}.service.port.name", field));
+                }
+                None
+            }
+        });
+        Some(Destination {
+            host: format!(
+                "{}.{}.svc.{}",
+                service.name,
+                namespace.unwrap_or("default"),
+                self.cluster_domain
+            ),
+            subset: None,
+            port,
+        })
+    }
+}
+
+/// Converts `ingress` for the default ingress gateway, see `IngressConverter::convert`.
+pub fn convert(ingress: &Ingress) -> Result<IngressResources, IngressError> {
+    IngressConverter::default().convert(ingress)
+}
+
+struct PathRoute {
+    exact: bool,
+    length: usize,
+    route: HttpRoute,
+}
+
+/// The URI matches of an Ingress path. A Prefix path `/foo` matches `/foo` and `/foo/bar` but not
+/// `/foobar`, so it becomes an exact match on `/foo` and a prefix match on `/foo/`. Paths of the
+/// ImplementationSpecific type are taken as prefixes, with a trailing `*` or `.*` stripped.
+fn matches(path_type: &str, path: &str) -> Vec<HttpMatchRequest> {
+    let uri = |uri: StringMatch| HttpMatchRequest {
+        uri: Some(uri),
+        ..HttpMatchRequest::default()
+    };
+    match path_type {
+        "Exact" => vec![uri(StringMatch::Exact(path.to_string()))],
+        "Prefix" => match path.trim_end_matches('/') {
+            "" => vec![uri(StringMatch::Prefix("/".to_string()))],
+            path => vec![
+                uri(StringMatch::Exact(path.to_string())),
+                uri(StringMatch::Prefix(format!("{}/", path))),
+            ],
+        },
+        _ => match path.trim_end_matches('*').trim_end_matches('.') {
+            "" => vec![uri(StringMatch::Prefix("/".to_string()))],
+            path => vec![uri(StringMatch::Prefix(path.to_string()))],
+        },
+    }
+}
+
+fn route(matches: Option<Vec<HttpMatchRequest>>, destination: Destination) -> HttpRoute {
+    HttpRoute {
+        r#match: matches,
+        route: Some(vec![HttpRouteDestination {
+            destination,
+            weight: None,
+            headers: None,
+            extra: Map::new(),
+        }]),
+        ..HttpRoute::default()
+    }
+}
+
+fn server(
+    number: i32,
+    protocol: &str,
+    name: &str,
+    hosts: Vec<String>,
+    tls: Option<ServerTLSSettings>,
+) -> Server {
+    Server {
+        port: Port {
+            number,
+            protocol: protocol.to_string(),
+            name: name.to_string(),
+            target_port: None,
+        },
+        bind: None,
+        hosts,
+        tls,
+        name: None,
+    }
+}
+
+/// The metadata of a converted resource, carrying the labels of the Ingress.
+fn metadata(ingress: &Ingress, name: String, namespace: Option<&str>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name),
+        namespace: namespace.map(str::to_string),
+        labels: ingress.metadata.labels.clone(),
+        ..ObjectMeta::default()
+    }
+}
+
+fn check<R: Validate + Resource>(resource: &R, metadata: &ObjectMeta) -> Result<(), IngressError> {
+    resource.validate().map_err(|errors| IngressError::Invalid {
+        kind: R::KIND,
+        name: metadata.name.clone().unwrap_or_default(),
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn ingress(metadata: Value, spec: Value) -> Ingress {
+        serde_json::from_value(json!({ "metadata": metadata, "spec": spec })).unwrap()
+    }
+
+    fn backend(name: &str, port: u16) -> Value {
+        json!({ "service": { "name": name, "port": { "number": port } } })
+    }
+
+    fn http(vs: &VirtualService) -> Value {
+        serde_json::to_value(&vs.spec.as_ref().unwrap().http).unwrap()
+    }
+
+    #[test]
+    fn converts_rules_into_a_gateway_and_a_virtual_service_per_host() {
+        let ingress = ingress(
+            json!({ "name": "shop", "namespace": "web", "labels": { "app": "shop" } }),
+            json!({
+                "rules": [
+                    { "host": "shop.example.com", "http": { "paths": [
+                        { "path": "/", "pathType": "Prefix", "backend": backend("frontend", 80) },
+                        { "path": "/api/", "pathType": "Prefix", "backend": backend("api", 8080) },
+                        { "path": "/healthz", "pathType": "Exact", "backend": backend("frontend", 80) },
+                    ] } },
+                    { "http": { "paths": [
+                        { "path": "/static.*", "pathType": "ImplementationSpecific", "backend": backend("cdn", 80) },
+                    ] } },
+                ],
+            }),
+        );
+
+        let resources = convert(&ingress).unwrap();
+
+        assert_eq!(
+            resources.gateway.metadata.name.as_deref(),
+            Some("shop-gateway")
+        );
+        assert_eq!(resources.gateway.metadata.namespace.as_deref(), Some("web"));
+        let gateway = resources.gateway.spec.as_ref().unwrap();
+        assert_eq!(gateway.selector["istio"], "ingressgateway");
+        assert_eq!(gateway.servers.len(), 1);
+        assert_eq!(gateway.servers[0].hosts, vec!["shop.example.com", "*"]);
+
+        let names: Vec<_> = resources
+            .virtual_services
+            .iter()
+            .map(|vs| vs.metadata.name.clone().unwrap())
+            .collect();
+        assert_eq!(names, vec!["shop-shop-example-com", "shop-wildcard"]);
+        let shop = &resources.virtual_services[0];
+        assert_eq!(
+            shop.metadata.labels,
+            Some(std::collections::BTreeMap::from([(
+                "app".to_string(),
+                "shop".to_string()
+            )]))
+        );
+        assert_eq!(
+            shop.spec.as_ref().unwrap().gateways,
+            Some(vec!["shop-gateway".to_string()])
+        );
+        assert_eq!(
+            http(shop),
+            json!([
+                {
+                    "match": [{ "uri": { "exact": "/healthz" } }],
+                    "route": [{ "destination": { "host": "frontend.web.svc.cluster.local", "port": { "number": 80 } } }],
+                },
+                {
+                    "match": [{ "uri": { "exact": "/api" } }, { "uri": { "prefix": "/api/" } }],
+                    "route": [{ "destination": { "host": "api.web.svc.cluster.local", "port": { "number": 8080 } } }],
+                },
+                {
+                    "match": [{ "uri": { "prefix": "/" } }],
+                    "route": [{ "destination": { "host": "frontend.web.svc.cluster.local", "port": { "number": 80 } } }],
+                },
+            ])
+        );
+        assert_eq!(
+            http(&resources.virtual_services[1])[0]["match"],
+            json!([{ "uri": { "prefix": "/static" } }])
+        );
+        assert!(resources.unsupported.is_empty());
+    }
+
+    #[test]
+    fn appends_the_default_backend_to_every_host() {
+        let ingress = ingress(
+            json!({ "name": "shop", "namespace": "web" }),
+            json!({
+                "defaultBackend": backend("fallback", 80),
+                "rules": [{ "host": "shop.example.com", "http": { "paths": [
+                    { "path": "/api", "pathType": "Exact", "backend": backend("api", 80) },
+                ] } }],
+            }),
+        );
+
+        let resources = convert(&ingress).unwrap();
+
+        assert_eq!(resources.virtual_services.len(), 2);
+        for vs in &resources.virtual_services {
+            let http = http(vs);
+            let last = http.as_array().unwrap().last().unwrap();
+            assert!(last.get("match").is_none());
+            assert_eq!(
+                last["route"][0]["destination"]["host"],
+                "fallback.web.svc.cluster.local"
+            );
+        }
+    }
+
+    #[test]
+    fn adds_an_https_server_per_tls_secret() {
+        let ingress = ingress(
+            json!({ "name": "shop", "namespace": "web" }),
+            json!({
+                "tls": [{ "hosts": ["shop.example.com"], "secretName": "shop-cert" }, { "hosts": ["other"] }],
+                "rules": [{ "host": "shop.example.com", "http": { "paths": [
+                    { "path": "/", "pathType": "Prefix", "backend": backend("frontend", 80) },
+                ] } }],
+            }),
+        );
+
+        let resources = IngressConverter::default()
+            .with_gateway_namespace("istio-system")
+            .with_cluster_domain("example.internal")
+            .convert(&ingress)
+            .unwrap();
+
+        assert_eq!(
+            resources.gateway.metadata.namespace.as_deref(),
+            Some("istio-system")
+        );
+        let servers = &resources.gateway.spec.as_ref().unwrap().servers;
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[1].port.number, 443);
+        assert_eq!(servers[1].port.name, "https-0");
+        let tls = servers[1].tls.as_ref().unwrap();
+        assert_eq!(tls.mode, Some(TLSmode::SIMPLE));
+        assert_eq!(tls.credential_name.as_deref(), Some("shop-cert"));
+        let spec = resources.virtual_services[0].spec.as_ref().unwrap();
+        assert_eq!(
+            spec.gateways,
+            Some(vec!["istio-system/shop-gateway".to_string()])
+        );
+        assert_eq!(
+            http(&resources.virtual_services[0])[0]["route"][0]["destination"]["host"],
+            "frontend.web.svc.example.internal"
+        );
+        assert_eq!(resources.unsupported, vec!["spec.tls[1]"]);
+    }
+
+    #[test]
+    fn reports_what_has_no_istio_equivalent() {
+        let ingress = ingress(
+            json!({
+                "name": "shop",
+                "namespace": "web",
+                "annotations": {
+                    "kubernetes.io/ingress.class": "nginx",
+                    "nginx.ingress.kubernetes.io/rewrite-target": "/",
+                    "team": "web",
+                },
+            }),
+            json!({
+                "rules": [{ "host": "shop.example.com", "http": { "paths": [
+                    { "path": "/", "pathType": "Prefix", "backend": { "service": { "name": "frontend", "port": { "name": "http" } } } },
+                    { "path": "/assets", "pathType": "Prefix", "backend": { "resource": { "kind": "StorageBucket", "name": "assets" } } },
+                ] } }],
+            }),
+        );
+
+        let resources = convert(&ingress).unwrap();
+
+        assert_eq!(
+            resources.unsupported,
+            vec![
+                "metadata.annotations.nginx.ingress.kubernetes.io/rewrite-target",
+                "spec.rules[0].http.paths[0].backend.service.port.name",
+                "spec.rules[0].http.paths[1].backend.resource",
+            ]
+        );
+        assert_eq!(
+            http(&resources.virtual_services[0])[0]["route"][0]["destination"],
+            json!({ "host": "frontend.web.svc.cluster.local" })
+        );
+    }
+
+    #[test]
+    fn rejects_ingresses_without_name_or_routes() {
+        let unnamed = ingress(
+            json!({}),
+            json!({ "defaultBackend": backend("frontend", 80) }),
+        );
+        assert_eq!(convert(&unnamed), Err(IngressError::MissingName));
+
+        let empty = ingress(
+            json!({ "name": "shop" }),
+            json!({ "rules": [{ "host": "shop.example.com" }] }),
+        );
+        assert_eq!(convert(&empty), Err(IngressError::NoRoutes));
+        assert_eq!(
+            IngressError::NoRoutes.to_string(),
+            "ingress routes no path to a service"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use crate::istio::mesh_config::MeshConfig;
+use crate::istio::Map;
+use k8s_openapi::api::apps::v1::DeploymentStrategy;
+use k8s_openapi::api::core::v1::{
+    Affinity, EnvVar, PodSecurityContext, Probe, ResourceRequirements, ServiceSpec, Toleration,
+    Volume, VolumeMount,
+};
+use k8s_openapi::api::policy::v1::PodDisruptionBudgetSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioOperator {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<IstioOperatorSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for IstioOperator {
+    const API_VERSION: &'static str = "install.istio.io/v1alpha1";
+    const GROUP: &'static str = "install.istio.io";
+    const KIND: &'static str = "IstioOperator";
+    const VERSION: &'static str = "v1alpha1";
+    const URL_PATH_SEGMENT: &'static str = "istiooperators";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for IstioOperator {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl IstioOperator {
+    /// An operator installing `profile`, e.g. `default` or `minimal`.
+    pub fn new(name: &str, namespace: &str, profile: &str) -> Self {
+        IstioOperator {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(IstioOperatorSpec {
+                profile: Some(profile.to_string()),
+                ..IstioOperatorSpec::default()
+            }),
+            status: None,
+        }
+    }
+
+    pub fn with_revision(mut self, revision: &str) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.revision = Some(revision.to_string());
+        }
+        self
+    }
+
+    pub fn with_mesh_config(mut self, mesh_config: MeshConfig) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.mesh_config = Some(mesh_config);
+        }
+        self
+    }
+
+    pub fn with_components(mut self, components: IstioComponentSetSpec) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.components = Some(components);
+        }
+        self
+    }
+}
+
+/// # IstioOperator
+/// IstioOperatorSpec defines the desired installed state of Istio components. The spec is a used to
+/// define a customization of the default profile values that are supplied with each Istio release.
+/// Because the spec is a customization API, specifying an empty IstioOperatorSpec results in a
+/// default Istio component values.
+///
+/// ```yaml
+/// apiVersion: install.istio.io/v1alpha1
+/// kind: IstioOperator
+/// metadata:
+///   name: control-plane
+///   namespace: istio-system
+/// spec:
+///   profile: default
+///   revision: 1-20
+///   meshConfig:
+///     accessLogFile: /dev/stdout
+///   components:
+///     pilot:
+///       k8s:
+///         resources:
+///           requests:
+///             cpu: 500m
+///     ingressGateways:
+///     - name: istio-ingressgateway
+///       enabled: true
+///       k8s:
+///         overlays:
+///         - kind: Deployment
+///           name: istio-ingressgateway
+///           patches:
+///           - path: spec.template.spec.containers.[name:istio-proxy].lifecycle
+///             value:
+///               preStop:
+///                 exec:
+///                   command: ["sleep", "10"]
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioOperatorSpec {
+    // Path or name for the profile e.g. minimal (looks in profiles dir for a file called minimal.yaml), /tmp/istio/install/values/custom/custom-install.yaml (local file path). default profile is used if this field is unset.
+    // No
+    pub profile: Option<String>,
+
+    // Path for the install package. e.g. /tmp/istio-installer/nightly (local file path)
+    // No
+    #[serde(rename = "installPackagePath")]
+    pub install_package_path: Option<String>,
+
+    // Root for docker image paths e.g. docker.io/istio
+    // No
+    pub hub: Option<String>,
+
+    // Version tag for docker images e.g. 1.7.2. Kept untyped as it may be written as a number.
+    // No
+    pub tag: Option<Value>,
+
+    // Namespace to install control plane resources into. If unset, Istio will be installed into the same namespace as the IstioOperator CR. You must also set values.global.istioNamespace if you wish to install Istio in a custom namespace.
+    // No
+    pub namespace: Option<String>,
+
+    // Identify the revision this installation is associated with. This option is currently experimental.
+    // No
+    pub revision: Option<String>,
+
+    // Config used by control plane components internally.
+    // No
+    #[serde(rename = "meshConfig")]
+    pub mesh_config: Option<MeshConfig>,
+
+    // Kubernetes resource settings, enablement and component sub-settings.
+    // No
+    pub components: Option<IstioComponentSetSpec>,
+
+    // Overrides for default values.yaml. This is a validated pass-through to Helm templates.
+    // No
+    pub values: Option<Value>,
+
+    // Unvalidated overrides for default values.yaml. Used for custom templates where new parameters are added.
+    // No
+    #[serde(rename = "unvalidatedValues")]
+    pub unvalidated_values: Option<Value>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # IstioComponentSetSpec
+/// IstioComponentSpec defines the desired installed state of Istio components.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioComponentSetSpec {
+    // No
+    pub base: Option<ComponentSpec>,
+
+    // No
+    pub pilot: Option<ComponentSpec>,
+
+    // No
+    pub cni: Option<ComponentSpec>,
+
+    // No
+    pub ztunnel: Option<ComponentSpec>,
+
+    // Remote cluster using an external control plane.
+    // No
+    #[serde(rename = "istiodRemote")]
+    pub istiod_remote: Option<ComponentSpec>,
+
+    // No
+    #[serde(rename = "ingressGateways")]
+    pub ingress_gateways: Option<Vec<GatewaySpec>>,
+
+    // No
+    #[serde(rename = "egressGateways")]
+    pub egress_gateways: Option<Vec<GatewaySpec>>,
+}
+
+impl IstioComponentSetSpec {
+    /// The ingress gateway called `name`, added when missing.
+    pub fn ingress_gateway_mut(&mut self, name: &str) -> &mut GatewaySpec {
+        gateway_mut(self.ingress_gateways.get_or_insert_with(Vec::new), name)
+    }
+
+    /// The egress gateway called `name`, added when missing.
+    pub fn egress_gateway_mut(&mut self, name: &str) -> &mut GatewaySpec {
+        gateway_mut(self.egress_gateways.get_or_insert_with(Vec::new), name)
+    }
+}
+
+fn gateway_mut<'a>(gateways: &'a mut Vec<GatewaySpec>, name: &str) -> &'a mut GatewaySpec {
+    let index = match gateways
+        .iter()
+        .position(|gateway| gateway.name.as_deref() == Some(name))
+    {
+        Some(index) => index,
+        None => {
+            gateways.push(GatewaySpec {
+                name: Some(name.to_string()),
+                ..GatewaySpec::default()
+            });
+            gateways.len() - 1
+        }
+    };
+    &mut gateways[index]
+}
+
+/// # ComponentSpec
+/// Configuration for internal components.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ComponentSpec {
+    // Selects whether this component is installed.
+    // No
+    pub enabled: Option<bool>,
+
+    // Namespace for the component.
+    // No
+    pub namespace: Option<String>,
+
+    // Hub for the component (overrides top level hub setting).
+    // No
+    pub hub: Option<String>,
+
+    // Tag for the component (overrides top level tag setting).
+    // No
+    pub tag: Option<Value>,
+
+    // Kubernetes resource spec.
+    // No
+    pub k8s: Option<KubernetesResourcesSpec>,
+}
+
+/// # GatewaySpec
+/// Configuration for gateways.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "IstioOperatorGatewaySpec"))]
+pub struct GatewaySpec {
+    // Selects whether this gateway is installed.
+    // No
+    pub enabled: Option<bool>,
+
+    // Namespace for the gateway.
+    // No
+    pub namespace: Option<String>,
+
+    // Name for the gateway.
+    // No
+    pub name: Option<String>,
+
+    // Labels for the gateway.
+    // No
+    pub label: Option<Map<String, String>>,
+
+    // Hub for the component (overrides top level hub setting).
+    // No
+    pub hub: Option<String>,
+
+    // Tag for the component (overrides top level tag setting).
+    // No
+    pub tag: Option<Value>,
+
+    // Kubernetes resource spec.
+    // No
+    pub k8s: Option<KubernetesResourcesSpec>,
+}
+
+/// # KubernetesResourcesSpec
+/// The Kubernetes settings of the Deployment, Service and other resources of a component. Fields
+/// not covered can be set with `overlays`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KubernetesResourcesSpec {
+    // k8s affinity.
+    // No
+    pub affinity: Option<Affinity>,
+
+    // Deployment environment variables.
+    // No
+    pub env: Option<Vec<EnvVar>>,
+
+    // k8s HorizontalPodAutoscaler settings. Kept untyped as scaleTargetRef is filled in by the operator.
+    // No
+    #[serde(rename = "hpaSpec")]
+    pub hpa_spec: Option<Value>,
+
+    // k8s imagePullPolicy.
+    // No
+    #[serde(rename = "imagePullPolicy")]
+    pub image_pull_policy: Option<String>,
+
+    // k8s nodeSelector.
+    // No
+    #[serde(rename = "nodeSelector")]
+    pub node_selector: Option<Map<String, String>>,
+
+    // k8s PodDisruptionBudget settings.
+    // No
+    #[serde(rename = "podDisruptionBudget")]
+    pub pod_disruption_budget: Option<PodDisruptionBudgetSpec>,
+
+    // k8s pod annotations.
+    // No
+    #[serde(rename = "podAnnotations")]
+    pub pod_annotations: Option<Map<String, String>>,
+
+    // k8s priority_class_name. Default for all resources unless overridden.
+    // No
+    #[serde(rename = "priorityClassName")]
+    pub priority_class_name: Option<String>,
+
+    // k8s readinessProbe settings.
+    // No
+    #[serde(rename = "readinessProbe")]
+    pub readiness_probe: Option<Probe>,
+
+    // k8s Deployment replicas setting.
+    // No
+    #[serde(rename = "replicaCount")]
+    pub replica_count: Option<u32>,
+
+    // k8s resources settings.
+    // No
+    pub resources: Option<ResourceRequirements>,
+
+    // k8s Service settings.
+    // No
+    pub service: Option<ServiceSpec>,
+
+    // k8s deployment strategy.
+    // No
+    pub strategy: Option<DeploymentStrategy>,
+
+    // k8s toleration
+    // No
+    pub tolerations: Option<Vec<Toleration>>,
+
+    // k8s service annotations.
+    // No
+    #[serde(rename = "serviceAnnotations")]
+    pub service_annotations: Option<Map<String, String>>,
+
+    // k8s pod security context.
+    // No
+    #[serde(rename = "securityContext")]
+    pub security_context: Option<PodSecurityContext>,
+
+    // k8s volumes.
+    // No
+    pub volumes: Option<Vec<Volume>>,
+
+    // k8s volumeMounts.
+    // No
+    #[serde(rename = "volumeMounts")]
+    pub volume_mounts: Option<Vec<VolumeMount>>,
+
+    // Overlays for k8s resources in rendered manifests.
+    // No
+    pub overlays: Option<Vec<K8sObjectOverlay>>,
+}
+
+impl KubernetesResourcesSpec {
+    pub fn with_overlay(mut self, overlay: K8sObjectOverlay) -> Self {
+        self.overlays.get_or_insert_with(Vec::new).push(overlay);
+        self
+    }
+}
+
+/// # K8sObjectOverlay
+/// Patches applied to a rendered resource of a component, selected by `kind` and `name`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct K8sObjectOverlay {
+    // Resource API version.
+    // No
+    #[serde(rename = "apiVersion")]
+    pub api_version: Option<String>,
+
+    // Resource kind.
+    // Yes
+    pub kind: String,
+
+    // Name of resource. Namespace is always the component namespace.
+    // Yes
+    pub name: String,
+
+    // List of patches to apply to resource.
+    // No
+    pub patches: Option<Vec<PathValue>>,
+}
+
+impl K8sObjectOverlay {
+    pub fn new(kind: &str, name: &str) -> Self {
+        K8sObjectOverlay {
+            api_version: None,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            patches: None,
+        }
+    }
+
+    /// Sets `path`, e.g. `spec.template.spec.containers.[name:istio-proxy].lifecycle`, to
+    /// `value`; a `None` value deletes the path.
+    pub fn with_patch(mut self, path: &str, value: Option<Value>) -> Self {
+        self.patches.get_or_insert_with(Vec::new).push(PathValue {
+            path: path.to_string(),
+            value,
+        });
+        self
+    }
+}
+
+/// # PathValue
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PathValue {
+    // Path of the form a.[key1:value1].b.[:value2] Where [key1:value1] is a selector for a key-value pair to identify a list element and [:value] is a value selector to identify a list element in a leaf list. All path intermediate nodes must exist.
+    // Yes
+    pub path: String,
+
+    // Value to add, delete or replace. For add, the path should be a new leaf. For delete, value should be unset. For replace, path should reference an existing node. All values are strings but are converted into appropriate type based on schema.
+    // No
+    pub value: Option<Value>,
+}
@@ -0,0 +1,311 @@
+// Sanity checks of the JWT rules of a RequestAuthentication before it is rolled out. istiod fetches
+// the JSON Web Key Set of every rule and, when it cannot, rejects all tokens for the selected
+// workloads, so a typo in `jwksUri` or `issuer` only shows up as 401s in production. The checks
+// fetch the key sets over HTTPS and need the `jwks` feature.
+use crate::istio::request_authentication::{JWTRule, RequestAuthentication};
+use crate::istio::Map;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use k8s_openapi::serde_json::{self, Value};
+use std::fmt;
+use std::time::Duration;
+
+/// Path of the OpenID Connect discovery document, relative to the issuer.
+pub const OPENID_CONFIGURATION: &str = "/.well-known/openid-configuration";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// # Jwks
+/// A JSON Web Key Set (RFC 7517).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+/// # Jwk
+/// A key of a JSON Web Key Set. Only the parameters shared by all key types are modelled, the
+/// type specific ones (`n`, `e`, `x`, `y`, ...) are kept in `params`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: Option<String>,
+    pub alg: Option<String>,
+    #[serde(rename = "use")]
+    pub key_use: Option<String>,
+    #[serde(flatten)]
+    pub params: Map<String, Value>,
+}
+
+impl Jwk {
+    /// Whether the key can verify signatures: keys without `use` can.
+    pub fn is_signing_key(&self) -> bool {
+        self.key_use
+            .as_deref()
+            .is_none_or(|key_use| key_use == "sig")
+    }
+}
+
+impl Jwks {
+    /// Parses a key set as served by a `jwksUri`.
+    pub fn parse(document: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(document)
+    }
+
+    /// Fetches and parses the key set served at `uri`.
+    pub fn fetch(uri: &str) -> Result<Self, JwksError> {
+        let document = get(uri)?;
+        Jwks::parse(&document).map_err(|error| JwksError::Invalid {
+            uri: uri.to_string(),
+            reason: error.to_string(),
+        })
+    }
+
+    pub fn key(&self, kid: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.kid.as_deref() == Some(kid))
+    }
+}
+
+/// # OpenIdConfiguration
+/// The part of an OpenID Connect discovery document used to locate the key set of an issuer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub jwks_uri: String,
+}
+
+impl OpenIdConfiguration {
+    /// Fetches the discovery document of `issuer`, which is how istiod finds the key set of a rule
+    /// without `jwksUri`.
+    pub fn discover(issuer: &str) -> Result<Self, JwksError> {
+        let uri = format!("{}{}", issuer.trim_end_matches('/'), OPENID_CONFIGURATION);
+        let document = get(&uri)?;
+        serde_json::from_str(&document).map_err(|error| JwksError::Invalid {
+            uri,
+            reason: error.to_string(),
+        })
+    }
+}
+
+fn get(uri: &str) -> Result<String, JwksError> {
+    let fetch_error = |reason: String| JwksError::Fetch {
+        uri: uri.to_string(),
+        reason,
+    };
+    ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .build()
+        .get(uri)
+        .call()
+        .map_err(|error| match error {
+            ureq::Error::Status(status, _) => fetch_error(format!("HTTP {}", status)),
+            ureq::Error::Transport(transport) => fetch_error(
+                transport
+                    .message()
+                    .map_or_else(|| transport.kind().to_string(), ToString::to_string),
+            ),
+        })?
+        .into_string()
+        .map_err(|error| fetch_error(error.to_string()))
+}
+
+/// # JwksError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JwksError {
+    /// The document could not be fetched.
+    Fetch { uri: String, reason: String },
+    /// The document is not a valid key set or discovery document.
+    Invalid { uri: String, reason: String },
+}
+
+impl fmt::Display for JwksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwksError::Fetch { uri, reason } => write!(f, "cannot fetch {}: {}", uri, reason),
+            JwksError::Invalid { uri, reason } => {
+                write!(f, "invalid document at {}: {}", uri, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JwksError {}
+
+/// # JwtRuleWarning
+/// A problem with a JWT rule that would make the proxies reject valid tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JwtRuleWarning {
+    /// The key set of the rule could not be fetched or parsed.
+    Jwks(JwksError),
+    /// The key set has no keys.
+    EmptyKeySet { uri: String },
+    /// None of the keys of the key set can verify signatures.
+    NoSigningKeys { uri: String },
+    /// The issuer of the discovery document, or of a token, is not the issuer of the rule.
+    IssuerMismatch { expected: String, found: String },
+    /// A token carries none of the audiences of the rule.
+    AudienceMismatch {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// A token is not signed by a key of the key set.
+    UnknownKey { kid: String },
+    /// A token is not a JWT.
+    MalformedToken(String),
+}
+
+impl fmt::Display for JwtRuleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtRuleWarning::Jwks(error) => write!(f, "{}", error),
+            JwtRuleWarning::EmptyKeySet { uri } => write!(f, "key set at {} has no keys", uri),
+            JwtRuleWarning::NoSigningKeys { uri } => {
+                write!(f, "key set at {} has no signing keys", uri)
+            }
+            JwtRuleWarning::IssuerMismatch { expected, found } => {
+                write!(f, "issuer is {}, the rule expects {}", found, expected)
+            }
+            JwtRuleWarning::AudienceMismatch { expected, found } => write!(
+                f,
+                "audiences [{}] match none of [{}]",
+                found.join(", "),
+                expected.join(", ")
+            ),
+            JwtRuleWarning::UnknownKey { kid } => write!(f, "key {} is not in the key set", kid),
+            JwtRuleWarning::MalformedToken(reason) => write!(f, "malformed token: {}", reason),
+        }
+    }
+}
+
+/// Checks that the key set of `rule` can be fetched and verify signatures. For rules without
+/// `jwksUri` the key set is located through the discovery document of the issuer; an issuer that
+/// is a URL also has its discovery document compared against the rule. An inline `jwks` is checked
+/// as is, without fetching anything.
+pub fn check_rule(rule: &JWTRule) -> Vec<JwtRuleWarning> {
+    let mut warnings = Vec::new();
+    if let Some(document) = &rule.jwks {
+        let uri = "the inline jwks".to_string();
+        let jwks = Jwks::parse(document).map_err(|error| JwksError::Invalid {
+            uri: uri.clone(),
+            reason: error.to_string(),
+        });
+        check_key_set(&mut warnings, uri, jwks);
+        return warnings;
+    }
+    let discovered = if rule.issuer.starts_with("https://") || rule.issuer.starts_with("http://") {
+        OpenIdConfiguration::discover(&rule.issuer)
+    } else {
+        Err(JwksError::Fetch {
+            uri: rule.issuer.clone(),
+            reason: "issuer is not a URL".to_string(),
+        })
+    };
+    if let Ok(configuration) = &discovered {
+        if configuration.issuer != rule.issuer {
+            warnings.push(JwtRuleWarning::IssuerMismatch {
+                expected: rule.issuer.clone(),
+                found: configuration.issuer.clone(),
+            });
+        }
+    }
+    let uri = match (&rule.jwks_uri, discovered) {
+        (Some(uri), _) => uri.clone(),
+        (None, Ok(configuration)) => configuration.jwks_uri,
+        (None, Err(error)) => {
+            warnings.push(JwtRuleWarning::Jwks(error));
+            return warnings;
+        }
+    };
+    let jwks = Jwks::fetch(&uri);
+    check_key_set(&mut warnings, uri, jwks);
+    warnings
+}
+
+fn check_key_set(warnings: &mut Vec<JwtRuleWarning>, uri: String, jwks: Result<Jwks, JwksError>) {
+    match jwks {
+        Ok(jwks) if jwks.keys.is_empty() => warnings.push(JwtRuleWarning::EmptyKeySet { uri }),
+        Ok(jwks) if !jwks.keys.iter().any(Jwk::is_signing_key) => {
+            warnings.push(JwtRuleWarning::NoSigningKeys { uri })
+        }
+        Ok(_) => {}
+        Err(error) => warnings.push(JwtRuleWarning::Jwks(error)),
+    }
+}
+
+/// Checks every rule of `authentication`, keyed by the index of the rule.
+pub fn check_request_authentication(
+    authentication: &RequestAuthentication,
+) -> Vec<(usize, JwtRuleWarning)> {
+    authentication
+        .spec
+        .iter()
+        .flat_map(|spec| spec.jwt_rules.iter().flatten())
+        .enumerate()
+        .flat_map(|(index, rule)| {
+            check_rule(rule)
+                .into_iter()
+                .map(move |warning| (index, warning))
+        })
+        .collect()
+}
+
+/// Checks a sample token against `rule` without verifying its signature: its `iss` must be the
+/// issuer of the rule, its `aud` must hold one of the audiences of the rule (if any), and, given
+/// the key set of the rule, its `kid` must be one of the keys.
+pub fn check_token(rule: &JWTRule, jwks: Option<&Jwks>, token: &str) -> Vec<JwtRuleWarning> {
+    let (header, claims) = match decode(token) {
+        Ok(decoded) => decoded,
+        Err(reason) => return vec![JwtRuleWarning::MalformedToken(reason)],
+    };
+    let mut warnings = Vec::new();
+    let issuer = claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if issuer != rule.issuer {
+        warnings.push(JwtRuleWarning::IssuerMismatch {
+            expected: rule.issuer.clone(),
+            found: issuer.to_string(),
+        });
+    }
+    if let Some(audiences) = rule.audiences.as_ref().filter(|a| !a.is_empty()) {
+        let found: Vec<String> = match claims.get("aud") {
+            Some(Value::String(audience)) => vec![audience.clone()],
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter_map(|value| value.as_str().map(ToString::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !found.iter().any(|audience| audiences.contains(audience)) {
+            warnings.push(JwtRuleWarning::AudienceMismatch {
+                expected: audiences.clone(),
+                found,
+            });
+        }
+    }
+    if let (Some(jwks), Some(kid)) = (jwks, header.get("kid").and_then(Value::as_str)) {
+        if jwks.key(kid).is_none() {
+            warnings.push(JwtRuleWarning::UnknownKey {
+                kid: kid.to_string(),
+            });
+        }
+    }
+    warnings
+}
+
+fn decode(token: &str) -> Result<(Value, Value), String> {
+    let part = |encoded: &str| -> Result<Value, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim_end_matches('='))
+            .map_err(|error| error.to_string())?;
+        serde_json::from_slice(&bytes).map_err(|error| error.to_string())
+    };
+    match token.split('.').collect::<Vec<_>>()[..] {
+        [header, claims, _] => Ok((part(header)?, part(claims)?)),
+        _ => Err("expected three dot-separated parts".to_string()),
+    }
+}
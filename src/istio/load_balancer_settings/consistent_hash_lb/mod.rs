@@ -1,9 +1,10 @@
-use std::time::Duration;
+use crate::istio::duration::IstioDuration;
 
 /// # HTTPCookie
 /// Describes a HTTP cookie that will be used as the hash key for the Consistent Hash load balancer. If the cookie is not present, it will be generated.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HTTPCookie {
     // Name of the cookie.
     // Yes
@@ -15,5 +16,5 @@ pub struct HTTPCookie {
 
     // Lifetime of the cookie.
     // Yes
-    pub ttl: Duration,
+    pub ttl: IstioDuration,
 }
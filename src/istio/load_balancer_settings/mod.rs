@@ -1,65 +1,202 @@
 pub mod consistent_hash_lb;
 
 /// # ConsistentHashLB
-/// Consistent Hash-based load balancing can be used to provide soft session affinity based on HTTP headers, cookies or other properties. The affinity to a particular destination host will be lost when one or more hosts are added/removed from the destination service.
+/// Consistent Hash-based load balancing can be used to provide soft session affinity based on HTTP headers, cookies or other properties. The affinity to a particular destination host may be lost when one or more hosts are added/removed from the destination service.
+///
+/// The hash key and the hash algorithm are set independently:
+/// ```yaml
+/// consistentHash:
+///   httpHeaderName: x-user
+///   maglev:
+///     tableSize: 65537
+/// ```
+/// Older manifests put the ring size next to the key, which is still read and written as is:
+/// ```yaml
+/// consistentHash:
+///   httpCookie:
+///     name: user
+///     ttl: 0s
+///   minimumRingSize: 1024
+/// ```
+///
+/// Exactly one hash key and at most one of `ringHash` and `maglev` must be set, and an invalid
+/// value for any of them is an error rather than a missing key.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum ConsistentHashLB {
-    HttpHeaderName {
-        // Hash based on a specific HTTP header.
-        // Required: No
-        #[serde(rename = "httpHeaderName")]
-        http_header_name: Option<String>,
-
-        // The minimum number of virtual nodes to use for the hash ring. Defaults to 1024. Larger ring sizes result in more granular load distributions. If the number of hosts in the load balancing pool is larger than the ring size, each host will be assigned a single virtual node.
-        // No
-        #[serde(rename = "minimumRingSize")]
-        minimum_ring_size: Option<u64>,
-    },
-    HttpCookie {
-        // Hash based on HTTP cookie.
-        // No
-        #[serde(rename = "httpCookie")]
-        http_cookie: Option<consistent_hash_lb::HTTPCookie>,
-
-        // The minimum number of virtual nodes to use for the hash ring. Defaults to 1024. Larger ring sizes result in more granular load distributions. If the number of hosts in the load balancing pool is larger than the ring size, each host will be assigned a single virtual node.
-        // No
-        #[serde(rename = "minimumRingSize")]
-        minimum_ring_size: Option<u64>,
-    },
-    UseSourceIp {
-        // Hash based on the source IP address. This is applicable for both TCP and HTTP connections.
-        // No
-        #[serde(rename = "useSourceIp")]
-        use_source_ip: Option<bool>,
-
-        // The minimum number of virtual nodes to use for the hash ring. Defaults to 1024. Larger ring sizes result in more granular load distributions. If the number of hosts in the load balancing pool is larger than the ring size, each host will be assigned a single virtual node.
-        // No
-        #[serde(rename = "minimumRingSize")]
-        minimum_ring_size: Option<u64>,
-    },
-    HttpQueryParameterName {
-        // Hash based on a specific HTTP query parameter.
-        // No
-        #[serde(rename = "httpQueryParameterName")]
-        http_query_parameter_name: Option<String>,
-
-        // The minimum number of virtual nodes to use for the hash ring. Defaults to 1024. Larger ring sizes result in more granular load distributions. If the number of hosts in the load balancing pool is larger than the ring size, each host will be assigned a single virtual node.
-        // No
-        #[serde(rename = "minimumRingSize")]
-        minimum_ring_size: Option<u64>,
-    },
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "RawConsistentHashLB")]
+pub struct ConsistentHashLB {
+    // What the hash is computed from.
+    // Yes
+    #[serde(flatten)]
+    pub hash_key: HashKey,
+
+    // The hash algorithm. Ring hash is used when unset.
+    // No
+    #[serde(flatten)]
+    pub hash_algorithm: Option<HashAlgorithm>,
+
+    // Deprecated: use ringHash instead. The minimum number of virtual nodes to use for the hash ring. Defaults to 1024.
+    // No
+    #[serde(rename = "minimumRingSize")]
+    pub minimum_ring_size: Option<u64>,
+}
+
+impl ConsistentHashLB {
+    /// Hashing over `hash_key` with the algorithm left to Istio.
+    pub fn new(hash_key: HashKey) -> Self {
+        ConsistentHashLB {
+            hash_key,
+            hash_algorithm: None,
+            minimum_ring_size: None,
+        }
+    }
+
+    /// Ring hash over `hash_key`, with Envoy's default ring size.
+    pub fn ring_hash(hash_key: HashKey) -> Self {
+        ConsistentHashLB {
+            hash_algorithm: Some(HashAlgorithm::RingHash(RingHash {
+                minimum_ring_size: None,
+            })),
+            ..ConsistentHashLB::new(hash_key)
+        }
+    }
+
+    /// Maglev over `hash_key`, with `table_size` entries or else Envoy's default table size.
+    pub fn maglev(hash_key: HashKey, table_size: Option<u64>) -> Self {
+        ConsistentHashLB {
+            hash_algorithm: Some(HashAlgorithm::Maglev(MagLev { table_size })),
+            ..ConsistentHashLB::new(hash_key)
+        }
+    }
+}
+
+/// `ConsistentHashLB` as written, with every key and algorithm as a plain key.
+#[derive(Deserialize)]
+struct RawConsistentHashLB {
+    #[serde(rename = "httpHeaderName")]
+    http_header_name: Option<String>,
+    #[serde(rename = "httpCookie")]
+    http_cookie: Option<consistent_hash_lb::HTTPCookie>,
+    #[serde(rename = "useSourceIp")]
+    use_source_ip: Option<bool>,
+    #[serde(rename = "httpQueryParameterName")]
+    http_query_parameter_name: Option<String>,
+    #[serde(rename = "ringHash")]
+    ring_hash: Option<RingHash>,
+    maglev: Option<MagLev>,
+    #[serde(rename = "minimumRingSize")]
+    minimum_ring_size: Option<u64>,
+}
+
+impl TryFrom<RawConsistentHashLB> for ConsistentHashLB {
+    type Error = &'static str;
+
+    fn try_from(raw: RawConsistentHashLB) -> Result<Self, Self::Error> {
+        let mut hash_keys = [
+            raw.http_header_name.map(HashKey::HttpHeaderName),
+            raw.http_cookie.map(HashKey::HttpCookie),
+            raw.use_source_ip.map(HashKey::UseSourceIp),
+            raw.http_query_parameter_name
+                .map(HashKey::HttpQueryParameterName),
+        ]
+        .into_iter()
+        .flatten();
+        let hash_key = match (hash_keys.next(), hash_keys.next()) {
+            (Some(hash_key), None) => hash_key,
+            (None, _) => return Err("consistentHash needs one of httpHeaderName, httpCookie, useSourceIp or httpQueryParameterName"),
+            (Some(_), Some(_)) => return Err("only one of httpHeaderName, httpCookie, useSourceIp or httpQueryParameterName can be set"),
+        };
+        let hash_algorithm = match (raw.ring_hash, raw.maglev) {
+            (Some(_), Some(_)) => return Err("only one of ringHash or maglev can be set"),
+            (Some(ring_hash), None) => Some(HashAlgorithm::RingHash(ring_hash)),
+            (None, Some(maglev)) => Some(HashAlgorithm::Maglev(maglev)),
+            (None, None) => None,
+        };
+        Ok(ConsistentHashLB {
+            hash_key,
+            hash_algorithm,
+            minimum_ring_size: raw.minimum_ring_size,
+        })
+    }
+}
+
+/// # HashKey
+/// The property of a request the consistent hash is computed from, serialized as a key of
+/// `consistentHash` itself, e.g. `httpCookie: { name: user, ttl: 0s }`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum HashKey {
+    // Hash based on a specific HTTP header.
+    #[serde(rename = "httpHeaderName")]
+    HttpHeaderName(String),
+
+    // Hash based on HTTP cookie.
+    #[serde(rename = "httpCookie")]
+    HttpCookie(consistent_hash_lb::HTTPCookie),
+
+    // Hash based on the source IP address. This is applicable for both TCP and HTTP connections.
+    #[serde(rename = "useSourceIp")]
+    UseSourceIp(bool),
+
+    // Hash based on a specific HTTP query parameter.
+    #[serde(rename = "httpQueryParameterName")]
+    HttpQueryParameterName(String),
+}
+
+/// # HashAlgorithm
+/// The algorithm distributing the hashes over the hosts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum HashAlgorithm {
+    // The ring/modulo hash load balancer implements consistent hashing to backend hosts.
+    #[serde(rename = "ringHash")]
+    RingHash(RingHash),
+
+    // The Maglev load balancer implements consistent hashing to backend hosts.
+    #[serde(rename = "maglev")]
+    Maglev(MagLev),
+}
+
+/// # RingHash
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RingHash {
+    // The minimum number of virtual nodes to use for the hash ring. Defaults to 1024. Larger ring sizes result in more granular load distributions. If the number of hosts in the load balancing pool is larger than the ring size, each host will be assigned a single virtual node.
+    // No
+    #[serde(rename = "minimumRingSize")]
+    pub minimum_ring_size: Option<u64>,
+}
+
+/// # MagLev
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MagLev {
+    // The table size for Maglev hashing. Maglev aims for “minimal disruption” rather than an absolute guarantee. Minimal disruption means that when the set of upstream hosts change, a connection will likely be sent to the same upstream as it was before. Increasing the table size reduces the amount of disruption. The table size must be prime number limited to 5000011. If it is not specified, the default is 65537.
+    // No
+    #[serde(rename = "tableSize")]
+    pub table_size: Option<u64>,
 }
 
 /// # SimpleLB
 /// Standard load balancing algorithms that require no tuning.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
 pub enum SimpleLB {
+    // No load balancing algorithm has been specified by the user. Istio will select an appropriate default.
+    UNSPECIFIED,
+
     // Round Robin policy. Default
     ROUND_ROBIN,
 
-    // The least request load balancer uses an O(1) algorithm which selects two random healthy hosts and picks the host which has fewer active requests.
+    // The least request load balancer spreads load across endpoints, favoring endpoints with the least outstanding requests. This is generally safer and outperforms ROUND_ROBIN in nearly all cases.
+    LEAST_REQUEST,
+
+    // Deprecated. Use LEAST_REQUEST instead.
     LEAST_CONN,
 
     // The random load balancer selects a random healthy host. The random load balancer generally performs better than round robin if no health checking policy is configured.
@@ -68,3 +205,99 @@ pub enum SimpleLB {
     // This option will forward the connection to the original IP address requested by the caller without doing any form of load balancing. This option must be used with care. It is meant for advanced use cases.Refer to Original Destination load balancer in Envoy for further details.
     PASSTHROUGH,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn consistent_hash(value: Value) -> Result<ConsistentHashLB, serde_json::Error> {
+        serde_json::from_value(value)
+    }
+
+    #[test]
+    fn reads_the_key_and_the_algorithm() {
+        let maglev = consistent_hash(json!({
+            "httpHeaderName": "x-user",
+            "maglev": { "tableSize": 65537 },
+        }))
+        .unwrap();
+        assert_eq!(
+            maglev,
+            ConsistentHashLB::maglev(HashKey::HttpHeaderName("x-user".into()), Some(65537))
+        );
+        let legacy =
+            consistent_hash(json!({ "useSourceIp": true, "minimumRingSize": 1024 })).unwrap();
+        assert_eq!(legacy.hash_algorithm, None);
+        assert_eq!(legacy.minimum_ring_size, Some(1024));
+    }
+
+    #[test]
+    fn rejects_invalid_algorithms() {
+        for value in [
+            json!({ "httpHeaderName": "x-user", "maglev": { "tableSize": "abc" } }),
+            json!({ "httpHeaderName": "x-user", "ringHash": { "minimumRingSize": -1 } }),
+            json!({ "httpHeaderName": "x-user", "maglev": 65537 }),
+        ] {
+            assert!(consistent_hash(value.clone()).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_and_conflicting_keys() {
+        let error = |value| consistent_hash(value).unwrap_err().to_string();
+        assert_eq!(
+            error(json!({ "maglev": {} })),
+            "consistentHash needs one of httpHeaderName, httpCookie, useSourceIp or httpQueryParameterName"
+        );
+        assert_eq!(
+            error(json!({ "httpHeaderName": "x-user", "useSourceIp": true })),
+            "only one of httpHeaderName, httpCookie, useSourceIp or httpQueryParameterName can be set"
+        );
+        assert_eq!(
+            error(json!({ "useSourceIp": true, "ringHash": {}, "maglev": {} })),
+            "only one of ringHash or maglev can be set"
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trips_every_key_and_algorithm() {
+        use crate::istio::destination_rule::DestinationRule;
+
+        let fixtures = [
+            "httpHeaderName: x-user",
+            "httpCookie:\n  name: user\n  path: /\n  ttl: 0s",
+            "useSourceIp: true",
+            "httpQueryParameterName: user",
+            "httpHeaderName: x-user\nringHash:\n  minimumRingSize: 1024",
+            "useSourceIp: true\nmaglev:\n  tableSize: 65537",
+        ];
+        for fixture in fixtures {
+            let consistent_hash = fixture.replace('\n', "\n        ");
+            let yaml = format!(
+                "apiVersion: networking.istio.io/v1beta1
+kind: DestinationRule
+metadata:
+  name: ratings
+spec:
+  host: ratings.prod.svc.cluster.local
+  trafficPolicy:
+    loadBalancer:
+      consistentHash:
+        {}
+",
+                consistent_hash
+            );
+            let rule = DestinationRule::from_yaml_str(&yaml).unwrap();
+            let written = rule.to_yaml_string().unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<serde_yaml::Value>(&written).unwrap(),
+                serde_yaml::from_str::<serde_yaml::Value>(&yaml).unwrap(),
+                "{}",
+                fixture
+            );
+            assert_eq!(DestinationRule::from_yaml_str(&written).unwrap(), rule);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use crate::istio::Map;
 
 /// # Distribute
 /// Describes how traffic originating in the ‘from’ zone or sub-zone is distributed over a set of ‘to’ zones. Syntax for specifying a zone is {region}/{zone}/{sub-zone} and terminal wildcards are allowed on any segment of the specification. Examples:
@@ -9,7 +9,8 @@ use std::collections::HashMap;
 ///
 /// `us-west/zone-1/*` - all sub-zones within us-west/zone-1
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Distribute {
     // Originating locality, `/` separated, e.g.`region / zone / sub_zone`.
     // Required: No
@@ -17,13 +18,14 @@ pub struct Distribute {
 
     // Map of upstream localities to traffic distribution weights.The sum of all weights should be 100.Any locality not present will receive no traffic.
     // Required: No
-    pub to: Option<HashMap<String, u32>>,
+    pub to: Option<Map<String, u32>>,
 }
 
 /// # Failover
 /// Specify the traffic failover policy across regions. Since zone and sub-zone failover is supported by default this only needs to be specified for regions when the operator needs to constrain traffic failover so that the default behavior of failing over to any endpoint globally does not apply. This is useful when failing over traffic across regions would not improve service health or may need to be restricted for other reasons like regulatory controls.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Failover {
     // Originating region.
     // No
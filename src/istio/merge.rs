@@ -0,0 +1,426 @@
+// Merging of VirtualService fragments that target the same host, as commonly produced when several
+// teams own routes of a shared host. Fragments are ordered deterministically (creation time, then
+// namespace and name), their rules are concatenated in that order and catch-all rules are moved
+// behind all matching rules. Anything that makes a rule unreachable or the merged result ambiguous
+// is reported as a conflict.
+use crate::istio::topology::{creation_order, ResourceRef, RouteProtocol};
+use crate::istio::virtual_service::{
+    HttpRoute, TcpRoute, TlsRoute, VirtualService, VirtualServiceSpec,
+};
+use crate::istio::Map;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// # MergeConflict
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum MergeConflict {
+    /// The same route name is used by several rules.
+    DuplicateRouteName {
+        name: String,
+        fragments: Vec<ResourceRef>,
+    },
+
+    /// A rule has the same match conditions as an earlier rule and will never be selected.
+    DuplicateMatch {
+        protocol: RouteProtocol,
+        first: ResourceRef,
+        shadowed: ResourceRef,
+    },
+
+    /// Several fragments declare a catch-all rule, only the first one is reachable.
+    MultipleCatchAll {
+        protocol: RouteProtocol,
+        effective: ResourceRef,
+        shadowed: Vec<ResourceRef>,
+    },
+
+    /// The fragments are bound to different gateways. The gateways of the first fragment are used.
+    GatewayMismatch { fragments: Vec<ResourceRef> },
+
+    /// The fragments are exported to different namespaces. The exportTo of the first fragment is
+    /// used.
+    ExportToMismatch { fragments: Vec<ResourceRef> },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |refs: &[ResourceRef]| {
+            refs.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            MergeConflict::DuplicateRouteName { name, fragments } => {
+                write!(f, "route name {:?} is used by {}", name, list(fragments))
+            }
+            MergeConflict::DuplicateMatch {
+                protocol,
+                first,
+                shadowed,
+            } => write!(
+                f,
+                "{:?} rule of {} is shadowed by a rule of {} with the same match",
+                protocol, shadowed, first
+            ),
+            MergeConflict::MultipleCatchAll {
+                protocol,
+                effective,
+                shadowed,
+            } => write!(
+                f,
+                "{:?} catch-all rules of {} are shadowed by the catch-all rule of {}",
+                protocol,
+                list(shadowed),
+                effective
+            ),
+            MergeConflict::GatewayMismatch { fragments } => {
+                write!(f, "fragments {} use different gateways", list(fragments))
+            }
+            MergeConflict::ExportToMismatch { fragments } => {
+                write!(f, "fragments {} use different exportTo", list(fragments))
+            }
+        }
+    }
+}
+
+/// # MergeResult
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeResult {
+    /// The merged spec, with `hosts` set to the merged host.
+    pub spec: VirtualServiceSpec,
+
+    /// The fragments that contributed to the spec, in merge order.
+    pub fragments: Vec<ResourceRef>,
+
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Merges the fragments that list `host` in their hosts into a single spec. Fragments not
+/// targeting the host are ignored.
+pub fn merge_virtual_services<'a>(
+    host: &str,
+    fragments: impl IntoIterator<Item = &'a VirtualService>,
+) -> MergeResult {
+    let mut fragments: Vec<(&VirtualService, &VirtualServiceSpec)> = fragments
+        .into_iter()
+        .filter_map(|vs| vs.spec.as_ref().map(|spec| (vs, spec)))
+        .filter(|(_, spec)| spec.hosts.iter().flatten().any(|h| h == host))
+        .collect();
+    fragments.sort_by_key(|(vs, _)| creation_order(&vs.metadata));
+
+    let refs: Vec<ResourceRef> = fragments
+        .iter()
+        .map(|(vs, _)| ResourceRef::from_metadata(&vs.metadata))
+        .collect();
+    let mut conflicts = Vec::new();
+
+    let gateways = fragments
+        .first()
+        .and_then(|(_, spec)| spec.gateways.clone());
+    if fragments.iter().any(|(_, spec)| spec.gateways != gateways) {
+        conflicts.push(MergeConflict::GatewayMismatch {
+            fragments: refs.clone(),
+        });
+    }
+    let export_to = fragments
+        .first()
+        .and_then(|(_, spec)| spec.export_to.clone());
+    if fragments
+        .iter()
+        .any(|(_, spec)| spec.export_to != export_to)
+    {
+        conflicts.push(MergeConflict::ExportToMismatch {
+            fragments: refs.clone(),
+        });
+    }
+
+    let http = merge_rules(
+        RouteProtocol::Http,
+        rules(&fragments, &refs, |spec| spec.http.as_ref()),
+        |rule: &HttpRoute| rule.r#match.as_ref().is_none_or(Vec::is_empty),
+        |a, b| same_matches(a.r#match.as_ref(), b.r#match.as_ref()),
+        &mut conflicts,
+    );
+    let tls = merge_rules(
+        RouteProtocol::Tls,
+        rules(&fragments, &refs, |spec| spec.tls.as_ref()),
+        |rule: &TlsRoute| rule.r#match.is_empty(),
+        |a, b| same_matches(Some(&a.r#match), Some(&b.r#match)),
+        &mut conflicts,
+    );
+    let tcp = merge_rules(
+        RouteProtocol::Tcp,
+        rules(&fragments, &refs, |spec| spec.tcp.as_ref()),
+        |rule: &TcpRoute| rule.r#match.as_ref().is_none_or(Vec::is_empty),
+        |a, b| same_matches(a.r#match.as_ref(), b.r#match.as_ref()),
+        &mut conflicts,
+    );
+
+    let mut names: BTreeMap<&str, Vec<ResourceRef>> = BTreeMap::new();
+    for (fragment, (_, spec)) in refs.iter().zip(&fragments) {
+        for name in spec
+            .http
+            .iter()
+            .flatten()
+            .filter_map(|rule| rule.name.as_deref())
+        {
+            names.entry(name).or_default().push(fragment.clone());
+        }
+    }
+    for (name, fragments) in names {
+        if fragments.len() > 1 {
+            conflicts.push(MergeConflict::DuplicateRouteName {
+                name: name.to_string(),
+                fragments,
+            });
+        }
+    }
+
+    // Fields this crate does not know are carried over, the first fragment setting one wins.
+    let mut extra = Map::new();
+    for (_, spec) in &fragments {
+        for (key, value) in &spec.extra {
+            extra.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    MergeResult {
+        spec: VirtualServiceSpec {
+            hosts: Some(vec![host.to_string()]),
+            gateways,
+            http: non_empty(http),
+            tls: non_empty(tls),
+            tcp: non_empty(tcp),
+            export_to,
+            extra,
+        },
+        fragments: refs,
+        conflicts,
+    }
+}
+
+/// The rules of all fragments in merge order, tagged with their fragment.
+fn rules<'a, T>(
+    fragments: &[(&'a VirtualService, &'a VirtualServiceSpec)],
+    refs: &'a [ResourceRef],
+    list: impl Fn(&'a VirtualServiceSpec) -> Option<&'a Vec<T>>,
+) -> Vec<(&'a ResourceRef, &'a T)> {
+    refs.iter()
+        .zip(fragments)
+        .flat_map(|(fragment, (_, spec))| {
+            list(spec)
+                .into_iter()
+                .flatten()
+                .map(move |rule| (fragment, rule))
+        })
+        .collect()
+}
+
+/// Orders the rules with all matching rules first and reports shadowed rules.
+fn merge_rules<T: Clone>(
+    protocol: RouteProtocol,
+    rules: Vec<(&ResourceRef, &T)>,
+    is_catch_all: impl Fn(&T) -> bool,
+    same_match: impl Fn(&T, &T) -> bool,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<T> {
+    let (catch_all, matching): (Vec<_>, Vec<_>) =
+        rules.into_iter().partition(|(_, rule)| is_catch_all(rule));
+
+    for (index, (fragment, rule)) in matching.iter().enumerate() {
+        if let Some((first, _)) = matching[..index]
+            .iter()
+            .find(|(other, earlier)| other != fragment && same_match(earlier, rule))
+        {
+            conflicts.push(MergeConflict::DuplicateMatch {
+                protocol,
+                first: (*first).clone(),
+                shadowed: (*fragment).clone(),
+            });
+        }
+    }
+    if let Some(((effective, _), shadowed)) = catch_all.split_first() {
+        let shadowed: Vec<ResourceRef> = shadowed
+            .iter()
+            .filter(|(fragment, _)| fragment != effective)
+            .map(|(fragment, _)| (*fragment).clone())
+            .collect();
+        if !shadowed.is_empty() {
+            conflicts.push(MergeConflict::MultipleCatchAll {
+                protocol,
+                effective: (*effective).clone(),
+                shadowed,
+            });
+        }
+    }
+
+    matching
+        .into_iter()
+        .chain(catch_all)
+        .map(|(_, rule)| rule.clone())
+        .collect()
+}
+
+/// Whether two match lists are equal regardless of their order.
+fn same_matches<T: PartialEq>(a: Option<&Vec<T>>, b: Option<&Vec<T>>) -> bool {
+    let empty = Vec::new();
+    let (a, b) = (a.unwrap_or(&empty), b.unwrap_or(&empty));
+    a.len() == b.len() && a.iter().all(|item| b.contains(item))
+}
+
+fn non_empty<T>(rules: Vec<T>) -> Option<Vec<T>> {
+    if rules.is_empty() {
+        None
+    } else {
+        Some(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn fragment(name: &str, created: &str, spec: Value) -> VirtualService {
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": "shop", "creationTimestamp": created },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn route_names(rules: &Option<Vec<HttpRoute>>) -> Vec<&str> {
+        rules
+            .iter()
+            .flatten()
+            .map(|rule| rule.name.as_deref().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn concatenates_rules_by_creation_time_with_catch_all_rules_last() {
+        let cart = fragment(
+            "cart",
+            "2022-02-01T00:00:00Z",
+            json!({
+                "hosts": ["shop.example.com"],
+                "http": [{ "name": "cart", "match": [{ "uri": { "prefix": "/cart" } }], "route": [{ "destination": { "host": "cart" } }] }],
+            }),
+        );
+        let web = fragment(
+            "web",
+            "2022-01-01T00:00:00Z",
+            json!({
+                "hosts": ["shop.example.com"],
+                "http": [
+                    { "name": "default", "route": [{ "destination": { "host": "web" } }] },
+                    { "name": "static", "match": [{ "uri": { "prefix": "/static" } }], "route": [{ "destination": { "host": "cdn" } }] },
+                ],
+            }),
+        );
+        let other = fragment(
+            "other",
+            "2021-01-01T00:00:00Z",
+            json!({ "hosts": ["other.example.com"], "http": [{ "name": "other", "route": [{ "destination": { "host": "other" } }] }] }),
+        );
+
+        let result = merge_virtual_services("shop.example.com", [&cart, &web, &other]);
+        assert!(result.is_clean(), "{:?}", result.conflicts);
+        assert_eq!(
+            result.fragments,
+            vec![
+                ResourceRef::new(Some("shop"), "web"),
+                ResourceRef::new(Some("shop"), "cart")
+            ]
+        );
+        assert_eq!(
+            result.spec.hosts,
+            Some(vec!["shop.example.com".to_string()])
+        );
+        assert_eq!(
+            route_names(&result.spec.http),
+            ["static", "cart", "default"]
+        );
+    }
+
+    #[test]
+    fn reports_shadowed_rules_and_mismatches() {
+        let a = fragment(
+            "a",
+            "2022-01-01T00:00:00Z",
+            json!({
+                "hosts": ["shop.example.com"],
+                "gateways": ["ingress"],
+                "http": [
+                    { "name": "api", "match": [{ "uri": { "prefix": "/api" } }], "route": [{ "destination": { "host": "api" } }] },
+                    { "route": [{ "destination": { "host": "web" } }] },
+                ],
+            }),
+        );
+        let b = fragment(
+            "b",
+            "2022-01-02T00:00:00Z",
+            json!({
+                "hosts": ["shop.example.com"],
+                "http": [
+                    { "name": "api", "match": [{ "uri": { "prefix": "/api" } }], "route": [{ "destination": { "host": "api-v2" } }] },
+                    { "route": [{ "destination": { "host": "web-v2" } }] },
+                ],
+            }),
+        );
+        let result = merge_virtual_services("shop.example.com", [&a, &b]);
+        let (a, b) = (
+            ResourceRef::new(Some("shop"), "a"),
+            ResourceRef::new(Some("shop"), "b"),
+        );
+        assert_eq!(result.spec.gateways, Some(vec!["ingress".to_string()]));
+        assert_eq!(
+            result.conflicts,
+            vec![
+                MergeConflict::GatewayMismatch {
+                    fragments: vec![a.clone(), b.clone()]
+                },
+                MergeConflict::DuplicateMatch {
+                    protocol: RouteProtocol::Http,
+                    first: a.clone(),
+                    shadowed: b.clone(),
+                },
+                MergeConflict::MultipleCatchAll {
+                    protocol: RouteProtocol::Http,
+                    effective: a.clone(),
+                    shadowed: vec![b.clone()],
+                },
+                MergeConflict::DuplicateRouteName {
+                    name: "api".to_string(),
+                    fragments: vec![a, b],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_of_the_first_fragment_win() {
+        let a = fragment(
+            "a",
+            "2022-01-01T00:00:00Z",
+            json!({ "hosts": ["h"], "future": 1 }),
+        );
+        let b = fragment(
+            "b",
+            "2022-01-02T00:00:00Z",
+            json!({ "hosts": ["h"], "future": 2, "other": true }),
+        );
+        let result = merge_virtual_services("h", [&b, &a]);
+        assert_eq!(result.spec.extra.get("future"), Some(&json!(1)));
+        assert_eq!(result.spec.extra.get("other"), Some(&json!(true)));
+        assert_eq!(result.spec.http, None);
+    }
+}
@@ -0,0 +1,207 @@
+// A partial model of the mesh-wide configuration of Istio, covering the defaults that interact
+// with the per-resource settings of this crate. istiod reads it from the `mesh` key of the `istio`
+// ConfigMap (`istio-<revision>` for a revisioned control plane) in its root namespace, and from the
+// `meshConfig` of an IstioOperator. Fields not modelled here are kept in `other` so a config can be
+// written back unchanged.
+use crate::istio::destination_rule::LocalityLoadBalancerSetting;
+use crate::istio::export_to::ExportTo;
+use crate::istio::patch_order::DEFAULT_ROOT_NAMESPACE;
+use crate::istio::sidecar::{OutboundTrafficMode, OutboundTrafficPolicy};
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use std::fmt;
+
+/// Name of the ConfigMap holding the mesh config of the default revision.
+pub const MESH_CONFIG_MAP: &str = "istio";
+
+/// Key of the mesh config in the ConfigMap.
+pub const MESH_CONFIG_KEY: &str = "mesh";
+
+/// # MeshConfig
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MeshConfig {
+    // The namespace to treat as the administrative root namespace for Istio configuration. Defaults to istio-system.
+    // No
+    #[serde(rename = "rootNamespace")]
+    pub root_namespace: Option<String>,
+
+    // The trust domain corresponds to the trust root of a system.
+    // No
+    #[serde(rename = "trustDomain")]
+    pub trust_domain: Option<String>,
+
+    // This flag is used to enable mutual TLS automatically for service to service communication within the mesh, default true. If set to true, and a given service does not have a corresponding DestinationRule configured, or its DestinationRule does not have ClientTLSSettings specified, Istio configures client side TLS configuration appropriately.
+    // No
+    #[serde(rename = "enableAutoMtls")]
+    pub enable_auto_mtls: Option<bool>,
+
+    // Set the default behavior of the sidecar for handling outbound traffic from the application.
+    // No
+    #[serde(rename = "outboundTrafficPolicy")]
+    pub outbound_traffic_policy: Option<OutboundTrafficPolicy>,
+
+    // Locality based load balancing distribution or failover settings. If unspecified, locality based load balancing will be enabled by default.
+    // No
+    #[serde(rename = "localityLbSetting")]
+    pub locality_lb_setting: Option<LocalityLoadBalancerSetting>,
+
+    // The default value for the VirtualService.export_to field. Has the same syntax as `exportTo`.
+    // No
+    #[serde(rename = "defaultVirtualServiceExportTo")]
+    pub default_virtual_service_export_to: Option<Vec<ExportTo>>,
+
+    // The default value for the DestinationRule.export_to field. Has the same syntax as `exportTo`.
+    // No
+    #[serde(rename = "defaultDestinationRuleExportTo")]
+    pub default_destination_rule_export_to: Option<Vec<ExportTo>>,
+
+    // The default value for the ServiceEntry.export_to field. Has the same syntax as `exportTo`.
+    // No
+    #[serde(rename = "defaultServiceExportTo")]
+    pub default_service_export_to: Option<Vec<ExportTo>>,
+
+    // Defines a list of extension providers that extend Istio's functionality. For example, the AuthorizationPolicy can be used with an extension provider to delegate the authorization decision to a custom authorization system.
+    // No
+    #[serde(rename = "extensionProviders")]
+    pub extension_providers: Option<Vec<ExtensionProvider>>,
+
+    // Default proxy config used by gateway and sidecars. Kept untyped.
+    // No
+    #[serde(rename = "defaultConfig")]
+    pub default_config: Option<Value>,
+
+    /// The fields of the mesh config not modelled above.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl MeshConfig {
+    /// Parses the mesh config as stored in the ConfigMap.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self, MeshConfigError> {
+        if yaml.trim().is_empty() {
+            return Ok(MeshConfig::default());
+        }
+        serde_yaml::from_str(yaml).map_err(|error| MeshConfigError::Invalid(error.to_string()))
+    }
+
+    /// Parses the mesh config out of the `istio` ConfigMap.
+    #[cfg(feature = "yaml")]
+    pub fn from_config_map(
+        config_map: &k8s_openapi::api::core::v1::ConfigMap,
+    ) -> Result<Self, MeshConfigError> {
+        config_map
+            .data
+            .as_ref()
+            .and_then(|data| data.get(MESH_CONFIG_KEY))
+            .ok_or(MeshConfigError::MissingKey)
+            .and_then(|yaml| MeshConfig::from_yaml(yaml))
+    }
+
+    /// The name of the ConfigMap holding the mesh config of `revision`, `None` for the default
+    /// revision.
+    pub fn config_map_name(revision: Option<&str>) -> String {
+        match revision {
+            Some(revision) if revision != "default" => {
+                format!("{}-{}", MESH_CONFIG_MAP, revision)
+            }
+            _ => MESH_CONFIG_MAP.to_string(),
+        }
+    }
+
+    pub fn root_namespace(&self) -> &str {
+        self.root_namespace
+            .as_deref()
+            .unwrap_or(DEFAULT_ROOT_NAMESPACE)
+    }
+
+    /// Whether sidecars block traffic to hosts not in the service registry, so external hosts need
+    /// a ServiceEntry.
+    pub fn is_registry_only(&self) -> bool {
+        self.outbound_traffic_policy
+            .as_ref()
+            .and_then(|policy| policy.mode)
+            == Some(OutboundTrafficMode::REGISTRY_ONLY)
+    }
+
+    pub fn extension_provider(&self, name: &str) -> Option<&ExtensionProvider> {
+        self.extension_providers
+            .iter()
+            .flatten()
+            .find(|provider| provider.name == name)
+    }
+}
+
+/// # ExtensionProvider
+/// A provider referenced by name from AuthorizationPolicies (CUSTOM action) or Telemetry. The
+/// external authorization providers are typed, other providers are kept as they are.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExtensionProvider {
+    // A unique name identifying the extension provider.
+    // Yes
+    pub name: String,
+
+    // Configures an external authorizer that implements the Envoy ext_authz filter authorization check service using the HTTP API.
+    // No
+    #[serde(rename = "envoyExtAuthzHttp")]
+    pub envoy_ext_authz_http: Option<ExternalAuthorizationProvider>,
+
+    // Configures an external authorizer that implements the Envoy ext_authz filter authorization check service using the gRPC API.
+    // No
+    #[serde(rename = "envoyExtAuthzGrpc")]
+    pub envoy_ext_authz_grpc: Option<ExternalAuthorizationProvider>,
+
+    // The other provider kinds, e.g. `zipkin` or `envoyFileAccessLog`, by key.
+    // No
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// # ExternalAuthorizationProvider
+/// The common settings of the HTTP and gRPC external authorization providers.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExternalAuthorizationProvider {
+    // Specifies the service that implements the Envoy ext_authz authorization check service. The format is `[<Namespace>/]<Hostname>`.
+    // Yes
+    pub service: String,
+
+    // Specifies the port of the service.
+    // Yes
+    pub port: u32,
+
+    // The maximum duration that the proxy will wait for a response from the provider, e.g. `0.2s`.
+    // No
+    pub timeout: Option<String>,
+
+    // If true, the HTTP request or TCP connection will be allowed even if the communication with the authorization service has failed, or if the authorization service has returned a HTTP 5xx error.
+    // No
+    #[serde(rename = "failOpen")]
+    pub fail_open: Option<bool>,
+}
+
+/// # MeshConfigError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshConfigError {
+    /// The ConfigMap has no `mesh` key.
+    MissingKey,
+    Invalid(String),
+}
+
+impl fmt::Display for MeshConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshConfigError::MissingKey => {
+                write!(f, "ConfigMap has no {} key", MESH_CONFIG_KEY)
+            }
+            MeshConfigError::Invalid(error) => write!(f, "invalid mesh config: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for MeshConfigError {}
@@ -1,29 +1,117 @@
+
+#[cfg(feature = "admission")]
+pub mod admission;
+pub mod analyze;
+pub mod annotations;
+pub mod api_ext;
+pub mod authorization_policy;
+pub mod binding;
+pub mod canary;
 pub mod client_tls_settings;
 pub mod connection_pool_settings;
+pub mod conversion;
+#[cfg(feature = "schema")]
+pub mod crd;
+pub mod defaulting;
+pub mod dependency;
 pub mod destination_rule;
+pub mod diff;
+pub mod duration;
+pub mod egress;
+pub mod envoy;
 pub mod envoy_filter;
+pub mod export_to;
 pub mod gateway;
+#[cfg(feature = "generated")]
+pub mod generated;
+pub mod host;
+pub mod http_filter;
+pub mod ingress;
+pub mod istio_operator;
+#[cfg(feature = "jwks")]
+pub mod jwks;
 pub mod load_balancer_settings;
 pub mod locality_load_balancer_settings;
+pub mod merge;
+pub mod mesh_config;
+pub mod mtls;
+pub mod object;
+pub mod ownership;
+pub mod patch_order;
+pub mod patterns;
+pub mod peer_authentication;
+#[cfg(feature = "prost")]
+pub mod proto;
+pub mod proxy_config;
+pub mod rate_limit;
+pub mod request_authentication;
+pub mod retry_on;
+pub mod revision;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod selector;
+pub mod serialization;
+pub mod service_entry;
+pub mod sidecar;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+pub mod status;
+pub mod subset_registry;
+pub mod topology;
+pub mod traffic_plan;
 pub mod traffic_policy;
+pub mod v1;
+pub mod v1alpha3;
+pub mod v1beta1;
+pub mod validate;
+pub mod version;
+mod versioned;
 pub mod virtual_service;
+pub mod wasm_plugin;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod weight;
+pub mod workload_entry;
+pub mod workload_group;
 
+pub use authorization_policy::AuthorizationPolicy;
 pub use destination_rule::DestinationRule;
 pub use envoy_filter::EnvoyFilter;
 pub use gateway::Gateway;
-use std::collections::HashMap;
+pub use istio_operator::IstioOperator;
+pub use peer_authentication::PeerAuthentication;
+pub use proxy_config::ProxyConfig;
+pub use request_authentication::RequestAuthentication;
+pub use service_entry::ServiceEntry;
+pub use sidecar::Sidecar;
 pub use virtual_service::VirtualService;
+pub use wasm_plugin::WasmPlugin;
+pub use workload_entry::WorkloadEntry;
+pub use workload_group::WorkloadGroup;
+
+/// Derives fluent `with_<field>` builder methods, see the `kube-resource-extra-derive` crate.
+#[cfg(feature = "derive")]
+pub use kube_resource_extra_derive::Builder;
+
+/// Map of the map fields of the resources. With the `indexmap` feature this is an `IndexMap`, so a
+/// resource read from YAML keeps its key order when it is serialized again.
+#[cfg(feature = "indexmap")]
+pub type Map<K, V> = indexmap::IndexMap<K, V>;
+#[cfg(not(feature = "indexmap"))]
+pub type Map<K, V> = std::collections::HashMap<K, V>;
 
 pub mod google {
+    use super::Map;
     use k8s_openapi::serde_json::Value;
-    use std::collections::HashMap;
 
     pub mod protobuf {
         /// # UInt32Value
         /// Wrapper message for uint32.
-        /// The JSON representation for UInt32Value is JSON number.
-        #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+        /// The JSON representation for UInt32Value is JSON number, so `consecutive5xxErrors: 7` is
+        /// read and written as is.
+        #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+        #[serde(transparent)]
         pub struct UInt32Value {
             // The uint32 value.
             // No
@@ -33,19 +121,18 @@ pub mod google {
     /// # Struct
     /// Struct represents a structured data value, consisting of fields which map to dynamically typed values. In some languages, Struct might be supported by a native representation. For example, in scripting languages like JS a struct is represented as an object. The details of that representation are described together with the proto support for the language.
     /// The JSON representation for Struct is JSON object.
-    #[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+    #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[serde(transparent)]
     pub struct Struct {
-        pub fields: HashMap<String, Value>,
+        pub fields: Map<String, Value>,
     }
 }
 
-/// # WorkloadSelector
-/// `WorkloadSelector` specifies the criteria used to determine if the `Gateway`, `Sidecar`, `EnvoyFilter`, or `ServiceEntry` configuration can be applied to a proxy. The matching criteria includes the metadata associated with a proxy, workload instance info such as labels attached to the pod/VM, or any other info that the proxy provides to Istio during the initial handshake. If multiple conditions are specified, all conditions need to match in order for the workload instance to be selected. Currently, only label based selection mechanism is supported.
-#[skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct WorkloadSelector {
-    // One or more labels that indicate a specific set of pods/VMs on which the configuration should be applied. The scope of label search is restricted to the configuration namespace in which the the resource is present.
-    // Yes
-    pub labels: HashMap<String, String>,
+pub mod r#type {
+    pub mod v1beta1 {
+        pub use crate::istio::selector::PolicyWorkloadSelector as WorkloadSelector;
+    }
 }
+
+pub use selector::WorkloadSelector;
@@ -0,0 +1,128 @@
+// Checks of the client TLS settings of DestinationRules against the mutual TLS required by
+// PeerAuthentications. Under STRICT the server sidecars only accept Istio mutual TLS, so a
+// DestinationRule disabling TLS or originating its own TLS for a host of a STRICT namespace breaks
+// the traffic to it. Only namespace-wide and mesh-wide policies are considered, as a host cannot
+// be mapped to the labels of its pods here.
+use crate::istio::client_tls_settings::TLSmode;
+use crate::istio::destination_rule::{ClientTLSSettings, DestinationRule, TrafficPolicy};
+use crate::istio::peer_authentication::{MutualTlsMode, PeerAuthentication};
+use crate::istio::topology::ResourceRef;
+use std::fmt;
+
+/// # MtlsConflict
+/// A DestinationRule whose TLS settings break the traffic to a host requiring mutual TLS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MtlsConflict {
+    pub destination_rule: ResourceRef,
+    pub host: String,
+
+    /// Where the settings are: `trafficPolicy`, `trafficPolicy.portLevelSettings[0]` or
+    /// `subsets[0].trafficPolicy`.
+    pub path: String,
+
+    /// The client TLS mode, `DISABLE`, `SIMPLE` or `MUTUAL`.
+    pub mode: String,
+
+    /// The PeerAuthentication requiring mutual TLS.
+    pub peer_authentication: ResourceRef,
+}
+
+impl fmt::Display for MtlsConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}.tls sets mode {} for {}, but {} requires STRICT mutual TLS",
+            self.destination_rule, self.path, self.mode, self.host, self.peer_authentication
+        )
+    }
+}
+
+/// The DestinationRules that would break under the STRICT policies among `peer_authentications`.
+/// `root_namespace` is the config root namespace of istiod, whose policy applies to the whole mesh.
+pub fn strict_conflicts<'a>(
+    peer_authentications: impl IntoIterator<Item = &'a PeerAuthentication>,
+    destination_rules: impl IntoIterator<Item = &'a DestinationRule>,
+    root_namespace: &str,
+) -> Vec<MtlsConflict> {
+    let policies: Vec<&PeerAuthentication> = peer_authentications
+        .into_iter()
+        .filter(|pa| pa.spec.as_ref().is_some_and(|spec| spec.selector.is_none()))
+        .collect();
+    // The namespace-wide policy of a namespace, falling back to the mesh-wide one.
+    let policy = |namespace: &str| {
+        let find = |namespace: &str| {
+            policies
+                .iter()
+                .copied()
+                .find(|pa| pa.metadata.namespace.as_deref() == Some(namespace))
+                .filter(|pa| pa.mode(None).is_some())
+        };
+        find(namespace).or_else(|| find(root_namespace))
+    };
+
+    let mut conflicts = Vec::new();
+    for rule in destination_rules {
+        let spec = match &rule.spec {
+            Some(spec) => spec,
+            None => continue,
+        };
+        let rule_namespace = rule.metadata.namespace.as_deref().unwrap_or_default();
+        let namespace = host_namespace(&spec.host, rule_namespace);
+        let policy = match policy(namespace) {
+            Some(policy) => policy,
+            None => continue,
+        };
+        let mut check = |path: String, tls: Option<&ClientTLSSettings>, port: Option<u32>| {
+            let mode = match tls.map(|tls| &tls.mode) {
+                Some(TLSmode::DISABLE) => "DISABLE",
+                Some(TLSmode::SIMPLE) => "SIMPLE",
+                Some(TLSmode::MUTUAL) => "MUTUAL",
+                Some(TLSmode::ISTIO_MUTUAL) | None => return,
+            };
+            if policy.mode(port) == Some(MutualTlsMode::STRICT) {
+                conflicts.push(MtlsConflict {
+                    destination_rule: ResourceRef::from_metadata(&rule.metadata),
+                    host: spec.host.clone(),
+                    path,
+                    mode: mode.to_string(),
+                    peer_authentication: ResourceRef::from_metadata(&policy.metadata),
+                });
+            }
+        };
+        let mut check_policy = |path: String, traffic_policy: &TrafficPolicy| {
+            check(path.clone(), traffic_policy.tls.as_ref(), None);
+            for (index, port) in traffic_policy
+                .port_level_settings
+                .iter()
+                .flatten()
+                .enumerate()
+            {
+                check(
+                    format!("{}.portLevelSettings[{}]", path, index),
+                    port.tls.as_ref(),
+                    port.port.as_ref().and_then(|port| port.number),
+                );
+            }
+        };
+        if let Some(traffic_policy) = &spec.traffic_policy {
+            check_policy("trafficPolicy".to_string(), traffic_policy);
+        }
+        for (index, subset) in spec.subsets.iter().flatten().enumerate() {
+            if let Some(traffic_policy) = &subset.traffic_policy {
+                check_policy(format!("subsets[{}].trafficPolicy", index), traffic_policy);
+            }
+        }
+    }
+    conflicts
+}
+
+/// The namespace of the service `host` of a DestinationRule in `namespace`: short names resolve in
+/// the namespace of the rule, `<service>.<namespace>[.svc.cluster.local]` names in their own.
+fn host_namespace<'a>(host: &'a str, namespace: &'a str) -> &'a str {
+    let labels: Vec<&str> = host.split('.').collect();
+    match labels.as_slice() {
+        [_] => namespace,
+        [_, namespace] | [_, namespace, "svc", ..] => namespace,
+        _ => "",
+    }
+}
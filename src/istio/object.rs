@@ -0,0 +1,75 @@
+// kube-rs integration of the resources. Every resource implements `k8s_openapi::Resource` and
+// `Metadata<Ty = ObjectMeta>`, which kube's blanket impl turns into a `kube::Resource` with a `()`
+// dynamic type, so `Api::<VirtualService>::namespaced(client, "default")` works as is. This module
+// adds kube's `HasSpec` and `HasStatus` so generic controller code can reach spec and status of
+// any kind. The spec of a resource is optional on the wire, hence `Spec = Option<...Spec>`.
+use crate::istio::authorization_policy::AuthorizationPolicySpec;
+use crate::istio::destination_rule::DestinationRuleSpec;
+use crate::istio::envoy_filter::EnvoyFilterSpec;
+use crate::istio::gateway::GatewaySpec;
+use crate::istio::istio_operator::IstioOperatorSpec;
+use crate::istio::peer_authentication::PeerAuthenticationSpec;
+use crate::istio::proxy_config::ProxyConfigSpec;
+use crate::istio::request_authentication::RequestAuthenticationSpec;
+use crate::istio::service_entry::ServiceEntrySpec;
+use crate::istio::sidecar::SidecarSpec;
+use crate::istio::status::IstioStatus;
+use crate::istio::virtual_service::VirtualServiceSpec;
+use crate::istio::wasm_plugin::WasmPluginSpec;
+use crate::istio::workload_entry::WorkloadEntrySpec;
+use crate::istio::workload_group::WorkloadGroupSpec;
+use crate::istio::{
+    AuthorizationPolicy, DestinationRule, EnvoyFilter, Gateway, IstioOperator, PeerAuthentication,
+    ProxyConfig, RequestAuthentication, ServiceEntry, Sidecar, VirtualService, WasmPlugin,
+    WorkloadEntry, WorkloadGroup,
+};
+
+/// Implements kube's `HasSpec` and `HasStatus` for `$resource`.
+macro_rules! object {
+    ($($resource:ty: $spec:ty, $status:ty;)*) => {
+        $(
+            impl kube::core::object::HasSpec for $resource {
+                type Spec = Option<$spec>;
+
+                fn spec(&self) -> &Self::Spec {
+                    &self.spec
+                }
+
+                fn spec_mut(&mut self) -> &mut Self::Spec {
+                    &mut self.spec
+                }
+            }
+
+            impl kube::core::object::HasStatus for $resource {
+                type Status = $status;
+
+                fn status(&self) -> Option<&Self::Status> {
+                    self.status.as_ref()
+                }
+
+                fn status_mut(&mut self) -> &mut Option<Self::Status> {
+                    &mut self.status
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use object;
+
+object! {
+    AuthorizationPolicy: AuthorizationPolicySpec, ();
+    DestinationRule: DestinationRuleSpec, IstioStatus;
+    EnvoyFilter: EnvoyFilterSpec, IstioStatus;
+    Gateway: GatewaySpec, IstioStatus;
+    IstioOperator: IstioOperatorSpec, ();
+    PeerAuthentication: PeerAuthenticationSpec, ();
+    ProxyConfig: ProxyConfigSpec, ();
+    RequestAuthentication: RequestAuthenticationSpec, ();
+    ServiceEntry: ServiceEntrySpec, ();
+    Sidecar: SidecarSpec, ();
+    VirtualService: VirtualServiceSpec, IstioStatus;
+    WasmPlugin: WasmPluginSpec, ();
+    WorkloadEntry: WorkloadEntrySpec, ();
+    WorkloadGroup: WorkloadGroupSpec, ();
+}
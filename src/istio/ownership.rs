@@ -0,0 +1,167 @@
+// Ownership and attribution of the Istio resources a controller creates: owner references so the
+// resources are garbage collected with their owner, and the `app.kubernetes.io` labels telling who
+// manages them. The `Ownership` trait is implemented for every kube resource.
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::Resource;
+use std::fmt;
+
+/// Label naming the tool managing a resource.
+pub const MANAGED_BY: &str = "app.kubernetes.io/managed-by";
+
+/// Label naming the application a resource is part of.
+pub const PART_OF: &str = "app.kubernetes.io/part-of";
+
+/// # Ownership
+pub trait Ownership: Resource {
+    /// Makes `owner` the controller of this resource, replacing an earlier reference to it. Fails
+    /// if another object already controls the resource, since Kubernetes allows only one.
+    fn set_controller<O: Resource>(
+        &mut self,
+        owner: &O,
+        dynamic_type: &O::DynamicType,
+    ) -> Result<(), OwnershipError> {
+        let mut reference = owner_reference(self.meta().namespace.as_deref(), owner, dynamic_type)?;
+        reference.controller = Some(true);
+        reference.block_owner_deletion = Some(true);
+        if let Some(other) = self
+            .meta()
+            .owner_references
+            .iter()
+            .flatten()
+            .find(|other| other.controller == Some(true) && other.uid != reference.uid)
+        {
+            return Err(OwnershipError::ConflictingController {
+                kind: other.kind.clone(),
+                name: other.name.clone(),
+            });
+        }
+        upsert(self, reference);
+        Ok(())
+    }
+
+    /// Adds `owner` as an owner of this resource without making it the controller.
+    fn add_owner<O: Resource>(
+        &mut self,
+        owner: &O,
+        dynamic_type: &O::DynamicType,
+    ) -> Result<(), OwnershipError> {
+        let reference = owner_reference(self.meta().namespace.as_deref(), owner, dynamic_type)?;
+        upsert(self, reference);
+        Ok(())
+    }
+
+    /// Whether the object with `uid` is an owner of this resource.
+    fn is_owned_by(&self, uid: &str) -> bool {
+        self.meta()
+            .owner_references
+            .iter()
+            .flatten()
+            .any(|reference| reference.uid == uid)
+    }
+
+    fn managed_by(&self) -> Option<&str> {
+        label(self, MANAGED_BY)
+    }
+
+    fn set_managed_by(&mut self, manager: &str) {
+        set_label(self, MANAGED_BY, manager);
+    }
+
+    fn part_of(&self) -> Option<&str> {
+        label(self, PART_OF)
+    }
+
+    fn set_part_of(&mut self, application: &str) {
+        set_label(self, PART_OF, application);
+    }
+}
+
+impl<K: Resource> Ownership for K {}
+
+/// # OwnershipError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OwnershipError {
+    /// The owner has no name or uid yet, i.e. it was not read from the cluster.
+    MissingIdentity,
+
+    /// A namespaced owner lives in a different namespace than the resource.
+    CrossNamespace { owner: String, namespace: String },
+
+    /// The resource is already controlled by another object.
+    ConflictingController { kind: String, name: String },
+}
+
+impl fmt::Display for OwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OwnershipError::MissingIdentity => write!(f, "owner has no name or uid"),
+            OwnershipError::CrossNamespace { owner, namespace } => write!(
+                f,
+                "owner in namespace {} cannot own a resource in namespace {}",
+                owner, namespace
+            ),
+            OwnershipError::ConflictingController { kind, name } => {
+                write!(f, "resource is already controlled by {} {}", kind, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OwnershipError {}
+
+fn owner_reference<O: Resource>(
+    namespace: Option<&str>,
+    owner: &O,
+    dynamic_type: &O::DynamicType,
+) -> Result<OwnerReference, OwnershipError> {
+    let meta = owner.meta();
+    if let (Some(owner), Some(namespace)) = (meta.namespace.as_deref(), namespace) {
+        if owner != namespace {
+            return Err(OwnershipError::CrossNamespace {
+                owner: owner.to_string(),
+                namespace: namespace.to_string(),
+            });
+        }
+    }
+    match (&meta.name, &meta.uid) {
+        (Some(name), Some(uid)) => Ok(OwnerReference {
+            api_version: O::api_version(dynamic_type).to_string(),
+            kind: O::kind(dynamic_type).to_string(),
+            name: name.clone(),
+            uid: uid.clone(),
+            ..OwnerReference::default()
+        }),
+        _ => Err(OwnershipError::MissingIdentity),
+    }
+}
+
+fn upsert<K: Resource + ?Sized>(resource: &mut K, reference: OwnerReference) {
+    let references = resource
+        .meta_mut()
+        .owner_references
+        .get_or_insert_with(Vec::new);
+    match references
+        .iter_mut()
+        .find(|other| other.uid == reference.uid)
+    {
+        Some(existing) => *existing = reference,
+        None => references.push(reference),
+    }
+}
+
+fn label<'a, K: Resource + ?Sized>(resource: &'a K, key: &str) -> Option<&'a str> {
+    resource
+        .meta()
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(key))
+        .map(String::as_str)
+}
+
+fn set_label<K: Resource + ?Sized>(resource: &mut K, key: &str, value: &str) {
+    resource
+        .meta_mut()
+        .labels
+        .get_or_insert_with(Default::default)
+        .insert(key.to_string(), value.to_string());
+}
@@ -0,0 +1,637 @@
+// Ordering and conflict analysis of EnvoyFilter patches. Patches are ordered the way istiod applies
+// them: EnvoyFilters of the config root namespace first, then by priority, creation time and name,
+// and within an EnvoyFilter in the order of its configPatches. Two patches conflict when they can
+// apply to the same workload, target the same object in overlapping contexts and the outcome of
+// the pair depends on their order.
+use crate::istio::envoy_filter::patch::Operation;
+use crate::istio::envoy_filter::{
+    ApplyTo, EnvoyConfigObjectPatch, EnvoyFilter, ObjectTypes, PatchContext,
+};
+use crate::istio::selector::{self, Labels, Selector};
+use crate::istio::topology::{creation_order, ResourceRef};
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Default config root namespace of istiod.
+pub const DEFAULT_ROOT_NAMESPACE: &str = "istio-system";
+
+/// # PatchTarget
+/// The object of the Envoy configuration a patch applies to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PatchTarget {
+    #[serde(rename = "applyTo")]
+    pub apply_to: ApplyTo,
+
+    pub context: PatchContext,
+
+    /// Path of the matched object, e.g.
+    /// `listener[port=8080]/filter[name=envoy.filters.network.http_connection_manager]`.
+    pub object: String,
+}
+
+impl PatchTarget {
+    pub fn new(patch: &EnvoyConfigObjectPatch) -> Self {
+        let matched = &patch.r#match;
+        let mut object = match &matched.proxy {
+            Some(proxy) => segment("proxy", &[("version", proxy.proxy_version.clone())]),
+            None => String::new(),
+        };
+        let mut push = |segment: String| {
+            if !object.is_empty() {
+                object.push('/');
+            }
+            object.push_str(&segment);
+        };
+        match &matched.object_types {
+            Some(ObjectTypes::Listener(listener)) => {
+                push(segment(
+                    "listener",
+                    &[
+                        ("port", listener.port_number.map(|p| p.to_string())),
+                        ("name", listener.name.clone()),
+                    ],
+                ));
+                if let Some(chain) = &listener.filter_chain {
+                    push(segment(
+                        "filterChain",
+                        &[
+                            ("name", chain.name.clone()),
+                            ("sni", chain.sni.clone()),
+                            ("transportProtocol", chain.transport_protocol.clone()),
+                            ("applicationProtocols", chain.application_protocols.clone()),
+                            (
+                                "destinationPort",
+                                chain.destination_port.map(|port| port.to_string()),
+                            ),
+                        ],
+                    ));
+                    if let Some(filter) = &chain.filter {
+                        push(segment("filter", &[("name", Some(filter.name.clone()))]));
+                        if let Some(sub_filter) = &filter.sub_filter {
+                            push(segment(
+                                "subFilter",
+                                &[("name", Some(sub_filter.name.clone()))],
+                            ));
+                        }
+                    }
+                }
+            }
+            Some(ObjectTypes::RouteConfiguration(route_configuration)) => {
+                push(segment(
+                    "routeConfiguration",
+                    &[
+                        (
+                            "port",
+                            route_configuration.port_number.map(|p| p.to_string()),
+                        ),
+                        ("portName", route_configuration.port_name.clone()),
+                        ("gateway", route_configuration.gateway.clone()),
+                        ("name", route_configuration.name.clone()),
+                    ],
+                ));
+                if let Some(vhost) = &route_configuration.vhost {
+                    push(segment("vhost", &[("name", vhost.name.clone())]));
+                    if let Some(route) = &vhost.route {
+                        push(segment(
+                            "route",
+                            &[
+                                ("name", route.name.clone()),
+                                ("action", route.action.as_ref().map(|a| format!("{:?}", a))),
+                            ],
+                        ));
+                    }
+                }
+            }
+            Some(ObjectTypes::Cluster(cluster)) => push(segment(
+                "cluster",
+                &[
+                    ("port", cluster.port_number.map(|p| p.to_string())),
+                    ("service", cluster.service.clone()),
+                    ("subset", cluster.subset.clone()),
+                    ("name", cluster.name.clone()),
+                ],
+            )),
+            None => {}
+        }
+        let context = matched.context.unwrap_or(PatchContext::ANY);
+        PatchTarget {
+            apply_to: patch.apply_to,
+            context,
+            object,
+        }
+    }
+
+    /// Whether both targets select the same object in at least one context.
+    pub fn overlaps(&self, other: &PatchTarget) -> bool {
+        let contexts = self.context == other.context
+            || self.context == PatchContext::ANY
+            || other.context == PatchContext::ANY;
+        self.apply_to == other.apply_to && contexts && self.object == other.object
+    }
+}
+
+impl fmt::Display for PatchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {:?} {}", self.context, self.apply_to, self.object)
+    }
+}
+
+/// # OrderedPatch
+/// A patch in application order.
+#[derive(Clone, Debug)]
+pub struct OrderedPatch<'a> {
+    pub envoy_filter: ResourceRef,
+
+    /// Index of the patch in the configPatches of its EnvoyFilter.
+    pub index: usize,
+
+    pub priority: i32,
+
+    pub operation: Operation,
+
+    pub target: PatchTarget,
+
+    pub patch: &'a EnvoyConfigObjectPatch,
+
+    filter: &'a EnvoyFilter,
+}
+
+impl<'a> OrderedPatch<'a> {
+    /// Whether the patch applies to a workload with `labels` in `namespace`: the EnvoyFilter must
+    /// live in that namespace or in `root_namespace`, and its workload selector must match.
+    pub fn applies_to(&self, namespace: &str, labels: &impl Labels, root_namespace: &str) -> bool {
+        let filter_namespace = self.envoy_filter.namespace.as_deref();
+        (filter_namespace == Some(namespace) || filter_namespace == Some(root_namespace))
+            && self
+                .filter
+                .spec
+                .as_ref()
+                .is_some_and(|spec| spec.workload_selector.matches(labels))
+    }
+
+    fn location(&self) -> PatchLocation {
+        PatchLocation {
+            envoy_filter: self.envoy_filter.clone(),
+            index: self.index,
+            operation: self.operation,
+        }
+    }
+}
+
+/// # PatchLocation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PatchLocation {
+    #[serde(rename = "envoyFilter")]
+    pub envoy_filter: ResourceRef,
+    pub index: usize,
+    pub operation: Operation,
+}
+
+impl fmt::Display for PatchLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} configPatches[{}] ({:?})",
+            self.envoy_filter, self.index, self.operation
+        )
+    }
+}
+
+/// # ConflictKind
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ConflictKind {
+    /// One patch removes the object the other one modifies or inserts relative to.
+    RemovedTarget,
+
+    /// The later patch replaces the object, discarding the earlier patch.
+    Overridden,
+
+    /// Both patches merge different values into the same fields.
+    ConflictingMerge { fields: Vec<String> },
+
+    /// Both patches insert at the same position with the same priority, so their relative order
+    /// only depends on creation time.
+    CompetingInsert,
+
+    /// Both patches add a filter of the same name.
+    DuplicateFilter { name: String },
+}
+
+/// # PatchConflict
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PatchConflict {
+    pub kind: ConflictKind,
+    pub target: PatchTarget,
+
+    /// The patch applied first.
+    pub first: PatchLocation,
+
+    pub second: PatchLocation,
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match &self.kind {
+            ConflictKind::RemovedTarget => "one patch removes the target of the other".to_string(),
+            ConflictKind::Overridden => "the second patch replaces the first".to_string(),
+            ConflictKind::ConflictingMerge { fields } => {
+                format!("both merge different values into {}", fields.join(", "))
+            }
+            ConflictKind::CompetingInsert => {
+                "both insert at the same position with the same priority".to_string()
+            }
+            ConflictKind::DuplicateFilter { name } => format!("both add filter {}", name),
+        };
+        write!(
+            f,
+            "{} and {} conflict on {}: {}",
+            self.first, self.second, self.target, reason
+        )
+    }
+}
+
+/// # PatchAnalysis
+#[derive(Clone, Debug)]
+pub struct PatchAnalysis<'a> {
+    /// All patches in application order.
+    pub patches: Vec<OrderedPatch<'a>>,
+
+    pub conflicts: Vec<PatchConflict>,
+}
+
+impl<'a> PatchAnalysis<'a> {
+    /// Orders the patches of `filters` and detects conflicts between them. `root_namespace` is the
+    /// config root namespace of istiod, usually `DEFAULT_ROOT_NAMESPACE`.
+    pub fn new(filters: impl IntoIterator<Item = &'a EnvoyFilter>, root_namespace: &str) -> Self {
+        let mut filters: Vec<&EnvoyFilter> =
+            filters.into_iter().filter(|ef| ef.spec.is_some()).collect();
+        filters.sort_by_key(|ef| {
+            let spec = ef.spec.as_ref().expect("filtered on spec");
+            (
+                ef.metadata.namespace.as_deref() != Some(root_namespace),
+                spec.priority.unwrap_or(0),
+                creation_order(&ef.metadata),
+            )
+        });
+
+        let patches: Vec<OrderedPatch> = filters
+            .into_iter()
+            .flat_map(|filter| {
+                let spec = filter.spec.as_ref().expect("filtered on spec");
+                spec.config_patches
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, patch)| OrderedPatch {
+                        envoy_filter: ResourceRef::from_metadata(&filter.metadata),
+                        index,
+                        priority: spec.priority.unwrap_or(0),
+                        operation: patch.patch.operation.unwrap_or(Operation::INVALID),
+                        target: PatchTarget::new(patch),
+                        patch,
+                        filter,
+                    })
+            })
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (position, second) in patches.iter().enumerate() {
+            for first in &patches[..position] {
+                if !first.target.overlaps(&second.target)
+                    || !share_workloads(first.filter, second.filter, root_namespace)
+                {
+                    continue;
+                }
+                if let Some(kind) = conflict(first, second) {
+                    conflicts.push(PatchConflict {
+                        kind,
+                        target: second.target.clone(),
+                        first: first.location(),
+                        second: second.location(),
+                    });
+                }
+            }
+        }
+
+        PatchAnalysis { patches, conflicts }
+    }
+
+    /// The patches applied to a workload with `labels` in `namespace`, in application order.
+    pub fn patches_for<'b, L: Labels>(
+        &'b self,
+        namespace: &'b str,
+        labels: &'b L,
+        root_namespace: &'b str,
+    ) -> impl Iterator<Item = &'b OrderedPatch<'a>> + 'b {
+        self.patches
+            .iter()
+            .filter(move |patch| patch.applies_to(namespace, labels, root_namespace))
+    }
+}
+
+fn conflict(first: &OrderedPatch, second: &OrderedPatch) -> Option<ConflictKind> {
+    use Operation::*;
+    match (first.operation, second.operation) {
+        (REMOVE, REMOVE) => None,
+        (REMOVE, _) | (_, REMOVE) => Some(ConflictKind::RemovedTarget),
+        (MERGE, REPLACE) | (REPLACE, REPLACE) => Some(ConflictKind::Overridden),
+        (MERGE, MERGE) => {
+            let fields = conflicting_fields(first.patch, second.patch);
+            if fields.is_empty() {
+                None
+            } else {
+                Some(ConflictKind::ConflictingMerge { fields })
+            }
+        }
+        (a, b) if inserts(a) && inserts(b) => match (name(first.patch), name(second.patch)) {
+            (Some(first_name), Some(second_name)) if first_name == second_name => {
+                Some(ConflictKind::DuplicateFilter {
+                    name: first_name.to_string(),
+                })
+            }
+            _ if a == b && a != ADD && first.priority == second.priority => {
+                Some(ConflictKind::CompetingInsert)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn inserts(operation: Operation) -> bool {
+    matches!(
+        operation,
+        Operation::ADD
+            | Operation::INSERT_BEFORE
+            | Operation::INSERT_AFTER
+            | Operation::INSERT_FIRST
+    )
+}
+
+/// The `name` of the object added by a patch.
+fn name(patch: &EnvoyConfigObjectPatch) -> Option<&str> {
+    patch
+        .patch
+        .value
+        .as_ref()
+        .and_then(|value| value.fields.get("name"))
+        .and_then(Value::as_str)
+}
+
+/// The leaf fields both patches set to different values.
+fn conflicting_fields(a: &EnvoyConfigObjectPatch, b: &EnvoyConfigObjectPatch) -> Vec<String> {
+    let leaves = |patch: &EnvoyConfigObjectPatch| {
+        let mut leaves = BTreeMap::new();
+        if let Some(value) = &patch.patch.value {
+            for (key, value) in &value.fields {
+                collect_leaves(key.clone(), value, &mut leaves);
+            }
+        }
+        leaves
+    };
+    let (a, b) = (leaves(a), leaves(b));
+    a.iter()
+        .filter(|(path, value)| b.get(*path).is_some_and(|other| other != *value))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+fn collect_leaves(path: String, value: &Value, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                collect_leaves(format!("{}.{}", path, key), value, leaves);
+            }
+        }
+        value => {
+            leaves.insert(path, value.clone());
+        }
+    }
+}
+
+/// Whether two EnvoyFilters can apply to the same workload: they must share a namespace (or one of
+/// them lives in the root namespace) and their workload selectors must not contradict each other.
+fn share_workloads(a: &EnvoyFilter, b: &EnvoyFilter, root_namespace: &str) -> bool {
+    let (a_ns, b_ns) = (
+        a.metadata.namespace.as_deref(),
+        b.metadata.namespace.as_deref(),
+    );
+    if a_ns != b_ns && a_ns != Some(root_namespace) && b_ns != Some(root_namespace) {
+        return false;
+    }
+    let labels = |filter: &'_ EnvoyFilter| -> Map<String, String> {
+        filter
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.workload_selector.as_ref())
+            .map(|selector| selector.labels.clone())
+            .unwrap_or_default()
+    };
+    selector::overlaps(&labels(a), &labels(b))
+}
+
+/// Formats `kind[key=value,...]`, leaving out unset and empty values.
+fn segment(kind: &str, attributes: &[(&str, Option<String>)]) -> String {
+    let attributes: Vec<String> = attributes
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .filter(|value| !value.is_empty())
+                .map(|value| format!("{}={}", key, value))
+        })
+        .collect();
+    if attributes.is_empty() {
+        kind.to_string()
+    } else {
+        format!("{}[{}]", kind, attributes.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    fn filter(name: &str, namespace: &str, priority: i32, spec: Value) -> EnvoyFilter {
+        let mut spec = spec;
+        spec["priority"] = json!(priority);
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": namespace },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn listener_patch(operation: &str, value: Value) -> Value {
+        json!({
+            "applyTo": "NETWORK_FILTER",
+            "match": {
+                "context": "SIDECAR_INBOUND",
+                "listener": {
+                    "portNumber": 8080,
+                    "filterChain": { "filter": { "name": "envoy.filters.network.http_connection_manager" } },
+                },
+            },
+            "patch": { "operation": operation, "value": value },
+        })
+    }
+
+    fn location(
+        filter: &str,
+        namespace: &str,
+        index: usize,
+        operation: Operation,
+    ) -> PatchLocation {
+        PatchLocation {
+            envoy_filter: ResourceRef::new(Some(namespace), filter),
+            index,
+            operation,
+        }
+    }
+
+    #[test]
+    fn orders_root_namespace_first_then_by_priority() {
+        let app = filter(
+            "app",
+            "shop",
+            -10,
+            json!({ "configPatches": [listener_patch("MERGE", json!({ "a": 1 }))] }),
+        );
+        let late = filter(
+            "late",
+            "istio-system",
+            10,
+            json!({ "configPatches": [listener_patch("MERGE", json!({ "b": 1 }))] }),
+        );
+        let early = filter(
+            "early",
+            "istio-system",
+            0,
+            json!({ "configPatches": [listener_patch("MERGE", json!({ "c": 1 }))] }),
+        );
+        let analysis = PatchAnalysis::new([&app, &late, &early], DEFAULT_ROOT_NAMESPACE);
+        let order: Vec<&str> = analysis
+            .patches
+            .iter()
+            .map(|patch| patch.envoy_filter.name.as_str())
+            .collect();
+        assert_eq!(order, ["early", "late", "app"]);
+        assert!(analysis.conflicts.is_empty());
+        assert_eq!(
+            analysis.patches[0].target.object,
+            "listener[port=8080]/filterChain/filter[name=envoy.filters.network.http_connection_manager]"
+        );
+
+        let labels: BTreeMap<String, String> = BTreeMap::new();
+        let applied: Vec<&str> = analysis
+            .patches_for("other", &labels, DEFAULT_ROOT_NAMESPACE)
+            .map(|patch| patch.envoy_filter.name.as_str())
+            .collect();
+        assert_eq!(applied, ["early", "late"]);
+    }
+
+    #[test]
+    fn reports_conflicting_merges_and_removed_targets() {
+        let first = filter(
+            "first",
+            "shop",
+            0,
+            json!({ "configPatches": [listener_patch("MERGE", json!({ "typed_config": { "stat_prefix": "a", "xff_num_trusted_hops": 1 } }))] }),
+        );
+        let second = filter(
+            "second",
+            "shop",
+            1,
+            json!({
+                "configPatches": [
+                    listener_patch("MERGE", json!({ "typed_config": { "stat_prefix": "b", "xff_num_trusted_hops": 1 } })),
+                    listener_patch("REMOVE", Value::Null),
+                ],
+            }),
+        );
+        let analysis = PatchAnalysis::new([&second, &first], DEFAULT_ROOT_NAMESPACE);
+        let kinds: Vec<(ConflictKind, PatchLocation, PatchLocation)> = analysis
+            .conflicts
+            .iter()
+            .map(|conflict| {
+                (
+                    conflict.kind.clone(),
+                    conflict.first.clone(),
+                    conflict.second.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    ConflictKind::ConflictingMerge {
+                        fields: vec!["typed_config.stat_prefix".to_string()]
+                    },
+                    location("first", "shop", 0, Operation::MERGE),
+                    location("second", "shop", 0, Operation::MERGE),
+                ),
+                (
+                    ConflictKind::RemovedTarget,
+                    location("first", "shop", 0, Operation::MERGE),
+                    location("second", "shop", 1, Operation::REMOVE),
+                ),
+                (
+                    ConflictKind::RemovedTarget,
+                    location("second", "shop", 0, Operation::MERGE),
+                    location("second", "shop", 1, Operation::REMOVE),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_of_disjoint_workloads_do_not_conflict() {
+        let selector = |app: &str| json!({ "labels": { "app": app } });
+        let a = filter(
+            "a",
+            "shop",
+            0,
+            json!({ "workloadSelector": selector("cart"), "configPatches": [listener_patch("REPLACE", json!({ "name": "x" }))] }),
+        );
+        let b = filter(
+            "b",
+            "shop",
+            0,
+            json!({ "workloadSelector": selector("web"), "configPatches": [listener_patch("REPLACE", json!({ "name": "y" }))] }),
+        );
+        let other_namespace = filter(
+            "c",
+            "other",
+            0,
+            json!({ "configPatches": [listener_patch("REPLACE", json!({ "name": "z" }))] }),
+        );
+        let analysis = PatchAnalysis::new([&a, &b, &other_namespace], DEFAULT_ROOT_NAMESPACE);
+        assert!(analysis.conflicts.is_empty());
+    }
+
+    #[test]
+    fn filters_added_twice_are_reported() {
+        let patch = json!({
+            "applyTo": "HTTP_FILTER",
+            "match": { "context": "SIDECAR_INBOUND", "listener": { "portNumber": 8080 } },
+            "patch": { "operation": "INSERT_BEFORE", "value": { "name": "envoy.filters.http.lua" } },
+        });
+        let a = filter("a", "shop", 0, json!({ "configPatches": [patch.clone()] }));
+        let b = filter("b", "shop", 0, json!({ "configPatches": [patch] }));
+        let analysis = PatchAnalysis::new([&a, &b], DEFAULT_ROOT_NAMESPACE);
+        assert_eq!(analysis.conflicts.len(), 1);
+        assert_eq!(
+            analysis.conflicts[0].kind,
+            ConflictKind::DuplicateFilter {
+                name: "envoy.filters.http.lua".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,1210 @@
+// Ready-made resource sets for common traffic management and security setups. Every generator
+// validates the resources it produces, so a returned set can be applied as is.
+use crate::istio::authorization_policy::rule::{From, To};
+use crate::istio::authorization_policy::{
+    Action, AuthorizationPolicy, AuthorizationPolicySpec, Condition, Operation, Rule, Source,
+};
+use crate::istio::client_tls_settings::TLSmode as ClientTLSmode;
+use crate::istio::destination_rule::{
+    ClientTLSSettings, DestinationRule, DestinationRuleSpec, LbPolicy, LoadBalancerSettings,
+    LocalityLoadBalancerSetting, OutlierDetection, Subset, TrafficPolicy,
+};
+use crate::istio::duration::IstioDuration;
+use crate::istio::gateway::{
+    Gateway, GatewaySpec, Port, Server, ServerTLSSettings, TLSmode as ServerTLSmode,
+};
+use crate::istio::google::protobuf::UInt32Value;
+use crate::istio::load_balancer_settings::SimpleLB;
+use crate::istio::locality_load_balancer_settings::Failover;
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::request_authentication::{
+    JWTRule, RequestAuthentication, RequestAuthenticationSpec,
+};
+use crate::istio::service_entry::{
+    Location, Resolution, ServiceEntry, ServiceEntrySpec, ServicePort,
+};
+use crate::istio::topology::MESH_GATEWAY;
+use crate::istio::traffic_policy::PortTrafficPolicy;
+use crate::istio::validate::{Validate, ValidationErrors};
+use crate::istio::virtual_service::{
+    Destination, FaultInjectionAbort, FaultInjectionDelay, HttpFaultInjection, HttpMatchRequest,
+    HttpRoute, HttpRouteDestination, Percent, PortSelector, RouteDestination, StringMatch,
+    TlsMatchAttributes, TlsRoute, VirtualService, VirtualServiceSpec,
+};
+use crate::istio::weight::Weight;
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::Resource;
+use std::fmt;
+use std::time::Duration;
+
+/// Label of the pods of a network gateway naming its network.
+pub const NETWORK_LABEL: &str = "topology.istio.io/network";
+
+/// # Service
+/// The service a pattern is generated for. Generated resources are named after the service and
+/// created in its namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Service {
+    pub name: String,
+    pub namespace: String,
+    pub host: String,
+}
+
+impl Service {
+    /// A Kubernetes service, addressed by its cluster-local FQDN.
+    pub fn new(name: &str, namespace: &str) -> Self {
+        Service {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            host: format!("{}.{}.svc.cluster.local", name, namespace),
+        }
+    }
+
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    fn metadata(&self) -> ObjectMeta {
+        ObjectMeta {
+            name: Some(self.name.clone()),
+            namespace: Some(self.namespace.clone()),
+            ..ObjectMeta::default()
+        }
+    }
+}
+
+/// # Version
+/// A version of a service, exposed as a DestinationRule subset selecting the version's pods.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    pub subset: String,
+    pub labels: Map<String, String>,
+}
+
+impl Version {
+    pub fn new<K: Into<String>, V: Into<String>>(
+        subset: &str,
+        labels: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        Version {
+            subset: subset.to_string(),
+            labels: labels
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    /// A version selected by the conventional `version` label, using the version as subset name.
+    pub fn labelled(version: &str) -> Self {
+        Version::new(version, [("version", version)])
+    }
+}
+
+/// # Active
+/// The live side of a blue-green deployment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Active {
+    Blue,
+    Green,
+}
+
+/// # Experiment
+/// A header-based experiment: requests carrying `header: value` are in the treatment group. The
+/// routes generated for it are named `treatment` and `control`, prefixed with the experiment name
+/// when there is one, so both groups can be told apart in the access logs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Experiment {
+    pub name: Option<String>,
+    pub header: String,
+    pub value: String,
+}
+
+impl Experiment {
+    pub fn new(header: &str, value: &str) -> Self {
+        Experiment {
+            name: None,
+            header: header.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// The paired routes of the experiment, treatment first. The control route has no match and
+    /// catches every remaining request, so the pair must be the last routes of a VirtualService.
+    pub fn routes(
+        &self,
+        service: &Service,
+        control: &Version,
+        treatment: &Version,
+    ) -> Vec<HttpRoute> {
+        let name = |group: &str| match &self.name {
+            Some(name) => format!("{}-{}", name, group),
+            None => group.to_string(),
+        };
+        let matches = HttpMatchRequest {
+            name: Some(treatment.subset.clone()),
+            uri: None,
+            scheme: None,
+            method: None,
+            authority: None,
+            headers: Some(Map::from([(
+                self.header.clone(),
+                StringMatch::Exact(self.value.clone()),
+            )])),
+            port: None,
+            source_labels: None,
+            gateways: None,
+            query_params: None,
+            ignore_uri_case: None,
+            without_headers: None,
+            source_namespace: None,
+            extra: Map::new(),
+        };
+        vec![
+            http_route(
+                &name("treatment"),
+                Some(vec![matches]),
+                vec![route(service, treatment, None)],
+            ),
+            http_route(&name("control"), None, vec![route(service, control, None)]),
+        ]
+    }
+}
+
+/// # ChaosProfile
+/// Faults injected into the traffic of a service. At least one fault must be set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChaosProfile {
+    /// Delay added to the given percentage of requests.
+    pub delay: Option<(Duration, f32)>,
+
+    /// HTTP status returned for the given percentage of requests.
+    pub abort: Option<(i32, f32)>,
+}
+
+/// # JwtProvider
+/// The issuer of the JWTs accepted by a service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JwtProvider {
+    pub issuer: String,
+
+    /// Discovered through OpenID Connect when unset.
+    pub jwks_uri: Option<String>,
+
+    /// Accepted audiences. When empty, tokens for the service name are accepted.
+    pub audiences: Vec<String>,
+}
+
+/// # PathAccess
+/// Who may call the paths of a [`PathRule`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathAccess {
+    /// Anyone, with or without a token.
+    Public,
+
+    /// Requests with a valid token of the provider.
+    Authenticated,
+
+    /// Requests with a valid token carrying one of the given values for each claim.
+    Claims(Vec<(String, Vec<String>)>),
+}
+
+/// # PathRule
+/// Access to a set of paths, e.g. `/api/*`. A rule without paths applies to every path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathRule {
+    pub paths: Vec<String>,
+
+    /// Restricts the rule to these methods, any method is allowed when empty.
+    pub methods: Vec<String>,
+
+    pub access: PathAccess,
+}
+
+impl PathRule {
+    pub fn new(paths: &[&str], access: PathAccess) -> Self {
+        PathRule {
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+            methods: Vec::new(),
+            access,
+        }
+    }
+
+    pub fn with_methods(mut self, methods: &[&str]) -> Self {
+        self.methods = methods.iter().map(|method| method.to_string()).collect();
+        self
+    }
+}
+
+/// # EgressGateway
+/// The egress gateway external traffic is routed through. The generated resources are created in
+/// the namespace of the gateway.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EgressGateway {
+    /// Name of the generated Gateway.
+    pub name: String,
+    pub namespace: String,
+
+    /// Host of the Kubernetes service of the gateway pods.
+    pub host: String,
+
+    /// Labels of the gateway pods.
+    pub selector: Map<String, String>,
+}
+
+impl EgressGateway {
+    /// The `istio-egressgateway` deployment of a default installation in `namespace`.
+    pub fn new(name: &str, namespace: &str) -> Self {
+        EgressGateway {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            host: format!("istio-egressgateway.{}.svc.cluster.local", namespace),
+            selector: Map::from([("istio".to_string(), "egressgateway".to_string())]),
+        }
+    }
+
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.host = format!("{}.{}.svc.cluster.local", service, self.namespace);
+        self
+    }
+
+    pub fn with_selector<K: Into<String>, V: Into<String>>(
+        mut self,
+        labels: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.selector = labels
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        self
+    }
+}
+
+/// # EastWestGateway
+/// The gateway carrying the traffic between the clusters of a multi-network mesh.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EastWestGateway {
+    /// Name of the network the gateway is the entry point of.
+    pub network: String,
+
+    /// Namespace of the gateway pods and the generated resources.
+    pub namespace: String,
+
+    /// Labels of the gateway pods.
+    pub selector: Map<String, String>,
+
+    /// Also expose istiod through the gateway, for remote clusters reaching the control plane of
+    /// this cluster.
+    pub expose_istiod: bool,
+}
+
+impl EastWestGateway {
+    /// The `istio-eastwestgateway` deployment of `network` in `istio-system`, as installed by the
+    /// Istio multi-cluster samples.
+    pub fn new(network: &str) -> Self {
+        EastWestGateway {
+            network: network.to_string(),
+            namespace: "istio-system".to_string(),
+            selector: Map::from([
+                ("istio".to_string(), "eastwestgateway".to_string()),
+                (NETWORK_LABEL.to_string(), network.to_string()),
+            ]),
+            expose_istiod: false,
+        }
+    }
+
+    pub fn in_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    pub fn with_istiod(mut self) -> Self {
+        self.expose_istiod = true;
+        self
+    }
+}
+
+/// # ResourceSet
+/// The resources generated by a pattern.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceSet {
+    pub virtual_services: Vec<VirtualService>,
+    pub destination_rules: Vec<DestinationRule>,
+    pub gateways: Vec<Gateway>,
+    pub service_entries: Vec<ServiceEntry>,
+    pub request_authentications: Vec<RequestAuthentication>,
+    pub authorization_policies: Vec<AuthorizationPolicy>,
+}
+
+impl ResourceSet {
+    /// Validates every resource of the set.
+    pub fn validate(&self) -> Result<(), PatternError> {
+        fn check<R: Validate + Resource>(
+            resource: &R,
+            metadata: &ObjectMeta,
+        ) -> Result<(), PatternError> {
+            resource.validate().map_err(|errors| PatternError::Invalid {
+                kind: R::KIND,
+                name: metadata.name.clone().unwrap_or_default(),
+                errors,
+            })
+        }
+        for vs in &self.virtual_services {
+            check(vs, &vs.metadata)?;
+        }
+        for dr in &self.destination_rules {
+            check(dr, &dr.metadata)?;
+        }
+        for gateway in &self.gateways {
+            check(gateway, &gateway.metadata)?;
+        }
+        Ok(())
+    }
+
+    fn validated(self) -> Result<Self, PatternError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+/// # PatternError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    /// A weight above 100 was requested.
+    InvalidWeight(u32),
+
+    /// A chaos profile without any fault.
+    EmptyProfile,
+
+    /// A JWT provider without issuer.
+    MissingIssuer,
+
+    /// No path rules were given, which would deny every request.
+    NoPathRules,
+
+    /// A claim requirement without any accepted value.
+    EmptyClaim(String),
+
+    /// A wildcard host was given where a resolvable DNS name is required.
+    WildcardHost(String),
+
+    /// A generated resource failed validation.
+    Invalid {
+        kind: &'static str,
+        name: String,
+        errors: ValidationErrors,
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::InvalidWeight(weight) => {
+                write!(f, "weight {} is outside of 0-100", weight)
+            }
+            PatternError::EmptyProfile => write!(f, "chaos profile injects no fault"),
+            PatternError::MissingIssuer => write!(f, "JWT provider has no issuer"),
+            PatternError::NoPathRules => write!(f, "at least one path rule is required"),
+            PatternError::EmptyClaim(claim) => write!(f, "claim {} accepts no value", claim),
+            PatternError::WildcardHost(host) => {
+                write!(f, "host {} must be a DNS name without wildcard", host)
+            }
+            PatternError::Invalid { kind, name, errors } => {
+                write!(f, "generated {} {} is invalid: {}", kind, name, errors)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Blue-green deployment: both versions are declared as subsets and all traffic goes to the
+/// active one. Switching is done by regenerating with the other side active.
+pub fn blue_green(
+    service: &Service,
+    blue: &Version,
+    green: &Version,
+    active: Active,
+) -> Result<ResourceSet, PatternError> {
+    let live = match active {
+        Active::Blue => blue,
+        Active::Green => green,
+    };
+    ResourceSet {
+        virtual_services: vec![virtual_service(
+            service,
+            vec![http_route("live", None, vec![route(service, live, None)])],
+        )],
+        destination_rules: vec![destination_rule(service, &[blue, green], None)],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Header-based A/B test: requests carrying `header: value` go to `treatment`, all other requests
+/// to `control`.
+pub fn header_ab(
+    service: &Service,
+    header: &str,
+    value: &str,
+    control: &Version,
+    treatment: &Version,
+) -> Result<ResourceSet, PatternError> {
+    ResourceSet {
+        virtual_services: vec![virtual_service(
+            service,
+            Experiment::new(header, value).routes(service, control, treatment),
+        )],
+        destination_rules: vec![destination_rule(service, &[control, treatment], None)],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Weighted canary: `canary_weight` percent of the traffic goes to `canary`, the rest to `stable`.
+pub fn weighted_canary(
+    service: &Service,
+    stable: &Version,
+    canary: &Version,
+    canary_weight: u32,
+) -> Result<ResourceSet, PatternError> {
+    let canary_weight =
+        Weight::new(canary_weight).map_err(|_| PatternError::InvalidWeight(canary_weight))?;
+    ResourceSet {
+        virtual_services: vec![virtual_service(
+            service,
+            vec![http_route(
+                "canary",
+                None,
+                vec![
+                    route(service, stable, Some(canary_weight.complement())),
+                    route(service, canary, Some(canary_weight)),
+                ],
+            )],
+        )],
+        destination_rules: vec![destination_rule(service, &[stable, canary], None)],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Fault injection: the faults of `profile` are applied to all traffic sent to the service.
+pub fn chaos(service: &Service, profile: &ChaosProfile) -> Result<ResourceSet, PatternError> {
+    if profile.delay.is_none() && profile.abort.is_none() {
+        return Err(PatternError::EmptyProfile);
+    }
+    let mut rule = http_route(
+        "chaos",
+        None,
+        vec![HttpRouteDestination {
+            destination: destination(service, None),
+            weight: None,
+            headers: None,
+            extra: Map::new(),
+        }],
+    );
+    rule.fault = Some(HttpFaultInjection {
+        delay: profile
+            .delay
+            .map(|(fixed_delay, percentage)| FaultInjectionDelay {
+                fixed_delay: fixed_delay.into(),
+                percentage: Some(Percent::from(percentage)),
+                percent: None,
+            }),
+        abort: profile
+            .abort
+            .map(|(http_status, percentage)| FaultInjectionAbort {
+                http_status: Some(http_status),
+                grpc_status: None,
+                http2_error: None,
+                percentage: Some(Percent::from(percentage)),
+            }),
+    });
+    ResourceSet {
+        virtual_services: vec![virtual_service(service, vec![rule])],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Locality failover: traffic stays in the caller's locality and fails over along `failover`
+/// (`(from, to)` region pairs) once outlier detection ejects the local endpoints after
+/// `consecutive_errors` consecutive 5xx errors.
+pub fn locality_failover(
+    service: &Service,
+    failover: &[(&str, &str)],
+    consecutive_errors: u32,
+) -> Result<ResourceSet, PatternError> {
+    let policy = TrafficPolicy {
+        load_balancer: Some(LoadBalancerSettings {
+            lb_policy: Some(LbPolicy::Simple(SimpleLB::ROUND_ROBIN)),
+            locality_lb_setting: Some(LocalityLoadBalancerSetting {
+                distribute: None,
+                failover: Some(
+                    failover
+                        .iter()
+                        .map(|(from, to)| Failover {
+                            from: Some(from.to_string()),
+                            to: Some(to.to_string()),
+                        })
+                        .collect(),
+                ),
+                failover_priority: None,
+                enabled: Some(true),
+            }),
+            warmup_duration_secs: None,
+        }),
+        connection_pool: None,
+        outlier_detection: Some(OutlierDetection {
+            split_external_local_origin_errors: None,
+            consecutive_local_origin_failures: None,
+            consecutive_gateway_errors: None,
+            consecutive5xx_errors: Some(UInt32Value {
+                value: Some(consecutive_errors),
+            }),
+            consecutive_errors: None,
+            interval: Some(IstioDuration::from_secs(10)),
+            base_ejection_time: Some(IstioDuration::from_secs(30)),
+            max_ejection_percent: Some(100),
+            min_health_percent: None,
+        }),
+        tls: None,
+        port_level_settings: None,
+        tunnel: None,
+        proxy_protocol: None,
+        extra: Map::new(),
+    };
+    ResourceSet {
+        destination_rules: vec![destination_rule(service, &[], Some(policy))],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// JWT authentication: a RequestAuthentication validating the tokens of `provider` on the pods
+/// selected by `labels`, and an ALLOW AuthorizationPolicy granting access according to `rules`.
+/// Requests matching none of the rules are denied. Both resources are named after the service.
+pub fn jwt_authentication<K: Into<String>, V: Into<String>>(
+    service: &Service,
+    labels: impl IntoIterator<Item = (K, V)>,
+    provider: &JwtProvider,
+    rules: &[PathRule],
+) -> Result<ResourceSet, PatternError> {
+    if provider.issuer.is_empty() {
+        return Err(PatternError::MissingIssuer);
+    }
+    if rules.is_empty() {
+        return Err(PatternError::NoPathRules);
+    }
+    let selector = WorkloadSelector {
+        match_labels: Some(
+            labels
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        ),
+    };
+    let non_empty = |values: &[String]| Some(values.to_vec()).filter(|v| !v.is_empty());
+
+    let mut policy_rules = Vec::new();
+    for rule in rules {
+        // Every authenticated rule requires a principal of the provider, so a token of another
+        // issuer accepted by a different RequestAuthentication does not grant access.
+        let from = match &rule.access {
+            PathAccess::Public => None,
+            PathAccess::Authenticated | PathAccess::Claims(_) => Some(vec![From {
+                source: Source {
+                    request_principals: Some(vec![format!("{}/*", provider.issuer)]),
+                    ..Source::default()
+                },
+            }]),
+        };
+        let when = match &rule.access {
+            PathAccess::Claims(claims) => {
+                let mut conditions = Vec::new();
+                for (claim, values) in claims {
+                    if values.is_empty() {
+                        return Err(PatternError::EmptyClaim(claim.clone()));
+                    }
+                    conditions.push(Condition {
+                        key: format!("request.auth.claims[{}]", claim),
+                        values: Some(values.clone()),
+                        not_values: None,
+                    });
+                }
+                Some(conditions).filter(|conditions| !conditions.is_empty())
+            }
+            _ => None,
+        };
+        let operation = Operation {
+            paths: non_empty(&rule.paths),
+            methods: non_empty(&rule.methods),
+            ..Operation::default()
+        };
+        policy_rules.push(Rule {
+            from,
+            to: Some(vec![To { operation }])
+                .filter(|_| !rule.paths.is_empty() || !rule.methods.is_empty()),
+            when,
+        });
+    }
+
+    ResourceSet {
+        request_authentications: vec![RequestAuthentication {
+            metadata: service.metadata(),
+            spec: Some(RequestAuthenticationSpec {
+                selector: Some(selector.clone()),
+                jwt_rules: Some(vec![JWTRule {
+                    issuer: provider.issuer.clone(),
+                    audiences: non_empty(&provider.audiences),
+                    jwks_uri: provider.jwks_uri.clone(),
+                    ..JWTRule::default()
+                }]),
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        authorization_policies: vec![AuthorizationPolicy {
+            metadata: service.metadata(),
+            spec: Some(AuthorizationPolicySpec {
+                selector: Some(selector),
+                rules: Some(policy_rules),
+                action: Some(Action::ALLOW),
+                provider: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Egress through a gateway with TLS origination: sidecars send plain HTTP for `external_host` on
+/// port 80 to the egress gateway over mutual TLS, and the gateway originates TLS to port 443 of
+/// the external host. Applications keep calling `http://<external_host>`.
+pub fn egress_gateway(
+    external_host: &str,
+    gateway: &EgressGateway,
+) -> Result<ResourceSet, PatternError> {
+    if external_host.is_empty() || external_host.contains('*') {
+        return Err(PatternError::WildcardHost(external_host.to_string()));
+    }
+    let name = external_host.replace('.', "-");
+    let metadata = |name: String| ObjectMeta {
+        name: Some(name),
+        namespace: Some(gateway.namespace.clone()),
+        ..ObjectMeta::default()
+    };
+    let port_tls = |port: u32, mode: ClientTLSmode| PortTrafficPolicy {
+        port: Some(PortSelector {
+            number: Some(port),
+            name: None,
+        }),
+        load_balancer: None,
+        connection_pool: None,
+        outlier_detection: None,
+        tls: Some(ClientTLSSettings {
+            mode,
+            client_certificate: None,
+            private_key: None,
+            ca_certificates: None,
+            credential_name: None,
+            subject_alt_names: None,
+            sni: Some(external_host.to_string()),
+            insecure_skip_verify: None,
+        }),
+    };
+    let forward = |gateway: &str, host: &str, subset: Option<&str>, port: u32| {
+        http_route(
+            &format!("{}-{}", name, gateway),
+            Some(vec![HttpMatchRequest {
+                name: None,
+                uri: None,
+                scheme: None,
+                method: None,
+                authority: None,
+                headers: None,
+                port: Some(80),
+                source_labels: None,
+                gateways: Some(vec![gateway.to_string()]),
+                query_params: None,
+                ignore_uri_case: None,
+                without_headers: None,
+                source_namespace: None,
+                extra: Map::new(),
+            }]),
+            vec![HttpRouteDestination {
+                destination: Destination {
+                    host: host.to_string(),
+                    subset: subset.map(str::to_string),
+                    port: Some(PortSelector {
+                        number: Some(port),
+                        name: None,
+                    }),
+                },
+                weight: None,
+                headers: None,
+                extra: Map::new(),
+            }],
+        )
+    };
+
+    let mut gateway_policy = empty_traffic_policy();
+    gateway_policy.port_level_settings = Some(vec![port_tls(80, ClientTLSmode::ISTIO_MUTUAL)]);
+    let mut origination_policy = empty_traffic_policy();
+    origination_policy.port_level_settings = Some(vec![port_tls(443, ClientTLSmode::SIMPLE)]);
+
+    ResourceSet {
+        service_entries: vec![ServiceEntry {
+            metadata: metadata(name.clone()),
+            spec: Some(ServiceEntrySpec {
+                hosts: vec![external_host.to_string()],
+                addresses: None,
+                ports: Some(vec![
+                    ServicePort {
+                        number: 80,
+                        protocol: Some("HTTP".to_string()),
+                        name: "http".to_string(),
+                        target_port: None,
+                    },
+                    ServicePort {
+                        number: 443,
+                        protocol: Some("HTTPS".to_string()),
+                        name: "https".to_string(),
+                        target_port: None,
+                    },
+                ]),
+                location: Some(Location::MESH_EXTERNAL),
+                resolution: Some(Resolution::DNS),
+                endpoints: None,
+                workload_selector: None,
+                export_to: None,
+                subject_alt_names: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        gateways: vec![Gateway {
+            metadata: metadata(gateway.name.clone()),
+            spec: Some(GatewaySpec {
+                servers: vec![Server {
+                    port: Port {
+                        number: 80,
+                        protocol: "HTTPS".to_string(),
+                        name: "https-port-for-tls-origination".to_string(),
+                        target_port: None,
+                    },
+                    bind: None,
+                    hosts: vec![external_host.to_string()],
+                    tls: Some(ServerTLSSettings {
+                        https_redirect: None,
+                        mode: Some(ServerTLSmode::ISTIO_MUTUAL),
+                        server_certificate: None,
+                        private_key: None,
+                        ca_certificates: None,
+                        credential_name: None,
+                        subject_alt_names: None,
+                        verify_certificate_spki: None,
+                        verify_certificate_hash: None,
+                        min_protocol_version: None,
+                        max_protocol_version: None,
+                        cipher_suites: None,
+                    }),
+                    name: None,
+                }],
+                selector: gateway.selector.clone(),
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        virtual_services: vec![VirtualService {
+            metadata: metadata(name.clone()),
+            spec: Some(VirtualServiceSpec {
+                hosts: Some(vec![external_host.to_string()]),
+                gateways: Some(vec![gateway.name.clone(), MESH_GATEWAY.to_string()]),
+                http: Some(vec![
+                    forward(MESH_GATEWAY, &gateway.host, Some(&name), 80),
+                    forward(&gateway.name, external_host, None, 443),
+                ]),
+                tls: None,
+                tcp: None,
+                export_to: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        destination_rules: vec![
+            DestinationRule {
+                metadata: metadata(format!("{}-egressgateway", name)),
+                spec: Some(DestinationRuleSpec {
+                    host: gateway.host.clone(),
+                    traffic_policy: None,
+                    subsets: Some(vec![Subset {
+                        name: name.clone(),
+                        labels: None,
+                        traffic_policy: Some(gateway_policy),
+                        extra: Map::new(),
+                    }]),
+                    export_to: None,
+                    workload_selector: None,
+                    extra: Map::new(),
+                }),
+                status: None,
+            },
+            DestinationRule {
+                metadata: metadata(format!("{}-tls-origination", name)),
+                spec: Some(DestinationRuleSpec {
+                    host: external_host.to_string(),
+                    traffic_policy: Some(origination_policy),
+                    subsets: None,
+                    export_to: None,
+                    workload_selector: None,
+                    extra: Map::new(),
+                }),
+                status: None,
+            },
+        ],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// Exposes `port` of `service` under `hostname` through the default ingress gateway: a Gateway
+/// accepting `hostname` and a VirtualService routing it to the service. With a `tls_credential`
+/// the gateway terminates TLS on 443 using that secret, which must live in the namespace of the
+/// ingress gateway pods, and redirects plain HTTP to HTTPS; otherwise it serves plain HTTP on 80.
+pub fn expose_service(
+    service: &Service,
+    port: u32,
+    hostname: &str,
+    tls_credential: Option<&str>,
+) -> Result<ResourceSet, PatternError> {
+    let gateway = format!("{}-ingress", service.name);
+    let mut metadata = service.metadata();
+    metadata.name = Some(gateway.clone());
+    ResourceSet {
+        gateways: vec![Gateway {
+            metadata,
+            spec: Some(GatewaySpec {
+                servers: ingress_servers(hostname, tls_credential),
+                selector: Map::from([("istio".to_string(), "ingressgateway".to_string())]),
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        virtual_services: vec![VirtualService {
+            metadata: service.metadata(),
+            spec: Some(VirtualServiceSpec {
+                hosts: Some(vec![hostname.to_string()]),
+                gateways: Some(vec![gateway]),
+                http: Some(vec![http_route(
+                    "ingress",
+                    None,
+                    vec![HttpRouteDestination {
+                        destination: Destination {
+                            host: service.host.clone(),
+                            subset: None,
+                            port: Some(PortSelector {
+                                number: Some(port),
+                                name: None,
+                            }),
+                        },
+                        weight: None,
+                        headers: None,
+                        extra: Map::new(),
+                    }],
+                )]),
+                tls: None,
+                tcp: None,
+                export_to: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }],
+        ..ResourceSet::default()
+    }
+    .validated()
+}
+
+/// The Gateway servers accepting `hostname`, as generated by `expose_service`, for adding the host
+/// to an existing Gateway.
+pub fn ingress_servers(hostname: &str, tls_credential: Option<&str>) -> Vec<Server> {
+    let name = hostname.replace('*', "wildcard").replace('.', "-");
+    let server = |number: i32, protocol: &str, tls: Option<ServerTLSSettings>| Server {
+        port: Port {
+            number,
+            protocol: protocol.to_string(),
+            name: format!("{}-{}", protocol.to_lowercase(), name),
+            target_port: None,
+        },
+        bind: None,
+        hosts: vec![hostname.to_string()],
+        tls,
+        name: None,
+    };
+    let tls = |mode: Option<ServerTLSmode>,
+               https_redirect: Option<bool>,
+               credential_name: Option<String>| ServerTLSSettings {
+        https_redirect,
+        mode,
+        server_certificate: None,
+        private_key: None,
+        ca_certificates: None,
+        credential_name,
+        subject_alt_names: None,
+        verify_certificate_spki: None,
+        verify_certificate_hash: None,
+        min_protocol_version: None,
+        max_protocol_version: None,
+        cipher_suites: None,
+    };
+    match tls_credential {
+        Some(credential) => vec![
+            server(
+                443,
+                "HTTPS",
+                Some(tls(
+                    Some(ServerTLSmode::SIMPLE),
+                    None,
+                    Some(credential.to_string()),
+                )),
+            ),
+            server(80, "HTTP", Some(tls(None, Some(true), None))),
+        ],
+        None => vec![server(80, "HTTP", None)],
+    }
+}
+
+/// East-west traffic of a multi-network mesh: the gateway exposing the services of this cluster to
+/// the other networks on port 15443. Connections are passed through on their SNI, so mutual TLS
+/// between the workloads is kept end to end. With `expose_istiod` the control plane ports are
+/// exposed as well, routed to istiod by a VirtualService.
+pub fn east_west_gateway(gateway: &EastWestGateway) -> Result<ResourceSet, PatternError> {
+    let metadata = |name: &str| ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(gateway.namespace.clone()),
+        ..ObjectMeta::default()
+    };
+    let server = |number: i32, name: &str, host: &str, mode: ServerTLSmode| Server {
+        port: Port {
+            number,
+            protocol: "TLS".to_string(),
+            name: name.to_string(),
+            target_port: None,
+        },
+        bind: None,
+        hosts: vec![host.to_string()],
+        tls: Some(ServerTLSSettings {
+            https_redirect: None,
+            mode: Some(mode),
+            server_certificate: None,
+            private_key: None,
+            ca_certificates: None,
+            credential_name: None,
+            subject_alt_names: None,
+            verify_certificate_spki: None,
+            verify_certificate_hash: None,
+            min_protocol_version: None,
+            max_protocol_version: None,
+            cipher_suites: None,
+        }),
+        name: None,
+    };
+    let gateway_resource = |name: &str, servers: Vec<Server>| Gateway {
+        metadata: metadata(name),
+        spec: Some(GatewaySpec {
+            servers,
+            selector: gateway.selector.clone(),
+            extra: Map::new(),
+        }),
+        status: None,
+    };
+
+    let mut resources = ResourceSet {
+        gateways: vec![gateway_resource(
+            "cross-network-gateway",
+            vec![server(
+                15443,
+                "tls",
+                "*.local",
+                ServerTLSmode::AUTO_PASSTHROUGH,
+            )],
+        )],
+        ..ResourceSet::default()
+    };
+    if gateway.expose_istiod {
+        let istiod = format!("istiod.{}.svc.cluster.local", gateway.namespace);
+        let forward = |port: u32, target: u32| TlsRoute {
+            r#match: vec![TlsMatchAttributes {
+                sni_hosts: vec!["*".to_string()],
+                destination_subnets: None,
+                port: Some(port),
+                source_labels: None,
+                gateways: None,
+                source_namespace: None,
+            }],
+            route: Some(vec![RouteDestination {
+                destination: Destination {
+                    host: istiod.clone(),
+                    subset: None,
+                    port: Some(PortSelector {
+                        number: Some(target),
+                        name: None,
+                    }),
+                },
+                weight: None,
+            }]),
+            extra: Map::new(),
+        };
+        resources.gateways.push(gateway_resource(
+            "istiod-gateway",
+            vec![
+                server(15012, "tls-istiod", "*", ServerTLSmode::PASSTHROUGH),
+                server(15017, "tls-istiodwebhook", "*", ServerTLSmode::PASSTHROUGH),
+            ],
+        ));
+        resources.virtual_services.push(VirtualService {
+            metadata: metadata("istiod-vs"),
+            spec: Some(VirtualServiceSpec {
+                hosts: Some(vec!["*".to_string()]),
+                gateways: Some(vec!["istiod-gateway".to_string()]),
+                http: None,
+                tls: Some(vec![forward(15012, 15012), forward(15017, 443)]),
+                tcp: None,
+                export_to: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        });
+    }
+    resources.validated()
+}
+
+fn virtual_service(service: &Service, http: Vec<HttpRoute>) -> VirtualService {
+    VirtualService {
+        metadata: service.metadata(),
+        spec: Some(VirtualServiceSpec {
+            hosts: Some(vec![service.host.clone()]),
+            gateways: None,
+            http: Some(http),
+            tls: None,
+            tcp: None,
+            export_to: None,
+            extra: Map::new(),
+        }),
+        status: None,
+    }
+}
+
+fn destination_rule(
+    service: &Service,
+    versions: &[&Version],
+    traffic_policy: Option<TrafficPolicy>,
+) -> DestinationRule {
+    DestinationRule {
+        metadata: service.metadata(),
+        spec: Some(DestinationRuleSpec {
+            host: service.host.clone(),
+            traffic_policy,
+            subsets: Some(
+                versions
+                    .iter()
+                    .map(|version| Subset {
+                        name: version.subset.clone(),
+                        labels: Some(version.labels.clone()),
+                        traffic_policy: None,
+                        extra: Map::new(),
+                    })
+                    .collect(),
+            )
+            .filter(|subsets: &Vec<Subset>| !subsets.is_empty()),
+            export_to: None,
+            workload_selector: None,
+            extra: Map::new(),
+        }),
+        status: None,
+    }
+}
+
+fn http_route(
+    name: &str,
+    matches: Option<Vec<HttpMatchRequest>>,
+    route: Vec<HttpRouteDestination>,
+) -> HttpRoute {
+    HttpRoute {
+        name: Some(name.to_string()),
+        r#match: matches,
+        route: Some(route),
+        redirect: None,
+        direct_response: None,
+        delegate: None,
+        rewrite: None,
+        timeout: None,
+        retries: None,
+        fault: None,
+        mirror: None,
+        mirror_percentage: None,
+        mirrors: None,
+        cors_policy: None,
+        headers: None,
+        mirror_percent: None,
+        extra: Map::new(),
+    }
+}
+
+fn route(service: &Service, version: &Version, weight: Option<Weight>) -> HttpRouteDestination {
+    HttpRouteDestination {
+        destination: destination(service, Some(version)),
+        weight,
+        headers: None,
+        extra: Map::new(),
+    }
+}
+
+fn destination(service: &Service, version: Option<&Version>) -> Destination {
+    Destination {
+        host: service.host.clone(),
+        subset: version.map(|version| version.subset.clone()),
+        port: None,
+    }
+}
+
+fn empty_traffic_policy() -> TrafficPolicy {
+    TrafficPolicy {
+        load_balancer: None,
+        connection_pool: None,
+        outlier_detection: None,
+        tls: None,
+        port_level_settings: None,
+        tunnel: None,
+        proxy_protocol: None,
+        extra: Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    #[test]
+    fn locality_failover_ejects_after_plain_error_count() {
+        let service = Service::new("reviews", "default");
+        let set = locality_failover(&service, &[("us-east", "us-west")], 7).unwrap();
+        let rule = serde_json::to_value(&set.destination_rules[0]).unwrap();
+        let policy = &rule["spec"]["trafficPolicy"];
+        assert_eq!(policy["outlierDetection"]["consecutive5xxErrors"], json!(7));
+        assert_eq!(
+            policy["loadBalancer"]["localityLbSetting"]["failover"],
+            json!([{ "from": "us-east", "to": "us-west" }])
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerAuthentication {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<PeerAuthenticationSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for PeerAuthentication {
+    const API_VERSION: &'static str = "security.istio.io/v1beta1";
+    const GROUP: &'static str = "security.istio.io";
+    const KIND: &'static str = "PeerAuthentication";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "peerauthentications";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for PeerAuthentication {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl PeerAuthentication {
+    /// Requires mutual TLS for every workload of `namespace`. The policy is named `default`, the
+    /// name istioctl and the Istio docs use for namespace-wide policies.
+    pub fn strict_for_namespace(namespace: &str) -> Self {
+        PeerAuthentication::new("default", namespace, None, MutualTlsMode::STRICT)
+    }
+
+    /// Accepts both mutual TLS and plain text for the workloads matched by `selector`, e.g. to
+    /// exempt them from a namespace-wide STRICT policy.
+    pub fn permissive_for_workload(
+        name: &str,
+        namespace: &str,
+        selector: WorkloadSelector,
+    ) -> Self {
+        PeerAuthentication::new(name, namespace, Some(selector), MutualTlsMode::PERMISSIVE)
+    }
+
+    /// The mode of the policy for traffic on `port`, taking port level settings into account.
+    /// `None` when the policy leaves the mode to its parent.
+    pub fn mode(&self, port: Option<u32>) -> Option<MutualTlsMode> {
+        let spec = self.spec.as_ref()?;
+        let port_mode = port
+            .and_then(|port| spec.port_level_mtls.as_ref()?.get(&port))
+            .and_then(|mtls| mtls.mode);
+        port_mode
+            .or_else(|| spec.mtls.as_ref().and_then(|mtls| mtls.mode))
+            .filter(|mode| *mode != MutualTlsMode::UNSET)
+    }
+
+    fn new(
+        name: &str,
+        namespace: &str,
+        selector: Option<WorkloadSelector>,
+        mode: MutualTlsMode,
+    ) -> Self {
+        PeerAuthentication {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(PeerAuthenticationSpec {
+                selector,
+                mtls: Some(MutualTls { mode: Some(mode) }),
+                port_level_mtls: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }
+    }
+}
+
+/// # PeerAuthentication
+/// PeerAuthentication defines how traffic will be tunneled (or not) to the sidecar.
+///
+/// Policy to allow mTLS traffic for all workloads under namespace `foo`:
+///
+/// ```yaml
+/// apiVersion: security.istio.io/v1beta1
+/// kind: PeerAuthentication
+/// metadata:
+///   name: default
+///   namespace: foo
+/// spec:
+///   mtls:
+///     mode: STRICT
+/// ```
+///
+/// For mesh level, put the policy in root-namespace according to your Istio installation.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerAuthenticationSpec {
+    // The selector determines the workloads to apply the ChannelAuthentication on. If not set, the policy will be applied to all workloads in the same namespace as the policy.
+    // No
+    pub selector: Option<WorkloadSelector>,
+
+    // Mutual TLS settings for workload. If not defined, inherit from parent.
+    // No
+    pub mtls: Option<MutualTls>,
+
+    // Port specific mutual TLS settings.
+    // No
+    #[serde(rename = "portLevelMtls")]
+    pub port_level_mtls: Option<Map<u32, MutualTls>>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # MutualTls
+/// Mutual TLS settings.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MutualTls {
+    // Defines the mTLS mode used for peer authentication.
+    // No
+    pub mode: Option<MutualTlsMode>,
+}
+
+/// # MutualTlsMode
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum MutualTlsMode {
+    /// Inherit from parent, if has one. Otherwise treated as PERMISSIVE.
+    UNSET,
+
+    /// Connection is not tunneled.
+    DISABLE,
+
+    /// Connection can be either plaintext or mTLS tunnel.
+    PERMISSIVE,
+
+    /// Connection is an mTLS tunnel (TLS with client cert must be presented).
+    STRICT,
+}
@@ -0,0 +1,1709 @@
+// Protobuf interop with the `istio.networking.v1beta1` API, for tooling that speaks proto (MCP,
+// xDS-adjacent services) rather than Kubernetes JSON. Needs the `prost` feature.
+//
+// The messages are written out by hand with the field numbers of istio/api, so no protoc is needed
+// to build the crate. They cover the routing core of VirtualService, DestinationRule and Gateway:
+// converting a spec that sets a field without a message here fails with `ProtoError::Unsupported`
+// naming the field, rather than dropping it. Going the other way, proto3 defaults (empty strings
+// and lists, zero ports and weights) are read as unset.
+use crate::istio::destination_rule::{
+    DestinationRuleSpec, LbPolicy, LoadBalancerSettings, OutlierDetection, Subset, TrafficPolicy,
+};
+use crate::istio::duration::IstioDuration;
+use crate::istio::export_to::ExportTo;
+use crate::istio::gateway::{self, GatewaySpec, Server, ServerTLSSettings, TLSProtocol, TLSmode};
+use crate::istio::google::protobuf::UInt32Value;
+use crate::istio::load_balancer_settings::consistent_hash_lb::HTTPCookie;
+use crate::istio::load_balancer_settings::{
+    ConsistentHashLB, HashAlgorithm, HashKey, MagLev, RingHash, SimpleLB,
+};
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::virtual_service::{
+    Delegate, Destination, HttpMatchRequest, HttpRoute, HttpRouteDestination, L4MatchAttributes,
+    PortSelector, RouteDestination, StringMatch, TcpRoute, TlsMatchAttributes, TlsRoute,
+    VirtualServiceSpec,
+};
+use crate::istio::weight::Weight;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod google {
+    pub mod protobuf {
+        /// # Duration
+        /// A signed, fixed-length span of time in seconds and nanoseconds.
+        #[derive(Clone, Copy, PartialEq, Eq, ::prost::Message)]
+        pub struct Duration {
+            #[prost(int64, tag = "1")]
+            pub seconds: i64,
+            #[prost(int32, tag = "2")]
+            pub nanos: i32,
+        }
+    }
+}
+
+pub mod r#type {
+    pub mod v1beta1 {
+        use std::collections::HashMap;
+
+        /// # WorkloadSelector
+        /// `istio.type.v1beta1.WorkloadSelector`.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct WorkloadSelector {
+            #[prost(map = "string, string", tag = "1")]
+            pub match_labels: HashMap<String, String>,
+        }
+    }
+}
+
+pub mod networking {
+    pub mod v1beta1 {
+        use super::super::google::protobuf::Duration;
+        use super::super::r#type::v1beta1::WorkloadSelector;
+        use std::collections::HashMap;
+
+        /// # VirtualService
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct VirtualService {
+            #[prost(string, repeated, tag = "1")]
+            pub hosts: Vec<String>,
+            #[prost(string, repeated, tag = "2")]
+            pub gateways: Vec<String>,
+            #[prost(message, repeated, tag = "3")]
+            pub http: Vec<HttpRoute>,
+            #[prost(message, repeated, tag = "5")]
+            pub tls: Vec<TlsRoute>,
+            #[prost(message, repeated, tag = "4")]
+            pub tcp: Vec<TcpRoute>,
+            #[prost(string, repeated, tag = "6")]
+            pub export_to: Vec<String>,
+        }
+
+        /// # Destination
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Destination {
+            #[prost(string, tag = "1")]
+            pub host: String,
+            #[prost(string, tag = "2")]
+            pub subset: String,
+            #[prost(message, optional, tag = "3")]
+            pub port: Option<PortSelector>,
+        }
+
+        /// # HTTPRoute
+        /// The routing part of an HTTP route: matches, destinations, delegation and timeout.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct HttpRoute {
+            #[prost(string, tag = "17")]
+            pub name: String,
+            #[prost(message, repeated, tag = "1")]
+            pub r#match: Vec<HttpMatchRequest>,
+            #[prost(message, repeated, tag = "2")]
+            pub route: Vec<HttpRouteDestination>,
+            #[prost(message, optional, tag = "20")]
+            pub delegate: Option<Delegate>,
+            #[prost(message, optional, tag = "6")]
+            pub timeout: Option<Duration>,
+        }
+
+        /// # Delegate
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Delegate {
+            #[prost(string, tag = "1")]
+            pub name: String,
+            #[prost(string, tag = "2")]
+            pub namespace: String,
+        }
+
+        /// # TLSRoute
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct TlsRoute {
+            #[prost(message, repeated, tag = "1")]
+            pub r#match: Vec<TlsMatchAttributes>,
+            #[prost(message, repeated, tag = "2")]
+            pub route: Vec<RouteDestination>,
+        }
+
+        /// # TCPRoute
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct TcpRoute {
+            #[prost(message, repeated, tag = "1")]
+            pub r#match: Vec<L4MatchAttributes>,
+            #[prost(message, repeated, tag = "2")]
+            pub route: Vec<RouteDestination>,
+        }
+
+        /// # HTTPMatchRequest
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct HttpMatchRequest {
+            #[prost(string, tag = "11")]
+            pub name: String,
+            #[prost(message, optional, tag = "1")]
+            pub uri: Option<StringMatch>,
+            #[prost(message, optional, tag = "2")]
+            pub scheme: Option<StringMatch>,
+            #[prost(message, optional, tag = "3")]
+            pub method: Option<StringMatch>,
+            #[prost(message, optional, tag = "4")]
+            pub authority: Option<StringMatch>,
+            #[prost(map = "string, message", tag = "5")]
+            pub headers: HashMap<String, StringMatch>,
+            #[prost(uint32, tag = "6")]
+            pub port: u32,
+            #[prost(map = "string, string", tag = "7")]
+            pub source_labels: HashMap<String, String>,
+            #[prost(string, repeated, tag = "8")]
+            pub gateways: Vec<String>,
+            #[prost(map = "string, message", tag = "9")]
+            pub query_params: HashMap<String, StringMatch>,
+            #[prost(bool, tag = "10")]
+            pub ignore_uri_case: bool,
+            #[prost(map = "string, message", tag = "12")]
+            pub without_headers: HashMap<String, StringMatch>,
+            #[prost(string, tag = "13")]
+            pub source_namespace: String,
+        }
+
+        /// # HTTPRouteDestination
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct HttpRouteDestination {
+            #[prost(message, optional, tag = "1")]
+            pub destination: Option<Destination>,
+            #[prost(int32, tag = "2")]
+            pub weight: i32,
+        }
+
+        /// # RouteDestination
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct RouteDestination {
+            #[prost(message, optional, tag = "1")]
+            pub destination: Option<Destination>,
+            #[prost(int32, tag = "2")]
+            pub weight: i32,
+        }
+
+        /// # L4MatchAttributes
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct L4MatchAttributes {
+            #[prost(string, repeated, tag = "1")]
+            pub destination_subnets: Vec<String>,
+            #[prost(uint32, tag = "2")]
+            pub port: u32,
+            #[prost(string, tag = "3")]
+            pub source_subnet: String,
+            #[prost(map = "string, string", tag = "4")]
+            pub source_labels: HashMap<String, String>,
+            #[prost(string, repeated, tag = "5")]
+            pub gateways: Vec<String>,
+            #[prost(string, tag = "6")]
+            pub source_namespace: String,
+        }
+
+        /// # TLSMatchAttributes
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct TlsMatchAttributes {
+            #[prost(string, repeated, tag = "1")]
+            pub sni_hosts: Vec<String>,
+            #[prost(string, repeated, tag = "2")]
+            pub destination_subnets: Vec<String>,
+            #[prost(uint32, tag = "3")]
+            pub port: u32,
+            #[prost(map = "string, string", tag = "5")]
+            pub source_labels: HashMap<String, String>,
+            #[prost(string, repeated, tag = "6")]
+            pub gateways: Vec<String>,
+            #[prost(string, tag = "7")]
+            pub source_namespace: String,
+        }
+
+        /// # StringMatch
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct StringMatch {
+            #[prost(oneof = "string_match::MatchType", tags = "1, 2, 3")]
+            pub match_type: Option<string_match::MatchType>,
+        }
+
+        pub mod string_match {
+            #[derive(Clone, PartialEq, ::prost::Oneof)]
+            pub enum MatchType {
+                #[prost(string, tag = "1")]
+                Exact(String),
+                #[prost(string, tag = "2")]
+                Prefix(String),
+                #[prost(string, tag = "3")]
+                Regex(String),
+            }
+        }
+
+        /// # PortSelector
+        #[derive(Clone, Copy, PartialEq, Eq, ::prost::Message)]
+        pub struct PortSelector {
+            #[prost(uint32, tag = "1")]
+            pub number: u32,
+        }
+
+        /// # DestinationRule
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct DestinationRule {
+            #[prost(string, tag = "1")]
+            pub host: String,
+            #[prost(message, optional, tag = "2")]
+            pub traffic_policy: Option<TrafficPolicy>,
+            #[prost(message, repeated, tag = "3")]
+            pub subsets: Vec<Subset>,
+            #[prost(string, repeated, tag = "4")]
+            pub export_to: Vec<String>,
+            #[prost(message, optional, tag = "5")]
+            pub workload_selector: Option<WorkloadSelector>,
+        }
+
+        /// # TrafficPolicy
+        /// Load balancing and outlier detection of a traffic policy.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct TrafficPolicy {
+            #[prost(message, optional, tag = "1")]
+            pub load_balancer: Option<LoadBalancerSettings>,
+            #[prost(message, optional, tag = "3")]
+            pub outlier_detection: Option<OutlierDetection>,
+        }
+
+        /// # Subset
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Subset {
+            #[prost(string, tag = "1")]
+            pub name: String,
+            #[prost(map = "string, string", tag = "2")]
+            pub labels: HashMap<String, String>,
+            #[prost(message, optional, tag = "3")]
+            pub traffic_policy: Option<TrafficPolicy>,
+        }
+
+        /// # LoadBalancerSettings
+        /// The load balancing policy and warmup, without locality load balancing.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct LoadBalancerSettings {
+            #[prost(oneof = "load_balancer_settings::LbPolicy", tags = "1, 2")]
+            pub lb_policy: Option<load_balancer_settings::LbPolicy>,
+            #[prost(message, optional, tag = "4")]
+            pub warmup_duration_secs: Option<Duration>,
+        }
+
+        pub mod load_balancer_settings {
+            /// # SimpleLB
+            #[derive(
+                Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration,
+            )]
+            #[repr(i32)]
+            pub enum SimpleLb {
+                Unspecified = 0,
+                LeastConn = 1,
+                Random = 2,
+                Passthrough = 3,
+                RoundRobin = 4,
+                LeastRequest = 5,
+            }
+
+            /// # ConsistentHashLB
+            #[derive(Clone, PartialEq, ::prost::Message)]
+            pub struct ConsistentHashLb {
+                #[prost(oneof = "consistent_hash_lb::HashKey", tags = "1, 2, 3, 5")]
+                pub hash_key: Option<consistent_hash_lb::HashKey>,
+                #[prost(oneof = "consistent_hash_lb::HashAlgorithm", tags = "6, 7")]
+                pub hash_algorithm: Option<consistent_hash_lb::HashAlgorithm>,
+                #[prost(uint64, tag = "4")]
+                pub minimum_ring_size: u64,
+            }
+
+            pub mod consistent_hash_lb {
+                use super::super::super::super::google::protobuf::Duration;
+
+                /// # HTTPCookie
+                #[derive(Clone, PartialEq, ::prost::Message)]
+                pub struct HttpCookie {
+                    #[prost(string, tag = "1")]
+                    pub name: String,
+                    #[prost(string, tag = "2")]
+                    pub path: String,
+                    #[prost(message, optional, tag = "3")]
+                    pub ttl: Option<Duration>,
+                }
+
+                /// # RingHash
+                #[derive(Clone, Copy, PartialEq, Eq, ::prost::Message)]
+                pub struct RingHash {
+                    #[prost(uint64, tag = "1")]
+                    pub minimum_ring_size: u64,
+                }
+
+                /// # MagLev
+                #[derive(Clone, Copy, PartialEq, Eq, ::prost::Message)]
+                pub struct MagLev {
+                    #[prost(uint64, tag = "1")]
+                    pub table_size: u64,
+                }
+
+                #[derive(Clone, PartialEq, ::prost::Oneof)]
+                pub enum HashKey {
+                    #[prost(string, tag = "1")]
+                    HttpHeaderName(String),
+                    #[prost(message, tag = "2")]
+                    HttpCookie(HttpCookie),
+                    #[prost(bool, tag = "3")]
+                    UseSourceIp(bool),
+                    #[prost(string, tag = "5")]
+                    HttpQueryParameterName(String),
+                }
+
+                #[derive(Clone, PartialEq, ::prost::Oneof)]
+                pub enum HashAlgorithm {
+                    #[prost(message, tag = "6")]
+                    RingHash(RingHash),
+                    #[prost(message, tag = "7")]
+                    Maglev(MagLev),
+                }
+            }
+
+            #[derive(Clone, PartialEq, ::prost::Oneof)]
+            pub enum LbPolicy {
+                #[prost(enumeration = "SimpleLb", tag = "1")]
+                Simple(i32),
+                #[prost(message, tag = "2")]
+                ConsistentHash(ConsistentHashLb),
+            }
+        }
+
+        /// # OutlierDetection
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct OutlierDetection {
+            #[prost(bool, tag = "8")]
+            pub split_external_local_origin_errors: bool,
+            #[prost(message, optional, tag = "9")]
+            pub consecutive_local_origin_failures: Option<u32>,
+            #[prost(message, optional, tag = "6")]
+            pub consecutive_gateway_errors: Option<u32>,
+            #[prost(message, optional, tag = "7")]
+            pub consecutive_5xx_errors: Option<u32>,
+            #[prost(int32, tag = "1")]
+            pub consecutive_errors: i32,
+            #[prost(message, optional, tag = "2")]
+            pub interval: Option<Duration>,
+            #[prost(message, optional, tag = "3")]
+            pub base_ejection_time: Option<Duration>,
+            #[prost(int32, tag = "4")]
+            pub max_ejection_percent: i32,
+            #[prost(int32, tag = "5")]
+            pub min_health_percent: i32,
+        }
+
+        /// # Gateway
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Gateway {
+            #[prost(message, repeated, tag = "1")]
+            pub servers: Vec<Server>,
+            #[prost(map = "string, string", tag = "2")]
+            pub selector: HashMap<String, String>,
+        }
+
+        /// # Server
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Server {
+            #[prost(message, optional, tag = "1")]
+            pub port: Option<Port>,
+            #[prost(string, tag = "4")]
+            pub bind: String,
+            #[prost(string, repeated, tag = "2")]
+            pub hosts: Vec<String>,
+            #[prost(message, optional, tag = "3")]
+            pub tls: Option<ServerTlsSettings>,
+            #[prost(string, tag = "6")]
+            pub name: String,
+        }
+
+        /// # Port
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Port {
+            #[prost(uint32, tag = "1")]
+            pub number: u32,
+            #[prost(string, tag = "2")]
+            pub protocol: String,
+            #[prost(string, tag = "3")]
+            pub name: String,
+            #[prost(uint32, tag = "4")]
+            pub target_port: u32,
+        }
+
+        /// # ServerTLSSettings
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct ServerTlsSettings {
+            #[prost(bool, tag = "1")]
+            pub https_redirect: bool,
+            #[prost(enumeration = "server_tls_settings::TlSmode", tag = "2")]
+            pub mode: i32,
+            #[prost(string, tag = "3")]
+            pub server_certificate: String,
+            #[prost(string, tag = "4")]
+            pub private_key: String,
+            #[prost(string, tag = "5")]
+            pub ca_certificates: String,
+            #[prost(string, tag = "10")]
+            pub credential_name: String,
+            #[prost(string, repeated, tag = "6")]
+            pub subject_alt_names: Vec<String>,
+            #[prost(string, repeated, tag = "11")]
+            pub verify_certificate_spki: Vec<String>,
+            #[prost(string, repeated, tag = "12")]
+            pub verify_certificate_hash: Vec<String>,
+            #[prost(enumeration = "server_tls_settings::TlsProtocol", tag = "7")]
+            pub min_protocol_version: i32,
+            #[prost(enumeration = "server_tls_settings::TlsProtocol", tag = "8")]
+            pub max_protocol_version: i32,
+            #[prost(string, repeated, tag = "9")]
+            pub cipher_suites: Vec<String>,
+        }
+
+        pub mod server_tls_settings {
+            /// # ServerTLSSettings.TLSmode
+            #[derive(
+                Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration,
+            )]
+            #[repr(i32)]
+            pub enum TlSmode {
+                Passthrough = 0,
+                Simple = 1,
+                Mutual = 2,
+                AutoPassthrough = 3,
+                IstioMutual = 4,
+                OptionalMutual = 5,
+            }
+
+            /// # ServerTLSSettings.TLSProtocol
+            #[derive(
+                Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration,
+            )]
+            #[repr(i32)]
+            pub enum TlsProtocol {
+                TlsAuto = 0,
+                TlSv10 = 1,
+                TlSv11 = 2,
+                TlSv12 = 3,
+                TlSv13 = 4,
+            }
+        }
+    }
+}
+
+use networking::v1beta1 as proto;
+use proto::load_balancer_settings::consistent_hash_lb as proto_hash;
+use proto::load_balancer_settings::SimpleLb;
+use proto::server_tls_settings::{TlSmode, TlsProtocol};
+
+/// # ProtoError
+/// A spec that has no protobuf representation here, or a message that has no representation in
+/// the spec types.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtoError {
+    /// A field without a counterpart in the messages of this module, e.g. `http[0].retries`.
+    Unsupported(String),
+
+    /// A value the target cannot hold, e.g. a negative port or an unknown enum value.
+    Invalid { path: String, message: String },
+}
+
+impl fmt::Display for ProtoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoError::Unsupported(path) => write!(f, "{}: not supported in protobuf", path),
+            ProtoError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for ProtoError {}
+
+fn field(path: &str, name: &str) -> String {
+    match path {
+        "" => name.to_string(),
+        _ => format!("{}.{}", path, name),
+    }
+}
+
+fn invalid(path: &str, message: impl fmt::Display) -> ProtoError {
+    ProtoError::Invalid {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Fails on the first of `fields` that is set, or on the first key of `extra`.
+fn unsupported(
+    path: &str,
+    fields: &[(&str, bool)],
+    extra: &Map<String, Value>,
+) -> Result<(), ProtoError> {
+    let set = fields
+        .iter()
+        .find(|(_, set)| *set)
+        .map(|(name, _)| *name)
+        .or_else(|| extra.keys().next().map(String::as_str));
+    match set {
+        Some(name) => Err(ProtoError::Unsupported(field(path, name))),
+        None => Ok(()),
+    }
+}
+
+/// Converts the items of `items`, with their index in the path.
+fn each<T, U>(
+    path: &str,
+    items: impl IntoIterator<Item = T>,
+    convert: impl Fn(&str, T) -> Result<U, ProtoError>,
+) -> Result<Vec<U>, ProtoError> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| convert(&format!("{}[{}]", path, i), item))
+        .collect()
+}
+
+fn to_hash_map<V: Clone, U>(
+    map: &Option<Map<String, V>>,
+    convert: impl Fn(&V) -> U,
+) -> HashMap<String, U> {
+    map.iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), convert(value)))
+        .collect()
+}
+
+fn from_hash_map<V, U>(
+    path: &str,
+    map: HashMap<String, V>,
+    convert: impl Fn(&str, V) -> Result<U, ProtoError>,
+) -> Result<Option<Map<String, U>>, ProtoError> {
+    let mut keys: Vec<String> = map.keys().cloned().collect();
+    keys.sort();
+    let mut map = map;
+    let converted: Map<String, U> = keys
+        .into_iter()
+        .map(|key| {
+            let value = map.remove(&key).expect("key of the map");
+            convert(&field(path, &key), value).map(|value| (key, value))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Some(converted).filter(|map| !map.is_empty()))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+fn non_empty_list<T>(values: Vec<T>) -> Option<Vec<T>> {
+    Some(values).filter(|values| !values.is_empty())
+}
+
+fn to_duration(duration: &IstioDuration) -> google::protobuf::Duration {
+    google::protobuf::Duration {
+        seconds: duration.as_secs() as i64,
+        nanos: duration.subsec_nanos() as i32,
+    }
+}
+
+fn from_duration(
+    path: &str,
+    duration: google::protobuf::Duration,
+) -> Result<IstioDuration, ProtoError> {
+    match (
+        u64::try_from(duration.seconds),
+        u32::try_from(duration.nanos),
+    ) {
+        (Ok(seconds), Ok(nanos)) if nanos < 1_000_000_000 => {
+            Ok(IstioDuration(std::time::Duration::new(seconds, nanos)))
+        }
+        _ => Err(invalid(path, "negative or malformed duration")),
+    }
+}
+
+fn to_port(path: &str, port: Option<i32>) -> Result<u32, ProtoError> {
+    port.map_or(Ok(0), |port| {
+        u32::try_from(port).map_err(|_| invalid(path, format!("invalid port {}", port)))
+    })
+}
+
+fn from_port(path: &str, port: u32) -> Result<Option<i32>, ProtoError> {
+    match port {
+        0 => Ok(None),
+        port => i32::try_from(port)
+            .map(Some)
+            .map_err(|_| invalid(path, format!("invalid port {}", port))),
+    }
+}
+
+fn to_weight(weight: Option<Weight>) -> i32 {
+    weight.map_or(0, |weight| weight.get() as i32)
+}
+
+fn from_weight(path: &str, weight: i32) -> Result<Option<Weight>, ProtoError> {
+    match weight {
+        0 => Ok(None),
+        weight => Weight::try_from(i64::from(weight))
+            .map(Some)
+            .map_err(|error| invalid(path, error)),
+    }
+}
+
+fn to_export_to(export_to: &Option<Vec<ExportTo>>) -> Vec<String> {
+    export_to
+        .iter()
+        .flatten()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn from_export_to(export_to: Vec<String>) -> Option<Vec<ExportTo>> {
+    non_empty_list(export_to.into_iter().map(ExportTo::from).collect())
+}
+
+fn to_string_match(string_match: &StringMatch) -> proto::StringMatch {
+    use proto::string_match::MatchType;
+    proto::StringMatch {
+        match_type: Some(match string_match {
+            StringMatch::Exact(value) => MatchType::Exact(value.clone()),
+            StringMatch::Prefix(value) => MatchType::Prefix(value.clone()),
+            StringMatch::Regex(value) => MatchType::Regex(value.clone()),
+        }),
+    }
+}
+
+fn from_string_match(
+    path: &str,
+    string_match: proto::StringMatch,
+) -> Result<StringMatch, ProtoError> {
+    use proto::string_match::MatchType;
+    match string_match.match_type {
+        Some(MatchType::Exact(value)) => Ok(StringMatch::Exact(value)),
+        Some(MatchType::Prefix(value)) => Ok(StringMatch::Prefix(value)),
+        Some(MatchType::Regex(value)) => Ok(StringMatch::Regex(value)),
+        None => Err(invalid(path, "one of exact, prefix or regex is required")),
+    }
+}
+
+fn from_string_matches(
+    path: &str,
+    map: HashMap<String, proto::StringMatch>,
+) -> Result<Option<Map<String, StringMatch>>, ProtoError> {
+    from_hash_map(path, map, from_string_match)
+}
+
+fn from_labels(labels: HashMap<String, String>) -> Option<Map<String, String>> {
+    Some(labels.into_iter().collect::<Map<String, String>>()).filter(|labels| !labels.is_empty())
+}
+
+impl TryFrom<&VirtualServiceSpec> for proto::VirtualService {
+    type Error = ProtoError;
+
+    fn try_from(spec: &VirtualServiceSpec) -> Result<Self, Self::Error> {
+        unsupported("", &[], &spec.extra)?;
+        Ok(proto::VirtualService {
+            hosts: spec.hosts.clone().unwrap_or_default(),
+            gateways: spec.gateways.clone().unwrap_or_default(),
+            http: each("http", spec.http.iter().flatten(), to_http_route)?,
+            tls: each("tls", spec.tls.iter().flatten(), to_tls_route)?,
+            tcp: each("tcp", spec.tcp.iter().flatten(), to_tcp_route)?,
+            export_to: to_export_to(&spec.export_to),
+        })
+    }
+}
+
+impl TryFrom<proto::VirtualService> for VirtualServiceSpec {
+    type Error = ProtoError;
+
+    fn try_from(message: proto::VirtualService) -> Result<Self, Self::Error> {
+        Ok(VirtualServiceSpec {
+            hosts: non_empty_list(message.hosts),
+            gateways: non_empty_list(message.gateways),
+            http: non_empty_list(each("http", message.http, from_http_route)?),
+            tls: non_empty_list(each("tls", message.tls, from_tls_route)?),
+            tcp: non_empty_list(each("tcp", message.tcp, from_tcp_route)?),
+            export_to: from_export_to(message.export_to),
+            extra: Map::new(),
+        })
+    }
+}
+
+fn to_http_route(path: &str, route: &HttpRoute) -> Result<proto::HttpRoute, ProtoError> {
+    unsupported(
+        path,
+        &[
+            ("redirect", route.redirect.is_some()),
+            ("directResponse", route.direct_response.is_some()),
+            ("rewrite", route.rewrite.is_some()),
+            ("retries", route.retries.is_some()),
+            ("fault", route.fault.is_some()),
+            ("mirror", route.mirror.is_some()),
+            ("mirrorPercentage", route.mirror_percentage.is_some()),
+            ("mirrors", route.mirrors.is_some()),
+            ("corsPolicy", route.cors_policy.is_some()),
+            ("headers", route.headers.is_some()),
+            ("mirrorPercent", route.mirror_percent.is_some()),
+        ],
+        &route.extra,
+    )?;
+    Ok(proto::HttpRoute {
+        name: route.name.clone().unwrap_or_default(),
+        r#match: each(
+            &field(path, "match"),
+            route.r#match.iter().flatten(),
+            to_http_match,
+        )?,
+        route: each(
+            &field(path, "route"),
+            route.route.iter().flatten(),
+            to_http_route_destination,
+        )?,
+        delegate: route.delegate.as_ref().map(|delegate| proto::Delegate {
+            name: delegate.name.clone().unwrap_or_default(),
+            namespace: delegate.namespace.clone().unwrap_or_default(),
+        }),
+        timeout: route.timeout.as_ref().map(to_duration),
+    })
+}
+
+fn from_http_route(path: &str, route: proto::HttpRoute) -> Result<HttpRoute, ProtoError> {
+    Ok(HttpRoute {
+        name: non_empty(route.name),
+        r#match: non_empty_list(each(&field(path, "match"), route.r#match, from_http_match)?),
+        route: non_empty_list(each(
+            &field(path, "route"),
+            route.route,
+            from_http_route_destination,
+        )?),
+        delegate: route.delegate.map(|delegate| Delegate {
+            name: non_empty(delegate.name),
+            namespace: non_empty(delegate.namespace),
+        }),
+        timeout: route
+            .timeout
+            .map(|timeout| from_duration(&field(path, "timeout"), timeout))
+            .transpose()?,
+        ..HttpRoute::default()
+    })
+}
+
+fn to_http_match(path: &str, m: &HttpMatchRequest) -> Result<proto::HttpMatchRequest, ProtoError> {
+    unsupported(path, &[], &m.extra)?;
+    Ok(proto::HttpMatchRequest {
+        name: m.name.clone().unwrap_or_default(),
+        uri: m.uri.as_ref().map(to_string_match),
+        scheme: m.scheme.as_ref().map(to_string_match),
+        method: m.method.as_ref().map(to_string_match),
+        authority: m.authority.as_ref().map(to_string_match),
+        headers: to_hash_map(&m.headers, to_string_match),
+        port: to_port(&field(path, "port"), m.port)?,
+        source_labels: to_hash_map(&m.source_labels, Clone::clone),
+        gateways: m.gateways.clone().unwrap_or_default(),
+        query_params: to_hash_map(&m.query_params, to_string_match),
+        ignore_uri_case: m.ignore_uri_case.unwrap_or_default(),
+        without_headers: to_hash_map(&m.without_headers, to_string_match),
+        source_namespace: m.source_namespace.clone().unwrap_or_default(),
+    })
+}
+
+fn from_http_match(path: &str, m: proto::HttpMatchRequest) -> Result<HttpMatchRequest, ProtoError> {
+    let string_match = |name: &str, value: Option<proto::StringMatch>| {
+        value
+            .map(|value| from_string_match(&field(path, name), value))
+            .transpose()
+    };
+    Ok(HttpMatchRequest {
+        name: non_empty(m.name),
+        uri: string_match("uri", m.uri)?,
+        scheme: string_match("scheme", m.scheme)?,
+        method: string_match("method", m.method)?,
+        authority: string_match("authority", m.authority)?,
+        headers: from_string_matches(&field(path, "headers"), m.headers)?,
+        port: from_port(&field(path, "port"), m.port)?,
+        source_labels: from_labels(m.source_labels),
+        gateways: non_empty_list(m.gateways),
+        query_params: from_string_matches(&field(path, "queryParams"), m.query_params)?,
+        ignore_uri_case: Some(m.ignore_uri_case).filter(|ignore| *ignore),
+        without_headers: from_string_matches(&field(path, "withoutHeaders"), m.without_headers)?,
+        source_namespace: non_empty(m.source_namespace),
+        extra: Map::new(),
+    })
+}
+
+fn to_destination(path: &str, destination: &Destination) -> Result<proto::Destination, ProtoError> {
+    let port = match &destination.port {
+        Some(PortSelector { name: Some(_), .. }) => {
+            return Err(ProtoError::Unsupported(field(path, "port.name")))
+        }
+        Some(port) => port.number.map(|number| proto::PortSelector { number }),
+        None => None,
+    };
+    Ok(proto::Destination {
+        host: destination.host.clone(),
+        subset: destination.subset.clone().unwrap_or_default(),
+        port,
+    })
+}
+
+fn from_destination(
+    path: &str,
+    destination: Option<proto::Destination>,
+) -> Result<Destination, ProtoError> {
+    let destination = destination.ok_or_else(|| invalid(path, "destination is required"))?;
+    Ok(Destination {
+        host: destination.host,
+        subset: non_empty(destination.subset),
+        port: destination
+            .port
+            .filter(|port| port.number != 0)
+            .map(|port| PortSelector::from(port.number)),
+    })
+}
+
+fn to_http_route_destination(
+    path: &str,
+    route: &HttpRouteDestination,
+) -> Result<proto::HttpRouteDestination, ProtoError> {
+    unsupported(path, &[("headers", route.headers.is_some())], &route.extra)?;
+    Ok(proto::HttpRouteDestination {
+        destination: Some(to_destination(
+            &field(path, "destination"),
+            &route.destination,
+        )?),
+        weight: to_weight(route.weight),
+    })
+}
+
+fn from_http_route_destination(
+    path: &str,
+    route: proto::HttpRouteDestination,
+) -> Result<HttpRouteDestination, ProtoError> {
+    Ok(HttpRouteDestination {
+        destination: from_destination(&field(path, "destination"), route.destination)?,
+        weight: from_weight(&field(path, "weight"), route.weight)?,
+        headers: None,
+        extra: Map::new(),
+    })
+}
+
+fn to_route_destination(
+    path: &str,
+    route: &RouteDestination,
+) -> Result<proto::RouteDestination, ProtoError> {
+    Ok(proto::RouteDestination {
+        destination: Some(to_destination(
+            &field(path, "destination"),
+            &route.destination,
+        )?),
+        weight: to_weight(route.weight),
+    })
+}
+
+fn from_route_destination(
+    path: &str,
+    route: proto::RouteDestination,
+) -> Result<RouteDestination, ProtoError> {
+    Ok(RouteDestination {
+        destination: from_destination(&field(path, "destination"), route.destination)?,
+        weight: from_weight(&field(path, "weight"), route.weight)?,
+    })
+}
+
+fn to_tls_route(path: &str, route: &TlsRoute) -> Result<proto::TlsRoute, ProtoError> {
+    unsupported(path, &[], &route.extra)?;
+    Ok(proto::TlsRoute {
+        r#match: each(&field(path, "match"), &route.r#match, |_, m| {
+            Ok(proto::TlsMatchAttributes {
+                sni_hosts: m.sni_hosts.clone(),
+                destination_subnets: m.destination_subnets.clone().unwrap_or_default(),
+                port: m.port.unwrap_or_default(),
+                source_labels: to_hash_map(&m.source_labels, Clone::clone),
+                gateways: m.gateways.clone().unwrap_or_default(),
+                source_namespace: m.source_namespace.clone().unwrap_or_default(),
+            })
+        })?,
+        route: each(
+            &field(path, "route"),
+            route.route.iter().flatten(),
+            to_route_destination,
+        )?,
+    })
+}
+
+fn from_tls_route(path: &str, route: proto::TlsRoute) -> Result<TlsRoute, ProtoError> {
+    Ok(TlsRoute {
+        r#match: route
+            .r#match
+            .into_iter()
+            .map(|m| TlsMatchAttributes {
+                sni_hosts: m.sni_hosts,
+                destination_subnets: non_empty_list(m.destination_subnets),
+                port: Some(m.port).filter(|port| *port != 0),
+                source_labels: from_labels(m.source_labels),
+                gateways: non_empty_list(m.gateways),
+                source_namespace: non_empty(m.source_namespace),
+            })
+            .collect(),
+        route: non_empty_list(each(
+            &field(path, "route"),
+            route.route,
+            from_route_destination,
+        )?),
+        extra: Map::new(),
+    })
+}
+
+fn to_tcp_route(path: &str, route: &TcpRoute) -> Result<proto::TcpRoute, ProtoError> {
+    unsupported(path, &[], &route.extra)?;
+    Ok(proto::TcpRoute {
+        r#match: each(
+            &field(path, "match"),
+            route.r#match.iter().flatten(),
+            |path, m| {
+                Ok(proto::L4MatchAttributes {
+                    destination_subnets: m.destination_subnets.clone().unwrap_or_default(),
+                    port: to_port(&field(path, "port"), m.port)?,
+                    source_subnet: m.source_subnet.clone().unwrap_or_default(),
+                    source_labels: to_hash_map(&m.source_labels, Clone::clone),
+                    gateways: m.gateways.clone().unwrap_or_default(),
+                    source_namespace: m.source_namespace.clone().unwrap_or_default(),
+                })
+            },
+        )?,
+        route: each(
+            &field(path, "route"),
+            route.route.iter().flatten(),
+            to_route_destination,
+        )?,
+    })
+}
+
+fn from_tcp_route(path: &str, route: proto::TcpRoute) -> Result<TcpRoute, ProtoError> {
+    Ok(TcpRoute {
+        r#match: non_empty_list(each(&field(path, "match"), route.r#match, |path, m| {
+            Ok(L4MatchAttributes {
+                destination_subnets: non_empty_list(m.destination_subnets),
+                port: from_port(&field(path, "port"), m.port)?,
+                source_subnet: non_empty(m.source_subnet),
+                source_labels: from_labels(m.source_labels),
+                gateways: non_empty_list(m.gateways),
+                source_namespace: non_empty(m.source_namespace),
+            })
+        })?),
+        route: non_empty_list(each(
+            &field(path, "route"),
+            route.route,
+            from_route_destination,
+        )?),
+        extra: Map::new(),
+    })
+}
+
+impl TryFrom<&DestinationRuleSpec> for proto::DestinationRule {
+    type Error = ProtoError;
+
+    fn try_from(spec: &DestinationRuleSpec) -> Result<Self, Self::Error> {
+        unsupported("", &[], &spec.extra)?;
+        Ok(proto::DestinationRule {
+            host: spec.host.clone(),
+            traffic_policy: spec
+                .traffic_policy
+                .as_ref()
+                .map(|policy| to_traffic_policy("trafficPolicy", policy))
+                .transpose()?,
+            subsets: each("subsets", spec.subsets.iter().flatten(), to_subset)?,
+            export_to: to_export_to(&spec.export_to),
+            workload_selector: spec.workload_selector.as_ref().map(|selector| {
+                r#type::v1beta1::WorkloadSelector {
+                    match_labels: to_hash_map(&selector.match_labels, Clone::clone),
+                }
+            }),
+        })
+    }
+}
+
+impl TryFrom<proto::DestinationRule> for DestinationRuleSpec {
+    type Error = ProtoError;
+
+    fn try_from(message: proto::DestinationRule) -> Result<Self, Self::Error> {
+        Ok(DestinationRuleSpec {
+            host: message.host,
+            traffic_policy: message
+                .traffic_policy
+                .map(|policy| from_traffic_policy("trafficPolicy", policy))
+                .transpose()?,
+            subsets: non_empty_list(each("subsets", message.subsets, from_subset)?),
+            export_to: from_export_to(message.export_to),
+            workload_selector: message.workload_selector.map(|selector| WorkloadSelector {
+                match_labels: from_labels(selector.match_labels),
+            }),
+            extra: Map::new(),
+        })
+    }
+}
+
+fn to_subset(path: &str, subset: &Subset) -> Result<proto::Subset, ProtoError> {
+    unsupported(path, &[], &subset.extra)?;
+    Ok(proto::Subset {
+        name: subset.name.clone(),
+        labels: to_hash_map(&subset.labels, Clone::clone),
+        traffic_policy: subset
+            .traffic_policy
+            .as_ref()
+            .map(|policy| to_traffic_policy(&field(path, "trafficPolicy"), policy))
+            .transpose()?,
+    })
+}
+
+fn from_subset(path: &str, subset: proto::Subset) -> Result<Subset, ProtoError> {
+    Ok(Subset {
+        name: subset.name,
+        labels: from_labels(subset.labels),
+        traffic_policy: subset
+            .traffic_policy
+            .map(|policy| from_traffic_policy(&field(path, "trafficPolicy"), policy))
+            .transpose()?,
+        extra: Map::new(),
+    })
+}
+
+fn to_traffic_policy(
+    path: &str,
+    policy: &TrafficPolicy,
+) -> Result<proto::TrafficPolicy, ProtoError> {
+    unsupported(
+        path,
+        &[
+            ("connectionPool", policy.connection_pool.is_some()),
+            ("tls", policy.tls.is_some()),
+            ("portLevelSettings", policy.port_level_settings.is_some()),
+            ("tunnel", policy.tunnel.is_some()),
+            ("proxyProtocol", policy.proxy_protocol.is_some()),
+        ],
+        &policy.extra,
+    )?;
+    Ok(proto::TrafficPolicy {
+        load_balancer: policy
+            .load_balancer
+            .as_ref()
+            .map(|load_balancer| to_load_balancer(&field(path, "loadBalancer"), load_balancer))
+            .transpose()?,
+        outlier_detection: policy.outlier_detection.as_ref().map(to_outlier_detection),
+    })
+}
+
+fn from_traffic_policy(
+    path: &str,
+    policy: proto::TrafficPolicy,
+) -> Result<TrafficPolicy, ProtoError> {
+    Ok(TrafficPolicy {
+        load_balancer: policy
+            .load_balancer
+            .map(|load_balancer| from_load_balancer(&field(path, "loadBalancer"), load_balancer))
+            .transpose()?,
+        outlier_detection: policy
+            .outlier_detection
+            .map(|outlier| from_outlier_detection(&field(path, "outlierDetection"), outlier))
+            .transpose()?,
+        ..TrafficPolicy::default()
+    })
+}
+
+fn to_load_balancer(
+    path: &str,
+    load_balancer: &LoadBalancerSettings,
+) -> Result<proto::LoadBalancerSettings, ProtoError> {
+    use proto::load_balancer_settings::LbPolicy as ProtoLbPolicy;
+    unsupported(
+        path,
+        &[(
+            "localityLbSetting",
+            load_balancer.locality_lb_setting.is_some(),
+        )],
+        &Map::new(),
+    )?;
+    let lb_policy = match &load_balancer.lb_policy {
+        Some(LbPolicy::Simple(simple)) => Some(ProtoLbPolicy::Simple(to_simple_lb(simple) as i32)),
+        Some(LbPolicy::ConsistentHash(consistent_hash)) => Some(ProtoLbPolicy::ConsistentHash(
+            to_consistent_hash(consistent_hash),
+        )),
+        None => None,
+    };
+    Ok(proto::LoadBalancerSettings {
+        lb_policy,
+        warmup_duration_secs: load_balancer.warmup_duration_secs.as_ref().map(to_duration),
+    })
+}
+
+fn from_load_balancer(
+    path: &str,
+    load_balancer: proto::LoadBalancerSettings,
+) -> Result<LoadBalancerSettings, ProtoError> {
+    use proto::load_balancer_settings::LbPolicy as ProtoLbPolicy;
+    let lb_policy = match load_balancer.lb_policy {
+        Some(ProtoLbPolicy::Simple(simple)) => {
+            let simple = SimpleLb::try_from(simple).map_err(|_| {
+                invalid(&field(path, "simple"), format!("unknown value {}", simple))
+            })?;
+            Some(LbPolicy::Simple(from_simple_lb(simple)))
+        }
+        Some(ProtoLbPolicy::ConsistentHash(consistent_hash)) => Some(LbPolicy::ConsistentHash(
+            from_consistent_hash(&field(path, "consistentHash"), consistent_hash)?,
+        )),
+        None => None,
+    };
+    Ok(LoadBalancerSettings {
+        lb_policy,
+        locality_lb_setting: None,
+        warmup_duration_secs: load_balancer
+            .warmup_duration_secs
+            .map(|warmup| from_duration(&field(path, "warmupDurationSecs"), warmup))
+            .transpose()?,
+    })
+}
+
+fn to_simple_lb(simple: &SimpleLB) -> SimpleLb {
+    match simple {
+        SimpleLB::UNSPECIFIED => SimpleLb::Unspecified,
+        SimpleLB::ROUND_ROBIN => SimpleLb::RoundRobin,
+        SimpleLB::LEAST_REQUEST => SimpleLb::LeastRequest,
+        SimpleLB::LEAST_CONN => SimpleLb::LeastConn,
+        SimpleLB::RANDOM => SimpleLb::Random,
+        SimpleLB::PASSTHROUGH => SimpleLb::Passthrough,
+    }
+}
+
+fn from_simple_lb(simple: SimpleLb) -> SimpleLB {
+    match simple {
+        SimpleLb::Unspecified => SimpleLB::UNSPECIFIED,
+        SimpleLb::RoundRobin => SimpleLB::ROUND_ROBIN,
+        SimpleLb::LeastRequest => SimpleLB::LEAST_REQUEST,
+        SimpleLb::LeastConn => SimpleLB::LEAST_CONN,
+        SimpleLb::Random => SimpleLB::RANDOM,
+        SimpleLb::Passthrough => SimpleLB::PASSTHROUGH,
+    }
+}
+
+fn to_consistent_hash(
+    consistent_hash: &ConsistentHashLB,
+) -> proto::load_balancer_settings::ConsistentHashLb {
+    let hash_key = match &consistent_hash.hash_key {
+        HashKey::HttpHeaderName(name) => proto_hash::HashKey::HttpHeaderName(name.clone()),
+        HashKey::HttpCookie(cookie) => proto_hash::HashKey::HttpCookie(proto_hash::HttpCookie {
+            name: cookie.name.clone(),
+            path: cookie.path.clone().unwrap_or_default(),
+            ttl: Some(to_duration(&cookie.ttl)),
+        }),
+        HashKey::UseSourceIp(use_source_ip) => proto_hash::HashKey::UseSourceIp(*use_source_ip),
+        HashKey::HttpQueryParameterName(name) => {
+            proto_hash::HashKey::HttpQueryParameterName(name.clone())
+        }
+    };
+    let hash_algorithm = consistent_hash
+        .hash_algorithm
+        .as_ref()
+        .map(|algorithm| match algorithm {
+            HashAlgorithm::RingHash(ring_hash) => {
+                proto_hash::HashAlgorithm::RingHash(proto_hash::RingHash {
+                    minimum_ring_size: ring_hash.minimum_ring_size.unwrap_or_default(),
+                })
+            }
+            HashAlgorithm::Maglev(maglev) => {
+                proto_hash::HashAlgorithm::Maglev(proto_hash::MagLev {
+                    table_size: maglev.table_size.unwrap_or_default(),
+                })
+            }
+        });
+    proto::load_balancer_settings::ConsistentHashLb {
+        hash_key: Some(hash_key),
+        hash_algorithm,
+        minimum_ring_size: consistent_hash.minimum_ring_size.unwrap_or_default(),
+    }
+}
+
+fn from_consistent_hash(
+    path: &str,
+    consistent_hash: proto::load_balancer_settings::ConsistentHashLb,
+) -> Result<ConsistentHashLB, ProtoError> {
+    let hash_key = match consistent_hash.hash_key {
+        Some(proto_hash::HashKey::HttpHeaderName(name)) => HashKey::HttpHeaderName(name),
+        Some(proto_hash::HashKey::HttpCookie(cookie)) => {
+            let ttl_path = field(path, "httpCookie.ttl");
+            let ttl = cookie
+                .ttl
+                .ok_or_else(|| invalid(&ttl_path, "ttl is required"))?;
+            HashKey::HttpCookie(HTTPCookie {
+                name: cookie.name,
+                path: non_empty(cookie.path),
+                ttl: from_duration(&ttl_path, ttl)?,
+            })
+        }
+        Some(proto_hash::HashKey::UseSourceIp(use_source_ip)) => {
+            HashKey::UseSourceIp(use_source_ip)
+        }
+        Some(proto_hash::HashKey::HttpQueryParameterName(name)) => {
+            HashKey::HttpQueryParameterName(name)
+        }
+        None => return Err(invalid(
+            path,
+            "one of httpHeaderName, httpCookie, useSourceIp or httpQueryParameterName is required",
+        )),
+    };
+    let positive = |value: u64| Some(value).filter(|value| *value != 0);
+    Ok(ConsistentHashLB {
+        hash_key,
+        hash_algorithm: consistent_hash
+            .hash_algorithm
+            .map(|algorithm| match algorithm {
+                proto_hash::HashAlgorithm::RingHash(ring_hash) => {
+                    HashAlgorithm::RingHash(RingHash {
+                        minimum_ring_size: positive(ring_hash.minimum_ring_size),
+                    })
+                }
+                proto_hash::HashAlgorithm::Maglev(maglev) => HashAlgorithm::Maglev(MagLev {
+                    table_size: positive(maglev.table_size),
+                }),
+            }),
+        minimum_ring_size: positive(consistent_hash.minimum_ring_size),
+    })
+}
+
+fn to_outlier_detection(outlier: &OutlierDetection) -> proto::OutlierDetection {
+    let count = |value: &Option<UInt32Value>| value.as_ref().and_then(|value| value.value);
+    proto::OutlierDetection {
+        split_external_local_origin_errors: outlier
+            .split_external_local_origin_errors
+            .unwrap_or_default(),
+        consecutive_local_origin_failures: count(&outlier.consecutive_local_origin_failures),
+        consecutive_gateway_errors: count(&outlier.consecutive_gateway_errors),
+        consecutive_5xx_errors: count(&outlier.consecutive5xx_errors),
+        consecutive_errors: outlier.consecutive_errors.unwrap_or_default(),
+        interval: outlier.interval.as_ref().map(to_duration),
+        base_ejection_time: outlier.base_ejection_time.as_ref().map(to_duration),
+        max_ejection_percent: outlier.max_ejection_percent.unwrap_or_default(),
+        min_health_percent: outlier.min_health_percent.unwrap_or_default(),
+    }
+}
+
+fn from_outlier_detection(
+    path: &str,
+    outlier: proto::OutlierDetection,
+) -> Result<OutlierDetection, ProtoError> {
+    let count = |value: Option<u32>| value.map(|value| UInt32Value { value: Some(value) });
+    let duration = |name: &str, value: Option<google::protobuf::Duration>| {
+        value
+            .map(|value| from_duration(&field(path, name), value))
+            .transpose()
+    };
+    let non_zero = |value: i32| Some(value).filter(|value| *value != 0);
+    Ok(OutlierDetection {
+        split_external_local_origin_errors: Some(outlier.split_external_local_origin_errors)
+            .filter(|split| *split),
+        consecutive_local_origin_failures: count(outlier.consecutive_local_origin_failures),
+        consecutive_gateway_errors: count(outlier.consecutive_gateway_errors),
+        consecutive5xx_errors: count(outlier.consecutive_5xx_errors),
+        consecutive_errors: non_zero(outlier.consecutive_errors),
+        interval: duration("interval", outlier.interval)?,
+        base_ejection_time: duration("baseEjectionTime", outlier.base_ejection_time)?,
+        max_ejection_percent: non_zero(outlier.max_ejection_percent),
+        min_health_percent: non_zero(outlier.min_health_percent),
+    })
+}
+
+impl TryFrom<&GatewaySpec> for proto::Gateway {
+    type Error = ProtoError;
+
+    fn try_from(spec: &GatewaySpec) -> Result<Self, Self::Error> {
+        unsupported("", &[], &spec.extra)?;
+        Ok(proto::Gateway {
+            servers: each("servers", &spec.servers, to_server)?,
+            selector: spec
+                .selector
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<proto::Gateway> for GatewaySpec {
+    type Error = ProtoError;
+
+    fn try_from(message: proto::Gateway) -> Result<Self, Self::Error> {
+        Ok(GatewaySpec {
+            servers: each("servers", message.servers, from_server)?,
+            selector: message.selector.into_iter().collect(),
+            extra: Map::new(),
+        })
+    }
+}
+
+fn to_server(path: &str, server: &Server) -> Result<proto::Server, ProtoError> {
+    let port_path = field(path, "port.number");
+    let number = u32::try_from(server.port.number)
+        .map_err(|_| invalid(&port_path, format!("invalid port {}", server.port.number)))?;
+    Ok(proto::Server {
+        port: Some(proto::Port {
+            number,
+            protocol: server.port.protocol.clone(),
+            name: server.port.name.clone(),
+            target_port: server.port.target_port.unwrap_or_default(),
+        }),
+        bind: server.bind.clone().unwrap_or_default(),
+        hosts: server.hosts.clone(),
+        tls: server.tls.as_ref().map(to_server_tls),
+        name: server.name.clone().unwrap_or_default(),
+    })
+}
+
+fn from_server(path: &str, server: proto::Server) -> Result<Server, ProtoError> {
+    let port = server
+        .port
+        .ok_or_else(|| invalid(&field(path, "port"), "port is required"))?;
+    let number = i32::try_from(port.number).map_err(|_| {
+        invalid(
+            &field(path, "port.number"),
+            format!("invalid port {}", port.number),
+        )
+    })?;
+    Ok(Server {
+        port: gateway::Port {
+            number,
+            protocol: port.protocol,
+            name: port.name,
+            target_port: Some(port.target_port).filter(|port| *port != 0),
+        },
+        bind: non_empty(server.bind),
+        hosts: server.hosts,
+        tls: server
+            .tls
+            .map(|tls| from_server_tls(&field(path, "tls"), tls))
+            .transpose()?,
+        name: non_empty(server.name),
+    })
+}
+
+fn to_server_tls(tls: &ServerTLSSettings) -> proto::ServerTlsSettings {
+    let mode = tls
+        .mode
+        .as_ref()
+        .map_or(TlSmode::Passthrough, |mode| match mode {
+            TLSmode::PASSTHROUGH => TlSmode::Passthrough,
+            TLSmode::SIMPLE => TlSmode::Simple,
+            TLSmode::MUTUAL => TlSmode::Mutual,
+            TLSmode::AUTO_PASSTHROUGH => TlSmode::AutoPassthrough,
+            TLSmode::ISTIO_MUTUAL => TlSmode::IstioMutual,
+        });
+    let protocol = |protocol: &Option<TLSProtocol>| {
+        protocol
+            .as_ref()
+            .map_or(TlsProtocol::TlsAuto, |protocol| match protocol {
+                TLSProtocol::TLS_AUTO => TlsProtocol::TlsAuto,
+                TLSProtocol::TLSV1_0 => TlsProtocol::TlSv10,
+                TLSProtocol::TLSV1_1 => TlsProtocol::TlSv11,
+                TLSProtocol::TLSV1_2 => TlsProtocol::TlSv12,
+                TLSProtocol::TLSV1_3 => TlsProtocol::TlSv13,
+            }) as i32
+    };
+    proto::ServerTlsSettings {
+        https_redirect: tls.https_redirect.unwrap_or_default(),
+        mode: mode as i32,
+        server_certificate: tls.server_certificate.clone().unwrap_or_default(),
+        private_key: tls.private_key.clone().unwrap_or_default(),
+        ca_certificates: tls.ca_certificates.clone().unwrap_or_default(),
+        credential_name: tls.credential_name.clone().unwrap_or_default(),
+        subject_alt_names: tls.subject_alt_names.clone().unwrap_or_default(),
+        verify_certificate_spki: tls.verify_certificate_spki.clone().unwrap_or_default(),
+        verify_certificate_hash: tls.verify_certificate_hash.clone().unwrap_or_default(),
+        min_protocol_version: protocol(&tls.min_protocol_version),
+        max_protocol_version: protocol(&tls.max_protocol_version),
+        cipher_suites: tls.cipher_suites.clone().unwrap_or_default(),
+    }
+}
+
+/// The default `PASSTHROUGH` mode and `TLS_AUTO` versions are read as unset.
+fn from_server_tls(
+    path: &str,
+    tls: proto::ServerTlsSettings,
+) -> Result<ServerTLSSettings, ProtoError> {
+    let mode = match TlSmode::try_from(tls.mode) {
+        Ok(TlSmode::Passthrough) => None,
+        Ok(TlSmode::Simple) => Some(TLSmode::SIMPLE),
+        Ok(TlSmode::Mutual) => Some(TLSmode::MUTUAL),
+        Ok(TlSmode::AutoPassthrough) => Some(TLSmode::AUTO_PASSTHROUGH),
+        Ok(TlSmode::IstioMutual) => Some(TLSmode::ISTIO_MUTUAL),
+        Ok(TlSmode::OptionalMutual) | Err(_) => {
+            return Err(invalid(
+                &field(path, "mode"),
+                format!("unsupported TLS mode {}", tls.mode),
+            ))
+        }
+    };
+    let protocol = |name: &str, value: i32| match TlsProtocol::try_from(value) {
+        Ok(TlsProtocol::TlsAuto) => Ok(None),
+        Ok(TlsProtocol::TlSv10) => Ok(Some(TLSProtocol::TLSV1_0)),
+        Ok(TlsProtocol::TlSv11) => Ok(Some(TLSProtocol::TLSV1_1)),
+        Ok(TlsProtocol::TlSv12) => Ok(Some(TLSProtocol::TLSV1_2)),
+        Ok(TlsProtocol::TlSv13) => Ok(Some(TLSProtocol::TLSV1_3)),
+        Err(_) => Err(invalid(
+            &field(path, name),
+            format!("unknown TLS version {}", value),
+        )),
+    };
+    Ok(ServerTLSSettings {
+        https_redirect: Some(tls.https_redirect).filter(|redirect| *redirect),
+        mode,
+        server_certificate: non_empty(tls.server_certificate),
+        private_key: non_empty(tls.private_key),
+        ca_certificates: non_empty(tls.ca_certificates),
+        credential_name: non_empty(tls.credential_name),
+        subject_alt_names: non_empty_list(tls.subject_alt_names),
+        verify_certificate_spki: non_empty_list(tls.verify_certificate_spki),
+        verify_certificate_hash: non_empty_list(tls.verify_certificate_hash),
+        min_protocol_version: protocol("minProtocolVersion", tls.min_protocol_version)?,
+        max_protocol_version: protocol("maxProtocolVersion", tls.max_protocol_version)?,
+        cipher_suites: non_empty_list(tls.cipher_suites),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+    use prost::Message;
+
+    fn round_trip<S, M>(spec: &S) -> S
+    where
+        M: Message + Default + for<'a> TryFrom<&'a S, Error = ProtoError>,
+        S: TryFrom<M, Error = ProtoError>,
+    {
+        let message = M::try_from(spec).unwrap();
+        let decoded = M::decode(message.encode_to_vec().as_slice()).unwrap();
+        S::try_from(decoded).unwrap()
+    }
+
+    #[test]
+    fn virtual_service_round_trips() {
+        let spec: VirtualServiceSpec = serde_json::from_value(json!({
+            "hosts": ["reviews"],
+            "gateways": ["mesh"],
+            "exportTo": ["."],
+            "http": [{
+                "name": "canary",
+                "match": [{
+                    "uri": {"prefix": "/api"},
+                    "headers": {"x-user": {"exact": "jason"}},
+                    "port": 8080,
+                    "ignoreUriCase": true
+                }],
+                "route": [
+                    {"destination": {"host": "reviews", "subset": "v1", "port": {"number": 9080}}, "weight": 90},
+                    {"destination": {"host": "reviews", "subset": "v2"}, "weight": 10}
+                ],
+                "timeout": "1.5s"
+            }],
+            "tls": [{
+                "match": [{"sniHosts": ["reviews.example.com"], "port": 443}],
+                "route": [{"destination": {"host": "reviews"}}]
+            }],
+            "tcp": [{
+                "match": [{"port": 27017, "sourceLabels": {"app": "db"}}],
+                "route": [{"destination": {"host": "mongo"}}]
+            }]
+        }))
+        .unwrap();
+
+        let decoded = round_trip::<_, proto::VirtualService>(&spec);
+
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_rule_round_trips() {
+        let spec: DestinationRuleSpec = serde_json::from_value(json!({
+            "host": "reviews",
+            "trafficPolicy": {
+                "loadBalancer": {"simple": "LEAST_REQUEST", "warmupDurationSecs": "30s"},
+                "outlierDetection": {"consecutive5xxErrors": 7, "interval": "5m", "baseEjectionTime": "15m"}
+            },
+            "subsets": [{
+                "name": "v1",
+                "labels": {"version": "v1"},
+                "trafficPolicy": {
+                    "loadBalancer": {"consistentHash": {"httpCookie": {"name": "user", "ttl": "0s"}, "maglev": {"tableSize": 65537}}}
+                }
+            }],
+            "workloadSelector": {"matchLabels": {"app": "productpage"}}
+        }))
+        .unwrap();
+
+        let decoded = round_trip::<_, proto::DestinationRule>(&spec);
+
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn gateway_round_trips() {
+        let spec: GatewaySpec = serde_json::from_value(json!({
+            "selector": {"istio": "ingressgateway"},
+            "servers": [{
+                "port": {"number": 443, "name": "https", "protocol": "HTTPS"},
+                "hosts": ["bookinfo.example.com"],
+                "tls": {"mode": "SIMPLE", "credentialName": "bookinfo", "minProtocolVersion": "TLSV1_2"}
+            }]
+        }))
+        .unwrap();
+
+        let decoded = round_trip::<_, proto::Gateway>(&spec);
+
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn encodes_with_the_istio_field_numbers() {
+        let message = proto::Destination {
+            host: "a".into(),
+            subset: "b".into(),
+            port: Some(proto::PortSelector { number: 80 }),
+        };
+
+        assert_eq!(
+            message.encode_to_vec(),
+            vec![0x0a, 1, b'a', 0x12, 1, b'b', 0x1a, 2, 0x08, 80]
+        );
+    }
+
+    #[test]
+    fn rejects_fields_without_a_message() {
+        let spec: VirtualServiceSpec = serde_json::from_value(json!({
+            "hosts": ["reviews"],
+            "http": [{"route": [{"destination": {"host": "reviews"}}], "retries": {"attempts": 3}}]
+        }))
+        .unwrap();
+        assert_eq!(
+            proto::VirtualService::try_from(&spec),
+            Err(ProtoError::Unsupported("http[0].retries".into()))
+        );
+
+        let spec: VirtualServiceSpec = serde_json::from_value(json!({
+            "hosts": ["reviews"],
+            "http": [{"route": [{"destination": {"host": "reviews", "port": {"name": "http"}}}]}]
+        }))
+        .unwrap();
+        assert_eq!(
+            proto::VirtualService::try_from(&spec),
+            Err(ProtoError::Unsupported(
+                "http[0].route[0].destination.port.name".into()
+            ))
+        );
+
+        let spec: DestinationRuleSpec = serde_json::from_value(json!({
+            "host": "reviews",
+            "subsets": [{"name": "v1", "trafficPolicy": {"tls": {"mode": "ISTIO_MUTUAL"}}}]
+        }))
+        .unwrap();
+        assert_eq!(
+            proto::DestinationRule::try_from(&spec),
+            Err(ProtoError::Unsupported(
+                "subsets[0].trafficPolicy.tls".into()
+            ))
+        );
+
+        let spec: DestinationRuleSpec = serde_json::from_value(json!({
+            "host": "reviews",
+            "futureField": true
+        }))
+        .unwrap();
+        assert_eq!(
+            proto::DestinationRule::try_from(&spec),
+            Err(ProtoError::Unsupported("futureField".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_messages() {
+        let message = proto::VirtualService {
+            http: vec![proto::HttpRoute {
+                r#match: vec![proto::HttpMatchRequest {
+                    uri: Some(proto::StringMatch { match_type: None }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            VirtualServiceSpec::try_from(message)
+                .unwrap_err()
+                .to_string(),
+            "http[0].match[0].uri: one of exact, prefix or regex is required"
+        );
+
+        let message = proto::DestinationRule {
+            host: "reviews".into(),
+            traffic_policy: Some(proto::TrafficPolicy {
+                load_balancer: Some(proto::LoadBalancerSettings {
+                    lb_policy: Some(proto::load_balancer_settings::LbPolicy::Simple(42)),
+                    warmup_duration_secs: None,
+                }),
+                outlier_detection: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            DestinationRuleSpec::try_from(message)
+                .unwrap_err()
+                .to_string(),
+            "trafficPolicy.loadBalancer.simple: unknown value 42"
+        );
+
+        let message = proto::HttpRouteDestination {
+            destination: Some(proto::Destination {
+                host: "reviews".into(),
+                ..Default::default()
+            }),
+            weight: -1,
+        };
+        assert!(matches!(
+            from_http_route_destination("route[0]", message),
+            Err(ProtoError::Invalid { path, .. }) if path == "route[0].weight"
+        ));
+    }
+
+    #[test]
+    fn reads_proto3_defaults_as_unset() {
+        let message = proto::VirtualService {
+            hosts: vec!["reviews".into()],
+            http: vec![proto::HttpRoute {
+                route: vec![proto::HttpRouteDestination {
+                    destination: Some(proto::Destination {
+                        host: "reviews".into(),
+                        port: Some(proto::PortSelector { number: 0 }),
+                        ..Default::default()
+                    }),
+                    weight: 0,
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let spec = VirtualServiceSpec::try_from(message).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            json!({"hosts": ["reviews"], "http": [{"route": [{"destination": {"host": "reviews"}}]}]})
+        );
+    }
+}
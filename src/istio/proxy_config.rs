@@ -0,0 +1,158 @@
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProxyConfig {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<ProxyConfigSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for ProxyConfig {
+    const API_VERSION: &'static str = "networking.istio.io/v1beta1";
+    const GROUP: &'static str = "networking.istio.io";
+    const KIND: &'static str = "ProxyConfig";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "proxyconfigs";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for ProxyConfig {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl ProxyConfig {
+    /// An empty ProxyConfig, applying to every workload of `namespace` until a selector is added.
+    pub fn new(name: &str, namespace: &str) -> Self {
+        ProxyConfig {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(ProxyConfigSpec::default()),
+            status: None,
+        }
+    }
+
+    /// Restricts the ProxyConfig to the workloads with the label.
+    pub fn with_selector(mut self, key: &str, value: &str) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.selector
+                .get_or_insert(WorkloadSelector { match_labels: None })
+                .match_labels
+                .get_or_insert_with(Map::default)
+                .insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: i32) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.concurrency = Some(concurrency);
+        }
+        self
+    }
+
+    pub fn with_environment_variable(mut self, key: &str, value: &str) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.environment_variables
+                .get_or_insert_with(Map::default)
+                .insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// Sets the image type of the proxy, e.g. `distroless`.
+    pub fn with_image_type(mut self, image_type: &str) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.image = Some(ProxyImage {
+                image_type: Some(image_type.to_string()),
+            });
+        }
+        self
+    }
+}
+
+/// # ProxyConfig
+/// `ProxyConfig` exposes proxy level configuration options. `ProxyConfig` can be configured on a
+/// per-workload basis, a per-namespace basis, or mesh-wide. `ProxyConfig` is not a required
+/// resource; there are default values in place, which are used if a `ProxyConfig` resource is not
+/// provided.
+///
+/// Values are merged from the mesh-wide `meshConfig.defaultConfig`, the ProxyConfig of the root
+/// namespace, the one of the workload's namespace and finally the ProxyConfig selecting the
+/// workload, the most specific winning.
+///
+/// ```yaml
+/// apiVersion: networking.istio.io/v1beta1
+/// kind: ProxyConfig
+/// metadata:
+///   name: per-workload-proxyconfig
+///   namespace: example
+/// spec:
+///   selector:
+///     matchLabels:
+///       app: ratings
+///   concurrency: 0
+///   environmentVariables:
+///     ISTIO_META_DNS_CAPTURE: "true"
+///   image:
+///     imageType: debug
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProxyConfigSpec {
+    // Optional. Selectors specify the set of pods/VMs on which this ProxyConfig resource should be applied. If not set, the ProxyConfig resource will be applied to all workloads in the namespace where this resource is defined.
+    // No
+    pub selector: Option<WorkloadSelector>,
+
+    // The number of worker threads to run. If unset, defaults to 2. If set to 0, this will be configured to use all cores on the machine using CPU requests and limits to choose a value, with limits taking precedence over requests.
+    // No
+    pub concurrency: Option<i32>,
+
+    // Additional environment variables for the proxy. Names starting with ISTIO_META_ will be included in the generated bootstrap configuration and sent to the XDS server.
+    // No
+    #[serde(rename = "environmentVariables")]
+    pub environment_variables: Option<Map<String, String>>,
+
+    // Specifies the details of the proxy image.
+    // No
+    pub image: Option<ProxyImage>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # ProxyImage
+/// The following values are used to construct proxy image url. The final url is
+/// `$hub/$image_name/$tag-$image_type`, e.g. `docker.io/istio/proxyv2:1.11.1` or
+/// `docker.io/istio/proxyv2:1.11.1-distroless`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProxyImage {
+    // The image type of the image. Istio publishes default, debug, and distroless images. Other values are allowed if those image types (example: centos) are published to the specified hub. supported values: default, debug, distroless.
+    // No
+    #[serde(rename = "imageType")]
+    pub image_type: Option<String>,
+}
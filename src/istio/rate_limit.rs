@@ -0,0 +1,908 @@
+// Generators for the EnvoyFilters enabling Envoy rate limiting on a workload: the local rate limit
+// filter, where every proxy enforces its own token buckets, and the global rate limit filter, where
+// proxies ask an external rate limit service (e.g. envoyproxy/ratelimit) whether a request is
+// allowed. Both filters are inserted in front of the router of the HTTP connection manager and
+// their descriptors are produced by rate limit actions merged into the virtual hosts.
+use crate::istio::envoy_filter::listener_match::{FilterChainMatch, FilterMatch, SubFilterMatch};
+use crate::istio::envoy_filter::patch::Operation;
+use crate::istio::envoy_filter::{
+    ApplyTo, EnvoyConfigObjectMatch, EnvoyConfigObjectPatch, EnvoyFilter, EnvoyFilterSpec,
+    ListenerMatch, ObjectTypes, Patch, PatchContext, RouteConfigurationMatch,
+};
+use crate::istio::google::Struct;
+use crate::istio::Map;
+use crate::istio::WorkloadSelector;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::{json, Value};
+use std::fmt;
+use std::time::Duration;
+
+pub const LOCAL_RATE_LIMIT_FILTER: &str = "envoy.filters.http.local_ratelimit";
+pub const RATE_LIMIT_FILTER: &str = "envoy.filters.http.ratelimit";
+
+const HTTP_CONNECTION_MANAGER: &str = "envoy.filters.network.http_connection_manager";
+const ROUTER: &str = "envoy.filters.http.router";
+const LOCAL_RATE_LIMIT_TYPE: &str =
+    "type.googleapis.com/envoy.extensions.filters.http.local_ratelimit.v3.LocalRateLimit";
+const RATE_LIMIT_TYPE: &str =
+    "type.googleapis.com/envoy.extensions.filters.http.ratelimit.v3.RateLimit";
+
+/// Shortest fill interval accepted by the local rate limit filter.
+const MIN_FILL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// # Workload
+/// The proxies a rate limit is applied to. The generated EnvoyFilter is named after the workload and
+/// created in its namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    pub name: String,
+    pub namespace: String,
+
+    /// Labels of the selected pods. Without labels the filter applies to every workload of the
+    /// namespace, or of the mesh in the config root namespace.
+    pub labels: Option<Map<String, String>>,
+
+    pub context: PatchContext,
+
+    /// Only rate limit traffic on this service or gateway port.
+    pub port: Option<u32>,
+}
+
+impl Workload {
+    /// Inbound traffic of the sidecars of the selected pods.
+    pub fn sidecar<K: Into<String>, V: Into<String>>(
+        name: &str,
+        namespace: &str,
+        labels: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        Workload::new(name, namespace, labels, PatchContext::SIDECAR_INBOUND)
+    }
+
+    /// Traffic entering the mesh through the selected gateway pods.
+    pub fn gateway<K: Into<String>, V: Into<String>>(
+        name: &str,
+        namespace: &str,
+        labels: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        Workload::new(name, namespace, labels, PatchContext::GATEWAY)
+    }
+
+    pub fn on_port(mut self, port: u32) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    fn new<K: Into<String>, V: Into<String>>(
+        name: &str,
+        namespace: &str,
+        labels: impl IntoIterator<Item = (K, V)>,
+        context: PatchContext,
+    ) -> Self {
+        let labels: Map<String, String> = labels
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        Workload {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            labels: Some(labels).filter(|labels| !labels.is_empty()),
+            context,
+            port: None,
+        }
+    }
+
+    fn envoy_filter(
+        &self,
+        suffix: &str,
+        config_patches: Vec<EnvoyConfigObjectPatch>,
+    ) -> EnvoyFilter {
+        EnvoyFilter {
+            metadata: ObjectMeta {
+                name: Some(format!("{}-{}", self.name, suffix)),
+                namespace: Some(self.namespace.clone()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(EnvoyFilterSpec {
+                workload_selector: self
+                    .labels
+                    .clone()
+                    .map(|labels| WorkloadSelector { labels }),
+                config_patches,
+                priority: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }
+    }
+
+    /// Inserts the HTTP filter `value` in front of the router.
+    fn http_filter(&self, value: Value) -> EnvoyConfigObjectPatch {
+        EnvoyConfigObjectPatch {
+            apply_to: ApplyTo::HTTP_FILTER,
+            r#match: EnvoyConfigObjectMatch {
+                context: Some(self.context),
+                proxy: None,
+                object_types: Some(ObjectTypes::Listener(ListenerMatch {
+                    port_number: self.port,
+                    filter_chain: Some(FilterChainMatch {
+                        name: None,
+                        sni: None,
+                        transport_protocol: None,
+                        application_protocols: None,
+                        filter: Some(FilterMatch {
+                            name: HTTP_CONNECTION_MANAGER.to_string(),
+                            sub_filter: Some(SubFilterMatch {
+                                name: ROUTER.to_string(),
+                            }),
+                        }),
+                        destination_port: None,
+                    }),
+                    name: None,
+                })),
+            },
+            patch: Patch {
+                operation: Some(Operation::INSERT_BEFORE),
+                value: Some(structure(value)),
+                filter_class: None,
+            },
+        }
+    }
+
+    /// Merges `value` into every virtual host of the workload.
+    fn virtual_hosts(&self, value: Value) -> EnvoyConfigObjectPatch {
+        EnvoyConfigObjectPatch {
+            apply_to: ApplyTo::VIRTUAL_HOST,
+            r#match: EnvoyConfigObjectMatch {
+                context: Some(self.context),
+                proxy: None,
+                object_types: Some(ObjectTypes::RouteConfiguration(RouteConfigurationMatch {
+                    port_number: self.port,
+                    port_name: None,
+                    gateway: None,
+                    vhost: None,
+                    name: None,
+                })),
+            },
+            patch: Patch {
+                operation: Some(Operation::MERGE),
+                value: Some(structure(value)),
+                filter_class: None,
+            },
+        }
+    }
+}
+
+/// # TokenBucket
+/// Allows `max_tokens` requests in a burst, refilled with `tokens_per_fill` tokens every
+/// `fill_interval`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenBucket {
+    pub max_tokens: u32,
+    pub tokens_per_fill: u32,
+    pub fill_interval: Duration,
+}
+
+impl TokenBucket {
+    /// `requests` per `interval`, without bursts above that.
+    pub fn new(requests: u32, interval: Duration) -> Self {
+        TokenBucket {
+            max_tokens: requests,
+            tokens_per_fill: requests,
+            fill_interval: interval,
+        }
+    }
+
+    fn check(&self, path: &str) -> Result<(), RateLimitError> {
+        let reason = if self.max_tokens == 0 || self.tokens_per_fill == 0 {
+            "max_tokens and tokens_per_fill must be greater than 0"
+        } else if self.fill_interval < MIN_FILL_INTERVAL {
+            "fill_interval must be at least 50ms"
+        } else {
+            return Ok(());
+        };
+        Err(RateLimitError::InvalidTokenBucket {
+            path: path.to_string(),
+            reason,
+        })
+    }
+
+    pub(crate) fn to_value(self) -> Value {
+        json!({
+            "max_tokens": self.max_tokens,
+            "tokens_per_fill": self.tokens_per_fill,
+            "fill_interval": proto_duration(self.fill_interval),
+        })
+    }
+}
+
+/// # Action
+/// Produces one entry of the descriptor of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// The value of a request header, e.g. `:path`.
+    RequestHeader {
+        header: String,
+        descriptor_key: String,
+    },
+
+    /// The client address, under the key `remote_address`.
+    RemoteAddress,
+
+    /// A constant value, under the key `generic_key` unless another key is given.
+    GenericKey {
+        descriptor_key: Option<String>,
+        value: String,
+    },
+}
+
+impl Action {
+    pub fn request_header(header: &str, descriptor_key: &str) -> Self {
+        Action::RequestHeader {
+            header: header.to_string(),
+            descriptor_key: descriptor_key.to_string(),
+        }
+    }
+
+    pub fn generic_key(value: &str) -> Self {
+        Action::GenericKey {
+            descriptor_key: None,
+            value: value.to_string(),
+        }
+    }
+
+    /// The key of the descriptor entry produced by the action.
+    pub fn descriptor_key(&self) -> &str {
+        match self {
+            Action::RequestHeader { descriptor_key, .. } => descriptor_key,
+            Action::RemoteAddress => "remote_address",
+            Action::GenericKey { descriptor_key, .. } => {
+                descriptor_key.as_deref().unwrap_or("generic_key")
+            }
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Action::RequestHeader {
+                header,
+                descriptor_key,
+            } => json!({
+                "request_headers": {
+                    "header_name": header,
+                    "descriptor_key": descriptor_key,
+                }
+            }),
+            Action::RemoteAddress => json!({ "remote_address": {} }),
+            Action::GenericKey {
+                descriptor_key,
+                value,
+            } => {
+                let mut generic_key = json!({ "descriptor_value": value });
+                if let Some(key) = descriptor_key {
+                    generic_key["descriptor_key"] = json!(key);
+                }
+                json!({ "generic_key": generic_key })
+            }
+        }
+    }
+}
+
+/// # LocalDescriptor
+/// A token bucket for the requests whose descriptor has exactly the given entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalDescriptor {
+    pub entries: Vec<(String, String)>,
+    pub bucket: TokenBucket,
+}
+
+/// # LocalRateLimit
+/// Rate limits enforced by every proxy of a workload on its own. `bucket` limits all requests of a
+/// virtual host; requests matching a descriptor are limited by the descriptor's bucket instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalRateLimit {
+    pub workload: Workload,
+    pub stat_prefix: String,
+    pub bucket: TokenBucket,
+
+    /// The actions producing the descriptor of a request, in order.
+    pub actions: Vec<Action>,
+
+    pub descriptors: Vec<LocalDescriptor>,
+}
+
+impl LocalRateLimit {
+    pub fn new(workload: Workload, bucket: TokenBucket) -> Self {
+        LocalRateLimit {
+            workload,
+            stat_prefix: "http_local_rate_limiter".to_string(),
+            bucket,
+            actions: Vec::new(),
+            descriptors: Vec::new(),
+        }
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn with_descriptor<K: Into<String>, V: Into<String>>(
+        mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        bucket: TokenBucket,
+    ) -> Self {
+        self.descriptors.push(LocalDescriptor {
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+            bucket,
+        });
+        self
+    }
+
+    /// The EnvoyFilter inserting the local rate limit filter and configuring it on the virtual
+    /// hosts of the workload.
+    pub fn envoy_filter(&self) -> Result<EnvoyFilter, RateLimitError> {
+        self.bucket.check("bucket")?;
+        if !self.descriptors.is_empty() && self.actions.is_empty() {
+            return Err(RateLimitError::MissingActions);
+        }
+        let expected: Vec<String> = self
+            .actions
+            .iter()
+            .map(|action| action.descriptor_key().to_string())
+            .collect();
+        for (i, descriptor) in self.descriptors.iter().enumerate() {
+            let path = format!("descriptors[{}]", i);
+            descriptor.bucket.check(&format!("{}.bucket", path))?;
+            let keys: Vec<String> = descriptor
+                .entries
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect();
+            if keys != expected {
+                return Err(RateLimitError::DescriptorMismatch {
+                    path,
+                    found: keys,
+                    expected,
+                });
+            }
+        }
+
+        let enabled = |runtime_key: &str| {
+            json!({
+                "runtime_key": runtime_key,
+                "default_value": { "numerator": 100, "denominator": "HUNDRED" },
+            })
+        };
+        let descriptors: Vec<Value> = self
+            .descriptors
+            .iter()
+            .map(|descriptor| {
+                let entries: Vec<Value> = descriptor
+                    .entries
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": value }))
+                    .collect();
+                json!({ "entries": entries, "token_bucket": descriptor.bucket.to_value() })
+            })
+            .collect();
+        let mut config = json!({
+            "@type": LOCAL_RATE_LIMIT_TYPE,
+            "stat_prefix": self.stat_prefix,
+            "token_bucket": self.bucket.to_value(),
+            "filter_enabled": enabled("local_rate_limit_enabled"),
+            "filter_enforced": enabled("local_rate_limit_enforced"),
+            "response_headers_to_add": [{
+                "append": false,
+                "header": { "key": "x-local-rate-limit", "value": "true" },
+            }],
+        });
+        if !descriptors.is_empty() {
+            config["descriptors"] = Value::Array(descriptors);
+        }
+        let mut virtual_host = json!({
+            "typed_per_filter_config": { LOCAL_RATE_LIMIT_FILTER: config },
+        });
+        if !self.actions.is_empty() {
+            virtual_host["rate_limits"] = json!([{ "actions": actions(&self.actions) }]);
+        }
+
+        Ok(self.workload.envoy_filter(
+            "local-ratelimit",
+            vec![
+                // Without a token bucket the filter is a no-op until the per virtual host
+                // configuration enables it.
+                self.workload.http_filter(json!({
+                    "name": LOCAL_RATE_LIMIT_FILTER,
+                    "typed_config": {
+                        "@type": LOCAL_RATE_LIMIT_TYPE,
+                        "stat_prefix": self.stat_prefix,
+                    },
+                })),
+                self.workload.virtual_hosts(virtual_host),
+            ],
+        ))
+    }
+}
+
+/// # Unit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+/// # Limit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Limit {
+    pub unit: Unit,
+    pub requests_per_unit: u32,
+}
+
+/// # GlobalDescriptor
+/// A descriptor of the rate limit service configuration. Nested descriptors match the entries
+/// produced by the following actions.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GlobalDescriptor {
+    pub key: String,
+
+    /// Matches any value of the entry when unset.
+    pub value: Option<String>,
+
+    #[serde(rename = "rate_limit")]
+    pub limit: Option<Limit>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub descriptors: Vec<GlobalDescriptor>,
+}
+
+impl GlobalDescriptor {
+    pub fn new(key: &str, value: Option<&str>, limit: Option<Limit>) -> Self {
+        GlobalDescriptor {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+            limit,
+            descriptors: Vec::new(),
+        }
+    }
+
+    pub fn with_descriptor(mut self, descriptor: GlobalDescriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+}
+
+/// # RateLimitServiceConfig
+/// The configuration of an envoyproxy/ratelimit service for the descriptors of a
+/// [`GlobalRateLimit`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RateLimitServiceConfig {
+    pub domain: String,
+    pub descriptors: Vec<GlobalDescriptor>,
+}
+
+/// # GlobalRateLimit
+/// Rate limits enforced by an external rate limit service shared by all proxies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobalRateLimit {
+    pub workload: Workload,
+
+    /// The domain of the rate limit service configuration.
+    pub domain: String,
+
+    /// Host and gRPC port of the rate limit service, e.g.
+    /// `ratelimit.istio-system.svc.cluster.local` and `8081`.
+    pub service: String,
+    pub port: u32,
+
+    pub timeout: Duration,
+
+    /// Reject requests when the rate limit service cannot be reached.
+    pub failure_mode_deny: bool,
+
+    /// The actions producing the descriptor of a request, in order.
+    pub actions: Vec<Action>,
+
+    pub descriptors: Vec<GlobalDescriptor>,
+}
+
+impl GlobalRateLimit {
+    pub fn new(workload: Workload, domain: &str, service: &str, port: u32) -> Self {
+        GlobalRateLimit {
+            workload,
+            domain: domain.to_string(),
+            service: service.to_string(),
+            port,
+            timeout: Duration::from_millis(100),
+            failure_mode_deny: false,
+            actions: Vec::new(),
+            descriptors: Vec::new(),
+        }
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn with_descriptor(mut self, descriptor: GlobalDescriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    /// The EnvoyFilter inserting the rate limit filter and adding the actions to the virtual hosts
+    /// of the workload.
+    pub fn envoy_filter(&self) -> Result<EnvoyFilter, RateLimitError> {
+        self.check()?;
+        Ok(self.workload.envoy_filter(
+            "ratelimit",
+            vec![
+                self.workload.http_filter(json!({
+                    "name": RATE_LIMIT_FILTER,
+                    "typed_config": {
+                        "@type": RATE_LIMIT_TYPE,
+                        "domain": self.domain,
+                        "failure_mode_deny": self.failure_mode_deny,
+                        "timeout": proto_duration(self.timeout),
+                        "rate_limit_service": {
+                            "grpc_service": {
+                                "envoy_grpc": {
+                                    "cluster_name": format!("outbound|{}||{}", self.port, self.service),
+                                    "authority": self.service,
+                                },
+                                "timeout": proto_duration(self.timeout),
+                            },
+                            "transport_api_version": "V3",
+                        },
+                    },
+                })),
+                self.workload
+                    .virtual_hosts(json!({ "rate_limits": [{ "actions": actions(&self.actions) }] })),
+            ],
+        ))
+    }
+
+    /// The rate limit service configuration of the descriptors.
+    pub fn service_config(&self) -> Result<RateLimitServiceConfig, RateLimitError> {
+        self.check()?;
+        Ok(RateLimitServiceConfig {
+            domain: self.domain.clone(),
+            descriptors: self.descriptors.clone(),
+        })
+    }
+
+    fn check(&self) -> Result<(), RateLimitError> {
+        if self.domain.is_empty() {
+            return Err(RateLimitError::MissingDomain);
+        }
+        if self.actions.is_empty() {
+            return Err(RateLimitError::MissingActions);
+        }
+        let keys: Vec<&str> = self.actions.iter().map(Action::descriptor_key).collect();
+        check_levels(&self.descriptors, "descriptors", &keys)
+    }
+}
+
+/// Checks that the descriptors at each nesting level use the key of the action at that position.
+fn check_levels(
+    descriptors: &[GlobalDescriptor],
+    path: &str,
+    keys: &[&str],
+) -> Result<(), RateLimitError> {
+    for (i, descriptor) in descriptors.iter().enumerate() {
+        let path = format!("{}[{}]", path, i);
+        if keys.first() != Some(&descriptor.key.as_str()) {
+            return Err(RateLimitError::DescriptorMismatch {
+                path,
+                found: vec![descriptor.key.clone()],
+                expected: keys.iter().take(1).map(|key| key.to_string()).collect(),
+            });
+        }
+        check_levels(
+            &descriptor.descriptors,
+            &format!("{}.descriptors", path),
+            &keys[1..],
+        )?;
+    }
+    Ok(())
+}
+
+/// # RateLimitError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitError {
+    InvalidTokenBucket {
+        path: String,
+        reason: &'static str,
+    },
+
+    /// Descriptors are configured but no action produces the descriptor of a request.
+    MissingActions,
+
+    /// The keys of a descriptor differ from the keys produced by the actions, so it never matches.
+    DescriptorMismatch {
+        path: String,
+        found: Vec<String>,
+        expected: Vec<String>,
+    },
+
+    MissingDomain,
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::InvalidTokenBucket { path, reason } => {
+                write!(f, "{}: {}", path, reason)
+            }
+            RateLimitError::MissingActions => {
+                write!(f, "at least one action is required to match descriptors")
+            }
+            RateLimitError::DescriptorMismatch {
+                path,
+                found,
+                expected,
+            } => write!(
+                f,
+                "{}: descriptor keys [{}] do not match the action keys [{}]",
+                path,
+                found.join(", "),
+                expected.join(", ")
+            ),
+            RateLimitError::MissingDomain => write!(f, "domain is required"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+fn actions(actions: &[Action]) -> Vec<Value> {
+    actions.iter().map(Action::to_value).collect()
+}
+
+pub(crate) fn structure(value: Value) -> Struct {
+    match value {
+        Value::Object(fields) => Struct {
+            fields: fields.into_iter().collect(),
+        },
+        _ => Struct::default(),
+    }
+}
+
+/// Formats a duration the way the protobuf JSON mapping expects, e.g. `60s` or `0.250s`.
+fn proto_duration(duration: Duration) -> String {
+    match duration.subsec_millis() {
+        0 => format!("{}s", duration.as_secs()),
+        millis => format!("{}.{:03}s", duration.as_secs(), millis),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::istio::validate::Validate;
+    use k8s_openapi::serde_json::{self, json};
+
+    fn workload() -> Workload {
+        Workload::sidecar("reviews", "bookinfo", [("app", "reviews")])
+    }
+
+    fn patches(filter: &EnvoyFilter) -> Value {
+        serde_json::to_value(filter).unwrap()["spec"]["configPatches"].clone()
+    }
+
+    #[test]
+    fn local_rate_limit_inserts_the_filter_and_configures_the_virtual_hosts() {
+        let filter =
+            LocalRateLimit::new(workload(), TokenBucket::new(100, Duration::from_secs(60)))
+                .with_action(Action::request_header(":path", "path"))
+                .with_descriptor(
+                    [("path", "/login")],
+                    TokenBucket::new(5, Duration::from_millis(1500)),
+                )
+                .envoy_filter()
+                .unwrap();
+
+        assert_eq!(
+            filter.metadata.name.as_deref(),
+            Some("reviews-local-ratelimit")
+        );
+        assert_eq!(filter.metadata.namespace.as_deref(), Some("bookinfo"));
+        assert!(filter.validate().is_ok());
+        let value = serde_json::to_value(&filter).unwrap();
+        assert_eq!(
+            value["spec"]["workloadSelector"],
+            json!({ "labels": { "app": "reviews" } })
+        );
+
+        let patches = patches(&filter);
+        assert_eq!(patches[0]["applyTo"], "HTTP_FILTER");
+        assert_eq!(
+            patches[0]["match"],
+            json!({
+                "context": "SIDECAR_INBOUND",
+                "listener": { "filterChain": { "filter": {
+                    "name": HTTP_CONNECTION_MANAGER,
+                    "subFilter": { "name": ROUTER },
+                } } },
+            })
+        );
+        assert_eq!(patches[0]["patch"]["operation"], "INSERT_BEFORE");
+        assert_eq!(
+            patches[0]["patch"]["value"]["name"],
+            LOCAL_RATE_LIMIT_FILTER
+        );
+
+        assert_eq!(patches[1]["applyTo"], "VIRTUAL_HOST");
+        assert_eq!(patches[1]["patch"]["operation"], "MERGE");
+        let virtual_host = &patches[1]["patch"]["value"];
+        assert_eq!(
+            virtual_host["rate_limits"],
+            json!([{ "actions": [{ "request_headers": { "header_name": ":path", "descriptor_key": "path" } }] }])
+        );
+        let config = &virtual_host["typed_per_filter_config"][LOCAL_RATE_LIMIT_FILTER];
+        assert_eq!(
+            config["token_bucket"],
+            json!({ "max_tokens": 100, "tokens_per_fill": 100, "fill_interval": "60s" })
+        );
+        assert_eq!(
+            config["descriptors"],
+            json!([{
+                "entries": [{ "key": "path", "value": "/login" }],
+                "token_bucket": { "max_tokens": 5, "tokens_per_fill": 5, "fill_interval": "1.500s" },
+            }])
+        );
+    }
+
+    #[test]
+    fn gateways_are_matched_on_their_port() {
+        let filter = LocalRateLimit::new(
+            Workload::gateway("ingress", "istio-system", [("istio", "ingressgateway")])
+                .on_port(8080),
+            TokenBucket::new(10, Duration::from_secs(1)),
+        )
+        .envoy_filter()
+        .unwrap();
+
+        let patches = patches(&filter);
+        assert_eq!(patches[0]["match"]["context"], "GATEWAY");
+        assert_eq!(patches[0]["match"]["listener"]["portNumber"], 8080);
+        assert_eq!(
+            patches[1]["match"],
+            json!({ "context": "GATEWAY", "routeConfiguration": { "portNumber": 8080 } })
+        );
+        assert!(patches[1]["patch"]["value"].get("rate_limits").is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_local_rate_limits() {
+        let bucket = TokenBucket::new(10, Duration::from_secs(1));
+
+        let error =
+            LocalRateLimit::new(workload(), TokenBucket::new(10, Duration::from_millis(10)))
+                .envoy_filter()
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "bucket: fill_interval must be at least 50ms"
+        );
+
+        let error = LocalRateLimit::new(workload(), TokenBucket::new(0, Duration::from_secs(1)))
+            .envoy_filter()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "bucket: max_tokens and tokens_per_fill must be greater than 0"
+        );
+
+        let error = LocalRateLimit::new(workload(), bucket)
+            .with_descriptor([("path", "/")], bucket)
+            .envoy_filter()
+            .unwrap_err();
+        assert_eq!(error, RateLimitError::MissingActions);
+
+        let error = LocalRateLimit::new(workload(), bucket)
+            .with_action(Action::RemoteAddress)
+            .with_descriptor([("path", "/")], bucket)
+            .envoy_filter()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "descriptors[0]: descriptor keys [path] do not match the action keys [remote_address]"
+        );
+    }
+
+    #[test]
+    fn global_rate_limit_points_the_filter_at_the_service() {
+        let rate_limit = GlobalRateLimit::new(
+            workload(),
+            "bookinfo",
+            "ratelimit.istio-system.svc.cluster.local",
+            8081,
+        )
+        .with_action(Action::RemoteAddress)
+        .with_action(Action::generic_key("reviews"))
+        .with_descriptor(
+            GlobalDescriptor::new(
+                "remote_address",
+                None,
+                Some(Limit {
+                    unit: Unit::Minute,
+                    requests_per_unit: 60,
+                }),
+            )
+            .with_descriptor(GlobalDescriptor::new(
+                "generic_key",
+                Some("reviews"),
+                None,
+            )),
+        );
+
+        let filter = rate_limit.envoy_filter().unwrap();
+        assert_eq!(filter.metadata.name.as_deref(), Some("reviews-ratelimit"));
+        let patches = patches(&filter);
+        let config = &patches[0]["patch"]["value"]["typed_config"];
+        assert_eq!(config["domain"], "bookinfo");
+        assert_eq!(config["failure_mode_deny"], false);
+        assert_eq!(config["timeout"], "0.100s");
+        assert_eq!(
+            config["rate_limit_service"]["grpc_service"]["envoy_grpc"]["cluster_name"],
+            "outbound|8081||ratelimit.istio-system.svc.cluster.local"
+        );
+        assert_eq!(
+            patches[1]["patch"]["value"]["rate_limits"],
+            json!([{ "actions": [
+                { "remote_address": {} },
+                { "generic_key": { "descriptor_value": "reviews" } },
+            ] }])
+        );
+
+        assert_eq!(
+            serde_json::to_value(rate_limit.service_config().unwrap()).unwrap(),
+            json!({
+                "domain": "bookinfo",
+                "descriptors": [{
+                    "key": "remote_address",
+                    "rate_limit": { "unit": "minute", "requests_per_unit": 60 },
+                    "descriptors": [{ "key": "generic_key", "value": "reviews" }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_global_rate_limits() {
+        let rate_limit = GlobalRateLimit::new(workload(), "", "ratelimit", 8081)
+            .with_action(Action::RemoteAddress);
+        assert_eq!(
+            rate_limit.envoy_filter(),
+            Err(RateLimitError::MissingDomain)
+        );
+
+        let rate_limit = GlobalRateLimit::new(workload(), "bookinfo", "ratelimit", 8081);
+        assert_eq!(
+            rate_limit.service_config(),
+            Err(RateLimitError::MissingActions)
+        );
+
+        let rate_limit = GlobalRateLimit::new(workload(), "bookinfo", "ratelimit", 8081)
+            .with_action(Action::RemoteAddress)
+            .with_descriptor(
+                GlobalDescriptor::new("remote_address", None, None)
+                    .with_descriptor(GlobalDescriptor::new("path", None, None)),
+            );
+        assert_eq!(
+            rate_limit.envoy_filter().unwrap_err().to_string(),
+            "descriptors[0].descriptors[0]: descriptor keys [path] do not match the action keys []"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RequestAuthentication {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<RequestAuthenticationSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for RequestAuthentication {
+    const API_VERSION: &'static str = "security.istio.io/v1beta1";
+    const GROUP: &'static str = "security.istio.io";
+    const KIND: &'static str = "RequestAuthentication";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "requestauthentications";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for RequestAuthentication {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+/// # RequestAuthentication
+/// RequestAuthentication defines what request authentication methods are supported by a workload.
+/// It will reject a request if the request contains invalid authentication information, based on
+/// the configured authentication rules. A request that does not contain any authentication
+/// credentials will be accepted but will not have any authenticated identity. To restrict access to
+/// authenticated requests only, this should be accompanied by an authorization rule.
+///
+/// Require JWT for all request for workloads that have label `app:httpbin`:
+///
+/// ```yaml
+/// apiVersion: security.istio.io/v1beta1
+/// kind: RequestAuthentication
+/// metadata:
+///   name: httpbin
+///   namespace: foo
+/// spec:
+///   selector:
+///     matchLabels:
+///       app: httpbin
+///   jwtRules:
+///   - issuer: "issuer-foo"
+///     jwksUri: https://example.com/.well-known/jwks.json
+/// ---
+/// apiVersion: security.istio.io/v1beta1
+/// kind: AuthorizationPolicy
+/// metadata:
+///   name: httpbin
+///   namespace: foo
+/// spec:
+///   selector:
+///     matchLabels:
+///       app: httpbin
+///   rules:
+///   - from:
+///     - source:
+///         requestPrincipals: ["*"]
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RequestAuthenticationSpec {
+    // Optional. The selector decides where to apply the request authentication policy. The selector will match with workloads in the same namespace as the request authentication policy. If the request authentication policy is in the root namespace, the selector will additionally match with workloads in all namespaces.
+    //
+    // If not set, the selector will match all workloads.
+    // No
+    pub selector: Option<WorkloadSelector>,
+
+    // Define the list of JWTs that can be validated at the selected workloads’ proxy. A valid token will be used to extract the authenticated identity. Each rule will be activated only when a token is presented at the location recognized by the rule. The token will be validated based on the JWT rule config. If validation fails, the request will be rejected. Note: Requests with multiple tokens (at different locations) are not supported, the output principal of such requests is undefined.
+    // No
+    #[serde(rename = "jwtRules")]
+    pub jwt_rules: Option<Vec<JWTRule>>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # JWTRule
+/// JSON Web Token (JWT) token format for authentication as defined by RFC 7519. See OAuth 2.0 and
+/// OIDC 1.0 for how this is used in the whole authentication flow.
+///
+/// Examples:
+///
+/// Spec for a JWT that is issued by `https://example.com`, with the audience claims must be either
+/// `bookstore_android.apps.example.com` or `bookstore_web.apps.example.com`. The token should be
+/// presented at the `Authorization` header (default). The JSON Web Key Set (JWKS) will be
+/// discovered following OpenID Connect protocol.
+///
+/// ```yaml
+/// issuer: https://example.com
+/// audiences:
+/// - bookstore_android.apps.example.com
+/// - bookstore_web.apps.example.com
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JWTRule {
+    // Identifies the issuer that issued the JWT. See issuer A JWT with different iss claim will be rejected.
+    //
+    // Example: https://foobar.auth0.com Example: 1234567-compute@developer.gserviceaccount.com
+    // Yes
+    pub issuer: String,
+
+    // The list of JWT audiences. that are allowed to access. A JWT containing any of these audiences will be accepted.
+    //
+    // The service name will be accepted if audiences is empty.
+    // No
+    pub audiences: Option<Vec<String>>,
+
+    // URL of the provider’s public key set to validate signature of the JWT. See OpenID Discovery.
+    //
+    // Optional if the key set document can either (a) be retrieved from OpenID Discovery of the issuer or (b) inferred from the email domain of the issuer (e.g. a Google service account).
+    //
+    // Example: https://www.googleapis.com/oauth2/v1/certs
+    // No
+    #[serde(rename = "jwksUri")]
+    pub jwks_uri: Option<String>,
+
+    // JSON Web Key Set of public keys to validate signature of the JWT. See https://auth0.com/docs/jwks.
+    //
+    // Note: Only one of jwksUri and jwks should be used.
+    // No
+    pub jwks: Option<String>,
+
+    // List of header locations from which JWT is expected. For example, below is the location spec if JWT is expected to be found in x-jwt-assertion header, and have Bearer prefix:
+    //
+    //   fromHeaders:
+    //   - name: x-jwt-assertion
+    //     prefix: "Bearer "
+    //
+    // Note: Requests with multiple tokens (at different locations) are not supported, the output principal of such requests is undefined.
+    // No
+    #[serde(rename = "fromHeaders")]
+    pub from_headers: Option<Vec<JWTHeader>>,
+
+    // List of query parameters from which JWT is expected. For example, if JWT is provided via query parameter my_token (e.g /path?my_token=<JWT>), the config is:
+    //
+    //   fromParams:
+    //   - "my_token"
+    //
+    // Note: Requests with multiple tokens (at different locations) are not supported, the output principal of such requests is undefined.
+    // No
+    #[serde(rename = "fromParams")]
+    pub from_params: Option<Vec<String>>,
+
+    // This field specifies the header name to output a successfully verified JWT payload to the backend. The forwarded data is base64_encoded(jwt_payload_in_JSON). If it is not specified, the payload will not be emitted.
+    // No
+    #[serde(rename = "outputPayloadToHeader")]
+    pub output_payload_to_header: Option<String>,
+
+    // If set to true, the original token will be kept for the upstream request. Default is false.
+    // No
+    #[serde(rename = "forwardOriginalToken")]
+    pub forward_original_token: Option<bool>,
+}
+
+impl JWTRule {
+    pub fn new(issuer: &str) -> Self {
+        JWTRule {
+            issuer: issuer.to_string(),
+            ..JWTRule::default()
+        }
+    }
+
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audiences
+            .get_or_insert_with(Vec::new)
+            .push(audience.to_string());
+        self
+    }
+
+    pub fn with_jwks_uri(mut self, jwks_uri: &str) -> Self {
+        self.jwks_uri = Some(jwks_uri.to_string());
+        self
+    }
+
+    /// Inlines the key set, as the JSON of a JWKS document.
+    pub fn with_jwks(mut self, jwks: &str) -> Self {
+        self.jwks = Some(jwks.to_string());
+        self
+    }
+
+    /// Reads the token from `name`, stripping `prefix` (e.g. `Bearer `) from the value.
+    pub fn from_header(mut self, name: &str, prefix: Option<&str>) -> Self {
+        self.from_headers
+            .get_or_insert_with(Vec::new)
+            .push(JWTHeader {
+                name: name.to_string(),
+                prefix: prefix.map(ToString::to_string),
+            });
+        self
+    }
+
+    pub fn from_param(mut self, param: &str) -> Self {
+        self.from_params
+            .get_or_insert_with(Vec::new)
+            .push(param.to_string());
+        self
+    }
+
+    pub fn with_output_payload_to_header(mut self, header: &str) -> Self {
+        self.output_payload_to_header = Some(header.to_string());
+        self
+    }
+
+    pub fn with_forward_original_token(mut self, forward: bool) -> Self {
+        self.forward_original_token = Some(forward);
+        self
+    }
+}
+
+/// # JWTHeader
+/// This message specifies a header location to extract JWT token.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JWTHeader {
+    // The HTTP header name.
+    // Yes
+    pub name: String,
+
+    // The prefix that should be stripped before decoding the token. For example, for Authorization: Bearer <token>, prefix=Bearer with a space at the end. If the header doesn't have this exact prefix, it is considered invalid.
+    // No
+    pub prefix: Option<String>,
+}
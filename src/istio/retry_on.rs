@@ -0,0 +1,282 @@
+// The `retryOn` conditions of an HTTP retry policy: the retry policies of Envoy's router, its gRPC
+// retry policies and plain HTTP status codes. `HttpRetry.retryOn` stays the comma-separated string
+// of the API; `HttpRetry::retry_policies` parses it so a typo is caught before the webhook sees it.
+use crate::istio::virtual_service::HttpRetry;
+use std::fmt;
+use std::str::FromStr;
+
+/// # RetryOn
+/// A condition under which a request is retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RetryOn {
+    /// `5xx`: the upstream responds with any 5xx status code or does not respond at all.
+    FiveXx,
+
+    /// `gateway-error`: the upstream responds with 502, 503 or 504.
+    GatewayError,
+
+    /// `reset`: the upstream does not respond at all.
+    Reset,
+
+    /// `reset-before-request`: the upstream resets the stream before the request was sent.
+    ResetBeforeRequest,
+
+    /// `connect-failure`: the connection to the upstream fails.
+    ConnectFailure,
+
+    /// `envoy-ratelimited`: the `x-envoy-ratelimited` header is present.
+    EnvoyRatelimited,
+
+    /// `retriable-4xx`: the upstream responds with 409.
+    Retriable4xx,
+
+    /// `refused-stream`: the upstream resets the stream with a REFUSED_STREAM error.
+    RefusedStream,
+
+    /// `retriable-status-codes`: the upstream responds with a status code listed in the
+    /// retriable status codes, including the ones given in `retryOn`.
+    RetriableStatusCodes,
+
+    /// `retriable-headers`: the upstream response matches the retriable headers.
+    RetriableHeaders,
+
+    /// `http3-post-connect-failure`: an HTTP/3 request fails after the connection was established.
+    Http3PostConnectFailure,
+
+    /// `cancelled`: the gRPC status is CANCELLED.
+    Cancelled,
+
+    /// `deadline-exceeded`: the gRPC status is DEADLINE_EXCEEDED.
+    DeadlineExceeded,
+
+    /// `internal`: the gRPC status is INTERNAL.
+    Internal,
+
+    /// `resource-exhausted`: the gRPC status is RESOURCE_EXHAUSTED.
+    ResourceExhausted,
+
+    /// `unavailable`: the gRPC status is UNAVAILABLE.
+    Unavailable,
+
+    /// An HTTP status code, added by Istio to the retriable status codes.
+    Status(u16),
+}
+
+impl RetryOn {
+    /// The named policies, in the order Envoy documents them.
+    pub const POLICIES: &'static [RetryOn] = &[
+        RetryOn::FiveXx,
+        RetryOn::GatewayError,
+        RetryOn::Reset,
+        RetryOn::ResetBeforeRequest,
+        RetryOn::ConnectFailure,
+        RetryOn::EnvoyRatelimited,
+        RetryOn::Retriable4xx,
+        RetryOn::RefusedStream,
+        RetryOn::RetriableStatusCodes,
+        RetryOn::RetriableHeaders,
+        RetryOn::Http3PostConnectFailure,
+        RetryOn::Cancelled,
+        RetryOn::DeadlineExceeded,
+        RetryOn::Internal,
+        RetryOn::ResourceExhausted,
+        RetryOn::Unavailable,
+    ];
+
+    /// The name of the policy in `retryOn`; `None` for status codes.
+    pub fn as_str(&self) -> Option<&'static str> {
+        let name = match self {
+            RetryOn::FiveXx => "5xx",
+            RetryOn::GatewayError => "gateway-error",
+            RetryOn::Reset => "reset",
+            RetryOn::ResetBeforeRequest => "reset-before-request",
+            RetryOn::ConnectFailure => "connect-failure",
+            RetryOn::EnvoyRatelimited => "envoy-ratelimited",
+            RetryOn::Retriable4xx => "retriable-4xx",
+            RetryOn::RefusedStream => "refused-stream",
+            RetryOn::RetriableStatusCodes => "retriable-status-codes",
+            RetryOn::RetriableHeaders => "retriable-headers",
+            RetryOn::Http3PostConnectFailure => "http3-post-connect-failure",
+            RetryOn::Cancelled => "cancelled",
+            RetryOn::DeadlineExceeded => "deadline-exceeded",
+            RetryOn::Internal => "internal",
+            RetryOn::ResourceExhausted => "resource-exhausted",
+            RetryOn::Unavailable => "unavailable",
+            RetryOn::Status(_) => return None,
+        };
+        Some(name)
+    }
+
+    /// Whether the condition is one of the gRPC retry policies.
+    pub fn is_grpc(&self) -> bool {
+        matches!(
+            self,
+            RetryOn::Cancelled
+                | RetryOn::DeadlineExceeded
+                | RetryOn::Internal
+                | RetryOn::ResourceExhausted
+                | RetryOn::Unavailable
+        )
+    }
+}
+
+impl fmt::Display for RetryOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryOn::Status(status) => write!(f, "{}", status),
+            _ => write!(f, "{}", self.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+impl FromStr for RetryOn {
+    type Err = RetryOnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(policy) = RetryOn::POLICIES
+            .iter()
+            .find(|policy| policy.as_str() == Some(s))
+        {
+            return Ok(*policy);
+        }
+        match s.parse::<u16>() {
+            Ok(status) if (100..=599).contains(&status) => Ok(RetryOn::Status(status)),
+            _ => Err(RetryOnError(s.to_string())),
+        }
+    }
+}
+
+/// # RetryOnError
+/// A `retryOn` entry that is neither a retry policy nor an HTTP status code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryOnError(pub String);
+
+impl fmt::Display for RetryOnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown retry policy {}", self.0)
+    }
+}
+
+impl std::error::Error for RetryOnError {}
+
+/// Parses a comma-separated `retryOn` list. Empty entries are skipped.
+pub fn parse(retry_on: &str) -> Result<Vec<RetryOn>, RetryOnError> {
+    retry_on
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Formats conditions as a comma-separated `retryOn` list.
+pub fn format(policies: &[RetryOn]) -> String {
+    policies
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl HttpRetry {
+    /// The parsed `retryOn` conditions, empty when unset.
+    pub fn retry_policies(&self) -> Result<Vec<RetryOn>, RetryOnError> {
+        self.retry_on
+            .as_deref()
+            .map(parse)
+            .unwrap_or(Ok(Vec::new()))
+    }
+
+    /// Sets `retryOn` to `policies`, unsetting it when there are none.
+    pub fn set_retry_policies(&mut self, policies: &[RetryOn]) {
+        self.retry_on = Some(format(policies)).filter(|retry_on| !retry_on.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    #[test]
+    fn every_policy_round_trips() {
+        for policy in RetryOn::POLICIES {
+            let name = policy.as_str().unwrap();
+            assert_eq!(policy.to_string(), name);
+            assert_eq!(name.parse::<RetryOn>(), Ok(*policy));
+        }
+        assert_eq!("503".parse::<RetryOn>(), Ok(RetryOn::Status(503)));
+        assert_eq!(RetryOn::Status(503).to_string(), "503");
+        assert_eq!(RetryOn::Status(503).as_str(), None);
+
+        let retry_on = "5xx,connect-failure,unavailable,503";
+        let policies = parse(retry_on).unwrap();
+        assert_eq!(
+            policies,
+            vec![
+                RetryOn::FiveXx,
+                RetryOn::ConnectFailure,
+                RetryOn::Unavailable,
+                RetryOn::Status(503),
+            ]
+        );
+        assert_eq!(format(&policies), retry_on);
+        assert!(policies[2].is_grpc());
+        assert!(!policies[0].is_grpc());
+    }
+
+    #[test]
+    fn rejects_unknown_policies() {
+        assert_eq!(
+            parse("5xx,gateway-eror"),
+            Err(RetryOnError("gateway-eror".to_string()))
+        );
+        assert_eq!(
+            "5XX".parse::<RetryOn>().unwrap_err().to_string(),
+            "unknown retry policy 5XX"
+        );
+        // Only HTTP status codes are accepted.
+        assert!("99".parse::<RetryOn>().is_err());
+        assert!("600".parse::<RetryOn>().is_err());
+        assert!("-1".parse::<RetryOn>().is_err());
+    }
+
+    #[test]
+    fn trims_whitespace_and_skips_empty_entries() {
+        assert_eq!(
+            parse(" 5xx , reset,\tretriable-4xx ,, 429 ,").unwrap(),
+            vec![
+                RetryOn::FiveXx,
+                RetryOn::Reset,
+                RetryOn::Retriable4xx,
+                RetryOn::Status(429),
+            ]
+        );
+        assert_eq!(parse(""), Ok(Vec::new()));
+        assert_eq!(parse(" , "), Ok(Vec::new()));
+        assert_eq!(
+            parse("reset, bogus "),
+            Err(RetryOnError("bogus".to_string()))
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_the_retry_on_of_a_retry_policy() {
+        let mut retry: HttpRetry = serde_json::from_value(json!({
+            "attempts": 3,
+            "retryOn": "gateway-error, 503",
+        }))
+        .unwrap();
+        assert_eq!(
+            retry.retry_policies().unwrap(),
+            vec![RetryOn::GatewayError, RetryOn::Status(503)]
+        );
+
+        retry.set_retry_policies(&[RetryOn::Reset, RetryOn::Cancelled]);
+        assert_eq!(retry.retry_on.as_deref(), Some("reset,cancelled"));
+
+        retry.set_retry_policies(&[]);
+        assert_eq!(retry.retry_on, None);
+        assert_eq!(retry.retry_policies(), Ok(Vec::new()));
+    }
+}
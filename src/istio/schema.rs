@@ -0,0 +1,301 @@
+// Schema drift between this crate and the Istio release installed in a cluster. The OpenAPI schemas
+// of the installed CRDs are compared with the schemas generated from the resource types, field by
+// field, so an upgrade of either side shows which fields the crate would drop when reading a
+// resource, and which fields it writes that the cluster would prune. The schemas are exported as
+// well, for tooling outside Rust such as editors validating manifests or policy engines. Needs the
+// `schema` feature.
+use crate::istio::authorization_policy::AuthorizationPolicy;
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::gateway::Gateway;
+use crate::istio::istio_operator::IstioOperator;
+use crate::istio::peer_authentication::PeerAuthentication;
+use crate::istio::proxy_config::ProxyConfig;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::sidecar::Sidecar;
+use crate::istio::virtual_service::VirtualService;
+use crate::istio::wasm_plugin::WasmPlugin;
+use crate::istio::workload_entry::WorkloadEntry;
+use crate::istio::workload_group::WorkloadGroup;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::serde_json::{self, json, Value};
+use k8s_openapi::Resource;
+use kube::api::Api;
+use kube::Client;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The OpenAPI v3 schema of `T`, with all subschemas inlined as in a CRD.
+pub fn schema_of<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::openapi3()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    serde_json::to_value(generator.into_root_schema_for::<T>()).unwrap_or(Value::Null)
+}
+
+/// The JSON Schema (draft 7) of a manifest of kind `T`, whose `apiVersion` and `kind` have to be
+/// the ones of `T`.
+pub fn manifest_schema<T: Resource + JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07().into_generator();
+    let mut schema =
+        serde_json::to_value(generator.into_root_schema_for::<T>()).unwrap_or(Value::Null);
+    if let Some(root) = schema.as_object_mut() {
+        let properties = root.entry("properties").or_insert_with(|| json!({}));
+        properties["apiVersion"] = json!({ "type": "string", "enum": [T::API_VERSION] });
+        properties["kind"] = json!({ "type": "string", "enum": [T::KIND] });
+        let required = root.entry("required").or_insert_with(|| json!([]));
+        if let Some(required) = required.as_array_mut() {
+            required.extend([json!("apiVersion"), json!("kind")]);
+        }
+    }
+    schema
+}
+
+/// The manifest schema of every kind the crate models, by kind.
+pub fn manifest_schemas() -> BTreeMap<String, Value> {
+    let mut schemas = BTreeMap::new();
+    macro_rules! export {
+        ($($resource:ty),*) => {
+            $(
+                schemas.insert(<$resource>::KIND.to_string(), manifest_schema::<$resource>());
+            )*
+        };
+    }
+    export!(
+        AuthorizationPolicy,
+        DestinationRule,
+        EnvoyFilter,
+        Gateway,
+        PeerAuthentication,
+        RequestAuthentication,
+        ServiceEntry,
+        Sidecar,
+        VirtualService,
+        WasmPlugin,
+        WorkloadEntry,
+        WorkloadGroup,
+        ProxyConfig,
+        IstioOperator
+    );
+    schemas
+}
+
+/// An OpenAPI v3 document with the kinds the crate models and every type they consist of under
+/// `components.schemas`, named after the types. It has no paths.
+pub fn openapi() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    macro_rules! export {
+        ($($resource:ty),*) => {
+            $(
+                generator.subschema_for::<$resource>();
+            )*
+        };
+    }
+    export!(
+        AuthorizationPolicy,
+        DestinationRule,
+        EnvoyFilter,
+        Gateway,
+        PeerAuthentication,
+        RequestAuthentication,
+        ServiceEntry,
+        Sidecar,
+        VirtualService,
+        WasmPlugin,
+        WorkloadEntry,
+        WorkloadGroup,
+        ProxyConfig,
+        IstioOperator
+    );
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {},
+        "components": { "schemas": generator.take_definitions() },
+    })
+}
+
+/// # Fields
+/// The fields a schema describes, as paths like `http[].route[].weight`. Entries of maps are
+/// written `.*`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fields {
+    pub fields: BTreeSet<String>,
+
+    /// Fields accepting any content (`x-kubernetes-preserve-unknown-fields`), whose subfields are
+    /// not compared.
+    pub open: BTreeSet<String>,
+}
+
+impl Fields {
+    pub fn of(schema: &Value) -> Self {
+        let mut fields = Fields::default();
+        fields.walk("", schema);
+        fields
+    }
+
+    fn walk(&mut self, path: &str, schema: &Value) {
+        if !path.is_empty() {
+            self.fields.insert(path.to_string());
+        }
+        if schema.get("x-kubernetes-preserve-unknown-fields") == Some(&Value::Bool(true)) {
+            self.open.insert(path.to_string());
+            return;
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, property) in properties {
+                let path = match path {
+                    "" => name.clone(),
+                    _ => format!("{}.{}", path, name),
+                };
+                self.walk(&path, property);
+            }
+        }
+        if let Some(items) = schema.get("items") {
+            self.walk(&format!("{}[]", path), items);
+        }
+        if let Some(values) = schema.get("additionalProperties").filter(|v| v.is_object()) {
+            self.walk(&format!("{}.*", path), values);
+        }
+        for combinator in ["allOf", "anyOf", "oneOf"] {
+            for subschema in schema
+                .get(combinator)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                self.walk(path, subschema);
+            }
+        }
+    }
+
+    fn covers(&self, path: &str) -> bool {
+        self.fields.contains(path)
+            || self.open.iter().any(|open| {
+                path.strip_prefix(open.as_str())
+                    .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('['))
+            })
+    }
+}
+
+/// # Drift
+/// The differences between the schema of a kind in the cluster and in this crate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Drift {
+    pub kind: String,
+    pub version: String,
+
+    /// Spec fields the cluster supports but the crate does not, dropped when the crate reads a
+    /// resource.
+    pub cluster_only: Vec<String>,
+
+    /// Spec fields of the crate the cluster does not know, pruned by the API server.
+    pub crate_only: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.cluster_only.is_empty() && self.crate_only.is_empty()
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}:", self.kind, self.version)?;
+        for field in &self.cluster_only {
+            write!(f, "\n  only in cluster: spec.{}", field)?;
+        }
+        for field in &self.crate_only {
+            write!(f, "\n  only in crate: spec.{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the schema of the spec of a kind in the cluster with the one of the crate.
+pub fn compare_specs(cluster: &Value, model: &Value) -> (Vec<String>, Vec<String>) {
+    let cluster = Fields::of(cluster);
+    let model = Fields::of(model);
+    let cluster_only = cluster
+        .fields
+        .iter()
+        .filter(|field| !model.covers(field))
+        .cloned()
+        .collect();
+    let crate_only = model
+        .fields
+        .iter()
+        .filter(|field| !cluster.covers(field))
+        .cloned()
+        .collect();
+    (cluster_only, crate_only)
+}
+
+/// Compares the spec schema of `T` with the one of its CRD, in the version the crate models.
+/// `None` if the CRD does not serve that version.
+pub fn drift<T: Resource + JsonSchema>(crd: &CustomResourceDefinition) -> Option<Drift> {
+    let version = crd
+        .spec
+        .versions
+        .iter()
+        .find(|version| version.name == T::VERSION)?;
+    let cluster = version
+        .schema
+        .as_ref()
+        .and_then(|validation| validation.open_api_v3_schema.as_ref())
+        .and_then(|schema| serde_json::to_value(schema).ok())?;
+    let model = schema_of::<T>();
+    let spec = |schema: &Value| {
+        schema
+            .pointer("/properties/spec")
+            .cloned()
+            .unwrap_or(Value::Null)
+    };
+    let (cluster_only, crate_only) = compare_specs(&spec(&cluster), &spec(&model));
+    Some(Drift {
+        kind: T::KIND.to_string(),
+        version: T::VERSION.to_string(),
+        cluster_only,
+        crate_only,
+    })
+}
+
+/// Fetches the CRDs of the kinds this crate models and reports the drift of each. Kinds whose CRD
+/// is not installed are skipped.
+pub async fn detect(client: Client) -> Result<Vec<Drift>, kube::Error> {
+    let api: Api<CustomResourceDefinition> = Api::all(client);
+    let mut drifts = Vec::new();
+    macro_rules! check {
+        ($($resource:ty),*) => {
+            $(
+                let name = format!("{}.{}", <$resource>::URL_PATH_SEGMENT, <$resource>::GROUP);
+                if let Some(crd) = api.get_opt(&name).await? {
+                    drifts.extend(drift::<$resource>(&crd));
+                }
+            )*
+        };
+    }
+    check!(
+        AuthorizationPolicy,
+        DestinationRule,
+        EnvoyFilter,
+        Gateway,
+        PeerAuthentication,
+        RequestAuthentication,
+        ServiceEntry,
+        Sidecar,
+        VirtualService,
+        WasmPlugin,
+        WorkloadEntry,
+        WorkloadGroup,
+        ProxyConfig,
+        IstioOperator
+    );
+    Ok(drifts)
+}
@@ -0,0 +1,140 @@
+// Label selectors used to pick the workloads a resource applies to. Networking resources select
+// with a plain label map, security resources with `matchLabels`; both, the `selector` map of a
+// Gateway and a Kubernetes `LabelSelector` with `matchExpressions` are evaluated through the
+// `Selector` trait.
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use std::collections::{BTreeMap, HashMap};
+
+/// # WorkloadSelector
+/// `WorkloadSelector` specifies the criteria used to determine if the `Gateway`, `Sidecar`, `EnvoyFilter`, or `ServiceEntry` configuration can be applied to a proxy. The matching criteria includes the metadata associated with a proxy, workload instance info such as labels attached to the pod/VM, or any other info that the proxy provides to Istio during the initial handshake. If multiple conditions are specified, all conditions need to match in order for the workload instance to be selected. Currently, only label based selection mechanism is supported.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadSelector {
+    // One or more labels that indicate a specific set of pods/VMs on which the configuration should be applied. The scope of label search is restricted to the configuration namespace in which the the resource is present.
+    // Yes
+    pub labels: Map<String, String>,
+}
+
+/// # PolicyWorkloadSelector
+/// WorkloadSelector specifies the criteria used to determine if a policy can be applied to a proxy. The matching criteria includes the metadata associated with a proxy, workload instance info such as labels attached to the pod/VM, or any other info that the proxy provides to Istio during the initial handshake. If multiple conditions are specified, all conditions need to match in order for the workload instance to be selected. Currently, only label based selection mechanism is supported.
+///
+/// Exported as `istio.type.v1beta1.WorkloadSelector` in `crate::istio::r#type::v1beta1`.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolicyWorkloadSelector {
+    // One or more labels that indicate a specific set of pods/VMs on which a policy should be applied. The scope of label search is restricted to the configuration namespace in which the resource is present.
+    // No
+    #[serde(rename = "matchLabels")]
+    pub match_labels: Option<Map<String, String>>,
+}
+
+/// # Labels
+/// The labels of a workload, as found in the metadata of a pod (`BTreeMap`) or in this crate
+/// (`HashMap`, or `IndexMap` with the `indexmap` feature).
+pub trait Labels {
+    fn label(&self, key: &str) -> Option<&str>;
+}
+
+impl Labels for HashMap<String, String> {
+    fn label(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+impl Labels for BTreeMap<String, String> {
+    fn label(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl Labels for indexmap::IndexMap<String, String> {
+    fn label(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+/// # Selector
+/// A label selector. An empty selector selects every workload.
+pub trait Selector {
+    /// Whether a workload with `labels` is selected.
+    fn matches(&self, labels: &impl Labels) -> bool;
+}
+
+impl Selector for HashMap<String, String> {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.iter()
+            .all(|(key, value)| labels.label(key) == Some(value.as_str()))
+    }
+}
+
+impl Selector for BTreeMap<String, String> {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.iter()
+            .all(|(key, value)| labels.label(key) == Some(value.as_str()))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl Selector for indexmap::IndexMap<String, String> {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.iter()
+            .all(|(key, value)| labels.label(key) == Some(value.as_str()))
+    }
+}
+
+impl Selector for WorkloadSelector {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.labels.matches(labels)
+    }
+}
+
+impl Selector for PolicyWorkloadSelector {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.match_labels
+            .as_ref()
+            .is_none_or(|match_labels| match_labels.matches(labels))
+    }
+}
+
+/// `matchExpressions` are supported with the operators `In`, `NotIn`, `Exists` and
+/// `DoesNotExist`. An expression with an unknown operator selects nothing, like the API server
+/// rejecting the selector.
+impl Selector for LabelSelector {
+    fn matches(&self, labels: &impl Labels) -> bool {
+        let expressions_match = self.match_expressions.iter().flatten().all(|requirement| {
+            let value = labels.label(&requirement.key);
+            let values = requirement.values.as_deref().unwrap_or_default();
+            match requirement.operator.as_str() {
+                "In" => value.is_some_and(|value| values.iter().any(|v| v == value)),
+                "NotIn" => value.is_none_or(|value| values.iter().all(|v| v != value)),
+                "Exists" => value.is_some(),
+                "DoesNotExist" => value.is_none(),
+                _ => false,
+            }
+        });
+        expressions_match
+            && self
+                .match_labels
+                .as_ref()
+                .is_none_or(|match_labels| match_labels.matches(labels))
+    }
+}
+
+impl<S: Selector> Selector for Option<S> {
+    /// A missing selector selects every workload.
+    fn matches(&self, labels: &impl Labels) -> bool {
+        self.as_ref()
+            .is_none_or(|selector| selector.matches(labels))
+    }
+}
+
+/// Whether some workload can be selected by both label maps, i.e. they do not require different
+/// values for the same label.
+pub fn overlaps(a: &Map<String, String>, b: &Map<String, String>) -> bool {
+    a.iter()
+        .all(|(key, value)| b.get(key).is_none_or(|other| other == value))
+}
@@ -0,0 +1,611 @@
+// Inherent JSON and YAML helpers of the resources, so the common case of reading or writing a
+// single manifest does not need serde wiring in the caller. The resources themselves do not carry
+// `apiVersion` and `kind`; the helpers add them from the `Resource` constants so the output can be
+// applied as is. The YAML helpers need the `yaml` feature.
+//
+// Reading a resource reports the path of the offending field together with the kind and name of
+// the resource, e.g. `VirtualService reviews: spec.http[2].retries.attempts: invalid type: string
+// "3", expected i32`, rather than the bare serde message.
+//
+// The strict variants reject fields this crate does not model, e.g. a misspelled `reties:`, which
+// the plain ones drop or keep in the `extra` maps of the spec, its routes and its policies. They compare the document with the
+// resource serialized again, so anything the resource lost or only kept as an extra is unknown.
+use crate::istio::authorization_policy::{AuthorizationPolicy, AuthorizationPolicySpec};
+use crate::istio::destination_rule::{DestinationRule, DestinationRuleSpec};
+use crate::istio::envoy_filter::{EnvoyFilter, EnvoyFilterSpec};
+use crate::istio::gateway::{Gateway, GatewaySpec};
+use crate::istio::istio_operator::{IstioOperator, IstioOperatorSpec};
+use crate::istio::peer_authentication::{PeerAuthentication, PeerAuthenticationSpec};
+use crate::istio::proxy_config::{ProxyConfig, ProxyConfigSpec};
+use crate::istio::request_authentication::{RequestAuthentication, RequestAuthenticationSpec};
+use crate::istio::service_entry::{ServiceEntry, ServiceEntrySpec};
+use crate::istio::sidecar::{Sidecar, SidecarSpec};
+use crate::istio::virtual_service::{VirtualService, VirtualServiceSpec};
+use crate::istio::wasm_plugin::{WasmPlugin, WasmPluginSpec};
+use crate::istio::workload_entry::{WorkloadEntry, WorkloadEntrySpec};
+use crate::istio::workload_group::{WorkloadGroup, WorkloadGroupSpec};
+use crate::istio::{v1, v1alpha3, Map};
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+/// A resource with its type information, as it appears in a manifest.
+#[derive(Serialize)]
+struct Manifest<'a, T> {
+    #[serde(rename = "apiVersion")]
+    api_version: &'static str,
+    kind: &'static str,
+    #[serde(flatten)]
+    resource: &'a T,
+}
+
+impl<'a, T: Resource> Manifest<'a, T> {
+    fn new(resource: &'a T) -> Self {
+        Manifest {
+            api_version: T::API_VERSION,
+            kind: T::KIND,
+            resource,
+        }
+    }
+}
+
+/// # DeserializeError
+/// A resource that could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeserializeError {
+    /// Kind of the resource, from the document or else the expected one.
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    /// Path of the offending field, e.g. `spec.http[2].retries.perTryTimeout`. `None` when the
+    /// document itself is malformed.
+    pub path: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, &self.name) {
+            (Some(kind), Some(name)) => write!(f, "{} {}: ", kind, name)?,
+            (Some(kind), None) => write!(f, "{}: ", kind)?,
+            (None, Some(name)) => write!(f, "{}: ", name)?,
+            (None, None) => {}
+        }
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// Deserializes a resource from a JSON value, reporting errors with the path of the offending
+/// field.
+pub fn deserialize<T: Resource + DeserializeOwned>(value: Value) -> Result<T, DeserializeError> {
+    let kind = value
+        .get("kind")
+        .and_then(Value::as_str)
+        .unwrap_or(T::KIND)
+        .to_string();
+    let name = value
+        .pointer("/metadata/name")
+        .and_then(Value::as_str)
+        .map(ToString::to_string);
+    serde_path_to_error::deserialize(value).map_err(|error| {
+        let path = error.path().to_string();
+        DeserializeError {
+            kind: Some(kind),
+            name,
+            path: Some(path).filter(|path| path != "."),
+            message: error.into_inner().to_string(),
+        }
+    })
+}
+
+/// Deserializes a resource from a single YAML document, see [`deserialize`].
+#[cfg(feature = "yaml")]
+pub fn deserialize_yaml<T: Resource + DeserializeOwned>(yaml: &str) -> Result<T, DeserializeError> {
+    let malformed = |message: String| DeserializeError {
+        kind: None,
+        name: None,
+        path: None,
+        message,
+    };
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(yaml).map_err(|error| malformed(error.to_string()))?;
+    // Going through a JSON value maps externally tagged enums (e.g. StringMatch) the way the API
+    // server does.
+    let value = serde_json::to_value(&yaml).map_err(|error| malformed(error.to_string()))?;
+    deserialize(value)
+}
+
+/// Deserializes a resource like [`deserialize`], failing on the first field this crate does not
+/// model, see [`unknown_fields`].
+pub fn deserialize_strict<T>(value: Value) -> Result<T, DeserializeError>
+where
+    T: Resource + DeserializeOwned + Serialize + PreservedFields,
+{
+    let resource: T = deserialize(value.clone())?;
+    match unknown_fields(&value, &resource).into_iter().next() {
+        Some(path) => Err(DeserializeError {
+            kind: Some(T::KIND.to_string()),
+            name: value
+                .pointer("/metadata/name")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            path: Some(path),
+            message: "unknown field".to_string(),
+        }),
+        None => Ok(resource),
+    }
+}
+
+/// Deserializes a resource from a single YAML document, see [`deserialize_strict`].
+#[cfg(feature = "yaml")]
+pub fn deserialize_yaml_strict<T>(yaml: &str) -> Result<T, DeserializeError>
+where
+    T: Resource + DeserializeOwned + Serialize + PreservedFields,
+{
+    let malformed = |message: String| DeserializeError {
+        kind: None,
+        name: None,
+        path: None,
+        message,
+    };
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(yaml).map_err(|error| malformed(error.to_string()))?;
+    let value = serde_json::to_value(&yaml).map_err(|error| malformed(error.to_string()))?;
+    deserialize_strict(value)
+}
+
+/// Resources that keep the fields of their spec this crate does not model, in the `extra` maps.
+pub trait PreservedFields {
+    /// Paths of the kept fields, e.g. `spec.futureField`.
+    fn preserved_fields(&self) -> Vec<String>;
+}
+
+/// Paths of the fields of `value` that `resource`, read from it, does not model, e.g.
+/// `spec.http[0].reties`. Null fields are ignored, as are the deprecated
+/// fields read into their replacement.
+pub fn unknown_fields<T>(value: &Value, resource: &T) -> Vec<String>
+where
+    T: Resource + Serialize + PreservedFields,
+{
+    let mut unknown = Vec::new();
+    if let Ok(serialized) = serde_json::to_value(Manifest::new(resource)) {
+        dropped_fields(value, &serialized, "", &mut unknown);
+    }
+    unknown.extend(resource.preserved_fields());
+    unknown
+}
+
+/// Fields read into another one, by the key of the object holding them: `corsPolicy.allowOrigin`
+/// into `allowOrigins`, and the `number` of a port selector holding a port name into `name`.
+const REPLACED_FIELDS: &[(&str, &str)] = &[("corsPolicy", "allowOrigin"), ("port", "number")];
+
+/// Whether `key` of the object at `path` is one of the `REPLACED_FIELDS`.
+fn replaced(path: &str, key: &str) -> bool {
+    let parent = path.rsplit('.').next().unwrap_or(path);
+    let parent = parent.split('[').next().unwrap_or(parent);
+    REPLACED_FIELDS.contains(&(parent, key))
+}
+
+fn dropped_fields(value: &Value, serialized: &Value, path: &str, dropped: &mut Vec<String>) {
+    match (value, serialized) {
+        (Value::Object(fields), Value::Object(kept)) => {
+            for (key, field) in fields {
+                let field_path = match path {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", path, key),
+                };
+                match kept.get(key) {
+                    Some(kept) => dropped_fields(field, kept, &field_path, dropped),
+                    None if field.is_null() || replaced(path, key) => {}
+                    None => dropped.push(field_path),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(kept)) => {
+            for (i, (item, kept)) in items.iter().zip(kept).enumerate() {
+                dropped_fields(item, kept, &format!("{}[{}]", path, i), dropped);
+            }
+        }
+        _ => {}
+    }
+}
+
+macro_rules! preserved_fields {
+    ($($spec:ty),* $(,)?) => {
+        $(
+            impl PreservedFields for $spec {
+                fn preserved_fields(&self) -> Vec<String> {
+                    self.extra.keys().cloned().collect()
+                }
+            }
+        )*
+    };
+}
+
+preserved_fields!(
+    AuthorizationPolicySpec,
+    EnvoyFilterSpec,
+    GatewaySpec,
+    IstioOperatorSpec,
+    PeerAuthenticationSpec,
+    ProxyConfigSpec,
+    RequestAuthenticationSpec,
+    ServiceEntrySpec,
+    SidecarSpec,
+    WasmPluginSpec,
+    WorkloadEntrySpec,
+);
+
+/// Adds the keys of `extra`, the extras of the object at `path`, to `fields`.
+fn extend_preserved(fields: &mut Vec<String>, path: &str, extra: &Map<String, Value>) {
+    fields.extend(extra.keys().map(|key| format!("{}.{}", path, key)));
+}
+
+/// The routes, their matches and their destinations have extras of their own.
+impl PreservedFields for VirtualServiceSpec {
+    fn preserved_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self.extra.keys().cloned().collect();
+        for (i, http) in self.http.iter().flatten().enumerate() {
+            let path = format!("http[{}]", i);
+            extend_preserved(&mut fields, &path, &http.extra);
+            for (j, m) in http.r#match.iter().flatten().enumerate() {
+                extend_preserved(&mut fields, &format!("{}.match[{}]", path, j), &m.extra);
+            }
+            for (j, route) in http.route.iter().flatten().enumerate() {
+                extend_preserved(&mut fields, &format!("{}.route[{}]", path, j), &route.extra);
+            }
+        }
+        for (i, tls) in self.tls.iter().flatten().enumerate() {
+            extend_preserved(&mut fields, &format!("tls[{}]", i), &tls.extra);
+        }
+        for (i, tcp) in self.tcp.iter().flatten().enumerate() {
+            extend_preserved(&mut fields, &format!("tcp[{}]", i), &tcp.extra);
+        }
+        fields
+    }
+}
+
+/// The traffic policies and the subsets have extras of their own.
+impl PreservedFields for DestinationRuleSpec {
+    fn preserved_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self.extra.keys().cloned().collect();
+        if let Some(policy) = &self.traffic_policy {
+            extend_preserved(&mut fields, "trafficPolicy", &policy.extra);
+        }
+        for (i, subset) in self.subsets.iter().flatten().enumerate() {
+            let path = format!("subsets[{}]", i);
+            extend_preserved(&mut fields, &path, &subset.extra);
+            if let Some(policy) = &subset.traffic_policy {
+                extend_preserved(
+                    &mut fields,
+                    &format!("{}.trafficPolicy", path),
+                    &policy.extra,
+                );
+            }
+        }
+        fields
+    }
+}
+
+/// The template is a `WorkloadEntrySpec` with extras of its own.
+impl PreservedFields for WorkloadGroupSpec {
+    fn preserved_fields(&self) -> Vec<String> {
+        let template = self
+            .template
+            .preserved_fields()
+            .into_iter()
+            .map(|field| format!("template.{}", field));
+        self.extra.keys().cloned().chain(template).collect()
+    }
+}
+
+macro_rules! serialization {
+    ($($resource:ty),* $(,)?) => {
+        $(
+            impl $resource {
+                /// The resource as a JSON value including `apiVersion` and `kind`, e.g. to hand it to a
+                /// dynamic API.
+                pub fn to_json_value(&self) -> serde_json::Result<Value> {
+                    serde_json::to_value(Manifest::new(self))
+                }
+
+                /// The resource as a YAML manifest including `apiVersion` and `kind`. It goes through
+                /// JSON first, as serde_yaml writes enums like `StringMatch` as YAML tags
+                /// (`uri: !prefix /a`), which Kubernetes does not read.
+                #[cfg(feature = "yaml")]
+                pub fn to_yaml_string(&self) -> serde_yaml::Result<String> {
+                    let value = self
+                        .to_json_value()
+                        .map_err(<serde_yaml::Error as serde::ser::Error>::custom)?;
+                    serde_yaml::to_string(&value)
+                }
+
+                /// Reads the resource from a JSON value. `apiVersion` and `kind` are not checked.
+                pub fn from_json_value(value: Value) -> Result<Self, DeserializeError> {
+                    deserialize(value)
+                }
+
+                /// Reads the resource from a single YAML document. `apiVersion` and `kind` are not checked.
+                #[cfg(feature = "yaml")]
+                pub fn from_yaml_str(yaml: &str) -> Result<Self, DeserializeError> {
+                    deserialize_yaml(yaml)
+                }
+
+                /// Like `from_json_value`, but fails on fields this crate does not model.
+                pub fn from_json_value_strict(value: Value) -> Result<Self, DeserializeError> {
+                    deserialize_strict(value)
+                }
+
+                /// Like `from_yaml_str`, but fails on fields this crate does not model.
+                #[cfg(feature = "yaml")]
+                pub fn from_yaml_str_strict(yaml: &str) -> Result<Self, DeserializeError> {
+                    deserialize_yaml_strict(yaml)
+                }
+            }
+
+            impl PreservedFields for $resource {
+                fn preserved_fields(&self) -> Vec<String> {
+                    self.spec
+                        .iter()
+                        .flat_map(PreservedFields::preserved_fields)
+                        .map(|field| format!("spec.{}", field))
+                        .collect()
+                }
+            }
+        )*
+    };
+}
+
+serialization!(
+    AuthorizationPolicy,
+    DestinationRule,
+    EnvoyFilter,
+    Gateway,
+    PeerAuthentication,
+    RequestAuthentication,
+    ServiceEntry,
+    Sidecar,
+    VirtualService,
+    WasmPlugin,
+    WorkloadEntry,
+    WorkloadGroup,
+    ProxyConfig,
+    IstioOperator,
+    v1alpha3::VirtualService,
+    v1alpha3::DestinationRule,
+    v1alpha3::Gateway,
+    v1::VirtualService,
+    v1::DestinationRule,
+    v1::Gateway,
+    v1::ServiceEntry,
+    v1::Sidecar,
+    v1::WorkloadEntry,
+    v1::WorkloadGroup,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::json;
+
+    fn reviews(http: Value) -> Value {
+        json!({
+            "apiVersion": VirtualService::API_VERSION,
+            "kind": "VirtualService",
+            "metadata": { "name": "reviews" },
+            "spec": { "hosts": ["reviews"], "http": [http] },
+        })
+    }
+
+    #[test]
+    fn json_value_carries_the_type_information() {
+        let value = reviews(json!({ "route": [{ "destination": { "host": "reviews" } }] }));
+        let vs = VirtualService::from_json_value(value.clone()).unwrap();
+        assert_eq!(vs.to_json_value().unwrap(), value);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trips() {
+        let vs = VirtualService::from_json_value(reviews(json!({
+            "match": [{ "uri": { "prefix": "/a" }, "headers": { "x": { "exact": "b" } } }],
+            "route": [{ "destination": { "host": "reviews" } }],
+        })))
+        .unwrap();
+        let yaml = vs.to_yaml_string().unwrap();
+        assert!(!yaml.contains('!'), "{}", yaml);
+        assert!(yaml.contains("prefix: /a"), "{}", yaml);
+        assert!(yaml.contains("exact: b"), "{}", yaml);
+        assert!(
+            yaml.starts_with("apiVersion: networking.istio.io/"),
+            "{}",
+            yaml
+        );
+        assert!(yaml.contains("kind: VirtualService"), "{}", yaml);
+        assert_eq!(VirtualService::from_yaml_str(&yaml).unwrap(), vs);
+    }
+
+    #[test]
+    fn replaced_fields_are_only_known_where_they_are_read() {
+        let legacy = reviews(json!({
+            "route": [{ "destination": { "host": "reviews", "port": { "number": "http" } } }],
+            "corsPolicy": { "allowOrigin": ["https://example.com"] },
+        }));
+        assert!(VirtualService::from_json_value_strict(legacy).is_ok());
+
+        let misplaced = reviews(json!({
+            "route": [{ "destination": { "host": "reviews", "number": 8080 } }],
+            "allowOrigin": ["https://example.com"],
+        }));
+        let vs = VirtualService::from_json_value(misplaced.clone()).unwrap();
+        let mut unknown = unknown_fields(&misplaced, &vs);
+        unknown.sort();
+        assert_eq!(
+            unknown,
+            [
+                "spec.http[0].allowOrigin",
+                "spec.http[0].route[0].destination.number"
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_resource_and_the_field() {
+        let error = VirtualService::from_json_value(reviews(json!({
+            "route": [{ "destination": { "host": "reviews" } }],
+            "retries": { "attempts": "3" },
+        })))
+        .unwrap_err();
+        assert_eq!(error.kind.as_deref(), Some("VirtualService"));
+        assert_eq!(error.name.as_deref(), Some("reviews"));
+        assert_eq!(error.path.as_deref(), Some("spec.http[0].retries.attempts"));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "VirtualService reviews: spec.http[0].retries.attempts: {}",
+                error.message
+            )
+        );
+    }
+
+    #[test]
+    fn errors_fall_back_to_the_expected_kind() {
+        let error = Gateway::from_json_value(json!({ "spec": { "servers": [] } })).unwrap_err();
+        assert_eq!(error.kind.as_deref(), Some("Gateway"));
+        assert_eq!(error.name, None);
+        assert_eq!(error.path.as_deref(), Some("spec"));
+        assert!(
+            error.to_string().starts_with("Gateway: spec: "),
+            "{}",
+            error
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn malformed_yaml_has_no_path() {
+        let error = VirtualService::from_yaml_str("spec: [").unwrap_err();
+        assert_eq!((error.kind, error.name, error.path), (None, None, None));
+    }
+
+    #[test]
+    fn strict_mode_rejects_misspelled_fields() {
+        let value = reviews(json!({
+            "route": [{ "destination": { "host": "reviews" } }],
+            "reties": { "attempts": 3 },
+        }));
+        assert!(VirtualService::from_json_value(value.clone()).is_ok());
+        let error = VirtualService::from_json_value_strict(value).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "VirtualService reviews: spec.http[0].reties: unknown field"
+        );
+    }
+
+    #[test]
+    fn strict_mode_rejects_preserved_fields() {
+        let mut value = reviews(json!({ "route": [{ "destination": { "host": "reviews" } }] }));
+        value["spec"]["futureField"] = json!(true);
+        let vs = VirtualService::from_json_value(value.clone()).unwrap();
+        assert_eq!(unknown_fields(&value, &vs), ["spec.futureField"]);
+        assert_eq!(
+            VirtualService::from_json_value_strict(value)
+                .unwrap_err()
+                .path
+                .as_deref(),
+            Some("spec.futureField")
+        );
+    }
+
+    #[test]
+    fn strict_mode_ignores_null_fields() {
+        let value = reviews(json!({
+            "route": [{ "destination": { "host": "reviews" } }],
+            "timeout": null,
+        }));
+        assert!(VirtualService::from_json_value_strict(value).is_ok());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn strict_mode_reads_yaml() {
+        let yaml = "
+apiVersion: networking.istio.io/v1beta1
+kind: DestinationRule
+metadata:
+  name: ratings
+spec:
+  host: ratings
+  trafficPolicy:
+    outlierDetection:
+      consecutive5xxErrors: 7
+      intervall: 5m
+";
+        let error = DestinationRule::from_yaml_str_strict(yaml).unwrap_err();
+        assert_eq!(
+            error.path.as_deref(),
+            Some("spec.trafficPolicy.outlierDetection.intervall")
+        );
+    }
+
+    #[test]
+    fn nested_unknown_fields_survive_a_round_trip() {
+        let value = reviews(json!({
+            "match": [{ "uri": { "prefix": "/" }, "futureMatch": "x" }],
+            "route": [{ "destination": { "host": "reviews" }, "futureDestination": 1 }],
+            "futureRoute": { "enabled": true },
+        }));
+        let vs = VirtualService::from_json_value(value.clone()).unwrap();
+        assert_eq!(vs.to_json_value().unwrap(), value);
+        let mut unknown = unknown_fields(&value, &vs);
+        unknown.sort();
+        assert_eq!(
+            unknown,
+            [
+                "spec.http[0].futureRoute",
+                "spec.http[0].match[0].futureMatch",
+                "spec.http[0].route[0].futureDestination",
+            ]
+        );
+
+        let value = json!({
+            "apiVersion": DestinationRule::API_VERSION,
+            "kind": "DestinationRule",
+            "metadata": { "name": "ratings" },
+            "spec": {
+                "host": "ratings",
+                "trafficPolicy": { "futurePolicy": "x" },
+                "subsets": [{
+                    "name": "v1",
+                    "futureSubset": "x",
+                    "trafficPolicy": { "futurePolicy": "y" },
+                }],
+            },
+        });
+        let rule = DestinationRule::from_json_value(value.clone()).unwrap();
+        assert_eq!(rule.to_json_value().unwrap(), value);
+        assert_eq!(
+            DestinationRule::from_json_value_strict(value)
+                .unwrap_err()
+                .path
+                .as_deref(),
+            Some("spec.trafficPolicy.futurePolicy")
+        );
+        let mut unknown = rule.preserved_fields();
+        unknown.sort();
+        assert_eq!(
+            unknown,
+            [
+                "spec.subsets[0].futureSubset",
+                "spec.subsets[0].trafficPolicy.futurePolicy",
+                "spec.trafficPolicy.futurePolicy",
+            ]
+        );
+    }
+}
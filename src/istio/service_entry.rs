@@ -0,0 +1,296 @@
+use crate::istio::client_tls_settings::TLSmode;
+use crate::istio::destination_rule::{
+    ClientTLSSettings, DestinationRule, DestinationRuleSpec, TrafficPolicy,
+};
+use crate::istio::export_to::ExportTo;
+use crate::istio::traffic_policy::PortTrafficPolicy;
+use crate::istio::virtual_service::PortSelector;
+use crate::istio::workload_entry::WorkloadEntrySpec;
+use crate::istio::Map;
+use crate::istio::WorkloadSelector;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServiceEntry {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<ServiceEntrySpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for ServiceEntry {
+    const API_VERSION: &'static str = "networking.istio.io/v1beta1";
+    const GROUP: &'static str = "networking.istio.io";
+    const KIND: &'static str = "ServiceEntry";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "serviceentries";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for ServiceEntry {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl ServiceEntry {
+    /// An external API reached over HTTPS on port 443: the sidecars pass the TLS connection
+    /// through, routing it on its SNI. The entry is named after the host.
+    pub fn external_https(host: &str) -> Self {
+        ServiceEntry::external(
+            host,
+            vec![ServicePort {
+                number: 443,
+                protocol: Some("TLS".to_string()),
+                name: "https".to_string(),
+                target_port: None,
+            }],
+        )
+    }
+
+    /// An external API the applications call over plain HTTP on port 80 while the sidecars
+    /// originate TLS to port 443, together with the DestinationRule doing the origination. Lets
+    /// the sidecars apply HTTP routing, retries and telemetry to the traffic.
+    pub fn external_https_with_origination(host: &str) -> (Self, DestinationRule) {
+        let entry = ServiceEntry::external(
+            host,
+            vec![ServicePort {
+                number: 80,
+                protocol: Some("HTTP".to_string()),
+                name: "http".to_string(),
+                target_port: Some(443),
+            }],
+        );
+        let origination = DestinationRule {
+            metadata: entry.metadata.clone(),
+            spec: Some(DestinationRuleSpec {
+                host: host.to_string(),
+                traffic_policy: Some(TrafficPolicy {
+                    load_balancer: None,
+                    connection_pool: None,
+                    outlier_detection: None,
+                    tls: None,
+                    tunnel: None,
+                    proxy_protocol: None,
+                    port_level_settings: Some(vec![PortTrafficPolicy {
+                        port: Some(PortSelector {
+                            number: Some(80),
+                            name: None,
+                        }),
+                        load_balancer: None,
+                        connection_pool: None,
+                        outlier_detection: None,
+                        tls: Some(ClientTLSSettings {
+                            mode: TLSmode::SIMPLE,
+                            client_certificate: None,
+                            private_key: None,
+                            ca_certificates: None,
+                            credential_name: None,
+                            subject_alt_names: None,
+                            sni: Some(host.to_string()),
+                            insecure_skip_verify: None,
+                        }),
+                    }]),
+                    extra: Map::new(),
+                }),
+                subsets: None,
+                export_to: None,
+                workload_selector: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        };
+        (entry, origination)
+    }
+
+    /// Creates the entry in `namespace`.
+    pub fn in_namespace(mut self, namespace: &str) -> Self {
+        self.metadata.namespace = Some(namespace.to_string());
+        self
+    }
+
+    fn external(host: &str, ports: Vec<ServicePort>) -> Self {
+        ServiceEntry {
+            metadata: ObjectMeta {
+                name: Some(host.replace('*', "wildcard").replace('.', "-")),
+                ..ObjectMeta::default()
+            },
+            spec: Some(ServiceEntrySpec {
+                hosts: vec![host.to_string()],
+                addresses: None,
+                ports: Some(ports),
+                location: Some(Location::MESH_EXTERNAL),
+                resolution: Some(Resolution::DNS),
+                endpoints: None,
+                workload_selector: None,
+                export_to: None,
+                subject_alt_names: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }
+    }
+}
+
+/// # ServiceEntry
+/// ServiceEntry enables adding additional entries into Istio’s internal service registry, so that
+/// auto-discovered services in the mesh can access/route to these manually specified services. A
+/// service entry describes the properties of a service (DNS name, VIPs, ports, protocols,
+/// endpoints). These services could be external to the mesh (e.g., web APIs) or mesh-internal
+/// services that are not part of the platform’s service registry (e.g., a set of VMs talking to
+/// services in Kubernetes).
+///
+/// The following example declares a few external APIs accessed by internal applications over
+/// HTTPS. The sidecar inspects the SNI value in the ClientHello message to route to the
+/// appropriate external service.
+///
+/// ```yaml
+/// apiVersion: networking.istio.io/v1beta1
+/// kind: ServiceEntry
+/// metadata:
+///   name: external-svc-https
+/// spec:
+///   hosts:
+///   - api.dropboxapi.com
+///   - www.googleapis.com
+///   - api.facebook.com
+///   location: MESH_EXTERNAL
+///   ports:
+///   - number: 443
+///     name: https
+///     protocol: TLS
+///   resolution: DNS
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServiceEntrySpec {
+    // The hosts associated with the ServiceEntry. Could be a DNS name with wildcard prefix.
+    //
+    // 1. The hosts field is used to select matching hosts in VirtualServices and DestinationRules.
+    // 2. For HTTP traffic the HTTP Host/Authority header will be matched against the hosts field.
+    // 3. For HTTPs or TLS traffic containing Server Name Indication (SNI), the SNI value will be matched against the hosts field.
+    //
+    // NOTE 1: When resolution is set to type DNS and no endpoints are specified, the host field will be used as the DNS name of the endpoint to route traffic to.
+    //
+    // NOTE 2: If the hostname matches with the name of a service from another service registry such as Kubernetes that also supplies its own set of endpoints, the ServiceEntry will be treated as a decorator of the existing Kubernetes service. Properties in the service entry will be added to the Kubernetes service if applicable. Currently, the only the following additional properties will be considered by istiod:
+    //
+    // subjectAltNames: In addition to verifying the SANs of the service accounts associated with the pods of the service, the SANs specified here will also be verified.
+    // Yes
+    pub hosts: Vec<String>,
+
+    // The virtual IP addresses associated with the service. Could be CIDR prefix. For HTTP traffic, generated route configurations will include http route domains for both the addresses and hosts field values and the destination will be identified based on the HTTP Host/Authority header. If one or more IP addresses are specified, the incoming traffic will be identified as belonging to this service if the destination IP matches the IP/CIDRs specified in the addresses field. If the Addresses field is empty, traffic will be identified solely based on the destination port. In such scenarios, the port on which the service is being accessed must not be shared by any other service in the mesh. In other words, the sidecar will behave as a simple TCP proxy, forwarding incoming traffic on a specified port to the specified destination endpoint IP/host. Unix domain socket addresses are not supported in this field.
+    // No
+    pub addresses: Option<Vec<String>>,
+
+    // The ports associated with the external service. If the Endpoints are Unix domain socket addresses, there must be exactly one port.
+    // No
+    pub ports: Option<Vec<ServicePort>>,
+
+    // Specify whether the service should be considered external to the mesh or part of the mesh.
+    // No
+    pub location: Option<Location>,
+
+    // Service discovery mode for the hosts. Care must be taken when setting the resolution mode to NONE for a TCP port without accompanying IP addresses. In such cases, traffic to any IP on said port will be allowed (i.e. 0.0.0.0:<port>).
+    // No
+    pub resolution: Option<Resolution>,
+
+    // One or more endpoints associated with the service. Only one of endpoints or workloadSelector can be specified.
+    // No
+    pub endpoints: Option<Vec<WorkloadEntrySpec>>,
+
+    // Applicable only for MESH_INTERNAL services. Only one of endpoints or workloadSelector can be specified. Selects one or more Kubernetes pods or VM workloads (specified using WorkloadEntry) based on their labels. The WorkloadEntry object representing the VMs should be defined in the same namespace as the ServiceEntry.
+    // No
+    #[serde(rename = "workloadSelector")]
+    pub workload_selector: Option<WorkloadSelector>,
+
+    // A list of namespaces to which this service is exported. Exporting a service allows it to be used by sidecars, gateways and virtual services defined in other namespaces. This feature provides a mechanism for service owners and mesh administrators to control the visibility of services across namespace boundaries.
+    //
+    // If no namespaces are specified then the service is exported to all namespaces by default.
+    //
+    // The value “.” is reserved and defines an export to the same namespace that the service is declared in. Similarly the value “*” is reserved and defines an export to all namespaces.
+    // No
+    #[serde(rename = "exportTo")]
+    pub export_to: Option<Vec<ExportTo>>,
+
+    // If specified, the proxy will verify that the server certificate’s subject alternate name matches one of the specified values.
+    // No
+    #[serde(rename = "subjectAltNames")]
+    pub subject_alt_names: Option<Vec<String>>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # ServicePort
+/// ServicePort describes the properties of a specific port of a service.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServicePort {
+    // A valid non-negative integer port number.
+    // Yes
+    pub number: u32,
+
+    // The protocol exposed on the port. MUST BE one of HTTP|HTTPS|GRPC|HTTP2|MONGO|TCP|TLS. TLS implies the connection will be routed based on the SNI header to the destination without terminating the TLS connection.
+    // No
+    pub protocol: Option<String>,
+
+    // Label assigned to the port.
+    // Yes
+    pub name: String,
+
+    // The port number on the endpoint where the traffic will be received. If unset, default to number.
+    // No
+    #[serde(rename = "targetPort")]
+    pub target_port: Option<u32>,
+}
+
+/// # ServiceEntry.Location
+/// Location specifies whether the service is part of Istio mesh or outside the mesh. Location
+/// determines the behavior of several features, such as service-to-service mTLS authentication,
+/// policy enforcement, etc.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
+pub enum Location {
+    // Signifies that the service is external to the mesh. Typically used to indicate external services consumed through APIs.
+    MESH_EXTERNAL,
+
+    // Signifies that the service is part of the mesh. Typically used to indicate services added explicitly as part of expanding the service mesh to include unmanaged infrastructure (e.g., VMs added to a Kubernetes based service mesh).
+    MESH_INTERNAL,
+}
+
+/// # ServiceEntry.Resolution
+/// Resolution determines how the proxy will resolve the IP addresses of the network endpoints
+/// associated with the service, so that it can route to one of them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
+pub enum Resolution {
+    // Assume that incoming connections have already been resolved (to a specific destination IP address).
+    NONE,
+
+    // Use the static IP addresses specified in endpoints (see below) as the backing instances associated with the service.
+    STATIC,
+
+    // Attempt to resolve the IP address by querying the ambient DNS, asynchronously. If no endpoints are specified, the proxy will resolve the DNS address specified in the hosts field, if wildcards are not used.
+    DNS,
+
+    // Attempt to resolve the IP address by querying the ambient DNS, asynchronously. Unlike DNS, DNS_ROUND_ROBIN only uses the first IP address returned when a new connection needs to be initiated without relying on complete results of DNS resolution and connections made to hosts will be retained even if DNS records change frequently eliminating draining connection pools and connection cycling.
+    DNS_ROUND_ROBIN,
+}
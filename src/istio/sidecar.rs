@@ -0,0 +1,347 @@
+use crate::istio::gateway::Port;
+use crate::istio::virtual_service::Destination;
+use crate::istio::{Map, WorkloadSelector};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Sidecar {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<SidecarSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for Sidecar {
+    const API_VERSION: &'static str = "networking.istio.io/v1beta1";
+    const GROUP: &'static str = "networking.istio.io";
+    const KIND: &'static str = "Sidecar";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "sidecars";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for Sidecar {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl Sidecar {
+    /// A Sidecar without listeners, which leaves the defaults of the mesh in place.
+    pub fn new(name: &str, namespace: &str) -> Self {
+        Sidecar {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(SidecarSpec {
+                workload_selector: None,
+                ingress: None,
+                egress: None,
+                outbound_traffic_policy: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }
+    }
+
+    /// Isolates the workloads of `namespace`: their sidecars only know the services of their own
+    /// namespace and of `istio-system`, and traffic to any other host is blocked. Other
+    /// namespaces are made reachable by adding their hosts to the egress listener; Istio allows
+    /// only one egress listener without a port.
+    pub fn namespace_isolation(namespace: &str) -> Self {
+        Sidecar::new("default", namespace)
+            .with_egress(IstioEgressListener::new(["./*", "istio-system/*"]))
+            .with_outbound_traffic_policy(OutboundTrafficPolicy::registry_only())
+    }
+
+    /// Applies the Sidecar to the pods with `labels` only, instead of the whole namespace.
+    pub fn with_workload_selector<K: Into<String>, V: Into<String>>(
+        mut self,
+        labels: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        let labels: Map<String, String> = labels
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        self.spec_mut().workload_selector = Some(WorkloadSelector { labels });
+        self
+    }
+
+    pub fn with_ingress(mut self, listener: IstioIngressListener) -> Self {
+        self.spec_mut()
+            .ingress
+            .get_or_insert_with(Vec::new)
+            .push(listener);
+        self
+    }
+
+    pub fn with_egress(mut self, listener: IstioEgressListener) -> Self {
+        self.spec_mut()
+            .egress
+            .get_or_insert_with(Vec::new)
+            .push(listener);
+        self
+    }
+
+    pub fn with_outbound_traffic_policy(mut self, policy: OutboundTrafficPolicy) -> Self {
+        self.spec_mut().outbound_traffic_policy = Some(policy);
+        self
+    }
+
+    fn spec_mut(&mut self) -> &mut SidecarSpec {
+        self.spec.get_or_insert(SidecarSpec {
+            workload_selector: None,
+            ingress: None,
+            egress: None,
+            outbound_traffic_policy: None,
+            extra: Map::new(),
+        })
+    }
+}
+
+/// # Sidecar
+/// `Sidecar` describes the configuration of the sidecar proxy that mediates inbound and outbound
+/// communication to the workload instance it is attached to. By default, Istio will program all
+/// sidecar proxies in the mesh with the necessary configuration required to reach every workload
+/// instance in the mesh, as well as accept traffic on all the ports associated with the workload.
+/// The `Sidecar` configuration provides a way to fine tune the set of ports, protocols that the
+/// proxy will accept when forwarding traffic to and from the workload. In addition, it is
+/// possible to restrict the set of services that the proxy can reach when forwarding outbound
+/// traffic from workload instances.
+///
+/// ```yaml
+/// apiVersion: networking.istio.io/v1beta1
+/// kind: Sidecar
+/// metadata:
+///   name: default
+///   namespace: istio-config
+/// spec:
+///   egress:
+///   - hosts:
+///     - "./*"
+///     - "istio-system/*"
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SidecarSpec {
+    // Criteria used to select the specific set of pods/VMs on which this Sidecar configuration should be applied. If omitted, the Sidecar configuration will be applied to all workload instances in the same namespace.
+    // No
+    #[serde(rename = "workloadSelector")]
+    pub workload_selector: Option<WorkloadSelector>,
+
+    // Ingress specifies the configuration of the sidecar for processing inbound traffic to the attached workload instance. If omitted, Istio will automatically configure the sidecar based on the information about the workload obtained from the orchestration platform (e.g., exposed ports, services, etc.). If specified, inbound ports are configured if and only if the workload instance is associated with a service.
+    // No
+    pub ingress: Option<Vec<IstioIngressListener>>,
+
+    // Egress specifies the configuration of the sidecar for processing outbound traffic from the attached workload instance to other services in the mesh. If not specified, inherits the system detected defaults from the namespace-wide or the global default Sidecar.
+    // No
+    pub egress: Option<Vec<IstioEgressListener>>,
+
+    // Configuration for the outbound traffic policy. If your application uses one or more external services that are not known apriori, setting the policy to ALLOW_ANY will cause the sidecars to route any unknown traffic originating from the application to its requested destination. If not specified, inherits the system detected defaults from the namespace-wide or the global default Sidecar.
+    // No
+    #[serde(rename = "outboundTrafficPolicy")]
+    pub outbound_traffic_policy: Option<OutboundTrafficPolicy>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # IstioIngressListener
+/// IstioIngressListener specifies the properties of an inbound traffic listener on the sidecar
+/// proxy attached to a workload instance.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioIngressListener {
+    // The port associated with the listener.
+    // Yes
+    pub port: Port,
+
+    // The IP(IPv4 or IPv6) to which the listener should be bound. Unix domain socket addresses are not allowed in the bind field for ingress listeners. If omitted, Istio will automatically configure the defaults based on imported services and the workload instances to which this configuration is applied to.
+    // No
+    pub bind: Option<String>,
+
+    // The captureMode option dictates how traffic to the listener is expected to be captured (or not).
+    // No
+    #[serde(rename = "captureMode")]
+    pub capture_mode: Option<CaptureMode>,
+
+    // The IP endpoint or Unix domain socket to which traffic should be forwarded to. This configuration can be used to redirect traffic arriving at the bind IP:Port on the sidecar to a localhost:port or Unix domain socket where the application workload instance is listening for connections. Arbitrary IPs are not supported. Format should be one of 127.0.0.1:PORT, [::1]:PORT (forward to localhost), 0.0.0.0:PORT, [::]:PORT (forward to the instance IP), or unix:///path/to/socket (forward to Unix domain socket).
+    // No
+    #[serde(rename = "defaultEndpoint")]
+    pub default_endpoint: Option<String>,
+}
+
+impl IstioIngressListener {
+    /// Accepts traffic on `port` and forwards it to the application on `default_endpoint`, e.g.
+    /// `127.0.0.1:8080`.
+    pub fn new(number: i32, protocol: &str, name: &str, default_endpoint: &str) -> Self {
+        IstioIngressListener {
+            port: port(number, protocol, name),
+            bind: None,
+            capture_mode: None,
+            default_endpoint: Some(default_endpoint.to_string()),
+        }
+    }
+
+    pub fn with_bind(mut self, bind: &str) -> Self {
+        self.bind = Some(bind.to_string());
+        self
+    }
+
+    pub fn with_capture_mode(mut self, capture_mode: CaptureMode) -> Self {
+        self.capture_mode = Some(capture_mode);
+        self
+    }
+}
+
+/// # IstioEgressListener
+/// IstioEgressListener specifies the properties of an outbound traffic listener on the sidecar
+/// proxy attached to a workload instance.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioEgressListener {
+    // The port associated with the listener. If using Unix domain socket, use 0 as the port number, with a valid protocol. The port if specified, will be used as the default destination port associated with the imported hosts. If the port is omitted, Istio will infer the listener ports based on the imported hosts.
+    // No
+    pub port: Option<Port>,
+
+    // The IP(IPv4 or IPv6) or the Unix domain socket to which the listener should be bound to. Port MUST be specified if bind is not empty. Format: IPv4 or IPv6 address formats or unix:///path/to/uds or unix://@foobar (Linux abstract namespace).
+    // No
+    pub bind: Option<String>,
+
+    // When the bind address is an IP, the captureMode option dictates how traffic to the listener is expected to be captured (or not). captureMode must be DEFAULT or NONE for Unix domain socket binds.
+    // No
+    #[serde(rename = "captureMode")]
+    pub capture_mode: Option<CaptureMode>,
+
+    // One or more service hosts exposed by the listener in namespace/dnsName format. Services in the specified namespace matching dnsName will be exposed. The corresponding service can be a service in the service registry (e.g., a Kubernetes or cloud foundry service) or a service specified using a ServiceEntry or VirtualService configuration. Any associated DestinationRule in the same namespace will also be used.
+    // Yes
+    pub hosts: Vec<String>,
+}
+
+impl IstioEgressListener {
+    /// Exposes `hosts`, in `namespace/dnsName` form, on every port.
+    pub fn new<H: Into<String>>(hosts: impl IntoIterator<Item = H>) -> Self {
+        IstioEgressListener {
+            port: None,
+            bind: None,
+            capture_mode: None,
+            hosts: hosts.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn on_port(mut self, number: i32, protocol: &str, name: &str) -> Self {
+        self.port = Some(port(number, protocol, name));
+        self
+    }
+
+    pub fn with_bind(mut self, bind: &str) -> Self {
+        self.bind = Some(bind.to_string());
+        self
+    }
+
+    pub fn with_capture_mode(mut self, capture_mode: CaptureMode) -> Self {
+        self.capture_mode = Some(capture_mode);
+        self
+    }
+}
+
+/// # CaptureMode
+/// CaptureMode describes how traffic to a listener is expected to be captured. Applicable only
+/// when the listener is bound to an IP.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CaptureMode {
+    /// The default capture mode defined by the environment.
+    DEFAULT,
+
+    /// Capture traffic using IPtables redirection.
+    IPTABLES,
+
+    /// No traffic capture. When used in an egress listener, the application is expected to
+    /// explicitly communicate with the listener port or Unix domain socket. When used in an
+    /// ingress listener, care needs to be taken to ensure that the listener port is not in use by
+    /// other processes on the host.
+    NONE,
+}
+
+/// # OutboundTrafficPolicy
+/// OutboundTrafficPolicy sets the default behavior of the sidecar for handling unknown outbound
+/// traffic from the application.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OutboundTrafficPolicy {
+    // No
+    pub mode: Option<OutboundTrafficMode>,
+
+    // Specifies the details of the egress proxy to which unknown traffic should be forwarded to from the sidecar. Valid only if the global outboundTrafficPolicy.mode is set to ALLOW_ANY.
+    // No
+    #[serde(rename = "egressProxy")]
+    pub egress_proxy: Option<Destination>,
+}
+
+impl OutboundTrafficPolicy {
+    /// Only allows traffic to hosts in the service registry or declared by ServiceEntries.
+    pub fn registry_only() -> Self {
+        OutboundTrafficPolicy {
+            mode: Some(OutboundTrafficMode::REGISTRY_ONLY),
+            egress_proxy: None,
+        }
+    }
+
+    /// Passes traffic to unknown hosts through.
+    pub fn allow_any() -> Self {
+        OutboundTrafficPolicy {
+            mode: Some(OutboundTrafficMode::ALLOW_ANY),
+            egress_proxy: None,
+        }
+    }
+
+    /// Sends traffic to unknown hosts to `egress_proxy` instead of passing it through.
+    pub fn via(mut self, egress_proxy: Destination) -> Self {
+        self.egress_proxy = Some(egress_proxy);
+        self
+    }
+}
+
+/// # OutboundTrafficMode
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
+pub enum OutboundTrafficMode {
+    /// Outbound traffic will be restricted to services defined in the service registry as well as
+    /// those defined through ServiceEntry configurations.
+    REGISTRY_ONLY,
+
+    /// Outbound traffic to unknown destinations will be allowed, in case there are no services or
+    /// ServiceEntry configurations for the destination port.
+    ALLOW_ANY,
+}
+
+fn port(number: i32, protocol: &str, name: &str) -> Port {
+    Port {
+        number,
+        protocol: protocol.to_string(),
+        name: name.to_string(),
+        target_port: None,
+    }
+}
@@ -0,0 +1,740 @@
+// Simulation of the HTTP routing of a VirtualService. A synthetic request is matched against the
+// route rules in order, as the proxies do, and the first matching rule handles it. `explain` also
+// records, for every rule, which clause of each match block failed, so a troubleshooting tool can
+// show why a request did not land where it was expected to. Needs the `simulation` feature.
+use crate::istio::host::Host;
+use crate::istio::topology::{ResourceRef, MESH_GATEWAY};
+use crate::istio::virtual_service::{HttpMatchRequest, HttpRoute, StringMatch, VirtualService};
+use crate::istio::Map;
+use regex::Regex;
+use std::fmt;
+
+/// # HttpRequest
+/// A synthetic request to route.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HttpRequest {
+    /// The `Host` header, with an optional port.
+    pub authority: String,
+
+    /// The path, with an optional query string.
+    pub uri: String,
+
+    pub method: String,
+    pub scheme: String,
+    pub port: Option<i32>,
+
+    /// Request headers, with lowercase names.
+    pub headers: Map<String, String>,
+
+    #[serde(rename = "sourceLabels")]
+    pub source_labels: Map<String, String>,
+
+    #[serde(rename = "sourceNamespace")]
+    pub source_namespace: Option<String>,
+
+    /// The gateway receiving the request. `None` for a request sent by a sidecar.
+    pub gateway: Option<ResourceRef>,
+}
+
+impl HttpRequest {
+    /// A plain HTTP GET of `uri` on `authority`, sent by a sidecar.
+    pub fn get(authority: &str, uri: &str) -> Self {
+        HttpRequest {
+            authority: authority.to_string(),
+            uri: uri.to_string(),
+            method: "GET".to_string(),
+            scheme: "http".to_string(),
+            ..HttpRequest::default()
+        }
+    }
+
+    pub fn with_method(mut self, method: &str) -> Self {
+        self.method = method.to_string();
+        self
+    }
+
+    pub fn with_scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    pub fn with_port(mut self, port: i32) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .insert(name.to_ascii_lowercase(), value.to_string());
+        self
+    }
+
+    pub fn with_source_label(mut self, key: &str, value: &str) -> Self {
+        self.source_labels
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_source_namespace(mut self, namespace: &str) -> Self {
+        self.source_namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Sends the request through the gateway `name` in `namespace`.
+    pub fn via_gateway(mut self, namespace: &str, name: &str) -> Self {
+        self.gateway = Some(ResourceRef::new(Some(namespace), name));
+        self
+    }
+
+    /// The path, without the query string.
+    pub fn path(&self) -> &str {
+        self.uri
+            .split_once('?')
+            .map_or(self.uri.as_str(), |(path, _)| path)
+    }
+
+    /// The value of the query parameter `name`, empty for a parameter without value.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.uri.split_once('?')?;
+        query
+            .split('&')
+            .map(|param| param.split_once('=').unwrap_or((param, "")))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The authority, without the port.
+    pub fn host(&self) -> &str {
+        match self.authority.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+            _ => &self.authority,
+        }
+    }
+}
+
+/// # Mismatch
+/// A clause of a match block the request does not satisfy.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Mismatch {
+    /// The clause, e.g. `uri` or `headers.x-user`.
+    pub clause: String,
+
+    /// What the clause requires, e.g. `prefix /api`.
+    pub expected: String,
+
+    /// The value of the request, `None` if the request has none.
+    pub found: Option<String>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.found {
+            Some(found) => write!(
+                f,
+                "{}: expected {}, found {}",
+                self.clause, self.expected, found
+            ),
+            None => write!(
+                f,
+                "{}: expected {}, found nothing",
+                self.clause, self.expected
+            ),
+        }
+    }
+}
+
+/// # MatchExplanation
+/// The outcome of a match block of a rule.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MatchExplanation {
+    pub index: usize,
+    pub name: Option<String>,
+
+    /// The failed clauses, empty if the block matched.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl MatchExplanation {
+    pub fn matched(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// # RuleOutcome
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RuleOutcome {
+    /// The rule handles the request.
+    Selected,
+
+    /// The rule matches, but an earlier rule handles the request.
+    Shadowed,
+
+    /// None of the match blocks of the rule match.
+    NotMatched,
+}
+
+/// # RuleExplanation
+/// The outcome of a route rule.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RuleExplanation {
+    pub index: usize,
+    pub name: Option<String>,
+    pub outcome: RuleOutcome,
+
+    /// One entry per match block. Empty for a rule without match blocks, which matches everything.
+    pub matches: Vec<MatchExplanation>,
+}
+
+/// # Explanation
+/// Why a request is, or is not, handled by each rule of a VirtualService.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Explanation {
+    /// Why the VirtualService does not apply to the request at all: its hosts or gateways do not
+    /// match. The rules are not evaluated in that case.
+    pub skipped: Vec<Mismatch>,
+
+    pub rules: Vec<RuleExplanation>,
+}
+
+impl Explanation {
+    /// The index of the rule handling the request.
+    pub fn selected(&self) -> Option<usize> {
+        self.rules
+            .iter()
+            .find(|rule| rule.outcome == RuleOutcome::Selected)
+            .map(|rule| rule.index)
+    }
+}
+
+/// The HTTP rule of `vs` handling `request`, if any.
+pub fn route<'a>(vs: &'a VirtualService, request: &HttpRequest) -> Option<&'a HttpRoute> {
+    let index = explain(vs, request).selected()?;
+    vs.spec.as_ref()?.http.as_ref()?.get(index)
+}
+
+/// Matches `request` against every HTTP rule of `vs`.
+pub fn explain(vs: &VirtualService, request: &HttpRequest) -> Explanation {
+    let mut explanation = Explanation {
+        skipped: Vec::new(),
+        rules: Vec::new(),
+    };
+    let spec = match &vs.spec {
+        Some(spec) => spec,
+        None => return explanation,
+    };
+    let namespace = vs.metadata.namespace.as_deref();
+    let hosts = spec.parse_hosts().unwrap_or_default();
+    let requested = Host::new(request.host()).ok();
+    if !requested
+        .as_ref()
+        .is_some_and(|requested| hosts.iter().any(|host| host.matches(requested)))
+    {
+        explanation.skipped.push(Mismatch {
+            clause: "hosts".to_string(),
+            expected: spec
+                .hosts
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            found: Some(request.host().to_string()),
+        });
+    }
+    if let Some(mismatch) = gateways(spec.gateways.as_ref(), namespace, request) {
+        explanation.skipped.push(mismatch);
+    }
+    if !explanation.skipped.is_empty() {
+        return explanation;
+    }
+
+    let mut selected = false;
+    for (index, rule) in spec.http.iter().flatten().enumerate() {
+        let matches: Vec<MatchExplanation> = rule
+            .r#match
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, block)| MatchExplanation {
+                index,
+                name: block.name.clone(),
+                mismatches: mismatches(block, namespace, request),
+            })
+            .collect();
+        let matched = matches.is_empty() || matches.iter().any(MatchExplanation::matched);
+        let outcome = match (matched, selected) {
+            (false, _) => RuleOutcome::NotMatched,
+            (true, false) => RuleOutcome::Selected,
+            (true, true) => RuleOutcome::Shadowed,
+        };
+        selected |= matched;
+        explanation.rules.push(RuleExplanation {
+            index,
+            name: rule.name.clone(),
+            outcome,
+            matches,
+        });
+    }
+    explanation
+}
+
+/// The clauses of `block` that `request` fails.
+fn mismatches(
+    block: &HttpMatchRequest,
+    namespace: Option<&str>,
+    request: &HttpRequest,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let ignore_case = block.ignore_uri_case.unwrap_or(false);
+    let mut check = |clause: String, condition: &StringMatch, value: Option<&str>, ignore_case| {
+        if !value.is_some_and(|value| string_matches(condition, value, ignore_case)) {
+            mismatches.push(Mismatch {
+                clause,
+                expected: describe(condition),
+                found: value.map(str::to_string),
+            });
+        }
+    };
+    if let Some(uri) = &block.uri {
+        check("uri".to_string(), uri, Some(request.path()), ignore_case);
+    }
+    if let Some(scheme) = &block.scheme {
+        check("scheme".to_string(), scheme, Some(&request.scheme), false);
+    }
+    if let Some(method) = &block.method {
+        check("method".to_string(), method, Some(&request.method), false);
+    }
+    if let Some(authority) = &block.authority {
+        check(
+            "authority".to_string(),
+            authority,
+            Some(&request.authority),
+            false,
+        );
+    }
+    for (name, header) in block.headers.iter().flatten() {
+        let value = request.headers.get(name).map(String::as_str);
+        check(format!("headers.{}", name), header, value, false);
+    }
+    for (name, param) in block.query_params.iter().flatten() {
+        check(
+            format!("queryParams.{}", name),
+            param,
+            request.query_param(name),
+            false,
+        );
+    }
+    for (name, header) in block.without_headers.iter().flatten() {
+        if let Some(value) = request.headers.get(name) {
+            if string_matches(header, value, false) {
+                mismatches.push(Mismatch {
+                    clause: format!("withoutHeaders.{}", name),
+                    expected: format!("not {}", describe(header)),
+                    found: Some(value.clone()),
+                });
+            }
+        }
+    }
+    if let Some(port) = block.port {
+        if request.port != Some(port) {
+            mismatches.push(Mismatch {
+                clause: "port".to_string(),
+                expected: port.to_string(),
+                found: request.port.map(|port| port.to_string()),
+            });
+        }
+    }
+    for (key, value) in block.source_labels.iter().flatten() {
+        let found = request.source_labels.get(key);
+        if found != Some(value) {
+            mismatches.push(Mismatch {
+                clause: format!("sourceLabels.{}", key),
+                expected: value.clone(),
+                found: found.cloned(),
+            });
+        }
+    }
+    if let Some(source_namespace) = &block.source_namespace {
+        if request.source_namespace.as_ref() != Some(source_namespace) {
+            mismatches.push(Mismatch {
+                clause: "sourceNamespace".to_string(),
+                expected: source_namespace.clone(),
+                found: request.source_namespace.clone(),
+            });
+        }
+    }
+    // The gateways of the VirtualService were checked before the rules; the ones of a match block
+    // narrow them down.
+    if block.gateways.is_some() {
+        mismatches.extend(gateways(block.gateways.as_ref(), namespace, request));
+    }
+    mismatches
+}
+
+/// A mismatch if the gateway of `request` is not one of `gateways`, which default to the mesh.
+fn gateways(
+    gateways: Option<&Vec<String>>,
+    namespace: Option<&str>,
+    request: &HttpRequest,
+) -> Option<Mismatch> {
+    let default_gateways = vec![MESH_GATEWAY.to_string()];
+    let gateways = gateways.unwrap_or(&default_gateways);
+    let applies = gateways.iter().any(|gateway| match &request.gateway {
+        None => gateway == MESH_GATEWAY,
+        Some(received) => {
+            gateway != MESH_GATEWAY && ResourceRef::parse(gateway, namespace) == *received
+        }
+    });
+    if applies {
+        return None;
+    }
+    Some(Mismatch {
+        clause: "gateways".to_string(),
+        expected: gateways.join(", "),
+        found: Some(
+            request
+                .gateway
+                .as_ref()
+                .map_or_else(|| MESH_GATEWAY.to_string(), ToString::to_string),
+        ),
+    })
+}
+
+/// Whether `value` satisfies `condition`. Regexes must match the whole value, as in Envoy; an
+/// invalid regex matches nothing. A regex is compiled on every call, see `StringMatcher`.
+pub fn string_matches(condition: &StringMatch, value: &str, ignore_case: bool) -> bool {
+    match condition {
+        StringMatch::Regex(regex) => anchored(regex).is_ok_and(|regex| regex.is_match(value)),
+        _ => literal_matches(condition, value, ignore_case),
+    }
+}
+
+fn literal_matches(condition: &StringMatch, value: &str, ignore_case: bool) -> bool {
+    match condition {
+        StringMatch::Exact(exact) if ignore_case => exact.eq_ignore_ascii_case(value),
+        StringMatch::Exact(exact) => exact == value,
+        StringMatch::Prefix(prefix) if ignore_case => value
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+        StringMatch::Prefix(prefix) => value.starts_with(prefix.as_str()),
+        StringMatch::Regex(_) => false,
+    }
+}
+
+fn anchored(regex: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", regex))
+}
+
+/// The evaluation of a `StringMatch`, available with the `simulation` feature, which brings in the
+/// regex engine.
+impl StringMatch {
+    /// Whether `value` satisfies the match: exact, prefix or a RE2 regex anchored at both ends. The
+    /// regex is compiled on every call; use a `StringMatcher` to match many values.
+    pub fn matches(&self, value: &str) -> bool {
+        string_matches(self, value, false)
+    }
+
+    /// Like `matches`, but compares exact and prefix matches ignoring ASCII case, as
+    /// `ignoreUriCase` does. Regexes are unaffected; use `(?i)` in the pattern instead.
+    pub fn matches_ignore_case(&self, value: &str) -> bool {
+        string_matches(self, value, true)
+    }
+}
+
+/// # StringMatcher
+/// A `StringMatch` with its regex compiled once, to match many values.
+#[derive(Clone, Debug)]
+pub struct StringMatcher {
+    condition: StringMatch,
+    regex: Option<Regex>,
+}
+
+impl StringMatcher {
+    /// Fails if the regex of `condition` is invalid.
+    pub fn new(condition: &StringMatch) -> Result<Self, regex::Error> {
+        let regex = match condition {
+            StringMatch::Regex(regex) => Some(anchored(regex)?),
+            _ => None,
+        };
+        Ok(StringMatcher {
+            condition: condition.clone(),
+            regex,
+        })
+    }
+
+    pub fn condition(&self) -> &StringMatch {
+        &self.condition
+    }
+
+    /// Same as `StringMatch::matches`.
+    pub fn matches(&self, value: &str) -> bool {
+        self.matches_with(value, false)
+    }
+
+    /// Same as `StringMatch::matches_ignore_case`.
+    pub fn matches_ignore_case(&self, value: &str) -> bool {
+        self.matches_with(value, true)
+    }
+
+    fn matches_with(&self, value: &str, ignore_case: bool) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(value),
+            None => literal_matches(&self.condition, value, ignore_case),
+        }
+    }
+}
+
+fn describe(condition: &StringMatch) -> String {
+    match condition {
+        StringMatch::Exact(exact) => format!("exact {}", exact),
+        StringMatch::Prefix(prefix) => format!("prefix {}", prefix),
+        StringMatch::Regex(regex) => format!("regex {}", regex),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::istio::weight::Weight;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn virtual_service(spec: Value) -> VirtualService {
+        serde_json::from_value(json!({
+            "metadata": { "name": "reviews", "namespace": "shop" },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn reviews() -> VirtualService {
+        virtual_service(json!({
+            "hosts": ["reviews.shop.svc.cluster.local"],
+            "http": [
+                {
+                    "name": "jason",
+                    "match": [{ "name": "user", "headers": { "end-user": { "exact": "jason" } } }],
+                    "route": [{ "destination": { "host": "reviews", "subset": "v2" } }],
+                },
+                {
+                    "name": "api",
+                    "match": [
+                        { "name": "v1", "uri": { "prefix": "/api/v1" } },
+                        { "name": "v2", "uri": { "regex": "/api/v2/[a-z]+" }, "method": { "exact": "POST" } },
+                    ],
+                    "route": [{ "destination": { "host": "reviews", "subset": "v3" } }],
+                },
+                {
+                    "name": "default",
+                    "route": [
+                        { "destination": { "host": "reviews", "subset": "v1" }, "weight": 80 },
+                        { "destination": { "host": "reviews", "subset": "v2" }, "weight": 20 },
+                    ],
+                },
+            ],
+        }))
+    }
+
+    fn outcomes(explanation: &Explanation) -> Vec<RuleOutcome> {
+        explanation.rules.iter().map(|rule| rule.outcome).collect()
+    }
+
+    #[test]
+    fn the_first_matching_rule_handles_the_request() {
+        let vs = reviews();
+        let request = HttpRequest::get("reviews.shop.svc.cluster.local", "/api/v1/books?page=2")
+            .with_header("End-User", "jason");
+        let explanation = explain(&vs, &request);
+        assert!(explanation.skipped.is_empty());
+        assert_eq!(explanation.selected(), Some(0));
+        assert_eq!(
+            outcomes(&explanation),
+            vec![
+                RuleOutcome::Selected,
+                RuleOutcome::Shadowed,
+                RuleOutcome::Shadowed,
+            ]
+        );
+        assert_eq!(
+            route(&vs, &request).and_then(|rule| rule.name.as_deref()),
+            Some("jason")
+        );
+
+        let request = HttpRequest::get("reviews.shop.svc.cluster.local:9080", "/api/v1/books");
+        assert_eq!(
+            route(&vs, &request).and_then(|rule| rule.name.as_deref()),
+            Some("api")
+        );
+    }
+
+    #[test]
+    fn any_match_block_selects_the_rule() {
+        let vs = reviews();
+        let request =
+            HttpRequest::get("reviews.shop.svc.cluster.local", "/api/v2/books").with_method("POST");
+        let explanation = explain(&vs, &request);
+        assert_eq!(explanation.selected(), Some(1));
+        let blocks = &explanation.rules[1].matches;
+        assert!(!blocks[0].matched());
+        assert!(blocks[1].matched());
+    }
+
+    #[test]
+    fn unmatched_requests_fall_through_to_the_default_rule_and_its_weights() {
+        let vs = reviews();
+        let request = HttpRequest::get("reviews.shop.svc.cluster.local", "/api/v2/books");
+        let explanation = explain(&vs, &request);
+        assert_eq!(
+            outcomes(&explanation),
+            vec![
+                RuleOutcome::NotMatched,
+                RuleOutcome::NotMatched,
+                RuleOutcome::Selected,
+            ]
+        );
+        assert_eq!(
+            explanation.rules[0].matches[0].mismatches,
+            vec![Mismatch {
+                clause: "headers.end-user".to_string(),
+                expected: "exact jason".to_string(),
+                found: None,
+            }]
+        );
+        assert_eq!(
+            explanation.rules[1].matches[0].mismatches[0].to_string(),
+            "uri: expected prefix /api/v1, found /api/v2/books"
+        );
+        assert_eq!(
+            explanation.rules[1].matches[1].mismatches[0].to_string(),
+            "method: expected exact POST, found GET"
+        );
+        // A rule without match blocks matches everything.
+        assert!(explanation.rules[2].matches.is_empty());
+
+        let destinations = route(&vs, &request).unwrap().route.as_ref().unwrap();
+        let weights: Vec<(Option<&str>, u32)> = destinations
+            .iter()
+            .map(|route| {
+                (
+                    route.destination.subset.as_deref(),
+                    route.weight.map_or(0, Weight::get),
+                )
+            })
+            .collect();
+        assert_eq!(weights, vec![(Some("v1"), 80), (Some("v2"), 20)]);
+    }
+
+    #[test]
+    fn requests_for_other_hosts_or_gateways_are_skipped() {
+        let vs = reviews();
+        let explanation = explain(
+            &vs,
+            &HttpRequest::get("ratings.shop.svc.cluster.local", "/"),
+        );
+        assert_eq!(
+            explanation.skipped,
+            vec![Mismatch {
+                clause: "hosts".to_string(),
+                expected: "reviews.shop.svc.cluster.local".to_string(),
+                found: Some("ratings.shop.svc.cluster.local".to_string()),
+            }]
+        );
+        assert!(explanation.rules.is_empty());
+        assert_eq!(explanation.selected(), None);
+
+        // The VirtualService binds to the mesh only.
+        let request = HttpRequest::get("reviews.shop.svc.cluster.local", "/")
+            .via_gateway("istio-system", "ingress");
+        let explanation = explain(&vs, &request);
+        assert_eq!(explanation.skipped[0].clause, "gateways");
+        assert!(route(&vs, &request).is_none());
+    }
+
+    #[test]
+    fn match_blocks_narrow_down_the_gateways() {
+        let vs = virtual_service(json!({
+            "hosts": ["*.example.com"],
+            "gateways": ["ingress", "mesh"],
+            "http": [
+                {
+                    "name": "external",
+                    "match": [{ "gateways": ["shop/ingress"] }],
+                    "route": [{ "destination": { "host": "web" } }],
+                },
+                { "name": "internal", "route": [{ "destination": { "host": "web-internal" } }] },
+            ],
+        }));
+        let request = HttpRequest::get("www.example.com", "/").via_gateway("shop", "ingress");
+        assert_eq!(
+            route(&vs, &request).and_then(|rule| rule.name.as_deref()),
+            Some("external")
+        );
+        let request = HttpRequest::get("www.example.com", "/");
+        assert_eq!(
+            route(&vs, &request).and_then(|rule| rule.name.as_deref()),
+            Some("internal")
+        );
+    }
+
+    #[test]
+    fn string_matches_exact_prefix_and_whole_regex() {
+        let exact = StringMatch::Exact("/api".to_string());
+        assert!(exact.matches("/api"));
+        assert!(!exact.matches("/api/v1"));
+        assert!(!exact.matches("/API"));
+
+        let prefix = StringMatch::Prefix("/api".to_string());
+        assert!(prefix.matches("/api/v1"));
+        assert!(prefix.matches("/api"));
+        assert!(!prefix.matches("/ap"));
+
+        let regex = StringMatch::Regex("/users/[0-9]+".to_string());
+        assert!(regex.matches("/users/42"));
+        assert!(!regex.matches("/users/42/orders"));
+        assert!(!regex.matches("/v1/users/42"));
+
+        let invalid = StringMatch::Regex("(".to_string());
+        assert!(!invalid.matches("("));
+    }
+
+    #[test]
+    fn ignore_case_applies_to_exact_and_prefix_only() {
+        assert!(StringMatch::Exact("/Api".to_string()).matches_ignore_case("/aPI"));
+        assert!(StringMatch::Prefix("/API".to_string()).matches_ignore_case("/api/v1"));
+        assert!(!StringMatch::Prefix("/api/v1".to_string()).matches_ignore_case("/API"));
+        // A prefix ending inside a multi-byte character does not panic.
+        assert!(!StringMatch::Prefix("/a".to_string()).matches_ignore_case("/é"));
+
+        let regex = StringMatch::Regex("/api".to_string());
+        assert!(!regex.matches_ignore_case("/API"));
+        assert!(StringMatch::Regex("(?i)/api".to_string()).matches("/API"));
+    }
+
+    #[test]
+    fn matcher_compiles_the_regex_once() {
+        let matcher = StringMatcher::new(&StringMatch::Regex("v[12]".to_string())).unwrap();
+        assert!(matcher.matches("v1"));
+        assert!(matcher.matches("v2"));
+        assert!(!matcher.matches("v3"));
+        assert_eq!(
+            matcher.condition(),
+            &StringMatch::Regex("v[12]".to_string())
+        );
+
+        let matcher = StringMatcher::new(&StringMatch::Prefix("/API".to_string())).unwrap();
+        assert!(matcher.matches_ignore_case("/api/v1"));
+        assert!(!matcher.matches("/api/v1"));
+
+        assert!(StringMatcher::new(&StringMatch::Regex("(".to_string())).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// The status istiod writes back to the networking resources when status reporting is enabled
+// (`PILOT_ENABLE_STATUS`): the reconciliation conditions and the messages of the analyzers. Keeping
+// it typed instead of dropping it lets a resource be read, changed and written back without losing
+// what the control plane reported.
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+
+/// The condition istiod sets once the resource has been distributed to the proxies.
+pub const RECONCILED: &str = "Reconciled";
+
+/// # IstioStatus
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioStatus {
+    // Current service state of the resource. More info: https://kubernetes.io/docs/concepts/workloads/pods/pod-lifecycle/#pod-conditions
+    // No
+    pub conditions: Option<Vec<IstioCondition>>,
+
+    // Includes any errors or warnings detected by Istio's analyzers.
+    // No
+    #[serde(rename = "validationMessages")]
+    pub validation_messages: Option<Vec<AnalysisMessageBase>>,
+
+    // Resource Generation to which the Reconciled Condition refers. When this value is not equal to the object's metadata generation, reconciled condition calculation for the current generation is still in progress. Written as a string by istiod.
+    // No
+    #[serde(rename = "observedGeneration")]
+    pub observed_generation: Option<IntOrString>,
+}
+
+impl IstioStatus {
+    pub fn condition(&self, r#type: &str) -> Option<&IstioCondition> {
+        self.conditions
+            .iter()
+            .flatten()
+            .find(|condition| condition.r#type.as_deref() == Some(r#type))
+    }
+
+    pub fn observed_generation(&self) -> Option<i64> {
+        match self.observed_generation.as_ref()? {
+            IntOrString::Int(generation) => Some(i64::from(*generation)),
+            IntOrString::String(generation) => generation.parse().ok(),
+        }
+    }
+
+    /// Whether `generation` of the resource has been distributed to all proxies.
+    pub fn is_reconciled(&self, generation: Option<i64>) -> bool {
+        self.observed_generation() == generation
+            && self
+                .condition(RECONCILED)
+                .is_some_and(|condition| condition.status.as_deref() == Some("True"))
+    }
+
+    /// The messages of the analyzers with `level` `ERROR`.
+    pub fn errors(&self) -> impl Iterator<Item = &AnalysisMessageBase> {
+        self.validation_messages
+            .iter()
+            .flatten()
+            .filter(|message| message.level == Some(Level::ERROR))
+    }
+}
+
+/// # IstioCondition
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IstioCondition {
+    // Type is the type of the condition.
+    // No
+    pub r#type: Option<String>,
+
+    // Status is the status of the condition. Can be True, False, Unknown.
+    // No
+    pub status: Option<String>,
+
+    // Last time we probed the condition.
+    // No
+    #[serde(rename = "lastProbeTime")]
+    pub last_probe_time: Option<Time>,
+
+    // Last time the condition transitioned from one status to another.
+    // No
+    #[serde(rename = "lastTransitionTime")]
+    pub last_transition_time: Option<Time>,
+
+    // Unique, one-word, CamelCase reason for the condition's last transition.
+    // No
+    pub reason: Option<String>,
+
+    // Human-readable message indicating details about last transition.
+    // No
+    pub message: Option<String>,
+}
+
+/// # AnalysisMessageBase
+/// The common fields of the messages reported by the Istio analyzers, e.g. `IST0101`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisMessageBase {
+    // No
+    pub r#type: Option<AnalysisMessageType>,
+
+    // Represents how severe a message is.
+    // No
+    pub level: Option<Level>,
+
+    // A url pointing to the Istio documentation for this specific error type. Should be of the form ^http(s)?://(www\.)?istio.io/docs/reference/config/analysis/(?P<code>[0-9]+)/$
+    // No
+    #[serde(rename = "documentationUrl")]
+    pub documentation_url: Option<String>,
+}
+
+/// # AnalysisMessageBase.Type
+/// A unique identifier for the type of message. Name is intended to be human-readable, code is
+/// intended to be machine readable.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisMessageType {
+    // A human-readable name for the message type. e.g. "InternalError", "PodMissingProxy".
+    // No
+    pub name: Option<String>,
+
+    // A 7 character code matching ^IST[0-9]{4}$ intended to uniquely identify the message type. (e.g. "IST0001" is mapped to the "InternalError" message type.)
+    // No
+    pub code: Option<String>,
+}
+
+/// # Level
+/// The values here are chosen so that more severe messages get sorted higher, as well as leaving
+/// space in between to add more later.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Level {
+    UNKNOWN,
+    ERROR,
+    WARNING,
+    INFO,
+}
@@ -0,0 +1,374 @@
+// Index of the subsets and traffic policies declared by DestinationRules, keyed by host. When
+// several DestinationRules target the same host they are merged the way istiod does: rules are
+// taken oldest first, the oldest rule setting a top-level traffic policy provides it and the
+// subsets of later rules are added unless a subset of the same name already exists. Everything
+// dropped by the merge is reported as a conflict.
+use crate::istio::destination_rule::{DestinationRule, Subset, TrafficPolicy};
+use crate::istio::topology::{creation_order, ResourceRef};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// # RegisteredSubset
+/// A subset together with the DestinationRule declaring it.
+#[derive(Clone, Debug)]
+pub struct RegisteredSubset<'a> {
+    pub subset: &'a Subset,
+    pub destination_rule: ResourceRef,
+}
+
+/// # HostEntry
+/// The merged DestinationRule configuration of a host.
+#[derive(Clone, Debug)]
+pub struct HostEntry<'a> {
+    pub host: String,
+
+    /// The DestinationRules targeting the host, oldest first.
+    pub destination_rules: Vec<ResourceRef>,
+
+    /// The top-level traffic policy of the oldest DestinationRule setting one.
+    pub traffic_policy: Option<&'a TrafficPolicy>,
+
+    /// The DestinationRule providing `traffic_policy`.
+    pub traffic_policy_from: Option<ResourceRef>,
+
+    pub subsets: Vec<RegisteredSubset<'a>>,
+}
+
+impl<'a> HostEntry<'a> {
+    pub fn subset(&self, name: &str) -> Option<&RegisteredSubset<'a>> {
+        self.subsets.iter().find(|s| s.subset.name == name)
+    }
+
+    /// The traffic policy applied to traffic sent to `subset`, or to the host when no subset is
+    /// given. Settings of the subset policy override the top-level settings field by field. `None`
+    /// when neither sets a policy.
+    pub fn effective_policy(&self, subset: Option<&str>) -> Option<TrafficPolicy> {
+        let top = self.traffic_policy.cloned();
+        let subset = subset
+            .and_then(|name| self.subset(name))
+            .and_then(|registered| registered.subset.traffic_policy.clone());
+        let (subset, top) = match (subset, top) {
+            (Some(subset), Some(top)) => (subset, top),
+            (subset, top) => return subset.or(top),
+        };
+        Some(TrafficPolicy {
+            load_balancer: subset.load_balancer.or(top.load_balancer),
+            connection_pool: subset.connection_pool.or(top.connection_pool),
+            outlier_detection: subset.outlier_detection.or(top.outlier_detection),
+            tls: subset.tls.or(top.tls),
+            port_level_settings: subset.port_level_settings.or(top.port_level_settings),
+            tunnel: subset.tunnel.or(top.tunnel),
+            proxy_protocol: subset.proxy_protocol.or(top.proxy_protocol),
+            extra: top.extra.into_iter().chain(subset.extra).collect(),
+        })
+    }
+}
+
+/// # RegistryConflict
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryConflict {
+    /// A subset name is declared by several DestinationRules of a host. Only the oldest
+    /// declaration is used.
+    DuplicateSubset {
+        host: String,
+        name: String,
+        kept: ResourceRef,
+        ignored: ResourceRef,
+    },
+
+    /// A DestinationRule sets a top-level traffic policy for a host that already has one from an
+    /// older DestinationRule. The newer policy is ignored.
+    IgnoredTrafficPolicy {
+        host: String,
+        kept: ResourceRef,
+        ignored: ResourceRef,
+    },
+}
+
+impl fmt::Display for RegistryConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryConflict::DuplicateSubset {
+                host,
+                name,
+                kept,
+                ignored,
+            } => write!(
+                f,
+                "subset {} of {} in {} is ignored, it is already declared by {}",
+                name, host, ignored, kept
+            ),
+            RegistryConflict::IgnoredTrafficPolicy {
+                host,
+                kept,
+                ignored,
+            } => write!(
+                f,
+                "traffic policy of {} in {} is ignored, {} already sets one",
+                host, ignored, kept
+            ),
+        }
+    }
+}
+
+/// # SubsetRegistry
+#[derive(Clone, Debug, Default)]
+pub struct SubsetRegistry<'a> {
+    hosts: BTreeMap<String, HostEntry<'a>>,
+    conflicts: Vec<RegistryConflict>,
+}
+
+impl<'a> SubsetRegistry<'a> {
+    pub fn new(destination_rules: impl IntoIterator<Item = &'a DestinationRule>) -> Self {
+        let mut rules: Vec<&DestinationRule> = destination_rules
+            .into_iter()
+            .filter(|dr| dr.spec.is_some())
+            .collect();
+        rules.sort_by_key(|dr| creation_order(&dr.metadata));
+
+        let mut registry = SubsetRegistry::default();
+        for rule in rules {
+            let spec = rule.spec.as_ref().expect("filtered on spec");
+            let rule_ref = ResourceRef::from_metadata(&rule.metadata);
+            let entry = registry
+                .hosts
+                .entry(spec.host.clone())
+                .or_insert_with(|| HostEntry {
+                    host: spec.host.clone(),
+                    destination_rules: Vec::new(),
+                    traffic_policy: None,
+                    traffic_policy_from: None,
+                    subsets: Vec::new(),
+                });
+            // Like istiod, a later policy is only adopted while no older rule has set one, even
+            // an empty one.
+            match (&entry.traffic_policy_from, &spec.traffic_policy) {
+                (None, Some(policy)) => {
+                    entry.traffic_policy = Some(policy);
+                    entry.traffic_policy_from = Some(rule_ref.clone());
+                }
+                (Some(kept), Some(policy)) if !is_empty(policy) => {
+                    registry
+                        .conflicts
+                        .push(RegistryConflict::IgnoredTrafficPolicy {
+                            host: spec.host.clone(),
+                            kept: kept.clone(),
+                            ignored: rule_ref.clone(),
+                        });
+                }
+                _ => {}
+            }
+            entry.destination_rules.push(rule_ref.clone());
+
+            for subset in spec.subsets.iter().flatten() {
+                match entry.subset(&subset.name) {
+                    Some(existing) => registry.conflicts.push(RegistryConflict::DuplicateSubset {
+                        host: spec.host.clone(),
+                        name: subset.name.clone(),
+                        kept: existing.destination_rule.clone(),
+                        ignored: rule_ref.clone(),
+                    }),
+                    None => entry.subsets.push(RegisteredSubset {
+                        subset,
+                        destination_rule: rule_ref.clone(),
+                    }),
+                }
+            }
+        }
+        registry
+    }
+
+    /// The entry of the DestinationRules declared for exactly `host`.
+    pub fn get(&self, host: &str) -> Option<&HostEntry<'a>> {
+        self.hosts.get(host)
+    }
+
+    /// The entry applying to `host`: the exact entry if there is one, otherwise the entry of the
+    /// most specific wildcard host (e.g. `*.example.com`) matching it.
+    pub fn lookup(&self, host: &str) -> Option<&HostEntry<'a>> {
+        self.get(host).or_else(|| {
+            self.hosts
+                .values()
+                .filter(|entry| match entry.host.strip_prefix('*') {
+                    Some(suffix) => host.ends_with(suffix),
+                    None => false,
+                })
+                .max_by_key(|entry| entry.host.len())
+        })
+    }
+
+    /// The subset `name` of the DestinationRules applying to `host`.
+    pub fn subset(&self, host: &str, name: &str) -> Option<&RegisteredSubset<'a>> {
+        self.lookup(host).and_then(|entry| entry.subset(name))
+    }
+
+    pub fn hosts(&self) -> impl Iterator<Item = &HostEntry<'a>> + '_ {
+        self.hosts.values()
+    }
+
+    pub fn conflicts(&self) -> &[RegistryConflict] {
+        &self.conflicts
+    }
+}
+
+/// Whether the policy sets nothing. The policy is destructured without `..`, so a field added to
+/// `TrafficPolicy` has to be considered here.
+fn is_empty(policy: &TrafficPolicy) -> bool {
+    let TrafficPolicy {
+        load_balancer,
+        connection_pool,
+        outlier_detection,
+        tls,
+        port_level_settings,
+        tunnel,
+        proxy_protocol,
+        extra,
+    } = policy;
+    load_balancer.is_none()
+        && connection_pool.is_none()
+        && outlier_detection.is_none()
+        && tls.is_none()
+        && port_level_settings.is_none()
+        && tunnel.is_none()
+        && proxy_protocol.is_none()
+        && extra.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn rule(name: &str, created: &str, spec: Value) -> DestinationRule {
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": "default", "creationTimestamp": created },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn reference(name: &str) -> ResourceRef {
+        ResourceRef::new(Some("default"), name)
+    }
+
+    #[test]
+    fn merges_subsets_oldest_first() {
+        let old = rule(
+            "old",
+            "2022-01-01T00:00:00Z",
+            json!({ "host": "reviews", "subsets": [{ "name": "v1", "labels": { "version": "v1" } }] }),
+        );
+        let new = rule(
+            "new",
+            "2022-01-02T00:00:00Z",
+            json!({
+                "host": "reviews",
+                "subsets": [
+                    { "name": "v1", "labels": { "version": "other" } },
+                    { "name": "v2", "labels": { "version": "v2" } },
+                ],
+            }),
+        );
+        let registry = SubsetRegistry::new([&new, &old]);
+        let entry = registry.get("reviews").unwrap();
+        assert_eq!(
+            entry.destination_rules,
+            [reference("old"), reference("new")]
+        );
+        assert_eq!(
+            registry.subset("reviews", "v1").unwrap().destination_rule,
+            reference("old")
+        );
+        assert_eq!(
+            registry.subset("reviews", "v2").unwrap().destination_rule,
+            reference("new")
+        );
+        assert_eq!(
+            registry.conflicts(),
+            [RegistryConflict::DuplicateSubset {
+                host: "reviews".to_string(),
+                name: "v1".to_string(),
+                kept: reference("old"),
+                ignored: reference("new"),
+            }]
+        );
+    }
+
+    #[test]
+    fn adopts_the_policy_of_a_later_rule_when_older_rules_set_none() {
+        let old = rule("old", "2022-01-01T00:00:00Z", json!({ "host": "reviews" }));
+        let new = rule(
+            "new",
+            "2022-01-02T00:00:00Z",
+            json!({ "host": "reviews", "trafficPolicy": { "loadBalancer": { "simple": "RANDOM" } } }),
+        );
+        let registry = SubsetRegistry::new([&old, &new]);
+        let entry = registry.get("reviews").unwrap();
+        assert!(entry.traffic_policy.unwrap().load_balancer.is_some());
+        assert_eq!(entry.traffic_policy_from, Some(reference("new")));
+        assert!(registry.conflicts().is_empty());
+    }
+
+    #[test]
+    fn reports_policies_ignored_because_an_older_rule_sets_one() {
+        let old = rule(
+            "old",
+            "2022-01-01T00:00:00Z",
+            json!({ "host": "reviews", "trafficPolicy": { "tls": { "mode": "ISTIO_MUTUAL" } } }),
+        );
+        // A policy with only a tunnel is not empty.
+        let new = rule(
+            "new",
+            "2022-01-02T00:00:00Z",
+            json!({ "host": "reviews", "trafficPolicy": { "tunnel": { "targetHost": "proxy", "targetPort": 8080 } } }),
+        );
+        let registry = SubsetRegistry::new([&old, &new]);
+        assert!(registry
+            .get("reviews")
+            .unwrap()
+            .traffic_policy
+            .unwrap()
+            .tunnel
+            .is_none());
+        assert_eq!(
+            registry.conflicts(),
+            [RegistryConflict::IgnoredTrafficPolicy {
+                host: "reviews".to_string(),
+                kept: reference("old"),
+                ignored: reference("new"),
+            }]
+        );
+    }
+
+    #[test]
+    fn subset_policies_override_the_top_level_policy_field_by_field() {
+        let dr = rule(
+            "reviews",
+            "2022-01-01T00:00:00Z",
+            json!({
+                "host": "*.example.com",
+                "trafficPolicy": {
+                    "loadBalancer": { "simple": "RANDOM" },
+                    "tls": { "mode": "ISTIO_MUTUAL" },
+                },
+                "subsets": [{
+                    "name": "v2",
+                    "labels": { "version": "v2" },
+                    "trafficPolicy": { "loadBalancer": { "simple": "LEAST_CONN" } },
+                }],
+            }),
+        );
+        let registry = SubsetRegistry::new([&dr]);
+        let entry = registry.lookup("reviews.example.com").unwrap();
+        let policy = entry.effective_policy(Some("v2")).unwrap();
+        assert_eq!(
+            serde_json::to_value(&policy).unwrap(),
+            json!({
+                "loadBalancer": { "simple": "LEAST_CONN" },
+                "tls": { "mode": "ISTIO_MUTUAL" },
+            })
+        );
+        assert_eq!(entry.effective_policy(None), entry.traffic_policy.cloned());
+        assert!(registry.lookup("example.org").is_none());
+    }
+}
@@ -0,0 +1,752 @@
+// Typed graph of the routing configuration in a mesh. The graph links gateways (and the reserved
+// `mesh` gateway) to the hosts bound to them, the hosts to the route rules declared for them in
+// VirtualServices, and the route rules to the destinations and DestinationRule subsets they send
+// traffic to. Destinations of hosts declared by a ServiceEntry are linked to it. It is intended
+// for visualization and impact-analysis tooling.
+use crate::istio::binding::hosts_intersect;
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::gateway::Gateway;
+use crate::istio::service_entry::ServiceEntry;
+use crate::istio::subset_registry::SubsetRegistry;
+use crate::istio::virtual_service::{Destination, VirtualService};
+use crate::istio::weight::Weight;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Name of the reserved gateway that represents all sidecars in the mesh.
+pub const MESH_GATEWAY: &str = "mesh";
+
+/// # ResourceRef
+/// Reference to a namespaced resource by namespace and name.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResourceRef {
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl ResourceRef {
+    pub fn new(namespace: Option<&str>, name: &str) -> Self {
+        ResourceRef {
+            namespace: namespace.map(str::to_string),
+            name: name.to_string(),
+        }
+    }
+
+    /// Builds a reference from the namespace and name of an object's metadata.
+    pub fn from_metadata(metadata: &ObjectMeta) -> Self {
+        ResourceRef {
+            namespace: metadata.namespace.clone(),
+            name: metadata.name.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Parses a `<namespace>/<name>` reference, defaulting the namespace to `namespace` when the
+    /// value carries no namespace qualifier (as done for the `gateways` of a VirtualService).
+    pub fn parse(value: &str, namespace: Option<&str>) -> Self {
+        match value.split_once('/') {
+            Some((ns, name)) => ResourceRef::new(Some(ns), name),
+            None => ResourceRef::new(namespace, value),
+        }
+    }
+}
+
+/// Sort key ordering objects by creation time, oldest first, then by namespace and name. Objects
+/// without a creation timestamp (e.g. loaded from files) sort after all created objects.
+pub(crate) fn creation_order(metadata: &ObjectMeta) -> (bool, Option<DateTime<Utc>>, ResourceRef) {
+    let created = metadata.creation_timestamp.as_ref().map(|time| time.0);
+    (
+        created.is_none(),
+        created,
+        ResourceRef::from_metadata(metadata),
+    )
+}
+
+impl fmt::Display for ResourceRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}/{}", namespace, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// # RouteProtocol
+/// The route list of a VirtualService a route rule is declared in.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RouteProtocol {
+    #[serde(rename = "http")]
+    Http,
+    #[serde(rename = "tls")]
+    Tls,
+    #[serde(rename = "tcp")]
+    Tcp,
+}
+
+/// # NodeId
+/// Index of a node in a `Topology`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeId(pub usize);
+
+/// # Node
+/// A vertex of the topology graph. Nodes are unique by value, so the same host or destination
+/// referenced by several resources is represented by a single node.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum Node {
+    /// The reserved `mesh` gateway, i.e. all sidecars in the mesh.
+    Mesh,
+
+    /// A gateway, either provided as a resource or referenced by a VirtualService.
+    Gateway { gateway: ResourceRef },
+
+    /// A host addressed by a VirtualService.
+    Host { host: String },
+
+    /// A route rule of a VirtualService, identified by its position in the protocol specific list.
+    RouteRule {
+        #[serde(rename = "virtualService")]
+        virtual_service: ResourceRef,
+        protocol: RouteProtocol,
+        index: usize,
+        name: Option<String>,
+    },
+
+    /// A destination traffic is forwarded or mirrored to.
+    Destination {
+        host: String,
+        subset: Option<String>,
+        port: Option<u32>,
+    },
+
+    /// A named subset declared by a DestinationRule.
+    Subset {
+        #[serde(rename = "destinationRule")]
+        destination_rule: ResourceRef,
+        host: String,
+        name: String,
+    },
+
+    /// A ServiceEntry adding hosts to the service registry of the mesh.
+    ServiceEntry {
+        #[serde(rename = "serviceEntry")]
+        service_entry: ResourceRef,
+    },
+}
+
+/// # EdgeKind
+/// The relation between two nodes together with its metadata.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EdgeKind {
+    /// Gateway (or mesh) → host, bound by the given VirtualService.
+    Binds {
+        #[serde(rename = "virtualService")]
+        virtual_service: ResourceRef,
+    },
+
+    /// Host → route rule declared for the host.
+    Routes,
+
+    /// Route rule → route rule of a delegate VirtualService.
+    Delegates,
+
+    /// Route rule → destination receiving the routed traffic.
+    Forwards { weight: Option<Weight> },
+
+    /// Route rule → destination receiving a copy of the traffic.
+    Mirrors { percentage: Option<f32> },
+
+    /// Destination → subset selected by it.
+    Selects,
+
+    /// Destination → ServiceEntry declaring its host.
+    Resolves,
+}
+
+/// # Edge
+/// A directed edge of the topology graph.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Edge {
+    pub from: NodeId,
+    pub to: NodeId,
+    #[serde(flatten)]
+    pub kind: EdgeKind,
+}
+
+/// # TopologyError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TopologyError {
+    /// Two VirtualServices share a namespace and name, e.g. manifests loaded without
+    /// `metadata.namespace`, so delegations to them and their route rules are ambiguous.
+    DuplicateVirtualService(ResourceRef),
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyError::DuplicateVirtualService(vs) => {
+                write!(f, "VirtualService {} is given more than once", vs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+/// # Topology
+/// Graph of gateways → hosts → route rules → destinations/subsets built from a set of resources.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Topology {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    #[serde(skip)]
+    index: HashMap<Node, NodeId>,
+}
+
+impl Topology {
+    pub fn builder<'a>() -> TopologyBuilder<'a> {
+        TopologyBuilder::default()
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)
+    }
+
+    /// Returns the id of the given node if it is part of the graph.
+    pub fn find(&self, node: &Node) -> Option<NodeId> {
+        self.index.get(node).copied()
+    }
+
+    pub fn outgoing(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    pub fn incoming(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.to == id)
+    }
+
+    /// All nodes reachable from `id`, e.g. every destination a gateway can send traffic to.
+    pub fn descendants(&self, id: NodeId) -> Vec<NodeId> {
+        self.walk(id, |edge| (edge.from, edge.to))
+    }
+
+    /// All nodes `id` can be reached from, e.g. every gateway and rule affected by a change to a
+    /// destination.
+    pub fn ancestors(&self, id: NodeId) -> Vec<NodeId> {
+        self.walk(id, |edge| (edge.to, edge.from))
+    }
+
+    fn walk(&self, start: NodeId, direction: impl Fn(&Edge) -> (NodeId, NodeId)) -> Vec<NodeId> {
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut found = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            for edge in &self.edges {
+                let (from, to) = direction(edge);
+                if from == current && seen.insert(to) {
+                    found.push(to);
+                    queue.push_back(to);
+                }
+            }
+        }
+        found
+    }
+
+    fn insert(&mut self, node: Node) -> NodeId {
+        if let Some(id) = self.index.get(&node) {
+            return *id;
+        }
+        let id = NodeId(self.nodes.len());
+        self.index.insert(node.clone(), id);
+        self.nodes.push(node);
+        id
+    }
+
+    fn connect(&mut self, from: NodeId, to: NodeId, kind: EdgeKind) {
+        let edge = Edge { from, to, kind };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+}
+
+/// # TopologyBuilder
+/// Collects the resources a `Topology` is built from.
+#[derive(Clone, Debug, Default)]
+pub struct TopologyBuilder<'a> {
+    virtual_services: Vec<&'a VirtualService>,
+    destination_rules: Vec<&'a DestinationRule>,
+    gateways: Vec<&'a Gateway>,
+    service_entries: Vec<&'a ServiceEntry>,
+}
+
+impl<'a> TopologyBuilder<'a> {
+    pub fn virtual_services(mut self, items: impl IntoIterator<Item = &'a VirtualService>) -> Self {
+        self.virtual_services.extend(items);
+        self
+    }
+
+    pub fn destination_rules(
+        mut self,
+        items: impl IntoIterator<Item = &'a DestinationRule>,
+    ) -> Self {
+        self.destination_rules.extend(items);
+        self
+    }
+
+    pub fn gateways(mut self, items: impl IntoIterator<Item = &'a Gateway>) -> Self {
+        self.gateways.extend(items);
+        self
+    }
+
+    pub fn service_entries(mut self, items: impl IntoIterator<Item = &'a ServiceEntry>) -> Self {
+        self.service_entries.extend(items);
+        self
+    }
+
+    /// Builds the graph. Route rules are identified by the namespace and name of their
+    /// VirtualService, so two VirtualServices sharing both are rejected.
+    pub fn build(self) -> Result<Topology, TopologyError> {
+        let mut topology = Topology::default();
+        let registry = SubsetRegistry::new(self.destination_rules.iter().copied());
+
+        for gateway in &self.gateways {
+            topology.insert(Node::Gateway {
+                gateway: ResourceRef::from_metadata(&gateway.metadata),
+            });
+        }
+        for se in &self.service_entries {
+            topology.insert(Node::ServiceEntry {
+                service_entry: ResourceRef::from_metadata(&se.metadata),
+            });
+        }
+
+        // Rule nodes are created for every VirtualService up front, so delegating rules can be
+        // linked to the rules of their delegate regardless of input order. They are kept by the
+        // position of the VirtualService in the input.
+        let mut positions: HashMap<ResourceRef, usize> = HashMap::new();
+        let mut rules: Vec<Vec<NodeId>> = Vec::new();
+        for (position, vs) in self.virtual_services.iter().enumerate() {
+            let vs_ref = ResourceRef::from_metadata(&vs.metadata);
+            if positions.insert(vs_ref.clone(), position).is_some() {
+                return Err(TopologyError::DuplicateVirtualService(vs_ref));
+            }
+            rules.push(
+                rule_nodes(vs)
+                    .into_iter()
+                    .map(|node| topology.insert(node))
+                    .collect(),
+            );
+        }
+
+        for (vs, rule_ids) in self.virtual_services.iter().zip(&rules) {
+            let vs_ref = ResourceRef::from_metadata(&vs.metadata);
+            let spec = match &vs.spec {
+                Some(spec) => spec,
+                None => continue,
+            };
+
+            let mut host_ids = Vec::new();
+            for host in spec.hosts.iter().flatten() {
+                host_ids.push(topology.insert(Node::Host { host: host.clone() }));
+            }
+
+            let default_gateways = vec![MESH_GATEWAY.to_string()];
+            for gateway in spec.gateways.as_ref().unwrap_or(&default_gateways) {
+                let gateway_id =
+                    topology.insert(gateway_node(gateway, vs_ref.namespace.as_deref()));
+                for host_id in &host_ids {
+                    topology.connect(
+                        gateway_id,
+                        *host_id,
+                        EdgeKind::Binds {
+                            virtual_service: vs_ref.clone(),
+                        },
+                    );
+                }
+            }
+
+            for host_id in &host_ids {
+                for rule_id in rule_ids {
+                    topology.connect(*host_id, *rule_id, EdgeKind::Routes);
+                }
+            }
+
+            // `rule_nodes` yields a rule per route in http, tls, tcp order, so the routes are
+            // walked in the same order.
+            let mut rule_ids = rule_ids.iter().copied();
+            for (http, rule_id) in spec.http.iter().flatten().zip(rule_ids.by_ref()) {
+                for route in http.route.iter().flatten() {
+                    let destination =
+                        self.destination(&registry, &mut topology, &route.destination);
+                    topology.connect(
+                        rule_id,
+                        destination,
+                        EdgeKind::Forwards {
+                            weight: route.weight,
+                        },
+                    );
+                }
+                for mirror in http.mirror_policies() {
+                    let percentage = mirror.percentage.map(|percent| percent.value as f32);
+                    let destination =
+                        self.destination(&registry, &mut topology, &mirror.destination);
+                    topology.connect(rule_id, destination, EdgeKind::Mirrors { percentage });
+                }
+                if let Some(delegate) = &http.delegate {
+                    let delegate_ref = ResourceRef {
+                        namespace: delegate
+                            .namespace
+                            .clone()
+                            .or_else(|| vs_ref.namespace.clone()),
+                        name: delegate.name.clone().unwrap_or_default(),
+                    };
+                    let delegate_rules = positions
+                        .get(&delegate_ref)
+                        .map(|position| &rules[*position]);
+                    for delegate_rule in delegate_rules.into_iter().flatten() {
+                        topology.connect(rule_id, *delegate_rule, EdgeKind::Delegates);
+                    }
+                }
+            }
+            let l4_routes = spec
+                .tls
+                .iter()
+                .flatten()
+                .map(|tls| &tls.route)
+                .chain(spec.tcp.iter().flatten().map(|tcp| &tcp.route));
+            for (routes, rule_id) in l4_routes.zip(rule_ids) {
+                for route in routes.iter().flatten() {
+                    let destination =
+                        self.destination(&registry, &mut topology, &route.destination);
+                    topology.connect(
+                        rule_id,
+                        destination,
+                        EdgeKind::Forwards {
+                            weight: route.weight,
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(topology)
+    }
+
+    /// Inserts the node of a route destination, linking it to the subset it selects when a
+    /// DestinationRule for the host declares it and to the ServiceEntries declaring the host.
+    fn destination(
+        &self,
+        registry: &SubsetRegistry,
+        topology: &mut Topology,
+        destination: &Destination,
+    ) -> NodeId {
+        let id = topology.insert(Node::Destination {
+            host: destination.host.clone(),
+            subset: destination.subset.clone(),
+            port: destination.port.as_ref().and_then(|port| port.number),
+        });
+        for se in &self.service_entries {
+            let declares = se
+                .spec
+                .iter()
+                .flat_map(|spec| &spec.hosts)
+                .any(|host| hosts_intersect(host, &destination.host));
+            if declares {
+                let se_id = topology.insert(Node::ServiceEntry {
+                    service_entry: ResourceRef::from_metadata(&se.metadata),
+                });
+                topology.connect(id, se_id, EdgeKind::Resolves);
+            }
+        }
+        let subset = match &destination.subset {
+            Some(subset) => subset,
+            None => return id,
+        };
+        if let Some(registered) = registry.subset(&destination.host, subset) {
+            let subset_id = topology.insert(Node::Subset {
+                destination_rule: registered.destination_rule.clone(),
+                host: destination.host.clone(),
+                name: subset.clone(),
+            });
+            topology.connect(id, subset_id, EdgeKind::Selects);
+        }
+        id
+    }
+}
+
+fn gateway_node(gateway: &str, namespace: Option<&str>) -> Node {
+    if gateway == MESH_GATEWAY {
+        Node::Mesh
+    } else {
+        Node::Gateway {
+            gateway: ResourceRef::parse(gateway, namespace),
+        }
+    }
+}
+
+/// The route rule nodes of a VirtualService in http, tls, tcp order.
+fn rule_nodes(vs: &VirtualService) -> Vec<Node> {
+    let vs_ref = ResourceRef::from_metadata(&vs.metadata);
+    let spec = match &vs.spec {
+        Some(spec) => spec,
+        None => return Vec::new(),
+    };
+    let rule = |protocol, index, name: Option<&String>| Node::RouteRule {
+        virtual_service: vs_ref.clone(),
+        protocol,
+        index,
+        name: name.cloned(),
+    };
+    let http = spec
+        .http
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, route)| rule(RouteProtocol::Http, index, route.name.as_ref()));
+    let tls = (0..spec.tls.as_ref().map_or(0, Vec::len))
+        .map(|index| rule(RouteProtocol::Tls, index, None));
+    let tcp = (0..spec.tcp.as_ref().map_or(0, Vec::len))
+        .map(|index| rule(RouteProtocol::Tcp, index, None));
+    http.chain(tls).chain(tcp).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json, Value};
+
+    fn resource<T: serde::de::DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn reviews() -> VirtualService {
+        resource(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": {
+                "hosts": ["reviews"],
+                "gateways": ["ingress"],
+                "http": [{
+                    "name": "split",
+                    "route": [
+                        { "destination": { "host": "reviews", "subset": "v1" }, "weight": 90 },
+                        { "destination": { "host": "reviews", "subset": "v2" }, "weight": 10 },
+                    ],
+                    "mirror": { "host": "api.example.com" },
+                    "mirrorPercentage": { "value": 5.0 },
+                }],
+            },
+        }))
+    }
+
+    fn id(topology: &Topology, node: Node) -> NodeId {
+        topology.find(&node).unwrap()
+    }
+
+    #[test]
+    fn links_gateways_hosts_rules_destinations_and_subsets() {
+        let vs = reviews();
+        let dr: DestinationRule = resource(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": { "host": "reviews", "subsets": [{ "name": "v1", "labels": { "version": "v1" } }] },
+        }));
+        let topology = Topology::builder()
+            .virtual_services([&vs])
+            .destination_rules([&dr])
+            .build()
+            .unwrap();
+
+        let vs_ref = ResourceRef::new(Some("default"), "reviews");
+        let gateway = id(
+            &topology,
+            Node::Gateway {
+                gateway: ResourceRef::new(Some("default"), "ingress"),
+            },
+        );
+        let host = id(
+            &topology,
+            Node::Host {
+                host: "reviews".to_string(),
+            },
+        );
+        let rule = id(
+            &topology,
+            Node::RouteRule {
+                virtual_service: vs_ref.clone(),
+                protocol: RouteProtocol::Http,
+                index: 0,
+                name: Some("split".to_string()),
+            },
+        );
+        let v1 = id(
+            &topology,
+            Node::Destination {
+                host: "reviews".to_string(),
+                subset: Some("v1".to_string()),
+                port: None,
+            },
+        );
+        let subset = id(
+            &topology,
+            Node::Subset {
+                destination_rule: vs_ref.clone(),
+                host: "reviews".to_string(),
+                name: "v1".to_string(),
+            },
+        );
+
+        assert!(topology.edges().contains(&Edge {
+            from: gateway,
+            to: host,
+            kind: EdgeKind::Binds {
+                virtual_service: vs_ref,
+            },
+        }));
+        assert!(topology.edges().contains(&Edge {
+            from: rule,
+            to: v1,
+            kind: EdgeKind::Forwards {
+                weight: Some(Weight::new(90).unwrap()),
+            },
+        }));
+        assert!(topology.outgoing(rule).any(|edge| edge.kind
+            == EdgeKind::Mirrors {
+                percentage: Some(5.0)
+            }));
+        assert!(topology.descendants(gateway).contains(&subset));
+        // v2 is not declared by the DestinationRule, so the destination selects no subset.
+        let v2 = id(
+            &topology,
+            Node::Destination {
+                host: "reviews".to_string(),
+                subset: Some("v2".to_string()),
+                port: None,
+            },
+        );
+        assert_eq!(topology.outgoing(v2).count(), 0);
+    }
+
+    #[test]
+    fn virtual_services_without_gateways_bind_to_the_mesh() {
+        let vs: VirtualService = resource(json!({
+            "metadata": { "name": "ratings" },
+            "spec": { "hosts": ["ratings"], "http": [{ "route": [{ "destination": { "host": "ratings" } }] }] },
+        }));
+        let topology = Topology::builder().virtual_services([&vs]).build().unwrap();
+        let mesh = id(&topology, Node::Mesh);
+        assert_eq!(topology.outgoing(mesh).count(), 1);
+    }
+
+    #[test]
+    fn delegating_rules_link_to_the_rules_of_the_delegate() {
+        let root: VirtualService = resource(json!({
+            "metadata": { "name": "root", "namespace": "default" },
+            "spec": { "hosts": ["bookinfo.com"], "http": [{ "delegate": { "name": "reviews" } }] },
+        }));
+        let delegate = reviews();
+        // The delegate comes first, so the link does not depend on the input order.
+        let topology = Topology::builder()
+            .virtual_services([&delegate, &root])
+            .build()
+            .unwrap();
+        let rule = |name: &str, label: Option<&str>| {
+            id(
+                &topology,
+                Node::RouteRule {
+                    virtual_service: ResourceRef::new(Some("default"), name),
+                    protocol: RouteProtocol::Http,
+                    index: 0,
+                    name: label.map(str::to_string),
+                },
+            )
+        };
+        assert!(topology.edges().contains(&Edge {
+            from: rule("root", None),
+            to: rule("reviews", Some("split")),
+            kind: EdgeKind::Delegates,
+        }));
+    }
+
+    #[test]
+    fn destinations_resolve_to_the_service_entries_declaring_their_host() {
+        let vs = reviews();
+        let se: ServiceEntry = resource(json!({
+            "metadata": { "name": "external", "namespace": "default" },
+            "spec": { "hosts": ["*.example.com"], "resolution": "DNS", "location": "MESH_EXTERNAL" },
+        }));
+        let topology = Topology::builder()
+            .virtual_services([&vs])
+            .service_entries([&se])
+            .build()
+            .unwrap();
+        let se_id = id(
+            &topology,
+            Node::ServiceEntry {
+                service_entry: ResourceRef::new(Some("default"), "external"),
+            },
+        );
+        let mirror = id(
+            &topology,
+            Node::Destination {
+                host: "api.example.com".to_string(),
+                subset: None,
+                port: None,
+            },
+        );
+        assert!(topology.edges().contains(&Edge {
+            from: mirror,
+            to: se_id,
+            kind: EdgeKind::Resolves,
+        }));
+        let gateway = ResourceRef::new(Some("default"), "ingress");
+        assert!(topology
+            .ancestors(se_id)
+            .contains(&id(&topology, Node::Gateway { gateway })));
+    }
+
+    #[test]
+    fn virtual_services_sharing_a_name_are_rejected() {
+        // Manifests loaded without a namespace: same reference, different number of routes.
+        let one: VirtualService = resource(json!({
+            "metadata": { "name": "reviews" },
+            "spec": { "hosts": ["a"], "http": [{ "route": [{ "destination": { "host": "a" } }] }] },
+        }));
+        let two: VirtualService = resource(json!({
+            "metadata": { "name": "reviews" },
+            "spec": {
+                "hosts": ["b"],
+                "http": [
+                    { "route": [{ "destination": { "host": "b" } }] },
+                    { "route": [{ "destination": { "host": "c" } }] },
+                ],
+                "tcp": [{ "route": [{ "destination": { "host": "d" } }] }],
+            },
+        }));
+        assert_eq!(
+            Topology::builder()
+                .virtual_services([&one, &two])
+                .build()
+                .unwrap_err(),
+            TopologyError::DuplicateVirtualService(ResourceRef::new(None, "reviews"))
+        );
+    }
+}
@@ -0,0 +1,430 @@
+// Traffic shifting on top of a VirtualService. Every operation keeps the weights of each touched
+// route summing to 100, only ever changes the route destinations of the rules and reports the
+// changed fields, so callers can build minimal patches for server-side apply.
+use crate::istio::diff::{self, Change};
+use crate::istio::virtual_service::{
+    Destination, HttpRouteDestination, RouteDestination, VirtualService,
+};
+use crate::istio::weight::{Weight, Weights};
+use k8s_openapi::serde_json::{json, Map, Value};
+use k8s_openapi::Resource;
+use std::fmt;
+
+/// # TrafficTarget
+/// A destination traffic can be shifted to, identified by host and optional subset.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TrafficTarget {
+    pub host: String,
+    pub subset: Option<String>,
+}
+
+impl TrafficTarget {
+    pub fn host(host: &str) -> Self {
+        TrafficTarget {
+            host: host.to_string(),
+            subset: None,
+        }
+    }
+
+    pub fn subset(host: &str, subset: &str) -> Self {
+        TrafficTarget {
+            host: host.to_string(),
+            subset: Some(subset.to_string()),
+        }
+    }
+
+    fn matches(&self, destination: &Destination) -> bool {
+        self.host == destination.host && self.subset == destination.subset
+    }
+}
+
+impl fmt::Display for TrafficTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subset {
+            Some(subset) => write!(f, "{}/{}", self.host, subset),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// # TrafficPlanError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrafficPlanError {
+    /// A weight above 100 was requested.
+    InvalidWeight(u32),
+
+    /// No route of the VirtualService forwards traffic to the target.
+    UnknownTarget(TrafficTarget),
+
+    /// The source of a shift carries less weight than requested in one of the routes.
+    InsufficientWeight {
+        target: TrafficTarget,
+        available: u32,
+        requested: u32,
+    },
+}
+
+impl fmt::Display for TrafficPlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrafficPlanError::InvalidWeight(weight) => {
+                write!(f, "weight {} is outside of 0-100", weight)
+            }
+            TrafficPlanError::UnknownTarget(target) => {
+                write!(f, "no route forwards traffic to {}", target)
+            }
+            TrafficPlanError::InsufficientWeight {
+                target,
+                available,
+                requested,
+            } => write!(
+                f,
+                "cannot shift {} from {}, it only receives {}",
+                requested, target, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TrafficPlanError {}
+
+/// # TrafficPlan
+/// Shifts traffic between the destinations of a VirtualService. The VirtualService the plan was
+/// created from is kept as baseline for rollbacks and patches.
+#[derive(Clone, Debug)]
+pub struct TrafficPlan {
+    original: VirtualService,
+    current: VirtualService,
+}
+
+impl TrafficPlan {
+    pub fn new(vs: VirtualService) -> Self {
+        TrafficPlan {
+            original: vs.clone(),
+            current: vs,
+        }
+    }
+
+    /// The VirtualService with all operations applied.
+    pub fn virtual_service(&self) -> &VirtualService {
+        &self.current
+    }
+
+    pub fn into_virtual_service(self) -> VirtualService {
+        self.current
+    }
+
+    /// Moves `weight` percentage points from `from` to `to` in every route forwarding to `from`.
+    /// `to` is added to routes that do not forward to it yet, copying the port and headers of
+    /// `from`.
+    pub fn shift(
+        &mut self,
+        from: &TrafficTarget,
+        to: &TrafficTarget,
+        weight: u32,
+    ) -> Result<Vec<Change>, TrafficPlanError> {
+        if Weight::new(weight).is_err() {
+            return Err(TrafficPlanError::InvalidWeight(weight));
+        }
+        self.update(from, |weights, targets| {
+            let source = targets
+                .iter()
+                .position(|t| from.matches(t))
+                .expect("route forwards to source");
+            if weights[source] < weight {
+                return Err(TrafficPlanError::InsufficientWeight {
+                    target: from.clone(),
+                    available: weights[source],
+                    requested: weight,
+                });
+            }
+            weights[source] -= weight;
+            let destination = match targets.iter().position(|t| to.matches(t)) {
+                Some(destination) => destination,
+                None => {
+                    targets.push(Destination {
+                        host: to.host.clone(),
+                        subset: to.subset.clone(),
+                        ..targets[source].clone()
+                    });
+                    weights.push(0);
+                    targets.len() - 1
+                }
+            };
+            weights[destination] += weight;
+            Ok(())
+        })
+    }
+
+    /// Sends all traffic of the routes forwarding to `target` to it. The other destinations of
+    /// those routes are kept with a weight of 0.
+    pub fn promote(&mut self, target: &TrafficTarget) -> Result<Vec<Change>, TrafficPlanError> {
+        self.update(target, |weights, targets| {
+            for (weight, destination) in weights.iter_mut().zip(targets.iter()) {
+                *weight = if target.matches(destination) { 100 } else { 0 };
+            }
+            Ok(())
+        })
+    }
+
+    /// Restores the route destinations of the original VirtualService.
+    pub fn rollback(&mut self) -> Vec<Change> {
+        let before = self.current.clone();
+        if let (Some(current), Some(original)) =
+            (self.current.spec.as_mut(), self.original.spec.as_ref())
+        {
+            for (http, original) in current
+                .http
+                .iter_mut()
+                .flatten()
+                .zip(original.http.iter().flatten())
+            {
+                http.route = original.route.clone();
+            }
+            for (tls, original) in current
+                .tls
+                .iter_mut()
+                .flatten()
+                .zip(original.tls.iter().flatten())
+            {
+                tls.route = original.route.clone();
+            }
+            for (tcp, original) in current
+                .tcp
+                .iter_mut()
+                .flatten()
+                .zip(original.tcp.iter().flatten())
+            {
+                tcp.route = original.route.clone();
+            }
+        }
+        diff::diff(&before, &self.current)
+    }
+
+    /// All changes made since the plan was created.
+    pub fn changes(&self) -> Vec<Change> {
+        diff::diff(&self.original, &self.current)
+    }
+
+    /// Builds a server-side apply body containing only the identity of the VirtualService and the
+    /// route lists changed since the plan was created. Route lists are atomic in the Istio CRDs,
+    /// so a changed list is sent as a whole.
+    pub fn apply_patch(&self) -> Value {
+        let original =
+            k8s_openapi::serde_json::to_value(&self.original.spec).unwrap_or(Value::Null);
+        let current = k8s_openapi::serde_json::to_value(&self.current.spec).unwrap_or(Value::Null);
+        let mut spec = Map::new();
+        for field in ["http", "tls", "tcp"] {
+            if let Some(value) = current.get(field) {
+                if original.get(field) != Some(value) {
+                    spec.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+        json!({
+            "apiVersion": VirtualService::API_VERSION,
+            "kind": VirtualService::KIND,
+            "metadata": {
+                "name": self.current.metadata.name,
+                "namespace": self.current.metadata.namespace,
+            },
+            "spec": spec,
+        })
+    }
+
+    /// Runs `operation` on the normalized weights of every route forwarding to `target` and writes
+    /// the resulting weights back. The operation is all or nothing: on error nothing is changed.
+    fn update(
+        &mut self,
+        target: &TrafficTarget,
+        mut operation: impl FnMut(&mut Vec<u32>, &mut Vec<Destination>) -> Result<(), TrafficPlanError>,
+    ) -> Result<Vec<Change>, TrafficPlanError> {
+        let before = self.current.clone();
+        let mut updated = self.current.clone();
+        let mut found = false;
+        if let Some(spec) = updated.spec.as_mut() {
+            for routes in spec
+                .http
+                .iter_mut()
+                .flatten()
+                .filter_map(|http| http.route.as_mut())
+            {
+                let mut targets: Vec<Destination> =
+                    routes.iter().map(|r| r.destination.clone()).collect();
+                if !targets.iter().any(|t| target.matches(t)) {
+                    continue;
+                }
+                found = true;
+                let headers = routes
+                    .iter()
+                    .find(|r| target.matches(&r.destination))
+                    .and_then(|r| r.headers.clone());
+                let mut weights = normalize(routes.iter().map(|r| r.weight));
+                operation(&mut weights, &mut targets)?;
+                for destination in targets.iter().skip(routes.len()) {
+                    routes.push(HttpRouteDestination {
+                        destination: destination.clone(),
+                        weight: None,
+                        headers: headers.clone(),
+                        extra: Default::default(),
+                    });
+                }
+                for (route, weight) in routes.iter_mut().zip(weights) {
+                    route.weight = Some(Weight::new(weight).expect("weights add up to 100"));
+                }
+            }
+            let l4_routes = spec
+                .tls
+                .iter_mut()
+                .flatten()
+                .filter_map(|tls| tls.route.as_mut())
+                .chain(
+                    spec.tcp
+                        .iter_mut()
+                        .flatten()
+                        .filter_map(|tcp| tcp.route.as_mut()),
+                );
+            for routes in l4_routes {
+                let mut targets: Vec<Destination> =
+                    routes.iter().map(|r| r.destination.clone()).collect();
+                if !targets.iter().any(|t| target.matches(t)) {
+                    continue;
+                }
+                found = true;
+                let mut weights = normalize(routes.iter().map(|r| r.weight));
+                operation(&mut weights, &mut targets)?;
+                for destination in targets.iter().skip(routes.len()) {
+                    routes.push(RouteDestination {
+                        destination: destination.clone(),
+                        weight: None,
+                    });
+                }
+                for (route, weight) in routes.iter_mut().zip(weights) {
+                    route.weight = Some(Weight::new(weight).expect("weights add up to 100"));
+                }
+            }
+        }
+        if !found {
+            return Err(TrafficPlanError::UnknownTarget(target.clone()));
+        }
+        self.current = updated;
+        Ok(diff::diff(&before, &self.current))
+    }
+}
+
+/// The weights of a route as plain percentages, see [`Weights::normalize`].
+fn normalize(weights: impl Iterator<Item = Option<Weight>>) -> Vec<u32> {
+    Weights::normalize(weights)
+        .iter()
+        .map(Weight::get)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::istio::diff::ChangeKind;
+    use k8s_openapi::serde_json;
+
+    fn reviews() -> VirtualService {
+        serde_json::from_value(json!({
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": {
+                "hosts": ["reviews"],
+                "http": [
+                    { "route": [{ "destination": { "host": "reviews", "subset": "v1" } }] },
+                    { "route": [{ "destination": { "host": "ratings" } }] },
+                ],
+            },
+        }))
+        .unwrap()
+    }
+
+    fn routes(plan: &TrafficPlan) -> Value {
+        serde_json::to_value(&plan.virtual_service().spec.as_ref().unwrap().http).unwrap()
+    }
+
+    #[test]
+    fn shift_moves_weight_to_a_new_destination() {
+        let mut plan = TrafficPlan::new(reviews());
+        let v1 = TrafficTarget::subset("reviews", "v1");
+        let v2 = TrafficTarget::subset("reviews", "v2");
+        let changes = plan.shift(&v1, &v2, 20).unwrap();
+        assert_eq!(
+            routes(&plan),
+            json!([
+                {
+                    "route": [
+                        { "destination": { "host": "reviews", "subset": "v1" }, "weight": 80 },
+                        { "destination": { "host": "reviews", "subset": "v2" }, "weight": 20 },
+                    ],
+                },
+                { "route": [{ "destination": { "host": "ratings" } }] },
+            ])
+        );
+        assert!(changes.iter().any(
+            |change| change.path == "spec.http[0].route[1]" && change.kind == ChangeKind::Added
+        ));
+
+        plan.shift(&v1, &v2, 30).unwrap();
+        assert_eq!(routes(&plan)[0]["route"][1]["weight"], json!(50));
+    }
+
+    #[test]
+    fn shift_fails_without_changing_anything() {
+        let mut plan = TrafficPlan::new(reviews());
+        let v1 = TrafficTarget::subset("reviews", "v1");
+        let v2 = TrafficTarget::subset("reviews", "v2");
+        assert_eq!(
+            plan.shift(&v1, &v2, 101),
+            Err(TrafficPlanError::InvalidWeight(101))
+        );
+        assert_eq!(
+            plan.shift(&v2, &v1, 10),
+            Err(TrafficPlanError::UnknownTarget(v2.clone()))
+        );
+        plan.shift(&v1, &v2, 60).unwrap();
+        assert_eq!(
+            plan.shift(&v1, &v2, 50),
+            Err(TrafficPlanError::InsufficientWeight {
+                target: v1,
+                available: 40,
+                requested: 50,
+            })
+        );
+        assert_eq!(routes(&plan)[0]["route"][0]["weight"], json!(40));
+    }
+
+    #[test]
+    fn promote_and_rollback() {
+        let mut plan = TrafficPlan::new(reviews());
+        let v1 = TrafficTarget::subset("reviews", "v1");
+        let v2 = TrafficTarget::subset("reviews", "v2");
+        plan.shift(&v1, &v2, 50).unwrap();
+        plan.promote(&v2).unwrap();
+        assert_eq!(routes(&plan)[0]["route"][0]["weight"], json!(0));
+        assert_eq!(routes(&plan)[0]["route"][1]["weight"], json!(100));
+
+        assert!(!plan.rollback().is_empty());
+        assert!(plan.changes().is_empty());
+        assert_eq!(plan.virtual_service(), &reviews());
+    }
+
+    #[test]
+    fn apply_patch_contains_only_the_changed_route_lists() {
+        let mut plan = TrafficPlan::new(reviews());
+        plan.shift(
+            &TrafficTarget::subset("reviews", "v1"),
+            &TrafficTarget::subset("reviews", "v2"),
+            10,
+        )
+        .unwrap();
+        let patch = plan.apply_patch();
+        assert_eq!(patch["kind"], json!("VirtualService"));
+        assert_eq!(patch["metadata"]["namespace"], json!("default"));
+        assert_eq!(patch["spec"]["http"], routes(&plan));
+        assert!(patch["spec"].get("hosts").is_none());
+    }
+}
@@ -6,7 +6,8 @@ use crate::istio::virtual_service::PortSelector;
 /// # PortTrafficPolicy
 /// Traffic policies that apply to specific ports of the service
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PortTrafficPolicy {
     // Specifies the number of a port on the destination service on which this policy is being applied.
     // No
@@ -31,3 +32,60 @@ pub struct PortTrafficPolicy {
     // No
     pub tls: Option<ClientTLSSettings>,
 }
+
+/// # TunnelSettings
+/// Settings for tunneling the connections to the upstream service over HTTP CONNECT or POST, e.g.
+/// through a proxy in front of the destination.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TunnelSettings {
+    // Specifies which protocol to use for tunneling the downstream connection. CONNECT is used by default if not specified.
+    // No
+    pub protocol: Option<TunnelProtocol>,
+
+    // Specifies a host to which the downstream connection is tunneled. Target host must be an FQDN or IP address.
+    // Yes
+    #[serde(rename = "targetHost")]
+    pub target_host: String,
+
+    // Specifies a port to which the downstream connection is tunneled.
+    // Yes
+    #[serde(rename = "targetPort")]
+    pub target_port: u32,
+}
+
+/// # TunnelProtocol
+/// HTTP method used to tunnel the connection. The HTTP version of the upstream requests is
+/// determined by the service protocol defined for the proxy.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TunnelProtocol {
+    // Uses HTTP CONNECT.
+    CONNECT,
+
+    // Uses HTTP POST.
+    POST,
+}
+
+/// # ProxyProtocol
+/// Sends the PROXY protocol header with the client address to the upstream service.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProxyProtocol {
+    // The PROXY protocol version to use.
+    // No
+    pub version: Option<ProxyProtocolVersion>,
+}
+
+/// # ProxyProtocolVersion
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ProxyProtocolVersion {
+    // PROXY protocol version 1. Human readable format.
+    V1,
+
+    // PROXY protocol version 2. Binary format.
+    V2,
+}
@@ -0,0 +1,26 @@
+// The `networking.istio.io/v1` resources, served from Istio 1.22 on. They convert to and from the
+// `v1beta1` types with `From`:
+//
+// ```ignore
+// let stable = v1::VirtualService::from(virtual_service);
+// let api: Api<v1::VirtualService> = Api::namespaced(client, "default");
+// ```
+use crate::istio::destination_rule::DestinationRuleSpec;
+use crate::istio::gateway::GatewaySpec;
+use crate::istio::service_entry::ServiceEntrySpec;
+use crate::istio::sidecar::SidecarSpec;
+use crate::istio::status::IstioStatus;
+use crate::istio::versioned::versioned;
+use crate::istio::virtual_service::VirtualServiceSpec;
+use crate::istio::workload_entry::WorkloadEntrySpec;
+use crate::istio::workload_group::WorkloadGroupSpec;
+
+versioned!("v1":
+    VirtualService(crate::istio::virtual_service::VirtualService, VirtualServiceSpec, IstioStatus),
+    DestinationRule(crate::istio::destination_rule::DestinationRule, DestinationRuleSpec, IstioStatus),
+    Gateway(crate::istio::gateway::Gateway, GatewaySpec, IstioStatus),
+    ServiceEntry(crate::istio::service_entry::ServiceEntry, ServiceEntrySpec, ()),
+    Sidecar(crate::istio::sidecar::Sidecar, SidecarSpec, ()),
+    WorkloadEntry(crate::istio::workload_entry::WorkloadEntry, WorkloadEntrySpec, ()),
+    WorkloadGroup(crate::istio::workload_group::WorkloadGroup, WorkloadGroupSpec, ()),
+);
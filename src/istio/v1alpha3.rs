@@ -0,0 +1,21 @@
+// The `networking.istio.io/v1alpha3` resources, still the only version served by older control
+// planes. They convert to and from the `v1beta1` types with `From`:
+//
+// ```ignore
+// let legacy: v1alpha3::VirtualService = api.get("reviews").await?;
+// let current = v1beta1::VirtualService::from(legacy);
+// ```
+use crate::istio::destination_rule::DestinationRuleSpec;
+use crate::istio::gateway::GatewaySpec;
+use crate::istio::status::IstioStatus;
+use crate::istio::versioned::versioned;
+use crate::istio::virtual_service::VirtualServiceSpec;
+
+/// EnvoyFilter is only served in `v1alpha3`.
+pub use crate::istio::envoy_filter::EnvoyFilter;
+
+versioned!("v1alpha3":
+    VirtualService(crate::istio::virtual_service::VirtualService, VirtualServiceSpec, IstioStatus),
+    DestinationRule(crate::istio::destination_rule::DestinationRule, DestinationRuleSpec, IstioStatus),
+    Gateway(crate::istio::gateway::Gateway, GatewaySpec, IstioStatus),
+);
@@ -0,0 +1,10 @@
+// The `networking.istio.io/v1beta1` resources. This is the version the resources of this crate are
+// modelled in, the module only gathers them next to `v1alpha3` and `v1`.
+pub use crate::istio::destination_rule::DestinationRule;
+pub use crate::istio::gateway::Gateway;
+pub use crate::istio::proxy_config::ProxyConfig;
+pub use crate::istio::service_entry::ServiceEntry;
+pub use crate::istio::sidecar::Sidecar;
+pub use crate::istio::virtual_service::VirtualService;
+pub use crate::istio::workload_entry::WorkloadEntry;
+pub use crate::istio::workload_group::WorkloadGroup;
@@ -0,0 +1,1401 @@
+// Offline validation of resources against the rules enforced by the Istio validation webhook.
+// Errors are reported with the path of the offending field, e.g. `spec.http[0].route`, so they can
+// be mapped back to the source manifest.
+use crate::istio::destination_rule::{
+    ConnectionPoolSettings, DestinationRule, LbPolicy, LoadBalancerSettings, TrafficPolicy,
+};
+use crate::istio::envoy_filter::EnvoyFilter;
+use crate::istio::export_to::ExportTo;
+use crate::istio::gateway::{Gateway, TLSmode};
+use crate::istio::host::Host;
+use crate::istio::load_balancer_settings::SimpleLB;
+use crate::istio::request_authentication::RequestAuthentication;
+use crate::istio::service_entry::{Location, Resolution, ServiceEntry};
+use crate::istio::traffic_policy::TunnelSettings;
+use crate::istio::version::{self, IstioVersion};
+use crate::istio::virtual_service::{
+    CorsPolicy, HttpFaultInjection, Percent, StringMatch, VirtualService,
+};
+use crate::istio::weight::{Weight, Weights};
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::Resource;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// # ValidationError
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ValidationError {
+    /// Path of the invalid field, e.g. `spec.http[0].route[1].weight`.
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ValidationError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// # ValidationErrors
+/// All errors found in a resource.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ValidationError> {
+        self.0.iter()
+    }
+
+    fn push(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ValidationError::new(path, message));
+    }
+
+    fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Derives `Validate` from `#[validate(...)]` field attributes, see the
+/// `kube-resource-extra-derive` crate.
+#[cfg(feature = "derive")]
+pub use kube_resource_extra_derive::Validate;
+
+/// # Validate
+/// Checks a resource without contacting a cluster.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+
+    /// Validates the resource and checks that `version` of Istio supports all fields it sets.
+    fn validate_for(&self, version: IstioVersion) -> Result<(), ValidationErrors>
+    where
+        Self: Serialize + Resource,
+    {
+        let mut errors = match self.validate() {
+            Ok(()) => ValidationErrors::default(),
+            Err(errors) => errors,
+        };
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        if let Some(object) = value.as_object_mut() {
+            object.insert("apiVersion".to_string(), Self::API_VERSION.into());
+            object.insert("kind".to_string(), Self::KIND.into());
+        }
+        errors.0.extend(version::unsupported(&value, version));
+        errors.into_result()
+    }
+}
+
+impl Validate for VirtualService {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let spec = match &self.spec {
+            Some(spec) => spec,
+            None => {
+                errors.push("spec", "spec is required");
+                return errors.into_result();
+            }
+        };
+
+        for (i, host) in spec.hosts.iter().flatten().enumerate() {
+            validate_host(&mut errors, &format!("spec.hosts[{}]", i), host);
+        }
+
+        for (i, http) in spec.http.iter().flatten().enumerate() {
+            let path = format!("spec.http[{}]", i);
+            let actions = [
+                http.route.is_some(),
+                http.redirect.is_some(),
+                http.direct_response.is_some(),
+                http.delegate.is_some(),
+            ];
+            match actions.iter().filter(|set| **set).count() {
+                0 => errors.push(
+                    &path,
+                    "one of route, redirect, directResponse or delegate is required",
+                ),
+                1 => {}
+                _ => errors.push(
+                    &path,
+                    "route, redirect, directResponse and delegate are mutually exclusive",
+                ),
+            }
+            if http.redirect.is_some() && http.rewrite.is_some() {
+                errors.push(&path, "rewrite cannot be combined with redirect");
+            }
+            if let Some(rewrite) = &http.rewrite {
+                if rewrite.uri.is_some() && rewrite.uri_regex_rewrite.is_some() {
+                    errors.push(
+                        format!("{}.rewrite", path),
+                        "uri and uriRegexRewrite are mutually exclusive",
+                    );
+                }
+                if let Some(regex) = &rewrite.uri_regex_rewrite {
+                    if regex.r#match.is_empty() {
+                        errors.push(
+                            format!("{}.rewrite.uriRegexRewrite.match", path),
+                            "match is required",
+                        );
+                    }
+                }
+            }
+            if let Some(response) = &http.direct_response {
+                if !(200..=599).contains(&response.status) {
+                    errors.push(
+                        format!("{}.directResponse.status", path),
+                        format!("invalid HTTP status {}", response.status),
+                    );
+                }
+            }
+            if let Some(routes) = &http.route {
+                validate_weights(
+                    &mut errors,
+                    &format!("{}.route", path),
+                    routes
+                        .iter()
+                        .map(|r| (r.destination.host.as_str(), r.weight)),
+                );
+            }
+            if let Some(retries) = &http.retries {
+                if let Err(error) = retries.retry_policies() {
+                    errors.push(format!("{}.retries.retryOn", path), error.to_string());
+                }
+            }
+            if let Some(fault) = &http.fault {
+                validate_fault(&mut errors, &format!("{}.fault", path), fault);
+            }
+            if let Some(cors) = &http.cors_policy {
+                validate_cors(&mut errors, &format!("{}.corsPolicy", path), cors);
+            }
+            if let Some(percentage) = &http.mirror_percentage {
+                validate_percent(
+                    &mut errors,
+                    &format!("{}.mirrorPercentage", path),
+                    percentage,
+                );
+            }
+            if http.mirror.is_some() && http.mirrors.is_some() {
+                errors.push(&path, "mirror and mirrors are mutually exclusive");
+            }
+            for (j, mirror) in http.mirrors.iter().flatten().enumerate() {
+                let mirror_path = format!("{}.mirrors[{}]", path, j);
+                if mirror.destination.host.is_empty() {
+                    errors.push(
+                        format!("{}.destination.host", mirror_path),
+                        "host is required",
+                    );
+                }
+                if let Some(percentage) = &mirror.percentage {
+                    validate_percent(
+                        &mut errors,
+                        &format!("{}.percentage", mirror_path),
+                        percentage,
+                    );
+                }
+            }
+        }
+
+        for (i, tls) in spec.tls.iter().flatten().enumerate() {
+            let path = format!("spec.tls[{}]", i);
+            if tls.r#match.is_empty() {
+                errors.push(format!("{}.match", path), "at least one match is required");
+            }
+            for (j, m) in tls.r#match.iter().enumerate() {
+                if m.sni_hosts.is_empty() {
+                    errors.push(
+                        format!("{}.match[{}].sniHosts", path, j),
+                        "at least one SNI host is required",
+                    );
+                }
+                for (k, subnet) in m.destination_subnets.iter().flatten().enumerate() {
+                    validate_subnet(
+                        &mut errors,
+                        &format!("{}.match[{}].destinationSubnets[{}]", path, j, k),
+                        subnet,
+                    );
+                }
+            }
+            validate_weights(
+                &mut errors,
+                &format!("{}.route", path),
+                tls.route
+                    .iter()
+                    .flatten()
+                    .map(|r| (r.destination.host.as_str(), r.weight)),
+            );
+        }
+
+        for (i, tcp) in spec.tcp.iter().flatten().enumerate() {
+            for (j, m) in tcp.r#match.iter().flatten().enumerate() {
+                let path = format!("spec.tcp[{}].match[{}]", i, j);
+                for (k, subnet) in m.destination_subnets.iter().flatten().enumerate() {
+                    validate_subnet(
+                        &mut errors,
+                        &format!("{}.destinationSubnets[{}]", path, k),
+                        subnet,
+                    );
+                }
+                if let Some(subnet) = &m.source_subnet {
+                    validate_subnet(&mut errors, &format!("{}.sourceSubnet", path), subnet);
+                }
+                if let Some(port) = m.port {
+                    if !(1..=65535).contains(&port) {
+                        errors.push(format!("{}.port", path), format!("invalid port {}", port));
+                    }
+                }
+            }
+            validate_weights(
+                &mut errors,
+                &format!("spec.tcp[{}].route", i),
+                tcp.route
+                    .iter()
+                    .flatten()
+                    .map(|r| (r.destination.host.as_str(), r.weight)),
+            );
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for DestinationRule {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let spec = match &self.spec {
+            Some(spec) => spec,
+            None => {
+                errors.push("spec", "spec is required");
+                return errors.into_result();
+            }
+        };
+
+        validate_host(&mut errors, "spec.host", &spec.host);
+        if let Some(policy) = &spec.traffic_policy {
+            validate_traffic_policy(&mut errors, "spec.trafficPolicy", policy);
+        }
+        let exported = spec
+            .export_to
+            .iter()
+            .flatten()
+            .any(|export| *export != ExportTo::CurrentNamespace);
+        if spec.workload_selector.is_some() && exported {
+            errors.push(
+                "spec.exportTo",
+                "a DestinationRule with workloadSelector can only be exported to its own namespace",
+            );
+        }
+        let mut names = HashSet::new();
+        for (i, subset) in spec.subsets.iter().flatten().enumerate() {
+            if let Some(policy) = &subset.traffic_policy {
+                validate_traffic_policy(
+                    &mut errors,
+                    &format!("spec.subsets[{}].trafficPolicy", i),
+                    policy,
+                );
+            }
+            let path = format!("spec.subsets[{}].name", i);
+            if subset.name.is_empty() {
+                errors.push(path, "subset name is required");
+            } else if !names.insert(subset.name.as_str()) {
+                errors.push(path, format!("duplicate subset name {}", subset.name));
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for Gateway {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let spec = match &self.spec {
+            Some(spec) => spec,
+            None => {
+                errors.push("spec", "spec is required");
+                return errors.into_result();
+            }
+        };
+
+        if spec.servers.is_empty() {
+            errors.push("spec.servers", "at least one server is required");
+        }
+        let mut names = HashSet::new();
+        for (i, server) in spec.servers.iter().enumerate() {
+            let path = format!("spec.servers[{}]", i);
+            if server.hosts.is_empty() {
+                errors.push(format!("{}.hosts", path), "at least one host is required");
+            }
+            for (j, host) in server.hosts.iter().enumerate() {
+                validate_host(&mut errors, &format!("{}.hosts[{}]", path, j), host);
+            }
+            let unix = server
+                .bind
+                .as_deref()
+                .is_some_and(|bind| bind.starts_with("unix://"));
+            let valid =
+                (1..=65535).contains(&server.port.number) || (unix && server.port.number == 0);
+            if !valid {
+                errors.push(
+                    format!("{}.port.number", path),
+                    format!("invalid port {}", server.port.number),
+                );
+            }
+            let protocol = server.port.protocol.to_ascii_uppercase();
+            if !PROTOCOLS.contains(&protocol.as_str()) {
+                errors.push(
+                    format!("{}.port.protocol", path),
+                    format!("unknown protocol {}", server.port.protocol),
+                );
+            }
+            if protocol == "HTTPS" && server.tls.is_none() {
+                errors.push(
+                    format!("{}.tls", path),
+                    "HTTPS servers require tls settings",
+                );
+            }
+            if let Some(tls) = &server.tls {
+                let terminates = matches!(tls.mode, Some(TLSmode::SIMPLE) | Some(TLSmode::MUTUAL));
+                let has_files = tls.server_certificate.is_some() && tls.private_key.is_some();
+                if terminates && tls.credential_name.is_none() && !has_files {
+                    errors.push(
+                        format!("{}.tls", path),
+                        "SIMPLE and MUTUAL modes require credentialName or serverCertificate and privateKey",
+                    );
+                }
+            }
+            if let Some(name) = &server.name {
+                if !names.insert(name.as_str()) {
+                    errors.push(
+                        format!("{}.name", path),
+                        format!("duplicate server name {}", name),
+                    );
+                }
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for ServiceEntry {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let spec = match &self.spec {
+            Some(spec) => spec,
+            None => {
+                errors.push("spec", "spec is required");
+                return errors.into_result();
+            }
+        };
+
+        if spec.hosts.is_empty() {
+            errors.push("spec.hosts", "at least one host is required");
+        }
+        for (i, host) in spec.hosts.iter().enumerate() {
+            validate_host(&mut errors, &format!("spec.hosts[{}]", i), host);
+        }
+        let mut numbers = HashSet::new();
+        let mut names = HashSet::new();
+        for (i, port) in spec.ports.iter().flatten().enumerate() {
+            let path = format!("spec.ports[{}]", i);
+            if !numbers.insert(port.number) {
+                errors.push(
+                    format!("{}.number", path),
+                    format!("duplicate port {}", port.number),
+                );
+            }
+            if !names.insert(port.name.as_str()) {
+                errors.push(
+                    format!("{}.name", path),
+                    format!("duplicate port name {}", port.name),
+                );
+            }
+        }
+        if spec.endpoints.is_some() && spec.workload_selector.is_some() {
+            errors.push(
+                "spec.workloadSelector",
+                "endpoints and workloadSelector are mutually exclusive",
+            );
+        }
+        if spec.workload_selector.is_some() && spec.location != Some(Location::MESH_INTERNAL) {
+            errors.push(
+                "spec.workloadSelector",
+                "workloadSelector requires location MESH_INTERNAL",
+            );
+        }
+        let has_endpoints = spec.endpoints.as_ref().is_some_and(|e| !e.is_empty());
+        match spec.resolution {
+            Some(Resolution::STATIC) if !has_endpoints && spec.workload_selector.is_none() => {
+                errors.push("spec.endpoints", "STATIC resolution requires endpoints")
+            }
+            Some(Resolution::DNS) | Some(Resolution::DNS_ROUND_ROBIN) if !has_endpoints => {
+                for (i, host) in spec.hosts.iter().enumerate() {
+                    if host.starts_with('*') {
+                        errors.push(
+                            format!("spec.hosts[{}]", i),
+                            "DNS resolution without endpoints cannot resolve a wildcard host",
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        for (i, endpoint) in spec.endpoints.iter().flatten().enumerate() {
+            if endpoint.address.is_none() && endpoint.network.is_none() {
+                errors.push(
+                    format!("spec.endpoints[{}].address", i),
+                    "address is required without network",
+                );
+            }
+            for (name, _) in endpoint.ports.iter().flatten() {
+                if !names.contains(name.as_str()) {
+                    errors.push(
+                        format!("spec.endpoints[{}].ports.{}", i, name),
+                        format!("no service port named {}", name),
+                    );
+                }
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for EnvoyFilter {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let patches = self.spec.iter().flat_map(|spec| spec.config_patches.iter());
+        for (i, patch) in patches.enumerate() {
+            if let Err(error) = patch.check() {
+                errors.push(format!("spec.configPatches[{}]", i), error.to_string());
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for RequestAuthentication {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let rules = self
+            .spec
+            .iter()
+            .flat_map(|spec| spec.jwt_rules.iter().flatten());
+        let mut issuers = HashSet::new();
+        for (i, rule) in rules.enumerate() {
+            let path = format!("spec.jwtRules[{}]", i);
+            if rule.issuer.is_empty() {
+                errors.push(format!("{}.issuer", path), "issuer is required");
+            } else if !issuers.insert(rule.issuer.as_str()) {
+                errors.push(
+                    format!("{}.issuer", path),
+                    format!("duplicate issuer {}", rule.issuer),
+                );
+            }
+            if rule.jwks_uri.is_some() && rule.jwks.is_some() {
+                errors.push(
+                    format!("{}.jwks", path),
+                    "jwksUri and jwks are mutually exclusive",
+                );
+            }
+            if let Some(uri) = &rule.jwks_uri {
+                if !(uri.starts_with("https://") || uri.starts_with("http://")) {
+                    errors.push(
+                        format!("{}.jwksUri", path),
+                        format!("{} is not an http(s) URL", uri),
+                    );
+                }
+            }
+            if let Some(jwks) = &rule.jwks {
+                let keys = serde_json::from_str::<Value>(jwks)
+                    .ok()
+                    .and_then(|jwks| jwks.get("keys").map(Value::is_array));
+                if keys != Some(true) {
+                    errors.push(format!("{}.jwks", path), "jwks is not a JSON Web Key Set");
+                }
+            }
+            for (j, header) in rule.from_headers.iter().flatten().enumerate() {
+                if header.name.is_empty() {
+                    errors.push(
+                        format!("{}.fromHeaders[{}].name", path, j),
+                        "header name is required",
+                    );
+                }
+            }
+            for (j, param) in rule.from_params.iter().flatten().enumerate() {
+                if param.is_empty() {
+                    errors.push(
+                        format!("{}.fromParams[{}]", path, j),
+                        "parameter name is required",
+                    );
+                }
+            }
+            if rule.output_payload_to_header.as_deref() == Some("") {
+                errors.push(
+                    format!("{}.outputPayloadToHeader", path),
+                    "header name must not be empty",
+                );
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+/// The protocols a gateway server port can expose.
+const PROTOCOLS: &[&str] = &["HTTP", "HTTPS", "GRPC", "HTTP2", "MONGO", "TCP", "TLS"];
+
+/// The gRPC status codes a fault can abort with, by name.
+const GRPC_STATUS_CODES: &[&str] = &[
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// The HTTP methods a CORS policy can allow.
+const HTTP_METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// Checks a DNS name with an optional wildcard in the left-most label.
+fn validate_host(errors: &mut ValidationErrors, path: &str, host: &str) {
+    if let Err(error) = Host::new(host) {
+        errors.push(path, error.to_string());
+    }
+}
+
+/// Checks an IP address with an optional prefix length, e.g. `10.0.0.0/8` or `2001:db8::1`.
+fn validate_subnet(errors: &mut ValidationErrors, path: &str, subnet: &str) {
+    let (address, prefix) = match subnet.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (subnet, None),
+    };
+    let max_prefix = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => {
+            errors.push(path, format!("invalid IP address or CIDR {}", subnet));
+            return;
+        }
+    };
+    if let Some(prefix) = prefix {
+        if !prefix
+            .parse::<u8>()
+            .is_ok_and(|prefix| prefix <= max_prefix)
+        {
+            errors.push(path, format!("invalid prefix length in {}", subnet));
+        }
+    }
+}
+
+/// Checks the weights of the destinations of a route: when traffic is split, the weights must add
+/// up to 100.
+fn validate_weights<'a>(
+    errors: &mut ValidationErrors,
+    path: &str,
+    routes: impl Iterator<Item = (&'a str, Option<Weight>)>,
+) {
+    let routes: Vec<(&str, Option<Weight>)> = routes.collect();
+    for (i, (host, _)) in routes.iter().enumerate() {
+        if host.is_empty() {
+            errors.push(
+                format!("{}[{}].destination.host", path, i),
+                "host is required",
+            );
+        }
+    }
+    if routes.iter().any(|(_, weight)| weight.is_some()) {
+        let weights = Weights::new(
+            routes
+                .iter()
+                .map(|(_, weight)| weight.unwrap_or_default())
+                .collect(),
+        );
+        if let Err(error) = weights.validate() {
+            errors.push(path, error.to_string());
+        }
+    }
+}
+
+fn validate_traffic_policy(errors: &mut ValidationErrors, path: &str, policy: &TrafficPolicy) {
+    if let Some(load_balancer) = &policy.load_balancer {
+        validate_load_balancer(errors, &format!("{}.loadBalancer", path), load_balancer);
+    }
+    if let Some(pool) = &policy.connection_pool {
+        validate_connection_pool(errors, &format!("{}.connectionPool", path), pool);
+    }
+    for (i, port) in policy.port_level_settings.iter().flatten().enumerate() {
+        if let Some(load_balancer) = &port.load_balancer {
+            validate_load_balancer(
+                errors,
+                &format!("{}.portLevelSettings[{}].loadBalancer", path, i),
+                load_balancer,
+            );
+        }
+        if let Some(pool) = &port.connection_pool {
+            validate_connection_pool(
+                errors,
+                &format!("{}.portLevelSettings[{}].connectionPool", path, i),
+                pool,
+            );
+        }
+    }
+    if let Some(tunnel) = &policy.tunnel {
+        validate_tunnel(errors, &format!("{}.tunnel", path), tunnel);
+    }
+}
+
+/// Envoy only supports slow start with the round robin and least request balancers.
+fn validate_load_balancer(
+    errors: &mut ValidationErrors,
+    path: &str,
+    load_balancer: &LoadBalancerSettings,
+) {
+    let warmup_supported = matches!(
+        load_balancer.lb_policy,
+        None | Some(LbPolicy::Simple(
+            SimpleLB::UNSPECIFIED
+                | SimpleLB::ROUND_ROBIN
+                | SimpleLB::LEAST_REQUEST
+                | SimpleLB::LEAST_CONN
+        ))
+    );
+    if load_balancer.warmup_duration_secs.is_some() && !warmup_supported {
+        errors.push(
+            format!("{}.warmupDurationSecs", path),
+            "warmup is only supported with ROUND_ROBIN and LEAST_REQUEST",
+        );
+    }
+    if let Some(LbPolicy::ConsistentHash(consistent_hash)) = &load_balancer.lb_policy {
+        if consistent_hash.minimum_ring_size.is_some() && consistent_hash.hash_algorithm.is_some() {
+            errors.push(
+                format!("{}.consistentHash.minimumRingSize", path),
+                "the deprecated minimumRingSize cannot be combined with ringHash or maglev",
+            );
+        }
+    }
+}
+
+/// Checks that the connection limits are not negative.
+fn validate_connection_pool(
+    errors: &mut ValidationErrors,
+    path: &str,
+    pool: &ConnectionPoolSettings,
+) {
+    let tcp = pool
+        .tcp
+        .iter()
+        .map(|tcp| ("tcp.maxConnections", tcp.max_connections));
+    let http = pool.http.iter().flat_map(|http| {
+        [
+            (
+                "http.http1MaxPendingRequests",
+                http.http1_max_pending_requests,
+            ),
+            ("http.http2MaxRequests", http.http2_max_requests),
+            (
+                "http.maxRequestsPerConnection",
+                http.max_requests_per_connection,
+            ),
+            ("http.maxRetries", http.max_retries),
+            ("http.maxConcurrentStreams", http.max_concurrent_streams),
+        ]
+    });
+    for (field, value) in tcp.chain(http) {
+        if value.is_some_and(|value| value < 0) {
+            errors.push(format!("{}.{}", path, field), "must not be negative");
+        }
+    }
+}
+
+fn validate_tunnel(errors: &mut ValidationErrors, path: &str, tunnel: &TunnelSettings) {
+    if tunnel.target_host.is_empty() {
+        errors.push(format!("{}.targetHost", path), "target host is required");
+    }
+    if !(1..=65535).contains(&tunnel.target_port) {
+        errors.push(
+            format!("{}.targetPort", path),
+            format!("invalid port {}", tunnel.target_port),
+        );
+    }
+}
+
+fn validate_fault(errors: &mut ValidationErrors, path: &str, fault: &HttpFaultInjection) {
+    if fault.delay.is_none() && fault.abort.is_none() {
+        errors.push(path, "one of delay or abort is required");
+    }
+    if let Some(percentage) = fault.delay.as_ref().and_then(|d| d.percentage.as_ref()) {
+        validate_percent(errors, &format!("{}.delay.percentage", path), percentage);
+    }
+    if let Some(abort) = &fault.abort {
+        let codes = [
+            abort.http_status.is_some(),
+            abort.grpc_status.is_some(),
+            abort.http2_error.is_some(),
+        ];
+        if codes.iter().filter(|set| **set).count() != 1 {
+            errors.push(
+                format!("{}.abort", path),
+                "exactly one of httpStatus, grpcStatus or http2Error is required",
+            );
+        }
+        if let Some(status) = abort.http_status {
+            if !(200..=599).contains(&status) {
+                errors.push(
+                    format!("{}.abort.httpStatus", path),
+                    format!("invalid HTTP status {}", status),
+                );
+            }
+        }
+        if let Some(status) = &abort.grpc_status {
+            if !GRPC_STATUS_CODES.contains(&status.as_str()) {
+                errors.push(
+                    format!("{}.abort.grpcStatus", path),
+                    format!("unknown gRPC status {}", status),
+                );
+            }
+        }
+        if let Some(percentage) = &abort.percentage {
+            validate_percent(errors, &format!("{}.abort.percentage", path), percentage);
+        }
+    }
+}
+
+fn validate_cors(errors: &mut ValidationErrors, path: &str, cors: &CorsPolicy) {
+    for (i, origin) in cors.allow_origins.iter().flatten().enumerate() {
+        let (StringMatch::Exact(value) | StringMatch::Prefix(value) | StringMatch::Regex(value)) =
+            origin;
+        if value.is_empty() {
+            errors.push(
+                format!("{}.allowOrigins[{}]", path, i),
+                "origin match must not be empty",
+            );
+        }
+    }
+    for (i, method) in cors.allow_methods.iter().flatten().enumerate() {
+        if !HTTP_METHODS.contains(&method.as_str()) {
+            errors.push(
+                format!("{}.allowMethods[{}]", path, i),
+                format!("invalid HTTP method {}", method),
+            );
+        }
+    }
+    if let Some(max_age) = cors.max_age {
+        if max_age.as_nanos() % 1_000_000 != 0 || max_age.is_zero() {
+            errors.push(
+                format!("{}.maxAge", path),
+                "maxAge must be a whole number of milliseconds of at least 1ms",
+            );
+        }
+    }
+}
+
+fn validate_percent(errors: &mut ValidationErrors, path: &str, percent: &Percent) {
+    if !(0.0..=100.0).contains(&percent.value) {
+        errors.push(
+            path,
+            format!("percentage {} is outside of 0-100", percent.value),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::json;
+    use serde::de::DeserializeOwned;
+
+    fn errors<T: Validate + DeserializeOwned>(spec: Value) -> Vec<(String, String)> {
+        let resource: T =
+            serde_json::from_value(json!({ "metadata": { "name": "test" }, "spec": spec }))
+                .unwrap();
+        match resource.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .iter()
+                .map(|error| (error.path.clone(), error.message.clone()))
+                .collect(),
+        }
+    }
+
+    fn expected(errors: &[(&str, &str)]) -> Vec<(String, String)> {
+        errors
+            .iter()
+            .map(|(path, message)| (path.to_string(), message.to_string()))
+            .collect()
+    }
+
+    fn route(host: &str) -> Value {
+        json!([{ "destination": { "host": host } }])
+    }
+
+    #[test]
+    fn requires_a_spec() {
+        let gateway: Gateway =
+            serde_json::from_value(json!({ "metadata": { "name": "test" } })).unwrap();
+        assert_eq!(
+            gateway.validate().unwrap_err().to_string(),
+            "spec: spec is required"
+        );
+    }
+
+    #[test]
+    fn virtual_service_hosts() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "hosts": ["reviews", "reviews.*"] })),
+            expected(&[(
+                "spec.hosts[1]",
+                "wildcard must be the left-most label of reviews.*"
+            )])
+        );
+    }
+
+    #[test]
+    fn http_route_actions() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [
+                {},
+                { "route": route("reviews"), "directResponse": { "status": 503 } },
+                { "redirect": { "uri": "/v2" }, "rewrite": { "uri": "/v1" } },
+            ] })),
+            expected(&[
+                (
+                    "spec.http[0]",
+                    "one of route, redirect, directResponse or delegate is required"
+                ),
+                (
+                    "spec.http[1]",
+                    "route, redirect, directResponse and delegate are mutually exclusive"
+                ),
+                ("spec.http[2]", "rewrite cannot be combined with redirect"),
+            ])
+        );
+    }
+
+    #[test]
+    fn http_rewrite_and_direct_response() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [
+                {
+                    "route": route("reviews"),
+                    "rewrite": { "uri": "/v1", "uriRegexRewrite": { "match": "", "rewrite": "/" } },
+                },
+                { "directResponse": { "status": 700 } },
+            ] })),
+            expected(&[
+                (
+                    "spec.http[0].rewrite",
+                    "uri and uriRegexRewrite are mutually exclusive"
+                ),
+                (
+                    "spec.http[0].rewrite.uriRegexRewrite.match",
+                    "match is required"
+                ),
+                (
+                    "spec.http[1].directResponse.status",
+                    "invalid HTTP status 700"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn route_hosts_and_weights() {
+        let weights = Weights::new(vec![Weight::new(50).unwrap(), Weight::new(40).unwrap()]);
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [{ "route": [
+                { "destination": { "host": "" }, "weight": 50 },
+                { "destination": { "host": "reviews" }, "weight": 40 },
+            ] }] })),
+            expected(&[
+                ("spec.http[0].route[0].destination.host", "host is required"),
+                (
+                    "spec.http[0].route",
+                    &weights.validate().unwrap_err().to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn http_retries() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [{
+                "route": route("reviews"),
+                "retries": { "attempts": 3, "retryOn": "5xx,sometimes" },
+            }] })),
+            expected(&[(
+                "spec.http[0].retries.retryOn",
+                "unknown retry policy sometimes"
+            )])
+        );
+    }
+
+    #[test]
+    fn http_faults() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [
+                { "route": route("reviews"), "fault": {} },
+                { "route": route("reviews"), "fault": {
+                    "delay": { "fixedDelay": "5s", "percentage": { "value": 101 } },
+                    "abort": { "httpStatus": 99, "grpcStatus": "NOPE", "percentage": { "value": -1 } },
+                } },
+            ] })),
+            expected(&[
+                ("spec.http[0].fault", "one of delay or abort is required"),
+                (
+                    "spec.http[1].fault.delay.percentage",
+                    "percentage 101 is outside of 0-100"
+                ),
+                (
+                    "spec.http[1].fault.abort",
+                    "exactly one of httpStatus, grpcStatus or http2Error is required"
+                ),
+                (
+                    "spec.http[1].fault.abort.httpStatus",
+                    "invalid HTTP status 99"
+                ),
+                (
+                    "spec.http[1].fault.abort.grpcStatus",
+                    "unknown gRPC status NOPE"
+                ),
+                (
+                    "spec.http[1].fault.abort.percentage",
+                    "percentage -1 is outside of 0-100"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn http_cors_policy() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [{
+                "route": route("reviews"),
+                "corsPolicy": {
+                    "allowOrigins": [{ "exact": "https://example.com" }, { "prefix": "" }],
+                    "allowMethods": ["GET", "FETCH"],
+                    "maxAge": "1500us",
+                },
+            }] })),
+            expected(&[
+                (
+                    "spec.http[0].corsPolicy.allowOrigins[1]",
+                    "origin match must not be empty"
+                ),
+                (
+                    "spec.http[0].corsPolicy.allowMethods[1]",
+                    "invalid HTTP method FETCH"
+                ),
+                (
+                    "spec.http[0].corsPolicy.maxAge",
+                    "maxAge must be a whole number of milliseconds of at least 1ms"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn http_mirrors() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "http": [
+                {
+                    "route": route("reviews"),
+                    "mirror": { "host": "shadow" },
+                    "mirrorPercentage": { "value": 200 },
+                    "mirrors": [{ "destination": { "host": "" }, "percentage": { "value": 150 } }],
+                },
+            ] })),
+            expected(&[
+                (
+                    "spec.http[0].mirrorPercentage",
+                    "percentage 200 is outside of 0-100"
+                ),
+                ("spec.http[0]", "mirror and mirrors are mutually exclusive"),
+                (
+                    "spec.http[0].mirrors[0].destination.host",
+                    "host is required"
+                ),
+                (
+                    "spec.http[0].mirrors[0].percentage",
+                    "percentage 150 is outside of 0-100"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn tls_routes() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "tls": [
+                { "match": [], "route": route("reviews") },
+                { "match": [{ "sniHosts": [], "destinationSubnets": ["10.0.0.0/33", "nope"] }],
+                  "route": route("") },
+            ] })),
+            expected(&[
+                ("spec.tls[0].match", "at least one match is required"),
+                (
+                    "spec.tls[1].match[0].sniHosts",
+                    "at least one SNI host is required"
+                ),
+                (
+                    "spec.tls[1].match[0].destinationSubnets[0]",
+                    "invalid prefix length in 10.0.0.0/33"
+                ),
+                (
+                    "spec.tls[1].match[0].destinationSubnets[1]",
+                    "invalid IP address or CIDR nope"
+                ),
+                ("spec.tls[1].route[0].destination.host", "host is required"),
+            ])
+        );
+    }
+
+    #[test]
+    fn tcp_routes() {
+        assert_eq!(
+            errors::<VirtualService>(json!({ "tcp": [{
+                "match": [{
+                    "destinationSubnets": ["2001:db8::/129"],
+                    "sourceSubnet": "10.0.0.1/x",
+                    "port": 70000,
+                }],
+                "route": route("reviews"),
+            }] })),
+            expected(&[
+                (
+                    "spec.tcp[0].match[0].destinationSubnets[0]",
+                    "invalid prefix length in 2001:db8::/129"
+                ),
+                (
+                    "spec.tcp[0].match[0].sourceSubnet",
+                    "invalid prefix length in 10.0.0.1/x"
+                ),
+                ("spec.tcp[0].match[0].port", "invalid port 70000"),
+            ])
+        );
+    }
+
+    #[test]
+    fn destination_rule_scope_and_subsets() {
+        assert_eq!(
+            errors::<DestinationRule>(json!({
+                "host": "reviews.*",
+                "exportTo": ["*"],
+                "workloadSelector": { "matchLabels": { "app": "ratings" } },
+                "subsets": [{ "name": "" }, { "name": "v1" }, { "name": "v1" }],
+            })),
+            expected(&[
+                ("spec.host", "wildcard must be the left-most label of reviews.*"),
+                (
+                    "spec.exportTo",
+                    "a DestinationRule with workloadSelector can only be exported to its own namespace"
+                ),
+                ("spec.subsets[0].name", "subset name is required"),
+                ("spec.subsets[2].name", "duplicate subset name v1"),
+            ])
+        );
+    }
+
+    #[test]
+    fn destination_rule_traffic_policies() {
+        assert_eq!(
+            errors::<DestinationRule>(json!({
+                "host": "ratings",
+                "trafficPolicy": {
+                    "loadBalancer": { "simple": "RANDOM", "warmupDurationSecs": "60s" },
+                    "connectionPool": {
+                        "tcp": { "maxConnections": -1 },
+                        "http": { "http2MaxRequests": -1 },
+                    },
+                    "portLevelSettings": [{
+                        "port": { "number": 80 },
+                        "loadBalancer": {
+                            "consistentHash": {
+                                "useSourceIp": true,
+                                "maglev": {},
+                                "minimumRingSize": 1024,
+                            },
+                        },
+                        "connectionPool": { "http": { "maxRetries": -1 } },
+                    }],
+                    "tunnel": { "protocol": "CONNECT", "targetHost": "", "targetPort": 0 },
+                },
+                "subsets": [{
+                    "name": "v1",
+                    "trafficPolicy": { "connectionPool": { "http": { "maxConcurrentStreams": -1 } } },
+                }],
+            })),
+            expected(&[
+                (
+                    "spec.trafficPolicy.loadBalancer.warmupDurationSecs",
+                    "warmup is only supported with ROUND_ROBIN and LEAST_REQUEST"
+                ),
+                (
+                    "spec.trafficPolicy.connectionPool.tcp.maxConnections",
+                    "must not be negative"
+                ),
+                (
+                    "spec.trafficPolicy.connectionPool.http.http2MaxRequests",
+                    "must not be negative"
+                ),
+                (
+                    "spec.trafficPolicy.portLevelSettings[0].loadBalancer.consistentHash.minimumRingSize",
+                    "the deprecated minimumRingSize cannot be combined with ringHash or maglev"
+                ),
+                (
+                    "spec.trafficPolicy.portLevelSettings[0].connectionPool.http.maxRetries",
+                    "must not be negative"
+                ),
+                ("spec.trafficPolicy.tunnel.targetHost", "target host is required"),
+                ("spec.trafficPolicy.tunnel.targetPort", "invalid port 0"),
+                (
+                    "spec.subsets[0].trafficPolicy.connectionPool.http.maxConcurrentStreams",
+                    "must not be negative"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn fields_unsupported_by_the_target_version() {
+        let rule: DestinationRule = serde_json::from_value(json!({
+            "metadata": { "name": "ratings" },
+            "spec": {
+                "host": "ratings",
+                "trafficPolicy": { "loadBalancer": { "simple": "RANDOM", "warmupDurationSecs": "60s" } },
+            },
+        }))
+        .unwrap();
+        let errors = rule.validate_for(IstioVersion::new(1, 13)).unwrap_err();
+        assert_eq!(
+            errors.0,
+            [
+                ValidationError::new(
+                    "spec.trafficPolicy.loadBalancer.warmupDurationSecs",
+                    "warmup is only supported with ROUND_ROBIN and LEAST_REQUEST",
+                ),
+                ValidationError::new(
+                    "spec.trafficPolicy.loadBalancer.warmupDurationSecs",
+                    "requires Istio 1.14, targeting 1.13",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn gateway_servers() {
+        assert_eq!(
+            errors::<Gateway>(json!({ "selector": {}, "servers": [] })),
+            expected(&[("spec.servers", "at least one server is required")])
+        );
+        assert_eq!(
+            errors::<Gateway>(json!({ "selector": { "istio": "ingressgateway" }, "servers": [
+                {
+                    "name": "web",
+                    "port": { "number": 0, "name": "web", "protocol": "FTP" },
+                    "hosts": [],
+                },
+                {
+                    "name": "web",
+                    "port": { "number": 443, "name": "https", "protocol": "https" },
+                    "hosts": ["reviews.*"],
+                },
+                {
+                    "port": { "number": 8443, "name": "tls", "protocol": "HTTPS" },
+                    "hosts": ["*"],
+                    "tls": { "mode": "SIMPLE" },
+                },
+                {
+                    "bind": "unix:///var/run/gateway.sock",
+                    "port": { "number": 0, "name": "uds", "protocol": "HTTP" },
+                    "hosts": ["*"],
+                },
+            ] })),
+            expected(&[
+                ("spec.servers[0].hosts", "at least one host is required"),
+                ("spec.servers[0].port.number", "invalid port 0"),
+                ("spec.servers[0].port.protocol", "unknown protocol FTP"),
+                (
+                    "spec.servers[1].hosts[0]",
+                    "wildcard must be the left-most label of reviews.*"
+                ),
+                ("spec.servers[1].tls", "HTTPS servers require tls settings"),
+                ("spec.servers[1].name", "duplicate server name web"),
+                (
+                    "spec.servers[2].tls",
+                    "SIMPLE and MUTUAL modes require credentialName or serverCertificate and privateKey"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn service_entry_hosts_and_ports() {
+        assert_eq!(
+            errors::<ServiceEntry>(json!({
+                "hosts": [],
+                "ports": [
+                    { "number": 80, "name": "http", "protocol": "HTTP" },
+                    { "number": 80, "name": "http", "protocol": "HTTP" },
+                ],
+            })),
+            expected(&[
+                ("spec.hosts", "at least one host is required"),
+                ("spec.ports[1].number", "duplicate port 80"),
+                ("spec.ports[1].name", "duplicate port name http"),
+            ])
+        );
+    }
+
+    #[test]
+    fn service_entry_endpoints_and_resolution() {
+        assert_eq!(
+            errors::<ServiceEntry>(json!({
+                "hosts": ["db.internal"],
+                "location": "MESH_EXTERNAL",
+                "resolution": "STATIC",
+                "endpoints": [],
+                "workloadSelector": { "labels": { "app": "db" } },
+            })),
+            expected(&[
+                (
+                    "spec.workloadSelector",
+                    "endpoints and workloadSelector are mutually exclusive"
+                ),
+                (
+                    "spec.workloadSelector",
+                    "workloadSelector requires location MESH_INTERNAL"
+                ),
+            ])
+        );
+        assert_eq!(
+            errors::<ServiceEntry>(json!({ "hosts": ["db.internal"], "resolution": "STATIC" })),
+            expected(&[("spec.endpoints", "STATIC resolution requires endpoints")])
+        );
+        assert_eq!(
+            errors::<ServiceEntry>(json!({ "hosts": ["*.example.com"], "resolution": "DNS" })),
+            expected(&[(
+                "spec.hosts[0]",
+                "DNS resolution without endpoints cannot resolve a wildcard host"
+            )])
+        );
+        assert_eq!(
+            errors::<ServiceEntry>(json!({
+                "hosts": ["db.internal"],
+                "ports": [{ "number": 5432, "name": "tcp", "protocol": "TCP" }],
+                "resolution": "STATIC",
+                "endpoints": [{ "ports": { "tcp": 5433, "http": 8080 } }],
+            })),
+            expected(&[
+                (
+                    "spec.endpoints[0].address",
+                    "address is required without network"
+                ),
+                ("spec.endpoints[0].ports.http", "no service port named http"),
+            ])
+        );
+    }
+
+    #[test]
+    fn envoy_filter_patches() {
+        assert_eq!(
+            errors::<EnvoyFilter>(json!({ "configPatches": [
+                { "applyTo": "HTTP_FILTER", "match": {}, "patch": { "operation": "REMOVE" } },
+                { "applyTo": "HTTP_FILTER", "match": {}, "patch": { "operation": "MERGE" } },
+            ] })),
+            expected(&[("spec.configPatches[1]", "MERGE requires a value")])
+        );
+    }
+
+    #[test]
+    fn request_authentication_rules() {
+        assert_eq!(
+            errors::<RequestAuthentication>(json!({ "jwtRules": [
+                { "issuer": "" },
+                {
+                    "issuer": "https://issuer.example.com",
+                    "jwksUri": "ftp://issuer.example.com/jwks",
+                    "jwks": "{}",
+                },
+                {
+                    "issuer": "https://issuer.example.com",
+                    "fromHeaders": [{ "name": "" }],
+                    "fromParams": [""],
+                    "outputPayloadToHeader": "",
+                },
+            ] })),
+            expected(&[
+                ("spec.jwtRules[0].issuer", "issuer is required"),
+                (
+                    "spec.jwtRules[1].jwks",
+                    "jwksUri and jwks are mutually exclusive"
+                ),
+                (
+                    "spec.jwtRules[1].jwksUri",
+                    "ftp://issuer.example.com/jwks is not an http(s) URL"
+                ),
+                ("spec.jwtRules[1].jwks", "jwks is not a JSON Web Key Set"),
+                (
+                    "spec.jwtRules[2].issuer",
+                    "duplicate issuer https://issuer.example.com"
+                ),
+                (
+                    "spec.jwtRules[2].fromHeaders[0].name",
+                    "header name is required"
+                ),
+                (
+                    "spec.jwtRules[2].fromParams[0]",
+                    "parameter name is required"
+                ),
+                (
+                    "spec.jwtRules[2].outputPayloadToHeader",
+                    "header name must not be empty"
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// Istio releases and the resource fields they introduced. The types of this crate follow the latest
+// release; a field an older control plane does not know is dropped by its API server or ignored by
+// istiod, so a manifest using it silently behaves differently on that mesh.
+use crate::istio::validate::ValidationError;
+use k8s_openapi::serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// # IstioVersion
+/// A minor release of Istio, e.g. `1.18`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct IstioVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl IstioVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        IstioVersion { major, minor }
+    }
+}
+
+impl fmt::Display for IstioVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Parses `1.18`, also accepting a patch release (`1.18.2`) and a leading `v`.
+impl FromStr for IstioVersion {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionError(s.to_string());
+        let mut parts = s.strip_prefix('v').unwrap_or(s).split('.');
+        let major = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        let minor = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        match parts.next() {
+            Some(patch) if patch.parse::<u32>().is_err() => Err(invalid()),
+            _ if parts.next().is_some() => Err(invalid()),
+            _ => Ok(IstioVersion { major, minor }),
+        }
+    }
+}
+
+impl TryFrom<String> for IstioVersion {
+    type Error = VersionError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<IstioVersion> for String {
+    fn from(version: IstioVersion) -> Self {
+        version.to_string()
+    }
+}
+
+/// # VersionError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionError(pub String);
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid Istio version {}", self.0)
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// # FieldIntroduction
+/// A field and the release that introduced it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldIntroduction {
+    pub kind: &'static str,
+
+    /// Path of the field in the serialized resource, `[]` standing for any item of a list.
+    pub path: &'static str,
+
+    pub since: IstioVersion,
+}
+
+/// Fields introduced after the oldest release this crate targets.
+pub const FIELDS: &[FieldIntroduction] = &[
+    FieldIntroduction {
+        kind: "DestinationRule",
+        path: "spec.trafficPolicy.loadBalancer.warmupDurationSecs",
+        since: IstioVersion::new(1, 14),
+    },
+    FieldIntroduction {
+        kind: "DestinationRule",
+        path: "spec.trafficPolicy.portLevelSettings[].loadBalancer.warmupDurationSecs",
+        since: IstioVersion::new(1, 14),
+    },
+    FieldIntroduction {
+        kind: "DestinationRule",
+        path: "spec.subsets[].trafficPolicy.loadBalancer.warmupDurationSecs",
+        since: IstioVersion::new(1, 14),
+    },
+    FieldIntroduction {
+        kind: "VirtualService",
+        path: "spec.http[].directResponse",
+        since: IstioVersion::new(1, 15),
+    },
+    FieldIntroduction {
+        kind: "VirtualService",
+        path: "spec.http[].mirrors",
+        since: IstioVersion::new(1, 18),
+    },
+    FieldIntroduction {
+        kind: "VirtualService",
+        path: "spec.http[].rewrite.uriRegexRewrite",
+        since: IstioVersion::new(1, 18),
+    },
+    FieldIntroduction {
+        kind: "VirtualService",
+        path: "spec.http[].corsPolicy.unmatchedPreflights",
+        since: IstioVersion::new(1, 22),
+    },
+];
+
+/// Kinds introduced after the oldest release this crate targets.
+pub const KINDS: &[(&str, IstioVersion)] = &[
+    ("WasmPlugin", IstioVersion::new(1, 12)),
+    ("ProxyConfig", IstioVersion::new(1, 13)),
+];
+
+/// The release that started serving the `v1` API versions of the networking and security kinds.
+pub const V1_API: IstioVersion = IstioVersion::new(1, 22);
+
+/// Fields of `resource`, a manifest with `apiVersion` and `kind`, that `version` does not support.
+/// Errors point at the field, e.g. `spec.http[2].rewrite.uriRegexRewrite`.
+pub fn unsupported(resource: &Value, version: IstioVersion) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let kind = resource
+        .get("kind")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let api_version = resource
+        .get("apiVersion")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if let Some((_, since)) = KINDS
+        .iter()
+        .find(|(name, since)| *name == kind && version < *since)
+    {
+        errors.push(ValidationError::new(
+            "kind",
+            format!("{} requires Istio {}, targeting {}", kind, since, version),
+        ));
+    }
+    if api_version.ends_with("/v1") && api_version.contains(".istio.io/") && version < V1_API {
+        errors.push(ValidationError::new(
+            "apiVersion",
+            format!(
+                "{} requires Istio {}, targeting {}",
+                api_version, V1_API, version
+            ),
+        ));
+    }
+    for field in FIELDS
+        .iter()
+        .filter(|field| field.kind == kind && version < field.since)
+    {
+        let segments: Vec<&str> = field.path.split('.').collect();
+        for path in find(resource, "", &segments) {
+            errors.push(ValidationError::new(
+                path,
+                format!("requires Istio {}, targeting {}", field.since, version),
+            ));
+        }
+    }
+    errors
+}
+
+/// The paths of the fields of `value` matching `segments`, expanding `[]` to every item.
+fn find(value: &Value, path: &str, segments: &[&str]) -> Vec<String> {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return vec![path.to_string()],
+    };
+    let (key, list) = match segment.strip_suffix("[]") {
+        Some(key) => (key, true),
+        None => (*segment, false),
+    };
+    let path = match path {
+        "" => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    };
+    match value.get(key) {
+        Some(Value::Array(items)) if list => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| find(item, &format!("{}[{}]", path, i), rest))
+            .collect(),
+        Some(field) if !list && !field.is_null() => find(field, &path, rest),
+        _ => Vec::new(),
+    }
+}
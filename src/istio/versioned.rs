@@ -0,0 +1,70 @@
+// Resources of the other API versions a kind is served in. Istio serves the versions of a
+// networking kind from one schema, so a versioned resource shares the spec and status of the type
+// this crate models (the `v1beta1` one) and only differs in its `apiVersion`. Conversions to and
+// from the modelled type are lossless `From` impls.
+
+/// Declares `$kind` of API version `$version`, sharing spec and status with `$base`.
+macro_rules! versioned {
+    ($version:literal: $($kind:ident($base:ty, $spec:ty, $status:ty)),* $(,)?) => {
+        $(
+            #[skip_serializing_none]
+            #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+            #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+            pub struct $kind {
+                /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+                pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+                /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+                pub spec: Option<$spec>,
+
+                /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+                pub status: Option<$status>,
+            }
+
+            impl k8s_openapi::Resource for $kind {
+                const API_VERSION: &'static str = concat!("networking.istio.io/", $version);
+                const GROUP: &'static str = "networking.istio.io";
+                const KIND: &'static str = <$base as k8s_openapi::Resource>::KIND;
+                const VERSION: &'static str = $version;
+                const URL_PATH_SEGMENT: &'static str = <$base as k8s_openapi::Resource>::URL_PATH_SEGMENT;
+                type Scope = k8s_openapi::NamespaceResourceScope;
+            }
+
+            impl k8s_openapi::Metadata for $kind {
+                type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+                fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
+                    &self.metadata
+                }
+                fn metadata_mut(&mut self) -> &mut <Self as k8s_openapi::Metadata>::Ty {
+                    &mut self.metadata
+                }
+            }
+
+            crate::istio::object::object! {
+                $kind: $spec, $status;
+            }
+
+            impl From<$base> for $kind {
+                fn from(resource: $base) -> Self {
+                    $kind {
+                        metadata: resource.metadata,
+                        spec: resource.spec,
+                        status: resource.status,
+                    }
+                }
+            }
+
+            impl From<$kind> for $base {
+                fn from(resource: $kind) -> Self {
+                    Self {
+                        metadata: resource.metadata,
+                        spec: resource.spec,
+                        status: resource.status,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use versioned;
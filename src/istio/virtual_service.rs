@@ -1,9 +1,15 @@
+use crate::istio::duration::IstioDuration;
+use crate::istio::export_to::ExportTo;
+use crate::istio::host::{self, Host, HostError};
+use crate::istio::status::IstioStatus;
+use crate::istio::weight::Weight;
+use crate::istio::Map;
+use k8s_openapi::serde_json::Value;
 use k8s_openapi::{Metadata, Resource};
-use std::collections::HashMap;
-use std::time::Duration;
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VirtualService {
     /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
     pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
@@ -12,7 +18,7 @@ pub struct VirtualService {
     pub spec: Option<VirtualServiceSpec>,
 
     /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
-    pub status: Option<()>,
+    pub status: Option<IstioStatus>,
 }
 
 impl Resource for VirtualService {
@@ -120,7 +126,8 @@ impl Metadata for VirtualService {
 /// # VirtualService
 /// Configuration affecting traffic routing.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VirtualServiceSpec {
     /// The destination hosts to which traffic is being sent. Could be a DNS name with wildcard prefix or an IP address. Depending on the platform, short-names can also be used instead of a FQDN (i.e. has no dots in the name). In such a scenario, the FQDN of the host would be derived based on the underlying platform.
     //
@@ -157,11 +164,25 @@ pub struct VirtualServiceSpec {
     /// The value “.” is reserved and defines an export to the same namespace that the virtual service is declared in. Similarly the value “*” is reserved and defines an export to all namespaces.
     /// Required: No
     #[serde(rename = "exportTo")]
-    pub export_to: Option<Vec<String>>,
+    pub export_to: Option<Vec<ExportTo>>,
+
+    /// Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as
+    /// is so that the resource survives a round trip.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl VirtualServiceSpec {
+    /// The hosts, parsed. Empty when no hosts are set.
+    pub fn parse_hosts(&self) -> Result<Vec<Host>, HostError> {
+        host::parse_all(self.hosts.iter().flatten())
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Destination {
     /// The name of a service from the service registry. Service names are looked up from the platform’s service registry (e.g., Kubernetes services, Consul services, etc.) and from the hosts declared by ServiceEntry. Traffic forwarded to destinations that are not found in either of the two, will be dropped.
     //
@@ -179,7 +200,8 @@ pub struct Destination {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Delegate {
     /// Name specifies the name of the delegate VirtualService.
     /// Required: No
@@ -191,7 +213,8 @@ pub struct Delegate {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Headers {
     /// Header manipulation rules to apply before forwarding a request to the destination service
     /// Required: No
@@ -202,20 +225,27 @@ pub struct Headers {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TlsRoute {
     /// Match conditions to be satisfied for the rule to be activated. All conditions inside a single
     /// match block have AND semantics, while the list of match blocks have OR semantics. The rule is
     /// matched if any one of the match blocks succeed.
     /// Required: Yes
-    pub r#match: Vec<TlsMatchAttribures>,
+    pub r#match: Vec<TlsMatchAttributes>,
     /// The destination to which the connection should be forwarded to.
     /// Required: No
     pub route: Option<Vec<RouteDestination>>,
+
+    /// Fields of the route unknown to this crate, kept as is like the `extra` of the spec.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TcpRoute {
     /// Match conditions to be satisfied for the rule to be activated. All conditions inside a single
     /// match block have AND semantics, while the list of match blocks have OR semantics. The rule is
@@ -225,10 +255,16 @@ pub struct TcpRoute {
     /// The destination to which the connection should be forwarded to.
     /// Required: No
     pub route: Option<Vec<RouteDestination>>,
+
+    /// Fields of the route unknown to this crate, kept as is like the `extra` of the spec.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpRoute {
     /// The name assigned to the route for debugging purposes. The route’s name will be concatenated
     /// with the match’s name and will be logged in the access logs for requests matching this route/match.
@@ -253,6 +289,13 @@ pub struct HttpRoute {
     /// Required: No
     pub redirect: Option<HttpRedirect>,
 
+    /// A HTTP rule can either return a direct_response, redirect or forward (default) traffic. The
+    /// direct_response primitive can be used to specify a fixed response to send to clients. It can
+    /// be set only when Route and Redirect are empty.
+    /// Required: No
+    #[serde(rename = "directResponse")]
+    pub direct_response: Option<HttpDirectResponse>,
+
     /// Delegate is used to specify the particular VirtualService which can be used to define delegate
     /// HTTPRoute. It can be set only when Route and Redirect are empty, and the route rules of the
     /// delegate VirtualService will be merged with that in the current one. > Note: 1. Only one level
@@ -268,7 +311,7 @@ pub struct HttpRoute {
 
     /// Timeout for HTTP requests.
     /// Required: No
-    pub timeout: Option<Duration>,
+    pub timeout: Option<IstioDuration>,
 
     /// Retry policy for HTTP requests.
     /// Required: No
@@ -292,6 +335,14 @@ pub struct HttpRoute {
     #[serde(rename = "mirrorPercentage")]
     pub mirror_percentage: Option<Percent>,
 
+    /// Specifies the destinations to mirror HTTP traffic in addition to the original destination.
+    /// Mirrored traffic is on a best effort basis where the sidecar/gateway will not wait for the
+    /// mirrored destinations to respond before returning the response from the original
+    /// destination. Statistics will be generated for the mirrored destination. Cannot be combined
+    /// with mirror.
+    /// Required: No
+    pub mirrors: Option<Vec<HttpMirrorPolicy>>,
+
     /// Cross-Origin Resource Sharing policy (CORS). Refer to CORS for further details about cross
     /// origin resource sharing.
     /// Required: No
@@ -307,10 +358,44 @@ pub struct HttpRoute {
     /// Required: No
     #[serde(rename = "mirrorPercent")]
     pub mirror_percent: Option<i32>,
+
+    /// Fields of the route unknown to this crate, kept as is like the `extra` of the spec.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl HttpRoute {
+    /// The mirrors of the route, from `mirrors` or else from the deprecated `mirror`,
+    /// `mirrorPercentage` and `mirrorPercent` fields.
+    pub fn mirror_policies(&self) -> Vec<HttpMirrorPolicy> {
+        match (&self.mirrors, &self.mirror) {
+            (Some(mirrors), _) => mirrors.clone(),
+            (None, Some(mirror)) => vec![HttpMirrorPolicy {
+                destination: mirror.clone(),
+                percentage: self.mirror_percentage.clone().or_else(|| {
+                    self.mirror_percent
+                        .map(|percent| Percent::from(percent as f64))
+                }),
+            }],
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Moves the deprecated single mirror into `mirrors`, clearing `mirror`, `mirrorPercentage`
+    /// and `mirrorPercent`. Routes already using `mirrors` only lose the deprecated fields.
+    pub fn migrate_mirror(&mut self) {
+        let mirrors = self.mirror_policies();
+        self.mirror = None;
+        self.mirror_percentage = None;
+        self.mirror_percent = None;
+        self.mirrors = Some(mirrors).filter(|mirrors| !mirrors.is_empty());
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpMatchRequest {
     /// The name assigned to a match. The match’s name will be concatenated with the parent route’s name and will be logged in the access logs for requests matching this route.
     /// Required: No
@@ -363,7 +448,7 @@ pub struct HttpMatchRequest {
     //
     /// If the value is empty and only the name of header is specfied, presence of the header is checked. > Note: The keys uri, scheme, method, and authority will be ignored.
     /// Required: No
-    pub headers: Option<HashMap<String, StringMatch>>,
+    pub headers: Option<Map<String, StringMatch>>,
 
     /// Specifies the ports on the host that is being addressed. Many services only expose a single port or label ports with the protocols they support, in these cases it is not required to explicitly select the port.
     /// Required: No
@@ -372,7 +457,7 @@ pub struct HttpMatchRequest {
     /// One or more labels that constrain the applicability of a rule to source (client) workloads with the given labels. If the VirtualService has a list of gateways specified in the top-level gateways field, it must include the reserved gateway mesh for this field to be applicable.
     /// Required: No
     #[serde(rename = "sourceLabels")]
-    pub source_labels: Option<HashMap<String, String>>,
+    pub source_labels: Option<Map<String, String>>,
 
     /// Names of gateways where the rule should be applied. Gateway names in the top-level gateways field of the VirtualService (if any) are overridden. The gateway match is independent of sourceLabels.
     /// Required: No
@@ -388,7 +473,7 @@ pub struct HttpMatchRequest {
     /// > Note: prefix matching is currently not supported.
     /// Required: No
     #[serde(rename = "queryParams")]
-    pub query_params: Option<HashMap<String, StringMatch>>,
+    pub query_params: Option<Map<String, StringMatch>>,
 
     /// Flag to specify whether the URI matching should be case-insensitive.
     //
@@ -400,16 +485,22 @@ pub struct HttpMatchRequest {
     /// withoutHeader has the same syntax with the header, but has opposite meaning. If a header is matched with a matching rule among withoutHeader, the traffic becomes not matched one.
     /// Required: No
     #[serde(rename = "withoutHeaders")]
-    pub without_headers: Option<HashMap<String, StringMatch>>,
+    pub without_headers: Option<Map<String, StringMatch>>,
 
     /// Source namespace constraining the applicability of a rule to workloads in that namespace. If the VirtualService has a list of gateways specified in the top-level gateways field, it must include the reserved gateway mesh for this field to be applicable.
     /// Required: No
     #[serde(rename = "sourceNamespace")]
     pub source_namespace: Option<String>,
+
+    /// Fields of the match unknown to this crate, kept as is like the `extra` of the spec.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RouteDestination {
     /// Destination uniquely identifies the instances of a service to which the request/connection should be forwarded to.
     /// Required: Yes
@@ -417,11 +508,12 @@ pub struct RouteDestination {
 
     /// Weight specifies the relative proportion of traffic to be forwarded to the destination. A destination will receive weight/(sum of all weights) requests. If there is only one destination in a rule, it will receive all traffic. Otherwise, if weight is 0, the destination will not receive any traffic.
     /// Required: No
-    pub weight: Option<i32>,
+    pub weight: Option<Weight>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct L4MatchAttributes {
     /// IPv4 or IPv6 ip addresses of destination with optional subnet. E.g., a.b.c.d/xx form or just a.b.c.d.
     /// Required: No
@@ -432,10 +524,15 @@ pub struct L4MatchAttributes {
     /// Required: No
     pub port: Option<i32>,
 
+    /// IPv4 or IPv6 ip address of source with optional subnet. E.g., a.b.c.d/xx form or just a.b.c.d
+    /// Required: No
+    #[serde(rename = "sourceSubnet")]
+    pub source_subnet: Option<String>,
+
     /// One or more labels that constrain the applicability of a rule to workloads with the given labels. If the VirtualService has a list of gateways specified in the top-level gateways field, it should include the reserved gateway mesh in order for this field to be applicable.
     /// Required: No
     #[serde(rename = "sourceLabels")]
-    pub source_labels: Option<HashMap<String, String>>,
+    pub source_labels: Option<Map<String, String>>,
 
     /// Names of gateways where the rule should be applied. Gateway names in the top-level gateways field of the VirtualService (if any) are overridden. The gateway match is independent of sourceLabels.
     /// Required: No
@@ -448,8 +545,10 @@ pub struct L4MatchAttributes {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-pub struct TlsMatchAttribures {
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "LegacyTlsMatchAttributes")]
+pub struct TlsMatchAttributes {
     /// SNI (server name indicator) to match on. Wildcard prefixes can be used in the SNI value, e.g., *.com will match foo.example.com as well as example.com. An SNI value must be a subset (i.e., fall within the domain) of the corresponding virtual serivce’s hosts.
     /// Required: Yes
     #[serde(rename = "sniHosts")]
@@ -467,11 +566,11 @@ pub struct TlsMatchAttribures {
     /// One or more labels that constrain the applicability of a rule to workloads with the given labels. If the VirtualService has a list of gateways specified in the top-level gateways field, it should include the reserved gateway mesh in order for this field to be applicable.
     /// Required: No
     #[serde(rename = "sourceLabels")]
-    pub source_labels: Option<HashMap<String, String>>,
+    pub source_labels: Option<Map<String, String>>,
 
     /// Names of gateways where the rule should be applied. Gateway names in the top-level gateways field of the VirtualService (if any) are overridden. The gateway match is independent of sourceLabels.
     /// Required: No
-    pub gateways: Option<String>,
+    pub gateways: Option<Vec<String>>,
 
     /// Source namespace constraining the applicability of a rule to workloads in that namespace. If the VirtualService has a list of gateways specified in the top-level gateways field, it must include the reserved gateway mesh for this field to be applicable.
     /// Required: No
@@ -479,8 +578,58 @@ pub struct TlsMatchAttribures {
     pub source_namespace: Option<String>,
 }
 
+impl TlsMatchAttributes {
+    /// The SNI hosts, parsed.
+    pub fn parse_sni_hosts(&self) -> Result<Vec<Host>, HostError> {
+        host::parse_all(&self.sni_hosts)
+    }
+}
+
+/// The former, misspelled name of `TlsMatchAttributes`.
+#[deprecated(note = "renamed to TlsMatchAttributes")]
+pub type TlsMatchAttribures = TlsMatchAttributes;
+
+/// The wire format of `TlsMatchAttributes`, whose `gateways` older manifests give as a single name.
+#[derive(Deserialize)]
+struct LegacyTlsMatchAttributes {
+    #[serde(rename = "sniHosts")]
+    sni_hosts: Vec<String>,
+    #[serde(rename = "destinationSubnets")]
+    destination_subnets: Option<Vec<String>>,
+    port: Option<u32>,
+    #[serde(rename = "sourceLabels")]
+    source_labels: Option<Map<String, String>>,
+    gateways: Option<LegacyGateways>,
+    #[serde(rename = "sourceNamespace")]
+    source_namespace: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyGateways {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<LegacyTlsMatchAttributes> for TlsMatchAttributes {
+    fn from(attributes: LegacyTlsMatchAttributes) -> Self {
+        TlsMatchAttributes {
+            sni_hosts: attributes.sni_hosts,
+            destination_subnets: attributes.destination_subnets,
+            port: attributes.port,
+            source_labels: attributes.source_labels,
+            gateways: attributes.gateways.map(|gateways| match gateways {
+                LegacyGateways::One(gateway) => vec![gateway],
+                LegacyGateways::Many(gateways) => gateways,
+            }),
+            source_namespace: attributes.source_namespace,
+        }
+    }
+}
+
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpRedirect {
     /// On a redirect, overwrite the Path portion of the URL with this value. Note that the entire path will be replaced, irrespective of the request URI being matched as an exact path or prefix.
     /// Required: No
@@ -509,8 +658,67 @@ pub struct HttpRedirect {
     pub redirect_code: Option<i32>,
 }
 
+/// # HttpMirrorPolicy
+/// HTTPMirrorPolicy can be used to specify the destinations to mirror HTTP traffic in addition to
+/// the original destination.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HttpMirrorPolicy {
+    /// Destination specifies the target of the mirror operation.
+    /// Required: Yes
+    pub destination: Destination,
+
+    /// Percentage of the traffic to be mirrored by the destination field. If this field is absent,
+    /// all the traffic (100%) will be mirrored. Max value is 100.
+    /// Required: No
+    pub percentage: Option<Percent>,
+}
+
+/// # HttpDirectResponse
+/// HTTPDirectResponse can be used to send a fixed response to clients.
+///
+/// ```yaml
+/// http:
+/// - match:
+///   - uri:
+///       exact: /unknown
+///   directResponse:
+///     status: 503
+///     body:
+///       string: "unknown path"
+/// ```
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HttpDirectResponse {
+    /// Specifies the HTTP response status to be returned.
+    /// Required: Yes
+    pub status: u32,
+
+    /// Specifies the content of the response body. If this setting is omitted, no body is included
+    /// in the generated response.
+    /// Required: No
+    pub body: Option<HttpBody>,
+}
+
+/// # HttpBody
+/// The body of a direct response, either text or base64 encoded binary data.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum HttpBody {
+    /// response body as a string
+    #[serde(rename = "string")]
+    String(String),
+
+    /// response body as base64 encoded bytes.
+    #[serde(rename = "bytes")]
+    Bytes(String),
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpRouteDestination {
     /// Destination uniquely identifies the instances of a service to which the request/connection should be forwarded to.
     /// Required: Yes
@@ -518,15 +726,21 @@ pub struct HttpRouteDestination {
 
     /// Weight specifies the relative proportion of traffic to be forwarded to the destination. A destination will receive weight/(sum of all weights) requests. If there is only one destination in a rule, it will receive all traffic. Otherwise, if weight is 0, the destination will not receive any traffic.
     /// Required: No
-    pub weight: Option<i32>,
+    pub weight: Option<Weight>,
 
     /// Header manipulation rules
     /// Required: No
     pub headers: Option<Headers>,
+
+    /// Fields of the destination unknown to this crate, kept as is like the `extra` of the spec.
+    /// Required: No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpRewrite {
     /// rewrite the path (or the prefix) portion of the URI with this value. If the original URI was matched based on prefix, the value provided in this field will replace the corresponding matched prefix.
     /// Required: No
@@ -535,10 +749,32 @@ pub struct HttpRewrite {
     /// rewrite the Authority/Host header with this value.
     /// Required: No
     pub authority: Option<String>,
+
+    /// rewrite the path portion of the URI with the specified regex. Cannot be combined with uri.
+    /// Required: No
+    #[serde(rename = "uriRegexRewrite")]
+    pub uri_regex_rewrite: Option<RegexRewrite>,
+}
+
+/// # RegexRewrite
+/// Rewrites the part of the path matched by an RE2 regex, e.g. `match: ^/service/([^/]+)(/.*)$`
+/// with `rewrite: \2/instance/\1`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RegexRewrite {
+    /// RE2 style regex-based match (https://github.com/google/re2/wiki/Syntax).
+    /// Required: Yes
+    pub r#match: String,
+
+    /// The string that should replace into matching portions of original URI. Capture groups in the pattern can be referenced in the new URI. Examples: Example 1: rewrite with capture groups Path pattern “/service/update/v1/api” with match “^/service/([^/]+)(/.*)$” and rewrite string of “\2/instance/\1” would transform into “/v1/api/instance/update”. Example 2: case-insensitive rewrite Path pattern “/aaa/XxX/bbb” with match “(?i)/xxx/” and a rewrite string of /yyy/ would do a case-insensitive match and transform the path to “/aaa/yyy/bbb”.
+    /// Required: Yes
+    pub rewrite: String,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum StringMatch {
     /// exact string match
     #[serde(rename = "exact")]
@@ -554,7 +790,8 @@ pub enum StringMatch {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpRetry {
     /// Number of retries to be allowed for a given request. The interval between retries will be determined automatically (25ms+). When request timeout of the HTTP route or per_try_timeout is configured, the actual number of retries attempted also depends on the specified request timeout and per_try_timeout values.
     /// Required: Yes
@@ -563,7 +800,7 @@ pub struct HttpRetry {
     /// Timeout per attempt for a given request, including the initial call and any retries. Format: 1h/1m/1s/1ms. MUST BE >=1ms. Default is same value as request timeout of the HTTP route, which means no timeout.
     /// Required: No
     #[serde(rename = "perTryTimeout")]
-    pub per_try_timeout: Option<Duration>,
+    pub per_try_timeout: Option<IstioDuration>,
 
     /// Specifies the conditions under which retry takes place. One or more policies can be specified using a ‘,’ delimited list. If retry_on specifies a valid HTTP status, it will be added to retriablestatuscodes retry policy. See the retry policies and gRPC retry policies for more details.
     /// Required: No
@@ -574,11 +811,25 @@ pub struct HttpRetry {
     /// Required: No
     #[serde(rename = "retryRemoteLocalities")]
     pub retry_remote_localities: Option<bool>,
+
+    /// Flag to specify whether the retries should ignore previously tried hosts during retry. Defaults to true.
+    /// Required: No
+    #[serde(rename = "retryIgnorePreviousHosts")]
+    pub retry_ignore_previous_hosts: Option<bool>,
+
+    /// Specifies the minimum duration between retry attempts. If unset, default minimum duration of 25ms is used as base interval for exponential backoff. This has an impact on the total number of retries that will be attempted based on the attempts field and route timeout. For example, with attempts is set to 3, backoff to 2s and timeout to 3s, the 1st retry will happen after 2s but the 2nd retry will not happen because of timeout.
+    /// Required: No
+    pub backoff: Option<IstioDuration>,
 }
 
 /// Cross-Origin Resource Sharing policy (CORS).
+///
+/// The deprecated `allowOrigin` list of exact origins is accepted on deserialize and converted to
+/// `allowOrigins` when those are not set.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "LegacyCorsPolicy")]
 pub struct CorsPolicy {
     /// String patterns that match allowed origins. An origin is allowed if any of the string matchers match. If a match is found, then the outgoing Access-Control-Allow-Origin would be set to the origin as provided by the client.
     /// Required: No
@@ -603,20 +854,78 @@ pub struct CorsPolicy {
     /// Specifies how long the results of a preflight request can be cached. Translates to the Access-Control-Max-Age header.
     /// Required: No
     #[serde(rename = "maxAge")]
-    pub max_age: Option<Duration>,
+    pub max_age: Option<IstioDuration>,
 
     /// Indicates whether the caller is allowed to send the actual request (not the preflight) using credentials. Translates to Access-Control-Allow-Credentials header.
     /// Required: No
     #[serde(rename = "allowCredentials")]
     pub allow_credentials: Option<bool>,
+
+    /// Indicates whether preflight requests not matching the configured allowed origin shouldn't be forwarded to the upstream. Default is forwarded upstream.
+    /// Required: No
+    #[serde(rename = "unmatchedPreflights")]
+    pub unmatched_preflights: Option<UnmatchedPreflights>,
+}
+
+/// # CorsPolicy.UnmatchedPreflights
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum UnmatchedPreflights {
+    /// Default to FORWARD
+    UNSPECIFIED,
+
+    /// Preflight requests not matching the configured allowed origin will be forwarded to the upstream.
+    FORWARD,
+
+    /// Preflight requests not matching the configured allowed origin will not be forwarded to the upstream. Instead, the proxy will respond directly.
+    IGNORE,
+}
+
+/// The wire format of `CorsPolicy`, including the deprecated `allowOrigin` field.
+#[derive(Deserialize)]
+struct LegacyCorsPolicy {
+    #[serde(rename = "allowOrigin")]
+    allow_origin: Option<Vec<String>>,
+    #[serde(rename = "allowOrigins")]
+    allow_origins: Option<Vec<StringMatch>>,
+    #[serde(rename = "allowMethods")]
+    allow_methods: Option<Vec<String>>,
+    #[serde(rename = "allowHeaders")]
+    allow_headers: Option<Vec<String>>,
+    #[serde(rename = "exposeHeaders")]
+    expose_headers: Option<Vec<String>>,
+    #[serde(rename = "maxAge")]
+    max_age: Option<IstioDuration>,
+    #[serde(rename = "allowCredentials")]
+    allow_credentials: Option<bool>,
+    #[serde(rename = "unmatchedPreflights")]
+    unmatched_preflights: Option<UnmatchedPreflights>,
 }
 
-//// # HTTPFaultInjection
-//// HTTPFaultInjection can be used to specify one or more faults to inject while forwarding HTTP requests to the destination specified in a route. Fault specification is part of a VirtualService rule. Faults include aborting the Http request from downstream service, and/or delaying proxying of requests. A fault rule MUST HAVE delay or abort or both.
+impl From<LegacyCorsPolicy> for CorsPolicy {
+    fn from(policy: LegacyCorsPolicy) -> Self {
+        let legacy = policy
+            .allow_origin
+            .map(|origins| origins.into_iter().map(StringMatch::Exact).collect());
+        CorsPolicy {
+            allow_origins: policy.allow_origins.or(legacy),
+            allow_methods: policy.allow_methods,
+            allow_headers: policy.allow_headers,
+            expose_headers: policy.expose_headers,
+            max_age: policy.max_age,
+            allow_credentials: policy.allow_credentials,
+            unmatched_preflights: policy.unmatched_preflights,
+        }
+    }
+}
+
+/// # HTTPFaultInjection
+/// HTTPFaultInjection can be used to specify one or more faults to inject while forwarding HTTP requests to the destination specified in a route. Fault specification is part of a VirtualService rule. Faults include aborting the Http request from downstream service, and/or delaying proxying of requests. A fault rule MUST HAVE delay or abort or both.
 ///
-//// > Note: Delay and abort faults are independent of one another, even if both are specified simultaneously.
+/// > Note: Delay and abort faults are independent of one another, even if both are specified simultaneously.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HttpFaultInjection {
     /// Delay requests before forwarding, emulating various failures such as network issues, overloaded upstream service, etc.
     /// Required: No
@@ -627,36 +936,132 @@ pub struct HttpFaultInjection {
     pub abort: Option<FaultInjectionAbort>,
 }
 
+/// # PortSelector
+/// PortSelector specifies the number of a port to be used for matching or selection for final
+/// routing.
+///
+/// Old manifests may quote the number (`number: "8080"`) or put a port name in it; on deserialize a
+/// quoted number is read as the number and anything else as the deprecated `name`.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "LegacyPortSelector")]
 pub struct PortSelector {
     /// Valid port number
     /// Required: No
     pub number: Option<u32>,
+
+    /// Deprecated: selecting a port by name was removed from the API. The field is only kept so
+    /// that old stored objects can still be parsed; current Istio versions ignore it.
+    /// Required: No
+    pub name: Option<String>,
 }
 
-#[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct Percent(pub f32);
+impl PortSelector {
+    /// Replaces a port selected by name with its number, looked up with `resolve`, e.g. in the ports
+    /// of the Service. Returns the number, `None` when the port is unset or the name is unknown, in
+    /// which case the selector is left unchanged.
+    pub fn normalize(&mut self, resolve: impl FnOnce(&str) -> Option<u32>) -> Option<u32> {
+        if self.number.is_none() {
+            self.number = self.name.as_deref().and_then(resolve);
+        }
+        if self.number.is_some() {
+            self.name = None;
+        }
+        self.number
+    }
+}
+
+impl From<u32> for PortSelector {
+    fn from(number: u32) -> Self {
+        PortSelector {
+            number: Some(number),
+            name: None,
+        }
+    }
+}
+
+/// The wire format of `PortSelector`, allowing a quoted or named `number`.
+#[derive(Deserialize)]
+struct LegacyPortSelector {
+    number: Option<LegacyPortNumber>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyPortNumber {
+    Number(u32),
+    Text(String),
+}
+
+impl From<LegacyPortSelector> for PortSelector {
+    fn from(selector: LegacyPortSelector) -> Self {
+        let (number, name) = match selector.number {
+            Some(LegacyPortNumber::Number(number)) => (Some(number), selector.name),
+            Some(LegacyPortNumber::Text(text)) => match text.trim().parse() {
+                Ok(number) => (Some(number), selector.name),
+                Err(_) => (None, selector.name.or(Some(text))),
+            },
+            None => (None, selector.name),
+        };
+        PortSelector { number, name }
+    }
+}
+
+/// # Percent
+/// Percent specifies a percentage in the range of [0.0, 100.0], serialized as `{ value: 12.5 }`.
+/// A bare number, as written by older versions of this crate, is accepted on deserialize.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "LegacyPercent")]
+pub struct Percent {
+    pub value: f64,
+}
+
+impl From<f64> for Percent {
+    fn from(value: f64) -> Self {
+        Percent { value }
+    }
+}
 
 impl From<f32> for Percent {
     fn from(value: f32) -> Self {
-        Percent(value)
+        Percent {
+            value: value as f64,
+        }
     }
 }
 
 impl Eq for Percent {}
 
+/// The wire format of `Percent`, either the object or a bare number.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyPercent {
+    Object { value: f64 },
+    Number(f64),
+}
+
+impl From<LegacyPercent> for Percent {
+    fn from(percent: LegacyPercent) -> Self {
+        match percent {
+            LegacyPercent::Object { value } | LegacyPercent::Number(value) => Percent { value },
+        }
+    }
+}
+
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HeaderOperations {
     /// Overwrite the headers specified by key with the given values
     /// Required: No
-    pub set: Option<HashMap<String, String>>,
+    pub set: Option<Map<String, String>>,
 
     /// Append the given values to the headers specified by keys (will create a comma-separated list of values)
     /// Required: No
-    pub add: Option<HashMap<String, String>>,
+    pub add: Option<Map<String, String>>,
 
     /// Remove the specified headers
     /// Required: No
@@ -664,12 +1069,13 @@ pub struct HeaderOperations {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct FaultInjectionDelay {
     /// Add a fixed delay before forwarding the request. Format: 1h/1m/1s/1ms. MUST be >=1ms.
     /// Required: Yes
     #[serde(rename = "fixedDelay")]
-    pub fixed_delay: Duration,
+    pub fixed_delay: IstioDuration,
 
     /// Percentage of requests on which the delay will be injected.
     /// Required: No
@@ -681,20 +1087,74 @@ pub struct FaultInjectionDelay {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct FaultInjectionAbort {
-    /// HTTP status code to use to abort the Http request.
-    /// Required: Yes
+    /// HTTP status code to use to abort the Http request. Exactly one of httpStatus, grpcStatus and http2Error must be set.
+    /// Required: No
     #[serde(rename = "httpStatus")]
-    pub http_status: i32,
+    pub http_status: Option<i32>,
+
+    /// GRPC status code to use to abort the request. The supported codes are documented in https://github.com/grpc/grpc/blob/master/doc/statuscodes.md. Note: If you want to return the status “Unavailable”, then you should specify the code as `UNAVAILABLE`(all caps), but not `14`.
+    /// Required: No
+    #[serde(rename = "grpcStatus")]
+    pub grpc_status: Option<String>,
+
+    /// HTTP/2 error code to use to abort the Http request.
+    /// Required: No
+    #[serde(rename = "http2Error")]
+    pub http2_error: Option<String>,
 
     /// Percentage of requests to be aborted with the error code provided.
     /// Required: No
     pub percentage: Option<Percent>,
 }
 
+impl FaultInjectionAbort {
+    /// Aborts `percentage` of the requests, or all of them, with `code`.
+    pub fn new(code: AbortCode, percentage: Option<Percent>) -> Self {
+        let mut abort = FaultInjectionAbort {
+            http_status: None,
+            grpc_status: None,
+            http2_error: None,
+            percentage,
+        };
+        match code {
+            AbortCode::HttpStatus(status) => abort.http_status = Some(status),
+            AbortCode::GrpcStatus(status) => abort.grpc_status = Some(status),
+            AbortCode::Http2Error(error) => abort.http2_error = Some(error),
+        }
+        abort
+    }
+
+    /// The error code the requests are aborted with, `None` unless exactly one is set.
+    pub fn code(&self) -> Option<AbortCode> {
+        match (&self.http_status, &self.grpc_status, &self.http2_error) {
+            (Some(status), None, None) => Some(AbortCode::HttpStatus(*status)),
+            (None, Some(status), None) => Some(AbortCode::GrpcStatus(status.clone())),
+            (None, None, Some(error)) => Some(AbortCode::Http2Error(error.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// # AbortCode
+/// The error code of a [`FaultInjectionAbort`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbortCode {
+    /// HTTP status code, e.g. `503`.
+    HttpStatus(i32),
+
+    /// gRPC status name, e.g. `UNAVAILABLE`.
+    GrpcStatus(String),
+
+    /// HTTP/2 error code name, e.g. `REFUSED_STREAM`.
+    Http2Error(String),
+}
+
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum RedirectPortSelection {
     #[serde(rename = "FROM_PROTOCOL_DEFAULT")]
     FromProtocolDefault,
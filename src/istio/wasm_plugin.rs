@@ -0,0 +1,226 @@
+// WasmPlugin extends the proxies of the selected workloads with WebAssembly filters. The
+// `pluginConfig` handed to a plugin is free-form; the `PluginConfig` trait lets a strongly typed
+// config struct be stored in and read back from it.
+use crate::istio::google::Struct;
+use crate::istio::r#type::v1beta1::WorkloadSelector;
+use crate::istio::Map;
+use k8s_openapi::serde_json::{self, Value};
+use k8s_openapi::{Metadata, Resource};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WasmPlugin {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<WasmPluginSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for WasmPlugin {
+    const API_VERSION: &'static str = "extensions.istio.io/v1alpha1";
+    const GROUP: &'static str = "extensions.istio.io";
+    const KIND: &'static str = "WasmPlugin";
+    const VERSION: &'static str = "v1alpha1";
+    const URL_PATH_SEGMENT: &'static str = "wasmplugins";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for WasmPlugin {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+/// # WasmPlugin
+/// WasmPlugins provides a mechanism to extend the functionality provided by the Istio proxy through
+/// WebAssembly filters. The order of execution (as part of Envoy's filter chain) is determined by
+/// phase and priority settings, so the configuration of complex interactions between user-supplied
+/// WasmPlugins and Istio's internal filters is possible.
+///
+/// ```yaml
+/// apiVersion: extensions.istio.io/v1alpha1
+/// kind: WasmPlugin
+/// metadata:
+///   name: openid-connect
+///   namespace: istio-ingress
+/// spec:
+///   selector:
+///     matchLabels:
+///       istio: ingressgateway
+///   url: file:///opt/filters/openid.wasm
+///   sha256: 1ef0c9a92b0420cf25f7fe5d481b231464bc88f486ca3b9c83ed5cc21d2f6210
+///   phase: AUTHN
+///   pluginConfig:
+///     openid_server: authn
+///     openid_realm: ingress
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WasmPluginSpec {
+    // Criteria used to select the specific set of pods/VMs on which this plugin configuration should be applied. If omitted, this configuration will be applied to all workload instances in the same namespace. If the WasmPlugin is present in the config root namespace, it will be applied to all applicable workloads in any namespace.
+    // No
+    pub selector: Option<WorkloadSelector>,
+
+    // URL of a Wasm module or OCI container. If no scheme is present, defaults to oci://, referencing an OCI image. Other valid schemes are file:// for referencing .wasm module files present locally within the proxy container, and http[s]:// for .wasm module files hosted remotely.
+    // Yes
+    pub url: String,
+
+    // SHA256 checksum that will be used to verify Wasm module or OCI container. If the url field already references a SHA256 (using the @sha256: notation), it must match the value of this field. If an OCI image is referenced by tag and this field is set, its checksum will be verified against the contents of this field after pulling.
+    // No
+    pub sha256: Option<String>,
+
+    // The pull behaviour to be applied when fetching an OCI image. Only relevant when images are referenced by tag instead of SHA. Defaults to IfNotPresent, except when an OCI image is referenced in the url and the latest tag is used, in which case Always is the default, mirroring K8s behaviour. Setting is ignored if url field is referencing a Wasm module directly using file:// or http[s]://
+    // No
+    #[serde(rename = "imagePullPolicy")]
+    pub image_pull_policy: Option<PullPolicy>,
+
+    // Credentials to use for OCI image pulling. Name of a K8s Secret in the same namespace as the WasmPlugin that contains a docker pull secret which is to be used to authenticate against the registry when pulling the image.
+    // No
+    #[serde(rename = "imagePullSecret")]
+    pub image_pull_secret: Option<String>,
+
+    // The configuration that will be passed on to the plugin.
+    // No
+    #[serde(rename = "pluginConfig")]
+    pub plugin_config: Option<Struct>,
+
+    // The plugin name to be used in the Envoy configuration (used to be called rootID). Some .wasm modules might require this value to select the Wasm plugin to execute.
+    // No
+    #[serde(rename = "pluginName")]
+    pub plugin_name: Option<String>,
+
+    // Determines where in the filter chain this WasmPlugin is to be injected.
+    // No
+    pub phase: Option<PluginPhase>,
+
+    // Determines ordering of WasmPlugins in the same phase. When multiple WasmPlugins are applied to the same workload in the same phase, they will be applied by priority, in descending order. If priority is not set, or two WasmPlugins exist with the same value, the ordering will be deterministically derived from name and namespace of the WasmPlugins. Defaults to 0.
+    // No
+    pub priority: Option<i32>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl WasmPluginSpec {
+    /// Stores `config` as the plugin config.
+    pub fn set_plugin_config<C: PluginConfig>(&mut self, config: &C) -> serde_json::Result<()> {
+        self.plugin_config = Some(config.to_struct()?);
+        Ok(())
+    }
+
+    /// Reads the plugin config as `C`, `None` when no config is set.
+    pub fn plugin_config_as<C: PluginConfig>(&self) -> Option<serde_json::Result<C>> {
+        self.plugin_config.as_ref().map(C::from_struct)
+    }
+}
+
+/// # PullPolicy
+/// The pull behaviour to be applied when fetching a Wasm module, mirroring K8s behaviour.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
+pub enum PullPolicy {
+    UNSPECIFIED_POLICY,
+    IfNotPresent,
+    Always,
+}
+
+/// # PluginPhase
+/// The phase in the filter chain where the plugin will be injected.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[allow(non_camel_case_types)]
+pub enum PluginPhase {
+    /// Control plane decides where to insert the plugin. This will generally be at the end of the
+    /// filter chain, right before the Router. Do not specify PluginPhase if the plugin is
+    /// independent of others.
+    UNSPECIFIED_PHASE,
+
+    /// Insert plugin before Istio authentication filters.
+    AUTHN,
+
+    /// Insert plugin before Istio authorization filters and after Istio authentication filters.
+    AUTHZ,
+
+    /// Insert plugin before Istio stats filters and after Istio authorization filters.
+    STATS,
+}
+
+/// # PluginConfig
+/// A typed plugin config. Implement it for a struct serializing to a JSON object to store it as the
+/// `pluginConfig` of a WasmPlugin.
+pub trait PluginConfig: Serialize + DeserializeOwned {
+    fn to_struct(&self) -> serde_json::Result<Struct> {
+        serde_json::from_value(serde_json::to_value(self)?)
+    }
+
+    fn from_struct(config: &Struct) -> serde_json::Result<Self> {
+        let fields: serde_json::Map<String, Value> = config
+            .fields
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        serde_json::from_value(Value::Object(fields))
+    }
+}
+
+/// # BasicAuth
+/// The config of the basic auth plugin of istio-ecosystem/wasm-extensions.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BasicAuth {
+    pub basic_auth_rules: Vec<BasicAuthRule>,
+}
+
+impl PluginConfig for BasicAuth {}
+
+/// # BasicAuthRule
+/// Requires one of `credentials` for the requests with a matching path and method.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BasicAuthRule {
+    pub prefix: Option<String>,
+    pub exact: Option<String>,
+    pub suffix: Option<String>,
+
+    /// The methods the rule applies to, all when empty.
+    pub request_methods: Vec<String>,
+
+    /// `user:password`, plain or base64 encoded.
+    pub credentials: Vec<String>,
+}
+
+/// # OpenPolicyAgent
+/// The config of the Open Policy Agent plugin of istio-ecosystem/wasm-extensions, which asks an OPA
+/// server whether a request is allowed.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OpenPolicyAgent {
+    /// `host:port` of the OPA server, e.g. `opa.istio-system.svc.cluster.local:8181`.
+    pub opa_service_host: String,
+
+    /// The Envoy cluster of the OPA server, e.g.
+    /// `outbound|8181||opa.istio-system.svc.cluster.local`.
+    pub opa_cluster_name: String,
+
+    /// How long a decision is cached, in seconds.
+    pub check_result_cache_valid_sec: Option<u64>,
+}
+
+impl PluginConfig for OpenPolicyAgent {}
@@ -0,0 +1,126 @@
+// Watching the Istio configuration of a cluster with kube-runtime. A `Scope` says which resources
+// to observe, a namespace and a label selector, and `watch` and `reflect` turn it into a watcher
+// stream or a reflector store kept up to date by the stream. The stream has to be polled for the
+// store to fill, e.g. with `stream.try_for_each(|_| async { Ok(()) })` in a task of its own.
+// Needs the `watch` feature.
+use crate::istio::destination_rule::DestinationRule;
+use crate::istio::gateway::Gateway;
+use crate::istio::virtual_service::VirtualService;
+use futures::Stream;
+use kube::api::{Api, ListParams};
+use kube::runtime::reflector::{self, Store};
+use kube::runtime::watcher::{self, Event};
+use kube::{Client, Resource};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+/// # Scope
+/// The resources to watch: the ones of a namespace or of all namespaces, optionally narrowed
+/// down by a label selector.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    /// The namespace to watch; all namespaces when unset.
+    pub namespace: Option<String>,
+
+    /// A label selector like `app=reviews,tier!=canary`.
+    pub label_selector: Option<String>,
+}
+
+impl Scope {
+    /// The resources of all namespaces.
+    pub fn all() -> Self {
+        Scope::default()
+    }
+
+    /// The resources of `namespace`.
+    pub fn namespace(namespace: &str) -> Self {
+        Scope {
+            namespace: Some(namespace.to_string()),
+            ..Scope::default()
+        }
+    }
+
+    pub fn with_label_selector(mut self, label_selector: &str) -> Self {
+        self.label_selector = Some(label_selector.to_string());
+        self
+    }
+
+    /// An `Api` of the namespace of the scope.
+    pub fn api<K>(&self, client: Client) -> Api<K>
+    where
+        K: Resource<DynamicType = ()>,
+    {
+        match &self.namespace {
+            Some(namespace) => Api::namespaced(client, namespace),
+            None => Api::all(client),
+        }
+    }
+
+    /// The list parameters selecting the labels of the scope.
+    pub fn list_params(&self) -> ListParams {
+        match &self.label_selector {
+            Some(label_selector) => ListParams::default().labels(label_selector),
+            None => ListParams::default(),
+        }
+    }
+}
+
+/// Watches the resources of kind `K` in `scope`.
+pub fn watch<K>(
+    client: Client,
+    scope: &Scope,
+) -> impl Stream<Item = watcher::Result<Event<K>>> + Send
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + 'static,
+{
+    watcher::watcher(scope.api(client), scope.list_params())
+}
+
+/// A store of the resources of kind `K` in `scope`, and the watch stream filling it.
+pub fn reflect<K>(
+    client: Client,
+    scope: &Scope,
+) -> (
+    Store<K>,
+    impl Stream<Item = watcher::Result<Event<K>>> + Send,
+)
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    let writer = reflector::store::Writer::default();
+    let store = writer.as_reader();
+    (store, reflector::reflector(writer, watch(client, scope)))
+}
+
+/// A store of the VirtualServices in `scope`, and the watch stream filling it.
+pub fn virtual_services(
+    client: Client,
+    scope: &Scope,
+) -> (
+    Store<VirtualService>,
+    impl Stream<Item = watcher::Result<Event<VirtualService>>> + Send,
+) {
+    reflect(client, scope)
+}
+
+/// A store of the DestinationRules in `scope`, and the watch stream filling it.
+pub fn destination_rules(
+    client: Client,
+    scope: &Scope,
+) -> (
+    Store<DestinationRule>,
+    impl Stream<Item = watcher::Result<Event<DestinationRule>>> + Send,
+) {
+    reflect(client, scope)
+}
+
+/// A store of the Gateways in `scope`, and the watch stream filling it.
+pub fn gateways(
+    client: Client,
+    scope: &Scope,
+) -> (
+    Store<Gateway>,
+    impl Stream<Item = watcher::Result<Event<Gateway>>> + Send,
+) {
+    reflect(client, scope)
+}
@@ -0,0 +1,322 @@
+// Route destination weights. A `Weight` is always within 0-100, `Weights` are the weights of the
+// destinations of one route, which Istio requires to add up to 100 when traffic is split.
+use std::convert::TryFrom;
+use std::fmt;
+
+/// # Weight
+/// The percentage of the traffic of a route sent to a destination.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "i64", into = "u32")]
+pub struct Weight(u32);
+
+impl Weight {
+    pub const MIN: Weight = Weight(0);
+    pub const MAX: Weight = Weight(100);
+
+    pub fn new(weight: u32) -> Result<Self, WeightError> {
+        Weight::try_from(weight as i64)
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// The weight left for the other destinations of a route, `100 - self`.
+    pub fn complement(self) -> Weight {
+        Weight(100 - self.0)
+    }
+}
+
+impl TryFrom<i64> for Weight {
+    type Error = WeightError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        if (0..=100).contains(&value) {
+            Ok(Weight(value as u32))
+        } else {
+            Err(WeightError::OutOfRange(value))
+        }
+    }
+}
+
+impl TryFrom<u32> for Weight {
+    type Error = WeightError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Weight::new(value)
+    }
+}
+
+impl From<Weight> for u32 {
+    fn from(weight: Weight) -> Self {
+        weight.0
+    }
+}
+
+impl fmt::Display for Weight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// # Weights
+/// The weights of the destinations of a route, in the order of the destinations.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Weights(Vec<Weight>);
+
+impl Weights {
+    pub fn new(weights: Vec<Weight>) -> Self {
+        Weights(weights)
+    }
+
+    /// Turns the optional weights of a route into weights adding up to 100. Unset weights count as
+    /// 0, unless no weight is set at all, in which case the traffic is split evenly. Other sums are
+    /// scaled proportionally, handing out the rounding remainder by largest fraction.
+    pub fn normalize(weights: impl IntoIterator<Item = Option<Weight>>) -> Self {
+        let weights: Vec<Option<Weight>> = weights.into_iter().collect();
+        let raw: Vec<u64> = if weights.iter().all(Option::is_none) {
+            vec![1; weights.len()]
+        } else {
+            weights
+                .iter()
+                .map(|weight| weight.map_or(0, |weight| weight.0 as u64))
+                .collect()
+        };
+        Weights(scale(&raw, 100))
+    }
+
+    pub fn total(&self) -> u32 {
+        self.0.iter().map(|weight| weight.0).sum()
+    }
+
+    /// Checks that the weights add up to 100. A single destination receives all traffic whatever
+    /// its weight.
+    pub fn validate(&self) -> Result<(), WeightError> {
+        match self.total() {
+            total if self.0.len() > 1 && total != 100 => Err(WeightError::Total(total)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the weight of the destination at `index` and scales the weights of the other
+    /// destinations proportionally to make up the rest. The others share the rest evenly when
+    /// they all weigh 0; a single destination always keeps all traffic.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn redistribute(&mut self, index: usize, weight: Weight) {
+        assert!(index < self.0.len(), "no destination at index {}", index);
+        if self.0.len() == 1 {
+            self.0[0] = Weight::MAX;
+            return;
+        }
+        let mut others: Vec<u64> = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, weight)| weight.0 as u64)
+            .collect();
+        if others.iter().all(|weight| *weight == 0) {
+            others.iter_mut().for_each(|weight| *weight = 1);
+        }
+        let mut scaled = scale(&others, weight.complement().0).into_iter();
+        for (i, slot) in self.0.iter_mut().enumerate() {
+            *slot = if i == index {
+                weight
+            } else {
+                scaled.next().expect("one weight per other destination")
+            };
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Weight> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Weight] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<Weight> {
+        self.0
+    }
+}
+
+/// Scales `raw` to add up to `target`, handing out the rounding remainder by largest fraction. All
+/// of `target` goes to the first weight when they add up to 0.
+fn scale(raw: &[u64], target: u32) -> Vec<Weight> {
+    if raw.is_empty() {
+        return Vec::new();
+    }
+    let target = target as u64;
+    let total: u64 = raw.iter().sum();
+    if total == 0 {
+        let mut first = vec![Weight(0); raw.len()];
+        first[0] = Weight(target as u32);
+        return first;
+    }
+    let mut scaled: Vec<u64> = raw.iter().map(|w| w * target / total).collect();
+    let mut remainders: Vec<(u64, usize)> = raw
+        .iter()
+        .enumerate()
+        .map(|(index, w)| (w * target % total, index))
+        .collect();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let missing = target - scaled.iter().sum::<u64>();
+    for (_, index) in remainders.into_iter().take(missing as usize) {
+        scaled[index] += 1;
+    }
+    scaled.into_iter().map(|w| Weight(w as u32)).collect()
+}
+
+/// # WeightError
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WeightError {
+    OutOfRange(i64),
+
+    /// The weights of a split route do not add up to 100.
+    Total(u32),
+}
+
+impl fmt::Display for WeightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeightError::OutOfRange(weight) => write!(f, "weight {} is outside of 0-100", weight),
+            WeightError::Total(total) => write!(f, "weights add up to {} instead of 100", total),
+        }
+    }
+}
+
+impl std::error::Error for WeightError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::serde_json::{self, json};
+
+    fn weights(weights: &[u32]) -> Weights {
+        Weights::new(weights.iter().map(|w| Weight::new(*w).unwrap()).collect())
+    }
+
+    fn values(weights: &Weights) -> Vec<u32> {
+        weights.iter().map(Weight::get).collect()
+    }
+
+    #[test]
+    fn weights_are_bounded_by_0_and_100() {
+        assert_eq!(Weight::new(0), Ok(Weight::MIN));
+        assert_eq!(Weight::new(100), Ok(Weight::MAX));
+        assert_eq!(Weight::new(101), Err(WeightError::OutOfRange(101)));
+        assert_eq!(Weight::try_from(-1i64), Err(WeightError::OutOfRange(-1)));
+        assert_eq!(Weight::MIN.complement(), Weight::MAX);
+        assert_eq!(Weight::MAX.complement(), Weight::MIN);
+        assert_eq!(Weight::new(30).unwrap().complement().get(), 70);
+
+        assert_eq!(serde_json::to_value(Weight::MAX).unwrap(), json!(100));
+        assert_eq!(
+            serde_json::from_value::<Weight>(json!(25)).unwrap().get(),
+            25
+        );
+        assert!(serde_json::from_value::<Weight>(json!(101)).is_err());
+        assert!(serde_json::from_value::<Weight>(json!(-5)).is_err());
+    }
+
+    #[test]
+    fn normalize_splits_unset_weights_evenly() {
+        assert_eq!(values(&Weights::normalize(vec![None, None])), vec![50, 50]);
+        assert_eq!(
+            values(&Weights::normalize(vec![None, None, None])),
+            vec![34, 33, 33]
+        );
+        assert_eq!(values(&Weights::normalize(vec![None])), vec![100]);
+        assert!(Weights::normalize(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn normalize_scales_set_weights_to_100() {
+        let set = |weights: &[Option<u32>]| {
+            values(&Weights::normalize(weights.iter().map(|weight| {
+                weight.map(|weight| Weight::new(weight).unwrap())
+            })))
+        };
+        assert_eq!(set(&[Some(80), Some(20)]), vec![80, 20]);
+        assert_eq!(set(&[Some(30), Some(30)]), vec![50, 50]);
+        // 14.28, 28.57 and 57.14: the remainder goes to the largest fraction.
+        assert_eq!(set(&[Some(10), Some(20), Some(40)]), vec![14, 29, 57]);
+        assert_eq!(set(&[Some(1), Some(1), Some(1)]), vec![34, 33, 33]);
+        // Unset weights count as 0 once any weight is set.
+        assert_eq!(set(&[Some(50), None]), vec![100, 0]);
+        // All traffic goes to the first destination when every weight is 0.
+        assert_eq!(set(&[Some(0), Some(0)]), vec![100, 0]);
+        assert_eq!(set(&[Some(100), Some(0)]), vec![100, 0]);
+    }
+
+    #[test]
+    fn redistribute_scales_the_other_destinations() {
+        let mut split = weights(&[80, 20]);
+        split.redistribute(1, Weight::new(50).unwrap());
+        assert_eq!(values(&split), vec![50, 50]);
+
+        let mut split = weights(&[50, 30, 20]);
+        split.redistribute(0, Weight::MIN);
+        assert_eq!(values(&split), vec![0, 60, 40]);
+        split.redistribute(0, Weight::MAX);
+        assert_eq!(values(&split), vec![100, 0, 0]);
+
+        // 37.5 each: the remainder goes to the first of equal fractions.
+        let mut split = weights(&[34, 33, 33]);
+        split.redistribute(0, Weight::new(25).unwrap());
+        assert_eq!(values(&split), vec![25, 38, 37]);
+        assert_eq!(split.total(), 100);
+    }
+
+    #[test]
+    fn redistribute_shares_evenly_between_destinations_without_traffic() {
+        let mut split = weights(&[100, 0, 0]);
+        split.redistribute(0, Weight::new(40).unwrap());
+        assert_eq!(values(&split), vec![40, 30, 30]);
+
+        let mut split = weights(&[100, 0, 0]);
+        split.redistribute(1, Weight::new(10).unwrap());
+        assert_eq!(values(&split), vec![90, 10, 0]);
+    }
+
+    #[test]
+    fn a_single_destination_keeps_all_traffic() {
+        let mut single = weights(&[30]);
+        assert_eq!(single.validate(), Ok(()));
+        single.redistribute(0, Weight::new(10).unwrap());
+        assert_eq!(values(&single), vec![100]);
+    }
+
+    #[test]
+    #[should_panic(expected = "no destination at index 2")]
+    fn redistribute_panics_out_of_bounds() {
+        weights(&[50, 50]).redistribute(2, Weight::MAX);
+    }
+
+    #[test]
+    fn validate_requires_split_weights_to_add_up_to_100() {
+        assert_eq!(weights(&[60, 40]).validate(), Ok(()));
+        assert_eq!(weights(&[100, 0]).validate(), Ok(()));
+        assert_eq!(weights(&[50, 40]).validate(), Err(WeightError::Total(90)));
+        assert_eq!(
+            weights(&[0, 0]).validate().unwrap_err().to_string(),
+            "weights add up to 0 instead of 100"
+        );
+        assert_eq!(Weights::default().validate(), Ok(()));
+    }
+}
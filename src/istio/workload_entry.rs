@@ -0,0 +1,174 @@
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadEntry {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<WorkloadEntrySpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for WorkloadEntry {
+    const API_VERSION: &'static str = "networking.istio.io/v1beta1";
+    const GROUP: &'static str = "networking.istio.io";
+    const KIND: &'static str = "WorkloadEntry";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "workloadentries";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for WorkloadEntry {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl WorkloadEntry {
+    /// A workload reachable at `address`, e.g. the IP of a VM.
+    pub fn new(name: &str, namespace: &str, address: &str) -> Self {
+        WorkloadEntry {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(WorkloadEntrySpec::new(address)),
+            status: None,
+        }
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.spec = Some(self.spec.take().unwrap_or_default().with_label(key, value));
+        self
+    }
+
+    pub fn with_port(mut self, name: &str, port: u32) -> Self {
+        self.spec = Some(self.spec.take().unwrap_or_default().with_port(name, port));
+        self
+    }
+
+    pub fn with_service_account(mut self, service_account: &str) -> Self {
+        self.spec_mut().service_account = Some(service_account.to_string());
+        self
+    }
+
+    pub fn with_network(mut self, network: &str) -> Self {
+        self.spec_mut().network = Some(network.to_string());
+        self
+    }
+
+    pub fn with_locality(mut self, locality: &str) -> Self {
+        self.spec_mut().locality = Some(locality.to_string());
+        self
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.spec_mut().weight = Some(weight);
+        self
+    }
+
+    fn spec_mut(&mut self) -> &mut WorkloadEntrySpec {
+        self.spec.get_or_insert_with(WorkloadEntrySpec::default)
+    }
+}
+
+/// # WorkloadEntry
+/// WorkloadEntry enables specifying the properties of a single non-Kubernetes workload such a VM
+/// or a bare metal services that can be referred to by service entries. It is also used as the
+/// `endpoints` of a `ServiceEntry`, where the `address` is a DNS name or IP of the endpoint.
+///
+/// ```yaml
+/// apiVersion: networking.istio.io/v1beta1
+/// kind: ServiceEntry
+/// metadata:
+///   name: external-svc-mongocluster
+/// spec:
+///   hosts:
+///   - mymongodb.somedomain # not used
+///   addresses:
+///   - 192.192.192.192/24 # VIPs
+///   ports:
+///   - number: 27018
+///     name: mongodb
+///     protocol: MONGO
+///   location: MESH_INTERNAL
+///   resolution: STATIC
+///   endpoints:
+///   - address: 2.2.2.2
+///   - address: 3.3.3.3
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadEntrySpec {
+    // Address associated with the network endpoint without the port. Domain names can be used if and only if the resolution is set to DNS, and must be fully-qualified without wildcards. Use the form unix:///absolute/path/to/socket for Unix domain socket endpoints. If address is empty, network must be specified.
+    // No
+    pub address: Option<String>,
+
+    // Set of ports associated with the endpoint. If the port map is specified, it must be a map of servicePortName to this endpoint’s port, such that traffic to the service port will be forwarded to the endpoint port that maps to the service’s portName. If omitted, and the targetPort is specified as part of the service’s port specification, traffic to the service port will be forwarded to one of the endpoints on the specified targetPort. If both the targetPort and endpoint’s port map are not specified, traffic to a service port will be forwarded to one of the endpoints on the same port.
+    // No
+    pub ports: Option<Map<String, u32>>,
+
+    // One or more labels associated with the endpoint.
+    // No
+    pub labels: Option<Map<String, String>>,
+
+    // Network enables Istio to group endpoints resident in the same L3 domain/network. All endpoints in the same network are assumed to be directly reachable from one another. When endpoints in different networks cannot reach each other directly, an Istio Gateway can be used to establish connectivity (usually using the AUTO_PASSTHROUGH mode in a Gateway Server). This is an advanced configuration used typically for spanning an Istio mesh over multiple clusters.
+    // No
+    pub network: Option<String>,
+
+    // The locality associated with the endpoint. A locality corresponds to a failure domain (e.g., country/region/zone). Arbitrary failure domain hierarchies can be represented by separating each encapsulating failure domain by /. For example, the locality of an an endpoint in US, in US-East-1 region, within availability zone az-1, in data center rack r11 can be represented as us/us-east-1/az-1/r11. Istio will configure the sidecar to route to endpoints within the same locality as the sidecar. If none of the endpoints in the locality are available, endpoints parent locality (but within the same network ID) will be chosen. For example, if there are two endpoints in same network (networkID “n1”), say e1 with locality us/us-east-1/az-1/r11 and e2 with locality us/us-east-1/az-2/r12, a sidecar from us/us-east-1/az-1/r11 locality will prefer e1 from the same locality over e2 from a different locality. Endpoint e2 could be the IP associated with a gateway (that bridges networks n1 and n2), or the IP associated with a standard service endpoint.
+    // No
+    pub locality: Option<String>,
+
+    // The load balancing weight associated with the endpoint. Endpoints with higher weights will receive proportionally higher traffic.
+    // No
+    pub weight: Option<u32>,
+
+    // The service account associated with the workload if a sidecar is present in the workload. The service account must be present in the same namespace as the configuration ( WorkloadEntry or a ServiceEntry)
+    // No
+    #[serde(rename = "serviceAccount")]
+    pub service_account: Option<String>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl WorkloadEntrySpec {
+    pub fn new(address: &str) -> Self {
+        WorkloadEntrySpec {
+            address: Some(address.to_string()),
+            ..WorkloadEntrySpec::default()
+        }
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels
+            .get_or_insert_with(Map::default)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Maps the service port `name` to `port` on the workload.
+    pub fn with_port(mut self, name: &str, port: u32) -> Self {
+        self.ports
+            .get_or_insert_with(Map::default)
+            .insert(name.to_string(), port);
+        self
+    }
+}
@@ -0,0 +1,309 @@
+use crate::istio::workload_entry::WorkloadEntrySpec;
+use crate::istio::Map;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::serde_json::Value;
+use k8s_openapi::{Metadata, Resource};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadGroup {
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of a service. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<WorkloadGroupSpec>,
+
+    /// Most recently observed status of the service. Populated by the system. Read-only. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub status: Option<()>,
+}
+
+impl Resource for WorkloadGroup {
+    const API_VERSION: &'static str = "networking.istio.io/v1beta1";
+    const GROUP: &'static str = "networking.istio.io";
+    const KIND: &'static str = "WorkloadGroup";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "workloadgroups";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl Metadata for WorkloadGroup {
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as Metadata>::Ty {
+        &self.metadata
+    }
+    fn metadata_mut(&mut self) -> &mut <Self as Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl WorkloadGroup {
+    /// A group whose WorkloadEntries are created from `template` as the VMs register.
+    pub fn new(name: &str, namespace: &str, template: WorkloadEntrySpec) -> Self {
+        WorkloadGroup {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: Some(WorkloadGroupSpec {
+                metadata: None,
+                template,
+                probe: None,
+                extra: Map::new(),
+            }),
+            status: None,
+        }
+    }
+
+    /// Adds a label to the WorkloadEntries of the group.
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.metadata
+                .get_or_insert_with(WorkloadGroupObjectMeta::default)
+                .labels
+                .get_or_insert_with(Map::default)
+                .insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    pub fn with_probe(mut self, probe: ReadinessProbe) -> Self {
+        if let Some(spec) = &mut self.spec {
+            spec.probe = Some(probe);
+        }
+        self
+    }
+}
+
+/// # WorkloadGroup
+/// WorkloadGroup describes a collection of workload instances. It provides a specification that the
+/// workload instances can use to bootstrap their proxies, including the metadata and identity. It
+/// is only intended to be used with non-k8s workloads like Virtual Machines, and is meant to mimic
+/// the existing sidecar injection and deployment specification model used for Kubernetes workloads
+/// to bootstrap Istio proxies.
+///
+/// ```yaml
+/// apiVersion: networking.istio.io/v1beta1
+/// kind: WorkloadGroup
+/// metadata:
+///   name: reviews
+///   namespace: bookinfo
+/// spec:
+///   metadata:
+///     labels:
+///       app.kubernetes.io/name: reviews
+///       app.kubernetes.io/version: "1.3.4"
+///   template:
+///     ports:
+///       grpc: 3550
+///       http: 8080
+///     serviceAccount: default
+///   probe:
+///     initialDelaySeconds: 5
+///     timeoutSeconds: 3
+///     periodSeconds: 4
+///     successThreshold: 3
+///     failureThreshold: 3
+///     httpGet:
+///      path: /foo/bar
+///      host: 127.0.0.1
+///      port: 3100
+///      scheme: HTTPS
+///      httpHeaders:
+///      - name: Lit-Header
+///        value: Im-The-Best
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadGroupSpec {
+    // Metadata that will be used for all corresponding WorkloadEntries. User labels for a workload should be set here in metadata rather than in template.
+    // No
+    pub metadata: Option<WorkloadGroupObjectMeta>,
+
+    // Template to be used for the generation of WorkloadEntry resources that belong to this WorkloadGroup. Please note that address and labels fields should not be set in the template, and an empty serviceAccount should default to default. The workload identities (mTLS certificates) will be bootstrapped using the specified service account’s token. Workload entries in this group will be in the same namespace as the workload group, and inherit the labels and annotations from the above metadata field.
+    // Yes
+    pub template: WorkloadEntrySpec,
+
+    // ReadinessProbe describes the configuration the user must provide for healthchecking on their workload. This configuration mirrors K8S in both syntax and logic for the most part.
+    // No
+    pub probe: Option<ReadinessProbe>,
+
+    // Fields unknown to this crate, e.g. ones added by a newer Istio release. They are kept as is so that the resource survives a round trip.
+    // No
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// # WorkloadGroup.ObjectMeta
+/// ObjectMeta describes metadata that will be attached to a WorkloadEntry. It is a subset of the
+/// supported Kubernetes metadata.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkloadGroupObjectMeta {
+    // Labels to attach
+    // No
+    pub labels: Option<Map<String, String>>,
+
+    // Annotations to attach
+    // No
+    pub annotations: Option<Map<String, String>>,
+}
+
+/// # ReadinessProbe
+/// The health check of the workloads of a group, run by their proxies. Exactly one of `httpGet`,
+/// `tcpSocket` and `exec` is set.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReadinessProbe {
+    // Number of seconds after the container has started before readiness probes are initiated.
+    // No
+    #[serde(rename = "initialDelaySeconds")]
+    pub initial_delay_seconds: Option<i32>,
+
+    // Number of seconds after which the probe times out. Defaults to 1 second. Minimum value is 1 second.
+    // No
+    #[serde(rename = "timeoutSeconds")]
+    pub timeout_seconds: Option<i32>,
+
+    // How often (in seconds) to perform the probe. Default to 10 seconds. Minimum value is 1 second.
+    // No
+    #[serde(rename = "periodSeconds")]
+    pub period_seconds: Option<i32>,
+
+    // Minimum consecutive successes for the probe to be considered successful after having failed. Defaults to 1 second.
+    // No
+    #[serde(rename = "successThreshold")]
+    pub success_threshold: Option<i32>,
+
+    // Minimum consecutive failures for the probe to be considered failed after having succeeded. Defaults to 3 seconds.
+    // No
+    #[serde(rename = "failureThreshold")]
+    pub failure_threshold: Option<i32>,
+
+    // httpGet is performed to a given endpoint and the status/able to connect determines health.
+    // No
+    #[serde(rename = "httpGet")]
+    pub http_get: Option<HTTPHealthCheckConfig>,
+
+    // Health is determined by if the proxy is able to connect.
+    // No
+    #[serde(rename = "tcpSocket")]
+    pub tcp_socket: Option<TCPHealthCheckConfig>,
+
+    // Health is determined by how the command that is executed exited.
+    // No
+    pub exec: Option<ExecHealthCheckConfig>,
+}
+
+impl ReadinessProbe {
+    /// Probes `path` over HTTP on `port` of the workload.
+    pub fn http_get(port: u32, path: &str) -> Self {
+        ReadinessProbe {
+            http_get: Some(HTTPHealthCheckConfig {
+                path: Some(path.to_string()),
+                port,
+                host: None,
+                scheme: None,
+                http_headers: None,
+            }),
+            ..ReadinessProbe::default()
+        }
+    }
+
+    /// Probes whether the proxy can connect to `port` of the workload.
+    pub fn tcp_socket(port: u32) -> Self {
+        ReadinessProbe {
+            tcp_socket: Some(TCPHealthCheckConfig { host: None, port }),
+            ..ReadinessProbe::default()
+        }
+    }
+
+    /// Probes by running `command` on the workload, healthy when it exits with 0.
+    pub fn exec<C: Into<String>>(command: impl IntoIterator<Item = C>) -> Self {
+        ReadinessProbe {
+            exec: Some(ExecHealthCheckConfig {
+                command: Some(command.into_iter().map(Into::into).collect()),
+            }),
+            ..ReadinessProbe::default()
+        }
+    }
+
+    pub fn with_period_seconds(mut self, period_seconds: i32) -> Self {
+        self.period_seconds = Some(period_seconds);
+        self
+    }
+
+    pub fn with_timeout_seconds(mut self, timeout_seconds: i32) -> Self {
+        self.timeout_seconds = Some(timeout_seconds);
+        self
+    }
+}
+
+/// # HTTPHealthCheckConfig
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HTTPHealthCheckConfig {
+    // Path to access on the HTTP server.
+    // No
+    pub path: Option<String>,
+
+    // Port on which the endpoint lives.
+    // Yes
+    pub port: u32,
+
+    // Host name to connect to, defaults to the pod IP. You probably want to set “Host” in httpHeaders instead.
+    // No
+    pub host: Option<String>,
+
+    // HTTP or HTTPS, defaults to HTTP
+    // No
+    pub scheme: Option<String>,
+
+    // Headers the proxy will pass on to make the request. Allows repeated headers.
+    // No
+    #[serde(rename = "httpHeaders")]
+    pub http_headers: Option<Vec<HTTPHeader>>,
+}
+
+/// # HTTPHeader
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HTTPHeader {
+    // The header field name
+    // No
+    pub name: Option<String>,
+
+    // The header field value
+    // No
+    pub value: Option<String>,
+}
+
+/// # TCPHealthCheckConfig
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TCPHealthCheckConfig {
+    // Host to connect to, defaults to localhost
+    // No
+    pub host: Option<String>,
+
+    // Port of host
+    // Yes
+    pub port: u32,
+}
+
+/// # ExecHealthCheckConfig
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExecHealthCheckConfig {
+    // Command to run. Exit status of 0 is treated as live/healthy and non-zero is unhealthy.
+    // No
+    pub command: Option<Vec<String>>,
+}
@@ -3,13 +3,15 @@
 //! In this case kube-extra is adding a well-known istio resource - `VirtualService` to allow to
 //! better manipulation of virtual services.
 
-#[macro_use]
-extern crate serde;
+#[cfg(feature = "istio")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "istio")]
 #[macro_use]
 extern crate serde_with;
 extern crate k8s_openapi;
+// Lets the code generated by the derive macros name this crate from inside it as well.
+extern crate self as kube_resource_extras;
 
 #[cfg(feature = "istio")]
 pub mod istio;
@@ -0,0 +1,486 @@
+// Rust code generation from the parsed protos, one module per proto package. Messages become serde
+// structs with every field optional, as in the proto3 JSON mapping, and the messages that are
+// custom resources get a resource struct with `impl Resource`, their spec keeping the message
+// name with a `Spec` suffix. The output follows the layout of the hand-written types.
+use crate::proto::{Enum, Field, FieldType, File, Message};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+/// The custom resources of the Istio API, by kind, with their plural name.
+const KINDS: &[(&str, &str)] = &[
+    ("AuthorizationPolicy", "authorizationpolicies"),
+    ("DestinationRule", "destinationrules"),
+    ("EnvoyFilter", "envoyfilters"),
+    ("Gateway", "gateways"),
+    ("PeerAuthentication", "peerauthentications"),
+    ("ProxyConfig", "proxyconfigs"),
+    ("RequestAuthentication", "requestauthentications"),
+    ("ServiceEntry", "serviceentries"),
+    ("Sidecar", "sidecars"),
+    ("Telemetry", "telemetries"),
+    ("VirtualService", "virtualservices"),
+    ("WasmPlugin", "wasmplugins"),
+    ("WorkloadEntry", "workloadentries"),
+    ("WorkloadGroup", "workloadgroups"),
+];
+
+/// The well-known protobuf types, in their JSON mapping.
+const WELL_KNOWN: &[(&str, &str)] = &[
+    ("google.protobuf.Any", "k8s_openapi::serde_json::Value"),
+    ("google.protobuf.BoolValue", "bool"),
+    ("google.protobuf.BytesValue", "String"),
+    ("google.protobuf.DoubleValue", "f64"),
+    ("google.protobuf.Duration", "String"),
+    ("google.protobuf.Empty", "k8s_openapi::serde_json::Value"),
+    ("google.protobuf.FloatValue", "f32"),
+    ("google.protobuf.Int32Value", "i32"),
+    ("google.protobuf.Int64Value", "i64"),
+    (
+        "google.protobuf.ListValue",
+        "Vec<k8s_openapi::serde_json::Value>",
+    ),
+    ("google.protobuf.StringValue", "String"),
+    ("google.protobuf.Struct", "k8s_openapi::serde_json::Value"),
+    ("google.protobuf.Timestamp", "String"),
+    ("google.protobuf.UInt32Value", "u32"),
+    ("google.protobuf.UInt64Value", "u64"),
+    ("google.protobuf.Value", "k8s_openapi::serde_json::Value"),
+];
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while",
+];
+
+#[derive(Clone, Debug)]
+struct Symbol {
+    module: String,
+    name: String,
+    message: Option<Message>,
+    /// The fully qualified scope the fields of a message resolve their types in.
+    scope: String,
+}
+
+/// # Generator
+pub struct Generator {
+    release: String,
+    files: Vec<File>,
+    symbols: HashMap<String, Symbol>,
+    /// Types that could not be resolved and were generated as `Value`.
+    pub unresolved: Vec<String>,
+}
+
+impl Generator {
+    pub fn new(release: &str, files: Vec<File>) -> Self {
+        let mut generator = Generator {
+            release: release.to_string(),
+            files: Vec::new(),
+            symbols: HashMap::new(),
+            unresolved: Vec::new(),
+        };
+        for file in &files {
+            let module = module_name(&file.package);
+            let scope = format!(".{}", file.package);
+            for message in &file.messages {
+                let name = if is_kind(&file.package, &message.name) {
+                    format!("{}Spec", message.name)
+                } else {
+                    message.name.clone()
+                };
+                generator.register_message(&module, &scope, message, name);
+            }
+            for enumeration in &file.enums {
+                generator.register(&module, &scope, &enumeration.name, enumeration.name.clone());
+            }
+        }
+        generator.files = files;
+        generator
+    }
+
+    fn register(&mut self, module: &str, scope: &str, proto_name: &str, name: String) {
+        self.symbols.insert(
+            format!("{}.{}", scope, proto_name),
+            Symbol {
+                module: module.to_string(),
+                name,
+                message: None,
+                scope: scope.to_string(),
+            },
+        );
+    }
+
+    fn register_message(&mut self, module: &str, scope: &str, message: &Message, name: String) {
+        let path = format!("{}.{}", scope, message.name);
+        for nested in &message.messages {
+            self.register_message(module, &path, nested, format!("{}{}", name, nested.name));
+        }
+        for nested in &message.enums {
+            let nested_name = format!("{}{}", name, nested.name);
+            self.register(module, &path, &nested.name, nested_name);
+        }
+        self.symbols.insert(
+            path.clone(),
+            Symbol {
+                module: module.to_string(),
+                name,
+                message: Some(message.clone()),
+                scope: path,
+            },
+        );
+    }
+
+    /// The generated modules, by module name, and the `mod.rs` declaring them.
+    pub fn generate(&mut self) -> BTreeMap<String, String> {
+        let mut packages: BTreeMap<String, Vec<File>> = BTreeMap::new();
+        for file in &self.files {
+            packages
+                .entry(file.package.clone())
+                .or_default()
+                .push(file.clone());
+        }
+        let mut modules = BTreeMap::new();
+        for (package, files) in packages {
+            let module = module_name(&package);
+            let mut code = format!(
+                "// Generated by `cargo xtask codegen` from the istio/api protos of Istio {}, package\n\
+                 // `{}`. Do not edit, regenerate instead.\n",
+                self.release, package
+            );
+            for file in &files {
+                let scope = format!(".{}", package);
+                for message in &file.messages {
+                    if is_kind(&package, &message.name) {
+                        code.push_str(&resource(&package, &message.name));
+                    }
+                    self.message(&mut code, &module, &scope, message);
+                }
+                for enumeration in &file.enums {
+                    code.push_str(&enumeration_code(&enumeration.name, enumeration));
+                }
+            }
+            modules.insert(format!("{}.rs", module), code);
+        }
+        let mut mod_rs = format!(
+            "// Types generated by `cargo xtask codegen` from the istio/api protos of Istio {}, one\n\
+             // module per proto package. Do not edit, regenerate instead. The hand-written types of the\n\
+             // parent module stay the stable API; these follow upstream and change with every release.\n\
+             #![allow(clippy::all)]\n\n",
+            self.release
+        );
+        for name in modules.keys() {
+            let _ = writeln!(mod_rs, "pub mod {};", name.trim_end_matches(".rs"));
+        }
+        modules.insert("mod.rs".to_string(), mod_rs);
+        modules
+    }
+
+    fn message(&mut self, code: &mut String, module: &str, scope: &str, message: &Message) {
+        let path = format!("{}.{}", scope, message.name);
+        let name = self.symbols[&path].name.clone();
+        code.push('\n');
+        code.push_str(&doc(&message.doc, ""));
+        code.push_str(
+            "#[skip_serializing_none]\n#[derive(Serialize, Deserialize, Clone, Debug)]\n",
+        );
+        code.push_str("#[cfg_attr(feature = \"schema\", derive(schemars::JsonSchema))]\n");
+        let _ = writeln!(code, "pub struct {} {{", name);
+        for (i, field) in message.fields.iter().enumerate() {
+            if i > 0 {
+                code.push('\n');
+            }
+            let mut field_doc = field.doc.clone();
+            if field.deprecated {
+                field_doc.push(String::new());
+                field_doc.push("Deprecated.".to_string());
+            }
+            code.push_str(&doc(&field_doc, "    "));
+            let json_name = json_name(&field.name);
+            let rust_name = rust_field_name(&field.name);
+            if json_name != rust_name.trim_start_matches("r#") {
+                let _ = writeln!(code, "    #[serde(rename = \"{}\")]", json_name);
+            }
+            let ty = self.field_type(module, &path, field);
+            let _ = writeln!(code, "    pub {}: Option<{}>,", rust_name, ty);
+        }
+        code.push_str("}\n");
+        for nested in &message.messages {
+            self.message(code, module, &path, nested);
+        }
+        for nested in &message.enums {
+            let nested_name = self.symbols[&format!("{}.{}", path, nested.name)]
+                .name
+                .clone();
+            code.push_str(&enumeration_code(&nested_name, nested));
+        }
+    }
+
+    fn field_type(&mut self, module: &str, scope: &str, field: &Field) -> String {
+        match &field.ty {
+            FieldType::Map(key, value) => format!(
+                "crate::istio::Map<{}, {}>",
+                self.resolve(module, scope, key),
+                self.resolve(module, scope, value)
+            ),
+            FieldType::Named(name) => {
+                let ty = self.resolve(module, scope, name);
+                let ty = match self.lookup(scope, name) {
+                    Some(target)
+                        if !field.repeated && self.reaches(&target, scope, &mut HashSet::new()) =>
+                    {
+                        format!("Box<{}>", ty)
+                    }
+                    _ => ty,
+                };
+                if field.repeated {
+                    format!("Vec<{}>", ty)
+                } else {
+                    ty
+                }
+            }
+        }
+    }
+
+    /// The fully qualified name of the message or enum `name` refers to from `scope`.
+    fn lookup(&self, scope: &str, name: &str) -> Option<String> {
+        if let Some(name) = name.strip_prefix('.') {
+            let qualified = format!(".{}", name);
+            return self.symbols.contains_key(&qualified).then_some(qualified);
+        }
+        let mut scope = scope.to_string();
+        loop {
+            let candidate = format!("{}.{}", scope, name);
+            if self.symbols.contains_key(&candidate) {
+                return Some(candidate);
+            }
+            match scope.rfind('.') {
+                Some(i) => scope.truncate(i),
+                None => return None,
+            }
+        }
+    }
+
+    /// Whether the message `from` contains the message `target` through singular fields, in which
+    /// case a field of type `from` in `target` needs a `Box`.
+    fn reaches(&self, from: &str, target: &str, visited: &mut HashSet<String>) -> bool {
+        if from == target {
+            return true;
+        }
+        if !visited.insert(from.to_string()) {
+            return false;
+        }
+        let symbol = match self.symbols.get(from) {
+            Some(symbol) => symbol,
+            None => return false,
+        };
+        symbol
+            .message
+            .iter()
+            .flat_map(|message| &message.fields)
+            .any(|field| match &field.ty {
+                FieldType::Named(name) if !field.repeated => self
+                    .lookup(&symbol.scope, name)
+                    .is_some_and(|next| self.reaches(&next, target, visited)),
+                _ => false,
+            })
+    }
+
+    fn resolve(&mut self, module: &str, scope: &str, name: &str) -> String {
+        if let Some(scalar) = scalar(name) {
+            return scalar.to_string();
+        }
+        let qualified = name.trim_start_matches('.');
+        if let Some((_, ty)) = WELL_KNOWN.iter().find(|(proto, _)| *proto == qualified) {
+            return ty.to_string();
+        }
+        match self
+            .lookup(scope, name)
+            .and_then(|name| self.symbols.get(&name))
+        {
+            Some(symbol) if symbol.module == module => symbol.name.clone(),
+            Some(symbol) => format!("super::{}::{}", symbol.module, symbol.name),
+            None => {
+                self.unresolved.push(format!("{} in {}", name, scope));
+                "k8s_openapi::serde_json::Value".to_string()
+            }
+        }
+    }
+}
+
+fn scalar(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "string" | "bytes" => "String",
+        "bool" => "bool",
+        "int32" | "sint32" | "sfixed32" => "i32",
+        "uint32" | "fixed32" => "u32",
+        "int64" | "sint64" | "sfixed64" => "i64",
+        "uint64" | "fixed64" => "u64",
+        "double" => "f64",
+        "float" => "f32",
+        _ => return None,
+    })
+}
+
+/// `istio.networking.v1beta1` becomes `networking_v1beta1`.
+fn module_name(package: &str) -> String {
+    package
+        .strip_prefix("istio.")
+        .unwrap_or(package)
+        .replace('.', "_")
+}
+
+fn is_kind(package: &str, message: &str) -> bool {
+    package.starts_with("istio.") && KINDS.iter().any(|(kind, _)| *kind == message)
+}
+
+fn resource(package: &str, kind: &str) -> String {
+    let parts: Vec<&str> = package.split('.').collect();
+    let group = format!("{}.istio.io", parts.get(1).unwrap_or(&""));
+    let version = parts.last().unwrap_or(&"");
+    let plural = KINDS
+        .iter()
+        .find(|(name, _)| *name == kind)
+        .map_or("", |(_, plural)| plural);
+    format!(
+        r#"
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct {kind} {{
+    /// Standard object's metadata. More info: https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#metadata
+    pub metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta,
+
+    /// Spec defines the behavior of the resource. https://git.k8s.io/community/contributors/devel/sig-architecture/api-conventions.md#spec-and-status
+    pub spec: Option<{kind}Spec>,
+
+    /// Most recently observed status of the resource. Populated by the system. Read-only.
+    pub status: Option<k8s_openapi::serde_json::Value>,
+}}
+
+impl k8s_openapi::Resource for {kind} {{
+    const API_VERSION: &'static str = "{group}/{version}";
+    const GROUP: &'static str = "{group}";
+    const KIND: &'static str = "{kind}";
+    const VERSION: &'static str = "{version}";
+    const URL_PATH_SEGMENT: &'static str = "{plural}";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}}
+
+impl k8s_openapi::Metadata for {kind} {{
+    type Ty = k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {{
+        &self.metadata
+    }}
+    fn metadata_mut(&mut self) -> &mut <Self as k8s_openapi::Metadata>::Ty {{
+        &mut self.metadata
+    }}
+}}
+"#
+    )
+}
+
+fn enumeration_code(name: &str, enumeration: &Enum) -> String {
+    let mut code = String::from("\n");
+    code.push_str(&doc(&enumeration.doc, ""));
+    code.push_str("#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]\n");
+    code.push_str("#[cfg_attr(feature = \"schema\", derive(schemars::JsonSchema))]\n");
+    let _ = writeln!(code, "pub enum {} {{", name);
+    for (i, value) in enumeration.values.iter().enumerate() {
+        if i > 0 {
+            code.push('\n');
+        }
+        code.push_str(&doc(&value.doc, "    "));
+        let _ = writeln!(code, "    {},", value.name);
+    }
+    code.push_str("}\n");
+    code
+}
+
+/// The lowerCamelCase JSON name of a proto field.
+fn json_name(field: &str) -> String {
+    let mut name = String::new();
+    let mut upper = false;
+    for c in field.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                name.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+fn rust_field_name(field: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in field.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+/// Doc comment lines from proto comments. Code generator directives (`+...`, `$hide_from_docs`)
+/// and HTML comments are dropped, and code blocks without language are marked as text so rustdoc
+/// does not compile them.
+fn doc(lines: &[String], indent: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut fenced = false;
+    let mut html_comment = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if html_comment || trimmed.starts_with("<!--") {
+            html_comment = !trimmed.ends_with("-->");
+            continue;
+        }
+        if trimmed.starts_with('+') || trimmed.starts_with("$hide_from_docs") {
+            continue;
+        }
+        if let Some(language) = trimmed.strip_prefix("```") {
+            let marker = match (fenced, language.is_empty()) {
+                (false, true) => "```text".to_string(),
+                _ => trimmed.to_string(),
+            };
+            fenced = !fenced;
+            out.push(marker);
+            continue;
+        }
+        // Indented lines outside of a code block would become Rust doctests.
+        out.push(if fenced {
+            line.trim_end().to_string()
+        } else {
+            trimmed.to_string()
+        });
+    }
+    while out.first().is_some_and(String::is_empty) {
+        out.remove(0);
+    }
+    while out.last().is_some_and(String::is_empty) {
+        out.pop();
+    }
+    if fenced {
+        out.push("```".to_string());
+    }
+    out.iter()
+        .map(|line| {
+            if line.is_empty() {
+                format!("{}///\n", indent)
+            } else {
+                format!("{}/// {}\n", indent, line)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,147 @@
+// Development tasks of the workspace, run with `cargo xtask <task>`.
+//
+// `codegen` generates the serde types of an Istio release from the istio/api proto definitions into
+// `src/istio/generated`, available with the `generated` feature, so new upstream fields land by
+// regenerating instead of hand-porting them:
+//
+//     cargo xtask codegen --release 1.22
+//
+// The protos are cloned from the `release-<release>` branch of istio/api into `target/`, or read
+// from an existing checkout given with `--api`.
+mod generate;
+mod proto;
+
+use clap::{Args, Parser, Subcommand};
+use generate::Generator;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::{fs, io};
+
+/// The proto packages generated, as directories of istio/api.
+const PACKAGES: &[&str] = &[
+    "extensions/v1alpha1",
+    "networking/v1alpha3",
+    "networking/v1beta1",
+    "security/v1beta1",
+    "telemetry/v1alpha1",
+    "type/v1beta1",
+];
+
+const ISTIO_API: &str = "https://github.com/istio/api";
+
+#[derive(Parser)]
+#[command(name = "xtask")]
+struct Cli {
+    #[command(subcommand)]
+    task: Task,
+}
+
+#[derive(Subcommand)]
+enum Task {
+    /// Generate the resource types of an Istio release from the istio/api protos.
+    Codegen(Codegen),
+}
+
+#[derive(Args)]
+struct Codegen {
+    /// The Istio release, e.g. 1.22.
+    #[arg(long)]
+    release: String,
+
+    /// A checkout of istio/api to read the protos from, instead of cloning the release.
+    #[arg(long)]
+    api: Option<PathBuf>,
+
+    /// The directory to generate into, relative to the workspace root.
+    #[arg(long, default_value = "src/istio/generated")]
+    out: PathBuf,
+}
+
+fn main() {
+    let Task::Codegen(codegen) = Cli::parse().task;
+    if let Err(error) = run(codegen) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(codegen: Codegen) -> Result<(), Box<dyn std::error::Error>> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .ok_or("xtask is not in a workspace")?;
+    let api = match codegen.api {
+        Some(api) => api,
+        None => clone(root, &codegen.release)?,
+    };
+
+    let mut files = Vec::new();
+    for package in PACKAGES {
+        let directory = api.join(package);
+        let mut protos: Vec<PathBuf> = fs::read_dir(&directory)
+            .map_err(|error| format!("{}: {}", directory.display(), error))?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "proto")
+            })
+            .collect();
+        protos.sort();
+        for path in protos {
+            let source = fs::read_to_string(&path)?;
+            let file =
+                proto::parse(&source).map_err(|error| format!("{}: {}", path.display(), error))?;
+            files.push(file);
+        }
+    }
+
+    let mut generator = Generator::new(&codegen.release, files);
+    let modules = generator.generate();
+    for unresolved in &generator.unresolved {
+        eprintln!(
+            "warning: unresolved type {}, generated as Value",
+            unresolved
+        );
+    }
+    let out = root.join(&codegen.out);
+    if out.exists() {
+        fs::remove_dir_all(&out)?;
+    }
+    fs::create_dir_all(&out)?;
+    for (name, code) in &modules {
+        fs::write(out.join(name), code)?;
+    }
+    let status = Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .arg(out.join("mod.rs"))
+        .status();
+    if !status.is_ok_and(|status| status.success()) {
+        eprintln!("warning: rustfmt failed, the generated code is not formatted");
+    }
+    println!(
+        "generated {} modules into {}",
+        modules.len() - 1,
+        out.display()
+    );
+    Ok(())
+}
+
+/// Clones the release branch of istio/api into `target/`, unless it already is.
+fn clone(root: &Path, release: &str) -> io::Result<PathBuf> {
+    let checkout = root.join("target").join(format!("istio-api-{}", release));
+    if checkout.exists() {
+        return Ok(checkout);
+    }
+    let status = Command::new("git")
+        .args(["clone", "--depth", "1", "--branch"])
+        .arg(format!("release-{}", release))
+        .arg(ISTIO_API)
+        .arg(&checkout)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "cloning release-{} of {} failed",
+            release, ISTIO_API
+        )));
+    }
+    Ok(checkout)
+}
@@ -0,0 +1,411 @@
+// A parser for the subset of proto3 used by the istio/api definitions: packages, imports, messages,
+// enums, oneofs and map fields. Options, reserved ranges, services and extensions are skipped.
+// Leading `//` comments are kept as documentation.
+use std::fmt;
+
+#[derive(Clone, Debug, Default)]
+pub struct File {
+    pub package: String,
+    pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Message {
+    pub name: String,
+    pub doc: Vec<String>,
+    pub fields: Vec<Field>,
+    pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    pub doc: Vec<String>,
+    pub ty: FieldType,
+    pub repeated: bool,
+    pub deprecated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Named(String),
+    Map(String, String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Enum {
+    pub name: String,
+    pub doc: Vec<String>,
+    pub values: Vec<EnumValue>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnumValue {
+    pub name: String,
+    pub doc: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(source: &str) -> Result<File, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        position: 0,
+    };
+    let mut file = File::default();
+    while let Some(token) = parser.peek() {
+        match token.text.as_str() {
+            "syntax" | "import" | "option" => parser.skip_statement(),
+            "package" => {
+                parser.next();
+                file.package = parser.identifier()?;
+                parser.expect(";")?;
+            }
+            "message" => file.messages.push(parser.message()?),
+            "enum" => file.enums.push(parser.enumeration()?),
+            "service" | "extend" => parser.skip_block()?,
+            ";" => {
+                parser.next();
+            }
+            _ => return Err(parser.error(format!("unexpected `{}`", token.text))),
+        }
+    }
+    Ok(file)
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    /// The `//` comment lines directly above the token.
+    doc: Vec<String>,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut doc: Vec<String> = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    // Blank lines end a comment block, so only the comment directly above a declaration is kept.
+    let mut newlines = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line += 1;
+                newlines += 1;
+                if newlines > 1 {
+                    doc.clear();
+                }
+            }
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                chars.next();
+                let mut comment = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    comment.push(c);
+                    chars.next();
+                }
+                if newlines == 0 && !tokens.is_empty() && doc.is_empty() {
+                    // A trailing comment on the line of the previous token.
+                    continue;
+                }
+                newlines = 0;
+                doc.push(comment.strip_prefix(' ').unwrap_or(&comment).to_string());
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut text = c.to_string();
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    text.push(next);
+                    if next == c && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+                tokens.push(token(text, line, &mut doc, &mut newlines));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                let mut text = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '.') {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                tokens.push(token(text, line, &mut doc, &mut newlines));
+            }
+            c => tokens.push(token(c.to_string(), line, &mut doc, &mut newlines)),
+        }
+    }
+    tokens
+}
+
+fn token(text: String, line: usize, doc: &mut Vec<String>, newlines: &mut usize) -> Token {
+    *newlines = 0;
+    Token {
+        text,
+        line,
+        doc: std::mem::take(doc),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError {
+            line: self
+                .tokens
+                .get(self.position.min(self.tokens.len().saturating_sub(1)))
+                .map_or(0, |token| token.line),
+            message,
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, ParseError> {
+        match self.next() {
+            Some(token) if token.text == text => Ok(token),
+            Some(token) => Err(self.error(format!("expected `{}`, found `{}`", text, token.text))),
+            None => Err(self.error(format!("expected `{}`, found the end", text))),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some(token)
+                if token
+                    .text
+                    .starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '.') =>
+            {
+                Ok(token.text)
+            }
+            Some(token) => Err(self.error(format!("expected a name, found `{}`", token.text))),
+            None => Err(self.error("expected a name, found the end".to_string())),
+        }
+    }
+
+    /// Skips a field or enum value number, which may be negative.
+    fn number(&mut self) {
+        if self.peek().is_some_and(|token| token.text == "-") {
+            self.next();
+        }
+        self.next();
+    }
+
+    /// Skips to the end of the current statement, including any `{ ... }` in it.
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                ";" if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips a `keyword name { ... }` block.
+    fn skip_block(&mut self) -> Result<(), ParseError> {
+        while self.peek().is_some_and(|token| token.text != "{") {
+            self.next();
+        }
+        self.expect("{")?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next().map(|token| token.text) {
+                Some(text) if text == "{" => depth += 1,
+                Some(text) if text == "}" => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("unterminated block".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses `[name = value, ...]` field options, returning whether the field is deprecated.
+    fn field_options(&mut self) -> Result<bool, ParseError> {
+        let mut deprecated = false;
+        if self.peek().is_some_and(|token| token.text == "[") {
+            let mut previous = String::new();
+            loop {
+                let token = self
+                    .next()
+                    .ok_or_else(|| self.error("unterminated field options".to_string()))?;
+                if token.text == "]" {
+                    break;
+                }
+                if previous == "=" && token.text == "true" {
+                    deprecated |= self.tokens[self.position - 3].text == "deprecated";
+                }
+                previous = token.text;
+            }
+        }
+        Ok(deprecated)
+    }
+
+    fn message(&mut self) -> Result<Message, ParseError> {
+        let keyword = self.expect("message")?;
+        let mut message = Message {
+            name: self.identifier()?,
+            doc: keyword.doc,
+            ..Message::default()
+        };
+        self.expect("{")?;
+        loop {
+            let token = self
+                .peek()
+                .cloned()
+                .ok_or_else(|| self.error(format!("unterminated message {}", message.name)))?;
+            match token.text.as_str() {
+                "}" => {
+                    self.next();
+                    return Ok(message);
+                }
+                ";" => {
+                    self.next();
+                }
+                "message" => message.messages.push(self.message()?),
+                "enum" => message.enums.push(self.enumeration()?),
+                "option" | "reserved" | "extensions" => self.skip_statement(),
+                "extend" => self.skip_block()?,
+                "oneof" => {
+                    self.next();
+                    self.identifier()?;
+                    self.expect("{")?;
+                    while self.peek().is_some_and(|token| token.text != "}") {
+                        if self.peek().is_some_and(|token| token.text == "option") {
+                            self.skip_statement();
+                        } else {
+                            message.fields.push(self.field()?);
+                        }
+                    }
+                    self.expect("}")?;
+                }
+                _ => message.fields.push(self.field()?),
+            }
+        }
+    }
+
+    fn field(&mut self) -> Result<Field, ParseError> {
+        let first = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("expected a field".to_string()))?;
+        let mut repeated = false;
+        match first.text.as_str() {
+            "repeated" => {
+                repeated = true;
+                self.next();
+            }
+            "optional" | "required" => {
+                self.next();
+            }
+            _ => {}
+        }
+        let ty = if self.peek().is_some_and(|token| token.text == "map") {
+            self.next();
+            self.expect("<")?;
+            let key = self.identifier()?;
+            self.expect(",")?;
+            let value = self.identifier()?;
+            self.expect(">")?;
+            FieldType::Map(key, value)
+        } else {
+            FieldType::Named(self.identifier()?)
+        };
+        let name = self.identifier()?;
+        self.expect("=")?;
+        self.number();
+        let deprecated = self.field_options()?;
+        self.expect(";")?;
+        Ok(Field {
+            name,
+            doc: first.doc,
+            ty,
+            repeated,
+            deprecated,
+        })
+    }
+
+    fn enumeration(&mut self) -> Result<Enum, ParseError> {
+        let keyword = self.expect("enum")?;
+        let mut enumeration = Enum {
+            name: self.identifier()?,
+            doc: keyword.doc,
+            values: Vec::new(),
+        };
+        self.expect("{")?;
+        loop {
+            let token = self
+                .peek()
+                .cloned()
+                .ok_or_else(|| self.error(format!("unterminated enum {}", enumeration.name)))?;
+            match token.text.as_str() {
+                "}" => {
+                    self.next();
+                    return Ok(enumeration);
+                }
+                ";" => {
+                    self.next();
+                }
+                "option" | "reserved" => self.skip_statement(),
+                _ => {
+                    let name = self.identifier()?;
+                    self.expect("=")?;
+                    self.number();
+                    self.field_options()?;
+                    self.expect(";")?;
+                    enumeration.values.push(EnumValue {
+                        name,
+                        doc: token.doc,
+                    });
+                }
+            }
+        }
+    }
+}